@@ -0,0 +1,1725 @@
+// Rejestr akcji UI: każda operacja aplikacji to wariant `Action`, a `Dispatcher` trzyma wspólny stan
+// i wykonuje akcje. Callbacki Slint tylko tłumaczą zdarzenie na akcję – te same akcje mogą wywołać
+// skróty klawiszowe czy skrypty, bez kopiowania logiki z closure'ów.
+
+use std::cell::RefCell;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::sync::{Arc, Mutex};
+use slint::{Color, ComponentHandle, Model, ModelRc, SharedString, VecModel, Weak};
+use tracing::{debug, error, info, warn};
+use crate::{AppWindow, CleanupLayer, LayerNode, Swatch};
+use crate::animated_export::AnimatedOptions;
+use crate::app_context::AppContext;
+use crate::channel_classification::{self, AovKind};
+use crate::color_picker::{self, ColorSample};
+use crate::cancel::CancelToken;
+use crate::compare;
+use crate::console;
+use crate::export_queue::{self, ExportSpec};
+use crate::export_handlers::{self, ChannelFormat, Collision, DeliveryOptions, NameFields, OutputTransform, UiExportConfig};
+use crate::file_operations;
+use crate::histogram;
+use crate::image_cache::{find_best_layer, load_specific_layer, ImageCache};
+use crate::io::file_operations::{self as io_files, FileMove};
+use crate::image_processing::{self, ChannelRemap, DisplayTransform, ExposureMode, GamutWarning, GrayscaleMode, InputColorSpace, LocalTonemap, Stage, TonemapMode};
+use crate::layer_cleanup::{self, CleanupPlan, LayerAction};
+use crate::layer_export::{self, LayerCompression, SampleKind};
+use crate::logging;
+use crate::progress::{self, ProgressSink};
+use crate::proxy_files;
+use crate::qc_report;
+use crate::sequence;
+use crate::display_profile::{self, DisplayProfile};
+use crate::display_filters::{Bloom, Sharpen};
+use crate::env_map::EnvView;
+use crate::stereo::{self, StereoMode};
+use crate::annotations::{self, Annotation, DrawPhase, Shape};
+use crate::snapshot_gallery::{self, Snapshot};
+use crate::raw_image::RawImage;
+use crate::render_settings::RenderSettings;
+use crate::history::{AbSnapshots, Change, History, ViewState};
+use crate::platform;
+use crate::theme::{self, ThemeMode};
+use crate::playlist;
+use crate::preload;
+use crate::timeline::{self, Timeline};
+use crate::preferences;
+use crate::remote;
+use crate::point_cloud::{Orbit, PointCloud, POINT_CLOUD_SIZE};
+use crate::video_export::VideoOptions;
+use crate::worker_threads;
+use crate::ui_handlers::{self, lock_or_recover, ConsoleModel, CurrentFilePathType, FolderBrowserType, ImageCacheType, ThrottledUpdate};
+
+#[derive(Clone, Debug)]
+pub enum Action {
+    // Plik i katalog roboczy
+    OpenFileDialog,
+    OpenFile(PathBuf),
+    /// Plik z paska miniatur; sąsiedzi wczytują się z wyprzedzeniem
+    OpenThumbnail(PathBuf),
+    /// Poprzednia / następna miniatura względem otwartej
+    StepThumbnail(i64),
+    ChooseWorkingFolder,
+    OpenFolder(PathBuf),
+    /// Podpowiedź dla miniatury pod kursorem
+    ThumbnailHovered(PathBuf),
+    /// Zaznaczenie miniatur (Ctrl+klik) do operacji na plikach
+    ClearThumbnailSelection,
+    /// Prawy klik: miniatura spoza zaznaczenia zostaje jedyną zaznaczoną
+    ThumbnailContextMenu(PathBuf),
+    /// Okno zmiany nazw / przeniesienia sekwencji zaznaczonych miniatur (szablon z tokenami)
+    OpenSequenceRename,
+    /// Plan na sucho wg szablonu i folderu docelowego z okna
+    PreviewSequenceRename,
+    ApplySequenceRename,
+    /// Cofa ostatnią partię z dziennika zmian nazw
+    UndoSequenceRename,
+    BrowseRenameDestination,
+    /// Zaznaczone miniatury do kosza systemu (kilka plików po potwierdzeniu)
+    TrashSelectedThumbnails,
+    /// Profil ICC monitora jako ostatnia transformacja podglądu (false = obejście)
+    SetMonitorProfile(bool),
+    /// Pełny plik zamiast bieżącego proxy
+    OpenOriginal,
+    /// Proxy (1/4, half-float, DWAA) dla ciężkich plików katalogu roboczego
+    GenerateProxies,
+    SetPreferProxies(bool),
+    /// Raport QC (HTML) katalogu roboczego, a bez niego bieżącego pliku
+    QcReport,
+    /// Spójność sekwencji bieżącego pliku (same nagłówki): braki klatek, zmiany rozdzielczości,
+    /// warstw i kompresji
+    CheckSequence,
+    /// Oś czasu sekwencji: klatka pod ułamkiem paska, numer klatki (zdalnie), krok o n pozycji
+    ScrubTimeline(f32),
+    /// Kilka plików jako lista odtwarzania (otwiera pierwszy)
+    OpenPlaylist(Vec<PathBuf>),
+    StepPlaylist(i64),
+    ClosePlaylist,
+    GotoFrame(i64),
+    StepFrame(i64),
+    TogglePlayback,
+    /// Następny tryb braków klatek (Hold → Slate → Skip)
+    CycleGapMode,
+    /// Motyw interfejsu (zapisywany w ustawieniach)
+    SetThemeMode(ThemeMode),
+    /// Akcent "#rrggbb" wpisany lub wybrany w menu
+    SetAccent(String),
+    /// Liczba wątków puli ciężkich zadań wpisana w menu ("Auto" albo liczba)
+    SetWorkerThreads(String),
+    /// Obniżony priorytet wątków puli ciężkich zadań (Windows)
+    SetWorkerLowPriority(bool),
+    /// Skany nagłówków przez mapowanie pliku w pamięć
+    SetMmapReads(bool),
+    /// Port zdalnego sterowania wpisany w menu ("Off" albo numer)
+    SetRemotePort(String),
+    RunScriptDialog,
+    RunScript(PathBuf),
+    /// Anulowanie zadania z listy zadań pod paskiem postępu (id z rejestru `progress`)
+    CancelTask(u64),
+    /// Wstrzymanie albo wznowienie zadania z listy (zadania kolejki eksportu)
+    PauseTask(u64),
+    /// Każdy kanał warstw (wszystkich lub bieżącej) jako osobny plik w skali szarości
+    ExportChannels { format: ChannelFormat, all_layers: bool },
+    /// Bieżący podgląd (po tone mappingu) jako PNG/JPEG/WebP/AVIF
+    ExportImage(DeliveryOptions),
+    /// Kanały bieżącego pliku przepięte wg mapowania (tekst `wyjście = źródło`) do nowego EXR
+    ExportRemappedExr { mapping: String, sample: SampleKind, compression: LayerCompression },
+    /// Mapowanie tożsamościowe bieżącej warstwy w polu mapowania okna eksportu
+    FillExrMapping,
+    /// Okno porządków: warstwy bieżącego pliku z proponowanymi nazwami bez prefiksów programów
+    OpenCleanup,
+    /// Kopia bieżącego pliku wg okna porządków (kolejka eksportu)
+    WriteCleanup,
+    /// Sekwencja bieżącego pliku (po tone mappingu) jako wideo MP4
+    ExportVideo(VideoOptions),
+    /// Sekwencja bieżącego pliku jako animowany GIF/WebP do szybkiego udostępnienia
+    ExportAnimation(AnimatedOptions),
+    /// Rysowanie notatki bieżącym narzędziem (punkt widoku 0..1)
+    AnnotationDraw { phase: DrawPhase, u: f32, v: f32 },
+    UndoAnnotation,
+    ClearAnnotations,
+    /// Bieżący podgląd z notatkami jako PNG (kolejka eksportu)
+    ExportAnnotated,
+    Exit,
+    // Parametry podglądu
+    SetExposure(f32),
+    /// Historia parametrów widoku i wyboru warstwy (Ctrl+Z / Ctrl+Y)
+    UndoView,
+    RedoView,
+    /// Ekspozycja i gamma z percentyli histogramu luminancji
+    AutoExposure,
+    /// Migawki A/B parametrów widoku
+    StoreSnapshotA,
+    ToggleAb,
+    /// Nazwana migawka widoku do galerii pliku; obszar widoku jako (u0, v0, u1, v1)
+    CaptureSnapshot { name: String, region: [f32; 4] },
+    RestoreSnapshot(usize),
+    DeleteSnapshot(usize),
+    SetGamma(f32),
+    SetExposureMode(ExposureMode),
+    /// Operator tone mappingu; promień (ułamek dłuższego boku) i siła dla trybu lokalnego
+    SetTonemap { mode: TonemapMode, radius: f32, strength: f32 },
+    SetMiddleGray(f32),
+    SetGrayscaleMode(GrayscaleMode),
+    SetGamutWarning(GamutWarning),
+    /// Pasma ekspozycji zamiast obrazu; legenda w EV albo nitach (właściwość UI)
+    SetFalseColor(bool),
+    SetDisplayTransform(DisplayTransform),
+    SetAovRemap(ChannelRemap),
+    /// Widok wektorów: długość mapowana na pełną jasność, nakładka strzałek
+    SetVectorDisplay { max_magnitude: f32, arrows: bool },
+    /// Podgląd normalnych oświetlonych światłem kierunkowym (kąty w stopniach)
+    SetRelight { enabled: bool, azimuth: f32, elevation: f32 },
+    /// Widok mapy otoczenia: panorama albo ściana sześcianu zamiast obrazu pliku
+    SetEnvView(EnvView),
+    /// Tryb stereo plików wielowidokowych (pojedynczy widok, obok siebie, anaglif)
+    SetStereoMode(StereoMode),
+    /// Przełącza bieżącą warstwę na jej odpowiednik w drugim oku
+    ToggleStereoView,
+    /// Wyostrzanie obrazu wyświetlanego (maska nieostra); eksport tylko z `in_exports`
+    SetSharpen { enabled: bool, amount: f32, radius: f32, in_exports: bool },
+    /// Poświata świateł HDR (bloom) z progiem i siłą; `display_only` wyłącza ją w eksporcie
+    SetBloom { enabled: bool, threshold: f32, intensity: f32, display_only: bool },
+    /// Focus peaking w widoku głębi: pasmo [near, far] w ułamkach znormalizowanego zakresu Z
+    SetFocusBand { enabled: bool, near: f32, far: f32 },
+    /// None = przestrzeń wykryta z nagłówka pliku
+    SetInputColorSpace(Option<InputColorSpace>),
+    /// Przełącznik etapu transformacji widoku (indeks w `image_processing::Stage::ALL`)
+    SetPipelineStage { index: usize, enabled: bool },
+    /// Balans bieli z ostatniej próbki próbnika koloru (neutralna szarość)
+    WhiteBalanceFromSample,
+    ResetWhiteBalance,
+    // Porównanie z referencją
+    SetReference,
+    ClearReference,
+    SetCompareMode(compare::DiffMode),
+    SetCompareTolerance(f32),
+    // Próbnik koloru: prostokąt (u0, v0, u1, v1) we współrzędnych widoku 0..1
+    SampleColor(f32, f32, f32, f32),
+    /// Powiększenie widoku podglądu (1.0 = dopasowanie do okna)
+    SetViewZoom(f32),
+    /// Kursor nad obrazem przy dużym powiększeniu – punkt widoku 0..1
+    HoverPixel(f32, f32),
+    ClearSwatches,
+    // Chmura punktów z AOV pozycji
+    OpenPointCloud,
+    OrbitPointCloud { yaw: f32, pitch: f32, zoom: f32 },
+    // Drzewo warstw
+    SelectLayerNode(LayerNode),
+    ToggleLayerNode(i32),
+    RegroupLayers,
+    SetLightingOnly(bool),
+    // Konsola
+    ClearConsole,
+    SaveConsoleLog,
+    SetConsoleSearch(SharedString),
+    SetConsoleLevel(SharedString),
+    SetConsoleCategory(SharedString),
+}
+
+impl Action {
+    /// Folder do przeglądarki miniatur albo plik do otwarcia (wiersz poleceń, inne uruchomienie)
+    pub fn open(path: PathBuf) -> Action {
+        if path.is_dir() { Action::OpenFolder(path) } else { Action::OpenFile(path) }
+    }
+}
+
+/// Wspólny stan aplikacji potrzebny do wykonania akcji (żyje w wątku UI)
+pub struct Dispatcher {
+    ui: Weak<AppWindow>,
+    image_cache: ImageCacheType,
+    current_file_path: CurrentFilePathType,
+    folder_browser: FolderBrowserType,
+    console_model: ConsoleModel,
+    /// Ustawienia widoku, porównanie i profil monitora (wspólne z handlerami UI)
+    context: Arc<AppContext>,
+    throttled_update: ThrottledUpdate,
+    swatches: RefCell<Vec<ColorSample>>,
+    point_cloud: RefCell<Option<PointCloud>>,
+    history: RefCell<History>,
+    /// Ostatnio zastosowane parametry widoku – UI zmienia własności przed wywołaniem callbacku,
+    /// więc stan "przed zmianą" trzymamy tutaj
+    view_state: RefCell<ViewState>,
+    snapshots: RefCell<AbSnapshots>,
+    /// Sekwencje otwarte w oknie zmiany nazw
+    rename_sequences: RefCell<Vec<sequence::Sequence>>,
+}
+
+impl Dispatcher {
+    pub fn new(
+        ui: &AppWindow,
+        image_cache: ImageCacheType,
+        current_file_path: CurrentFilePathType,
+        console_model: ConsoleModel,
+        context: Arc<AppContext>,
+    ) -> Rc<Self> {
+        let ui_weak_for_throttle = ui.as_weak();
+        let cache_for_throttle = image_cache.clone();
+        let context_for_throttle = context.clone();
+        let throttled_update = ThrottledUpdate::new(move |exp, gamma| {
+            if ui_weak_for_throttle.upgrade().is_some() {
+                ui_handlers::handle_parameter_changed_throttled(ui_weak_for_throttle.clone(), cache_for_throttle.clone(), &context_for_throttle, exp, gamma);
+            }
+        });
+
+        Rc::new(Self {
+            ui: ui.as_weak(),
+            image_cache,
+            current_file_path,
+            folder_browser: Arc::new(Mutex::new(Default::default())),
+            console_model,
+            context,
+            throttled_update,
+            swatches: RefCell::new(Vec::new()),
+            point_cloud: RefCell::new(None),
+            history: RefCell::new(History::default()),
+            view_state: RefCell::new(ViewState {
+                exposure: ui.get_exposure_value(),
+                gamma: ui.get_gamma_value(),
+                exposure_mode: ExposureMode::from_label(&ui.get_exposure_mode()),
+                selection: None,
+            }),
+            snapshots: RefCell::new(AbSnapshots::default()),
+            rename_sequences: RefCell::new(Vec::new()),
+        })
+    }
+
+    /// Kontekst aplikacji (zdalne sterowanie czeka przez niego na koniec wczytywania pliku)
+    pub fn context(&self) -> &Arc<AppContext> {
+        &self.context
+    }
+
+    /// Stan widoku do historii: śledzone parametry i wybór z drzewa warstw
+    fn current_view(&self, ui: &AppWindow) -> ViewState {
+        let mut state = self.view_state.borrow().clone();
+        state.selection = usize::try_from(ui.get_selected_layer_node()).ok()
+            .and_then(|id| ui.get_layer_nodes().row_data(id))
+            .filter(|node| node.kind != ui_handlers::NODE_KIND_GROUP)
+            .map(|node| (node.layer.to_string(), node.channel.to_string()));
+        state
+    }
+
+    /// Po przywróceniu sesji parametry UI zmieniono bez akcji – historia zaczyna od nich
+    pub fn sync_view_state(&self, ui: &AppWindow) {
+        let mut state = self.view_state.borrow_mut();
+        state.exposure = ui.get_exposure_value();
+        state.gamma = ui.get_gamma_value();
+        state.exposure_mode = ExposureMode::from_label(&ui.get_exposure_mode());
+    }
+
+    fn record_view_change(&self, change: Change, apply: impl FnOnce(&mut ViewState)) {
+        let Some(ui) = self.ui.upgrade() else { return; };
+        let before = self.current_view(&ui);
+        self.history.borrow_mut().record(before, change);
+        apply(&mut self.view_state.borrow_mut());
+    }
+
+    /// Cofa (undo = true) albo ponawia krok: ustawia kontrolki, tryb ekspozycji i wybór warstwy
+    fn step_history(&self, undo: bool) {
+        let Some(ui) = self.ui.upgrade() else { return; };
+        let current = self.current_view(&ui);
+        let step = if undo { self.history.borrow_mut().undo(current.clone()) } else { self.history.borrow_mut().redo(current.clone()) };
+        let Some(state) = step else {
+            ui.set_status_text(if undo { "Nothing to undo".into() } else { "Nothing to redo".into() });
+            return;
+        };
+        debug!(target: "ui", "{}: {:?}", if undo { "undo" } else { "redo" }, state);
+        self.apply_view(&ui, state, &current);
+    }
+
+    /// Przełącza migawki A/B; wybór warstwy zostaje bez zmian (porównanie na tym samym obrazie)
+    fn toggle_ab(&self) {
+        let Some(ui) = self.ui.upgrade() else { return; };
+        let current = self.current_view(&ui);
+        let mut snapshots = self.snapshots.borrow_mut();
+        let Some(mut target) = snapshots.toggle(current.clone()) else {
+            ui.set_status_text("Store snapshot A first".into());
+            return;
+        };
+        let label = snapshots.active_label();
+        drop(snapshots);
+        target.selection = current.selection.clone();
+        self.history.borrow_mut().record(current.clone(), Change::Snapshot);
+        info!(target: "ui", "view snapshot {}: exp={:.2}, gamma={:.2}, {:?}", label, target.exposure, target.gamma, target.exposure_mode);
+        ui.set_ab_slot(label.into());
+        self.apply_view(&ui, target, &current);
+    }
+
+    /// Zapisuje w galerii pliku bieżący widok z miniaturą widocznego obszaru podglądu
+    fn capture_snapshot(&self, name: String, [u0, v0, u1, v1]: [f32; 4]) {
+        let Some(ui) = self.ui.upgrade() else { return; };
+        if lock_or_recover(&self.image_cache).is_none() {
+            ui.set_status_text("Error: No file loaded".into());
+            return;
+        }
+        let (u0, v0) = (u0.clamp(0.0, 1.0), v0.clamp(0.0, 1.0));
+        let region = [u0, v0, (u1.clamp(0.0, 1.0) - u0).max(1e-3), (v1.clamp(0.0, 1.0) - v0).max(1e-3)];
+        let view = self.current_view(&ui);
+        let preview = ui.get_exr_image().to_rgba8().map(|buffer| RawImage { width: buffer.width(), height: buffer.height(), pixels: buffer.as_bytes().to_vec() });
+        let stamp = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).map(|d| d.as_millis()).unwrap_or_default();
+        let mut status = String::new();
+        ui_handlers::edit_snapshots(&ui, &self.context, |file, items| {
+            let name = match name.trim() {
+                "" => format!("Snapshot {}", items.len() + 1),
+                name => name.to_string(),
+            };
+            let thumbnail = format!("{}.png", stamp);
+            match preview.as_ref().and_then(|p| snapshot_gallery::crop_thumbnail(p, region)) {
+                Some(image) => if let Err(e) = snapshot_gallery::save_thumbnail(file, &thumbnail, &image) {
+                    warn!(target: "io", "snapshot thumbnail for {}: {}", file.display(), e);
+                },
+                None => warn!(target: "ui", "snapshot \"{}\": preview not available for a thumbnail", name),
+            }
+            info!(target: "ui", "snapshot \"{}\" of {}: region {:?}, exp={:.2}, gamma={:.2}", name, file.display(), region, view.exposure, view.gamma);
+            status = format!("Snapshot \"{}\" captured", name);
+            items.push(Snapshot {
+                name,
+                region,
+                exposure: view.exposure,
+                gamma: view.gamma,
+                exposure_mode: view.exposure_mode.label().to_string(),
+                selection: view.selection.clone(),
+                thumbnail,
+            });
+            true
+        });
+        ui.set_snapshot_name("".into());
+        ui.set_status_text(status.into());
+    }
+
+    /// Przywraca migawkę galerii (krok historii): parametry i warstwę, powiększenie na jej obszar
+    /// i jego chwilowe podświetlenie
+    fn restore_snapshot(&self, index: usize) {
+        let Some(ui) = self.ui.upgrade() else { return; };
+        let Some(snapshot) = ui_handlers::snapshot(&self.context, index) else { return; };
+        let current = self.current_view(&ui);
+        let target = ViewState {
+            exposure: snapshot.exposure,
+            gamma: snapshot.gamma,
+            exposure_mode: ExposureMode::from_label(&snapshot.exposure_mode),
+            selection: snapshot.selection.clone().or_else(|| current.selection.clone()),
+        };
+        self.history.borrow_mut().record(current.clone(), Change::Snapshot);
+        info!(target: "ui", "restoring snapshot \"{}\": region {:?}", snapshot.name, snapshot.region);
+        self.apply_view(&ui, target, &current);
+        let [u, v, w, h] = snapshot.region;
+        ui.invoke_show_region(u, v, w, h);
+        self.dispatch(Action::SetViewZoom(ui.get_view_zoom()));
+        ui.set_status_text(format!("Snapshot \"{}\"", snapshot.name).into());
+    }
+
+    /// Biel i czerń z percentyli histogramu bieżącego obrazu; zmiana jest krokiem historii
+    fn auto_exposure(&self) {
+        let Some(ui) = self.ui.upgrade() else { return; };
+        let settings = self.context.render_settings(ui.get_exposure_value(), ui.get_gamma_value());
+        let graph = settings.graph;
+        let auto = match lock_or_recover(&self.image_cache).as_ref() {
+            Some(cache) => cache.channel_histograms(self.context.view().selection, &settings.transform, &graph.tone_params()).and_then(|(hists, _)| histogram::auto_exposure(&hists[histogram::LUMINANCE], graph)),
+            None => return,
+        };
+        let Some(auto) = auto else {
+            warn!(target: "processing", "auto exposure: no positive values in the image or selection");
+            return;
+        };
+        info!(target: "processing", "auto exposure{}: P{} = {:.5} → black, P{} = {:.5} → 1.0: exposure {:+.2} EV, gamma {:.2}",
+            if self.context.view().selection.is_some() { " (selection)" } else { "" }, histogram::AUTO_LOW_PERCENTILE, auto.black, histogram::AUTO_HIGH_PERCENTILE, auto.white, auto.exposure, auto.gamma);
+        let current = self.current_view(&ui);
+        let target = ViewState { exposure: auto.exposure, gamma: auto.gamma, ..current.clone() };
+        self.history.borrow_mut().record(current.clone(), Change::AutoExposure);
+        self.apply_view(&ui, target, &current);
+    }
+
+    /// Ustawia kontrolki i tryb ekspozycji, w razie potrzeby wybiera warstwę, a podgląd odświeża
+    /// tą samą ścieżką throttlingu co suwaki
+    fn apply_view(&self, ui: &AppWindow, state: ViewState, current: &ViewState) {
+        ui.set_exposure_value(state.exposure);
+        ui.set_gamma_value(state.gamma);
+        ui.set_exposure_mode(state.exposure_mode.label().into());
+        self.context.update_view(|view| view.render.graph.exposure_mode = state.exposure_mode);
+        if state.selection != current.selection {
+            // Węzeł szukany po nazwach – identyfikatory zmieniają się przy przegrupowaniu drzewa
+            let node = state.selection.as_ref().and_then(|(layer, channel)| {
+                ui.get_layer_nodes().iter().find(|n| n.kind != ui_handlers::NODE_KIND_GROUP && n.layer == layer.as_str() && n.channel == channel.as_str())
+            });
+            if let Some(node) = node {
+                ui_handlers::handle_layer_tree_click(self.ui.clone(), self.image_cache.clone(), node, self.current_file_path.clone(), self.context.clone());
+            }
+        }
+        *self.view_state.borrow_mut() = state.clone();
+        self.throttled_update.update_gamma(state.gamma);
+        self.throttled_update.update_exposure(state.exposure);
+    }
+
+    /// Przerysowuje podgląd przez ten sam throttling co suwak ekspozycji
+    fn refresh(&self) {
+        if let Some(ui) = self.ui.upgrade() {
+            self.throttled_update.update_exposure(ui.get_exposure_value());
+        }
+    }
+
+    pub fn dispatch(&self, action: Action) {
+        match action {
+            Action::OpenFileDialog => {
+                ui_handlers::handle_open_exr(self.ui.clone(), self.current_file_path.clone(), self.image_cache.clone(), self.context.clone());
+            }
+            Action::OpenFile(path) => {
+                info!(target: "ui", "opening {}", path.display());
+                preload::clear();
+                ui_handlers::handle_open_exr_from_path(self.ui.clone(), self.current_file_path.clone(), self.image_cache.clone(), self.context.clone(), path);
+            }
+            Action::OpenThumbnail(path) => {
+                let Some(ui) = self.ui.upgrade() else { return; };
+                let paths: Vec<PathBuf> = ui.get_thumbnails().iter().map(|t| PathBuf::from(t.path.as_str())).collect();
+                if let Some(index) = paths.iter().position(|p| *p == path) {
+                    let neighbours = [index.checked_sub(1), Some(index + 1)].into_iter().flatten().filter_map(|i| paths.get(i).cloned()).collect();
+                    preload::schedule(&ui, neighbours);
+                }
+                ui_handlers::handle_open_exr_from_path(self.ui.clone(), self.current_file_path.clone(), self.image_cache.clone(), self.context.clone(), path);
+            }
+            Action::StepThumbnail(delta) => {
+                let Some(ui) = self.ui.upgrade() else { return; };
+                let thumbnails = ui.get_thumbnails();
+                let current = ui.get_opened_thumbnail_path();
+                let Some(index) = thumbnails.iter().position(|t| t.path == current) else { return; };
+                let Some(next) = index.checked_add_signed(delta as isize).and_then(|i| thumbnails.row_data(i)) else { return; };
+                ui.set_opened_thumbnail_path(next.path.clone());
+                self.dispatch(Action::OpenThumbnail(PathBuf::from(next.path.as_str())));
+            }
+            Action::ChooseWorkingFolder => {
+                info!(target: "ui", "choosing working folder...");
+                let ui = self.ui.clone();
+                // Wybrany folder wraca przez callback UI (Action::OpenFolder)
+                let spawned = slint::spawn_local(async move {
+                    match (file_operations::pick_working_folder().await, ui.upgrade()) {
+                        (Some(dir), Some(ui)) => ui.invoke_folder_selected(dir.display().to_string().into()),
+                        _ => info!(target: "ui", "folder selection canceled"),
+                    }
+                });
+                if let Err(e) = spawned {
+                    error!(target: "ui", "cannot show the folder dialog: {}", e);
+                }
+            }
+            Action::OpenFolder(dir) => {
+                preload::clear();
+                if let Some(ui) = self.ui.upgrade() {
+                    ui.set_show_folder_browser(true);
+                }
+                ui_handlers::handle_folder_selected(self.ui.clone(), self.folder_browser.clone(), self.context.clone(), dir);
+            }
+            Action::ThumbnailHovered(path) => {
+                let Some(ui) = self.ui.upgrade() else { return; };
+                // Kursor mógł już przejść na inną miniaturę
+                if ui.get_thumb_tooltip_path().as_str() != path.display().to_string() { return; }
+                let text = crate::thumbnails::thumbnail_tooltip(&path).unwrap_or_else(|e| {
+                    warn!(target: "io", "thumbnail tooltip: {:#}", e);
+                    format!("{}\n{:#}", path.display(), e)
+                });
+                ui.set_thumb_tooltip_text(text.into());
+            }
+            Action::ClearThumbnailSelection => {
+                let Some(ui) = self.ui.upgrade() else { return; };
+                select_thumbnails(&ui, |_| false);
+            }
+            Action::ThumbnailContextMenu(path) => {
+                let Some(ui) = self.ui.upgrade() else { return; };
+                let path = path.display().to_string();
+                if !ui.get_thumbnails().iter().any(|t| t.selected && t.path.as_str() == path) {
+                    select_thumbnails(&ui, |t| t == path);
+                }
+            }
+            Action::OpenSequenceRename => self.open_sequence_rename(),
+            Action::PreviewSequenceRename => self.preview_sequence_rename(),
+            Action::ApplySequenceRename => self.apply_sequence_rename(),
+            Action::UndoSequenceRename => self.undo_sequence_rename(),
+            Action::BrowseRenameDestination => {
+                let Some(ui) = self.ui.upgrade() else { return; };
+                let start = self.rename_sequences.borrow().first()
+                    .and_then(|s| s.frames.first()?.1.parent().map(PathBuf::from))
+                    .or_else(|| self.working_dir(&ui))
+                    .unwrap_or_default();
+                if let Some(dir) = file_operations::export_folder_dialog(&start) {
+                    ui.set_rename_destination(dir.display().to_string().into());
+                    self.preview_sequence_rename();
+                }
+            }
+            Action::TrashSelectedThumbnails => self.trash_selected_thumbnails(),
+            Action::SetMonitorProfile(enabled) => self.set_monitor_profile(enabled),
+            Action::OpenOriginal => {
+                ui_handlers::handle_open_original(self.ui.clone(), self.current_file_path.clone(), self.image_cache.clone(), self.context.clone());
+            }
+            Action::GenerateProxies => self.generate_proxies(),
+            Action::QcReport => self.qc_report(),
+            Action::CheckSequence => self.check_sequence(),
+            Action::ScrubTimeline(fraction) => {
+                if let Some(frame) = timeline::frame_at(fraction) {
+                    self.show_frame(|_| frame);
+                }
+            }
+            Action::GotoFrame(frame) => self.show_frame(|_| frame),
+            Action::OpenPlaylist(files) => {
+                let Some(ui) = self.ui.upgrade() else { return; };
+                let files: Vec<PathBuf> = files.into_iter().filter(|f| f.is_file()).collect();
+                if let Some(first) = playlist::set(&ui, files) {
+                    self.dispatch(Action::OpenFile(first));
+                }
+            }
+            Action::StepPlaylist(delta) => {
+                let Some(ui) = self.ui.upgrade() else { return; };
+                match playlist::step(&ui, delta) {
+                    Some(path) => ui_handlers::handle_open_exr_from_path(self.ui.clone(), self.current_file_path.clone(), self.image_cache.clone(), self.context.clone(), path),
+                    None => ui.set_status_text("No playlist – select several files in the Open dialog".into()),
+                }
+            }
+            Action::ClosePlaylist => {
+                if let Some(ui) = self.ui.upgrade() {
+                    playlist::clear(&ui);
+                }
+            }
+            Action::StepFrame(delta) => self.show_frame(|t| t.step(delta, timeline::gap_mode())),
+            Action::TogglePlayback => {
+                let Some(ui) = self.ui.upgrade() else { return; };
+                let context = self.context.clone();
+                timeline::toggle_playback(&ui, move || ui_handlers::is_loading(&context));
+            }
+            Action::CycleGapMode => {
+                let Some(ui) = self.ui.upgrade() else { return; };
+                let mode = timeline::gap_mode().next();
+                timeline::set_gap_mode(mode);
+                info!(target: "ui", "missing frames: {}", mode.label());
+                // Bieżąca pozycja może być brakiem – pokaż ją w nowym trybie
+                self.show_frame(|t| t.current);
+                ui.set_timeline_gap_mode(mode.label().into());
+            }
+            Action::SetPreferProxies(prefer) => {
+                proxy_files::set_prefer_proxies(prefer);
+                info!(target: "io", "prefer proxy files: {}", prefer);
+            }
+            Action::SetThemeMode(mode) => self.set_theme(|t| t.mode = mode),
+            Action::SetAccent(hex) => match theme::parse_accent(&hex) {
+                Some(accent) => self.set_theme(|t| t.accent = accent),
+                None => {
+                    if let Some(ui) = self.ui.upgrade() {
+                        // Przywróć w polu bieżący akcent
+                        ui.set_accent_hex(theme::accent_hex(theme::current().accent).into());
+                        ui.set_status_text(format!("Invalid accent color: {} (expected #rrggbb)", hex).into());
+                    }
+                }
+            },
+            Action::SetWorkerThreads(text) => {
+                let Some(ui) = self.ui.upgrade() else { return; };
+                match worker_threads::parse_threads(&text) {
+                    Some(threads) => {
+                        preferences::update(|p| p.worker_threads = threads);
+                        ui.set_status_text(format!("Background jobs: {} worker threads (from the next job)", worker_threads::thread_count(threads)).into());
+                    }
+                    None => ui.set_status_text(format!("Invalid worker thread count: {} (expected Auto or a number)", text).into()),
+                }
+                preferences::apply(&ui);
+            }
+            Action::SetWorkerLowPriority(low) => {
+                preferences::update(|p| p.worker_low_priority = low);
+                info!(target: "processing", "low-priority worker threads: {}", low);
+                if let Some(ui) = self.ui.upgrade() {
+                    preferences::apply(&ui);
+                }
+            }
+            Action::SetMmapReads(enabled) => {
+                preferences::update(|p| p.mmap_reads = enabled);
+                crate::io::set_mmap_reads(enabled);
+                info!(target: "io", "memory-mapped header reads: {}", enabled);
+                if let Some(ui) = self.ui.upgrade() {
+                    preferences::apply(&ui);
+                }
+            }
+            Action::SetRemotePort(text) => {
+                let Some(ui) = self.ui.upgrade() else { return; };
+                match preferences::parse_remote_port(&text) {
+                    Some(port) => {
+                        preferences::update(|p| p.remote_port = port);
+                        // Nasłuch nie jest zamykany w trakcie pracy – zmiana działającego portu po restarcie
+                        let status = match (port, remote::listening_port()) {
+                            (Some(port), None) if remote::start(port) => format!("Remote control: listening on 127.0.0.1:{}", port),
+                            (Some(port), None) => format!("Remote control: cannot listen on port {} (see log)", port),
+                            (port, Some(running)) if port == Some(running) => format!("Remote control: listening on 127.0.0.1:{}", running),
+                            (_, Some(_)) => "Remote control: change takes effect after restart".to_string(),
+                            (None, None) => "Remote control: off".to_string(),
+                        };
+                        ui.set_status_text(status.into());
+                    }
+                    None => ui.set_status_text(format!("Invalid remote port: {} (expected Off or 1-65535)", text).into()),
+                }
+                preferences::apply(&ui);
+            }
+            Action::RunScriptDialog => {
+                if let Some(script) = file_operations::open_script_dialog() {
+                    self.dispatch(Action::RunScript(script));
+                }
+            }
+            Action::RunScript(script) => self.run_script(script),
+            Action::CancelTask(id) => {
+                let Some(ui) = self.ui.upgrade() else { return; };
+                if !progress::cancel(&ui, id) {
+                    debug!(target: "ui", "task {} is not cancellable or already finished", id);
+                }
+                // Anulowane zadanie czekające w kolejce eksportu znika od razu
+                export_queue::pump(&ui);
+            }
+            Action::PauseTask(id) => {
+                let Some(ui) = self.ui.upgrade() else { return; };
+                if progress::toggle_pause(&ui, id).is_none() {
+                    debug!(target: "ui", "task {} cannot be paused", id);
+                }
+                // Wznowione zadanie może zająć wolne miejsce; stan wstrzymania trafia do pliku kolejki
+                export_queue::pump(&ui);
+            }
+            Action::ExportChannels { format, all_layers } => self.export_channels(format, all_layers),
+            Action::ExportImage(options) => self.export_image(options),
+            Action::ExportRemappedExr { mapping, sample, compression } => self.export_remapped_exr(&mapping, sample, compression),
+            Action::FillExrMapping => {
+                let Some(ui) = self.ui.upgrade() else { return; };
+                let mapping = lock_or_recover(&self.image_cache).as_ref().and_then(|cache| {
+                    let layer = cache.layers_info.iter().find(|l| l.name == cache.current_layer_name)?;
+                    let channels: Vec<String> = layer.channels.iter().map(|c| c.name.clone()).collect();
+                    Some(layer_export::identity_mapping(&layer.name, &channels))
+                });
+                if let Some(mapping) = mapping {
+                    ui.set_export_exr_mapping(mapping.into());
+                }
+            }
+            Action::OpenCleanup => self.open_cleanup(),
+            Action::WriteCleanup => self.write_cleanup(),
+            Action::ExportVideo(options) => self.export_video(options),
+            Action::ExportAnimation(options) => self.export_animation(options),
+            Action::AnnotationDraw { phase, u, v } => self.draw_annotation(phase, u, v),
+            Action::UndoAnnotation => {
+                let Some(ui) = self.ui.upgrade() else { return; };
+                ui_handlers::edit_annotations(&ui, &self.context, |items, _| items.pop().is_some());
+            }
+            Action::ClearAnnotations => {
+                let Some(ui) = self.ui.upgrade() else { return; };
+                ui_handlers::edit_annotations(&ui, &self.context, |items, draft| {
+                    *draft = None;
+                    !std::mem::take(items).is_empty()
+                });
+            }
+            Action::ExportAnnotated => self.export_annotated(),
+            Action::Exit => ui_handlers::handle_exit(self.ui.clone()),
+
+            Action::SetExposure(exposure) => {
+                self.record_view_change(Change::Exposure, |s| s.exposure = exposure);
+                self.throttled_update.update_exposure(exposure);
+            }
+            Action::SetGamma(gamma) => {
+                self.record_view_change(Change::Gamma, |s| s.gamma = gamma);
+                self.throttled_update.update_gamma(gamma);
+            }
+            Action::AutoExposure => self.auto_exposure(),
+            Action::UndoView => self.step_history(true),
+            Action::RedoView => self.step_history(false),
+            Action::StoreSnapshotA => {
+                let Some(ui) = self.ui.upgrade() else { return; };
+                let mut snapshots = self.snapshots.borrow_mut();
+                snapshots.store_a(self.current_view(&ui));
+                ui.set_ab_slot(snapshots.active_label().into());
+                ui.set_status_text("Snapshot A stored – adjust the view, then toggle A/B".into());
+            }
+            Action::ToggleAb => self.toggle_ab(),
+            Action::CaptureSnapshot { name, region } => self.capture_snapshot(name, region),
+            Action::RestoreSnapshot(index) => self.restore_snapshot(index),
+            Action::DeleteSnapshot(index) => {
+                let Some(ui) = self.ui.upgrade() else { return; };
+                ui_handlers::edit_snapshots(&ui, &self.context, |file, items| {
+                    if index >= items.len() {
+                        return false;
+                    }
+                    snapshot_gallery::remove_thumbnail(file, &items.remove(index));
+                    true
+                });
+            }
+            // Tryb ekspozycji i pivot zmieniają sposób mapowania – odśwież podgląd
+            Action::SetExposureMode(mode) => {
+                self.record_view_change(Change::ExposureMode, |s| s.exposure_mode = mode);
+                self.context.update_view(|view| view.render.graph.exposure_mode = mode);
+                info!(target: "processing", "exposure mode: {:?}", mode);
+                self.refresh();
+            }
+            Action::SetTonemap { mode, radius, strength } => {
+                self.context.update_view(|view| view.render.graph.local_tonemap = LocalTonemap::for_mode(mode, radius, strength));
+                info!(target: "processing", "tone mapping: {} (radius {:.3}, strength {:.2})", mode.label(), radius, strength);
+                self.refresh();
+            }
+            Action::SetMiddleGray(pivot) => {
+                self.context.update_view(|view| view.render.graph.middle_gray = pivot.clamp(0.001, 10.0));
+                self.refresh();
+            }
+            Action::SetGrayscaleMode(mode) => {
+                self.context.update_view(|view| view.render.grayscale = mode);
+                info!(target: "processing", "display mode: {:?}", mode);
+                self.refresh();
+            }
+            Action::SetFalseColor(enabled) => {
+                self.context.update_view(|view| view.render.false_color = enabled);
+                info!(target: "processing", "false color: {}", if enabled { "on" } else { "off" });
+                self.refresh();
+            }
+            Action::SetGamutWarning(target) => {
+                self.context.update_view(|view| view.render.gamut_warning = target);
+                info!(target: "processing", "gamut warning: {:?}", target);
+                self.refresh();
+            }
+            // Obrót/odbicie to tylko remapowanie indeksów przy generowaniu obrazu – wystarczy przerysować podgląd
+            Action::SetDisplayTransform(transform) => {
+                self.context.update_view(|view| view.render.transform = transform);
+                info!(target: "processing", "display transform: {:?}", transform);
+                self.refresh();
+            }
+            Action::SetVectorDisplay { max_magnitude, arrows } => {
+                self.context.update_view(|view| {
+                    view.render.vector_max_magnitude = max_magnitude.max(1e-3);
+                    view.render.vector_arrows = arrows;
+                });
+                self.refresh();
+            }
+            Action::SetRelight { enabled, azimuth, elevation } => {
+                self.context.update_view(|view| view.render.relight = enabled.then(|| image_processing::relight_direction(azimuth, elevation)));
+                self.refresh();
+            }
+            Action::SetEnvView(view) => {
+                self.context.update_view(|settings| settings.render.env_view = view);
+                debug!(target: "processing", "environment map view: {:?}", view);
+                self.refresh();
+            }
+            Action::SetStereoMode(mode) => {
+                self.context.update_view(|view| view.render.stereo_mode = mode);
+                info!(target: "processing", "stereo mode: {:?}", mode);
+                let path = lock_or_recover(&self.current_file_path).clone();
+                if let (Some(path), Some(cache)) = (path, lock_or_recover(&self.image_cache).as_mut()) {
+                    cache.set_stereo_mode(&path, mode);
+                }
+                self.refresh();
+            }
+            Action::ToggleStereoView => {
+                let Some(ui) = self.ui.upgrade() else { return; };
+                let target = lock_or_recover(&self.image_cache).as_ref().and_then(|cache| {
+                    let (left, right) = stereo::eye_views(&cache.views);
+                    let other = if stereo::view_of(&cache.current_layer_name, &cache.views) == right { left } else { right };
+                    stereo::layer_in_view(&cache.current_layer_name, other, &cache.views, cache.layers_info.iter().map(|l| l.name.as_str()))
+                });
+                let node = target.as_ref().and_then(|layer| {
+                    ui.get_layer_nodes().iter().find(|n| n.kind == ui_handlers::NODE_KIND_LAYER && n.layer == layer.as_str())
+                });
+                match node {
+                    Some(node) => self.dispatch(Action::SelectLayerNode(node)),
+                    None => ui.set_status_text("No matching layer in the other view".into()),
+                }
+            }
+            Action::SetSharpen { enabled, amount, radius, in_exports } => {
+                self.context.update_view(|view| view.render.sharpen = enabled.then_some(Sharpen { amount, radius, in_exports }));
+                debug!(target: "processing", "sharpen: {} (amount {:.2}, radius {:.1} px, exports {})", enabled, amount, radius, in_exports);
+                self.refresh();
+            }
+            Action::SetBloom { enabled, threshold, intensity, display_only } => {
+                self.context.update_view(|view| view.render.bloom = enabled.then_some(Bloom { threshold, intensity, display_only }));
+                debug!(target: "processing", "bloom: {} (threshold {:.2}, intensity {:.2}, display only {})", enabled, threshold, intensity, display_only);
+                self.refresh();
+            }
+            Action::SetFocusBand { enabled, near, far } => {
+                self.context.update_view(|view| view.render.focus_band = enabled.then(|| image_processing::focus_band(near, far)));
+                self.refresh_focus_band();
+            }
+            Action::SetInputColorSpace(space) => {
+                self.context.update_view(|view| view.input_override = space);
+                info!(target: "processing", "input color space: {}", space.map_or("Auto", InputColorSpace::label));
+                self.refresh();
+            }
+            Action::SetPipelineStage { index, enabled } => {
+                let Some(stage) = Stage::from_index(index) else { return; };
+                self.context.update_view(|view| view.render.graph.set_enabled(stage, enabled));
+                info!(target: "processing", "pipeline stage {}: {}", stage.label(), if enabled { "on" } else { "off" });
+                self.refresh();
+            }
+            Action::WhiteBalanceFromSample => self.white_balance_from_sample(),
+            Action::ResetWhiteBalance => {
+                self.context.update_view(|view| view.render.graph.white_balance = image_processing::white_balance_gains(None));
+                info!(target: "processing", "white balance reset");
+                self.refresh();
+            }
+            // Gain/offset/abs dotyczą bieżącego widoku AOV technicznego
+            Action::SetAovRemap(new_remap) => {
+                if let Some(remap) = lock_or_recover(&self.image_cache).as_mut().and_then(|c| c.channel_remap.as_mut()) {
+                    *remap = new_remap;
+                }
+                self.refresh();
+            }
+
+            // Porównanie z referencją: różnica przeliczana przy każdym odświeżeniu podglądu
+            Action::SetReference => self.set_reference(),
+            Action::ClearReference => {
+                self.context.compare().clear_reference();
+                if let Some(ui) = self.ui.upgrade() {
+                    ui.set_has_reference(false);
+                    ui.set_reference_name("".into());
+                }
+                self.refresh();
+            }
+            Action::SetCompareMode(mode) => {
+                self.context.compare().set_mode(mode);
+                info!(target: "processing", "compare mode: {:?}", mode);
+                self.refresh();
+            }
+            Action::SetCompareTolerance(tolerance) => {
+                self.context.compare().set_tolerance(tolerance);
+                self.refresh();
+            }
+
+            Action::SampleColor(u0, v0, u1, v1) => self.sample_color(u0, v0, u1, v1),
+            Action::SetViewZoom(zoom) => {
+                if ui_handlers::set_preview_zoom(&self.context, zoom) {
+                    self.refresh();
+                }
+            }
+            Action::HoverPixel(u, v) => {
+                let Some(ui) = self.ui.upgrade() else { return; };
+                let transform = self.context.view().render.transform;
+                let pixel = lock_or_recover(&self.image_cache).as_ref().and_then(|cache| cache.pixel_at(u, v, &transform));
+                let readout = pixel.map(|((x, y), (ox, oy))| format!("x {} y {}  ·  data window {}, {}", ox + x as i32, oy + y as i32, x, y));
+                ui.set_pixel_readout(readout.unwrap_or_default().into());
+            }
+            Action::ClearSwatches => {
+                self.swatches.borrow_mut().clear();
+                self.show_swatches();
+                if let Some(ui) = self.ui.upgrade() {
+                    ui_handlers::set_selection(&ui, &self.context, None);
+                }
+                self.refresh();
+            }
+
+            Action::OpenPointCloud => self.open_point_cloud(),
+            Action::OrbitPointCloud { yaw, pitch, zoom } => {
+                if let (Some(ui), Some(cloud)) = (self.ui.upgrade(), self.point_cloud.borrow().as_ref()) {
+                    ui.set_point_cloud_image(cloud.render(Orbit { yaw, pitch, zoom }, POINT_CLOUD_SIZE.0, POINT_CLOUD_SIZE.1));
+                }
+            }
+
+            Action::SelectLayerNode(node) => {
+                let Some(ui) = self.ui.upgrade() else { return; };
+                let (before, selected) = (self.current_view(&ui), ui.get_selected_layer_node());
+                ui_handlers::handle_layer_tree_click(self.ui.clone(), self.image_cache.clone(), node, self.current_file_path.clone(), self.context.clone());
+                // Krok historii tylko gdy wybór faktycznie się zmienił (nie dla zwijania grup i błędów wczytania)
+                if ui.get_selected_layer_node() != selected {
+                    self.history.borrow_mut().record(before, Change::Selection);
+                }
+            }
+            Action::ToggleLayerNode(id) => ui_handlers::handle_layer_node_toggled(self.ui.clone(), id),
+            Action::RegroupLayers => ui_handlers::handle_layer_grouping_changed(self.ui.clone(), self.image_cache.clone()),
+            Action::SetLightingOnly(lighting_only) => ui_handlers::handle_lighting_only_changed(self.ui.clone(), lighting_only),
+
+            Action::ClearConsole => {
+                console::clear(&self.console_model);
+                if let Some(ui) = self.ui.upgrade() {
+                    ui.set_status_text(SharedString::from("Console cleared"));
+                }
+            }
+            Action::SaveConsoleLog => self.save_console_log(),
+            Action::SetConsoleSearch(text) => console::set_search(&self.console_model, &text),
+            Action::SetConsoleLevel(level) => {
+                if let Some(filter) = logging::parse_level(&level) {
+                    logging::set_level(filter);
+                    info!(target: "ui", "log level set to {}", filter);
+                }
+            }
+            Action::SetConsoleCategory(category) => console::set_category(&self.console_model, &category),
+        }
+    }
+
+    fn set_reference(&self) {
+        let Some(ui) = self.ui.upgrade() else { return; };
+        {
+            let Some(cache) = &*lock_or_recover(&self.image_cache) else {
+                ui.set_status_text("Error: No file loaded".into());
+                return;
+            };
+            self.context.compare().set_reference(cache);
+            let name = lock_or_recover(&self.current_file_path)
+                .as_ref()
+                .map(file_operations::get_file_name)
+                .unwrap_or_default();
+            let label = if cache.current_layer_name.is_empty() { name } else { format!("{} [{}]", name, cache.current_layer_name) };
+            info!(target: "processing", "reference set: {}", label);
+            ui.set_reference_name(label.into());
+            ui.set_has_reference(true);
+        }
+        self.refresh();
+    }
+
+    /// Buduje chmurę z pierwszej warstwy pozycji i warstwy beauty bieżącego pliku i otwiera okno podglądu
+    fn open_point_cloud(&self) {
+        let Some(ui) = self.ui.upgrade() else { return; };
+        let Some(path) = lock_or_recover(&self.current_file_path).clone() else {
+            ui.set_status_text("Error: No file loaded".into());
+            return;
+        };
+        let (position_layer, beauty_layer) = {
+            let guard = lock_or_recover(&self.image_cache);
+            let Some(cache) = guard.as_ref() else {
+                ui.set_status_text("Error: No file loaded".into());
+                return;
+            };
+            let position = cache.layers_info.iter()
+                .find(|l| channel_classification::classify(&l.name, "") == AovKind::Position)
+                .map(|l| l.name.clone());
+            (position, find_best_layer(&cache.layers_info))
+        };
+        let Some(position_layer) = position_layer else {
+            ui.set_status_text("No position AOV (P) in this file".into());
+            return;
+        };
+
+        let loaded = load_specific_layer(&path, &position_layer)
+            .and_then(|position| Ok((position, load_specific_layer(&path, &beauty_layer)?)));
+        let ((position, width, height, _), (beauty, beauty_width, beauty_height, _)) = match loaded {
+            Ok(layers) => layers,
+            Err(e) => {
+                error!(target: "io", "point cloud: cannot load layers: {}", e);
+                ui.set_status_text(format!("Point cloud error: {}", e).into());
+                return;
+            }
+        };
+        // Beauty o innej rozdzielczości (np. inne data window) – punkty bez koloru
+        let beauty: &[(f32, f32, f32, f32)] = if (beauty_width, beauty_height) == (width, height) { &beauty } else { &[] };
+        let cloud = PointCloud::from_pixels(&position, beauty, width as usize, height as usize, ui.get_exposure_value(), ui.get_gamma_value());
+        info!(target: "processing", "point cloud: {} points from '{}' (every {} px), colored by '{}'",
+            cloud.len(), position_layer, cloud.step, beauty_layer);
+
+        ui.set_point_cloud_info(format!("{} — {} points, every {} px", position_layer, cloud.len(), cloud.step).into());
+        ui.set_point_cloud_image(cloud.render(
+            Orbit { yaw: 30.0, pitch: 20.0, zoom: 1.0 },
+            POINT_CLOUD_SIZE.0,
+            POINT_CLOUD_SIZE.1,
+        ));
+        ui.set_internal_pointcloud_visible(true);
+        *self.point_cloud.borrow_mut() = Some(cloud);
+    }
+
+    /// Przerysowuje widok głębi od razu (bez throttlingu, który nadpisałby status ekspozycją)
+    /// i pokazuje pasmo ostrości w jednostkach Z
+    fn refresh_focus_band(&self) {
+        let Some(ui) = self.ui.upgrade() else { return; };
+        let guard = lock_or_recover(&self.image_cache);
+        let Some(cache) = guard.as_ref().filter(|c| c.depth_view.is_some()) else { return; };
+        let settings = self.context.render_settings(ui.get_exposure_value(), ui.get_gamma_value());
+        ui.set_exr_image(self.context.for_display(cache.process_to_image(&settings)));
+        if let Some((near, far)) = settings.focus_band {
+            let (lo, hi) = cache.depth_range();
+            ui.set_status_text(format!("Focus band: Z {:.3} – {:.3}", lo + near * (hi - lo), lo + far * (hi - lo)).into());
+        }
+    }
+
+    fn sample_color(&self, u0: f32, v0: f32, u1: f32, v1: f32) {
+        let Some(ui) = self.ui.upgrade() else { return; };
+        // Dla zaznaczonego obszaru (nie pojedynczego piksela) także statystyki surowych wartości
+        let transform = self.context.view().render.transform;
+        let (sample, stats) = match lock_or_recover(&self.image_cache).as_ref() {
+            Some(cache) => {
+                let sample = cache.sample_region(u0, v0, u1, v1, &transform);
+                let is_region = sample.is_some_and(|s| s.width > 1 || s.height > 1);
+                (sample, if is_region { cache.region_stats(u0, v0, u1, v1, &transform) } else { None })
+            }
+            None => return,
+        };
+        let Some(sample) = sample else { return; };
+        // Zaznaczony obszar zostaje jako marquee: histogram i "Auto" liczone tylko z niego
+        let region = stats.is_some().then_some([u0, v0, u1, v1]);
+        if region != self.context.view().selection {
+            ui_handlers::set_selection(&ui, &self.context, region);
+            self.refresh();
+        }
+        match stats {
+            Some(stats) => {
+                let lines = stats.lines();
+                info!(target: "processing", "region stats {}: {}", sample.area_text(), lines.join(" | "));
+                ui.set_region_stats(lines.join("\n").into());
+            }
+            None => ui.set_region_stats("".into()),
+        }
+        let [r, g, b] = sample.rgb8();
+        ui.set_picked_color(Color::from_rgb_u8(r, g, b));
+        ui.set_picked_float(sample.float_text().into());
+        ui.set_picked_8bit(sample.rgb8_text().into());
+        ui.set_picked_hex(sample.hex().into());
+        info!(target: "ui", "color sample {}: {} {}", sample.area_text(), sample.float_text(), sample.hex());
+
+        {
+            let mut swatches = self.swatches.borrow_mut();
+            swatches.insert(0, sample);
+            swatches.truncate(color_picker::HISTORY_LEN);
+        }
+        self.show_swatches();
+    }
+
+    /// Próbka jest w przestrzeni pliku – balans bieli działa po macierzy wejściowej, więc przeliczana
+    /// tym samym etapem co podgląd
+    fn white_balance_from_sample(&self) {
+        let Some(sample) = self.swatches.borrow().first().copied() else {
+            warn!(target: "processing", "white balance: no color sample (use the color picker first)");
+            return;
+        };
+        let mut graph = self.context.processing_graph(0.0, 1.0);
+        graph.white_balance = [1.0; 3];
+        let (r, g, b) = graph.tone_params().working_rgb(sample.r, sample.g, sample.b);
+        if r <= 0.0 || g <= 0.0 || b <= 0.0 || !(r + g + b).is_finite() {
+            warn!(target: "processing", "white balance: sample {} is not a usable neutral", sample.float_text());
+            return;
+        }
+        let view = self.context.update_view(|view| view.render.graph.white_balance = image_processing::white_balance_gains(Some([r, g, b])));
+        info!(target: "processing", "white balance from sample {}: {}", sample.area_text(), view.render.graph.describe(Stage::WhiteBalance));
+        self.refresh();
+    }
+
+    fn show_swatches(&self) {
+        let Some(ui) = self.ui.upgrade() else { return; };
+        let swatches = self.swatches.borrow();
+        let items: Vec<Swatch> = swatches.iter()
+            .map(|s| {
+                let [r, g, b] = s.rgb8();
+                Swatch { color: Color::from_rgb_u8(r, g, b), text: format!("{} {}", s.float_text(), s.area_text()).into() }
+            })
+            .collect();
+        ui.set_swatches(ModelRc::new(VecModel::from(items)));
+        ui.set_swatches_nuke_text(color_picker::nuke_text(&swatches).into());
+        ui.set_swatches_houdini_text(color_picker::houdini_text(&swatches).into());
+    }
+
+    /// Skrypt działa w osobnym wątku; postęp trafia do listy zadań, wynik do paska statusu
+    #[cfg(feature = "scripting")]
+    fn run_script(&self, script: PathBuf) {
+        let ui = self.ui.clone();
+        info!(target: "script", "running {}", script.display());
+        let name = script.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+        let task = progress::register(ui.clone(), format!("Script {}", name), None);
+        std::thread::spawn(move || {
+            // Zadanie znika z listy razem z domknięciem po zakończeniu skryptu
+            let report = move |fraction: f32, message: &str| task.set(fraction, Some(message));
+            if let Err(e) = crate::scripting::run_script(&script, report) {
+                error!(target: "script", "{}: {}", script.display(), e);
+                let _ = ui.upgrade_in_event_loop(move |ui| ui.set_status_text(format!("Script error: {}", e).into()));
+            }
+        });
+    }
+
+    #[cfg(not(feature = "scripting"))]
+    fn run_script(&self, script: PathBuf) {
+        error!(target: "script", "cannot run {}: scripting support not compiled in", script.display());
+        if let Some(ui) = self.ui.upgrade() {
+            ui.set_status_text("Scripting is not available in this build (enable the \"scripting\" feature)".into());
+        }
+    }
+
+    /// Włączenie wczytuje profil na nowo – okno mogło zostać przeniesione na inny monitor
+    fn set_theme(&self, f: impl FnOnce(&mut theme::ThemeSettings)) {
+        let settings = theme::update(f);
+        info!(target: "ui", "theme: {:?}, accent {}", settings.mode, theme::accent_hex(settings.accent));
+        let Some(ui) = self.ui.upgrade() else { return; };
+        theme::apply(&ui);
+        ui_handlers::recolor_layer_nodes(&ui);
+    }
+
+    fn set_monitor_profile(&self, enabled: bool) {
+        let Some(ui) = self.ui.upgrade() else { return; };
+        if enabled {
+            let (position, size) = (ui.window().position(), ui.window().size());
+            let center = (position.x + size.width as i32 / 2, position.y + size.height as i32 / 2);
+            let path = std::env::var_os(display_profile::ICC_PROFILE_ENV).map(PathBuf::from)
+                .or_else(|| platform::monitor_icc_profile(center.0, center.1));
+            let loaded = path
+                .ok_or_else(|| "no ICC profile assigned to this monitor".to_string())
+                .and_then(|p| DisplayProfile::load(&p));
+            match loaded {
+                Ok(profile) => {
+                    info!(target: "ui", "monitor ICC profile: {}", profile.description);
+                    ui.set_monitor_profile_name(profile.description.clone().into());
+                    self.context.set_monitor_profile(Some(profile));
+                }
+                Err(e) => {
+                    warn!(target: "ui", "monitor ICC profile unavailable: {}", e);
+                    ui.set_monitor_profile_enabled(false);
+                    ui.set_status_text(format!("Monitor ICC profile unavailable: {}", e).into());
+                    return;
+                }
+            }
+        }
+        self.context.set_monitor_profile_enabled(enabled);
+        self.refresh();
+    }
+
+    /// Katalog roboczy (panel folderów), a bez niego katalog bieżącego pliku
+    fn working_dir(&self, ui: &AppWindow) -> Option<PathBuf> {
+        let folder = ui.get_current_folder();
+        if !folder.is_empty() {
+            return Some(PathBuf::from(folder.as_str()));
+        }
+        lock_or_recover(&self.current_file_path).as_ref().and_then(|p| p.parent().map(PathBuf::from))
+    }
+
+    /// Generowanie w osobnym wątku; postęp trafia do listy zadań, wynik do paska statusu
+    fn generate_proxies(&self) {
+        let Some(ui) = self.ui.upgrade() else { return; };
+        let Some(dir) = self.working_dir(&ui) else {
+            ui.set_status_text("Select a working folder first".into());
+            return;
+        };
+        info!(target: "io", "generating proxies in {}", dir.display());
+        let task = progress::register(self.ui.clone(), "Generate proxies", None);
+        task.start_indeterminate(Some(&format!("Generating proxies in {}...", dir.display())));
+        let ui = self.ui.clone();
+        std::thread::spawn(move || {
+            let result = worker_threads::install(|| proxy_files::generate_for_directory(&dir, |fraction, message| task.set(fraction, Some(message))));
+            task.reset();
+            let _ = ui.upgrade_in_event_loop(move |ui| {
+                match result {
+                    Ok(summary) => {
+                        info!(target: "io", "proxies: {} written, {} up to date, {} failed", summary.written, summary.up_to_date, summary.failed);
+                        ui.set_status_text(format!(
+                            "Proxies: {} written, {} up to date, {} failed",
+                            summary.written, summary.up_to_date, summary.failed
+                        ).into());
+                    }
+                    Err(e) => {
+                        error!(target: "io", "generating proxies in {}: {}", dir.display(), e);
+                        ui.set_status_text(format!("Proxy error: {}", e).into());
+                    }
+                }
+            });
+        });
+    }
+
+    /// Raport w osobnym wątku (z anulowaniem); postęp trafia do listy zadań, wynik do paska statusu
+    fn qc_report(&self) {
+        let Some(ui) = self.ui.upgrade() else { return; };
+        let folder = ui.get_current_folder();
+        let input = if folder.is_empty() {
+            lock_or_recover(&self.current_file_path).clone()
+        } else {
+            Some(PathBuf::from(folder.as_str()))
+        };
+        let Some(input) = input else {
+            ui.set_status_text("Open a file or select a working folder first".into());
+            return;
+        };
+        let Some(output) = file_operations::save_report_dialog(&input) else { return; };
+        info!(target: "io", "QC report of {} → {}", input.display(), output.display());
+        let cancel = CancelToken::new();
+        let task = progress::register(self.ui.clone(), "QC report", Some(cancel.clone()));
+        task.start_indeterminate(Some(&format!("QC report of {}...", input.display())));
+        let ui = self.ui.clone();
+        std::thread::spawn(move || {
+            let result = worker_threads::install(|| qc_report::write_report(&input, &output, &cancel, |fraction, message| task.set(fraction, Some(message))));
+            task.reset();
+            let _ = ui.upgrade_in_event_loop(move |ui| {
+                match result {
+                    Ok(summary) => ui.set_status_text(format!(
+                        "QC report: {} files, {} unreadable, {} with NaN/Inf → {}",
+                        summary.files, summary.failed, summary.non_finite, output.display()
+                    ).into()),
+                    Err(e) => {
+                        error!(target: "io", "QC report of {}: {}", input.display(), e);
+                        ui.set_status_text(format!("QC report error: {}", e).into());
+                    }
+                }
+            });
+        });
+    }
+
+    /// Pozycja osi czasu wybrana przez `frame`; wczytuje plik tylko gdy pokazać trzeba inny
+    fn show_frame(&self, frame: impl FnOnce(&Timeline) -> i64) {
+        let Some(ui) = self.ui.upgrade() else { return; };
+        if !timeline::has_timeline() {
+            ui.set_status_text("The current file is not part of an image sequence".into());
+            return;
+        }
+        let current = lock_or_recover(&self.current_file_path).clone();
+        let current = current.map(|p| proxy_files::original_path(&p).unwrap_or(p));
+        if let Some(path) = timeline::show(&ui, frame, current.as_deref()) {
+            ui_handlers::handle_open_exr_from_path(self.ui.clone(), self.current_file_path.clone(), self.image_cache.clone(), self.context.clone(), path);
+        }
+    }
+
+    /// Skan w osobnym wątku (z anulowaniem); tabela niespójności w oknie "Sequence check"
+    fn check_sequence(&self) {
+        let Some(ui) = self.ui.upgrade() else { return; };
+        let Some(path) = lock_or_recover(&self.current_file_path).clone() else {
+            ui.set_status_text("Error: No file loaded".into());
+            return;
+        };
+        let cancel = CancelToken::new();
+        let task = progress::register(self.ui.clone(), "Sequence check", Some(cancel.clone()));
+        task.start_indeterminate(Some("Looking for the frame sequence..."));
+        let ui = self.ui.clone();
+        std::thread::spawn(move || {
+            let result = sequence::sequence_of(&path).and_then(|found| match found {
+                Some(seq) => sequence::check(&seq, &cancel, |fraction, message| task.set(fraction, Some(message))).map(Some),
+                None => Ok(None),
+            });
+            task.reset();
+            let _ = ui.upgrade_in_event_loop(move |ui| {
+                match result {
+                    Ok(Some(check)) => {
+                        let (keys, values): (Vec<SharedString>, Vec<SharedString>) = check.rows().into_iter()
+                            .map(|(k, v)| (SharedString::from(k), SharedString::from(v)))
+                            .unzip();
+                        ui.set_sequence_check_keys(ModelRc::new(VecModel::from(keys)));
+                        ui.set_sequence_check_values(ModelRc::new(VecModel::from(values)));
+                        ui.set_internal_sequence_visible(true);
+                        ui.set_status_text(format!("{}: {} frames, {} issue(s)", check.label, check.frames, check.issues.len()).into());
+                    }
+                    Ok(None) => ui.set_status_text(format!("{} is not part of an image sequence", path.display()).into()),
+                    Err(e) => {
+                        error!(target: "io", "sequence check of {}: {}", path.display(), e);
+                        ui.set_status_text(format!("Sequence check error: {}", e).into());
+                    }
+                }
+            });
+        });
+    }
+
+    /// Sekwencje zaznaczonych miniatur (każda raz, niezależnie od liczby zaznaczonych klatek)
+    fn open_sequence_rename(&self) {
+        let Some(ui) = self.ui.upgrade() else { return; };
+        let mut sequences: Vec<sequence::Sequence> = Vec::new();
+        let mut singles = Vec::new();
+        for thumb in ui.get_thumbnails().iter().filter(|t| t.selected) {
+            let path = PathBuf::from(thumb.path.as_str());
+            if sequences.iter().any(|s| s.index_of(&path).is_some()) {
+                continue;
+            }
+            match sequence::sequence_of(&path) {
+                Ok(Some(found)) => sequences.push(found),
+                Ok(None) => singles.push(thumb.name.to_string()),
+                Err(e) => warn!(target: "io", "{:#}", e),
+            }
+        }
+        if sequences.is_empty() {
+            ui.set_status_text("Rename: the selection contains no image sequences".into());
+            return;
+        }
+        let files: usize = sequences.iter().map(|s| s.frames.len()).sum();
+        let mut summary = format!("{} sequence(s), {} files", sequences.len(), files);
+        if !singles.is_empty() {
+            summary.push_str(&format!("; skipped (not sequences): {}", singles.join(", ")));
+        }
+        ui.set_rename_summary(summary.into());
+        ui.set_rename_can_undo(io_files::can_undo());
+        *self.rename_sequences.borrow_mut() = sequences;
+        ui.set_internal_rename_visible(true);
+        self.preview_sequence_rename();
+    }
+
+    fn rename_plan(&self, ui: &AppWindow) -> Result<Vec<FileMove>, String> {
+        let destination = ui.get_rename_destination();
+        let destination = Some(destination.trim()).filter(|d| !d.is_empty()).map(PathBuf::from);
+        io_files::plan(&self.rename_sequences.borrow(), &ui.get_rename_template(), destination.as_deref())
+    }
+
+    fn preview_sequence_rename(&self) {
+        let Some(ui) = self.ui.upgrade() else { return; };
+        let (lines, message) = match self.rename_plan(&ui) {
+            Ok(moves) if moves.is_empty() => (Vec::new(), "Nothing to rename".to_string()),
+            Ok(moves) => (io_files::preview(&self.rename_sequences.borrow(), &moves), format!("{} file(s) will be renamed or moved", moves.len())),
+            Err(e) => (Vec::new(), e),
+        };
+        let lines: Vec<SharedString> = lines.into_iter().map(SharedString::from).collect();
+        ui.set_rename_preview(ModelRc::new(VecModel::from(lines)));
+        ui.set_rename_message(message.into());
+    }
+
+    /// Zmiana nazw w osobnym wątku; po niej dziennik cofania i odświeżenie miniatur
+    fn apply_sequence_rename(&self) {
+        let Some(ui) = self.ui.upgrade() else { return; };
+        let moves = match self.rename_plan(&ui) {
+            Ok(moves) if !moves.is_empty() => moves,
+            Ok(_) => return,
+            Err(e) => {
+                ui.set_rename_message(e.into());
+                return;
+            }
+        };
+        info!(target: "io", "renaming {} file(s) with template {}", moves.len(), ui.get_rename_template());
+        // Plan dotyczy starych nazw – po wykonaniu okno czeka na nowe zaznaczenie albo cofnięcie
+        self.rename_sequences.borrow_mut().clear();
+        ui.set_rename_preview(ModelRc::default());
+        ui.set_rename_message("Renaming...".into());
+        self.move_files_in_background("Rename sequences", move |progress| {
+            io_files::apply(&moves, progress)?;
+            io_files::record(&moves);
+            Ok(Some(moves))
+        });
+    }
+
+    fn undo_sequence_rename(&self) {
+        info!(target: "io", "undoing the last sequence rename");
+        self.move_files_in_background("Undo rename", |progress| io_files::undo_last(progress));
+    }
+
+    /// Wspólne zakończenie zmiany nazw i cofnięcia: bieżący plik pod nową ścieżką, odświeżone
+    /// miniatury katalogu roboczego, wynik w oknie i pasku statusu
+    fn move_files_in_background(
+        &self,
+        name: &str,
+        work: impl FnOnce(&dyn Fn(f32, &str)) -> std::io::Result<Option<Vec<FileMove>>> + Send + 'static,
+    ) {
+        let task = progress::register(self.ui.clone(), name, None);
+        task.start_indeterminate(Some(&format!("{}...", name)));
+        let (ui, current_file_path, context) = (self.ui.clone(), self.current_file_path.clone(), self.context.clone());
+        std::thread::spawn(move || {
+            let result = work(&|fraction, message| task.set(fraction, Some(message)));
+            task.reset();
+            let _ = ui.upgrade_in_event_loop(move |ui| {
+                let message = match result {
+                    Ok(Some(moves)) => {
+                        let mut current = lock_or_recover(&current_file_path);
+                        if let Some(m) = moves.iter().find(|m| current.as_ref() == Some(&m.from)) {
+                            *current = Some(m.to.clone());
+                            ui.set_opened_thumbnail_path(m.to.display().to_string().into());
+                        }
+                        drop(current);
+                        let folder = ui.get_current_folder();
+                        if !folder.is_empty() {
+                            ui_handlers::load_thumbnails_for_directory(&ui, &context, std::path::Path::new(folder.as_str()), ui.get_thumbs_viewport_x());
+                        }
+                        format!("{} file(s) renamed or moved", moves.len())
+                    }
+                    Ok(None) => "Nothing to undo".to_string(),
+                    Err(e) => {
+                        error!(target: "io", "renaming files: {}", e);
+                        format!("Error: {}", e)
+                    }
+                };
+                ui.set_rename_can_undo(io_files::can_undo());
+                ui.set_rename_message(message.clone().into());
+                ui.set_status_text(message.into());
+            });
+        });
+    }
+
+    /// Kosz w osobnym wątku (pliki z innego dysku są kopiowane); potem z paska znikają miniatury
+    /// plików, których już nie ma – także gdy część się nie powiodła
+    fn trash_selected_thumbnails(&self) {
+        let Some(ui) = self.ui.upgrade() else { return; };
+        let paths: Vec<PathBuf> = ui.get_thumbnails().iter().filter(|t| t.selected).map(|t| PathBuf::from(t.path.as_str())).collect();
+        if paths.is_empty() || (paths.len() > 1 && !file_operations::confirm_trash_dialog(&paths)) {
+            return;
+        }
+        info!(target: "io", "moving {} file(s) to the recycle bin", paths.len());
+        let task = progress::register(self.ui.clone(), "Move to recycle bin", None);
+        task.start_indeterminate(Some(&format!("Moving {} file(s) to the recycle bin...", paths.len())));
+        let ui = self.ui.clone();
+        std::thread::spawn(move || {
+            let result = platform::move_to_trash(&paths);
+            task.reset();
+            let _ = ui.upgrade_in_event_loop(move |ui| {
+                let remaining: Vec<_> = ui.get_thumbnails().iter().filter(|t| Path::new(t.path.as_str()).exists()).collect();
+                let removed = ui.get_thumbnails().row_count() - remaining.len();
+                ui.set_thumbnails(ModelRc::new(VecModel::from(remaining)));
+                if !Path::new(ui.get_opened_thumbnail_path().as_str()).exists() {
+                    ui.set_opened_thumbnail_path("".into());
+                }
+                match result {
+                    Ok(()) => ui.set_status_text(format!("Moved {} file(s) to the recycle bin", removed).into()),
+                    Err(e) => {
+                        error!(target: "io", "moving to the recycle bin: {}", e);
+                        ui.set_status_text(format!("Recycle bin error ({} file(s) moved): {}", removed, e).into());
+                    }
+                }
+            });
+        });
+    }
+
+    /// Zlecenie do kolejki eksportu; postęp trafia do listy zadań, wynik do paska statusu
+    fn export_channels(&self, format: ChannelFormat, all_layers: bool) {
+        let Some(ui) = self.ui.upgrade() else { return; };
+        let Some(path) = lock_or_recover(&self.current_file_path).clone() else {
+            ui.set_status_text("Error: No file loaded".into());
+            return;
+        };
+        let layers = match (all_layers, lock_or_recover(&self.image_cache).as_ref()) {
+            (false, Some(cache)) => Some(vec![cache.current_layer_name.clone()]),
+            _ => None,
+        };
+        let Some(output_dir) = file_operations::export_folder_dialog(&export_handlers::default_output_dir(&path)) else { return; };
+        let config = UiExportConfig::from_ui(&ui);
+
+        info!(target: "io", "exporting channels of {} → {} ({:?})", path.display(), output_dir.display(), format);
+        export_queue::enqueue(&ui, ExportSpec::Channels { source: path, layers, output_dir, format, config }, None);
+    }
+
+    /// Eksport tego, co widać (te same ustawienia ekspozycji, gammy i przestrzeni wejściowej), ale
+    /// renderowany od nowa w pełnej rozdzielczości w kolejce eksportu – bez udziału buforów
+    /// podglądu i profilu monitora. Plik jest weryfikowany po zapisie.
+    fn export_image(&self, options: DeliveryOptions) {
+        let Some(ui) = self.ui.upgrade() else { return; };
+        let Some(path) = lock_or_recover(&self.current_file_path).clone() else {
+            ui.set_status_text("Error: No file loaded".into());
+            return;
+        };
+        // Kopia współdzieli piksele – blokada cache trwa tylko chwilę, podgląd działa dalej
+        let Some(source) = lock_or_recover(&self.image_cache).as_ref().map(ImageCache::detached) else { return; };
+        let layer_name = source.current_layer_name.clone();
+        // Migawka widoku z chwili zlecenia – późniejsze zmiany podglądu nie trafiają do eksportu
+        let settings = self.context.render_settings(ui.get_exposure_value(), ui.get_gamma_value());
+        let Some(output_dir) = file_operations::export_folder_dialog(&export_handlers::default_output_dir(&path)) else { return; };
+        let config = UiExportConfig::from_ui(&ui);
+        let settings = config.render_settings(settings);
+        let name = path.file_stem().map(|s| s.to_string_lossy().into_owned()).unwrap_or_default();
+        let fields = NameFields { name: &name, layer: &layer_name, channel: "", tonemap: options.output.tag() };
+        let stem = export_handlers::fill_template(&config.template, &fields);
+        let extension = options.format.extension();
+        let Some(target) = export_handlers::claim_target(&output_dir, &stem, extension, config.collision) else {
+            ui.set_status_text(format!("Skipped: {}.{} already exists", stem, extension).into());
+            return;
+        };
+
+        info!(target: "io", "exporting {} → {} ({:?}, {:?}, quality {}, {:?})", path.display(), target.display(), options.format, options.output, options.quality, options.subsampling);
+        let spec = ExportSpec::Image { source: path, layer: layer_name, settings, target, collision: config.collision, options };
+        export_queue::enqueue(&ui, spec, Some(source));
+    }
+
+    /// Notatka narzędziem z panelu: pióro i strzałka powstają między wciśnięciem a puszczeniem
+    /// przycisku (zwykły klik jest pomijany), tekst w miejscu kliknięcia
+    fn draw_annotation(&self, phase: DrawPhase, u: f32, v: f32) {
+        const MIN_STEP: f32 = 0.002;
+        let Some(ui) = self.ui.upgrade() else { return; };
+        let point = [u.clamp(0.0, 1.0), v.clamp(0.0, 1.0)];
+        let distance = |a: [f32; 2], b: [f32; 2]| (a[0] - b[0]).hypot(a[1] - b[1]);
+        let color = annotations::color_from_label(&ui.get_annotation_color());
+        let tool = ui.get_annotate_tool();
+        let text = ui.get_annotation_text().trim().to_string();
+        if phase == DrawPhase::Start && tool == "Text" && text.is_empty() {
+            ui.set_status_text("Type the note text first, then click the image".into());
+            return;
+        }
+        ui_handlers::edit_annotations(&ui, &self.context, |items, draft| match phase {
+            DrawPhase::Start => {
+                let shape = match tool.as_str() {
+                    "Text" => {
+                        items.push(Annotation { shape: Shape::Text { at: point, text }, color });
+                        return true;
+                    }
+                    "Arrow" => Shape::Arrow { from: point, to: point },
+                    _ => Shape::Pen(vec![point]),
+                };
+                *draft = Some(Annotation { shape, color });
+                false
+            }
+            DrawPhase::Move => {
+                match draft.as_mut().map(|a| &mut a.shape) {
+                    Some(Shape::Pen(points)) if points.last().is_some_and(|&last| distance(last, point) >= MIN_STEP) => points.push(point),
+                    Some(Shape::Arrow { to, .. }) => *to = point,
+                    _ => {}
+                }
+                false
+            }
+            DrawPhase::End => {
+                let Some(done) = draft.take() else { return false; };
+                let keep = match &done.shape {
+                    Shape::Pen(points) => points.len() > 1,
+                    Shape::Arrow { from, to } => distance(*from, *to) >= MIN_STEP,
+                    Shape::Text { .. } => true,
+                };
+                if keep {
+                    items.push(done);
+                }
+                keep
+            }
+        });
+    }
+
+    /// Podgląd z notatkami pliku jako `<nazwa>_notes.png` w folderze eksportu
+    fn export_annotated(&self) {
+        let Some(ui) = self.ui.upgrade() else { return; };
+        let (Some(path), Some(notes)) = (lock_or_recover(&self.current_file_path).clone(), ui_handlers::annotations_file(&self.context)) else {
+            ui.set_status_text("Error: No file loaded".into());
+            return;
+        };
+        if !annotations::sidecar_path(&notes).exists() {
+            ui.set_status_text("No annotations to export".into());
+            return;
+        }
+        let Some(source) = lock_or_recover(&self.image_cache).as_ref().map(ImageCache::detached) else { return; };
+        let layer = source.current_layer_name.clone();
+        // Migawka widoku z chwili zlecenia – późniejsze zmiany podglądu nie trafiają do eksportu
+        let settings = self.context.render_settings(ui.get_exposure_value(), ui.get_gamma_value());
+        let Some(output_dir) = file_operations::export_folder_dialog(&export_handlers::default_output_dir(&path)) else { return; };
+        // Notatki mają współrzędne w orientacji widoku, więc obrót/odbicie zostają niezależnie od opcji
+        let config = UiExportConfig::from_ui(&ui);
+        let name = notes.file_stem().map(|s| s.to_string_lossy().into_owned()).unwrap_or_default();
+        let Some(target) = export_handlers::claim_target(&output_dir, &format!("{}_notes", name), "png", config.collision) else {
+            ui.set_status_text(format!("Skipped: {}_notes.png already exists", name).into());
+            return;
+        };
+        info!(target: "io", "exporting annotated {} → {}", path.display(), target.display());
+        let spec = ExportSpec::Annotated { source: path, notes, layer, settings, target, collision: config.collision };
+        export_queue::enqueue(&ui, spec, Some(source));
+    }
+
+    /// Przepięcie kanałów (np. AO do alfy) do nowego pliku EXR w kolejce eksportu
+    fn export_remapped_exr(&self, mapping: &str, sample: SampleKind, compression: LayerCompression) {
+        let Some(ui) = self.ui.upgrade() else { return; };
+        let Some(path) = lock_or_recover(&self.current_file_path).clone() else {
+            ui.set_status_text("Error: No file loaded".into());
+            return;
+        };
+        let mapping = match layer_export::parse_mapping(mapping) {
+            Ok(mapping) => mapping,
+            Err(e) => {
+                ui.set_status_text(format!("Channel mapping: {}", e).into());
+                return;
+            }
+        };
+        let Some(output_dir) = file_operations::export_folder_dialog(&export_handlers::default_output_dir(&path)) else { return; };
+        let config = UiExportConfig::from_ui(&ui);
+        let name = path.file_stem().map(|s| s.to_string_lossy().into_owned()).unwrap_or_default();
+        let fields = NameFields { name: &name, layer: "remap", channel: "", tonemap: "raw" };
+        let stem = export_handlers::fill_template(&config.template, &fields);
+        let target = export_handlers::claim_target(&output_dir, &stem, "exr", config.collision);
+        let Some(target) = target.filter(|t| *t != path) else {
+            ui.set_status_text(format!("Skipped: {}.exr already exists", stem).into());
+            return;
+        };
+
+        info!(target: "io", "exporting {} → {} ({} channels, {}, {})", path.display(), target.display(), mapping.len(), sample.label(), compression.label());
+        export_queue::enqueue(&ui, ExportSpec::Remap { source: path, mapping, sample, compression, target, collision: config.collision }, None);
+    }
+
+    fn open_cleanup(&self) {
+        let Some(ui) = self.ui.upgrade() else { return; };
+        let Some(path) = lock_or_recover(&self.current_file_path).clone() else {
+            ui.set_status_text("Error: No file loaded".into());
+            return;
+        };
+        let counts = match layer_cleanup::layer_channel_counts(&path) {
+            Ok(counts) => counts,
+            Err(e) => {
+                ui.set_status_text(format!("Error reading {}: {}", path.display(), e).into());
+                return;
+            }
+        };
+        let layers: Vec<CleanupLayer> = counts.into_iter()
+            .map(|(name, channels)| CleanupLayer {
+                rename: layer_cleanup::suggested_name(&name).unwrap_or_else(|| name.clone()).into(),
+                name: name.into(),
+                channels: channels as i32,
+                keep: true,
+            })
+            .collect();
+        ui.set_cleanup_file_name(path.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default().into());
+        ui.set_cleanup_layers(ModelRc::new(VecModel::from(layers)));
+        ui.set_internal_cleanup_visible(true);
+    }
+
+    fn write_cleanup(&self) {
+        let Some(ui) = self.ui.upgrade() else { return; };
+        let Some(path) = lock_or_recover(&self.current_file_path).clone() else { return; };
+        let plan: CleanupPlan = ui.get_cleanup_layers().iter()
+            .filter_map(|layer| {
+                let action = if !layer.keep {
+                    LayerAction::Drop
+                } else if layer.rename.trim() != layer.name.as_str() {
+                    LayerAction::Rename(layer.rename.trim().to_string())
+                } else {
+                    return None;
+                };
+                Some((layer.name.to_string(), action))
+            })
+            .collect();
+        if plan.is_empty() {
+            ui.set_status_text("Clean up: nothing to change".into());
+            return;
+        }
+        let Some(target) = file_operations::save_exr_copy_dialog(&path) else { return; };
+        if target == path {
+            ui.set_status_text("Clean up: choose a different file than the source".into());
+            return;
+        }
+        if !export_handlers::hold_target(&target) {
+            ui.set_status_text(format!("Clean up: {} is the target of a queued export", target.display()).into());
+            return;
+        }
+        info!(target: "io", "clean copy of {} → {} ({} layer change(s))", path.display(), target.display(), plan.len());
+        export_queue::enqueue(&ui, ExportSpec::Cleanup { source: path, plan, target }, None);
+    }
+
+    /// Wideo podglądowe sekwencji z osi czasu: każda klatka renderowana jak eksport obrazu (bieżąca
+    /// warstwa i ustawienia widoku), braki wg trybu osi czasu
+    fn export_video(&self, options: VideoOptions) {
+        info!(target: "io", "video export: {:?}, {:?}, {} fps", options.size, options.quality, options.fps);
+        self.export_sequence(
+            "mp4",
+            |frames, layer, settings, target, collision| ExportSpec::Video { frames, layer, settings, target, collision, options },
+        );
+    }
+
+    /// Animowany GIF/WebP sekwencji – klatki jak przy wideo, co N-ta i zmniejszone
+    fn export_animation(&self, options: AnimatedOptions) {
+        info!(target: "io", "animation export: {}, max {} px, every {} frame(s), {} fps", options.format.label(), options.max_side, options.every, options.fps);
+        self.export_sequence(
+            options.format.extension(),
+            |frames, layer, settings, target, collision| ExportSpec::Animation { frames, layer, settings, target, collision, options },
+        );
+    }
+
+    /// Wspólna część eksportu sekwencji: lista klatek osi czasu, widok, folder i nazwa pliku wg
+    /// szablonu (`{name}` to nazwa sekwencji bez numeru)
+    fn export_sequence(&self, extension: &str, spec: impl FnOnce(Vec<Option<PathBuf>>, String, RenderSettings, PathBuf, Collision) -> ExportSpec) {
+        let Some(ui) = self.ui.upgrade() else { return; };
+        let Some(path) = lock_or_recover(&self.current_file_path).clone() else {
+            ui.set_status_text("Error: No file loaded".into());
+            return;
+        };
+        let Some((sequence, frames)) = timeline::playlist() else {
+            ui.set_status_text("Sequence export needs an image sequence".into());
+            return;
+        };
+        let Some(layer_name) = lock_or_recover(&self.image_cache).as_ref().map(|c| c.current_layer_name.clone()) else { return; };
+        // Migawka widoku z chwili zlecenia – późniejsze zmiany podglądu nie trafiają do eksportu
+        let settings = self.context.render_settings(ui.get_exposure_value(), ui.get_gamma_value());
+        let Some(output_dir) = file_operations::export_folder_dialog(&export_handlers::default_output_dir(&path)) else { return; };
+        let config = UiExportConfig::from_ui(&ui);
+        let settings = config.render_settings(settings);
+        let name = sequence.prefix.trim_end_matches(['.', '_', '-']);
+        let fields = NameFields { name, layer: &layer_name, channel: "", tonemap: OutputTransform::Look.tag() };
+        let stem = export_handlers::fill_template(&config.template, &fields);
+        let Some(target) = export_handlers::claim_target(&output_dir, &stem, extension, config.collision) else {
+            ui.set_status_text(format!("Skipped: {}.{} already exists", stem, extension).into());
+            return;
+        };
+
+        info!(target: "io", "exporting {} → {} ({} frames)", sequence.label(), target.display(), frames.len());
+        export_queue::enqueue(&ui, spec(frames, layer_name, settings, target, config.collision), None);
+    }
+
+    fn save_console_log(&self) {
+        let Some(ui) = self.ui.upgrade() else { return; };
+        let Some(path) = file_operations::save_log_dialog() else { return; };
+        match console::save_to_file(&self.console_model, &path) {
+            Ok(count) => ui.set_status_text(format!("Console log saved: {} lines → {}", count, path.display()).into()),
+            Err(e) => {
+                ui.set_status_text(format!("Error saving console log: {}", e).into());
+                error!(target: "io", "saving console log: {}", e);
+            }
+        }
+    }
+}
+
+/// Ustawia zaznaczenie miniatur wg ścieżki (zmienia tylko wiersze, które tego wymagają)
+fn select_thumbnails(ui: &AppWindow, selected: impl Fn(&str) -> bool) {
+    let thumbnails = ui.get_thumbnails();
+    for row in 0..thumbnails.row_count() {
+        let Some(mut thumb) = thumbnails.row_data(row) else { continue };
+        let select = selected(thumb.path.as_str());
+        if thumb.selected != select {
+            thumb.selected = select;
+            thumbnails.set_row_data(row, thumb);
+        }
+    }
+}
@@ -0,0 +1,157 @@
+// Animowany podgląd sekwencji (GIF albo WebP) do wklejenia w komunikatorze: klatki jak przy
+// eksporcie wideo (bieżący widok, braki wg osi czasu), ale co N-ta, zmniejszone do limitu dłuższego
+// boku i z limitem liczby klatek – dłuższa sekwencja dostaje większy krok zamiast ogromnego pliku.
+// Czas klatki rośnie z krokiem, więc animacja trwa tyle, co sekwencja.
+
+use std::fs::{self, File};
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+use image::codecs::gif::{GifEncoder, Repeat};
+use image::{Delay, Frame, RgbaImage};
+use tracing::info;
+use crate::cancel::CancelToken;
+use crate::render_settings::RenderSettings;
+use crate::utils::error_handling::{ExrError, ExrResult};
+use crate::video_export::{self, Rendered};
+
+/// Najwięcej klatek animacji; krok jest zwiększany, aż sekwencja się zmieści
+pub const MAX_FRAMES: usize = 150;
+/// Jakość stratnego WebP (0-100)
+const WEBP_QUALITY: f32 = 80.0;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AnimatedFormat {
+    Gif,
+    Webp,
+}
+
+impl AnimatedFormat {
+    pub fn from_label(label: &str) -> Self {
+        if label.starts_with("WebP") { AnimatedFormat::Webp } else { AnimatedFormat::Gif }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            AnimatedFormat::Gif => "GIF",
+            AnimatedFormat::Webp => "WebP",
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            AnimatedFormat::Gif => "gif",
+            AnimatedFormat::Webp => "webp",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct AnimatedOptions {
+    pub format: AnimatedFormat,
+    /// Limit dłuższego boku w pikselach (bez powiększania)
+    pub max_side: u32,
+    /// Co która klatka sekwencji (1 = wszystkie)
+    pub every: u32,
+    /// Tempo sekwencji; czas klatki animacji to `every / fps`
+    pub fps: u32,
+}
+
+/// Co `every`-ta klatka, z krokiem zwiększonym do limitu `MAX_FRAMES`; zwraca klatki i użyty krok
+pub fn decimate(frames: &[Option<PathBuf>], every: u32) -> (Vec<Option<PathBuf>>, u32) {
+    let every = (every.max(1) as usize).max(frames.len().div_ceil(MAX_FRAMES));
+    (frames.iter().step_by(every).cloned().collect(), every as u32)
+}
+
+fn encode_error(target: &Path, e: impl std::fmt::Display) -> ExrError {
+    ExrError::Io(std::io::Error::other(format!("{}: {}", target.display(), e)))
+}
+
+/// Klatka zmniejszona do limitu dłuższego boku
+fn fit(frame: Rendered, max_side: u32) -> RgbaImage {
+    let image = match frame {
+        Rendered::Image(image) => RgbaImage::from_raw(image.width, image.height, image.pixels.clone()).expect("RGBA8 buffer"),
+        Rendered::Black { width, height } => RgbaImage::from_pixel(width, height, image::Rgba([0, 0, 0, 255])),
+    };
+    let scale = (max_side as f32 / image.width().max(image.height()) as f32).min(1.0);
+    if scale >= 1.0 {
+        return image;
+    }
+    let (width, height) = (((image.width() as f32 * scale).round() as u32).max(1), ((image.height() as f32 * scale).round() as u32).max(1));
+    image::imageops::resize(&image, width, height, image::imageops::FilterType::Triangle)
+}
+
+/// Koduje animację do `target` (zapętloną); niedokończony plik jest usuwany. Zwraca liczbę
+/// wyrenderowanych pikseli.
+pub fn export_animation(
+    frames: &[Option<PathBuf>],
+    layer: &str,
+    settings: &RenderSettings,
+    target: &Path,
+    options: AnimatedOptions,
+    cancel: &CancelToken,
+    report: &(dyn Fn(f32, &str) + Sync),
+) -> ExrResult<u64> {
+    let (frames, every) = decimate(frames, options.every);
+    let delay_ms = 1000 * every / options.fps.max(1);
+    let render = |sink: &mut dyn FnMut(Rendered) -> ExrResult<()>| video_export::render_frames(&frames, layer, settings, cancel, report, sink);
+    let result = match options.format {
+        AnimatedFormat::Gif => encode_gif(target, options.max_side, delay_ms, &render),
+        AnimatedFormat::Webp => encode_webp(target, options.max_side, delay_ms, &render),
+    };
+    if result.is_err() {
+        let _ = fs::remove_file(target);
+    }
+    let pixels = result?;
+    let size = fs::metadata(target).map_or(0, |m| m.len());
+    info!(target: "io", "animation written: {} ({} frames, every {}, {} ms/frame, {:.1} MB)", target.display(), frames.len(), every, delay_ms, size as f64 / 1e6);
+    Ok(pixels)
+}
+
+/// Renderuje klatki, przekazując każdą do odbiorcy (zob. `video_export::render_frames`)
+type Render<'a> = &'a dyn Fn(&mut dyn FnMut(Rendered) -> ExrResult<()>) -> ExrResult<u64>;
+
+fn encode_gif(target: &Path, max_side: u32, delay_ms: u32, render: Render) -> ExrResult<u64> {
+    // Kwantyzacja palety co klatkę; szybkość 10 z 30 to rozsądny kompromis jakości i czasu
+    let mut encoder = GifEncoder::new_with_speed(BufWriter::new(File::create(target)?), 10);
+    encoder.set_repeat(Repeat::Infinite).map_err(|e| encode_error(target, e))?;
+    let delay = Delay::from_numer_denom_ms(delay_ms, 1);
+    render(&mut |frame| {
+        encoder.encode_frame(Frame::from_parts(fit(frame, max_side), 0, 0, delay)).map_err(|e| encode_error(target, e))
+    })
+}
+
+fn encode_webp(target: &Path, max_side: u32, delay_ms: u32, render: Render) -> ExrResult<u64> {
+    // Koder animacji WebP potrzebuje wszystkich klatek naraz (są już zmniejszone)
+    let mut images = Vec::new();
+    let pixels = render(&mut |frame| {
+        images.push(fit(frame, max_side));
+        Ok(())
+    })?;
+    let mut config = webp::WebPConfig::new().map_err(|_| encode_error(target, "cannot initialize the WebP encoder"))?;
+    config.quality = WEBP_QUALITY;
+    let (width, height) = images[0].dimensions();
+    let mut encoder = webp::AnimEncoder::new(width, height, &config);
+    encoder.set_loop_count(0);
+    for (i, image) in images.iter().enumerate() {
+        encoder.add_frame(webp::AnimFrame::from_rgba(image.as_raw(), width, height, (i as u32 * delay_ms) as i32));
+    }
+    let encoded = encoder.try_encode().map_err(|e| encode_error(target, format!("{:?}", e)))?;
+    fs::write(target, &*encoded)?;
+    Ok(pixels)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decimation_respects_frame_limit() {
+        let frames: Vec<Option<PathBuf>> = (0..400).map(|i| Some(PathBuf::from(format!("f.{:04}.exr", i)))).collect();
+        let (short, every) = decimate(&frames[..10], 2);
+        assert_eq!((short.len(), every), (5, 2));
+        assert_eq!(short[1], frames[2]);
+        let (long, every) = decimate(&frames, 1);
+        assert_eq!(every, 3);
+        assert!(long.len() <= MAX_FRAMES);
+    }
+}
@@ -0,0 +1,262 @@
+// Notatki rysowane na podglądzie (pióro, strzałka, tekst) do przeglądów i dailies. Zapisywane
+// w pliku obok obrazu (`<plik>.annotations.json`), wyświetlane w nakładce nad obrazem – dane EXR
+// nie są nigdy zmieniane. Współrzędne 0..1 w orientacji widoku (po obrocie/odbiciu), tak jak
+// zaznaczenie próbnika; eksport "spłaszczony" rysuje je na renderze w pełnej rozdzielczości.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use serde_json::{json, Value};
+use tracing::warn;
+use crate::image_cache::draw_line;
+use crate::raw_image::RawImage;
+use crate::utils::channel_config::parse_hex_color;
+
+/// Etap rysowania notatki myszą
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DrawPhase {
+    Start,
+    Move,
+    End,
+}
+
+impl DrawPhase {
+    /// 0 = wciśnięcie, 1 = ruch, 2 = puszczenie (callback `annotation-draw`)
+    pub fn from_index(index: i32) -> Self {
+        match index {
+            0 => DrawPhase::Start,
+            1 => DrawPhase::Move,
+            _ => DrawPhase::End,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Shape {
+    Pen(Vec<[f32; 2]>),
+    Arrow { from: [f32; 2], to: [f32; 2] },
+    /// Lewy górny róg i treść
+    Text { at: [f32; 2], text: String },
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Annotation {
+    pub shape: Shape,
+    pub color: [u8; 3],
+}
+
+/// Kolory do wyboru w panelu
+pub const COLORS: [(&str, [u8; 3]); 4] = [("Red", [255, 59, 48]), ("Yellow", [255, 214, 10]), ("Green", [52, 199, 89]), ("White", [255, 255, 255])];
+
+pub fn color_from_label(label: &str) -> [u8; 3] {
+    COLORS.iter().find(|(name, _)| *name == label).map_or(COLORS[0].1, |(_, rgb)| *rgb)
+}
+
+/// Wysokość tekstu jako ułamek wysokości obrazu (nakładka i eksport)
+pub const TEXT_HEIGHT: f32 = 0.03;
+/// Długość grotu strzałki jako ułamek wysokości obrazu
+const ARROW_HEAD: f32 = 0.03;
+
+pub fn sidecar_path(file: &Path) -> PathBuf {
+    let mut name = file.file_name().unwrap_or_default().to_os_string();
+    name.push(".annotations.json");
+    file.with_file_name(name)
+}
+
+/// Notatki pliku; brak albo nieczytelny plik obok = brak notatek
+pub fn load(file: &Path) -> Vec<Annotation> {
+    let path = sidecar_path(file);
+    let Ok(text) = fs::read_to_string(&path) else { return Vec::new(); };
+    let Ok(Value::Array(items)) = serde_json::from_str::<Value>(&text) else {
+        warn!(target: "io", "ignoring unreadable annotations {}", path.display());
+        return Vec::new();
+    };
+    items.iter().filter_map(from_json).collect()
+}
+
+/// Zapis atomowy; brak notatek usuwa plik obok obrazu
+pub fn save(file: &Path, annotations: &[Annotation]) -> io::Result<()> {
+    let path = sidecar_path(file);
+    if annotations.is_empty() {
+        return match fs::remove_file(&path) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+            _ => Ok(()),
+        };
+    }
+    let value = Value::Array(annotations.iter().map(to_json).collect());
+    let tmp = path.with_extension("tmp");
+    fs::write(&tmp, serde_json::to_string_pretty(&value).unwrap_or_default())?;
+    fs::rename(&tmp, &path)
+}
+
+fn to_json(annotation: &Annotation) -> Value {
+    let [r, g, b] = annotation.color;
+    let color = format!("#{:02x}{:02x}{:02x}", r, g, b);
+    match &annotation.shape {
+        Shape::Pen(points) => json!({ "kind": "pen", "color": color, "points": points }),
+        Shape::Arrow { from, to } => json!({ "kind": "arrow", "color": color, "from": from, "to": to }),
+        Shape::Text { at, text } => json!({ "kind": "text", "color": color, "at": at, "text": text }),
+    }
+}
+
+fn from_json(value: &Value) -> Option<Annotation> {
+    let point = |v: &Value| -> Option<[f32; 2]> {
+        let pair = v.as_array()?;
+        Some([pair.first()?.as_f64()? as f32, pair.get(1)?.as_f64()? as f32])
+    };
+    let shape = match value.get("kind")?.as_str()? {
+        "pen" => Shape::Pen(value.get("points")?.as_array()?.iter().filter_map(point).collect()),
+        "arrow" => Shape::Arrow { from: point(value.get("from")?)?, to: point(value.get("to")?)? },
+        "text" => Shape::Text { at: point(value.get("at")?)?, text: value.get("text")?.as_str()?.to_string() },
+        _ => return None,
+    };
+    let (r, g, b) = value.get("color").and_then(Value::as_str).and_then(parse_hex_color).unwrap_or((255, 59, 48));
+    Some(Annotation { shape, color: [r, g, b] })
+}
+
+/// Końce dwóch odcinków grotu; `aspect` = szerokość / wysokość obrazu (grot bez zniekształceń)
+fn arrow_head(from: [f32; 2], to: [f32; 2], aspect: f32) -> [[f32; 2]; 2] {
+    let (dx, dy) = ((from[0] - to[0]) * aspect, from[1] - to[1]);
+    let length = (dx * dx + dy * dy).sqrt().max(1e-6);
+    let head = ARROW_HEAD.min(length * 0.4);
+    [0.5f32, -0.5].map(|angle| {
+        let (sin, cos) = angle.sin_cos();
+        let (x, y) = ((dx * cos - dy * sin) / length * head, (dx * sin + dy * cos) / length * head);
+        [to[0] + x / aspect, to[1] + y]
+    })
+}
+
+impl Annotation {
+    /// Polecenia ścieżki SVG w układzie 0..1 dla nakładki; pusty napis dla tekstu
+    pub fn path_commands(&self, aspect: f32) -> String {
+        let point = |p: [f32; 2]| format!("{:.4} {:.4}", p[0], p[1]);
+        match &self.shape {
+            Shape::Pen(points) => points.iter().enumerate()
+                .map(|(i, &p)| format!("{} {}", if i == 0 { "M" } else { "L" }, point(p)))
+                .collect::<Vec<_>>()
+                .join(" "),
+            Shape::Arrow { from, to } => {
+                let [left, right] = arrow_head(*from, *to, aspect);
+                format!("M {} L {} M {} L {} L {}", point(*from), point(*to), point(left), point(*to), point(right))
+            }
+            Shape::Text { .. } => String::new(),
+        }
+    }
+}
+
+/// Rysuje notatki na obrazie eksportu (grubość linii i wielkość tekstu względem rozmiaru obrazu)
+pub fn flatten(image: &mut RawImage, annotations: &[Annotation]) {
+    let (w, h) = (image.width as f32, image.height as f32);
+    if w == 0.0 || h == 0.0 {
+        return;
+    }
+    let thickness = (w.max(h) / 600.0).round().max(2.0) as i64;
+    let to_px = |p: [f32; 2]| (p[0] * w, p[1] * h);
+    for annotation in annotations {
+        let mut plot = |x: i64, y: i64| fill_rect(image, x - thickness / 2, y - thickness / 2, thickness, thickness, annotation.color);
+        let mut segment = |a: [f32; 2], b: [f32; 2]| {
+            let ((x0, y0), (x1, y1)) = (to_px(a), to_px(b));
+            draw_line(&mut plot, x0, y0, x1, y1);
+        };
+        match &annotation.shape {
+            Shape::Pen(points) => points.windows(2).for_each(|pair| segment(pair[0], pair[1])),
+            Shape::Arrow { from, to } => {
+                segment(*from, *to);
+                for end in arrow_head(*from, *to, w / h) {
+                    segment(end, *to);
+                }
+            }
+            Shape::Text { at, text } => {
+                let scale = (TEXT_HEIGHT * h / 7.0).round().max(1.0) as i64;
+                let (x, y) = to_px(*at);
+                let shadow = (scale / 3).max(1);
+                draw_text(image, x as i64 + shadow, y as i64 + shadow, scale, text, [0, 0, 0]);
+                draw_text(image, x as i64, y as i64, scale, text, annotation.color);
+            }
+        }
+    }
+}
+
+fn fill_rect(image: &mut RawImage, x: i64, y: i64, width: i64, height: i64, color: [u8; 3]) {
+    let (w, h) = (image.width as i64, image.height as i64);
+    for py in y.max(0)..(y + height).min(h) {
+        for px in x.max(0)..(x + width).min(w) {
+            let i = (py * w + px) as usize * 4;
+            image.pixels[i..i + 4].copy_from_slice(&[color[0], color[1], color[2], 255]);
+        }
+    }
+}
+
+/// Tekst czcionką bitmapową 5×7 (wielkie litery, cyfry, interpunkcja); piksel czcionki = `scale` px
+fn draw_text(image: &mut RawImage, x: i64, y: i64, scale: i64, text: &str, color: [u8; 3]) {
+    for (i, ch) in text.chars().enumerate() {
+        let ch = ch.to_ascii_uppercase();
+        let rows = FONT.iter().find(|(c, _)| *c == ch).or_else(|| FONT.iter().find(|(c, _)| *c == '?')).map_or([0; 7], |(_, rows)| *rows);
+        let left = x + i as i64 * 6 * scale;
+        for (row, bits) in rows.iter().enumerate() {
+            for col in 0..5 {
+                if bits & (0x10 >> col) != 0 {
+                    fill_rect(image, left + col * scale, y + row as i64 * scale, scale, scale, color);
+                }
+            }
+        }
+    }
+}
+
+const FONT: &[(char, [u8; 7])] = &[
+    ('A', [0x0E, 0x11, 0x11, 0x1F, 0x11, 0x11, 0x11]), ('B', [0x1E, 0x11, 0x11, 0x1E, 0x11, 0x11, 0x1E]),
+    ('C', [0x0E, 0x11, 0x10, 0x10, 0x10, 0x11, 0x0E]), ('D', [0x1E, 0x11, 0x11, 0x11, 0x11, 0x11, 0x1E]),
+    ('E', [0x1F, 0x10, 0x10, 0x1E, 0x10, 0x10, 0x1F]), ('F', [0x1F, 0x10, 0x10, 0x1E, 0x10, 0x10, 0x10]),
+    ('G', [0x0E, 0x11, 0x10, 0x17, 0x11, 0x11, 0x0F]), ('H', [0x11, 0x11, 0x11, 0x1F, 0x11, 0x11, 0x11]),
+    ('I', [0x0E, 0x04, 0x04, 0x04, 0x04, 0x04, 0x0E]), ('J', [0x07, 0x02, 0x02, 0x02, 0x02, 0x12, 0x0C]),
+    ('K', [0x11, 0x12, 0x14, 0x18, 0x14, 0x12, 0x11]), ('L', [0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x1F]),
+    ('M', [0x11, 0x1B, 0x15, 0x15, 0x11, 0x11, 0x11]), ('N', [0x11, 0x11, 0x19, 0x15, 0x13, 0x11, 0x11]),
+    ('O', [0x0E, 0x11, 0x11, 0x11, 0x11, 0x11, 0x0E]), ('P', [0x1E, 0x11, 0x11, 0x1E, 0x10, 0x10, 0x10]),
+    ('Q', [0x0E, 0x11, 0x11, 0x11, 0x15, 0x12, 0x0D]), ('R', [0x1E, 0x11, 0x11, 0x1E, 0x14, 0x12, 0x11]),
+    ('S', [0x0F, 0x10, 0x10, 0x0E, 0x01, 0x01, 0x1E]), ('T', [0x1F, 0x04, 0x04, 0x04, 0x04, 0x04, 0x04]),
+    ('U', [0x11, 0x11, 0x11, 0x11, 0x11, 0x11, 0x0E]), ('V', [0x11, 0x11, 0x11, 0x11, 0x11, 0x0A, 0x04]),
+    ('W', [0x11, 0x11, 0x11, 0x15, 0x15, 0x15, 0x0A]), ('X', [0x11, 0x11, 0x0A, 0x04, 0x0A, 0x11, 0x11]),
+    ('Y', [0x11, 0x11, 0x0A, 0x04, 0x04, 0x04, 0x04]), ('Z', [0x1F, 0x01, 0x02, 0x04, 0x08, 0x10, 0x1F]),
+    ('0', [0x0E, 0x11, 0x13, 0x15, 0x19, 0x11, 0x0E]), ('1', [0x04, 0x0C, 0x04, 0x04, 0x04, 0x04, 0x0E]),
+    ('2', [0x0E, 0x11, 0x01, 0x02, 0x04, 0x08, 0x1F]), ('3', [0x1F, 0x02, 0x04, 0x02, 0x01, 0x11, 0x0E]),
+    ('4', [0x02, 0x06, 0x0A, 0x12, 0x1F, 0x02, 0x02]), ('5', [0x1F, 0x10, 0x1E, 0x01, 0x01, 0x11, 0x0E]),
+    ('6', [0x06, 0x08, 0x10, 0x1E, 0x11, 0x11, 0x0E]), ('7', [0x1F, 0x01, 0x02, 0x04, 0x08, 0x08, 0x08]),
+    ('8', [0x0E, 0x11, 0x11, 0x0E, 0x11, 0x11, 0x0E]), ('9', [0x0E, 0x11, 0x11, 0x0F, 0x01, 0x02, 0x0C]),
+    (' ', [0x00; 7]), ('.', [0x00, 0x00, 0x00, 0x00, 0x00, 0x0C, 0x0C]),
+    (',', [0x00, 0x00, 0x00, 0x00, 0x0C, 0x04, 0x08]), (':', [0x00, 0x0C, 0x0C, 0x00, 0x0C, 0x0C, 0x00]),
+    ('!', [0x04, 0x04, 0x04, 0x04, 0x04, 0x00, 0x04]), ('?', [0x0E, 0x11, 0x01, 0x02, 0x04, 0x00, 0x04]),
+    ('-', [0x00, 0x00, 0x00, 0x1F, 0x00, 0x00, 0x00]), ('+', [0x00, 0x04, 0x04, 0x1F, 0x04, 0x04, 0x00]),
+    ('/', [0x00, 0x01, 0x02, 0x04, 0x08, 0x10, 0x00]), ('(', [0x02, 0x04, 0x08, 0x08, 0x08, 0x04, 0x02]),
+    (')', [0x08, 0x04, 0x02, 0x02, 0x02, 0x04, 0x08]), ('\'', [0x04, 0x04, 0x08, 0x00, 0x00, 0x00, 0x00]),
+    ('"', [0x0A, 0x0A, 0x00, 0x00, 0x00, 0x00, 0x00]), ('#', [0x0A, 0x0A, 0x1F, 0x0A, 0x1F, 0x0A, 0x0A]),
+    ('%', [0x18, 0x19, 0x02, 0x04, 0x08, 0x13, 0x03]), ('=', [0x00, 0x00, 0x1F, 0x00, 0x1F, 0x00, 0x00]),
+    ('_', [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x1F]), ('<', [0x02, 0x04, 0x08, 0x10, 0x08, 0x04, 0x02]),
+    ('>', [0x08, 0x04, 0x02, 0x01, 0x02, 0x04, 0x08]), ('*', [0x00, 0x04, 0x15, 0x0E, 0x15, 0x04, 0x00]),
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn annotations_round_trip_and_flatten() {
+        let notes = vec![
+            Annotation { shape: Shape::Pen(vec![[0.1, 0.1], [0.5, 0.5]]), color: [255, 59, 48] },
+            Annotation { shape: Shape::Arrow { from: [0.9, 0.1], to: [0.6, 0.4] }, color: [255, 214, 10] },
+            Annotation { shape: Shape::Text { at: [0.1, 0.8], text: "Fix edge".to_string() }, color: [255, 255, 255] },
+        ];
+        let parsed: Vec<Annotation> = notes.iter().map(to_json).filter_map(|v| from_json(&v)).collect();
+        assert_eq!(parsed, notes);
+        assert_eq!(sidecar_path(Path::new("/shots/a.0001.exr")), Path::new("/shots/a.0001.exr.annotations.json"));
+        assert_eq!(notes[0].path_commands(1.0), "M 0.1000 0.1000 L 0.5000 0.5000");
+        assert!(notes[1].path_commands(2.0).starts_with("M 0.9000 0.1000 L 0.6000 0.4000 M "));
+
+        let (width, height) = (200u32, 100u32);
+        let mut image = RawImage { width, height, pixels: vec![0; (width * height * 4) as usize] };
+        flatten(&mut image, &notes);
+        let at = |x: u32, y: u32| &image.pixels[((y * width + x) * 4) as usize..][..4];
+        assert_eq!(at(60, 30), [255, 59, 48, 255]);
+        assert_eq!(at(150, 90), [0, 0, 0, 0]);
+        assert!(image.pixels.chunks_exact(4).any(|p| p == [255, 255, 255, 255]));
+    }
+}
@@ -0,0 +1,209 @@
+// Kontekst aplikacji: ustawienia widoku, z których powstaje podgląd (graf przetwarzania, tryby
+// diagnostyczne, filtry, przestrzeń wejściowa wykryta z pliku i wybrana ręcznie, zaznaczenie), porównanie
+// z referencją, profil monitora, stan bieżącego pliku (wczytywanie, notatki, galeria migawek)
+// i miniaturek folderu. Tworzony raz w main.rs i przekazywany (Arc) do `Dispatcher` i handlerów UI
+// zamiast zmiennych globalnych – testy budują własne, niezależne konteksty.
+// Wątki robocze (eksport, kolejka) dostają z niego migawkę `RenderSettings`, nie sam kontekst.
+
+use std::path::PathBuf;
+use std::sync::mpsc::Sender;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Instant;
+use slint::Image;
+use crate::annotations::Annotation;
+use crate::cancel::CancelToken;
+use crate::color_processing::Mat3;
+use crate::compare::CompareState;
+use crate::display_profile::{self, DisplayProfile};
+use crate::image_processing::{InputColorSpace, ProcessingGraph};
+use crate::raw_image::RawImage;
+use crate::render_settings::RenderSettings;
+use crate::snapshot_gallery::Snapshot;
+
+/// Ustawienia widoku zmieniane z paneli
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ViewSettings {
+    /// Ustawienia renderu; ekspozycję, gammę i przestrzeń wejściową grafu uzupełnia `render_settings`
+    pub render: RenderSettings,
+    /// Przestrzeń wykryta z nagłówka bieżącego pliku (tryb "Auto")
+    pub detected_input: InputColorSpace,
+    /// Ręczny wybór przestrzeni wejściowej; None = wykryta z pliku
+    pub input_override: Option<InputColorSpace>,
+    /// Zaznaczenie dla histogramu i "Auto" (patrz `ui_handlers::set_selection`)
+    pub selection: Option<[f32; 4]>,
+    /// Widok powiększony ponad dopasowanie do okna – podgląd dużych plików w pełnej rozdzielczości
+    pub preview_zoomed: bool,
+}
+
+impl Default for ViewSettings {
+    fn default() -> Self {
+        ViewSettings {
+            render: RenderSettings::standard(0.0, 2.2),
+            detected_input: InputColorSpace::LinearRec709,
+            input_override: None,
+            selection: None,
+            preview_zoomed: false,
+        }
+    }
+}
+
+impl ViewSettings {
+    /// Ustawienia renderu przy ekspozycji i gammie z suwaków
+    pub fn render_settings(&self, exposure: f32, gamma: f32) -> RenderSettings {
+        let mut settings = self.render;
+        settings.graph.exposure = exposure;
+        settings.graph.gamma = gamma;
+        settings.graph.input = self.input_override.unwrap_or(self.detected_input);
+        settings
+    }
+}
+
+/// Wczytywanie pliku w tle (`ui_handlers::open_exr`)
+#[derive(Default)]
+pub struct LoadState {
+    /// Token anulowania bieżącego wczytywania (None gdy nic się nie wczytuje)
+    pub cancel: Option<CancelToken>,
+    /// Oczekujący na wynik bieżącego wczytywania (`ui_handlers::notify_when_loaded`)
+    pub waiters: Vec<Sender<Result<(), String>>>,
+}
+
+/// Notatki wyświetlane w nakładce podglądu: zapisane w pliku obok obrazu i rysowana właśnie
+pub struct AnnotationState {
+    /// Plik oryginału (także gdy wczytano proxy)
+    pub file: Option<PathBuf>,
+    /// Szerokość / wysokość obrazu w widoku – kształt grotów strzałek
+    pub aspect: f32,
+    pub items: Vec<Annotation>,
+    pub draft: Option<Annotation>,
+}
+
+impl Default for AnnotationState {
+    fn default() -> Self {
+        AnnotationState { file: None, aspect: 1.0, items: Vec::new(), draft: None }
+    }
+}
+
+#[derive(Default)]
+pub struct AppContext {
+    view: Mutex<ViewSettings>,
+    compare: Mutex<CompareState>,
+    /// Profil monitora i czy jest włączony (`for_display`)
+    monitor_profile: Mutex<(bool, Option<Arc<DisplayProfile>>)>,
+    load: Mutex<LoadState>,
+    /// Token anulowania bieżącego generowania miniaturek
+    thumbnails_cancel: Mutex<Option<CancelToken>>,
+    annotations: Mutex<AnnotationState>,
+    /// Galeria migawek bieżącego pliku (plik oryginału, migawki)
+    snapshots: Mutex<(Option<PathBuf>, Vec<Snapshot>)>,
+    /// Ostatni wpis logu o odświeżeniu podglądu (throttling wpisów przy przeciąganiu suwaków)
+    last_preview_log: Mutex<Option<Instant>>,
+}
+
+fn lock<T>(m: &Mutex<T>) -> MutexGuard<'_, T> {
+    m.lock().unwrap_or_else(|p| p.into_inner())
+}
+
+impl AppContext {
+    pub fn view(&self) -> ViewSettings {
+        *lock(&self.view)
+    }
+
+    /// Zmienia ustawienia widoku; zwraca je po zmianie
+    pub fn update_view(&self, f: impl FnOnce(&mut ViewSettings)) -> ViewSettings {
+        let mut guard = lock(&self.view);
+        f(&mut guard);
+        *guard
+    }
+
+    /// Bieżące ustawienia renderu podglądu przy ekspozycji i gammie z suwaków
+    pub fn render_settings(&self, exposure: f32, gamma: f32) -> RenderSettings {
+        self.view().render_settings(exposure, gamma)
+    }
+
+    /// Graf bieżących ustawień renderu (panel "Pipeline", histogram, "Auto")
+    pub fn processing_graph(&self, exposure: f32, gamma: f32) -> ProcessingGraph {
+        self.render_settings(exposure, gamma).graph
+    }
+
+    /// Przestrzeń wykryta z nagłówka bieżącego pliku (tryb "Auto"); `matrix` (prymarki pliku →
+    /// Rec.709) tylko dla `FilePrimaries`
+    pub fn set_detected_input(&self, space: InputColorSpace, matrix: Option<Mat3>) {
+        self.update_view(|view| {
+            view.detected_input = space;
+            if let Some(matrix) = matrix {
+                view.render.graph.file_primaries = matrix;
+            }
+        });
+    }
+
+    pub fn compare(&self) -> MutexGuard<'_, CompareState> {
+        lock(&self.compare)
+    }
+
+    pub fn set_monitor_profile(&self, profile: Option<DisplayProfile>) {
+        lock(&self.monitor_profile).1 = profile.map(Arc::new);
+    }
+
+    pub fn set_monitor_profile_enabled(&self, enabled: bool) {
+        lock(&self.monitor_profile).0 = enabled;
+    }
+
+    /// Obraz do pokazania w oknie: z wyostrzaniem podglądu i profilem monitora, jeśli aktywne
+    pub fn for_display(&self, image: RawImage) -> Image {
+        let profile = match &*lock(&self.monitor_profile) {
+            (true, profile) => profile.clone(),
+            (false, _) => None,
+        };
+        display_profile::for_display(image, self.view().render.sharpen, profile.as_deref())
+    }
+
+    pub fn load(&self) -> MutexGuard<'_, LoadState> {
+        lock(&self.load)
+    }
+
+    pub fn thumbnails_cancel(&self) -> MutexGuard<'_, Option<CancelToken>> {
+        lock(&self.thumbnails_cancel)
+    }
+
+    pub fn annotations(&self) -> MutexGuard<'_, AnnotationState> {
+        lock(&self.annotations)
+    }
+
+    pub fn snapshots(&self) -> MutexGuard<'_, (Option<PathBuf>, Vec<Snapshot>)> {
+        lock(&self.snapshots)
+    }
+
+    pub fn last_preview_log(&self) -> MutexGuard<'_, Option<Instant>> {
+        lock(&self.last_preview_log)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::image_processing::{DisplayTransform, Stage};
+
+    #[test]
+    fn contexts_do_not_share_view_settings() {
+        let (first, second) = (Arc::new(AppContext::default()), Arc::new(AppContext::default()));
+        let primaries = [[0.9, 0.1, 0.0], [0.05, 0.9, 0.05], [0.0, 0.2, 0.8]];
+        first.set_detected_input(InputColorSpace::FilePrimaries, Some(primaries));
+        first.update_view(|view| {
+            view.render.transform = DisplayTransform { quarter_turns: 1, flip_h: true, flip_v: false };
+            view.render.false_color = true;
+            view.render.graph.set_enabled(Stage::Tonemap, false);
+        });
+
+        let settings = first.render_settings(1.5, 2.4);
+        assert_eq!((settings.graph.exposure, settings.graph.gamma), (1.5, 2.4));
+        assert_eq!(settings.graph.input, InputColorSpace::FilePrimaries);
+        assert_eq!(settings.graph.input_to(InputColorSpace::LinearRec709), Some(primaries));
+        assert!(settings.false_color && !settings.graph.is_enabled(Stage::Tonemap));
+        assert_eq!(second.render_settings(1.5, 2.4), RenderSettings::standard(1.5, 2.4));
+
+        // Ręczny wybór ma pierwszeństwo przed przestrzenią wykrytą z pliku
+        first.update_view(|view| view.input_override = Some(InputColorSpace::AcesCg));
+        assert_eq!(first.processing_graph(0.0, 2.2).input, InputColorSpace::AcesCg);
+        assert_eq!(second.processing_graph(0.0, 2.2).input, InputColorSpace::LinearRec709);
+    }
+}
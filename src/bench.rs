@@ -0,0 +1,99 @@
+// Benchmarki potoku (criterion) na syntetycznych plikach z `io::synthetic`: wczytanie pliku,
+// przełączenie warstwy, process_to_image w kilku rozdzielczościach i miniatury katalogu.
+// Aplikacja jest samym binarium, więc benchmarki działają jako test (dostęp do modułów crate'a):
+//   cargo test --release --features synthetic bench_pipeline -- --ignored --nocapture
+// Criterion zapisuje wyniki w target/criterion i przy kolejnym przebiegu zgłasza regresje.
+
+use std::path::PathBuf;
+use std::time::Duration;
+use criterion::{BenchmarkId, Criterion, Throughput};
+use crate::cancel::CancelToken;
+use crate::image_cache::ImageCache;
+use crate::io::synthetic::write_synthetic_exr;
+use crate::progress::NoopProgress;
+use crate::render_settings::RenderSettings;
+
+/// Rozdzielczości: podgląd, HD, 4K UHD
+const RESOLUTIONS: [(usize, usize); 3] = [(512, 512), (1920, 1080), (3840, 2160)];
+/// Warstwy AOV w plikach testowych (oprócz beauty)
+const AOV_LAYERS: usize = 3;
+/// Liczba plików w katalogu miniatur
+const THUMBNAIL_FILES: usize = 8;
+
+fn bench_dir() -> PathBuf {
+    std::env::temp_dir().join("exruster-bench")
+}
+
+/// Plik syntetyczny w katalogu benchmarków (tworzony raz, przy kolejnych przebiegach ponownie używany)
+fn synthetic_file(dir: &str, name: &str, width: usize, height: usize) -> PathBuf {
+    let dir = bench_dir().join(dir);
+    std::fs::create_dir_all(&dir).expect("create bench directory");
+    let path = dir.join(name);
+    if !path.exists() {
+        write_synthetic_exr(&path, width, height, AOV_LAYERS).expect("write synthetic EXR");
+    }
+    path
+}
+
+fn criterion() -> Criterion {
+    Criterion::default()
+        .sample_size(10)
+        .warm_up_time(Duration::from_millis(500))
+        .measurement_time(Duration::from_secs(3))
+}
+
+#[test]
+#[ignore = "benchmark: cargo test --release --features synthetic bench_pipeline -- --ignored --nocapture"]
+fn bench_pipeline() {
+    let mut c = criterion();
+    let files: Vec<(String, PathBuf, usize)> = RESOLUTIONS
+        .iter()
+        .map(|&(w, h)| (format!("{}x{}", w, h), synthetic_file("files", &format!("{}x{}.exr", w, h), w, h), w * h))
+        .collect();
+
+    let mut group = c.benchmark_group("load");
+    for (label, path, pixels) in &files {
+        group.throughput(Throughput::Elements(*pixels as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(label), path, |b, path| {
+            b.iter(|| ImageCache::new(path, &CancelToken::new(), &NoopProgress).expect("load"))
+        });
+    }
+    group.finish();
+
+    let mut group = c.benchmark_group("layer_switch");
+    for (label, path, _) in &files {
+        let mut cache = ImageCache::new(path, &CancelToken::new(), &NoopProgress).expect("load");
+        // Naprzemiennie beauty i pierwsza warstwa AOV
+        let layers: Vec<String> = cache.layers_info.iter().take(2).map(|l| l.name.clone()).collect();
+        let mut next = 1;
+        group.bench_function(BenchmarkId::from_parameter(label), |b| {
+            b.iter(|| {
+                cache.load_layer(path, &layers[next]).expect("switch layer");
+                next = 1 - next;
+            })
+        });
+    }
+    group.finish();
+
+    let mut group = c.benchmark_group("process_to_image");
+    let settings = RenderSettings::standard(0.5, 2.2);
+    for (label, path, pixels) in &files {
+        let cache = ImageCache::new(path, &CancelToken::new(), &NoopProgress).expect("load");
+        group.throughput(Throughput::Elements(*pixels as u64));
+        group.bench_function(BenchmarkId::from_parameter(label), |b| b.iter(|| cache.process_to_image(&settings)));
+    }
+    group.finish();
+
+    let thumbs_dir = bench_dir().join("thumbnails");
+    for i in 0..THUMBNAIL_FILES {
+        synthetic_file("thumbnails", &format!("shot_{:02}.exr", i), 1920, 1080);
+    }
+    let mut group = c.benchmark_group("thumbnails");
+    group.throughput(Throughput::Elements(THUMBNAIL_FILES as u64));
+    group.bench_function(BenchmarkId::from_parameter(format!("{}x1920x1080", THUMBNAIL_FILES)), |b| {
+        b.iter(|| crate::thumbnails::generate_thumbnail_works(&thumbs_dir, 150, 0.0, 2.2, &CancelToken::new(), &NoopProgress).expect("thumbnails"))
+    });
+    group.finish();
+
+    c.final_summary();
+}
@@ -0,0 +1,55 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use anyhow::Context;
+
+/// Stan panelu nawigacji po folderach: bieżący folder oraz zapamiętane pozycje przewinięcia miniaturek
+#[derive(Default)]
+pub struct FolderBrowser {
+    pub current_dir: Option<PathBuf>,
+    scroll_positions: HashMap<PathBuf, f32>,
+}
+
+impl FolderBrowser {
+    /// Zapamiętuje pozycję przewinięcia paska miniaturek dla bieżącego folderu
+    pub fn remember_scroll(&mut self, viewport_x: f32) {
+        if let Some(dir) = &self.current_dir {
+            self.scroll_positions.insert(dir.clone(), viewport_x);
+        }
+    }
+
+    /// Zwraca zapamiętaną pozycję przewinięcia dla folderu (0 jeśli folder nie był odwiedzany)
+    pub fn scroll_for(&self, dir: &Path) -> f32 {
+        self.scroll_positions.get(dir).copied().unwrap_or(0.0)
+    }
+}
+
+/// Pojedynczy wpis listy folderów (nazwa wyświetlana + pełna ścieżka)
+pub struct FolderEntry {
+    pub name: String,
+    pub path: PathBuf,
+}
+
+/// Buduje listę wpisów dla panelu folderów: ".." (jeśli istnieje rodzic), a potem podfoldery alfabetycznie.
+/// Ukryte foldery (zaczynające się od '.') są pomijane.
+pub fn list_folder_entries(dir: &Path) -> anyhow::Result<Vec<FolderEntry>> {
+    let entries = fs::read_dir(dir)
+        .with_context(|| format!("Nie można odczytać katalogu: {}", dir.display()))?;
+
+    let mut subdirs: Vec<FolderEntry> = Vec::new();
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if !path.is_dir() { continue; }
+        let name = entry.file_name().to_string_lossy().to_string();
+        if name.starts_with('.') { continue; }
+        subdirs.push(FolderEntry { name, path });
+    }
+    subdirs.sort_by_key(|e| e.name.to_lowercase());
+
+    let mut out = Vec::with_capacity(subdirs.len() + 1);
+    if let Some(parent) = dir.parent() {
+        out.push(FolderEntry { name: "..".to_string(), path: parent.to_path_buf() });
+    }
+    out.extend(subdirs);
+    Ok(out)
+}
@@ -0,0 +1,89 @@
+use std::io::{Read, Seek, SeekFrom};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use crate::utils::error_handling::{ExrError, ExrResult};
+
+/// Współdzielona flaga anulowania długich operacji (np. wczytywania pliku w tle)
+#[derive(Clone, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    pub fn new() -> Self { Self::default() }
+
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+
+    /// Ten sam token (klon), nie tylko ten sam stan flagi
+    pub fn same_as(&self, other: &CancelToken) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+
+    /// Zwraca błąd, jeśli operacja została anulowana (do użycia z `?` między etapami)
+    pub fn check(&self) -> ExrResult<()> {
+        if self.is_cancelled() {
+            return Err(ExrError::Canceled);
+        }
+        Ok(())
+    }
+}
+
+/// Współdzielona flaga wstrzymania długiej pracy (np. zadania eksportu): praca woła `wait` między
+/// etapami i stoi, dopóki flaga jest ustawiona
+#[derive(Clone, Default)]
+pub struct PauseToken(Arc<AtomicBool>);
+
+impl PauseToken {
+    pub fn new() -> Self { Self::default() }
+
+    pub fn set_paused(&self, paused: bool) {
+        self.0.store(paused, Ordering::Relaxed);
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+
+    /// Czeka na wznowienie (anulowanie też kończy czekanie); zwraca czas postoju
+    pub fn wait(&self, cancel: &CancelToken) -> Duration {
+        let start = Instant::now();
+        while self.is_paused() && !cancel.is_cancelled() {
+            std::thread::sleep(Duration::from_millis(50));
+        }
+        start.elapsed()
+    }
+}
+
+/// Reader przerywający odczyt po anulowaniu tokenu.
+/// Dekoder EXR pobiera kolejne bloki z pliku, więc błąd odczytu przerywa dekodowanie
+/// i zwalnia zaalokowane bufory bez czekania na koniec pliku.
+pub struct CancellableReader<R> {
+    inner: R,
+    token: CancelToken,
+}
+
+impl<R> CancellableReader<R> {
+    pub fn new(inner: R, token: CancelToken) -> Self {
+        Self { inner, token }
+    }
+}
+
+impl<R: Read> Read for CancellableReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        if self.token.is_cancelled() {
+            return Err(std::io::Error::other(ExrError::Canceled));
+        }
+        self.inner.read(buf)
+    }
+}
+
+impl<R: Seek> Seek for CancellableReader<R> {
+    fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+        self.inner.seek(pos)
+    }
+}
@@ -0,0 +1,122 @@
+use std::sync::LazyLock;
+use crate::image_processing::{ChannelRemap, VectorView};
+use crate::utils::channel_config::{self, ChannelConfig, ChannelRule, DisplayMode, Normalization};
+
+/// Rodzaj AOV rozpoznany po nazwie warstwy/kanału – decyduje o sposobie wyświetlania podglądu
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AovKind {
+    Color,
+    Depth,
+    Normal,
+    Position,
+    Velocity,
+    Id,
+    Emission,
+    Cryptomatte,
+}
+
+impl AovKind {
+    /// Nazwa sekcji w pliku reguł (np. "normal", "velocity")
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.trim().to_ascii_lowercase().as_str() {
+            "color" => Some(AovKind::Color),
+            "depth" => Some(AovKind::Depth),
+            "normal" => Some(AovKind::Normal),
+            "position" => Some(AovKind::Position),
+            "velocity" | "motion" => Some(AovKind::Velocity),
+            "id" => Some(AovKind::Id),
+            "emission" => Some(AovKind::Emission),
+            "cryptomatte" => Some(AovKind::Cryptomatte),
+            _ => None,
+        }
+    }
+
+    /// Sekcja panelu warstw przy grupowaniu AOV
+    pub fn section(self) -> &'static str {
+        match self {
+            AovKind::Color | AovKind::Emission => SECTION_LIGHTING,
+            AovKind::Depth | AovKind::Normal | AovKind::Position | AovKind::Velocity => "Data",
+            AovKind::Cryptomatte => "Cryptomatte",
+            AovKind::Id => "Tech",
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            AovKind::Color => "Color",
+            AovKind::Depth => "Depth",
+            AovKind::Normal => "Normal",
+            AovKind::Position => "Position",
+            AovKind::Velocity => "Velocity",
+            AovKind::Id => "ID",
+            AovKind::Emission => "Emission",
+            AovKind::Cryptomatte => "Cryptomatte",
+        }
+    }
+}
+
+pub const SECTION_LIGHTING: &str = "Lighting";
+/// Kolejność sekcji w panelu warstw
+pub const SECTIONS: [&str; 4] = [SECTION_LIGHTING, "Data", "Cryptomatte", "Tech"];
+
+/// Sposób renderowania podglądu wynikający z reguły dla danego rodzaju AOV
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PreviewMode {
+    /// Ekspozycja + tone mapping
+    Color,
+    /// Liniowe mapowanie zakresu (gain/offset/abs)
+    Remap(ChannelRemap),
+    /// Auto-normalizacja percentylowa (opcjonalnie odwrócona)
+    Percentile { invert: bool },
+    /// Wektor 2D ze składowych wskazanych w regule (np. velocity: r, g) jako HSV + strzałki
+    Vector(VectorView),
+}
+
+// Reguły wczytywane raz przy pierwszym użyciu (zmiana pliku wymaga restartu)
+static CONFIG: LazyLock<ChannelConfig> = LazyLock::new(channel_config::load_or_create);
+
+/// Klasyfikuje AOV: najpierw ostatni człon nazwy warstwy (np. "RenderLayer.Normal" → "Normal"),
+/// a dla warstw kolorowych także nazwa kanału (np. "Z" w warstwie beauty). Pusty `channel` = cała warstwa.
+pub fn classify(layer: &str, channel: &str) -> AovKind {
+    let layer_segment = layer.rsplit('.').next().unwrap_or(layer);
+    if let Some(rule) = find_rule(layer_segment) {
+        return rule.kind;
+    }
+    if !channel.trim().is_empty() {
+        if let Some(rule) = find_rule(channel) {
+            return rule.kind;
+        }
+    }
+    AovKind::Color
+}
+
+fn find_rule(name: &str) -> Option<&'static ChannelRule> {
+    if name.trim().is_empty() { return None; }
+    CONFIG.rules.iter().find(|r| r.matches(name))
+}
+
+fn rule_for(kind: AovKind) -> Option<&'static ChannelRule> {
+    CONFIG.rules.iter().find(|r| r.kind == kind)
+}
+
+/// Domyślny tryb podglądu dla rodzaju AOV (display + normalization z reguły).
+/// Widok wektorowy wymaga całej warstwy; pojedynczy kanał pokazywany jest jak dane.
+pub fn preview_mode(kind: AovKind, whole_layer: bool) -> PreviewMode {
+    let Some(rule) = rule_for(kind) else { return PreviewMode::Color; };
+    match (rule.display, rule.normalization) {
+        (DisplayMode::Color, _) => PreviewMode::Color,
+        (DisplayMode::Vector, _) if whole_layer => {
+            let (x, y) = rule.vector.unwrap_or((0, 1));
+            PreviewMode::Vector(VectorView { x, y })
+        }
+        (_, Normalization::Remap(remap)) => PreviewMode::Remap(remap),
+        (_, Normalization::Percentile { invert }) => PreviewMode::Percentile { invert },
+        // Dane bez normalizacji: wartości wprost, obcięte do [0, 1]
+        (_, Normalization::None) => PreviewMode::Remap(ChannelRemap { gain: 1.0, offset: 0.0, abs: false }),
+    }
+}
+
+/// Ikona i kolor wiersza warstwy w drzewie (None = domyślne)
+pub fn tree_style(kind: AovKind) -> (Option<&'static str>, Option<(u8, u8, u8)>) {
+    rule_for(kind).map(|r| (r.icon.as_deref(), r.color)).unwrap_or((None, None))
+}
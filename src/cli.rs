@@ -0,0 +1,207 @@
+// Tryb wiersza poleceń: operacje bez UI (automatyczne porównania renderów w skryptach/CI).
+// Na Windows aplikacja jest okienkowa, więc wynik widać po przekierowaniu (np. `> wynik.txt`).
+
+use std::path::{Path, PathBuf};
+use crate::cancel::CancelToken;
+use crate::layer_cleanup::{self, CleanupPlan, LayerAction};
+use crate::metrics;
+
+const USAGE: &str = "\
+Usage:
+  EXRuster [file.exr... | folder]
+      Opens the file, or browses the folder in the thumbnail strip. Several files open as a
+      playlist (previous/next with PageUp/PageDown); they are not passed to a running window.
+  EXRuster --compare <a.exr> <b.exr> [--min-psnr <dB>] [--min-ssim <0..1>]
+      Prints PSNR and SSIM (on ACES tone-mapped values, so HDR highlights count) and per-channel
+      linear MAE; exit code 1 when below a threshold, 2 on error.
+  EXRuster --qc-report <file.exr | folder> <report.html>
+      Writes an HTML QC report (thumbnail, layers, min/max, NaN/Inf counts, histogram, metadata);
+      print it to PDF from a browser. Exit code 1 when a file is unreadable or has NaN/Inf, 2 on error.
+  EXRuster --clean-up <in.exr> <out.exr> [--drop <layer>]... [--rename <layer>=<new>]... [--fix-prefixes]
+      Writes a copy with layers dropped or renamed; --fix-prefixes strips render-layer prefixes
+      (\"RenderLayer.Image.R\" → \"beauty.R\"). Pixel data and attributes are kept; exit code 2 on error.
+  EXRuster --script <batch.rhai>
+      Runs a Rhai batch script (requires the \"scripting\" build feature); exit code 2 on error.
+  EXRuster --register | --unregister
+      Adds (removes) EXRuster in the .exr \"Open with\" list and the Explorer folder menu
+      (\"Browse folder in EXRuster\"), for the current user; Windows only.
+
+Environment:
+  EXRUSTER_SINGLE_INSTANCE=0
+      Always open a new window; by default a file or folder opened while EXRuster is running
+      is passed to the existing window.
+  EXRUSTER_EXPORT_JOBS=<n>
+      Number of export jobs run at the same time (default 2); further jobs wait in the queue.
+  EXRUSTER_FFMPEG=<path>
+      ffmpeg executable used for sequence video export (default: ffmpeg from PATH).
+  EXRUSTER_SCAN_DEPTH=<n>
+      Include EXR files up to <n> subfolder levels deep in the thumbnail strip (default 0).
+  EXRUSTER_GAP_MODE=hold|slate|skip
+      What the sequence timeline shows for a missing frame: the previous frame (default),
+      a \"missing frame\" slate, or nothing (playback and scrubbing jump over gaps).
+  EXRUSTER_SCAN_IGNORE=<pattern,...>
+      File and folder names skipped by the folder scan, `*` matches any text (default \"_tmp,cache\").";
+
+/// Zwraca kod wyjścia, jeśli argumenty wybierają tryb CLI; None = uruchom UI
+pub fn run_from_args() -> Option<i32> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    match args.first().map(String::as_str) {
+        Some("--compare") => Some(run_compare(&args[1..])),
+        Some("--qc-report") => Some(run_qc_report(&args[1..])),
+        Some("--clean-up") => Some(run_clean_up(&args[1..])),
+        Some("--script") => Some(run_script(args.get(1))),
+        Some("--register") => Some(run_association(true)),
+        Some("--unregister") => Some(run_association(false)),
+        Some("--help") | Some("-h") => {
+            println!("{}", USAGE);
+            Some(0)
+        }
+        _ => None,
+    }
+}
+
+/// Pliki lub folder do otwarcia w UI: argumenty, jeśli pierwszy nie jest opcją (dwuklik, "Otwórz za
+/// pomocą", kilka zaznaczonych plików)
+pub fn startup_paths() -> Vec<PathBuf> {
+    let paths: Vec<PathBuf> = std::env::args_os().skip(1).map(PathBuf::from).collect();
+    if paths.first().is_some_and(|p| p.to_string_lossy().starts_with("--")) {
+        return Vec::new();
+    }
+    paths
+}
+
+fn run_association(register: bool) -> i32 {
+    let result = if register {
+        crate::platform::register_file_association()
+    } else {
+        crate::platform::unregister_file_association()
+    };
+    match result {
+        Ok(()) => {
+            println!("{}", if register { "EXRuster registered for .exr files and folders" } else { "EXRuster file association removed" });
+            0
+        }
+        Err(e) => {
+            eprintln!("error: {}", e);
+            2
+        }
+    }
+}
+
+#[cfg(feature = "scripting")]
+fn run_script(script: Option<&String>) -> i32 {
+    let Some(script) = script else {
+        eprintln!("{}", USAGE);
+        return 2;
+    };
+    let report = |fraction: f32, message: &str| println!("[{:3.0}%] {}", fraction * 100.0, message);
+    match crate::scripting::run_script(std::path::Path::new(script), report) {
+        Ok(()) => 0,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            2
+        }
+    }
+}
+
+#[cfg(not(feature = "scripting"))]
+fn run_script(_script: Option<&String>) -> i32 {
+    eprintln!("error: scripting support not compiled in (build with --features scripting)");
+    2
+}
+
+fn run_qc_report(args: &[String]) -> i32 {
+    let (Some(input), Some(output)) = (args.first(), args.get(1)) else {
+        eprintln!("{}", USAGE);
+        return 2;
+    };
+    let report = |fraction: f32, message: &str| println!("[{:3.0}%] {}", fraction * 100.0, message);
+    match crate::qc_report::write_report(Path::new(input), Path::new(output), &CancelToken::new(), report) {
+        Ok(summary) => {
+            println!("{} files, {} unreadable, {} with NaN/Inf", summary.files, summary.failed, summary.non_finite);
+            if summary.passed() { 0 } else { 1 }
+        }
+        Err(e) => {
+            eprintln!("error: {:#}", e);
+            2
+        }
+    }
+}
+
+fn run_clean_up(args: &[String]) -> i32 {
+    let (Some(input), Some(output)) = (args.first(), args.get(1)) else {
+        eprintln!("{}", USAGE);
+        return 2;
+    };
+    let input = Path::new(input);
+    let mut plan = CleanupPlan::new();
+    let mut rest = args[2..].iter();
+    while let Some(flag) = rest.next() {
+        match (flag.as_str(), rest.clone().next()) {
+            ("--fix-prefixes", _) => match crate::io::fast_exr_metadata::read(input) {
+                Ok(meta) => plan.extend(layer_cleanup::prefix_fixes(&meta.layer_names)),
+                Err(e) => {
+                    eprintln!("error: {}", e);
+                    return 2;
+                }
+            },
+            ("--drop", Some(layer)) => {
+                plan.insert(layer.clone(), LayerAction::Drop);
+                rest.next();
+            }
+            ("--rename", Some(rule)) if rule.contains('=') => {
+                let (layer, name) = rule.split_once('=').unwrap_or_default();
+                plan.insert(layer.to_string(), LayerAction::Rename(name.to_string()));
+                rest.next();
+            }
+            _ => {
+                eprintln!("invalid argument: {}\n{}", flag, USAGE);
+                return 2;
+            }
+        }
+    }
+
+    match layer_cleanup::write_cleaned(input, Path::new(output), &plan) {
+        Ok(summary) => {
+            println!("{} channels kept ({} renamed), {} dropped", summary.kept, summary.renamed, summary.dropped);
+            0
+        }
+        Err(e) => {
+            eprintln!("error: {}", e);
+            2
+        }
+    }
+}
+
+fn run_compare(args: &[String]) -> i32 {
+    let (Some(a), Some(b)) = (args.first(), args.get(1)) else {
+        eprintln!("{}", USAGE);
+        return 2;
+    };
+    let mut min_psnr: Option<f64> = None;
+    let mut min_ssim: Option<f64> = None;
+    let mut rest = args[2..].iter();
+    while let Some(flag) = rest.next() {
+        let value = rest.next().and_then(|v| v.parse::<f64>().ok());
+        match (flag.as_str(), value) {
+            ("--min-psnr", Some(v)) => min_psnr = Some(v),
+            ("--min-ssim", Some(v)) => min_ssim = Some(v),
+            _ => {
+                eprintln!("invalid argument: {}\n{}", flag, USAGE);
+                return 2;
+            }
+        }
+    }
+
+    match metrics::compare_files(&PathBuf::from(a), &PathBuf::from(b)) {
+        Ok(m) => {
+            println!("{}", m.summary());
+            let failed = min_psnr.is_some_and(|t| m.psnr < t) || min_ssim.is_some_and(|t| m.ssim < t);
+            if failed { 1 } else { 0 }
+        }
+        Err(e) => {
+            eprintln!("error: {}", e);
+            2
+        }
+    }
+}
@@ -0,0 +1,70 @@
+// Próbnik koloru: uśrednione liniowe RGB z kliknięcia lub zaznaczonego obszaru oraz historia próbek.
+// Historia kopiowana jest jako tekst gotowy do wklejenia w Nuke (knob koloru) albo Houdini (wektor).
+
+/// Uśredniona próbka: wartości liniowe z pliku (przed ekspozycją i tone mappingiem)
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ColorSample {
+    pub r: f32,
+    pub g: f32,
+    pub b: f32,
+    pub a: f32,
+    /// Obszar w pikselach źródłowych: lewy górny róg i rozmiar
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// Maksymalna liczba próbek w historii (najnowsza na początku)
+pub const HISTORY_LEN: usize = 16;
+
+impl ColorSample {
+    pub fn float_text(&self) -> String {
+        format!("{:.4} {:.4} {:.4} {:.4}", self.r, self.g, self.b, self.a)
+    }
+
+    /// 8 bitów po kodowaniu sRGB (wartości poza 0..1 obcięte)
+    pub fn rgb8(&self) -> [u8; 3] {
+        [encode_srgb8(self.r), encode_srgb8(self.g), encode_srgb8(self.b)]
+    }
+
+    pub fn rgb8_text(&self) -> String {
+        let [r, g, b] = self.rgb8();
+        format!("{} {} {}", r, g, b)
+    }
+
+    pub fn hex(&self) -> String {
+        let [r, g, b] = self.rgb8();
+        format!("#{:02X}{:02X}{:02X}", r, g, b)
+    }
+
+    pub fn area_text(&self) -> String {
+        if self.width <= 1 && self.height <= 1 {
+            format!("@ {},{}", self.x, self.y)
+        } else {
+            format!("@ {},{} {}x{}", self.x, self.y, self.width, self.height)
+        }
+    }
+}
+
+/// Historia jako wartości knoba koloru Nuke: `{r g b a}`, po jednej na linię
+pub fn nuke_text(history: &[ColorSample]) -> String {
+    history.iter()
+        .map(|s| format!("{{{} {} {} {}}}", s.r, s.g, s.b, s.a))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Historia jako wektory Houdini: `{r, g, b}`, po jednym na linię
+pub fn houdini_text(history: &[ColorSample]) -> String {
+    history.iter()
+        .map(|s| format!("{{{}, {}, {}}}", s.r, s.g, s.b))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn encode_srgb8(linear: f32) -> u8 {
+    let v = if linear.is_finite() { linear.clamp(0.0, 1.0) } else { 0.0 };
+    let encoded = if v <= 0.003_130_8 { v * 12.92 } else { 1.055 * v.powf(1.0 / 2.4) - 0.055 };
+    (encoded * 255.0).round() as u8
+}
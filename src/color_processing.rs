@@ -0,0 +1,269 @@
+// Przestrzenie robocze ACES: macierze przejść AP0 (ACES2065-1) i AP1 (ACEScg) do/z XYZ oraz
+// liniowego Rec.709/sRGB. Wartości ACES ↔ XYZ z S-2014-004 i TB-2014-004; Rec.709 ↔ ACES zawiera
+// adaptację bieli D65 ↔ D60 metodą Bradforda (jak w transformacjach OCIO ACES 1.x). XYZ jest względne
+// wobec bieli danej przestrzeni (D60 dla ACES, D65 dla Rec.709), więc AP0 ↔ AP1 przechodzi przez XYZ
+// bez adaptacji, a pozostałe pary – przez macierze Rec.709. Dowolne prymarki z atrybutu
+// `chromaticities` (np. z bielą D60 lub DCI) trafiają do Rec.709 z adaptacją Bradforda do D65 –
+// tę macierz zna tylko graf przetwarzania (`ProcessingGraph::input_to`), tu prymarki pliku liczą się
+// jak Rec.709.
+
+use crate::image_processing::InputColorSpace;
+
+pub type Mat3 = [[f32; 3]; 3];
+
+pub const AP0_TO_XYZ: Mat3 = [
+    [0.952_552_4, 0.0, 0.000_093_68],
+    [0.343_966_45, 0.728_166_1, -0.072_132_55],
+    [0.0, 0.0, 1.008_825_2],
+];
+pub const XYZ_TO_AP0: Mat3 = [
+    [1.049_811, 0.0, -0.000_097_48],
+    [-0.495_903, 1.373_313, 0.098_240_04],
+    [0.0, 0.0, 0.991_252],
+];
+pub const AP1_TO_XYZ: Mat3 = [
+    [0.662_454_2, 0.134_004_2, 0.156_187_69],
+    [0.272_228_72, 0.674_081_8, 0.053_689_52],
+    [-0.005_574_65, 0.004_060_73, 1.010_339_1],
+];
+pub const XYZ_TO_AP1: Mat3 = [
+    [1.641_023_4, -0.324_803_3, -0.236_424_7],
+    [-0.663_662_86, 1.615_331_6, 0.016_756_35],
+    [0.011_721_89, -0.008_284_44, 0.988_394_86],
+];
+pub const REC709_TO_XYZ: Mat3 = [
+    [0.412_456_4, 0.357_576_1, 0.180_437_5],
+    [0.212_672_9, 0.715_152_2, 0.072_175],
+    [0.019_333_9, 0.119_192, 0.950_304_1],
+];
+pub const XYZ_TO_REC709: Mat3 = [
+    [3.240_454_2, -1.537_138_5, -0.498_531_4],
+    [-0.969_266, 1.876_010_8, 0.041_556],
+    [0.055_643_4, -0.204_025_9, 1.057_225_2],
+];
+pub const AP0_TO_REC709: Mat3 = [
+    [2.521_686, -1.134_131, -0.387_555],
+    [-0.276_479, 1.372_719, -0.096_240],
+    [-0.015_378, -0.152_975, 1.168_353],
+];
+pub const REC709_TO_AP0: Mat3 = [
+    [0.439_701, 0.382_978, 0.177_335],
+    [0.089_792_3, 0.813_423, 0.096_761_6],
+    [0.017_544, 0.111_544, 0.870_704],
+];
+pub const AP1_TO_REC709: Mat3 = [
+    [1.705_051, -0.621_792, -0.083_259],
+    [-0.130_256, 1.140_805, -0.010_548],
+    [-0.024_003, -0.128_969, 1.152_972],
+];
+pub const REC709_TO_AP1: Mat3 = [
+    [0.613_097_4, 0.339_523_1, 0.047_379_5],
+    [0.070_193_7, 0.916_353_9, 0.013_452_4],
+    [0.020_615_6, 0.109_569_8, 0.869_815_1],
+];
+
+/// Biel D65 (x, y) – biel Rec.709/sRGB
+pub const D65_WHITE: (f32, f32) = (0.3127, 0.3290);
+
+/// Macierz czopków Bradforda (XYZ → LMS)
+const BRADFORD: Mat3 = [
+    [0.8951, 0.2664, -0.1614],
+    [-0.7502, 1.7135, 0.0367],
+    [0.0389, -0.0685, 1.0296],
+];
+
+#[inline]
+pub fn apply(m: &Mat3, [r, g, b]: [f32; 3]) -> [f32; 3] {
+    [
+        m[0][0] * r + m[0][1] * g + m[0][2] * b,
+        m[1][0] * r + m[1][1] * g + m[1][2] * b,
+        m[2][0] * r + m[2][1] * g + m[2][2] * b,
+    ]
+}
+
+/// Złożenie macierzy: najpierw `b`, potem `a`
+pub fn mul(a: &Mat3, b: &Mat3) -> Mat3 {
+    let mut out = [[0.0; 3]; 3];
+    for (i, row) in out.iter_mut().enumerate() {
+        for (j, v) in row.iter_mut().enumerate() {
+            *v = (0..3).map(|k| a[i][k] * b[k][j]).sum();
+        }
+    }
+    out
+}
+
+/// Odwrotność macierzy; None dla macierzy osobliwej
+pub fn invert(m: &Mat3) -> Option<Mat3> {
+    let cofactor = |r0: usize, r1: usize, c0: usize, c1: usize| m[r0][c0] * m[r1][c1] - m[r0][c1] * m[r1][c0];
+    let adjugate = [
+        [cofactor(1, 2, 1, 2), -cofactor(0, 2, 1, 2), cofactor(0, 1, 1, 2)],
+        [-cofactor(1, 2, 0, 2), cofactor(0, 2, 0, 2), -cofactor(0, 1, 0, 2)],
+        [cofactor(1, 2, 0, 1), -cofactor(0, 2, 0, 1), cofactor(0, 1, 0, 1)],
+    ];
+    let det = m[0][0] * adjugate[0][0] + m[0][1] * adjugate[1][0] + m[0][2] * adjugate[2][0];
+    if det.abs() < 1e-12 || !det.is_finite() {
+        return None;
+    }
+    Some(adjugate.map(|row| row.map(|v| v / det)))
+}
+
+/// Punkt (x, y) jako XYZ przy Y = 1; None dla y ≈ 0
+fn xy_to_xyz((x, y): (f32, f32)) -> Option<[f32; 3]> {
+    (y.abs() > 1e-6).then(|| [x / y, 1.0, (1.0 - x - y) / y])
+}
+
+/// RGB → XYZ (względem własnej bieli) z prymarek R, G, B i bieli (x, y)
+pub fn rgb_to_xyz_from_chromaticities(primaries: [(f32, f32); 3], white: (f32, f32)) -> Option<Mat3> {
+    let [r, g, b] = [xy_to_xyz(primaries[0])?, xy_to_xyz(primaries[1])?, xy_to_xyz(primaries[2])?];
+    let columns = [[r[0], g[0], b[0]], [r[1], g[1], b[1]], [r[2], g[2], b[2]]];
+    // Skale prymarek tak, by RGB (1, 1, 1) dawało biel
+    let scale = apply(&invert(&columns)?, xy_to_xyz(white)?);
+    Some(columns.map(|row| [row[0] * scale[0], row[1] * scale[1], row[2] * scale[2]]))
+}
+
+/// Adaptacja chromatyczna Bradforda XYZ z bieli `from` do bieli `to` (x, y)
+pub fn bradford_adaptation(from: (f32, f32), to: (f32, f32)) -> Option<Mat3> {
+    let (src, dst) = (apply(&BRADFORD, xy_to_xyz(from)?), apply(&BRADFORD, xy_to_xyz(to)?));
+    let gain = [[dst[0] / src[0], 0.0, 0.0], [0.0, dst[1] / src[1], 0.0], [0.0, 0.0, dst[2] / src[2]]];
+    Some(mul(&invert(&BRADFORD)?, &mul(&gain, &BRADFORD)))
+}
+
+/// RGB o prymarkach i bieli z atrybutu `chromaticities` → liniowe Rec.709/sRGB; biel inna niż D65
+/// jest adaptowana (Bradford), więc biel pliku pozostaje bielą. None dla zdegenerowanych prymarek.
+pub fn compute_rgb_to_srgb_matrix_from_chromaticities(primaries: [(f32, f32); 3], white: (f32, f32)) -> Option<Mat3> {
+    let to_xyz = rgb_to_xyz_from_chromaticities(primaries, white)?;
+    let adapted = mul(&bradford_adaptation(white, D65_WHITE)?, &to_xyz);
+    let m = mul(&XYZ_TO_REC709, &adapted);
+    m.iter().flatten().all(|v| v.is_finite()).then_some(m)
+}
+
+/// RGB → XYZ względem bieli przestrzeni (dla prymarek z pliku – po adaptacji do D65)
+pub fn to_xyz_matrix(space: InputColorSpace) -> Mat3 {
+    match space {
+        InputColorSpace::LinearRec709 | InputColorSpace::FilePrimaries => REC709_TO_XYZ,
+        InputColorSpace::Aces2065 => AP0_TO_XYZ,
+        InputColorSpace::AcesCg => AP1_TO_XYZ,
+    }
+}
+
+/// XYZ → RGB względem bieli przestrzeni
+pub fn from_xyz_matrix(space: InputColorSpace) -> Mat3 {
+    match space {
+        InputColorSpace::LinearRec709 | InputColorSpace::FilePrimaries => XYZ_TO_REC709,
+        InputColorSpace::Aces2065 => XYZ_TO_AP0,
+        InputColorSpace::AcesCg => XYZ_TO_AP1,
+    }
+}
+
+pub const IDENTITY: Mat3 = [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]];
+
+/// Macierz przejścia między przestrzeniami; None = bez konwersji (prymarki pliku jak Rec.709)
+pub fn conversion_matrix(from: InputColorSpace, to: InputColorSpace) -> Option<Mat3> {
+    use InputColorSpace::*;
+    let known = |space| if space == FilePrimaries { LinearRec709 } else { space };
+    match (known(from), known(to)) {
+        (from, to) if from == to => None,
+        (Aces2065, LinearRec709) => Some(AP0_TO_REC709),
+        (AcesCg, LinearRec709) => Some(AP1_TO_REC709),
+        (LinearRec709, Aces2065) => Some(REC709_TO_AP0),
+        (LinearRec709, AcesCg) => Some(REC709_TO_AP1),
+        // AP0 ↔ AP1: wspólna biel D60
+        (from, to) => Some(mul(&from_xyz_matrix(to), &to_xyz_matrix(from))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use InputColorSpace::*;
+
+    fn assert_close(actual: [f32; 3], expected: [f32; 3], tolerance: f32, what: &str) {
+        for (a, e) in actual.iter().zip(expected) {
+            assert!((a - e).abs() < tolerance, "{what}: {actual:?} != {expected:?}");
+        }
+    }
+
+    #[test]
+    fn white_points() {
+        // Biel ACES (x 0.32168, y 0.33767) i D65 (x 0.3127, y 0.3290) jako XYZ przy Y = 1
+        assert_close(apply(&AP0_TO_XYZ, [1.0; 3]), [0.952_646, 1.0, 1.008_825], 1e-4, "AP0 white");
+        assert_close(apply(&AP1_TO_XYZ, [1.0; 3]), [0.952_646, 1.0, 1.008_825], 1e-4, "AP1 white");
+        assert_close(apply(&REC709_TO_XYZ, [1.0; 3]), [0.950_47, 1.0, 1.088_83], 1e-4, "D65 white");
+        // Wiersz Y macierzy Rec.709 to współczynniki luminancji
+        assert_close(REC709_TO_XYZ[1], [0.2126, 0.7152, 0.0722], 1e-4, "Rec.709 luma");
+        for (from, to) in [(Aces2065, LinearRec709), (AcesCg, LinearRec709), (LinearRec709, Aces2065), (LinearRec709, AcesCg), (Aces2065, AcesCg)] {
+            let m = conversion_matrix(from, to).unwrap();
+            assert_close(apply(&m, [0.18; 3]), [0.18; 3], 5e-4, &format!("{from:?} → {to:?} grey"));
+        }
+    }
+
+    #[test]
+    fn published_matrices() {
+        // AP0 → AP1 z TB-2014-004 (ACES 1.x: ACES2065-1 → ACEScg)
+        let ap0_to_ap1 = conversion_matrix(Aces2065, AcesCg).unwrap();
+        let expected = [
+            [1.451_439_3, -0.236_510_7, -0.214_928_6],
+            [-0.076_553_77, 1.176_229_7, -0.099_675_93],
+            [0.008_316_15, -0.006_032_45, 0.997_716_3],
+        ];
+        for (row, want) in ap0_to_ap1.iter().zip(expected) {
+            assert_close(*row, want, 1e-4, "AP0 → AP1");
+        }
+        // Czyste prymarki sRGB w ACES2065-1 (kolumny macierzy OCIO "Utility - Linear - sRGB")
+        assert_close(apply(&REC709_TO_AP0, [1.0, 0.0, 0.0]), [0.439_701, 0.089_792_3, 0.017_544], 1e-6, "sRGB red → AP0");
+        assert_close(apply(&REC709_TO_AP1, [0.0, 0.0, 1.0]), [0.047_379_5, 0.013_452_4, 0.869_815_1], 1e-6, "sRGB blue → AP1");
+    }
+
+    #[test]
+    fn round_trips() {
+        let samples = [[0.5, 0.25, 0.1], [0.1, 0.4, 0.8], [2.0, 0.01, 0.3]];
+        for space in [LinearRec709, Aces2065, AcesCg] {
+            let (to, from) = (to_xyz_matrix(space), from_xyz_matrix(space));
+            for rgb in samples {
+                assert_close(apply(&from, apply(&to, rgb)), rgb, 1e-4, &format!("{space:?} ↔ XYZ"));
+            }
+            for other in [LinearRec709, Aces2065, AcesCg] {
+                let there = conversion_matrix(space, other).unwrap_or(IDENTITY);
+                let back = conversion_matrix(other, space).unwrap_or(IDENTITY);
+                for rgb in samples {
+                    assert_close(apply(&back, apply(&there, rgb)), rgb, 2e-3, &format!("{space:?} ↔ {other:?}"));
+                }
+            }
+        }
+    }
+
+    fn assert_matrix(actual: Mat3, expected: Mat3, tolerance: f32, what: &str) {
+        for (row, want) in actual.iter().zip(expected) {
+            assert_close(*row, want, tolerance, what);
+        }
+    }
+
+    #[test]
+    fn chromatic_adaptation() {
+        // Bradford D60 (ACES) → D65 wg tabel Lindblooma / colour-science
+        let d60_to_d65 = bradford_adaptation((0.32168, 0.33767), D65_WHITE).unwrap();
+        assert_matrix(d60_to_d65, [
+            [0.987_224, -0.006_113_27, 0.015_953_3],
+            [-0.007_598_36, 1.001_86, 0.005_330_02],
+            [0.003_072_57, -0.005_095_95, 1.081_68],
+        ], 2e-4, "Bradford D60 → D65");
+        assert_matrix(bradford_adaptation(D65_WHITE, D65_WHITE).unwrap(), IDENTITY, 1e-6, "Bradford D65 → D65");
+
+        let rec709 = [(0.64, 0.33), (0.30, 0.60), (0.15, 0.06)];
+        let ap0 = [(0.7347, 0.2653), (0.0, 1.0), (0.0001, -0.077)];
+        let ap1 = [(0.713, 0.293), (0.165, 0.830), (0.128, 0.044)];
+        let aces_white = (0.32168, 0.33767);
+        assert_matrix(compute_rgb_to_srgb_matrix_from_chromaticities(rec709, D65_WHITE).unwrap(), IDENTITY, 1e-3, "Rec.709 D65");
+        assert_matrix(rgb_to_xyz_from_chromaticities(ap0, aces_white).unwrap(), AP0_TO_XYZ, 1e-4, "AP0 → XYZ");
+        assert_matrix(rgb_to_xyz_from_chromaticities(ap1, aces_white).unwrap(), AP1_TO_XYZ, 1e-4, "AP1 → XYZ");
+        // Wyliczone z prymarek ACES muszą zgadzać się z opublikowanymi macierzami ACES → sRGB
+        assert_matrix(compute_rgb_to_srgb_matrix_from_chromaticities(ap0, aces_white).unwrap(), AP0_TO_REC709, 2e-3, "AP0 → Rec.709");
+        assert_matrix(compute_rgb_to_srgb_matrix_from_chromaticities(ap1, aces_white).unwrap(), AP1_TO_REC709, 2e-3, "AP1 → Rec.709");
+        // Rec.709 z bielą D60 i P3 z bielą DCI: biel pliku staje się bielą D65 (1, 1, 1)
+        for (primaries, white) in [(rec709, aces_white), ([(0.680, 0.320), (0.265, 0.690), (0.150, 0.060)], (0.314, 0.351))] {
+            let m = compute_rgb_to_srgb_matrix_from_chromaticities(primaries, white).unwrap();
+            assert_close(apply(&m, [1.0; 3]), [1.0; 3], 1e-3, &format!("white {white:?}"));
+        }
+        assert!(compute_rgb_to_srgb_matrix_from_chromaticities([(0.3, 0.3); 3], D65_WHITE).is_none(), "degenerate primaries");
+    }
+}
@@ -0,0 +1,185 @@
+use rayon::prelude::*;
+use slint::Rgba8Pixel;
+use crate::raw_image::RawImage;
+use crate::image_cache::ImageCache;
+use crate::image_processing::DisplayTransform;
+use crate::layer_cache::Pixels;
+use crate::metrics::{self, ImageMetrics};
+
+/// Sposób wizualizacji różnicy względem obrazu referencyjnego
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DiffMode {
+    Off,
+    /// |A − B| per kanał
+    Absolute,
+    /// Różnica luminancji: czerwony = jaśniej niż referencja, niebieski = ciemniej
+    Signed,
+    /// |A − B| / |B| (błąd względny)
+    Relative,
+}
+
+impl DiffMode {
+    pub fn from_label(label: &str) -> Self {
+        if label.starts_with("Abs") {
+            DiffMode::Absolute
+        } else if label.starts_with("Signed") {
+            DiffMode::Signed
+        } else if label.starts_with("Rel") {
+            DiffMode::Relative
+        } else {
+            DiffMode::Off
+        }
+    }
+}
+
+/// Piksele obrazu referencyjnego (współdzielone z cache warstw – bez kopiowania)
+struct Reference {
+    pixels: Pixels,
+    width: u32,
+    height: u32,
+}
+
+/// Stan porównania z referencją (jeden na kontekst aplikacji, patrz `AppContext::compare`)
+pub struct CompareState {
+    reference: Option<Reference>,
+    mode: DiffMode,
+    tolerance: f32,
+}
+
+impl Default for CompareState {
+    fn default() -> Self {
+        CompareState { reference: None, mode: DiffMode::Off, tolerance: 0.01 }
+    }
+}
+
+impl CompareState {
+    /// Zapamiętuje bieżący obraz (aktualną warstwę/kanał) jako referencję
+    pub fn set_reference(&mut self, cache: &ImageCache) {
+        self.reference = Some(Reference {
+            pixels: cache.raw_pixels.clone(),
+            width: cache.width,
+            height: cache.height,
+        });
+    }
+
+    pub fn clear_reference(&mut self) {
+        self.reference = None;
+    }
+
+    pub fn set_mode(&mut self, mode: DiffMode) {
+        self.mode = mode;
+    }
+
+    /// Próg błędu; piksele powyżej są zaznaczane na mapie ciepła (0 = bez zaznaczania)
+    pub fn set_tolerance(&mut self, tolerance: f32) {
+        self.tolerance = tolerance.max(0.0);
+    }
+}
+
+/// Metryki porównania wyświetlane w pasku statusu
+#[derive(Clone, Copy, Debug)]
+pub struct DiffStats {
+    pub metrics: ImageMetrics,
+    pub max_error: f32,
+    pub failing: usize,
+    pub total: usize,
+}
+
+impl DiffStats {
+    pub fn summary(&self) -> String {
+        let failing_pct = if self.total > 0 { self.failing as f64 * 100.0 / self.total as f64 } else { 0.0 };
+        format!("{} | max error: {:.5} | over tolerance: {} ({:.2}%)", self.metrics.summary(), self.max_error, self.failing, failing_pct)
+    }
+}
+
+#[inline]
+fn luma(r: f32, g: f32, b: f32) -> f32 {
+    0.2126 * r + 0.7152 * g + 0.0722 * b
+}
+
+#[inline]
+fn finite(v: f32) -> f32 {
+    if v.is_finite() { v } else { 0.0 }
+}
+
+/// Błąd piksela używany do progu tolerancji: maksimum po kanałach RGB (bezwzględny lub względny)
+#[inline]
+fn pixel_error(mode: DiffMode, a: (f32, f32, f32, f32), b: (f32, f32, f32, f32)) -> f32 {
+    let pairs = [(a.0, b.0), (a.1, b.1), (a.2, b.2)];
+    pairs.iter().fold(0.0f32, |acc, &(x, y)| {
+        let d = finite(x - y).abs();
+        let e = if mode == DiffMode::Relative { d / finite(y).abs().max(1e-4) } else { d };
+        acc.max(e)
+    })
+}
+
+impl CompareState {
+    /// Renderuje różnicę bieżącego obrazu względem referencji w orientacji widoku `transform`.
+    /// None = porównanie nieaktywne; Err = obrazy mają różne wymiary.
+    pub fn render_diff(&self, cache: &ImageCache, exposure: f32, gamma: f32, transform: &DisplayTransform) -> Option<Result<(RawImage, DiffStats), String>> {
+        let reference = self.reference.as_ref()?;
+        if self.mode == DiffMode::Off {
+            return None;
+        }
+        let metrics = match metrics::compare_pixels(&cache.raw_pixels, &reference.pixels, cache.width, cache.height, reference.width, reference.height) {
+            Ok(metrics) => metrics,
+            Err(e) => return Some(Err(format!("Reference: {}", e))),
+        };
+
+        let (mode, tolerance) = (self.mode, self.tolerance);
+        let stats = compute_stats(mode, tolerance, &cache.raw_pixels, &reference.pixels, metrics);
+
+        // Wizualizacja: różnica wzmocniona ekspozycją i zakodowana gammą; piksele ponad tolerancją jako mapa ciepła
+        let scale = 2.0_f32.powf(exposure);
+        let gamma_inv = 1.0 / gamma.max(1e-4);
+        let encode = |v: f32| -> u8 { ((v * scale).clamp(0.0, 1.0).powf(gamma_inv) * 255.0).round() as u8 };
+        let image = cache.map_pixels(transform, |i, a| {
+            let b = reference.pixels[i];
+            if tolerance > 0.0 {
+                let err = pixel_error(mode, a, b);
+                if err > tolerance {
+                    // 1× tolerancji → pomarańczowy, ≥10× → czerwony
+                    let k = ((err / tolerance).ln() / 10f32.ln()).clamp(0.0, 1.0);
+                    return Rgba8Pixel { r: 255, g: (160.0 * (1.0 - k)) as u8, b: 0, a: 255 };
+                }
+            }
+            match mode {
+                DiffMode::Signed => {
+                    let d = finite(luma(a.0, a.1, a.2) - luma(b.0, b.1, b.2));
+                    let v = encode(d.abs());
+                    if d >= 0.0 { Rgba8Pixel { r: v, g: 0, b: 0, a: 255 } } else { Rgba8Pixel { r: 0, g: 0, b: v, a: 255 } }
+                }
+                DiffMode::Relative => {
+                    let v = encode(pixel_error(DiffMode::Relative, a, b));
+                    Rgba8Pixel { r: v, g: v, b: v, a: 255 }
+                }
+                _ => Rgba8Pixel {
+                    r: encode(finite(a.0 - b.0).abs()),
+                    g: encode(finite(a.1 - b.1).abs()),
+                    b: encode(finite(a.2 - b.2).abs()),
+                    a: 255,
+                },
+            }
+        });
+        Some(Ok((image, stats)))
+    }
+}
+
+fn compute_stats(
+    mode: DiffMode,
+    tolerance: f32,
+    current: &[(f32, f32, f32, f32)],
+    reference: &[(f32, f32, f32, f32)],
+    metrics: ImageMetrics,
+) -> DiffStats {
+    let (max_error, failing) = current
+        .par_iter()
+        .zip(reference.par_iter())
+        .map(|(&a, &b)| {
+            let fails = tolerance > 0.0 && pixel_error(mode, a, b) > tolerance;
+            (pixel_error(DiffMode::Absolute, a, b), fails as usize)
+        })
+        .reduce(|| (0.0, 0), |x, y| (x.0.max(y.0), x.1 + y.1));
+
+    DiffStats { metrics, max_error, failing, total: current.len() }
+}
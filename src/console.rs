@@ -0,0 +1,105 @@
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::path::Path;
+use std::time::Duration;
+use slint::{Model, SharedString, Timer, TimerMode};
+use crate::logging::{self, LogEntry};
+use crate::ui_handlers::ConsoleModel;
+
+/// Limit wpisów trzymanych w konsoli – starsze są usuwane (bufor cykliczny)
+const MAX_CONSOLE_ENTRIES: usize = 5000;
+
+/// Stan konsoli: historia wpisów oraz aktywne filtry widoku
+#[derive(Default)]
+struct ConsoleState {
+    history: VecDeque<LogEntry>,
+    category: Option<String>,
+    search: String,
+}
+
+impl ConsoleState {
+    fn matches(&self, entry: &LogEntry, line: &str) -> bool {
+        self.category.as_deref().is_none_or(|c| c == entry.category)
+            && (self.search.is_empty() || line.to_lowercase().contains(&self.search))
+    }
+
+    /// Linie widoczne przy bieżących filtrach
+    fn visible_lines(&self) -> Vec<SharedString> {
+        self.history.iter()
+            .map(|e| (e, e.format_line()))
+            .filter(|(e, line)| self.matches(e, line))
+            .map(|(_, line)| line.into())
+            .collect()
+    }
+}
+
+thread_local! {
+    static CONSOLE: RefCell<ConsoleState> = RefCell::new(ConsoleState::default());
+    // Timer przenoszący wpisy z warstwy logowania do konsoli w UI
+    static CONSOLE_PUMP_TIMER: Timer = Timer::default();
+}
+
+/// Uruchamia okresowe przenoszenie wpisów logu do modelu konsoli (logować można z dowolnego wątku)
+pub fn start_log_pump(console: ConsoleModel) {
+    CONSOLE_PUMP_TIMER.with(|timer| {
+        timer.start(TimerMode::Repeated, Duration::from_millis(50), move || flush(&console));
+    });
+}
+
+/// Dopisuje nowe wpisy do historii i (jeśli pasują do filtrów) do modelu konsoli
+fn flush(console: &ConsoleModel) {
+    let entries = logging::drain_pending();
+    if entries.is_empty() { return; }
+
+    CONSOLE.with(|state| {
+        let mut state = state.borrow_mut();
+        for entry in entries {
+            let line = entry.format_line();
+            if state.matches(&entry, &line) {
+                console.push(line.into());
+            }
+            state.history.push_back(entry);
+        }
+        while state.history.len() > MAX_CONSOLE_ENTRIES {
+            state.history.pop_front();
+        }
+    });
+    // Model zawiera podzbiór historii, więc ten sam limit wystarcza
+    let overflow = console.row_count().saturating_sub(MAX_CONSOLE_ENTRIES);
+    for _ in 0..overflow {
+        console.remove(0);
+    }
+}
+
+/// Ustawia filtr kategorii ("All" = wszystkie) i przebudowuje widok
+pub fn set_category(console: &ConsoleModel, category: &str) {
+    CONSOLE.with(|state| {
+        let mut state = state.borrow_mut();
+        state.category = (category != "All").then(|| category.to_string());
+        console.set_vec(state.visible_lines());
+    });
+}
+
+/// Ustawia filtr tekstowy (bez rozróżniania wielkości liter) i przebudowuje widok
+pub fn set_search(console: &ConsoleModel, text: &str) {
+    CONSOLE.with(|state| {
+        let mut state = state.borrow_mut();
+        state.search = text.trim().to_lowercase();
+        console.set_vec(state.visible_lines());
+    });
+}
+
+/// Czyści konsolę: model i historię wpisów
+pub fn clear(console: &ConsoleModel) {
+    CONSOLE.with(|state| state.borrow_mut().history.clear());
+    console.set_vec(Vec::<SharedString>::new());
+}
+
+/// Zapisuje widoczne (przefiltrowane) linie konsoli do pliku; zwraca liczbę linii
+pub fn save_to_file(console: &ConsoleModel, path: &Path) -> std::io::Result<usize> {
+    let lines: Vec<String> = console.iter().map(|l| l.to_string()).collect();
+    let mut text = lines.join("\n");
+    text.push('\n');
+    std::fs::write(path, text)?;
+    Ok(lines.len())
+}
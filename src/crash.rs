@@ -0,0 +1,72 @@
+use std::fs;
+use std::path::PathBuf;
+use rfd::{MessageButtons, MessageDialog, MessageDialogResult, MessageLevel};
+use crate::session::{self, SessionState};
+
+// Znacznik awarii: ścieżka do raportu ostatniej awarii, usuwany po pokazaniu dialogu
+const CRASH_MARKER: &str = "crash_pending.txt";
+
+/// Instaluje hook paniki: zapisuje raport awarii (komunikat, backtrace, ostatni plik, wersja)
+/// oraz bieżącą sesję. W aplikacji okienkowej stderr jest niewidoczny, więc to jedyny ślad awarii.
+pub fn install_panic_hook() {
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        session::save_current();
+        let _ = write_crash_report(info);
+        default_hook(info);
+    }));
+}
+
+fn write_crash_report(info: &std::panic::PanicHookInfo<'_>) -> std::io::Result<PathBuf> {
+    let dir = session::app_data_dir();
+    fs::create_dir_all(&dir)?;
+
+    let timestamp = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    let last_file = session::load_saved()
+        .and_then(|s| s.last_file)
+        .map(|p| p.display().to_string())
+        .unwrap_or_else(|| "-".to_string());
+    let thread = std::thread::current();
+    let report = format!(
+        "EXRuster {} crash report\ntimestamp: {}\nthread: {}\nlast file: {}\n\n{}\n\nbacktrace:\n{}\n",
+        env!("CARGO_PKG_VERSION"),
+        timestamp,
+        thread.name().unwrap_or("<unnamed>"),
+        last_file,
+        info,
+        std::backtrace::Backtrace::force_capture(),
+    );
+
+    let path = dir.join(format!("crash-{}.txt", timestamp));
+    fs::write(&path, report)?;
+    fs::write(dir.join(CRASH_MARKER), path.display().to_string())?;
+    Ok(path)
+}
+
+/// Po starcie: jeśli poprzednia sesja zakończyła się awarią, pokazuje natywny dialog
+/// z lokalizacją raportu i pyta o przywrócenie sesji. Zwraca sesję do przywrócenia.
+pub fn check_previous_crash() -> Option<SessionState> {
+    let marker = session::app_data_dir().join(CRASH_MARKER);
+    let report_path = fs::read_to_string(&marker).ok()?;
+    let _ = fs::remove_file(&marker);
+
+    let saved = session::load_saved();
+    let can_restore = saved.as_ref().is_some_and(|s| s.last_file.is_some() || s.working_dir.is_some());
+    let description = if can_restore {
+        format!("EXRuster closed unexpectedly last time.\nA crash report was saved to:\n{}\n\nRestore the previous session?", report_path.trim())
+    } else {
+        format!("EXRuster closed unexpectedly last time.\nA crash report was saved to:\n{}", report_path.trim())
+    };
+
+    let result = MessageDialog::new()
+        .set_level(MessageLevel::Warning)
+        .set_title("EXRuster - crash report")
+        .set_description(description)
+        .set_buttons(if can_restore { MessageButtons::YesNo } else { MessageButtons::Ok })
+        .show();
+
+    if can_restore && result == MessageDialogResult::Yes { saved } else { None }
+}
@@ -0,0 +1,363 @@
+// Podgląd plików deep EXR (deep scanline / deep tile). Biblioteka exr nie dekoduje danych deep,
+// więc bloki czytamy surowo, rozpakowujemy samodzielnie (NONE / RLE / ZIPS) i spłaszczamy
+// próbki każdego piksela kompozycją front-to-back do zwykłego obrazu RGBA.
+
+use std::io::{Read, Seek, SeekFrom};
+use std::path::Path;
+use rayon::prelude::*;
+use ::exr::compression::Compression;
+use ::exr::io::PeekRead;
+use ::exr::meta::attribute::SampleType;
+use ::exr::meta::header::Header;
+use ::exr::meta::{magic_number, BlockDescription, Headers, MetaData, Requirements};
+use crate::cancel::CancelToken;
+use crate::image_cache::{alloc_pixels, channel_alias_to_short, open_cancellable, sample_as_f32, LoadedLayer};
+use crate::utils::error_handling::{ExrError, ExrResult};
+use crate::utils::split_layer_and_short;
+
+type Pixel = (f32, f32, f32, f32);
+
+/// Czy którakolwiek część pliku zawiera dane deep
+pub fn has_deep_parts(headers: &[Header]) -> bool {
+    headers.iter().any(|h| h.deep)
+}
+
+/// Krótki opis typu części do metadanych (None dla zwykłych części)
+pub fn deep_kind_label(header: &Header) -> Option<&'static str> {
+    if !header.deep { return None; }
+    Some(match header.blocks {
+        BlockDescription::ScanLines => "deep scanline",
+        BlockDescription::Tiles(_) => "deep tile",
+    })
+}
+
+/// Indeksy kanałów wybranej części: R, G, B, A, Z
+struct DeepChannels {
+    rgba: [Option<usize>; 4],
+    z: Option<usize>,
+}
+
+/// Wybiera pierwszą część deep z kanałami koloru (lub po prostu pierwszą część deep)
+fn choose_part(headers: &[Header]) -> Option<(usize, DeepChannels, String)> {
+    let mut fallback = None;
+    for (index, header) in headers.iter().enumerate().filter(|(_, h)| h.deep) {
+        let base_attr: Option<String> = header.own_attributes.layer_name.as_ref().map(|s| s.to_string());
+        let mut channels = DeepChannels { rgba: [None; 4], z: None };
+        let mut layer_name: Option<String> = None;
+        for (idx, ch) in header.channels.list.iter().enumerate() {
+            let (lname, short) = split_layer_and_short(&ch.name.to_string(), base_attr.as_deref());
+            let slot = match channel_alias_to_short(&short).as_str() {
+                "R" => &mut channels.rgba[0],
+                "G" => &mut channels.rgba[1],
+                "B" => &mut channels.rgba[2],
+                "A" => &mut channels.rgba[3],
+                "Z" => &mut channels.z,
+                _ => continue,
+            };
+            if slot.is_none() {
+                *slot = Some(idx);
+                layer_name.get_or_insert(lname);
+            }
+        }
+        if channels.rgba[..3].iter().any(|c| c.is_some()) {
+            return Some((index, channels, layer_name.unwrap_or_default()));
+        }
+        if fallback.is_none() {
+            let first = header.channels.list.first().map(|ch| ch.name.to_string()).unwrap_or_default();
+            let (lname, _) = split_layer_and_short(&first, base_attr.as_deref());
+            // Brak koloru: pokaż pierwszy kanał jako szarość
+            fallback = Some((index, DeepChannels { rgba: [Some(0), None, None, None], z: channels.z }, lname));
+        }
+    }
+    fallback
+}
+
+/// Odczyt nagłówków bez walidacji biblioteki exr (która odrzuca części deep)
+pub fn read_headers(path: &Path) -> ExrResult<Headers> {
+    read_headers_from(crate::io::open(path)?)
+}
+
+/// Jak `read_headers`, dla dowolnego strumienia (bajty nagłówka w pamięci, testy odporności)
+pub fn read_headers_from(read: impl Read) -> ExrResult<Headers> {
+    read_meta(&mut PeekRead::new(read)).map(|(_, headers)| headers)
+}
+
+pub(crate) fn read_meta(read: &mut PeekRead<impl Read>) -> ExrResult<(Requirements, Headers)> {
+    magic_number::validate_exr(read)?;
+    let requirements = Requirements::read(read)?;
+    requirements.validate()?;
+    let headers = Header::read_all(read, &requirements, false)?;
+    Ok((requirements, headers))
+}
+
+/// Wczytuje pierwszą część deep i spłaszcza ją do obrazu RGBA (poziom 0, bez mip-map)
+pub(crate) fn load_flattened(path: &Path, cancel: &CancelToken) -> ExrResult<LoadedLayer> {
+    let mut read = PeekRead::new(open_cancellable(path, cancel)?);
+    let (requirements, headers) = read_meta(&mut read)?;
+    let offset_tables = MetaData::read_offset_tables(&mut read, &headers)?;
+    let (part, channels, layer_name) = choose_part(&headers)
+        .ok_or_else(|| ExrError::Unsupported("no deep part with channels".into()))?;
+    let header = &headers[part];
+
+    // Bloki wybranej części czytamy samodzielnie: czytnik exr ogranicza rozmiar bloku do danych płaskich
+    let mut file = open_cancellable(path, cancel)?;
+    let mut blocks: Vec<DeepBlock> = Vec::with_capacity(offset_tables[part].len());
+    for &offset in &offset_tables[part] {
+        file.seek(SeekFrom::Start(offset))?;
+        if let Some(block) = read_block(&mut file, header, requirements.is_multilayer())? {
+            blocks.push(block);
+        }
+    }
+    cancel.check()?;
+
+    // Dekodowanie i spłaszczanie bloków równolegle
+    let width = header.layer_size.width();
+    let height = header.layer_size.height();
+    let decoded: Vec<(BlockArea, Vec<Pixel>)> = blocks
+        .par_iter()
+        .map(|block| {
+            if cancel.is_cancelled() { return Err(ExrError::Canceled); }
+            flatten_block(header, &channels, block).map(|pixels| (block.area, pixels))
+        })
+        .collect::<ExrResult<_>>()?;
+
+    let mut pixels = alloc_pixels(width, height)?;
+    pixels.resize(width * height, (0.0, 0.0, 0.0, 0.0));
+    for (area, block_pixels) in decoded {
+        for (row, line) in block_pixels.chunks(area.width).take(area.height).enumerate() {
+            let start = (area.y + row) * width + area.x;
+            pixels[start..start + line.len()].copy_from_slice(line);
+        }
+    }
+
+    // Brakujące kanały duplikujemy jak w kompozycie warstwy (G←R, B←G)
+    if channels.rgba[1].is_none() || channels.rgba[2].is_none() {
+        for px in pixels.iter_mut() {
+            if channels.rgba[1].is_none() { px.1 = px.0; }
+            if channels.rgba[2].is_none() { px.2 = px.1; }
+        }
+    }
+
+    Ok((pixels, width as u32, height as u32, layer_name))
+}
+
+/// Prostokąt pikseli pokryty przez blok (względem okna danych)
+#[derive(Clone, Copy)]
+struct BlockArea {
+    x: usize,
+    y: usize,
+    width: usize,
+    height: usize,
+}
+
+/// Skompresowany blok deep: tabela przesunięć i dane próbek
+struct DeepBlock {
+    area: BlockArea,
+    table: Vec<u8>,
+    data: Vec<u8>,
+    data_size: usize,
+}
+
+pub(crate) fn read_i32(read: &mut impl Read) -> ExrResult<i32> {
+    let mut bytes = [0u8; 4];
+    read.read_exact(&mut bytes)?;
+    Ok(i32::from_le_bytes(bytes))
+}
+
+fn read_u64(read: &mut impl Read) -> ExrResult<u64> {
+    let mut bytes = [0u8; 8];
+    read.read_exact(&mut bytes)?;
+    Ok(u64::from_le_bytes(bytes))
+}
+
+pub(crate) fn read_bytes(read: &mut impl Read, len: u64) -> ExrResult<Vec<u8>> {
+    // read_to_end rośnie stopniowo – uszkodzony rozmiar nie zaalokuje od razu gigabajtów
+    let mut out = Vec::new();
+    read.take(len).read_to_end(&mut out)?;
+    if out.len() as u64 != len {
+        return Err(ExrError::CorruptHeader("block truncated".into()));
+    }
+    Ok(out)
+}
+
+/// Czyta blok spod bieżącej pozycji; None dla bloków spoza obrazu lub mniejszych poziomów mip/rip
+fn read_block(read: &mut impl Read, header: &Header, multipart: bool) -> ExrResult<Option<DeepBlock>> {
+    if multipart {
+        read_i32(read)?; // numer części – offsety pochodzą z tabeli tej części
+    }
+    let (width, height) = (header.layer_size.width(), header.layer_size.height());
+    let area = match header.blocks {
+        BlockDescription::ScanLines => {
+            let y = read_i32(read)? - header.own_attributes.layer_position.y();
+            usize::try_from(y).ok().filter(|&y| y < height).map(|y| BlockArea {
+                x: 0,
+                y,
+                width,
+                height: header.compression.scan_lines_per_block().min(height - y),
+            })
+        }
+        BlockDescription::Tiles(tiles) => {
+            let coords = [read_i32(read)?, read_i32(read)?, read_i32(read)?, read_i32(read)?];
+            let x = usize::try_from(coords[0]).unwrap_or(usize::MAX).saturating_mul(tiles.tile_size.width());
+            let y = usize::try_from(coords[1]).unwrap_or(usize::MAX).saturating_mul(tiles.tile_size.height());
+            (coords[2] == 0 && coords[3] == 0 && x < width && y < height).then(|| BlockArea {
+                x,
+                y,
+                width: tiles.tile_size.width().min(width - x),
+                height: tiles.tile_size.height().min(height - y),
+            })
+        }
+    };
+    let Some(area) = area else { return Ok(None); };
+
+    let table_size = read_u64(read)?;
+    let packed_size = read_u64(read)?;
+    let data_size = usize::try_from(read_u64(read)?).map_err(|_| ExrError::CorruptHeader("deep block size".into()))?;
+    let table = read_bytes(read, table_size)?;
+    let data = read_bytes(read, packed_size)?;
+    Ok(Some(DeepBlock { area, table, data, data_size }))
+}
+
+fn flatten_block(header: &Header, channels: &DeepChannels, block: &DeepBlock) -> ExrResult<Vec<Pixel>> {
+    let pixel_count = block.area.width * block.area.height;
+
+    // Tabela przesunięć: skumulowana liczba próbek (i32) dla kolejnych pikseli bloku
+    let table = decompress(header.compression, &block.table, pixel_count * 4, "deep data")?;
+    // Dokładnie jeden wpis na piksel bloku – inaczej wiersze wyszłyby poza prostokąt bloku
+    if table.len() != pixel_count * 4 {
+        return Err(ExrError::CorruptHeader("deep block: sample table does not match block size".into()));
+    }
+    let offsets: Vec<usize> = table
+        .chunks_exact(4)
+        .map(|b| i32::from_le_bytes([b[0], b[1], b[2], b[3]]).max(0) as usize)
+        .collect();
+    let total_samples = offsets.last().copied().unwrap_or(0);
+
+    // Dane próbek: kanał po kanale (kolejność z listy kanałów), w każdym wszystkie próbki bloku
+    let data = decompress(header.compression, &block.data, block.data_size, "deep data")?;
+    let mut channel_data: Vec<(&[u8], SampleType)> = Vec::with_capacity(header.channels.list.len());
+    let mut start = 0usize;
+    for ch in &header.channels.list {
+        let len = total_samples * ch.sample_type.bytes_per_sample();
+        let bytes = data.get(start..start + len).ok_or_else(|| ExrError::CorruptHeader("deep sample data too short".into()))?;
+        channel_data.push((bytes, ch.sample_type));
+        start += len;
+    }
+    let sample = |channel: Option<usize>, index: usize, default: f32| -> f32 {
+        channel.map_or(default, |c| sample_as_f32(channel_data[c].0, channel_data[c].1, index))
+    };
+
+    let mut samples: Vec<(f32, Pixel)> = Vec::new();
+    let mut out = Vec::with_capacity(pixel_count);
+    let mut first = 0usize;
+    for &end in &offsets {
+        let end = end.min(total_samples).max(first);
+        samples.clear();
+        samples.extend((first..end).map(|i| {
+            let [r, g, b, a] = channels.rgba;
+            (sample(channels.z, i, 0.0), (sample(r, i, 0.0), sample(g, i, 0.0), sample(b, i, 0.0), sample(a, i, 1.0)))
+        }));
+        out.push(composite_front_to_back(&mut samples));
+        first = end;
+    }
+    Ok(out)
+}
+
+/// Sortuje próbki po Z i składa je operatorem "over" (próbki deep są premultiplikowane).
+/// Próbki objętościowe (ZBack) traktujemy jak punktowe – to tylko podgląd.
+fn composite_front_to_back(samples: &mut [(f32, Pixel)]) -> Pixel {
+    samples.sort_by(|a, b| a.0.total_cmp(&b.0));
+    let mut acc = (0.0f32, 0.0f32, 0.0f32, 0.0f32);
+    for &(_, (r, g, b, a)) in samples.iter() {
+        let t = 1.0 - acc.3;
+        acc.0 += t * r;
+        acc.1 += t * g;
+        acc.2 += t * b;
+        acc.3 += t * a.clamp(0.0, 1.0);
+        if acc.3 >= 0.9999 { break; }
+    }
+    acc
+}
+
+/// Rozpakowuje tabelę lub dane próbek (`what` – rodzaj danych do komunikatów; także bloki
+/// z kanałami podpróbkowanymi). OpenEXR zapisuje blok bez kompresji, gdy ta nie daje zysku.
+pub(crate) fn decompress(compression: Compression, data: &[u8], expected: usize, what: &str) -> ExrResult<Vec<u8>> {
+    if compression == Compression::Uncompressed || data.len() == expected {
+        if data.len() != expected {
+            return Err(ExrError::CorruptHeader(format!("{} block: unexpected size", what)));
+        }
+        return Ok(data.to_vec());
+    }
+    let bytes = match compression {
+        Compression::RLE => unpack_rle(data, expected, what)?,
+        Compression::ZIP1 | Compression::ZIP16 => miniz_oxide::inflate::decompress_to_vec_zlib_with_limit(data, expected)
+            .map_err(|e| ExrError::CorruptHeader(format!("{} block: {:?}", what, e.status)))?,
+        other => return Err(ExrError::UnsupportedCompression(format!("{} for {}", other, what))),
+    };
+    if bytes.len() != expected {
+        return Err(ExrError::CorruptHeader(format!("{} block: unexpected decompressed size", what)));
+    }
+    Ok(interleave(differences_to_samples(bytes)))
+}
+
+/// RLE z OpenEXR: ujemny licznik = tyle bajtów dosłownie, dodatni = następny bajt powtórzony (n + 1) razy
+fn unpack_rle(mut data: &[u8], expected: usize, what: &str) -> ExrResult<Vec<u8>> {
+    let corrupt = || ExrError::CorruptHeader(format!("{} block: invalid RLE data", what));
+    let mut out = Vec::with_capacity(expected);
+    while let Some((&count, rest)) = data.split_first() {
+        if out.len() >= expected { break; }
+        let count = count as i8 as i32;
+        if count < 0 {
+            let n = (-count) as usize;
+            out.extend_from_slice(rest.get(..n).ok_or_else(corrupt)?);
+            data = &rest[n..];
+        } else {
+            let (&value, rest) = rest.split_first().ok_or_else(corrupt)?;
+            out.resize(out.len() + count as usize + 1, value);
+            data = rest;
+        }
+    }
+    Ok(out)
+}
+
+/// Odwraca predyktor: każdy bajt przechowuje różnicę względem poprzedniego (+128)
+fn differences_to_samples(mut bytes: Vec<u8>) -> Vec<u8> {
+    for i in 1..bytes.len() {
+        bytes[i] = bytes[i - 1].wrapping_add(bytes[i]).wrapping_sub(128);
+    }
+    bytes
+}
+
+/// Scala dwie połówki bufora (bajty parzyste, potem nieparzyste) z powrotem w kolejność naturalną
+fn interleave(bytes: Vec<u8>) -> Vec<u8> {
+    let (first, second) = bytes.split_at(bytes.len().div_ceil(2));
+    let mut out = Vec::with_capacity(bytes.len());
+    for (i, &b) in first.iter().enumerate() {
+        out.push(b);
+        if let Some(&b) = second.get(i) { out.push(b); }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ::exr::meta::attribute::{ChannelDescription, Text};
+
+    fn block(table_entries: usize, samples: usize) -> DeepBlock {
+        let table = (1..=table_entries as i32).flat_map(|n| n.min(samples as i32).to_le_bytes()).collect();
+        DeepBlock { area: BlockArea { x: 0, y: 0, width: 2, height: 1 }, table, data: vec![0; samples * 4], data_size: samples * 4 }
+    }
+
+    #[test]
+    fn block_with_wrong_table_size_is_rejected() {
+        let channels = vec![ChannelDescription::new(Text::from("R"), SampleType::F32, false)];
+        let header = Header::new(Text::from("deep"), (2, 1), channels.into());
+        let deep = DeepChannels { rgba: [Some(0), None, None, None], z: None };
+        assert_eq!(flatten_block(&header, &deep, &block(2, 2)).unwrap().len(), 2);
+        // Tabela dłuższa (więcej wierszy niż blok) i ucięta – błąd zamiast paniki przy składaniu obrazu
+        assert!(matches!(flatten_block(&header, &deep, &block(4, 2)), Err(ExrError::CorruptHeader(_))));
+        assert!(matches!(flatten_block(&header, &deep, &block(1, 2)), Err(ExrError::CorruptHeader(_))));
+        assert!(decompress(Compression::Uncompressed, &[0; 7], 8, "deep data").is_err());
+    }
+}
@@ -0,0 +1,121 @@
+// Skan folderu dla paska miniaturek. Domyślnie tylko wybrany folder; EXRUSTER_SCAN_DEPTH=<n>
+// schodzi do n poziomów podfolderów (przeglądanych równolegle na puli Rayon). Pliki i foldery
+// o nazwach pasujących do wzorców EXRUSTER_SCAN_IGNORE (glob z `*`, oddzielone przecinkami,
+// domyślnie "_tmp,cache") są pomijane. Każdy folder odwiedzany jest raz po kanonizacji ścieżki,
+// więc pętle dowiązań symbolicznych nie zapętlają skanu.
+
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use anyhow::Context;
+use rayon::prelude::*;
+use tracing::warn;
+use crate::cancel::CancelToken;
+use crate::ui_handlers::lock_or_recover;
+use crate::utils::channel_config::glob_match;
+
+/// Zmienna środowiskowa: głębokość skanu podfolderów (0 = bez rekursji)
+pub const SCAN_DEPTH_ENV: &str = "EXRUSTER_SCAN_DEPTH";
+/// Zmienna środowiskowa: wzorce pomijanych nazw, oddzielone przecinkami
+pub const SCAN_IGNORE_ENV: &str = "EXRUSTER_SCAN_IGNORE";
+const DEFAULT_IGNORE: &str = "_tmp,cache";
+
+pub struct ScanOptions {
+    pub max_depth: usize,
+    /// Wzorce małymi literami
+    pub ignore: Vec<String>,
+}
+
+impl ScanOptions {
+    pub fn from_env() -> Self {
+        let max_depth = std::env::var(SCAN_DEPTH_ENV).ok().and_then(|v| v.trim().parse().ok()).unwrap_or(0);
+        let ignore = std::env::var(SCAN_IGNORE_ENV).unwrap_or_else(|_| DEFAULT_IGNORE.to_string());
+        let ignore = ignore.split(',').map(|p| p.trim().to_lowercase()).filter(|p| !p.is_empty()).collect();
+        Self { max_depth, ignore }
+    }
+
+    fn ignored(&self, name: &str) -> bool {
+        let name = name.to_lowercase();
+        self.ignore.iter().any(|p| glob_match(p, &name))
+    }
+}
+
+/// Wynik skanu: pliki EXR (bez plików proxy), ich łączny rozmiar i liczba przejrzanych folderów
+#[derive(Default)]
+pub struct ScanSummary {
+    pub files: Vec<PathBuf>,
+    pub total_bytes: u64,
+    pub folders: usize,
+}
+
+impl ScanSummary {
+    fn merge(mut self, other: ScanSummary) -> ScanSummary {
+        self.files.extend(other.files);
+        self.total_bytes += other.total_bytes;
+        self.folders += other.folders;
+        self
+    }
+}
+
+/// Skanuje `dir`; błąd tylko gdy nie da się odczytać samego folderu lub skan anulowano –
+/// nieczytelne podfoldery są pomijane z ostrzeżeniem
+pub fn scan(dir: &Path, options: &ScanOptions, cancel: &CancelToken) -> anyhow::Result<ScanSummary> {
+    let entries = fs::read_dir(dir)
+        .with_context(|| format!("Nie można odczytać katalogu: {}", dir.display()))?;
+    let visited = Mutex::new(HashSet::from([fs::canonicalize(dir).unwrap_or_else(|_| dir.to_path_buf())]));
+    let summary = scan_entries(entries, 0, options, &visited, cancel);
+    cancel.check()?;
+    Ok(summary)
+}
+
+fn scan_entries(
+    entries: fs::ReadDir,
+    depth: usize,
+    options: &ScanOptions,
+    visited: &Mutex<HashSet<PathBuf>>,
+    cancel: &CancelToken,
+) -> ScanSummary {
+    let mut summary = ScanSummary { folders: 1, ..Default::default() };
+    let mut subdirs = Vec::new();
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if entry.file_name().to_str().is_some_and(|name| options.ignored(name)) {
+            continue;
+        }
+        // fs::metadata podąża za dowiązaniami – dowiązany folder skanujemy jak zwykły
+        let Ok(meta) = fs::metadata(&path) else { continue };
+        if meta.is_dir() {
+            if depth < options.max_depth {
+                subdirs.push(path);
+            }
+        } else if path.extension().and_then(|e| e.to_str()).is_some_and(|e| e.eq_ignore_ascii_case("exr"))
+            // Pliki proxy są tylko zastępstwem oryginałów – nie dublujemy ich na liście
+            && !crate::proxy_files::is_proxy(&path)
+        {
+            summary.total_bytes += meta.len();
+            summary.files.push(path);
+        }
+    }
+
+    subdirs
+        .into_par_iter()
+        .filter_map(|sub| {
+            if cancel.is_cancelled() {
+                return None;
+            }
+            let canonical = fs::canonicalize(&sub).unwrap_or_else(|_| sub.clone());
+            if !lock_or_recover(visited).insert(canonical) {
+                return None;
+            }
+            match fs::read_dir(&sub) {
+                Ok(entries) => Some(scan_entries(entries, depth + 1, options, visited, cancel)),
+                Err(e) => {
+                    warn!(target: "io", "skipping folder {}: {}", sub.display(), e);
+                    None
+                }
+            }
+        })
+        .reduce(ScanSummary::default, ScanSummary::merge)
+        .merge(summary)
+}
@@ -0,0 +1,216 @@
+// Filtry podglądu na obrazie wyświetlanym (RGBA8 po tone mappingu i gammie): wyostrzanie maską
+// nieostrą dla miękkich renderów oglądanych w dopasowaniu do okna. Działają na granicy UI
+// (`display_profile::for_display`), więc nie zmieniają pamięci podręcznej renderów, histogramu ani
+// próbnika koloru. Eksport obrazu i wideo dostaje filtr tylko przy jawnie włączonym "Apply to exports"
+// (`RenderSettings::for_export`).
+//
+// Wyjątkiem jest bloom: poświata świateł liczona w wartościach sceny przed tone mappingiem
+// (`ImageCache` renderuje wtedy z kopii pikseli z dodaną poświatą). Przy "Display only" eksport
+// jej nie zawiera.
+
+use rayon::prelude::*;
+use crate::raw_image::RawImage;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Sharpen {
+    /// Siła: 1.0 = różnica względem rozmycia dodana w całości
+    pub amount: f32,
+    /// Sigma rozmycia Gaussa w pikselach obrazu wyświetlanego
+    pub radius: f32,
+    pub in_exports: bool,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Bloom {
+    /// Próg luminancji sceny (po ekspozycji; 1.0 = biel wyświetlacza)
+    pub threshold: f32,
+    /// Mnożnik poświaty dodawanej do obrazu
+    pub intensity: f32,
+    /// Tylko podgląd – eksport bez poświaty
+    pub display_only: bool,
+}
+
+/// Wszystkie włączone filtry (podgląd)
+pub fn apply_to_display(image: &mut RawImage, sharpen: Option<Sharpen>) {
+    if let Some(s) = sharpen {
+        unsharp_mask(image, s.amount, s.radius);
+    }
+}
+
+/// Jądro Gaussa o promieniu 3σ, znormalizowane
+fn gaussian_kernel(sigma: f32) -> Vec<f32> {
+    let radius = (sigma * 3.0).ceil().max(1.0) as i32;
+    let weights: Vec<f32> = (-radius..=radius).map(|i| (-(i * i) as f32 / (2.0 * sigma * sigma)).exp()).collect();
+    let sum: f32 = weights.iter().sum();
+    weights.into_iter().map(|w| w / sum).collect()
+}
+
+/// Rozdzielne rozmycie Gaussa obrazu o `channels` wartościach f32 na piksel (krawędzie powielone).
+/// Oba przebiegi sumują całe wiersze naraz – pętle bez rozgałęzień kompilator wektoryzuje.
+pub fn gaussian_blur(data: &[f32], channels: usize, width: usize, height: usize, sigma: f32) -> Vec<f32> {
+    let kernel = gaussian_kernel(sigma.max(0.1));
+    let radius = (kernel.len() / 2) as isize;
+    let stride = width * channels;
+    let clamp = |i: isize, len: usize| i.clamp(0, len as isize - 1) as usize;
+
+    // Poziomo: wiersz z dopełnionymi krawędziami, potem suma przesuniętych wycinków
+    let mut horizontal = vec![0.0f32; data.len()];
+    horizontal.par_chunks_mut(stride).zip(data.par_chunks(stride)).for_each(|(out, row)| {
+        let padded: Vec<f32> = (-radius..width as isize + radius)
+            .flat_map(|x| row[clamp(x, width) * channels..][..channels].iter().copied())
+            .collect();
+        for (k, &w) in kernel.iter().enumerate() {
+            for (o, &v) in out.iter_mut().zip(&padded[k * channels..]) {
+                *o += w * v;
+            }
+        }
+    });
+
+    let mut out = vec![0.0f32; data.len()];
+    out.par_chunks_mut(stride).enumerate().for_each(|(y, row)| {
+        for (k, &w) in kernel.iter().enumerate() {
+            let source = &horizontal[clamp(y as isize + k as isize - radius, height) * stride..][..stride];
+            for (o, &v) in row.iter_mut().zip(source) {
+                *o += w * v;
+            }
+        }
+    });
+    out
+}
+
+/// Maska nieostra na RGB (wartości zakodowane, alfa bez zmian): v + amount · (v − rozmycie)
+pub fn unsharp_mask(image: &mut RawImage, amount: f32, radius: f32) {
+    let (width, height) = (image.width as usize, image.height as usize);
+    if width == 0 || height == 0 || amount <= 0.0 {
+        return;
+    }
+    let rgb: Vec<f32> = image.pixels.chunks_exact(4).flat_map(|p| [p[0] as f32, p[1] as f32, p[2] as f32]).collect();
+    let blurred = gaussian_blur(&rgb, 3, width, height, radius);
+    image.pixels.par_chunks_exact_mut(4).zip(rgb.par_chunks_exact(3).zip(blurred.par_chunks_exact(3))).for_each(|(px, (src, blur))| {
+        for c in 0..3 {
+            px[c] = (src[c] + amount * (src[c] - blur[c])).round().clamp(0.0, 255.0) as u8;
+        }
+    });
+}
+
+/// Obraz w zmniejszonej rozdzielczości (średnie z bloków `factor` × `factor`) do szerokich rozmyć;
+/// odczyt z interpolacją dwuliniową we współrzędnych pełnego obrazu
+pub struct LowRes {
+    pub data: Vec<f32>,
+    channels: usize,
+    width: usize,
+    height: usize,
+    factor: usize,
+}
+
+impl LowRes {
+    /// Średnie wartości `f(piksel)` tak, by dłuższy bok miał najwyżej `max_size` px
+    pub fn average<const C: usize>(pixels: &[(f32, f32, f32, f32)], width: usize, height: usize, max_size: usize, f: impl Fn((f32, f32, f32, f32)) -> [f32; C] + Sync) -> Self {
+        let factor = width.max(height).div_ceil(max_size).max(1);
+        let (low_w, low_h) = (width.div_ceil(factor), height.div_ceil(factor));
+        let mut data = vec![0.0f32; low_w * low_h * C];
+        data.par_chunks_mut(low_w * C).enumerate().for_each(|(ly, row)| {
+            let ys = ly * factor..((ly + 1) * factor).min(height);
+            for (lx, out) in row.chunks_exact_mut(C).enumerate() {
+                let xs = lx * factor..((lx + 1) * factor).min(width);
+                let count = (ys.len() * xs.len()) as f32;
+                for y in ys.clone() {
+                    for &px in &pixels[y * width + xs.start..y * width + xs.end] {
+                        for (o, v) in out.iter_mut().zip(f(px)) {
+                            *o += v / count;
+                        }
+                    }
+                }
+            }
+        });
+        LowRes { data, channels: C, width: low_w, height: low_h, factor }
+    }
+
+    /// Rozmycie Gaussa o sigmie podanej jako ułamek dłuższego boku
+    pub fn blur(&mut self, sigma_fraction: f32) {
+        let sigma = sigma_fraction * self.width.max(self.height) as f32;
+        self.data = gaussian_blur(&self.data, self.channels, self.width, self.height, sigma);
+    }
+
+    /// Wartość kanału `c` w pikselu (x, y) pełnego obrazu
+    pub fn at(&self, x: usize, y: usize, c: usize) -> f32 {
+        let coord = |v: usize, len: usize| ((v as f32 + 0.5) / self.factor as f32 - 0.5).clamp(0.0, (len - 1) as f32);
+        let (fx, fy) = (coord(x, self.width), coord(y, self.height));
+        let (x0, y0) = (fx as usize, fy as usize);
+        let (x1, y1) = ((x0 + 1).min(self.width - 1), (y0 + 1).min(self.height - 1));
+        let (tx, ty) = (fx.fract(), fy.fract());
+        let v = |x: usize, y: usize| self.data[(y * self.width + x) * self.channels + c];
+        let top = v(x0, y0) * (1.0 - tx) + v(x1, y0) * tx;
+        let bottom = v(x0, y1) * (1.0 - tx) + v(x1, y1) * tx;
+        top * (1.0 - ty) + bottom * ty
+    }
+}
+
+/// Dłuższy bok bufora poświaty
+const BLOOM_RESOLUTION: usize = 512;
+/// Sigma rozmycia poświaty jako ułamek dłuższego boku obrazu
+const BLOOM_SIGMA: f32 = 0.015;
+
+/// Piksele z dodaną poświatą: część jasności ponad próg (w skali sceny, czyli po `scene_multiplier`)
+/// uśredniona do małego bufora, rozmyta i dodana z powrotem. Barwa świateł zostaje zachowana,
+/// alfa bez zmian; wynik w jednostkach wejścia.
+pub fn add_bloom(pixels: &[(f32, f32, f32, f32)], width: usize, height: usize, scene_multiplier: f32, bloom: &Bloom) -> Vec<(f32, f32, f32, f32)> {
+    if width == 0 || height == 0 || bloom.intensity <= 0.0 {
+        return pixels.to_vec();
+    }
+    let mut glow = LowRes::average(pixels, width, height, BLOOM_RESOLUTION, |(r, g, b, _)| {
+        let scene = (0.2126 * r + 0.7152 * g + 0.0722 * b) * scene_multiplier;
+        if scene.is_finite() && scene > bloom.threshold && r >= 0.0 && g >= 0.0 && b >= 0.0 {
+            let weight = (scene - bloom.threshold) / scene;
+            [r * weight, g * weight, b * weight]
+        } else {
+            [0.0; 3]
+        }
+    });
+    glow.blur(BLOOM_SIGMA);
+
+    let mut out = pixels.to_vec();
+    out.par_chunks_mut(width).enumerate().for_each(|(y, row)| {
+        for (x, px) in row.iter_mut().enumerate() {
+            let value = |c: usize| glow.at(x, y, c) * bloom.intensity;
+            *px = (px.0 + value(0), px.1 + value(1), px.2 + value(2), px.3);
+        }
+    });
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unsharp_mask_keeps_flat_areas_and_boosts_edges() {
+        let flat = vec![0.5f32; 7 * 5 * 2];
+        assert!(gaussian_blur(&flat, 2, 7, 5, 1.5).iter().all(|v| (v - 0.5).abs() < 1e-5));
+
+        // Pionowa krawędź 64 | 192: po wyostrzeniu ciemna strona ciemnieje, jasna jaśnieje
+        let (width, height) = (8u32, 4u32);
+        let pixels = (0..width * height).flat_map(|i| { let v = if i % width < 4 { 64 } else { 192 }; [v, v, v, 255] }).collect();
+        let mut image = RawImage { width, height, pixels };
+        unsharp_mask(&mut image, 1.0, 1.0);
+        let at = |x: usize| image.pixels[x * 4];
+        assert!(at(3) < 64 && at(4) > 192, "{} {}", at(3), at(4));
+        assert_eq!((at(0), at(7), image.pixels[3]), (64, 192, 255));
+    }
+
+    #[test]
+    fn bloom_spreads_only_highlights() {
+        let (width, height) = (33, 33);
+        let mut pixels = vec![(0.2f32, 0.2, 0.2, 1.0); width * height];
+        let bloom = Bloom { threshold: 1.0, intensity: 1.0, display_only: true };
+        assert_eq!(add_bloom(&pixels, width, height, 1.0, &bloom), pixels);
+
+        pixels[16 * width + 16] = (50.0, 50.0, 50.0, 1.0);
+        let out = add_bloom(&pixels, width, height, 1.0, &bloom);
+        let near = out[16 * width + 17];
+        assert!(near.0 > 0.2 && near.0 == near.2 && near.3 == 1.0, "{:?}", near);
+        assert!(out[0].0 < near.0);
+        // Po obniżeniu ekspozycji to samo światło jest poniżej progu
+        assert_eq!(add_bloom(&pixels, width, height, 0.01, &bloom), pixels);
+    }
+}
@@ -0,0 +1,242 @@
+// Profil ICC monitora jako ostatnia transformacja wyświetlania: podgląd (sRGB) → liniowe sRGB →
+// XYZ (D50) → liniowe RGB monitora → krzywe TRC monitora. Obsługiwane są profile macierzowe
+// (rXYZ/gXYZ/bXYZ + rTRC/gTRC/bTRC), czyli praktycznie wszystkie profile z kalibratorów;
+// profile oparte na tablicach LUT są zgłaszane jako nieobsługiwane.
+
+use std::path::Path;
+use slint::Image;
+use crate::display_filters::Sharpen;
+use crate::raw_image::RawImage;
+
+/// Ustawienie środowiskowe wskazujące plik profilu (ma pierwszeństwo przed zapytaniem systemu)
+pub const ICC_PROFILE_ENV: &str = "EXRUSTER_ICC_PROFILE";
+
+/// Rozdzielczość odwróconych krzywych TRC (wejście liniowe 0..1)
+const INVERSE_TRC_SIZE: usize = 4096;
+
+/// sRGB (D65) → XYZ D50, z adaptacją Bradforda (przestrzeń połączeń profili ICC)
+const SRGB_TO_XYZ_D50: [[f32; 3]; 3] = [
+    [0.436_074_7, 0.385_064_9, 0.143_080_4],
+    [0.222_504_5, 0.716_878_6, 0.060_616_9],
+    [0.013_932_2, 0.097_104_5, 0.714_173_3],
+];
+
+pub struct DisplayProfile {
+    pub description: String,
+    /// Liniowe sRGB → liniowe RGB monitora
+    to_monitor: [[f32; 3]; 3],
+    /// Dekodowanie 8-bit sRGB do wartości liniowych
+    srgb_decode: [f32; 256],
+    /// Odwrócone krzywe TRC monitora: liniowe → 8-bit
+    inverse_trc: [Vec<u8>; 3],
+}
+
+/// Obraz do pokazania w oknie: z filtrami podglądu i profilem monitora, jeśli aktywne
+/// (granica UI: tu obraz przetwarzania staje się obrazem Slint; ustawienia z `AppContext::for_display`)
+pub fn for_display(mut image: RawImage, sharpen: Option<Sharpen>, profile: Option<&DisplayProfile>) -> Image {
+    crate::display_filters::apply_to_display(&mut image, sharpen);
+    if let Some(profile) = profile {
+        profile.apply(&mut image.pixels);
+    }
+    image.to_slint_image()
+}
+
+impl DisplayProfile {
+    /// Przelicza piksele RGBA8 (4 bajty na piksel, alfa bez zmian)
+    pub fn apply(&self, pixels: &mut [u8]) {
+        use rayon::prelude::*;
+        let m = &self.to_monitor;
+        pixels.par_chunks_exact_mut(4).for_each(|px| {
+            let (r, g, b) = (self.srgb_decode[px[0] as usize], self.srgb_decode[px[1] as usize], self.srgb_decode[px[2] as usize]);
+            let encode = |channel: usize, v: f32| {
+                let table = &self.inverse_trc[channel];
+                table[((v.clamp(0.0, 1.0) * (INVERSE_TRC_SIZE - 1) as f32).round()) as usize]
+            };
+            px[0] = encode(0, m[0][0] * r + m[0][1] * g + m[0][2] * b);
+            px[1] = encode(1, m[1][0] * r + m[1][1] * g + m[1][2] * b);
+            px[2] = encode(2, m[2][0] * r + m[2][1] * g + m[2][2] * b);
+        });
+    }
+
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let data = std::fs::read(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        let mut profile = Self::parse(&data)?;
+        if profile.description.is_empty() {
+            profile.description = path.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+        }
+        Ok(profile)
+    }
+
+    pub fn parse(data: &[u8]) -> Result<Self, String> {
+        if data.len() < 132 || &data[36..40] != b"acsp" {
+            return Err("not an ICC profile".into());
+        }
+        if &data[16..20] != b"RGB " {
+            return Err("not an RGB display profile".into());
+        }
+        let tags = tag_table(data);
+        let tag = |sig: &[u8; 4]| tags.iter().find(|(s, _)| s == sig).map(|&(_, slice)| slice);
+
+        let mut columns = [[0.0f32; 3]; 3];
+        for (column, sig) in columns.iter_mut().zip([b"rXYZ", b"gXYZ", b"bXYZ"]) {
+            *column = tag(sig).and_then(read_xyz).ok_or("LUT-based profiles are not supported (no colorant tags)")?;
+        }
+        // Kolumny macierzy RGB monitora → XYZ D50
+        let monitor_to_xyz = [
+            [columns[0][0], columns[1][0], columns[2][0]],
+            [columns[0][1], columns[1][1], columns[2][1]],
+            [columns[0][2], columns[1][2], columns[2][2]],
+        ];
+        let xyz_to_monitor = invert(&monitor_to_xyz).ok_or("singular colorant matrix")?;
+
+        let mut inverse_trc: [Vec<u8>; 3] = Default::default();
+        for (table, sig) in inverse_trc.iter_mut().zip([b"rTRC", b"gTRC", b"bTRC"]) {
+            let curve = tag(sig).and_then(read_curve).ok_or("missing or unsupported TRC curve")?;
+            *table = invert_curve(&curve);
+        }
+
+        let srgb_decode: [f32; 256] = std::array::from_fn(|i| {
+            let v = i as f32 / 255.0;
+            if v <= 0.040_45 { v / 12.92 } else { ((v + 0.055) / 1.055).powf(2.4) }
+        });
+        Ok(DisplayProfile {
+            description: tag(b"desc").and_then(read_description).unwrap_or_default(),
+            to_monitor: multiply(&xyz_to_monitor, &SRGB_TO_XYZ_D50),
+            srgb_decode,
+            inverse_trc,
+        })
+    }
+}
+
+fn be_u32(data: &[u8], at: usize) -> Option<u32> {
+    data.get(at..at + 4).map(|b| u32::from_be_bytes([b[0], b[1], b[2], b[3]]))
+}
+
+fn be_u16(data: &[u8], at: usize) -> Option<u16> {
+    data.get(at..at + 2).map(|b| u16::from_be_bytes([b[0], b[1]]))
+}
+
+fn s15_fixed16(data: &[u8], at: usize) -> Option<f32> {
+    be_u32(data, at).map(|v| v as i32 as f32 / 65536.0)
+}
+
+/// Sygnatury tagów z wycinkami danych (tagi wskazujące poza plik są pomijane)
+fn tag_table(data: &[u8]) -> Vec<([u8; 4], &[u8])> {
+    let count = be_u32(data, 128).unwrap_or(0) as usize;
+    (0..count.min(1024))
+        .filter_map(|i| {
+            let entry = 132 + i * 12;
+            let sig: [u8; 4] = data.get(entry..entry + 4)?.try_into().ok()?;
+            let offset = be_u32(data, entry + 4)? as usize;
+            let size = be_u32(data, entry + 8)? as usize;
+            Some((sig, data.get(offset..offset.checked_add(size)?)?))
+        })
+        .collect()
+}
+
+fn read_xyz(tag: &[u8]) -> Option<[f32; 3]> {
+    if tag.get(0..4)? != b"XYZ " { return None; }
+    Some([s15_fixed16(tag, 8)?, s15_fixed16(tag, 12)?, s15_fixed16(tag, 16)?])
+}
+
+/// Krzywa TRC próbkowana w INVERSE_TRC_SIZE punktach (wejście zakodowane 0..1 → liniowe)
+fn read_curve(tag: &[u8]) -> Option<Vec<f32>> {
+    let sample = |f: &dyn Fn(f32) -> f32| -> Vec<f32> {
+        (0..INVERSE_TRC_SIZE).map(|i| f(i as f32 / (INVERSE_TRC_SIZE - 1) as f32).clamp(0.0, 1.0)).collect()
+    };
+    match tag.get(0..4)? {
+        b"curv" => {
+            let count = be_u32(tag, 8)? as usize;
+            match count {
+                0 => Some(sample(&|x| x)),
+                1 => {
+                    let gamma = be_u16(tag, 12)? as f32 / 256.0;
+                    Some(sample(&|x| x.powf(gamma)))
+                }
+                _ => {
+                    let table: Vec<f32> = (0..count).map(|i| be_u16(tag, 12 + i * 2).map(|v| v as f32 / 65535.0)).collect::<Option<_>>()?;
+                    // Interpolacja liniowa między wpisami tablicy
+                    Some(sample(&|x| {
+                        let pos = x * (count - 1) as f32;
+                        let i = (pos as usize).min(count - 2);
+                        let t = pos - i as f32;
+                        table[i] * (1.0 - t) + table[i + 1] * t
+                    }))
+                }
+            }
+        }
+        b"para" => {
+            let function = be_u16(tag, 8)?;
+            let param_count = [1, 3, 4, 5, 7].get(function as usize).copied()?;
+            let p: Vec<f32> = (0..param_count).map(|i| s15_fixed16(tag, 12 + i * 4)).collect::<Option<_>>()?;
+            let g = p[0];
+            Some(match function {
+                0 => sample(&|x| x.powf(g)),
+                1 => sample(&|x| if x >= -p[2] / p[1] { (p[1] * x + p[2]).max(0.0).powf(g) } else { 0.0 }),
+                2 => sample(&|x| if x >= -p[2] / p[1] { (p[1] * x + p[2]).max(0.0).powf(g) + p[3] } else { p[3] }),
+                3 => sample(&|x| if x >= p[4] { (p[1] * x + p[2]).max(0.0).powf(g) } else { p[3] * x }),
+                _ => sample(&|x| if x >= p[4] { (p[1] * x + p[2]).max(0.0).powf(g) + p[5] } else { p[3] * x + p[6] }),
+            })
+        }
+        _ => None,
+    }
+}
+
+/// Odwrócenie monotonicznej krzywej: dla każdej wartości liniowej najbliższe wejście zakodowane (8-bit)
+fn invert_curve(curve: &[f32]) -> Vec<u8> {
+    let last = (curve.len() - 1) as f32;
+    (0..INVERSE_TRC_SIZE)
+        .map(|i| {
+            let target = i as f32 / (INVERSE_TRC_SIZE - 1) as f32;
+            let index = curve.partition_point(|&v| v < target).min(curve.len() - 1);
+            (index as f32 / last * 255.0).round() as u8
+        })
+        .collect()
+}
+
+/// Opis profilu: v2 `desc` (ASCII) albo v4 `mluc` (pierwszy rekord, UTF-16BE)
+fn read_description(tag: &[u8]) -> Option<String> {
+    match tag.get(0..4)? {
+        b"desc" => {
+            let len = be_u32(tag, 8)? as usize;
+            let text = tag.get(12..12 + len)?;
+            Some(String::from_utf8_lossy(text).trim_end_matches('\0').to_string())
+        }
+        b"mluc" => {
+            let len = be_u32(tag, 20)? as usize;
+            let offset = be_u32(tag, 24)? as usize;
+            let units: Vec<u16> = tag.get(offset..offset + len)?.chunks_exact(2).map(|c| u16::from_be_bytes([c[0], c[1]])).collect();
+            Some(String::from_utf16_lossy(&units).trim_end_matches('\0').to_string())
+        }
+        _ => None,
+    }
+}
+
+fn multiply(a: &[[f32; 3]; 3], b: &[[f32; 3]; 3]) -> [[f32; 3]; 3] {
+    std::array::from_fn(|r| std::array::from_fn(|c| (0..3).map(|k| a[r][k] * b[k][c]).sum()))
+}
+
+fn invert(m: &[[f32; 3]; 3]) -> Option<[[f32; 3]; 3]> {
+    let det = m[0][0] * (m[1][1] * m[2][2] - m[1][2] * m[2][1])
+        - m[0][1] * (m[1][0] * m[2][2] - m[1][2] * m[2][0])
+        + m[0][2] * (m[1][0] * m[2][1] - m[1][1] * m[2][0]);
+    if det.abs() < 1e-9 { return None; }
+    let inv = 1.0 / det;
+    Some([
+        [
+            (m[1][1] * m[2][2] - m[1][2] * m[2][1]) * inv,
+            (m[0][2] * m[2][1] - m[0][1] * m[2][2]) * inv,
+            (m[0][1] * m[1][2] - m[0][2] * m[1][1]) * inv,
+        ],
+        [
+            (m[1][2] * m[2][0] - m[1][0] * m[2][2]) * inv,
+            (m[0][0] * m[2][2] - m[0][2] * m[2][0]) * inv,
+            (m[0][2] * m[1][0] - m[0][0] * m[1][2]) * inv,
+        ],
+        [
+            (m[1][0] * m[2][1] - m[1][1] * m[2][0]) * inv,
+            (m[0][1] * m[2][0] - m[0][0] * m[2][1]) * inv,
+            (m[0][0] * m[1][1] - m[0][1] * m[1][0]) * inv,
+        ],
+    ])
+}
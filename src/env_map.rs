@@ -0,0 +1,214 @@
+// Podgląd map otoczenia (atrybut `envmap` nagłówka): lat-long albo sześć ścian sześcianu ułożonych
+// pionowo (+X, -X, +Y, -Y, +Z, -Z) wg konwencji OpenEXR (ImfEnvmap). Widok panoramy (kamera
+// perspektywiczna z odchyleniem/pochyleniem i polem widzenia) albo rozwinięta ściana sześcianu to
+// przeprojektowanie pikseli sceny w `ImageCache` – dalej obraz idzie zwykłym pipeline'em wyświetlania.
+
+use std::f32::consts::PI;
+use std::hash::{Hash, Hasher};
+use ::exr::meta::attribute::EnvironmentMap;
+use ::exr::meta::header::Header;
+use rayon::prelude::*;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum EnvMapKind {
+    LatLong,
+    Cube,
+}
+
+/// Rodzaj mapy otoczenia z pierwszego nagłówka, który ją deklaruje
+pub fn detect(headers: &[Header]) -> Option<EnvMapKind> {
+    headers.iter().find_map(|h| h.own_attributes.environment_map).map(|map| match map {
+        EnvironmentMap::LatitudeLongitude => EnvMapKind::LatLong,
+        EnvironmentMap::Cube => EnvMapKind::Cube,
+    })
+}
+
+/// Sposób oglądania mapy; kąty w stopniach
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum EnvView {
+    /// Obraz pliku bez przeprojektowania
+    Flat,
+    Panorama { yaw: f32, pitch: f32, fov: f32 },
+    /// Ściana sześcianu widziana ze środka (0..6 = +X, -X, +Y, -Y, +Z, -Z)
+    Face(u8),
+}
+
+impl Hash for EnvView {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        match *self {
+            EnvView::Flat => 0u8.hash(state),
+            EnvView::Panorama { yaw, pitch, fov } => (1u8, yaw.to_bits(), pitch.to_bits(), fov.to_bits()).hash(state),
+            EnvView::Face(face) => (2u8, face).hash(state),
+        }
+    }
+}
+
+/// Etykiety ścian w UI (kolejność jak w `EnvView::Face`)
+pub const FACE_LABELS: [&str; 6] = ["+X", "-X", "+Y", "-Y", "+Z", "-Z"];
+
+impl EnvView {
+    /// Tryb z etykiety listy w UI ("Flat", "Panorama" albo etykieta ściany)
+    pub fn from_label(label: &str, yaw: f32, pitch: f32, fov: f32) -> Self {
+        match label {
+            "Panorama" => EnvView::Panorama { yaw, pitch: pitch.clamp(-89.0, 89.0), fov: fov.clamp(10.0, 160.0) },
+            _ => FACE_LABELS.iter().position(|f| *f == label).map_or(EnvView::Flat, |face| EnvView::Face(face as u8)),
+        }
+    }
+
+    /// Etykieta listy w UI (odwrotność `from_label`)
+    pub fn label(self) -> &'static str {
+        match self {
+            EnvView::Flat => "Flat",
+            EnvView::Panorama { .. } => "Panorama",
+            EnvView::Face(face) => FACE_LABELS[face as usize % FACE_LABELS.len()],
+        }
+    }
+}
+
+impl EnvMapKind {
+    pub fn label(self) -> &'static str {
+        match self {
+            EnvMapKind::LatLong => "Lat-long",
+            EnvMapKind::Cube => "Cube",
+        }
+    }
+}
+
+type Pixel = (f32, f32, f32, f32);
+type Vec3 = [f32; 3];
+
+fn cross(a: Vec3, b: Vec3) -> Vec3 {
+    [a[1] * b[2] - a[2] * b[1], a[2] * b[0] - a[0] * b[2], a[0] * b[1] - a[1] * b[0]]
+}
+
+/// Bok ściany mapy sześciennej
+fn face_size(width: usize, height: usize) -> usize {
+    width.min(height / 6)
+}
+
+/// Indeks piksela mapy w kierunku `dir` (najbliższy sąsiad)
+fn sample_index(kind: EnvMapKind, dir: Vec3, width: usize, height: usize) -> usize {
+    let [x, y, z] = dir;
+    let (px, py) = match kind {
+        EnvMapKind::LatLong => {
+            let length = (x * x + y * y + z * z).sqrt().max(f32::MIN_POSITIVE);
+            let latitude = (y / length).clamp(-1.0, 1.0).asin();
+            let longitude = if x == 0.0 && z == 0.0 { 0.0 } else { x.atan2(z) };
+            ((longitude / (-2.0 * PI) + 0.5) * (width - 1) as f32, (latitude / -PI + 0.5) * (height - 1) as f32)
+        }
+        EnvMapKind::Cube => {
+            let size = face_size(width, height).max(1);
+            let last = (size - 1) as f32;
+            let (ax, ay, az) = (x.abs(), y.abs(), z.abs());
+            // Ściana i pozycja na niej (`faceAndPixelPosition`), potem położenie w pliku (`pixelPosition`)
+            let (face, u, v) = if ax >= ay && ax >= az {
+                let m = ax.max(f32::MIN_POSITIVE);
+                (if x > 0.0 { 0 } else { 1 }, y / m, z / m)
+            } else if ay >= az {
+                (if y > 0.0 { 2 } else { 3 }, x / ay, z / ay)
+            } else {
+                (if z > 0.0 { 4 } else { 5 }, x / az, y / az)
+            };
+            let (u, v) = ((u + 1.0) * 0.5 * last, (v + 1.0) * 0.5 * last);
+            let (fx, fy) = match face {
+                0 => (v, last - u),
+                1 => (last - v, last - u),
+                2 => (u, last - v),
+                3 => (u, v),
+                4 => (last - u, last - v),
+                _ => (u, last - v),
+            };
+            (fx, fy + (face * size) as f32)
+        }
+    };
+    let px = (px.round().max(0.0) as usize).min(width - 1);
+    let py = (py.round().max(0.0) as usize).min(height - 1);
+    py * width + px
+}
+
+/// Kamera widoku: kierunek patrzenia i wektor "w górę"
+fn camera(view: EnvView) -> Option<(Vec3, Vec3, f32)> {
+    match view {
+        EnvView::Flat => None,
+        EnvView::Panorama { yaw, pitch, fov } => {
+            let (yaw, pitch) = (yaw.to_radians(), pitch.to_radians());
+            Some(([-yaw.sin() * pitch.cos(), pitch.sin(), yaw.cos() * pitch.cos()], [0.0, 1.0, 0.0], fov))
+        }
+        EnvView::Face(face) => {
+            let (forward, up) = match face {
+                0 => ([1.0, 0.0, 0.0], [0.0, 1.0, 0.0]),
+                1 => ([-1.0, 0.0, 0.0], [0.0, 1.0, 0.0]),
+                2 => ([0.0, 1.0, 0.0], [0.0, 0.0, -1.0]),
+                3 => ([0.0, -1.0, 0.0], [0.0, 0.0, 1.0]),
+                4 => ([0.0, 0.0, 1.0], [0.0, 1.0, 0.0]),
+                _ => ([0.0, 0.0, -1.0], [0.0, 1.0, 0.0]),
+            };
+            Some((forward, up, 90.0))
+        }
+    }
+}
+
+/// Rozmiar obrazu widoku: panorama 2:1 o szerokości mapy lat-long (cztery ściany sześcianu),
+/// ściana – kwadrat o boku ćwierci obwodu
+fn output_size(kind: EnvMapKind, view: EnvView, width: usize, height: usize) -> (usize, usize) {
+    let circumference = match kind {
+        EnvMapKind::LatLong => width,
+        EnvMapKind::Cube => 4 * face_size(width, height),
+    };
+    match view {
+        EnvView::Face(_) => ((circumference / 4).max(1), (circumference / 4).max(1)),
+        _ => (circumference.max(2), (circumference / 2).max(1)),
+    }
+}
+
+/// Piksele widoku `view` mapy `kind`; None dla widoku płaskiego albo pustej mapy
+pub fn reproject(pixels: &[Pixel], width: usize, height: usize, kind: EnvMapKind, view: EnvView) -> Option<(Vec<Pixel>, usize, usize)> {
+    let (forward, world_up, fov) = camera(view)?;
+    if width == 0 || height == 0 || (kind == EnvMapKind::Cube && face_size(width, height) == 0) {
+        return None;
+    }
+    let right = cross(forward, world_up);
+    let right_length = (right[0] * right[0] + right[1] * right[1] + right[2] * right[2]).sqrt();
+    let right = right.map(|c| c / right_length);
+    let up = cross(right, forward);
+    let (out_w, out_h) = output_size(kind, view, width, height);
+    let half_width = (fov.to_radians() * 0.5).tan();
+    let half_height = half_width * out_h as f32 / out_w as f32;
+    let mut out = vec![(0.0, 0.0, 0.0, 0.0); out_w * out_h];
+    out.par_chunks_mut(out_w).enumerate().for_each(|(y, row)| {
+        let ny = (1.0 - 2.0 * (y as f32 + 0.5) / out_h as f32) * half_height;
+        for (x, px) in row.iter_mut().enumerate() {
+            let nx = (2.0 * (x as f32 + 0.5) / out_w as f32 - 1.0) * half_width;
+            let dir = [0, 1, 2].map(|i| forward[i] + nx * right[i] + ny * up[i]);
+            *px = pixels[sample_index(kind, dir, width, height)];
+        }
+    });
+    Some((out, out_w, out_h))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn faces_sample_matching_map_regions() {
+        // Mapa sześcienna 2×12: każda ściana wypełniona własnym numerem
+        let (width, height) = (2, 12);
+        let cube: Vec<Pixel> = (0..width * height).map(|i| ((i / (width * 2)) as f32, 0.0, 0.0, 1.0)).collect();
+        for face in 0..6u8 {
+            let (pixels, w, h) = reproject(&cube, width, height, EnvMapKind::Cube, EnvView::Face(face)).unwrap();
+            assert_eq!((w, h), (2, 2));
+            assert!(pixels.iter().all(|p| p.0 == face as f32), "face {}: {:?}", face, pixels);
+        }
+
+        // Lat-long 8×4: górna połowa jasna; ściana +Y widzi tylko górę, panorama na wprost obie połowy
+        let (width, height) = (8, 4);
+        let latlong: Vec<Pixel> = (0..width * height).map(|i| if i / width < 2 { (1.0, 1.0, 1.0, 1.0) } else { (0.0, 0.0, 0.0, 1.0) }).collect();
+        let (top, _, _) = reproject(&latlong, width, height, EnvMapKind::LatLong, EnvView::from_label("+Y", 0.0, 0.0, 90.0)).unwrap();
+        assert!(top.iter().all(|p| p.0 == 1.0));
+        let (front, w, h) = reproject(&latlong, width, height, EnvMapKind::LatLong, EnvView::from_label("Panorama", 0.0, 0.0, 90.0)).unwrap();
+        assert_eq!((w, h), (8, 4));
+        assert_eq!((front[0].0, front[w * h - 1].0), (1.0, 0.0));
+        assert!(reproject(&latlong, width, height, EnvMapKind::LatLong, EnvView::Flat).is_none());
+    }
+}
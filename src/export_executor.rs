@@ -0,0 +1,47 @@
+// Wykonawca eksportów: praca w pełnej rozdzielczości na puli ciężkich zadań (`worker_threads`),
+// oddzielnej od globalnej puli podglądu, więc eksport dużego pliku nie blokuje suwaków. Każde zadanie ma własny kanał
+// postępu; przekaźnik przenosi komunikaty do zadania w rejestrze postępu (lista zadań w UI).
+// Zadania zleca `export_queue`, który pilnuje limitu równoległych eksportów.
+
+use std::sync::mpsc;
+use crate::AppWindow;
+use crate::cancel::CancelToken;
+use crate::progress::{ProgressSink, TaskProgress};
+use crate::worker_threads;
+
+/// Uruchamia `job` w tle na puli ciężkich zadań (także jego `par_iter`). `report(ułamek, komunikat)` wysyła
+/// postęp kanałem do zadania `task` – do UI trafiają nowe komunikaty i przyrosty co najmniej 1%.
+/// `cancel` anuluje przycisk ✕ na liście zadań. `done` dostaje wynik w pętli zdarzeń, po ostatnim
+/// komunikacie postępu i po usunięciu zadania z listy.
+pub fn spawn<T, J, D>(ui: slint::Weak<AppWindow>, task: TaskProgress, cancel: CancelToken, job: J, done: D)
+where
+    T: Send + 'static,
+    J: FnOnce(&(dyn Fn(f32, &str) + Sync), &CancelToken) -> T + Send + 'static,
+    D: FnOnce(AppWindow, T) + Send + 'static,
+{
+    let (sender, receiver) = mpsc::channel::<(f32, String)>();
+    let forwarder = std::thread::spawn(move || {
+        let (mut shown, mut message) = (-1.0, String::new());
+        for (fraction, text) in receiver {
+            if text == message && fraction - shown < 0.01 {
+                continue;
+            }
+            shown = fraction;
+            message.clone_from(&text);
+            task.set(fraction, Some(&text));
+        }
+        task
+    });
+    std::thread::spawn(move || {
+        let result = {
+            let report = move |fraction: f32, text: &str| {
+                let _ = sender.send((fraction.clamp(0.0, 1.0), text.to_string()));
+            };
+            worker_threads::install(|| job(&report, &cancel))
+        };
+        // Nadawca upuszczony razem z `report` – przekaźnik kończy po opróżnieniu kanału; zadanie
+        // znika z listy przed `done`, więc jego ostatni komunikat nie nadpisze statusu wyniku
+        drop(forwarder.join());
+        let _ = ui.upgrade_in_event_loop(move |ui| done(ui, result));
+    });
+}
@@ -0,0 +1,592 @@
+// Eksport plików: kanały do osobnych plików ("explode channels") w skali szarości – PNG/TIFF 16-bit
+// (wartości 0..1) lub TIFF 32-bit float (surowe dane) – oraz obraz w wybranym stanie barwnym
+// (po tone mappingu jak w podglądzie, sRGB, Rec.709 albo surowy liniowy) w formatach 8-bit do szybkich
+// przeglądów (PNG, JPEG, WebP, AVIF), PNG 16-bit lub TIFF 32-bit float. Nazwy plików wg wspólnego szablonu.
+
+use std::collections::HashSet;
+use std::fs::{self, File};
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+use std::sync::{LazyLock, Mutex, MutexGuard};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use exr::prelude as exr;
+use rayon::prelude::*;
+use tracing::info;
+use crate::AppWindow;
+use crate::cancel::CancelToken;
+use crate::color_processing;
+use crate::image_processing::{DisplayTransform, InputColorSpace, ProcessingGraph};
+use crate::render_settings::RenderSettings;
+use crate::utils::error_handling::{ExrError, ExrResult};
+use crate::utils::split_layer_and_short;
+
+/// Domyślny szablon nazw przy eksporcie kanałów
+pub const DEFAULT_CHANNEL_TEMPLATE: &str = "{name}_{layer}_{channel}";
+
+/// Co zrobić, gdy plik o docelowej nazwie już istnieje
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Collision {
+    Overwrite,
+    Skip,
+    /// Dopisuje kolejny numer: name_1.tif, name_2.tif, ...
+    Increment,
+}
+
+impl Collision {
+    pub fn from_label(label: &str) -> Self {
+        if label.starts_with("Overwrite") {
+            Collision::Overwrite
+        } else if label.starts_with("Skip") {
+            Collision::Skip
+        } else {
+            Collision::Increment
+        }
+    }
+
+    /// Odwrotność `from_label` (zapis kolejki eksportu)
+    pub fn label(self) -> &'static str {
+        match self {
+            Collision::Overwrite => "Overwrite",
+            Collision::Skip => "Skip",
+            Collision::Increment => "Increment",
+        }
+    }
+}
+
+/// Ustawienia nazewnictwa z panelu eksportu – wspólne dla wszystkich rodzajów eksportu
+#[derive(Clone, Debug)]
+pub struct UiExportConfig {
+    pub template: String,
+    pub collision: Collision,
+    /// Obrót/odbicie podglądu także w renderowanych eksportach (domyślnie plik w orientacji źródła)
+    pub display_transform: bool,
+}
+
+impl UiExportConfig {
+    pub fn from_ui(ui: &AppWindow) -> Self {
+        let template = ui.get_export_name_template().trim().to_string();
+        UiExportConfig {
+            template: if template.is_empty() { DEFAULT_CHANNEL_TEMPLATE.to_string() } else { template },
+            collision: Collision::from_label(&ui.get_export_collision()),
+            display_transform: ui.get_export_display_transform(),
+        }
+    }
+
+    /// Migawka widoku dla zlecenia eksportu: bez obrotu/odbicia, chyba że włączono je dla eksportu
+    pub fn render_settings(&self, settings: RenderSettings) -> RenderSettings {
+        if self.display_transform { settings } else { RenderSettings { transform: DisplayTransform::default(), ..settings } }
+    }
+}
+
+/// Wartości tokenów szablonu dla jednego pliku wynikowego
+pub struct NameFields<'a> {
+    /// Nazwa pliku źródłowego bez rozszerzenia
+    pub name: &'a str,
+    pub layer: &'a str,
+    pub channel: &'a str,
+    /// Sposób mapowania wartości, np. "raw" dla surowych danych kanału
+    pub tonemap: &'a str,
+}
+
+/// Podstawia tokeny {name} (alias {file}), {layer}, {channel}, {frame}, {date}, {tonemap}.
+/// {frame} to końcowe cyfry nazwy źródła (np. "shot.0042" → "0042"), {date} to data UTC (RRRR-MM-DD).
+/// Znaki niedozwolone w nazwach plików zamieniane są na '_'.
+pub fn fill_template(template: &str, fields: &NameFields) -> String {
+    // Warstwa bez nazwy (główne RGBA) też musi dać czytelny fragment nazwy
+    let layer = if fields.layer.is_empty() { "main" } else { fields.layer };
+    let name = template
+        .replace("{name}", fields.name)
+        .replace("{file}", fields.name)
+        .replace("{layer}", layer)
+        .replace("{channel}", fields.channel)
+        .replace("{frame}", frame_number(fields.name))
+        .replace("{date}", &crate::utils::utc_date_time(std::time::SystemTime::now()).0)
+        .replace("{tonemap}", fields.tonemap);
+    let name: String = name.chars()
+        .map(|c| if c.is_alphanumeric() || matches!(c, '-' | '_' | '.' | ' ') { c } else { '_' })
+        .collect();
+    // Puste tokeny (np. brak numeru klatki) nie mogą zostawić separatorów na brzegach
+    let name = name.trim_matches(|c| matches!(c, '_' | '.' | ' ' | '-'));
+    if name.is_empty() { "export".to_string() } else { name.to_string() }
+}
+
+/// Ścieżka docelowa `dir/<szablon>.<ext>` po rozwiązaniu kolizji; None = pominąć (Skip).
+/// `claimed` zbiera nazwy przydzielone w bieżącym eksporcie – pliki jeszcze nie istnieją,
+/// a mimo to nie mogą dostać tej samej nazwy przy Increment. Overwrite nadpisuje tylko plik
+/// na dysku; nazwy zajętej przez inne zlecenie nie dostaje (dwa zapisy do jednego pliku).
+pub fn plan_target(dir: &Path, stem: &str, extension: &str, collision: Collision, claimed: &mut HashSet<PathBuf>) -> Option<PathBuf> {
+    let taken = |p: &PathBuf| p.exists() || claimed.contains(p);
+    let target = dir.join(format!("{}.{}", stem, extension));
+    let target = match collision {
+        _ if !taken(&target) => target,
+        Collision::Overwrite if !claimed.contains(&target) => target,
+        Collision::Overwrite | Collision::Skip => return None,
+        Collision::Increment => (1u32..)
+            .map(|n| dir.join(format!("{}_{}.{}", stem, n, extension)))
+            .find(|p| !taken(p))?,
+    };
+    claimed.insert(target.clone());
+    Some(target)
+}
+
+/// Cele zleceń eksportu, których pliki mogą jeszcze nie istnieć – wspólne dla całej kolejki
+/// (zlecenia w wątku UI, nazwy kanałów i ponowne sprawdzenie celu w wątkach eksportu)
+static CLAIMED_TARGETS: LazyLock<Mutex<HashSet<PathBuf>>> = LazyLock::new(Default::default);
+
+fn claimed_targets() -> MutexGuard<'static, HashSet<PathBuf>> {
+    CLAIMED_TARGETS.lock().unwrap_or_else(|p| p.into_inner())
+}
+
+/// `plan_target` względem dysku i celów wszystkich zleceń; ścieżka pozostaje zajęta do
+/// `release_target` (koniec zadania)
+pub fn claim_target(dir: &Path, stem: &str, extension: &str, collision: Collision) -> Option<PathBuf> {
+    plan_target(dir, stem, extension, collision, &mut claimed_targets())
+}
+
+/// Zajmuje cel wybrany poza `claim_target` (okno zapisu, zlecenie wznowione po restarcie);
+/// false, gdy cel należy już do innego zlecenia
+pub fn hold_target(target: &Path) -> bool {
+    claimed_targets().insert(target.to_path_buf())
+}
+
+pub fn release_target(target: &Path) {
+    claimed_targets().remove(target);
+}
+
+/// Końcowa sekwencja cyfr nazwy (numer klatki sekwencji); pusty napis gdy brak
+fn frame_number(name: &str) -> &str {
+    let digits = name.bytes().rev().take_while(u8::is_ascii_digit).count();
+    &name[name.len() - digits..]
+}
+
+/// Wynik eksportu: zapisane pliki i pominięte z powodu kolizji nazw
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ExportSummary {
+    pub written: usize,
+    pub skipped: usize,
+    /// Łączna liczba pikseli zapisanych kanałów (przepustowość w kolejce eksportu)
+    pub pixels: u64,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ChannelFormat {
+    /// PNG 16-bit, wartości obcinane do 0..1
+    Png16,
+    /// TIFF 16-bit, wartości obcinane do 0..1
+    Tiff16,
+    /// TIFF 32-bit float – pełny zakres bez obcinania
+    Tiff32,
+}
+
+impl ChannelFormat {
+    pub fn from_label(label: &str) -> Self {
+        if label.starts_with("TIFF 32") {
+            ChannelFormat::Tiff32
+        } else if label.starts_with("TIFF") {
+            ChannelFormat::Tiff16
+        } else {
+            ChannelFormat::Png16
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            ChannelFormat::Png16 => "PNG 16-bit",
+            ChannelFormat::Tiff16 => "TIFF 16-bit",
+            ChannelFormat::Tiff32 => "TIFF 32-bit float",
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            ChannelFormat::Png16 => "png",
+            ChannelFormat::Tiff16 | ChannelFormat::Tiff32 => "tif",
+        }
+    }
+}
+
+/// Zapisuje każdy kanał warstw z `layers` (None = wszystkie) do `output_dir`; nazwy plików i kolizje
+/// wg `config`, z pominięciem celów innych zleceń (`claim_target`) – nazwy zajęte do końca zapisu.
+/// `progress(ułamek, komunikat)` wołane z wątków roboczych.
+pub fn explode_channels(
+    path: &Path,
+    layers: Option<&[String]>,
+    output_dir: &Path,
+    format: ChannelFormat,
+    config: &UiExportConfig,
+    cancel: &CancelToken,
+    progress: impl Fn(f32, &str) + Sync,
+) -> ExrResult<ExportSummary> {
+    let image = exr::read_all_flat_layers_from_file(path)?;
+    let file_stem = path.file_stem().map(|s| s.to_string_lossy().into_owned()).unwrap_or_default();
+
+    // (nazwa warstwy, kanał, próbki, rozmiar)
+    let mut channels = Vec::new();
+    for layer in &image.layer_data {
+        let base_attr: Option<String> = layer.attributes.layer_name.as_ref().map(|s| s.to_string());
+        for channel in &layer.channel_data.list {
+            let (layer_name, short) = split_layer_and_short(&channel.name.to_string(), base_attr.as_deref());
+            if layers.is_some_and(|selected| !selected.contains(&layer_name)) { continue; }
+            channels.push((layer_name, short, &channel.sample_data, layer.size));
+        }
+    }
+    if channels.is_empty() {
+        let wanted = layers.map(|l| l.join(", ")).unwrap_or_default();
+        return Err(ExrError::MissingLayer(wanted));
+    }
+    fs::create_dir_all(output_dir).map_err(ExrError::Io)?;
+
+    // Nazwy przydzielane sekwencyjnie (deterministyczna numeracja), kodowanie równolegle
+    let requested = channels.len();
+    let jobs: Vec<_> = {
+        let mut claimed = claimed_targets();
+        channels.into_iter()
+            .filter_map(|(layer_name, short, samples, size)| {
+                let fields = NameFields { name: &file_stem, layer: &layer_name, channel: &short, tonemap: "raw" };
+                let stem = fill_template(&config.template, &fields);
+                let target = plan_target(output_dir, &stem, format.extension(), config.collision, &mut claimed);
+                if target.is_none() {
+                    info!(target: "io", "skipping {}.{}: file exists or is queued", stem, format.extension());
+                }
+                target.map(|t| (t, samples, size))
+            })
+            .collect()
+    };
+
+    let total = jobs.len();
+    let pixels = jobs.iter().map(|(_, _, size)| size.area() as u64).sum();
+    let done = AtomicUsize::new(0);
+    let result = jobs.par_iter().try_for_each(|(target, samples, size)| -> ExrResult<()> {
+        cancel.check()?;
+        let values: Vec<f32> = (0..size.area()).map(|i| samples.value_by_flat_index(i).to_f32()).collect();
+        write_channel(target, &values, size.width() as u32, size.height() as u32, format)?;
+        let n = done.fetch_add(1, Ordering::Relaxed) + 1;
+        let file_name = target.file_name().map(|f| f.to_string_lossy()).unwrap_or_default();
+        progress(n as f32 / total as f32, &format!("Exported {}/{}: {}", n, total, file_name));
+        Ok(())
+    });
+    let mut claimed = claimed_targets();
+    for (target, _, _) in &jobs {
+        claimed.remove(target);
+    }
+    result?;
+    Ok(ExportSummary { written: total, skipped: requested - total, pixels })
+}
+
+fn write_channel(target: &Path, values: &[f32], width: u32, height: u32, format: ChannelFormat) -> ExrResult<()> {
+    let encode_error = |e: &dyn std::fmt::Display| ExrError::Io(std::io::Error::other(format!("{}: {}", target.display(), e)));
+    // NaN → 0 (rzutowanie `as` saturuje, NaN daje 0)
+    let to_u16 = |v: &f32| (v.clamp(0.0, 1.0) * 65535.0).round() as u16;
+    match format {
+        ChannelFormat::Png16 => {
+            let data: Vec<u16> = values.iter().map(to_u16).collect();
+            let buffer = image::ImageBuffer::<image::Luma<u16>, Vec<u16>>::from_raw(width, height, data)
+                .ok_or_else(|| encode_error(&"buffer size mismatch"))?;
+            buffer.save_with_format(target, image::ImageFormat::Png).map_err(|e| encode_error(&e))
+        }
+        ChannelFormat::Tiff16 | ChannelFormat::Tiff32 => {
+            let file = BufWriter::new(File::create(target).map_err(ExrError::Io)?);
+            let mut encoder = tiff::encoder::TiffEncoder::new(file).map_err(|e| encode_error(&e))?;
+            let written = if format == ChannelFormat::Tiff16 {
+                let data: Vec<u16> = values.iter().map(to_u16).collect();
+                encoder.write_image::<tiff::encoder::colortype::Gray16>(width, height, &data)
+            } else {
+                encoder.write_image::<tiff::encoder::colortype::Gray32Float>(width, height, values)
+            };
+            written.map_err(|e| encode_error(&e))
+        }
+    }
+}
+
+/// Stan barwny eksportowanego obrazu
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OutputTransform {
+    /// Jak w podglądzie: ekspozycja, ACES i gamma (8 bitów na kanał)
+    Look,
+    /// Prymarki Rec.709 z ekspozycją, bez tone mappingu, wartości obcinane do 0..1, krzywa sRGB
+    Srgb,
+    /// Jak `Srgb`, ale z krzywą BT.709 (OETF wideo)
+    Rec709,
+    /// Wartości z pliku bez ekspozycji i konwersji prymarek; pełny zakres tylko w TIFF 32-bit float
+    Linear,
+    /// Jak `Linear`, ale w prymarkach AP1 (ACEScg) niezależnie od przestrzeni wejściowej
+    AcesCg,
+}
+
+impl OutputTransform {
+    pub fn from_label(label: &str) -> Self {
+        if label.starts_with("sRGB") {
+            OutputTransform::Srgb
+        } else if label.starts_with("Rec") {
+            OutputTransform::Rec709
+        } else if label.starts_with("Raw") {
+            OutputTransform::Linear
+        } else if label.starts_with("ACEScg") {
+            OutputTransform::AcesCg
+        } else {
+            OutputTransform::Look
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            OutputTransform::Look => "Tone-mapped (current look)",
+            OutputTransform::Srgb => "sRGB display",
+            OutputTransform::Rec709 => "Rec.709",
+            OutputTransform::Linear => "Raw linear",
+            OutputTransform::AcesCg => "ACEScg linear",
+        }
+    }
+
+    /// Wartość pola {tonemap} szablonu nazw
+    pub fn tag(self) -> &'static str {
+        match self {
+            OutputTransform::Look => "aces",
+            OutputTransform::Srgb => "srgb",
+            OutputTransform::Rec709 => "rec709",
+            OutputTransform::Linear => "linear",
+            OutputTransform::AcesCg => "acescg",
+        }
+    }
+
+    /// Mapowanie piksela źródła na stan wyjściowy (nie dotyczy `Look`, renderowanego jak podgląd);
+    /// przestrzeń wejściowa i ekspozycja (tylko dla stanów wyświetlania) z grafu zlecenia, NaN → 0
+    pub fn pixel_mapper(self, graph: &ProcessingGraph) -> impl Fn(f32, f32, f32) -> [f32; 3] + Sync {
+        let target = if self == OutputTransform::AcesCg { InputColorSpace::AcesCg } else { InputColorSpace::LinearRec709 };
+        let matrix = graph.input_to(target);
+        let gain = 2.0_f32.powf(graph.exposure);
+        move |r, g, b| {
+            if self == OutputTransform::Linear {
+                return [r, g, b];
+            }
+            let rgb = matrix.map_or([r, g, b], |m| color_processing::apply(&m, [r, g, b]));
+            if self == OutputTransform::AcesCg {
+                return rgb;
+            }
+            rgb.map(|v| {
+                let v = if v.is_nan() { 0.0 } else { (v * gain).clamp(0.0, 1.0) };
+                if self == OutputTransform::Rec709 {
+                    if v < 0.018 { 4.5 * v } else { 1.099 * v.powf(0.45) - 0.099 }
+                } else if v <= 0.003_130_8 {
+                    12.92 * v
+                } else {
+                    1.055 * v.powf(1.0 / 2.4) - 0.055
+                }
+            })
+        }
+    }
+}
+
+/// Format pliku dla obrazu: 8-bit do przeglądów albo PNG 16-bit / TIFF 32-bit float
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DeliveryFormat {
+    Png,
+    Jpeg,
+    /// Stratny WebP – chroma zawsze 4:2:0 (ograniczenie formatu)
+    Webp,
+    /// AVIF (AV1) – chroma zawsze 4:4:4
+    Avif,
+    Png16,
+    /// Bez obcinania – jedyny format zachowujący pełny zakres stanów liniowych (`OutputTransform`)
+    Tiff32,
+}
+
+impl DeliveryFormat {
+    pub fn from_label(label: &str) -> Self {
+        if label.starts_with("PNG 16") {
+            DeliveryFormat::Png16
+        } else if label.starts_with("TIFF") {
+            DeliveryFormat::Tiff32
+        } else if label.starts_with("JPEG") {
+            DeliveryFormat::Jpeg
+        } else if label.starts_with("WebP") {
+            DeliveryFormat::Webp
+        } else if label.starts_with("AVIF") {
+            DeliveryFormat::Avif
+        } else {
+            DeliveryFormat::Png
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            DeliveryFormat::Png => "PNG 8-bit",
+            DeliveryFormat::Jpeg => "JPEG",
+            DeliveryFormat::Webp => "WebP",
+            DeliveryFormat::Avif => "AVIF",
+            DeliveryFormat::Png16 => "PNG 16-bit",
+            DeliveryFormat::Tiff32 => "TIFF 32-bit float",
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            DeliveryFormat::Png => "png",
+            DeliveryFormat::Jpeg => "jpg",
+            DeliveryFormat::Webp => "webp",
+            DeliveryFormat::Avif => "avif",
+            DeliveryFormat::Png16 => "png",
+            DeliveryFormat::Tiff32 => "tif",
+        }
+    }
+}
+
+/// Próbkowanie chrominancji JPEG
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ChromaSubsampling {
+    Yuv444,
+    Yuv422,
+    Yuv420,
+}
+
+impl ChromaSubsampling {
+    pub fn from_label(label: &str) -> Self {
+        match label {
+            "4:4:4" => ChromaSubsampling::Yuv444,
+            "4:2:2" => ChromaSubsampling::Yuv422,
+            _ => ChromaSubsampling::Yuv420,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            ChromaSubsampling::Yuv444 => "4:4:4",
+            ChromaSubsampling::Yuv422 => "4:2:2",
+            ChromaSubsampling::Yuv420 => "4:2:0",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct DeliveryOptions {
+    pub format: DeliveryFormat,
+    /// Jakość 1..100 (ignorowana dla PNG i TIFF)
+    pub quality: u8,
+    pub subsampling: ChromaSubsampling,
+    pub output: OutputTransform,
+}
+
+/// Zapisuje obraz RGB8 (bufor podglądu po tone mappingu) i sprawdza wynik: plik jest dekodowany
+/// i porównywany z buforem źródłowym. Zwraca średni błąd bezwzględny w skali 0..255
+/// albo None, gdy format nie ma dekodera w aplikacji (AVIF).
+pub fn export_delivery(target: &Path, rgb: &[u8], width: u32, height: u32, options: DeliveryOptions) -> ExrResult<Option<f32>> {
+    let encode_error = |e: &dyn std::fmt::Display| ExrError::Io(std::io::Error::other(format!("{}: {}", target.display(), e)));
+    let quality = options.quality.clamp(1, 100);
+    match options.format {
+        DeliveryFormat::Png16 | DeliveryFormat::Tiff32 => {
+            let rgb: Vec<f32> = rgb.iter().map(|&v| v as f32 / 255.0).collect();
+            return export_delivery_f32(target, &rgb, width, height, options);
+        }
+        DeliveryFormat::Png => image::save_buffer_with_format(target, rgb, width, height, image::ExtendedColorType::Rgb8, image::ImageFormat::Png)
+            .map_err(|e| encode_error(&e))?,
+        DeliveryFormat::Jpeg => {
+            let (Ok(w), Ok(h)) = (u16::try_from(width), u16::try_from(height)) else {
+                return Err(encode_error(&"JPEG is limited to 65535 px per side"));
+            };
+            let mut encoder = jpeg_encoder::Encoder::new_file(target, quality).map_err(|e| encode_error(&e))?;
+            encoder.set_sampling_factor(match options.subsampling {
+                ChromaSubsampling::Yuv444 => jpeg_encoder::SamplingFactor::R_4_4_4,
+                ChromaSubsampling::Yuv422 => jpeg_encoder::SamplingFactor::R_4_2_2,
+                ChromaSubsampling::Yuv420 => jpeg_encoder::SamplingFactor::R_4_2_0,
+            });
+            encoder.encode(rgb, w, h, jpeg_encoder::ColorType::Rgb).map_err(|e| encode_error(&e))?;
+        }
+        DeliveryFormat::Webp => {
+            let encoded = webp::Encoder::from_rgb(rgb, width, height)
+                .encode_simple(false, quality as f32)
+                .map_err(|e| encode_error(&format!("{:?}", e)))?;
+            fs::write(target, &*encoded)?;
+        }
+        DeliveryFormat::Avif => {
+            use image::ImageEncoder;
+            let file = BufWriter::new(File::create(target)?);
+            // Szybkość 6 z 10: rozsądny kompromis czasu kodowania AV1 dla podglądów
+            image::codecs::avif::AvifEncoder::new_with_speed_quality(file, 6, quality)
+                .write_image(rgb, width, height, image::ExtendedColorType::Rgb8)
+                .map_err(|e| encode_error(&e))?;
+        }
+    }
+
+    if options.format == DeliveryFormat::Avif {
+        return Ok(None);
+    }
+    let decoded = image::open(target).map_err(|e| encode_error(&e))?.into_rgb8();
+    if decoded.dimensions() != (width, height) {
+        return Err(encode_error(&"written image has different dimensions"));
+    }
+    let total: u64 = decoded.as_raw().iter().zip(rgb).map(|(&a, &b)| a.abs_diff(b) as u64).sum();
+    Ok(Some(total as f32 / rgb.len().max(1) as f32))
+}
+
+/// Zapisuje obraz RGB f32 (wartości w stanie `options.output`): TIFF 32-bit float bez obcinania,
+/// pozostałe formaty po kwantyzacji zakresu 0..1. Weryfikacja jak w `export_delivery`
+/// (błąd w skali 0..255); TIFF nie ma dekodera w aplikacji.
+pub fn export_delivery_f32(target: &Path, rgb: &[f32], width: u32, height: u32, options: DeliveryOptions) -> ExrResult<Option<f32>> {
+    let encode_error = |e: &dyn std::fmt::Display| ExrError::Io(std::io::Error::other(format!("{}: {}", target.display(), e)));
+    match options.format {
+        DeliveryFormat::Tiff32 => {
+            let file = BufWriter::new(File::create(target)?);
+            let mut encoder = tiff::encoder::TiffEncoder::new(file).map_err(|e| encode_error(&e))?;
+            encoder.write_image::<tiff::encoder::colortype::RGB32Float>(width, height, rgb).map_err(|e| encode_error(&e))?;
+            Ok(None)
+        }
+        DeliveryFormat::Png16 => {
+            // NaN → 0 (rzutowanie `as` saturuje, NaN daje 0)
+            let data: Vec<u16> = rgb.iter().map(|v| (v.clamp(0.0, 1.0) * 65535.0).round() as u16).collect();
+            let buffer = image::ImageBuffer::<image::Rgb<u16>, Vec<u16>>::from_raw(width, height, data)
+                .ok_or_else(|| encode_error(&"buffer size mismatch"))?;
+            buffer.save_with_format(target, image::ImageFormat::Png).map_err(|e| encode_error(&e))?;
+            let decoded = image::open(target).map_err(|e| encode_error(&e))?.into_rgb16();
+            if decoded.dimensions() != (width, height) {
+                return Err(encode_error(&"written image has different dimensions"));
+            }
+            let total: u64 = decoded.as_raw().iter().zip(buffer.as_raw()).map(|(&a, &b)| a.abs_diff(b) as u64).sum();
+            Ok(Some(total as f32 / 257.0 / rgb.len().max(1) as f32))
+        }
+        _ => {
+            let data: Vec<u8> = rgb.iter().map(|v| (v.clamp(0.0, 1.0) * 255.0).round() as u8).collect();
+            export_delivery(target, &data, width, height, options)
+        }
+    }
+}
+
+/// Katalog docelowy podpowiadany w dialogu: obok pliku źródłowego
+pub fn default_output_dir(path: &Path) -> PathBuf {
+    path.parent().map(Path::to_path_buf).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn queued_targets_never_share_a_name() {
+        // Katalog nie istnieje – o kolizjach decydują wyłącznie cele zleceń
+        let dir = std::env::temp_dir().join(format!("exruster-claims-{}", std::process::id()));
+        let first = claim_target(&dir, "shot", "png", Collision::Increment).unwrap();
+        let second = claim_target(&dir, "shot", "png", Collision::Increment).unwrap();
+        assert_eq!((first.file_name().unwrap(), second.file_name().unwrap()), ("shot.png".as_ref(), "shot_1.png".as_ref()));
+        assert_eq!(claim_target(&dir, "shot", "png", Collision::Skip), None);
+
+        release_target(&first);
+        assert_eq!(claim_target(&dir, "shot", "png", Collision::Skip), Some(first.clone()));
+        hold_target(&dir.join("shot_2.png"));
+        assert_eq!(claim_target(&dir, "shot", "png", Collision::Increment), Some(dir.join("shot_3.png")));
+        for name in ["shot.png", "shot_1.png", "shot_2.png", "shot_3.png"] {
+            release_target(&dir.join(name));
+        }
+    }
+
+    #[test]
+    fn overwrite_never_takes_a_queued_target() {
+        let dir = std::env::temp_dir().join(format!("exruster-overwrite-{}", std::process::id()));
+        let first = claim_target(&dir, "shot", "png", Collision::Overwrite).unwrap();
+        assert_eq!(claim_target(&dir, "shot", "png", Collision::Overwrite), None);
+        assert!(!hold_target(&first));
+
+        // Koniec pierwszego zlecenia zwalnia cel – drugie zlecenie nie trzyma go równolegle
+        release_target(&first);
+        assert_eq!(claim_target(&dir, "shot", "png", Collision::Overwrite), Some(first.clone()));
+        release_target(&first);
+    }
+}
@@ -0,0 +1,611 @@
+// Kolejka eksportu: każde zlecenie (kanały, obraz, obraz z notatkami, przepięty lub uporządkowany EXR, wideo, animacja) trafia na listę zadań pod paskiem postępu,
+// gdzie można je wstrzymać lub anulować. Naraz działa najwyżej EXRUSTER_EXPORT_JOBS zadań
+// (domyślnie 2) na puli `export_executor`, reszta czeka w kolejności zleceń; wstrzymane zadanie
+// zachowuje swoje miejsce. Po każdej zmianie kolejka jest zapisywana w export_queue.json w katalogu
+// danych aplikacji, więc zadania przerwane awarią lub zamknięciem wznawiają się przy następnym
+// uruchomieniu. Przepustowość (MPix/s) każdego zakończonego zadania trafia do konsoli.
+//
+// Cele zleceń są przydzielane względem dysku i celów wszystkich zleceń w kolejce
+// (`export_handlers::claim_target`),
+// a tuż przed zapisem sprawdzane ponownie – plik powstały w międzyczasie jest pomijany albo
+// numerowany wg ustawienia kolizji, nadpisywany tylko przy Overwrite.
+
+use std::cell::RefCell;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+use serde_json::{json, Value};
+use slint::ComponentHandle;
+use tracing::{error, info, warn};
+use crate::AppWindow;
+use crate::cancel::{CancelToken, PauseToken};
+use crate::animated_export::{self, AnimatedFormat, AnimatedOptions};
+use crate::annotations;
+use crate::export_executor;
+use crate::export_handlers::{self, ChannelFormat, ChromaSubsampling, Collision, DeliveryFormat, DeliveryOptions, OutputTransform, UiExportConfig};
+use crate::image_cache::ImageCache;
+use crate::layer_cleanup::{self, CleanupPlan, LayerAction};
+use crate::layer_export::{self, LayerCompression, OutputChannel, SampleKind};
+use crate::progress::{self, NoopProgress, TaskProgress};
+use crate::render_settings::RenderSettings;
+use crate::session::app_data_dir;
+use crate::stereo::StereoMode;
+use crate::utils::error_handling::ExrResult;
+use crate::video_export::{self, VideoOptions, VideoQuality, VideoSize};
+
+/// Zmienna środowiskowa: liczba jednocześnie wykonywanych zadań eksportu
+pub const EXPORT_JOBS_ENV: &str = "EXRUSTER_EXPORT_JOBS";
+const DEFAULT_JOBS: usize = 2;
+const QUEUE_FILE: &str = "export_queue.json";
+
+/// Zlecenie eksportu – wszystko, co potrzebne do wykonania go od nowa po restarcie aplikacji;
+/// render tylko z migawki ustawień widoku z chwili zlecenia (`settings`)
+#[derive(Clone, Debug)]
+pub enum ExportSpec {
+    /// Kanały warstw (None = wszystkich) jako osobne pliki w skali szarości
+    Channels { source: PathBuf, layers: Option<Vec<String>>, output_dir: PathBuf, format: ChannelFormat, config: UiExportConfig },
+    /// Warstwa po tone mappingu jako plik 8-bit
+    Image { source: PathBuf, layer: String, settings: RenderSettings, target: PathBuf, collision: Collision, options: DeliveryOptions },
+    /// Warstwa po tone mappingu z narysowanymi notatkami pliku `notes` jako PNG (dailies)
+    Annotated { source: PathBuf, notes: PathBuf, layer: String, settings: RenderSettings, target: PathBuf, collision: Collision },
+    /// Klatki sekwencji po tone mappingu jako wideo (None = brakująca klatka, czarna)
+    Video { frames: Vec<Option<PathBuf>>, layer: String, settings: RenderSettings, target: PathBuf, collision: Collision, options: VideoOptions },
+    /// Kanały źródła przepięte do nowego pliku EXR
+    Remap { source: PathBuf, mapping: Vec<OutputChannel>, sample: SampleKind, compression: LayerCompression, target: PathBuf, collision: Collision },
+    /// Kopia pliku z warstwami usuniętymi lub przemianowanymi (cel z okna zapisu – nadpisanie potwierdzone)
+    Cleanup { source: PathBuf, plan: CleanupPlan, target: PathBuf },
+    /// Klatki sekwencji jako animowany GIF/WebP (co N-ta, zmniejszone)
+    Animation { frames: Vec<Option<PathBuf>>, layer: String, settings: RenderSettings, target: PathBuf, collision: Collision, options: AnimatedOptions },
+}
+
+impl ExportSpec {
+    /// Nazwa na liście zadań
+    fn name(&self) -> String {
+        let file = |p: &Path| p.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+        match (self, self.target()) {
+            (ExportSpec::Channels { source, .. }, _) => format!("Export channels {}", file(source)),
+            (_, target) => format!("Export {}", target.map(file).unwrap_or_default()),
+        }
+    }
+
+    /// Plik docelowy zlecenia; None dla kanałów (nazwy przydzielane przy wykonaniu)
+    fn target(&self) -> Option<&Path> {
+        match self {
+            ExportSpec::Channels { .. } => None,
+            ExportSpec::Image { target, .. } | ExportSpec::Annotated { target, .. } | ExportSpec::Video { target, .. } | ExportSpec::Animation { target, .. } | ExportSpec::Remap { target, .. } | ExportSpec::Cleanup { target, .. } => Some(target),
+        }
+    }
+
+    /// Postępowanie z plikiem, który powstał pod celem po zleceniu
+    fn collision(&self) -> Collision {
+        match self {
+            ExportSpec::Channels { config, .. } => config.collision,
+            ExportSpec::Image { collision, .. } | ExportSpec::Annotated { collision, .. } | ExportSpec::Video { collision, .. } | ExportSpec::Animation { collision, .. } | ExportSpec::Remap { collision, .. } => *collision,
+            ExportSpec::Cleanup { .. } => Collision::Overwrite,
+        }
+    }
+
+    /// Kopia zlecenia z innym plikiem docelowym
+    fn with_target(mut self, path: PathBuf) -> Self {
+        match &mut self {
+            ExportSpec::Channels { .. } => {}
+            ExportSpec::Image { target, .. } | ExportSpec::Annotated { target, .. } | ExportSpec::Video { target, .. } | ExportSpec::Animation { target, .. } | ExportSpec::Remap { target, .. } | ExportSpec::Cleanup { target, .. } => *target = path,
+        }
+        self
+    }
+
+    fn to_json(&self) -> Value {
+        match self {
+            ExportSpec::Channels { source, layers, output_dir, format, config } => json!({
+                "kind": "channels",
+                "source": source.to_string_lossy(),
+                "layers": layers,
+                "output_dir": output_dir.to_string_lossy(),
+                "format": format.label(),
+                "template": config.template,
+                "collision": config.collision.label(),
+                "display_transform": config.display_transform,
+            }),
+            ExportSpec::Image { source, layer, settings, target, collision, options } => json!({
+                "kind": "image",
+                "source": source.to_string_lossy(),
+                "layer": layer,
+                "settings": settings.to_json(),
+                "target": target.to_string_lossy(),
+                "collision": collision.label(),
+                "format": options.format.label(),
+                "quality": options.quality,
+                "subsampling": options.subsampling.label(),
+                "output": options.output.label(),
+            }),
+            ExportSpec::Annotated { source, notes, layer, settings, target, collision } => json!({
+                "kind": "annotated",
+                "source": source.to_string_lossy(),
+                "notes": notes.to_string_lossy(),
+                "layer": layer,
+                "settings": settings.to_json(),
+                "target": target.to_string_lossy(),
+                "collision": collision.label(),
+            }),
+            ExportSpec::Video { frames, layer, settings, target, collision, options } => json!({
+                "kind": "video",
+                "frames": frames.iter().map(|f| f.as_ref().map(|p| p.to_string_lossy())).collect::<Vec<_>>(),
+                "layer": layer,
+                "settings": settings.to_json(),
+                "target": target.to_string_lossy(),
+                "collision": collision.label(),
+                "size": options.size.label(),
+                "quality": options.quality.label(),
+                "fps": options.fps,
+            }),
+            ExportSpec::Remap { source, mapping, sample, compression, target, collision } => json!({
+                "kind": "remap",
+                "source": source.to_string_lossy(),
+                "mapping": mapping.iter().map(ToString::to_string).collect::<Vec<_>>().join("\n"),
+                "sample": sample.label(),
+                "compression": compression.label(),
+                "target": target.to_string_lossy(),
+                "collision": collision.label(),
+            }),
+            ExportSpec::Cleanup { source, plan, target } => {
+                let mut drop = Vec::new();
+                let mut rename = serde_json::Map::new();
+                for (layer, action) in plan {
+                    match action {
+                        LayerAction::Drop => drop.push(layer.clone()),
+                        LayerAction::Rename(name) => { rename.insert(layer.clone(), json!(name)); }
+                    }
+                }
+                json!({
+                    "kind": "cleanup",
+                    "source": source.to_string_lossy(),
+                    "drop": drop,
+                    "rename": rename,
+                    "target": target.to_string_lossy(),
+                })
+            }
+            ExportSpec::Animation { frames, layer, settings, target, collision, options } => json!({
+                "kind": "animation",
+                "frames": frames.iter().map(|f| f.as_ref().map(|p| p.to_string_lossy())).collect::<Vec<_>>(),
+                "layer": layer,
+                "settings": settings.to_json(),
+                "target": target.to_string_lossy(),
+                "collision": collision.label(),
+                "format": options.format.label(),
+                "max_side": options.max_side,
+                "every": options.every,
+                "fps": options.fps,
+            }),
+        }
+    }
+
+    fn from_json(value: &Value) -> Option<Self> {
+        let text = |key: &str| value.get(key).and_then(Value::as_str).map(str::to_string);
+        let number = |key: &str| value.get(key).and_then(Value::as_f64);
+        // Zlecenie bez migawki ustawień albo polityki kolizji jest niepoprawne – nie zgadujemy
+        let settings = || RenderSettings::from_json(value.get("settings")?);
+        let collision = || text("collision").map(|label| Collision::from_label(&label));
+        match value.get("kind")?.as_str()? {
+            "channels" => Some(ExportSpec::Channels {
+                source: text("source")?.into(),
+                layers: value.get("layers").and_then(Value::as_array)
+                    .map(|list| list.iter().filter_map(Value::as_str).map(str::to_string).collect()),
+                output_dir: text("output_dir")?.into(),
+                format: ChannelFormat::from_label(&text("format")?),
+                config: UiExportConfig {
+                    template: text("template")?,
+                    collision: Collision::from_label(&text("collision")?),
+                    display_transform: value.get("display_transform").and_then(Value::as_bool).unwrap_or(false),
+                },
+            }),
+            "image" => Some(ExportSpec::Image {
+                source: text("source")?.into(),
+                layer: text("layer")?,
+                settings: settings()?,
+                target: text("target")?.into(),
+                collision: collision()?,
+                options: DeliveryOptions {
+                    format: DeliveryFormat::from_label(&text("format")?),
+                    quality: number("quality")?.clamp(1.0, 100.0) as u8,
+                    subsampling: ChromaSubsampling::from_label(&text("subsampling")?),
+                    output: OutputTransform::from_label(&text("output")?),
+                },
+            }),
+            "annotated" => Some(ExportSpec::Annotated {
+                source: text("source")?.into(),
+                notes: text("notes")?.into(),
+                layer: text("layer")?,
+                settings: settings()?,
+                target: text("target")?.into(),
+                collision: collision()?,
+            }),
+            "video" => Some(ExportSpec::Video {
+                frames: value.get("frames")?.as_array()?.iter().map(|f| f.as_str().map(PathBuf::from)).collect(),
+                layer: text("layer")?,
+                settings: settings()?,
+                target: text("target")?.into(),
+                collision: collision()?,
+                options: VideoOptions {
+                    size: VideoSize::from_label(&text("size")?),
+                    quality: VideoQuality::from_label(&text("quality")?),
+                    fps: number("fps")?.clamp(1.0, 120.0) as u32,
+                },
+            }),
+            "remap" => Some(ExportSpec::Remap {
+                source: text("source")?.into(),
+                mapping: layer_export::parse_mapping(&text("mapping")?).ok()?,
+                sample: SampleKind::from_label(&text("sample")?),
+                compression: LayerCompression::from_label(&text("compression")?),
+                target: text("target")?.into(),
+                collision: collision()?,
+            }),
+            "cleanup" => {
+                let drop = value.get("drop")?.as_array()?.iter()
+                    .filter_map(Value::as_str)
+                    .map(|layer| (layer.to_string(), LayerAction::Drop));
+                let rename = value.get("rename")?.as_object()?.iter()
+                    .filter_map(|(layer, name)| Some((layer.clone(), LayerAction::Rename(name.as_str()?.to_string()))));
+                Some(ExportSpec::Cleanup { source: text("source")?.into(), plan: drop.chain(rename).collect(), target: text("target")?.into() })
+            }
+            "animation" => Some(ExportSpec::Animation {
+                frames: value.get("frames")?.as_array()?.iter().map(|f| f.as_str().map(PathBuf::from)).collect(),
+                layer: text("layer")?,
+                settings: settings()?,
+                target: text("target")?.into(),
+                collision: collision()?,
+                options: AnimatedOptions {
+                    format: AnimatedFormat::from_label(&text("format")?),
+                    max_side: number("max_side")?.clamp(16.0, 8192.0) as u32,
+                    every: number("every")?.clamp(1.0, 100.0) as u32,
+                    fps: number("fps")?.clamp(1.0, 120.0) as u32,
+                },
+            }),
+            _ => None,
+        }
+    }
+}
+
+struct Job {
+    id: u64,
+    spec: ExportSpec,
+    /// Kopia podglądu z chwili zlecenia (współdzieli piksele); zadanie wznowione po restarcie
+    /// wczytuje plik od nowa
+    preview: Option<ImageCache>,
+    /// Wpis listy zadań; przy starcie przechodzi do wykonawcy
+    task: Option<TaskProgress>,
+    running: bool,
+    cancel: CancelToken,
+    pause: PauseToken,
+    /// Wznowione po restarcie – pliki zapisane przed przerwaniem są pomijane
+    resumed: bool,
+}
+
+/// Wynik zadania: liczba pikseli (przepustowość) i komunikat do paska statusu
+struct Outcome {
+    pixels: u64,
+    status: String,
+}
+
+thread_local! {
+    // Kolejka żyje w wątku UI: zlecenia, przyciski listy zadań i zakończenia zadań przychodzą przez pętlę zdarzeń
+    static QUEUE: RefCell<Vec<Job>> = const { RefCell::new(Vec::new()) };
+}
+
+static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+
+fn max_running() -> usize {
+    std::env::var(EXPORT_JOBS_ENV).ok()
+        .and_then(|v| v.trim().parse().ok())
+        .filter(|n| *n > 0)
+        .unwrap_or(DEFAULT_JOBS)
+}
+
+/// Dodaje zlecenie na koniec kolejki (wątek UI); `preview` to kopia bieżącego podglądu dla eksportu obrazu
+pub fn enqueue(ui: &AppWindow, spec: ExportSpec, preview: Option<ImageCache>) {
+    info!(target: "io", "queued: {}", spec.name());
+    push(ui, spec, preview, false, false);
+    pump(ui);
+}
+
+/// Wznawia zadania zapisane przed zamknięciem lub awarią (wątek UI, po zbudowaniu okna)
+pub fn resume(ui: &AppWindow) {
+    let jobs = load(&app_data_dir().join(QUEUE_FILE));
+    if jobs.is_empty() {
+        return;
+    }
+    info!(target: "io", "resuming {} export job(s) from the previous session", jobs.len());
+    for (spec, paused) in jobs {
+        push(ui, spec, None, paused, true);
+    }
+    pump(ui);
+}
+
+fn push(ui: &AppWindow, spec: ExportSpec, preview: Option<ImageCache>, paused: bool, resumed: bool) {
+    let (cancel, pause) = (CancelToken::new(), PauseToken::new());
+    pause.set_paused(paused);
+    let task = progress::register(ui.as_weak(), spec.name(), Some(cancel.clone())).pausable(pause.clone());
+    if !paused {
+        task.set_message("Queued");
+    }
+    // Cele wznowionych zleceń też blokują nazwę dla kolejnych (nowe zajęto już przy zleceniu)
+    if let Some(target) = spec.target() {
+        export_handlers::hold_target(target);
+    }
+    let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+    QUEUE.with(|q| q.borrow_mut().push(Job { id, spec, preview, task: Some(task), running: false, cancel, pause, resumed }));
+}
+
+/// Porządkuje kolejkę po każdej zmianie (zlecenie, ✕, ❚❚/▶, koniec zadania): usuwa anulowane
+/// oczekujące zadania, uruchamia kolejne do limitu i zapisuje stan na dysk
+pub fn pump(ui: &AppWindow) {
+    QUEUE.with(|q| {
+        let mut q = q.borrow_mut();
+        q.retain(|job| {
+            let keep = job.running || !job.cancel.is_cancelled();
+            if let (false, Some(target)) = (keep, job.spec.target()) {
+                export_handlers::release_target(target);
+            }
+            keep
+        });
+        let free = max_running().saturating_sub(q.iter().filter(|job| job.running).count());
+        for job in q.iter_mut().filter(|job| !job.running && !job.pause.is_paused()).take(free) {
+            start(ui, job);
+        }
+        save(&app_data_dir().join(QUEUE_FILE), &q);
+        ui.set_export_jobs(q.len() as i32);
+    });
+}
+
+/// Przekazuje zadanie wykonawcy; wpis zostaje w kolejce (i w pliku) do zakończenia
+fn start(ui: &AppWindow, job: &mut Job) {
+    let Some(task) = job.task.take() else { return; };
+    job.running = true;
+    let (id, spec, preview, pause, resumed) = (job.id, job.spec.clone(), job.preview.take(), job.pause.clone(), job.resumed);
+    let name = spec.name();
+    info!(target: "io", "starting: {}", name);
+    export_executor::spawn(
+        ui.as_weak(),
+        task,
+        job.cancel.clone(),
+        move |report, cancel| {
+            let started = Instant::now();
+            // Postój nie wlicza się do przepustowości
+            let paused_ms = AtomicU64::new(0);
+            let report = |fraction: f32, message: &str| {
+                let waited = pause.wait(cancel);
+                paused_ms.fetch_add(waited.as_millis() as u64, Ordering::Relaxed);
+                report(fraction, message);
+            };
+            let result = run(&spec, preview, resumed, cancel, &report);
+            let active = started.elapsed().saturating_sub(Duration::from_millis(paused_ms.load(Ordering::Relaxed)));
+            (result, active)
+        },
+        move |ui, (result, active)| finished(&ui, id, &name, result, active),
+    );
+}
+
+/// Wykonuje zlecenie po ponownym sprawdzeniu celu; zwalnia cel po zakończeniu
+fn run(spec: &ExportSpec, preview: Option<ImageCache>, resumed: bool, cancel: &CancelToken, report: &(dyn Fn(f32, &str) + Sync)) -> ExrResult<Outcome> {
+    let Some(planned) = spec.target() else { return write(spec, preview, resumed, cancel, report); };
+    let result = match recheck_target(planned, spec.collision()) {
+        None => {
+            info!(target: "io", "skipping {}: file exists", planned.display());
+            Ok(Outcome { pixels: 0, status: format!("Skipped: {} already exists", planned.display()) })
+        }
+        Some(target) if target == planned => write(spec, preview, resumed, cancel, report),
+        Some(target) => {
+            info!(target: "io", "{} appeared after queuing, writing {}", planned.display(), target.display());
+            let result = write(&spec.clone().with_target(target.clone()), preview, resumed, cancel, report);
+            export_handlers::release_target(&target);
+            result
+        }
+    };
+    export_handlers::release_target(planned);
+    result
+}
+
+/// Cel sprawdzony tuż przed zapisem (także w zadaniu wznowionym po restarcie): plik, który istnieje
+/// (inny program, ręczna kopia, wynik przerwanego zadania), nadpisuje tylko Overwrite, Increment
+/// dostaje kolejny wolny numer, Skip – None.
+fn recheck_target(target: &Path, collision: Collision) -> Option<PathBuf> {
+    if collision == Collision::Overwrite || !target.exists() {
+        return Some(target.to_path_buf());
+    }
+    let dir = target.parent().unwrap_or(Path::new(""));
+    let stem = target.file_stem()?.to_string_lossy();
+    let extension = target.extension().map(|e| e.to_string_lossy()).unwrap_or_default();
+    export_handlers::claim_target(dir, &stem, &extension, collision)
+}
+
+fn write(spec: &ExportSpec, preview: Option<ImageCache>, resumed: bool, cancel: &CancelToken, report: &(dyn Fn(f32, &str) + Sync)) -> ExrResult<Outcome> {
+    match spec {
+        ExportSpec::Channels { source, layers, output_dir, format, config } => {
+            let mut config = config.clone();
+            if resumed {
+                config.collision = Collision::Skip;
+            }
+            let summary = export_handlers::explode_channels(source, layers.as_deref(), output_dir, *format, &config, cancel, report)?;
+            info!(target: "io", "exported {} channels to {} ({} skipped)", summary.written, output_dir.display(), summary.skipped);
+            let skipped = if summary.skipped > 0 { format!(", {} skipped (exist)", summary.skipped) } else { String::new() };
+            Ok(Outcome { pixels: summary.pixels, status: format!("Exported {} channels → {}{}", summary.written, output_dir.display(), skipped) })
+        }
+        ExportSpec::Image { source, layer, settings, target, options, .. } => {
+            let cache = preview_or_load(preview, source, layer, settings.stereo_mode, cancel, report)?;
+            // Render to 90% paska, reszta to kodowanie i weryfikacja
+            let (error, width, height) = if options.output == OutputTransform::Look {
+                let image = cache.render_full_resolution(settings, cancel, &|f| report(f * 0.9, "Rendering full resolution..."))?;
+                cancel.check()?;
+                report(0.9, &format!("Encoding {}...", target.display()));
+                let rgb: Vec<u8> = image.pixels.chunks_exact(4).flat_map(|p| [p[0], p[1], p[2]]).collect();
+                (export_handlers::export_delivery(target, &rgb, image.width, image.height, *options)?, image.width, image.height)
+            } else {
+                if matches!(options.output, OutputTransform::Linear | OutputTransform::AcesCg) && options.format != DeliveryFormat::Tiff32 {
+                    warn!(target: "io", "{}: linear values outside 0..1 are clipped in {}", target.display(), options.format.label());
+                }
+                report(0.0, "Rendering full resolution...");
+                let map = options.output.pixel_mapper(&settings.graph);
+                let (rgb, width, height) = cache.render_rgb_f32(settings, &map, cancel)?;
+                report(0.9, &format!("Encoding {}...", target.display()));
+                (export_handlers::export_delivery_f32(target, &rgb, width, height, *options)?, width, height)
+            };
+            let check = error.map_or("not verified".to_string(), |e| format!("mean error {:.2}/255", e));
+            info!(target: "io", "exported {} ({}, {})", target.display(), options.output.label(), check);
+            Ok(Outcome { pixels: width as u64 * height as u64, status: format!("Exported {} ({})", target.display(), check) })
+        }
+        ExportSpec::Annotated { source, notes, layer, settings, target, .. } => {
+            let cache = preview_or_load(preview, source, layer, settings.stereo_mode, cancel, report)?;
+            let mut image = cache.render_full_resolution(settings, cancel, &|f| report(f * 0.9, "Rendering full resolution..."))?;
+            cancel.check()?;
+            let notes = annotations::load(notes);
+            annotations::flatten(&mut image, &notes);
+            report(0.9, &format!("Encoding {}...", target.display()));
+            let rgb: Vec<u8> = image.pixels.chunks_exact(4).flat_map(|p| [p[0], p[1], p[2]]).collect();
+            let options = DeliveryOptions { format: DeliveryFormat::Png, quality: 100, subsampling: ChromaSubsampling::Yuv444, output: OutputTransform::Look };
+            export_handlers::export_delivery(target, &rgb, image.width, image.height, options)?;
+            info!(target: "io", "exported {} ({} annotations)", target.display(), notes.len());
+            Ok(Outcome { pixels: image.width as u64 * image.height as u64, status: format!("Exported {} ({} annotations)", target.display(), notes.len()) })
+        }
+        ExportSpec::Video { frames, layer, settings, target, options, .. } => {
+            // Wznowione zadanie koduje całe wideo od nowa (niedokończony plik usuwa enkoder)
+            let pixels = video_export::export_sequence(frames, layer, settings, target, *options, cancel, report)?;
+            Ok(Outcome { pixels, status: format!("Exported {} ({} frames)", target.display(), frames.len()) })
+        }
+        ExportSpec::Remap { source, mapping, sample, compression, target, .. } => {
+            let pixels = layer_export::export_remapped(source, mapping, *sample, *compression, target, cancel, report)?;
+            Ok(Outcome { pixels, status: format!("Exported {} ({} channels)", target.display(), mapping.len()) })
+        }
+        ExportSpec::Cleanup { source, plan, target } => {
+            report(0.0, &format!("Writing {}...", target.display()));
+            let summary = layer_cleanup::write_cleaned(source, target, plan)?;
+            let status = format!("Wrote {} ({} channels kept, {} dropped, {} renamed)", target.display(), summary.kept, summary.dropped, summary.renamed);
+            Ok(Outcome { pixels: 0, status })
+        }
+        ExportSpec::Animation { frames, layer, settings, target, options, .. } => {
+            let pixels = animated_export::export_animation(frames, layer, settings, target, *options, cancel, report)?;
+            Ok(Outcome { pixels, status: format!("Exported {}", target.display()) })
+        }
+    }
+}
+
+/// Kopia podglądu z chwili zlecenia albo plik wczytany od nowa (zadanie wznowione po restarcie),
+/// wtedy z drugim okiem dla trybu stereo zlecenia
+fn preview_or_load(preview: Option<ImageCache>, source: &Path, layer: &str, stereo_mode: StereoMode, cancel: &CancelToken, report: &(dyn Fn(f32, &str) + Sync)) -> ExrResult<ImageCache> {
+    if let Some(cache) = preview {
+        return Ok(cache);
+    }
+    report(0.0, &format!("Loading {}...", source.display()));
+    let source = source.to_path_buf();
+    let mut cache = ImageCache::new(&source, cancel, &NoopProgress)?;
+    if cache.current_layer_name != layer {
+        cache.load_layer(&source, layer)?;
+    }
+    cache.set_stereo_mode(&source, stereo_mode);
+    Ok(cache)
+}
+
+fn finished(ui: &AppWindow, id: u64, name: &str, result: ExrResult<Outcome>, active: Duration) {
+    QUEUE.with(|q| q.borrow_mut().retain(|job| job.id != id));
+    match result {
+        Ok(outcome) => {
+            let mpix = outcome.pixels as f64 / 1e6;
+            let seconds = active.as_secs_f64();
+            info!(target: "io", "{}: {:.1} MPix in {:.2} s ({:.1} MPix/s)", name, mpix, seconds, mpix / seconds.max(1e-3));
+            ui.set_status_text(outcome.status.into());
+        }
+        Err(e) if e.is_canceled() => {
+            info!(target: "io", "{}: canceled", name);
+            ui.set_status_text(format!("{}: canceled", name).into());
+        }
+        Err(e) => {
+            error!(target: "io", "{} failed: {}", name, e);
+            ui.set_status_text(format!("Export error: {}", e).into());
+        }
+    }
+    pump(ui);
+}
+
+/// Zapis atomowy jak w sesji; pusta kolejka usuwa plik
+fn save(path: &Path, jobs: &[Job]) {
+    let result = if jobs.is_empty() {
+        match fs::remove_file(path) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e),
+            _ => Ok(()),
+        }
+    } else {
+        let list: Vec<Value> = jobs.iter()
+            .map(|job| {
+                let mut value = job.spec.to_json();
+                value["paused"] = json!(job.pause.is_paused());
+                value
+            })
+            .collect();
+        let tmp = path.with_extension("tmp");
+        path.parent().map_or(Ok(()), fs::create_dir_all)
+            .and_then(|_| fs::write(&tmp, Value::Array(list).to_string()))
+            .and_then(|_| fs::rename(&tmp, path))
+    };
+    if let Err(e) = result {
+        warn!(target: "io", "cannot save export queue: {}", e);
+    }
+}
+
+/// Zlecenia z pliku kolejki wraz ze stanem wstrzymania; uszkodzone wpisy są pomijane
+fn load(path: &Path) -> Vec<(ExportSpec, bool)> {
+    let Ok(text) = fs::read_to_string(path) else { return Vec::new(); };
+    let Ok(Value::Array(list)) = serde_json::from_str::<Value>(&text) else {
+        warn!(target: "io", "ignoring unreadable export queue {}", path.display());
+        return Vec::new();
+    };
+    list.iter()
+        .filter_map(|value| {
+            let spec = ExportSpec::from_json(value);
+            if spec.is_none() {
+                warn!(target: "io", "skipping invalid export job: {}", value);
+            }
+            Some((spec?, value.get("paused").and_then(Value::as_bool).unwrap_or(false)))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn saved_job_needs_settings_and_collision() {
+        let spec = ExportSpec::Image {
+            source: "shot.exr".into(),
+            layer: "beauty".into(),
+            settings: RenderSettings::standard(1.0, 2.2),
+            target: "out/shot.jpg".into(),
+            collision: Collision::Skip,
+            options: DeliveryOptions { format: DeliveryFormat::Jpeg, quality: 90, subsampling: ChromaSubsampling::Yuv420, output: OutputTransform::Srgb },
+        };
+        let saved = spec.to_json();
+        let restored = ExportSpec::from_json(&saved).unwrap();
+        assert_eq!((restored.target(), restored.collision()), (spec.target(), Collision::Skip));
+
+        // Brak polityki kolizji, migawki albo stanu barwnego odrzuca zlecenie zamiast zgadywać
+        for key in ["collision", "settings", "output"] {
+            let mut broken = saved.clone();
+            broken.as_object_mut().unwrap().remove(key);
+            assert!(ExportSpec::from_json(&broken).is_none(), "job without {} accepted", key);
+        }
+    }
+
+    #[test]
+    fn existing_target_follows_collision_policy() {
+        let dir = std::env::temp_dir().join(format!("exruster-recheck-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let (existing, missing) = (dir.join("shot.png"), dir.join("other.png"));
+        fs::write(&existing, b"partial").unwrap();
+
+        assert_eq!(recheck_target(&missing, Collision::Skip), Some(missing.clone()));
+        assert_eq!(recheck_target(&existing, Collision::Overwrite), Some(existing.clone()));
+        assert_eq!(recheck_target(&existing, Collision::Skip), None);
+        assert_eq!(recheck_target(&existing, Collision::Increment), Some(dir.join("shot_1.png")));
+        export_handlers::release_target(&dir.join("shot_1.png"));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,7 +1,10 @@
 use std::fs;
 use std::path::{Path, PathBuf};
 use anyhow::Context;
-use exr::prelude as exr;
+use ::exr::meta::attribute::{AttributeValue, Chromaticities};
+use ::exr::meta::header::Header;
+use crate::color_processing::{self, Mat3};
+use crate::image_processing::InputColorSpace;
 use crate::utils::{split_layer_and_short, human_size};
 
 #[derive(Debug, Clone)]
@@ -23,6 +26,8 @@ pub struct LayerMetadata {
     pub name: String,               // pusta nazwa oznacza warstwę bazową bez prefiksu
     pub width: u32,
     pub height: u32,
+    /// Typ części z danymi deep ("deep scanline" / "deep tile"); None dla zwykłych warstw
+    pub deep: Option<&'static str>,
     #[allow(dead_code)]
     pub channel_groups: Vec<LayerChannelsGroup>,
     pub attributes: Vec<(String, String)>,
@@ -36,6 +41,8 @@ pub struct ExrMetadata {
     pub file_size_bytes: u64,
     pub groups: Vec<MetadataGroup>,
     pub layers: Vec<LayerMetadata>,
+    /// Przestrzeń barw wykryta z nagłówka pierwszej części
+    pub color_space: DetectedColorSpace,
 }
 
 /// Publiczne API: odczytuje metadane z pliku EXR, porządkuje je i zwraca strukturę
@@ -44,39 +51,51 @@ pub fn read_and_group_metadata(path: &Path) -> anyhow::Result<ExrMetadata> {
         .with_context(|| format!("Nie można pobrać metadata pliku: {}", path.display()))?;
     let file_size_bytes = meta.len();
 
-    // Pełne dane o warstwach i kanałach (bez potrzeby czytania pikseli)
-    let image = exr::read_all_data_from_file(path)
+    // Same nagłówki: dane o warstwach i kanałach bez dekodowania pikseli
+    // Bez walidacji exr, aby pokazać też pliki z częściami deep
+    let headers = crate::deep_exr::read_headers(path)
         .with_context(|| format!("Błąd odczytu EXR (nagłówki): {}", path.display()))?;
+    Ok(group_headers(path, file_size_bytes, &headers))
+}
 
+/// Porządkuje odczytane nagłówki w grupy i warstwy do UI
+pub fn group_headers(path: &Path, file_size_bytes: u64, headers: &[Header]) -> ExrMetadata {
     // Grupa ogólna (do UI): podstawowe informacje o pliku i obrazie
     let mut general_items: Vec<(String, String)> = Vec::new();
     general_items.push(("Ścieżka".into(), path.display().to_string()));
     general_items.push(("Rozmiar pliku".into(), human_size(file_size_bytes)));
-    general_items.push(("Warstwy".into(), image.layer_data.len().to_string()));
+    general_items.push(("Warstwy".into(), headers.len().to_string()));
+    if crate::deep_exr::has_deep_parts(headers) {
+        general_items.push(("Dane deep".into(), "tak – podgląd spłaszczony (front-to-back)".into()));
+    }
+    let color_space = detect_color_space(headers);
+    general_items.push(("Przestrzeń barw".into(), format!("{} ({})", color_space.space.label(), color_space.reason)));
 
-    // Zbierz nagłówek pliku jako key→value (bezpośrednia iteracja po atrybutach)
-    let header_items: Vec<(String, String)> = image.attributes.other.iter()
-        .map(|(name, value)| (name.to_string(), format!("{:?}", value)))
-        .collect();
+    // Zbierz nagłówek pliku jako key→value (atrybuty współdzielone przez wszystkie części)
+    let header_items: Vec<(String, String)> = headers.first()
+        .map(|h| h.shared_attributes.other.iter()
+            .map(|(name, value)| (name.to_string(), format!("{:?}", value)))
+            .collect())
+        .unwrap_or_default();
     let mut groups: Vec<MetadataGroup> = Vec::new();
     groups.push(MetadataGroup { name: "Ogólne".into(), items: general_items });
     groups.push(MetadataGroup { name: "Nagłówek".into(), items: header_items });
 
     // Buduj warstwy i ich grupy kanałów
-    let mut layers: Vec<LayerMetadata> = Vec::with_capacity(image.layer_data.len());
-    for layer in image.layer_data.iter() {
-        let base_layer_name: Option<String> = layer
-            .attributes
+    let mut layers: Vec<LayerMetadata> = Vec::with_capacity(headers.len());
+    for header in headers.iter() {
+        let base_layer_name: Option<String> = header
+            .own_attributes
             .layer_name
             .as_ref()
             .map(|s| s.to_string());
 
-        let w = layer.size.width() as u32;
-        let h = layer.size.height() as u32;
+        let w = header.layer_size.width() as u32;
+        let h = header.layer_size.height() as u32;
 
         // Grupowanie kanałów według logiki do UI
         let mut groups: GroupBuckets = GroupBuckets::new();
-        for ch in &layer.channel_data.list {
+        for ch in &header.channels.list {
             let full = ch.name.to_string();
             let (lname, short) = split_layer_and_short(&full, base_layer_name.as_deref());
             let _ = lname; // lname nieużywane dalej, ale poprawne dla dopasowania
@@ -88,10 +107,16 @@ pub fn read_and_group_metadata(path: &Path) -> anyhow::Result<ExrMetadata> {
         // Nazwa warstwy (pusta dla warstwy bazowej)
         let layer_name = base_layer_name.unwrap_or_else(|| "".to_string());
         // Atrybuty warstwy (bezpośrednia iteracja po atrybutach)
-        let layer_items: Vec<(String, String)> = layer.attributes.other.iter()
+        let mut layer_items: Vec<(String, String)> = header.own_attributes.other.iter()
             .map(|(name, value)| (name.to_string(), format!("{:?}", value)))
             .collect();
-        layers.push(LayerMetadata { name: layer_name, width: w, height: h, channel_groups, attributes: layer_items });
+        let deep = crate::deep_exr::deep_kind_label(header);
+        if deep.is_some() {
+            if let Some(max) = header.max_samples_per_pixel {
+                layer_items.insert(0, ("max próbek/piksel".into(), max.to_string()));
+            }
+        }
+        layers.push(LayerMetadata { name: layer_name, width: w, height: h, deep, channel_groups, attributes: layer_items });
     }
 
     // Posortuj warstwy: najpierw bez nazwy (bazowa), potem alfabetycznie
@@ -104,7 +129,7 @@ pub fn read_and_group_metadata(path: &Path) -> anyhow::Result<ExrMetadata> {
         }
     });
 
-    Ok(ExrMetadata { path: path.to_path_buf(), file_size_bytes, groups, layers })
+    ExrMetadata { path: path.to_path_buf(), file_size_bytes, groups, layers, color_space }
 }
 
 /// Akcesorium: przygotuj proste linie tekstowe na potrzeby UI (np. lista stringów)
@@ -122,7 +147,8 @@ pub fn build_ui_lines(meta: &ExrMetadata) -> Vec<String> {
     // Warstwy (tylko atrybuty; bez list kanałów)
     for layer in &meta.layers {
         let title = if layer.name.is_empty() { "(domyślna)".to_string() } else { layer.name.clone() };
-        out.push(format!("Warstwa: {}  — {}x{}", title, layer.width, layer.height));
+        let deep = layer.deep.map(|kind| format!("  [{}]", kind)).unwrap_or_default();
+        out.push(format!("Warstwa: {}  — {}x{}{}", title, layer.width, layer.height, deep));
         // Atrybuty warstwy jako key→value
         for (k, v) in &layer.attributes {
             if k.is_empty() { out.push(format!("  {}", v)); } else { out.push(format!("  {}: {}", k, v)); }
@@ -171,6 +197,9 @@ pub fn build_ui_rows(meta: &ExrMetadata) -> Vec<(String, String)> {
         let label = if layer.name.is_empty() { "(domyślna)".to_string() } else { layer.name.clone() };
         rows.push((format!("Warstwa: {}", label), "".into()));
         rows.push(("Wymiary".into(), format!("{}x{}", layer.width, layer.height)));
+        if let Some(kind) = layer.deep {
+            rows.push(("Typ".into(), kind.to_string()));
+        }
         for (k, v) in &layer.attributes {
             let key = k.trim();
             let pretty_k = if key.is_empty() { "Atrybut".to_string() } else { key.to_string() };
@@ -319,6 +348,80 @@ fn pretty_display_window(v: &str) -> String {
     format!("position: ({}, {}); size: {}x{}", pos.0, pos.1, size.0, size.1)
 }
 
+// Prymarki i biel (x, y) w kolejności R, G, B, W
+const AP0_PRIMARIES: [(f32, f32); 4] = [(0.7347, 0.2653), (0.0, 1.0), (0.0001, -0.077), (0.32168, 0.33767)];
+const AP1_PRIMARIES: [(f32, f32); 4] = [(0.713, 0.293), (0.165, 0.830), (0.128, 0.044), (0.32168, 0.33767)];
+const REC709_PRIMARIES: [(f32, f32); 4] = [(0.64, 0.33), (0.30, 0.60), (0.15, 0.06), (0.3127, 0.3290)];
+
+/// Przestrzeń barw części pliku z uzasadnieniem (do notki w konsoli)
+#[derive(Clone, Debug)]
+pub struct DetectedColorSpace {
+    pub space: InputColorSpace,
+    /// Prymarki części → Rec.709, tylko dla `InputColorSpace::FilePrimaries`
+    pub matrix: Option<Mat3>,
+    pub reason: String,
+}
+
+/// Wykrywa przestrzeń barw pierwszej części pliku (zob. `detect_part_color_space`)
+pub fn detect_color_space(headers: &[Header]) -> DetectedColorSpace {
+    if headers.is_empty() {
+        return DetectedColorSpace { space: InputColorSpace::LinearRec709, matrix: None, reason: "no headers".into() };
+    }
+    detect_part_color_space(headers, 0)
+}
+
+/// Wykrywa przestrzeń barw części `index`: flaga kontenera ACES, potem atrybut `chromaticities` tej części
+/// (pliki wieloczęściowe mogą mieszać np. plany z kamery i rendery ACEScg). Bez `chromaticities`
+/// OpenEXR zakłada prymarki Rec.709 – uzasadnienie mówi o tym wprost. Inne prymarki lub biel
+/// (np. D60, DCI) dają macierz do Rec.709 z adaptacją bieli. `whiteLuminance` różne od pierwszej
+/// części skaluje wartości tak, by biel wszystkich części miała tę samą luminancję.
+pub fn detect_part_color_space(headers: &[Header], index: usize) -> DetectedColorSpace {
+    let header = &headers[index];
+    let aces_container = header.shared_attributes.other.iter()
+        .chain(header.own_attributes.other.iter())
+        .any(|(name, value)| name.eq("acesImageContainerFlag") && matches!(value, AttributeValue::I32(1)));
+
+    let (space, matrix, mut reason) = match header.shared_attributes.chromaticities {
+        Some(c) if primaries_match(&c, &AP0_PRIMARIES) => {
+            let reason = if aces_container { "acesImageContainerFlag + AP0 chromaticities" } else { "AP0 chromaticities" };
+            (InputColorSpace::Aces2065, None, reason.to_string())
+        }
+        Some(c) if primaries_match(&c, &AP1_PRIMARIES) => (InputColorSpace::AcesCg, None, "AP1 chromaticities".into()),
+        Some(c) if primaries_match(&c, &REC709_PRIMARIES) => (InputColorSpace::LinearRec709, None, "Rec.709 chromaticities".into()),
+        Some(c) => {
+            let xy = |v: ::exr::math::Vec2<f32>| (v.x(), v.y());
+            let white = xy(c.white);
+            match color_processing::compute_rgb_to_srgb_matrix_from_chromaticities([xy(c.red), xy(c.green), xy(c.blue)], white) {
+                Some(m) => {
+                    let reason = format!("chromaticities with white ({:.4}, {:.4}), Bradford-adapted to D65", white.0, white.1);
+                    (InputColorSpace::FilePrimaries, Some(m), reason)
+                }
+                None => (InputColorSpace::LinearRec709, None, "invalid chromaticities, treated as Rec.709".into()),
+            }
+        }
+        // Kontener ACES wymaga prymarek AP0, więc sama flaga wystarcza
+        None if aces_container => (InputColorSpace::Aces2065, None, "acesImageContainerFlag".into()),
+        None => (InputColorSpace::LinearRec709, None, "no chromaticities attribute, assuming Rec.709/sRGB primaries".into()),
+    };
+
+    let luminance = |h: &Header| h.own_attributes.white_luminance.filter(|l| l.is_finite() && *l > 0.0);
+    match (luminance(header), luminance(&headers[0])) {
+        (Some(own), Some(reference)) if (own / reference - 1.0).abs() > 1e-3 => {
+            let scale = own / reference;
+            let base = matrix.or_else(|| color_processing::conversion_matrix(space, InputColorSpace::LinearRec709))
+                .unwrap_or([[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]]);
+            reason.push_str(&format!(", whiteLuminance {} vs {} cd/m² in the first part", own, reference));
+            DetectedColorSpace { space: InputColorSpace::FilePrimaries, matrix: Some(base.map(|row| row.map(|v| v * scale))), reason }
+        }
+        _ => DetectedColorSpace { space, matrix, reason },
+    }
+}
+
+fn primaries_match(c: &Chromaticities, expected: &[(f32, f32); 4]) -> bool {
+    [c.red, c.green, c.blue, c.white].iter().zip(expected)
+        .all(|(v, &(x, y))| (v.x() - x).abs() < 0.005 && (v.y() - y).abs() < 0.005)
+}
+
 fn pretty_chromaticities(v: &str) -> String {
     // heurystyczny parser: wyciągnij pary (x,y) w kolejności R,G,B,W
     let mut nums: Vec<f64> = Vec::new();
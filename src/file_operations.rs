@@ -1,16 +1,74 @@
-use rfd::FileDialog;
-use std::path::PathBuf;
+use rfd::{AsyncFileDialog, FileDialog, MessageButtons, MessageDialog, MessageDialogResult, MessageLevel};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use tracing::{debug, warn};
+use crate::session::app_data_dir;
 
-/// Otwiera dialog wyboru pliku i zwraca wybraną ścieżkę
-/// 
-/// # Returns
-/// * `Option<PathBuf>` - ścieżka do wybranego pliku lub None jeśli anulowano
-pub fn open_file_dialog() -> Option<PathBuf> {
-    FileDialog::new()
+/// Ostatni folder dialogów otwierania (pliki i folder roboczy)
+const DIALOG_DIR_FILE: &str = "dialog_dir.txt";
+
+// Otwarty dialog asynchroniczny – kolejne kliknięcie "Open" nie otwiera drugiego
+static DIALOG_OPEN: AtomicBool = AtomicBool::new(false);
+
+/// Dialog otwierania obrazów (także kilku naraz) bez blokowania pętli zdarzeń – przy wolnych
+/// udziałach sieciowych UI dalej się odświeża. Wołane z `slint::spawn_local`; pusta lista =
+/// anulowano. Dowolna klatka sekwencji otwiera ją razem z osią czasu.
+pub async fn pick_image_files() -> Vec<PathBuf> {
+    let dialog = AsyncFileDialog::new()
+        .add_filter("OpenEXR (frames and sequences)", &["exr"])
         .add_filter("Obrazy", &["exr", "png", "jpg", "jpeg", "gif"])
         .add_filter("Wszystkie pliki", &["*"])
-        .set_title("Otwórz plik obrazu")
-        .pick_file()
+        .set_title("Otwórz pliki obrazów");
+    let Some(_guard) = DialogGuard::acquire() else { return Vec::new(); };
+    let files = with_last_dir(dialog).pick_files().await.unwrap_or_default();
+    let paths: Vec<PathBuf> = files.iter().map(|f| f.path().to_path_buf()).collect();
+    if let Some(dir) = paths.first().and_then(|p| p.parent()) {
+        remember_dialog_dir(dir);
+    }
+    paths
+}
+
+/// Asynchroniczny dialog wyboru folderu roboczego (jak `pick_image_files`)
+pub async fn pick_working_folder() -> Option<PathBuf> {
+    let dialog = AsyncFileDialog::new().set_title("Wybierz folder roboczy");
+    let _guard = DialogGuard::acquire()?;
+    let dir = with_last_dir(dialog).pick_folder().await?.path().to_path_buf();
+    remember_dialog_dir(&dir);
+    Some(dir)
+}
+
+struct DialogGuard;
+
+impl DialogGuard {
+    fn acquire() -> Option<Self> {
+        if DIALOG_OPEN.swap(true, Ordering::AcqRel) {
+            debug!(target: "ui", "file dialog already open");
+            return None;
+        }
+        Some(DialogGuard)
+    }
+}
+
+impl Drop for DialogGuard {
+    fn drop(&mut self) {
+        DIALOG_OPEN.store(false, Ordering::Release);
+    }
+}
+
+fn with_last_dir(dialog: AsyncFileDialog) -> AsyncFileDialog {
+    match fs::read_to_string(app_data_dir().join(DIALOG_DIR_FILE)) {
+        Ok(dir) if !dir.trim().is_empty() => dialog.set_directory(dir.trim()),
+        _ => dialog,
+    }
+}
+
+fn remember_dialog_dir(dir: &Path) {
+    let path = app_data_dir().join(DIALOG_DIR_FILE);
+    let saved = path.parent().map_or(Ok(()), fs::create_dir_all).and_then(|_| fs::write(&path, dir.display().to_string()));
+    if let Err(e) = saved {
+        warn!(target: "io", "cannot remember dialog folder: {}", e);
+    }
 }
 
 
@@ -28,9 +86,66 @@ pub fn get_file_name(path: &PathBuf) -> String {
         .to_string()
 }
 
-/// Otwiera dialog wyboru folderu roboczego
-pub fn open_folder_dialog() -> Option<PathBuf> {
+/// Otwiera dialog wyboru katalogu docelowego eksportu
+pub fn export_folder_dialog(start: &Path) -> Option<PathBuf> {
     FileDialog::new()
-        .set_title("Wybierz folder roboczy")
+        .set_title("Wybierz folder eksportu")
+        .set_directory(start)
         .pick_folder()
 }
+
+/// Otwiera dialog wyboru skryptu wsadowego (Rhai)
+pub fn open_script_dialog() -> Option<PathBuf> {
+    FileDialog::new()
+        .add_filter("Rhai script", &["rhai"])
+        .set_title("Uruchom skrypt")
+        .pick_file()
+}
+
+/// Otwiera dialog zapisu logu konsoli
+pub fn save_log_dialog() -> Option<PathBuf> {
+    FileDialog::new()
+        .add_filter("Log", &["log", "txt"])
+        .set_title("Zapisz log konsoli")
+        .set_file_name("exruster-console.log")
+        .save_file()
+}
+
+/// Otwiera dialog zapisu raportu QC; domyślna nazwa od pliku lub folderu źródłowego
+pub fn save_report_dialog(source: &Path) -> Option<PathBuf> {
+    let stem = source.file_stem().map(|s| s.to_string_lossy().into_owned()).unwrap_or_else(|| "exruster".into());
+    let dir = if source.is_dir() { source } else { source.parent().unwrap_or(source) };
+    FileDialog::new()
+        .add_filter("HTML", &["html", "htm"])
+        .set_title("Zapisz raport QC")
+        .set_directory(dir)
+        .set_file_name(format!("{}_qc.html", stem))
+        .save_file()
+}
+
+/// Dialog zapisu kopii EXR obok źródła (domyślnie `<nazwa>_clean.exr`)
+pub fn save_exr_copy_dialog(source: &Path) -> Option<PathBuf> {
+    let stem = source.file_stem().map(|s| s.to_string_lossy().into_owned()).unwrap_or_else(|| "exruster".into());
+    FileDialog::new()
+        .add_filter("EXR", &["exr"])
+        .set_title("Zapisz kopię EXR")
+        .set_directory(source.parent().unwrap_or(source))
+        .set_file_name(format!("{}_clean.exr", stem))
+        .save_file()
+}
+
+/// Potwierdzenie przeniesienia kilku plików do kosza (lista skrócona do pierwszych nazw)
+pub fn confirm_trash_dialog(paths: &[PathBuf]) -> bool {
+    const SHOWN: usize = 10;
+    let mut names: Vec<String> = paths.iter().take(SHOWN).map(get_file_name).collect();
+    if paths.len() > SHOWN {
+        names.push(format!("... and {} more", paths.len() - SHOWN));
+    }
+    let result = MessageDialog::new()
+        .set_level(MessageLevel::Warning)
+        .set_title("EXRuster - move to recycle bin")
+        .set_description(format!("Move {} files to the recycle bin?\n\n{}", paths.len(), names.join("\n")))
+        .set_buttons(MessageButtons::YesNo)
+        .show();
+    result == MessageDialogResult::Yes
+}
@@ -0,0 +1,254 @@
+// Histogramy sceny (R, G, B, luminancja) w skali logarytmicznej (ułamki stopnia), gotowe krzywe
+// i znaczniki percentyli dla panelu oraz automatyczna ekspozycja z percentyli: biel (P99.5) na 1.0,
+// czerń (P0.5) na zero w 8 bitach.
+
+use rayon::prelude::*;
+use crate::image_processing::{ExposureMode, ProcessingGraph, ToneParams};
+
+/// Zakres histogramu w stopniach względem 1.0; wartości spoza trafiają do skrajnych przedziałów
+const MIN_EV: i32 = -20;
+const MAX_EV: i32 = 12;
+const BINS_PER_STOP: usize = 16;
+pub const BINS: usize = (MAX_EV - MIN_EV) as usize * BINS_PER_STOP;
+
+/// Kanały histogramu w kolejności modelu UI; luminancja Rec.709 ostatnia
+pub const CHANNELS: [&str; 4] = ["R", "G", "B", "L"];
+pub const LUMINANCE: usize = 3;
+/// Pionowe znaczniki na krzywych
+pub const MARKER_PERCENTILES: [f32; 3] = [1.0, 50.0, 99.0];
+
+/// Percentyle używane przez "Auto"
+pub const AUTO_LOW_PERCENTILE: f32 = 0.5;
+pub const AUTO_HIGH_PERCENTILE: f32 = 99.5;
+
+/// Zakresy suwaków ekspozycji i gammy w UI
+const EXPOSURE_RANGE: (f32, f32) = (-5.0, 5.0);
+const GAMMA_RANGE: (f32, f32) = (0.5, 4.5);
+/// Wartość wyświetlana zaokrąglana do 0 w 8 bitach
+const BLACK_CODE: f32 = 0.5 / 255.0;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Histogram {
+    /// Liczności przedziałów log2 (od MIN_EV)
+    pub bins: Vec<u64>,
+    /// Wartości ≤ 0 – poniżej pierwszego przedziału
+    pub zero: u64,
+    /// Wszystkie próbki poza NaN
+    pub total: u64,
+}
+
+impl Histogram {
+    fn empty() -> Self {
+        Histogram { bins: vec![0; BINS], zero: 0, total: 0 }
+    }
+
+    /// Histogram dowolnych wartości (testy; panel liczy kanały razem w `channel_histograms`)
+    #[cfg(test)]
+    pub fn from_values(values: impl ParallelIterator<Item = f32>) -> Self {
+        values
+            .fold(Histogram::empty, |mut h, v| {
+                h.add(v);
+                h
+            })
+            .reduce(Histogram::empty, Histogram::merge)
+    }
+
+    fn add(&mut self, v: f32) {
+        if v.is_nan() {
+            return;
+        }
+        self.total += 1;
+        if v <= 0.0 {
+            self.zero += 1;
+        } else {
+            self.bins[bin_index(v)] += 1;
+        }
+    }
+
+    fn merge(mut self, other: Histogram) -> Histogram {
+        for (a, b) in self.bins.iter_mut().zip(&other.bins) {
+            *a += b;
+        }
+        self.zero += other.zero;
+        self.total += other.total;
+        self
+    }
+
+    /// Wartość (środek przedziału), poniżej której leży `percent` % próbek; None dla pustego
+    pub fn percentile(&self, percent: f32) -> Option<f32> {
+        if self.total == 0 {
+            return None;
+        }
+        let rank = ((percent / 100.0).clamp(0.0, 1.0) * self.total as f32).ceil().max(1.0) as u64;
+        let mut seen = self.zero;
+        if seen >= rank {
+            return Some(0.0);
+        }
+        for (i, &count) in self.bins.iter().enumerate() {
+            seen += count;
+            if seen >= rank {
+                return Some(bin_value(i));
+            }
+        }
+        Some(bin_value(BINS - 1))
+    }
+}
+
+impl Histogram {
+    /// Przedziały od pierwszego do ostatniego niepustego, z marginesem 1 EV (oś pozioma panelu)
+    pub fn visible_range(&self) -> Option<(usize, usize)> {
+        let first = self.bins.iter().position(|&c| c > 0)?;
+        let last = self.bins.iter().rposition(|&c| c > 0)?;
+        Some((first.saturating_sub(BINS_PER_STOP), (last + BINS_PER_STOP).min(BINS - 1)))
+    }
+
+    /// Zamknięta krzywa dla `Path` w Slint: viewbox szerokości `end - start + 1` i wysokości 100,
+    /// wysokość słupka względem najliczniejszego przedziału w zakresie
+    pub fn path_commands(&self, (start, end): (usize, usize)) -> String {
+        let bins = &self.bins[start..=end];
+        let max = bins.iter().copied().max().unwrap_or(0).max(1) as f32;
+        let mut path = String::from("M 0 100");
+        for (x, &count) in bins.iter().enumerate() {
+            let y = 100.0 - 100.0 * count as f32 / max;
+            path.push_str(&format!(" L {} {:.1} L {} {:.1}", x, y, x + 1, y));
+        }
+        path.push_str(&format!(" L {} 100 Z", bins.len()));
+        path
+    }
+
+    /// Pionowe odcinki w położeniach percentyli (ten sam viewbox co `path_commands`); czerń przy
+    /// lewej krawędzi
+    pub fn marker_commands(&self, (start, end): (usize, usize), percents: &[f32]) -> String {
+        let width = (end - start + 1) as f32;
+        percents.iter()
+            .filter_map(|&p| self.percentile(p))
+            .map(|v| {
+                let x = if v > 0.0 { (v.log2() - MIN_EV as f32) * BINS_PER_STOP as f32 - start as f32 } else { 0.0 };
+                let x = x.clamp(0.0, width);
+                format!("M {:.1} 0 L {:.1} 100", x, x)
+            })
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+/// Wspólny zakres osi dla kilku histogramów (suma `visible_range`)
+pub fn common_range(histograms: &[Histogram]) -> Option<(usize, usize)> {
+    histograms.iter()
+        .filter_map(Histogram::visible_range)
+        .reduce(|(a0, a1), (b0, b1)| (a0.min(b0), a1.max(b1)))
+}
+
+/// Dolna krawędź przedziału w EV (stopnie względem 1.0)
+pub fn bin_ev(index: usize) -> f32 {
+    MIN_EV as f32 + index as f32 / BINS_PER_STOP as f32
+}
+
+fn bin_index(v: f32) -> usize {
+    ((v.log2() - MIN_EV as f32) * BINS_PER_STOP as f32).floor().clamp(0.0, (BINS - 1) as f32) as usize
+}
+
+fn bin_value(index: usize) -> f32 {
+    (MIN_EV as f32 + (index as f32 + 0.5) / BINS_PER_STOP as f32).exp2()
+}
+
+/// Histogramy R, G, B i luminancji Rec.709 (kolejność `CHANNELS`) w przestrzeni roboczej (po
+/// macierzy wejściowej i balansie bieli), jednym przebiegiem
+pub fn channel_histograms(pixels: &[(f32, f32, f32, f32)], params: &ToneParams) -> [Histogram; 4] {
+    let empty = || [Histogram::empty(), Histogram::empty(), Histogram::empty(), Histogram::empty()];
+    pixels.par_iter()
+        .fold(empty, |mut h, &(r, g, b, _)| {
+            let (r, g, b) = params.working_rgb(r, g, b);
+            for (hist, v) in h.iter_mut().zip([r, g, b, 0.2126 * r + 0.7152 * g + 0.0722 * b]) {
+                hist.add(v);
+            }
+            h
+        })
+        .reduce(empty, |a, b| {
+            let [a0, a1, a2, a3] = a;
+            let [b0, b1, b2, b3] = b;
+            [a0.merge(b0), a1.merge(b1), a2.merge(b2), a3.merge(b3)]
+        })
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct AutoExposure {
+    pub exposure: f32,
+    pub gamma: f32,
+    /// Wartości sceny percentyli czerni i bieli
+    pub black: f32,
+    pub white: f32,
+}
+
+/// Ekspozycja, przy której percentyl bieli wypada na 1.0 (w trybie display: po tone mappingu),
+/// i gamma, przy której percentyl czerni wypada na 0 w 8 bitach. None dla obrazu bez jasnych
+/// wartości. Wyniki ograniczone do zakresów suwaków.
+pub fn auto_exposure(hist: &Histogram, graph: ProcessingGraph) -> Option<AutoExposure> {
+    let black = hist.percentile(AUTO_LOW_PERCENTILE)?;
+    let white = hist.percentile(AUTO_HIGH_PERCENTILE)?;
+    if white <= 0.0 {
+        return None;
+    }
+    let mut graph = graph;
+    graph.exposure = 0.0;
+    graph.gamma = 1.0;
+    let base = graph.tone_params();
+    let exposure = match graph.exposure_mode {
+        ExposureMode::SceneLinear => -(white * base.scene_multiplier).log2(),
+        ExposureMode::DisplayGain => -base.display_value(white).log2(),
+    };
+    graph.exposure = exposure.clamp(EXPOSURE_RANGE.0, EXPOSURE_RANGE.1);
+
+    // t^(1/gamma) = BLACK_CODE; czerń już zerowa (lub przepalona) – gamma standardowa
+    let t = graph.tone_params().display_value(black);
+    let gamma = if t > 0.0 && t < 1.0 { t.ln() / BLACK_CODE.ln() } else { 2.2 };
+    Some(AutoExposure { exposure: graph.exposure, gamma: gamma.clamp(GAMMA_RANGE.0, GAMMA_RANGE.1), black, white })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn percentiles() {
+        // 1000 próbek: 10 czarnych, reszta od 2^-10 do 2^2
+        let values: Vec<f32> = (0..1000).map(|i| if i < 10 { 0.0 } else { (-10.0 + 12.0 * i as f32 / 999.0).exp2() }).collect();
+        let hist = Histogram::from_values(values.par_iter().copied().chain(rayon::iter::once(f32::NAN)));
+        assert_eq!(hist.total, 1000);
+        assert_eq!(hist.percentile(0.5), Some(0.0));
+        let close = |a: f32, b: f32| (a.log2() - b.log2()).abs() <= 1.0 / BINS_PER_STOP as f32;
+        assert!(close(hist.percentile(50.0).unwrap(), values[500]));
+        assert!(close(hist.percentile(99.5).unwrap(), values[995]));
+        assert!(Histogram::empty().percentile(50.0).is_none());
+    }
+
+    #[test]
+    fn channels_and_markers() {
+        let params = ProcessingGraph::standard(0.0, 2.2).tone_params();
+        let [r, g, b, l] = channel_histograms(&[(1.0, 0.25, 0.0, 1.0), (1.0, 0.25, 0.0, 1.0)], &params);
+        assert_eq!((r.percentile(50.0), g.percentile(50.0), b.percentile(50.0)), (Some(bin_value(bin_index(1.0))), Some(bin_value(bin_index(0.25))), Some(0.0)));
+        assert_eq!(l.total, 2);
+
+        // Wspólny zakres obejmuje R i G (od 0.25 do 1.0) z marginesem; znacznik R na 1.0
+        let range = common_range(&[r.clone(), g, b]).unwrap();
+        assert_eq!(range, (bin_index(0.25) - BINS_PER_STOP, bin_index(1.0) + BINS_PER_STOP));
+        let x = bin_index(1.0) - range.0;
+        assert_eq!(r.marker_commands(range, &[50.0]), format!("M {0}.5 0 L {0}.5 100", x));
+    }
+
+    #[test]
+    fn auto_exposure_maps_white_point() {
+        let values: Vec<f32> = (0..1000).map(|i| (-8.0 + 12.0 * i as f32 / 999.0).exp2()).collect();
+        let hist = Histogram::from_values(values.into_par_iter());
+        let graph = ProcessingGraph::standard(0.0, 2.2);
+        let auto = auto_exposure(&hist, graph).unwrap();
+        let mut tuned = graph;
+        tuned.exposure = auto.exposure;
+        let params = tuned.tone_params();
+        assert!((auto.white * params.scene_multiplier - 1.0).abs() < 1e-3, "{:?}", auto);
+        // Czerń po gammie trafia w 0 w 8 bitach
+        let black = params.display_value(auto.black).powf(1.0 / auto.gamma);
+        assert!((black - BLACK_CODE).abs() < 1e-4, "{:?}", auto);
+        assert!(auto_exposure(&Histogram::from_values(vec![0.0f32; 4].into_par_iter()), graph).is_none());
+    }
+}
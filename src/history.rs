@@ -0,0 +1,112 @@
+// Historia stanu widoku (Ctrl+Z / Ctrl+Y): ekspozycja, gamma, tryb ekspozycji względem tone mappingu
+// oraz wybrana warstwa/kanał. Przeciąganie suwaka daje serię zmian – łączymy je w jeden krok.
+
+use std::time::{Duration, Instant};
+use crate::image_processing::ExposureMode;
+
+/// Maksymalna liczba kroków cofania
+const MAX_STEPS: usize = 100;
+/// Zmiany tego samego parametru w takim odstępie należą do jednego kroku (jedno przeciągnięcie suwaka)
+const COALESCE_WINDOW: Duration = Duration::from_millis(600);
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ViewState {
+    pub exposure: f32,
+    pub gamma: f32,
+    pub exposure_mode: ExposureMode,
+    /// Wybrany węzeł drzewa warstw jako (warstwa, kanał); kanał pusty dla całej warstwy
+    pub selection: Option<(String, String)>,
+}
+
+/// Rodzaj zmiany – decyduje o łączeniu kolejnych zmian w jeden krok
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Change {
+    Exposure,
+    Gamma,
+    ExposureMode,
+    Selection,
+    /// Przełączenie migawek A/B
+    Snapshot,
+    /// Ekspozycja i gamma z percentyli histogramu ("Auto")
+    AutoExposure,
+}
+
+#[derive(Default)]
+pub struct History {
+    undo: Vec<ViewState>,
+    redo: Vec<ViewState>,
+    last_change: Option<(Change, Instant)>,
+}
+
+impl History {
+    /// Zapamiętuje stan sprzed zmiany; ciągłe zmiany tego samego parametru tworzą jeden krok
+    pub fn record(&mut self, before: ViewState, change: Change) {
+        let now = Instant::now();
+        let continues = self.last_change
+            .is_some_and(|(last, at)| last == change && matches!(change, Change::Exposure | Change::Gamma | Change::ExposureMode) && now.duration_since(at) < COALESCE_WINDOW);
+        self.last_change = Some((change, now));
+        if continues || self.undo.last() == Some(&before) {
+            return;
+        }
+        self.undo.push(before);
+        if self.undo.len() > MAX_STEPS {
+            self.undo.remove(0);
+        }
+        self.redo.clear();
+    }
+
+    pub fn undo(&mut self, current: ViewState) -> Option<ViewState> {
+        let state = self.undo.pop()?;
+        self.redo.push(current);
+        self.last_change = None;
+        Some(state)
+    }
+
+    pub fn redo(&mut self, current: ViewState) -> Option<ViewState> {
+        let state = self.redo.pop()?;
+        self.undo.push(current);
+        self.last_change = None;
+        Some(state)
+    }
+}
+
+/// Migawki A/B parametrów widoku: zapis A, zmiany (B), przełączanie jednym klawiszem.
+/// Zmiany wprowadzone na aktywnej migawce trafiają do niej przy przełączeniu.
+#[derive(Default)]
+pub struct AbSnapshots {
+    a: Option<ViewState>,
+    b: Option<ViewState>,
+    showing_a: bool,
+}
+
+impl AbSnapshots {
+    /// Nowa migawka A; dalsze zmiany tworzą wariant B
+    pub fn store_a(&mut self, state: ViewState) {
+        self.a = Some(state);
+        self.b = None;
+        self.showing_a = false;
+    }
+
+    /// Zapisuje bieżący stan w aktywnej migawce i zwraca drugą (None bez zapisanego A)
+    pub fn toggle(&mut self, current: ViewState) -> Option<ViewState> {
+        let a = self.a.clone()?;
+        let target = if self.showing_a {
+            self.a = Some(current);
+            self.b.clone()?
+        } else {
+            self.b = Some(current);
+            a
+        };
+        self.showing_a = !self.showing_a;
+        Some(target)
+    }
+
+    /// Etykieta aktywnej migawki ("" gdy brak A)
+    pub fn active_label(&self) -> &'static str {
+        match (&self.a, self.showing_a) {
+            (None, _) => "",
+            (Some(_), true) => "A",
+            (Some(_), false) => "B",
+        }
+    }
+}
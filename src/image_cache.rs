@@ -1,10 +1,33 @@
-use slint::{Image, Rgba8Pixel, SharedPixelBuffer};
+use slint::Rgba8Pixel;
 use exr::prelude as exr;
-use std::path::PathBuf;
-use crate::image_processing::process_pixel;
+use std::path::{Path, PathBuf};
+use crate::image_processing::{local_adaptation, false_color_pixel, vector_to_hsv, shade_normal, focus_peak, GAMUT_WARNING_COLOR, ChannelRemap, DisplayTransform, GamutWarning, GrayscaleMode, ToneParams, VectorView};
+use crate::render_settings::RenderSettings;
 use rayon::prelude::*;
 use std::collections::HashMap;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::sync::{Arc, Mutex};
 use crate::utils::split_layer_and_short;
+use crate::cancel::{CancelToken, CancellableReader};
+use crate::utils::error_handling::{ExrError, ExrResult};
+use crate::layer_cache::{self, CachedLayer, LayerCache, Pixels};
+use crate::color_picker::ColorSample;
+use crate::exr_metadata::{detect_part_color_space, DetectedColorSpace};
+use crate::metrics::{region_stats, RegionStats};
+use crate::simd_processing;
+use crate::tiles::{self, Rect, TileJob};
+use crate::raw_image::RawImage;
+use crate::display_filters;
+use crate::env_map::{self, EnvMapKind};
+use crate::stereo::{self, StereoMode};
+use crate::histogram::{self, Histogram};
+use crate::render_cache::RenderCache;
+use crate::ui_handlers::lock_or_recover;
+use crate::progress::ProgressSink;
+use tracing::{debug, warn};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use crate::io::luminance_chroma;
+use crate::io::recovery::{self, Damage};
 
 /// Zwraca kanoniczny skrót kanału na podstawie aliasów/nazw przyjaznych.
 /// Np. "red"/"Red"/"RED"/"R"/"R8" → "R"; analogicznie dla G/B/A.
@@ -23,6 +46,32 @@ pub(crate) fn channel_alias_to_short(input: &str) -> String {
 pub struct LayerInfo {
     pub name: String,
     pub channels: Vec<ChannelInfo>,
+    /// Przestrzeń barw części pliku, w której warstwa występuje po raz pierwszy
+    pub color_space: DetectedColorSpace,
+    /// Początek okna danych tej części (współrzędne pikseli EXR)
+    pub data_origin: (i32, i32),
+}
+
+impl LayerInfo {
+    /// Jedyny kanał danych warstwy monochromatycznej (zob. `monochrome_channel`)
+    pub fn monochrome_channel(&self) -> Option<&str> {
+        monochrome_channel(self.channels.iter().map(|c| c.name.as_str()))
+    }
+}
+
+/// Warstwa monochromatyczna: bez kanałów R/G/B, dokładnie jeden kanał poza alfą (luminancja Y
+/// albo pojedynczy kanał danych). Zwraca jego krótką nazwę; takie warstwy pomijają kompozyt RGB.
+pub(crate) fn monochrome_channel<'a>(names: impl IntoIterator<Item = &'a str>) -> Option<&'a str> {
+    let mut found = None;
+    for name in names {
+        match channel_alias_to_short(name).as_str() {
+            "A" => {}
+            "R" | "G" | "B" => return None,
+            _ if found.is_some() => return None,
+            _ => found = Some(name),
+        }
+    }
+    found
 }
 
 // split_layer_and_short przeniesione do utils
@@ -32,137 +81,646 @@ pub struct ChannelInfo {
     pub name: String,           // krótka nazwa (po ostatniej kropce)
 }
 
+/// Odcinek (DDA) rysowany funkcją `plot(x, y)`
+pub(crate) fn draw_line(plot: &mut impl FnMut(i64, i64), x0: f32, y0: f32, x1: f32, y1: f32) {
+    let steps = (x1 - x0).abs().max((y1 - y0).abs()).ceil().max(1.0);
+    for i in 0..=steps as u32 {
+        let t = i as f32 / steps;
+        plot((x0 + (x1 - x0) * t).round() as i64, (y0 + (y1 - y0) * t).round() as i64);
+    }
+}
+
+/// Surowe piksele zaznaczenia i jego obrys w pikselach źródłowych [x, y, szer., wys.]
+type RegionPixels = (Vec<(f32, f32, f32, f32)>, [u32; 4]);
+
+/// Standardowy pipeline koloru bez transformacji wyświetlania: wiersz kafelka jest ciągły w źródle,
+/// więc liczy go kernel wektorowy
+struct ToneRowJob<'a> {
+    pixels: &'a [(f32, f32, f32, f32)],
+    width: u32,
+    params: ToneParams,
+}
+
+impl TileJob for ToneRowJob<'_> {
+    fn pixel(&self, x: u32, y: u32) -> Rgba8Pixel {
+        let (r, g, b, a) = self.pixels[y as usize * self.width as usize + x as usize];
+        self.params.pixel(r, g, b, a)
+    }
+
+    fn row(&self, x: u32, y: u32, out: &mut [Rgba8Pixel]) {
+        let start = y as usize * self.width as usize + x as usize;
+        simd_processing::process_row(&self.params, &self.pixels[start..start + out.len()], out);
+    }
+}
+
 pub struct ImageCache {
-    pub raw_pixels: Vec<(f32, f32, f32, f32)>,
+    pub raw_pixels: Pixels,
     pub width: u32,
     pub height: u32,
     pub layers_info: Vec<LayerInfo>,
     pub current_layer_name: String,
+    /// Mapowanie zakresu dla AOV technicznych; None = zwykły pipeline ekspozycji i tone mappingu
+    pub channel_remap: Option<ChannelRemap>,
+    /// Widok wektorów 2D (motion vectors) zamiast pipeline'u koloru
+    pub vector_view: Option<VectorView>,
+    /// Warstwa normalnych – przy włączonym relight cieniowana światłem kierunkowym (N·L)
+    pub normals_view: bool,
+    /// Widok głębi (auto-normalizacja percentylowa); wartość = odwrócenie skali
+    pub depth_view: Option<bool>,
+    /// Plik zawiera dane deep – obraz to spłaszczony podgląd (patrz `deep_exr`)
+    pub deep_preview: bool,
+    /// Plik uszkodzony (np. obcięty zapis) – obraz złożony z odzyskanych bloków
+    pub damage: Option<Damage>,
+    /// Bieżący obraz jest monochromatyczny (warstwa Y/jednokanałowa albo widok kanału): piksele
+    /// R=G=B bez macierzy wejściowej i balansu bieli, histogram tylko luminancji
+    pub monochrome: bool,
+    /// Plik jest mapą otoczenia – widok może ją przeprojektować (`RenderSettings::env_view`)
+    pub env_map: Option<EnvMapKind>,
+    /// Widoki pliku stereo/wielowidokowego (`stereo::parse_views`); puste dla zwykłego pliku
+    pub views: Vec<String>,
+    /// Tryb stereo widoku (`set_stereo_mode`); decyduje, czy przy zmianie warstwy wczytać drugie oko
+    stereo_mode: StereoMode,
+    /// Piksele drugiego oka bieżącej warstwy, gdy tryb stereo składa oba widoki
+    stereo_pair: Option<Pixels>,
+    /// Ostatnio oglądane warstwy i kanały tego pliku
+    layer_cache: LayerCache,
+    /// Kanały planarne warstw (wg nazwy) – kompozyt i widoki kanałów powstają z nich bez ponownego
+    /// odczytu pliku; łączny rozmiar ograniczony budżetem pamięci podręcznej
+    channels: HashMap<String, Arc<LayerChannels>>,
+    /// Ostatnie obrazy podglądu wg hasha parametrów (przełączanie A/B bez przeliczania)
+    renders: Mutex<RenderCache>,
+}
+
+/// Rodzaj renderu podglądu – część klucza `RenderCache`
+#[derive(Clone, Copy, Hash)]
+enum RenderKind {
+    Image,
+    Composite { lighting_rgb: bool },
+    Thumbnail { max_size: u32 },
 }
 
 impl ImageCache {
-    pub fn new(path: &PathBuf) -> anyhow::Result<Self> {
+    /// Wczytuje plik; anulowanie `cancel` przerywa dekodowanie i zwraca błąd
+    /// Postęp dekodowania warstw (0..1) trafia do `progress`
+    pub fn new(path: &PathBuf, cancel: &CancelToken, progress: &dyn ProgressSink) -> ExrResult<Self> {
         // Najpierw wyciągnij informacje o warstwach, wybierz najlepszą i wczytaj ją jako startowy podgląd
-        let layers_info = extract_layers_info(path)?;
-        let best_layer = find_best_layer(&layers_info);
-        let (raw_pixels, width, height, current_layer_name) = load_specific_layer(path, &best_layer)?;
+        let headers = crate::deep_exr::read_headers(path)?;
+        let layers_info = layers_info_from_headers(&headers);
+        cancel.check()?;
+
+        // Pliki deep nie przejdą przez zwykły odczyt – spłaszczamy próbki do podglądu
+        let deep_preview = crate::deep_exr::has_deep_parts(&headers);
+        let mut channels = HashMap::new();
+        let (raw_pixels, width, height, current_layer_name) = if deep_preview {
+            crate::deep_exr::load_flattened(path, cancel)?
+        } else {
+            let best = find_best_layer(&layers_info);
+            channels = build_layer_channels(path, &layers_info, &best, cancel, progress)?;
+            match channels.get(&best) {
+                Some(layer) => (layer.compose_rgba()?, layer.width, layer.height, best),
+                None => load_layer_with_channels(path, &best, cancel)?.0,
+            }
+        };
+
+        let damage = channels.values().find_map(|c| c.damage);
+        let monochrome = !deep_preview && is_monochrome_layer(&layers_info, &current_layer_name);
+        let env_map = env_map::detect(&headers);
+        let views = stereo::parse_views(&headers);
+        let raw_pixels: Pixels = raw_pixels.into();
+        let mut layer_cache = LayerCache::new(layer_cache::DEFAULT_BUDGET_BYTES);
+        layer_cache.insert(
+            layer_cache::key(&current_layer_name, None),
+            CachedLayer { pixels: raw_pixels.clone(), width, height, name: current_layer_name.clone() },
+        );
 
-        Ok(ImageCache { raw_pixels, width, height, layers_info, current_layer_name })
+        let cache = ImageCache { raw_pixels, width, height, layers_info, current_layer_name, channel_remap: None, vector_view: None, normals_view: false, depth_view: None, deep_preview, damage, monochrome, env_map, views, stereo_mode: StereoMode::Single, stereo_pair: None, layer_cache, channels, renders: Mutex::default() };
+        Ok(cache)
     }
-    
-    pub fn load_layer(&mut self, path: &PathBuf, layer_name: &str) -> anyhow::Result<()> {
-        let (raw_pixels, width, height, current_layer_name) = load_specific_layer(path, layer_name)?;
-        
-        self.raw_pixels = raw_pixels;
-        self.width = width;
-        self.height = height;
-        self.current_layer_name = current_layer_name;
-        
+
+    pub fn load_layer(&mut self, path: &PathBuf, layer_name: &str) -> ExrResult<()> {
+        let layer = self.layer_pixels(path, layer_name)?;
+        self.show(layer);
+        self.monochrome = is_monochrome_layer(&self.layers_info, layer_name);
+        self.update_stereo_pair(path);
         Ok(())
     }
+
+    /// Kompozyt warstwy z pamięci podręcznej albo zdekodowany (i zapamiętany) – bez zmiany bieżącego obrazu
+    fn layer_pixels(&mut self, path: &PathBuf, layer_name: &str) -> ExrResult<CachedLayer> {
+        let known = self.channels.get(layer_name).cloned();
+        let mut loaded = None;
+        let layer = self.cached_or_load(layer_cache::key(layer_name, None), || match &known {
+            Some(layer) => Ok((layer.compose_rgba()?, layer.width, layer.height, layer_name.to_string())),
+            None => {
+                let (layer, channels) = load_layer_with_channels(path, layer_name, &CancelToken::new())?;
+                loaded = channels;
+                Ok(layer)
+            }
+        })?;
+        if let Some(channels) = loaded {
+            self.keep_channels(channels);
+        }
+        Ok(layer)
+    }
+
+    /// Ustawia tryb stereo widoku i wczytuje (albo zwalnia) drugie oko bieżącej warstwy
+    pub fn set_stereo_mode(&mut self, path: &PathBuf, mode: StereoMode) {
+        self.stereo_mode = mode;
+        self.update_stereo_pair(path);
+    }
+
+    /// Wczytuje drugie oko bieżącej warstwy, gdy tryb stereo go wymaga (inaczej zwalnia poprzednie).
+    /// Widok bez odpowiednika albo o innym rozmiarze zostaje pokazany pojedynczo.
+    fn update_stereo_pair(&mut self, path: &PathBuf) {
+        self.stereo_pair = None;
+        if self.stereo_mode == StereoMode::Single || self.views.is_empty() {
+            return;
+        }
+        let (left, right) = stereo::eye_views(&self.views);
+        let other = if stereo::view_of(&self.current_layer_name, &self.views) == right { left } else { right };
+        let Some(layer) = stereo::layer_in_view(&self.current_layer_name, other, &self.views, self.layers_info.iter().map(|l| l.name.as_str())) else {
+            debug!(target: "processing", "no stereo counterpart for layer '{}'", self.current_layer_name);
+            return;
+        };
+        match self.layer_pixels(path, &layer) {
+            Ok(pair) if (pair.width, pair.height) == (self.width, self.height) => self.stereo_pair = Some(pair.pixels),
+            Ok(pair) => warn!(target: "processing", "stereo view '{}' is {}x{}, current is {}x{} – showing single view", layer, pair.width, pair.height, self.width, self.height),
+            Err(e) => warn!(target: "io", "loading stereo view '{}': {}", layer, e),
+        }
+    }
+
+    /// Zapamiętuje kanały wczytanej na żądanie warstwy; po przekroczeniu budżetu zostają tylko one
+    fn keep_channels(&mut self, channels: Arc<LayerChannels>) {
+        let total: usize = self.channels.values().chain(std::iter::once(&channels)).map(|c| c.byte_size()).sum();
+        if total > layer_cache::DEFAULT_BUDGET_BYTES {
+            self.channels.clear();
+        }
+        self.channels.insert(channels.name.clone(), channels);
+    }
+
+    /// Warstwa z pamięci podręcznej (bez kopiowania pikseli) albo zdekodowana i zapamiętana
+    fn cached_or_load(&mut self, key: String, load: impl FnOnce() -> ExrResult<LoadedLayer>) -> ExrResult<CachedLayer> {
+        if let Some(layer) = self.layer_cache.get(&key) {
+            return Ok(layer);
+        }
+        let (pixels, width, height, name) = load()?;
+        let layer = CachedLayer { pixels: pixels.into(), width, height, name };
+        self.layer_cache.insert(key, layer.clone());
+        Ok(layer)
+    }
+
+    fn show(&mut self, layer: CachedLayer) {
+        self.raw_pixels = layer.pixels;
+        self.width = layer.width;
+        self.height = layer.height;
+        self.current_layer_name = layer.name;
+    }
     
-    pub fn process_to_image(&self, exposure: f32, gamma: f32) -> Image {
-        let mut buffer = SharedPixelBuffer::<Rgba8Pixel>::new(self.width, self.height);
-        let slice = buffer.make_mut_slice();
-        
-        // Użycie większych chunków dla lepszej wydajności
-        let chunk_size = if self.raw_pixels.len() > 1_000_000 { 
-            4096 
-        } else { 
-            2048 
+    /// Parametry tonalne bieżącego obrazu; monochromatyczny nie ma barw, więc bez macierzy
+    /// wejściowej i balansu bieli (szarość zostaje szarością)
+    fn tone_params(&self, settings: &RenderSettings) -> ToneParams {
+        let params = settings.graph.tone_params();
+        if self.monochrome { params.without_matrix() } else { params }
+    }
+
+    /// Piksel podglądu: mapowanie zakresu (AOV techniczne) albo pipeline z `ProcessingGraph`
+    /// (macierz wejściowa i balans bieli, redukcja do skali szarości, jeśli wybrano taki tryb widoku)
+    #[inline]
+    fn render_pixel(&self, r: f32, g: f32, b: f32, a: f32, params: &ToneParams, settings: &RenderSettings) -> Rgba8Pixel {
+        if let Some(remap) = self.channel_remap {
+            return remap.remap_pixel(r, g, b, a);
+        }
+        let (r, g, b) = params.working_rgb(r, g, b);
+        if settings.gamut_warning.is_out_of_gamut(r, g, b) {
+            return GAMUT_WARNING_COLOR;
+        }
+        if settings.false_color {
+            return false_color_pixel(GrayscaleMode::Luma.reduce(r, g, b), params);
+        }
+        let params = params.without_matrix();
+        match settings.grayscale {
+            GrayscaleMode::Off => params.pixel(r, g, b, a),
+            mode => {
+                let y = mode.reduce(r, g, b);
+                params.pixel(y, y, y, a)
+            }
+        }
+    }
+
+    /// Zadanie wektorowe, gdy żadne ustawienie nie wymaga pełnego `render_pixel`
+    /// (mapowanie AOV, skala szarości, ostrzeżenie o gamucie, false color, obrót/odbicie)
+    fn tone_row_job(&self, params: ToneParams, settings: &RenderSettings) -> Option<ToneRowJob<'_>> {
+        let plain = self.channel_remap.is_none()
+            && settings.grayscale == GrayscaleMode::Off
+            && settings.gamut_warning == GamutWarning::Off
+            && !settings.false_color
+            && settings.transform.is_identity();
+        plain.then(|| ToneRowJob { pixels: &self.raw_pixels, width: self.width, params })
+    }
+
+    pub fn process_to_image(&self, settings: &RenderSettings) -> RawImage {
+        self.cached(RenderKind::Image, settings, || self.with_scene_passes(settings, |c| c.render_image(settings)))
+    }
+
+    fn render_image(&self, settings: &RenderSettings) -> RawImage {
+        if let Some(view) = self.vector_view {
+            return self.process_vector_image(view, settings);
+        }
+        if let Some(invert) = self.depth_view {
+            return self.process_depth_image(invert, settings);
+        }
+        if let Some(light) = self.relight(settings) {
+            return self.map_pixels(&settings.transform, |_, (r, g, b, _)| shade_normal((r, g, b), light));
+        }
+        let params = self.tone_params(settings);
+        if let Some(job) = self.tone_row_job(params, settings) {
+            return tiles::render(&job, Rect::full(self.width, self.height));
+        }
+        self.map_pixels(&settings.transform, |_, (r, g, b, a)| self.render_pixel(r, g, b, a, &params, settings))
+    }
+
+    pub fn process_to_composite(&self, settings: &RenderSettings, lighting_rgb: bool) -> RawImage {
+        self.cached(RenderKind::Composite { lighting_rgb }, settings, || {
+            self.with_scene_passes(settings, |c| c.render_composite(settings, lighting_rgb))
+        })
+    }
+
+    fn render_composite(&self, settings: &RenderSettings, lighting_rgb: bool) -> RawImage {
+        // Przetwarzanie pikseli: jeśli lighting_rgb=true (lub ogólnie warstwa kolorowa), zachowujemy normalne RGB
+        // (o ile nie wybrano widoku w skali szarości); w przeciwnym razie grayscale wg wybranej redukcji
+        // (domyślnie luminancja Rec.709), liczonej w przestrzeni sceny przed tone mappingiem.
+        if let Some(view) = self.vector_view {
+            return self.process_vector_image(view, settings);
+        }
+        if let Some(invert) = self.depth_view {
+            return self.process_depth_image(invert, settings);
+        }
+        if let Some(light) = self.relight(settings) {
+            return self.map_pixels(&settings.transform, |_, (r, g, b, _)| shade_normal((r, g, b), light));
+        }
+        let params = self.tone_params(settings);
+        let working = params.without_matrix();
+        self.map_pixels(&settings.transform, |_, (r, g, b, a)| {
+            if lighting_rgb || self.channel_remap.is_some() {
+                self.render_pixel(r, g, b, a, &params, settings)
+            } else {
+                let (r, g, b) = params.working_rgb(r, g, b);
+                let y = settings.grayscale.reduce(r, g, b);
+                if settings.false_color { false_color_pixel(y, &params) } else { working.pixel(y, y, y, a) }
+            }
+        })
+    }
+
+    /// Hash wszystkiego, od czego zależy obraz podglądu poza samymi pikselami: parametry tonalne
+    /// z `ProcessingGraph`, tryby widoku, transformacja wyświetlania i rozmiar wyjścia
+    fn render_key(&self, kind: RenderKind, settings: &RenderSettings) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        kind.hash(&mut hasher);
+        self.tone_params(settings).hash(&mut hasher);
+        (settings.grayscale, settings.gamut_warning, settings.false_color, settings.transform).hash(&mut hasher);
+        self.channel_remap.map(|r| (r.gain.to_bits(), r.offset.to_bits(), r.abs)).hash(&mut hasher);
+        self.vector_view.map(|view| (view, settings.vector_max_magnitude.to_bits(), settings.vector_arrows)).hash(&mut hasher);
+        self.relight(settings).map(|light| light.map(f32::to_bits)).hash(&mut hasher);
+        self.depth_view.map(|invert| (invert, settings.focus_band.map(|(near, far)| (near.to_bits(), far.to_bits())))).hash(&mut hasher);
+        settings.bloom.map(|b| (b.threshold.to_bits(), b.intensity.to_bits())).hash(&mut hasher);
+        settings.graph.local_adaptation().map(|l| (l.radius.to_bits(), l.strength.to_bits())).hash(&mut hasher);
+        self.env_map.map(|kind| (kind, settings.env_view)).hash(&mut hasher);
+        self.stereo_pair.as_ref().map(|pair| (settings.stereo_mode, Arc::as_ptr(pair) as *const () as usize)).hash(&mut hasher);
+        hasher.finish()
+    }
+
+    /// `render` na kopii pikseli po przebiegach całego obrazu w wartościach sceny – złożenie obu oczu
+    /// stereo (`stereo::compose`) albo przeprojektowanie mapy otoczenia (`env_map::reproject`), w dowolnym
+    /// widoku; poświata świateł (`display_filters::add_bloom`), potem lokalny tone mapping – gdy widok
+    /// to zwykły pipeline koloru; bez przebiegów na `self`
+    fn with_scene_passes<T>(&self, settings: &RenderSettings, render: impl FnOnce(&ImageCache) -> T) -> T {
+        let plain = self.channel_remap.is_none() && self.vector_view.is_none() && self.depth_view.is_none() && self.relight(settings).is_none() && !settings.false_color;
+        let bloom = settings.bloom.filter(|_| plain);
+        let local = settings.graph.local_adaptation().filter(|_| plain);
+        let reprojected = self.stereo_image(settings.stereo_mode)
+            .or_else(|| self.env_map.and_then(|kind| env_map::reproject(&self.raw_pixels, self.width as usize, self.height as usize, kind, settings.env_view)));
+        if bloom.is_none() && local.is_none() && reprojected.is_none() {
+            return render(self);
+        }
+        let (mut pixels, width, height) = reprojected.unwrap_or_else(|| (self.raw_pixels.to_vec(), self.width as usize, self.height as usize));
+        if let Some(bloom) = bloom {
+            pixels = display_filters::add_bloom(&pixels, width, height, settings.graph.tone_params().scene_multiplier, &bloom);
+        }
+        if let Some(local) = local {
+            pixels = local_adaptation(&pixels, width, height, &local);
+        }
+        let mut processed = self.detached();
+        processed.raw_pixels = pixels.into();
+        (processed.width, processed.height) = (width as u32, height as u32);
+        render(&processed)
+    }
+
+    /// Obraz z pamięci podręcznej renderów albo `render` (blokada nie jest trzymana podczas liczenia)
+    fn cached(&self, kind: RenderKind, settings: &RenderSettings, render: impl FnOnce() -> RawImage) -> RawImage {
+        let key = self.render_key(kind, settings);
+        if let Some(image) = lock_or_recover(&self.renders).get(key, &self.raw_pixels) {
+            return image;
+        }
+        let image = render();
+        lock_or_recover(&self.renders).insert(key, &self.raw_pixels, image.clone());
+        image
+    }
+
+    /// Obraz obu oczu w trybie stereo `mode` (bieżąca warstwa to lewe albo prawe oko)
+    fn stereo_image(&self, mode: StereoMode) -> Option<SceneImage> {
+        let pair = self.stereo_pair.as_ref()?;
+        let right = stereo::eye_views(&self.views).1;
+        let (left, right) = if stereo::view_of(&self.current_layer_name, &self.views) == right { (pair, &self.raw_pixels) } else { (&self.raw_pixels, pair) };
+        stereo::compose(mode, left, right, self.width as usize, self.height as usize)
+    }
+
+    /// Kierunek światła, jeśli bieżąca warstwa to normalne, a podgląd relight jest włączony
+    fn relight(&self, settings: &RenderSettings) -> Option<[f32; 3]> {
+        settings.relight.filter(|_| self.normals_view)
+    }
+
+    /// Podgląd wektorów 2D: kodowanie HSV oraz opcjonalnie rzadkie strzałki
+    /// (rysowane w układzie źródła, więc obracają się razem z obrazem)
+    fn process_vector_image(&self, view: VectorView, settings: &RenderSettings) -> RawImage {
+        let max_magnitude = settings.vector_max_magnitude;
+        let mut colors: Vec<Rgba8Pixel> = self.raw_pixels.par_iter()
+            .map(|&px| {
+                let (x, y) = view.vector(px);
+                vector_to_hsv(x, y, max_magnitude)
+            })
+            .collect();
+        if settings.vector_arrows {
+            self.draw_vector_arrows(&mut colors, view, max_magnitude);
+        }
+        self.map_pixels(&settings.transform, |src, _| colors[src])
+    }
+
+    /// Strzałka co `SPACING` pikseli; długość proporcjonalna do wektora (pełna przy `max_magnitude`).
+    /// Oś y wektora skierowana w górę (konwencja Nuke), oś y obrazu w dół.
+    fn draw_vector_arrows(&self, colors: &mut [Rgba8Pixel], view: VectorView, max_magnitude: f32) {
+        const SPACING: u32 = 24;
+        const HEAD: f32 = 4.0;
+        let (w, h) = (self.width as i64, self.height as i64);
+        let mut plot = |x: i64, y: i64| {
+            if x >= 0 && y >= 0 && x < w && y < h {
+                colors[(y * w + x) as usize] = Rgba8Pixel { r: 255, g: 255, b: 255, a: 255 };
+            }
         };
-        
-        // Przetwarzanie z lepszą lokalność pamięci
-        self.raw_pixels.par_chunks(chunk_size)
-            .zip(slice.par_chunks_mut(chunk_size))
-            .for_each(|(input_chunk, output_chunk)| {
-                for (input_pixel, output_pixel) in input_chunk.iter().zip(output_chunk.iter_mut()) {
-                    let (r, g, b, a) = *input_pixel;
-                    *output_pixel = process_pixel(r, g, b, a, exposure, gamma);
+        for cy in (SPACING / 2..self.height).step_by(SPACING as usize) {
+            for cx in (SPACING / 2..self.width).step_by(SPACING as usize) {
+                let (vx, vy) = view.vector(self.raw_pixels[(cy * self.width + cx) as usize]);
+                let magnitude = (vx * vx + vy * vy).sqrt();
+                if !magnitude.is_finite() || magnitude < 1e-6 { continue; }
+                let len = (magnitude / max_magnitude).min(1.0) * (SPACING as f32 - HEAD);
+                let (dx, dy) = (vx / magnitude, -vy / magnitude);
+                let (x0, y0) = (cx as f32, cy as f32);
+                let (x1, y1) = (x0 + dx * len, y0 + dy * len);
+                draw_line(&mut plot, x0, y0, x1, y1);
+                // Grot: dwa odcinki odchylone o ±150° od kierunku
+                for angle in [2.6f32, -2.6] {
+                    let (sin, cos) = angle.sin_cos();
+                    draw_line(&mut plot, x1, y1, x1 + (dx * cos - dy * sin) * HEAD, y1 + (dx * sin + dy * cos) * HEAD);
                 }
-            });
-        
-        Image::from_rgba8(buffer)
-    }
-
-    pub fn process_to_composite(&self, exposure: f32, gamma: f32, lighting_rgb: bool) -> Image {
-        let mut buffer = SharedPixelBuffer::<Rgba8Pixel>::new(self.width, self.height);
-        let slice = buffer.make_mut_slice();
-
-        // Przetwarzanie pikseli: jeśli lighting_rgb=true (lub ogólnie warstwa kolorowa), zachowujemy normalne RGB;
-        // w przeciwnym razie generujemy grayscale jako sumę R+G+B (po tone map i gamma).
-        self.raw_pixels
-            .par_iter()
-            .zip(slice.par_iter_mut())
-            .for_each(|(&(r, g, b, a), out)| {
-                if lighting_rgb {
-                    *out = process_pixel(r, g, b, a, exposure, gamma);
-                } else {
-                    // Utrzymaj istniejące zachowanie grayscale
-                    let px = process_pixel(r, g, b, a, exposure, gamma);
-                    let rr = (px.r as f32) / 255.0;
-                    let gg = (px.g as f32) / 255.0;
-                    let bb = (px.b as f32) / 255.0;
-                    let gray = (rr.max(gg).max(bb)).clamp(0.0, 1.0);
-                    let g8 = (gray * 255.0).round().clamp(0.0, 255.0) as u8;
-                    *out = Rgba8Pixel { r: g8, g: g8, b: g8, a: px.a };
-                }
-            });
+            }
+        }
+    }
+
+    /// Generuje obraz funkcją `f(indeks_źródłowy, piksel)` dla każdego piksela,
+    /// z uwzględnieniem obrotu/odbicia (remapowanie indeksów)
+    pub(crate) fn map_pixels<F>(&self, transform: &DisplayTransform, f: F) -> RawImage
+    where
+        F: Fn(usize, (f32, f32, f32, f32)) -> Rgba8Pixel + Sync,
+    {
+        let (out_w, out_h) = transform.output_size(self.width, self.height);
+        let identity = transform.is_identity();
+        let job = |x: u32, y: u32| {
+            let src = if identity {
+                y as usize * self.width as usize + x as usize
+            } else {
+                transform.source_index(x, y, self.width, self.height)
+            };
+            f(src, self.raw_pixels[src])
+        };
+        tiles::render(&job, Rect::full(out_w, out_h))
+    }
+
+    /// Kopia do renderu w tle: współdzieli piksele bieżącej warstwy (Arc), bez pamięci podręcznej warstw
+    pub fn detached(&self) -> ImageCache {
+        ImageCache {
+            raw_pixels: self.raw_pixels.clone(),
+            width: self.width,
+            height: self.height,
+            layers_info: self.layers_info.clone(),
+            current_layer_name: self.current_layer_name.clone(),
+            channel_remap: self.channel_remap,
+            vector_view: self.vector_view,
+            normals_view: self.normals_view,
+            depth_view: self.depth_view,
+            deep_preview: self.deep_preview,
+            damage: self.damage,
+            monochrome: self.monochrome,
+            env_map: self.env_map,
+            views: self.views.clone(),
+            stereo_mode: self.stereo_mode,
+            stereo_pair: self.stereo_pair.clone(),
+            layer_cache: LayerCache::new(0),
+            channels: HashMap::new(),
+            renders: Mutex::default(),
+        }
+    }
+
+    /// Render eksportu w pełnej rozdzielczości: kafelki z anulowaniem i postępem po każdym kafelku.
+    /// Zawsze bez nakładek diagnostycznych i tylko z filtrami włączonymi dla eksportu
+    /// (`RenderSettings::for_export`), niezależnie od trybów widoku w `settings`.
+    pub fn render_full_resolution(&self, settings: &RenderSettings, cancel: &CancelToken, progress: &(dyn Fn(f32) + Sync)) -> ExrResult<RawImage> {
+        let settings = settings.for_export();
+        let mut image = self.with_scene_passes(&settings, |c| c.render_look(&settings, cancel, progress))?;
+        if let Some(sharpen) = settings.sharpen {
+            display_filters::unsharp_mask(&mut image, sharpen.amount, sharpen.radius);
+        }
+        Ok(image)
+    }
+
+    /// Widoki specjalne (wektory, głębia, relight) liczone są jednym przebiegiem jak w podglądzie
+    fn render_look(&self, settings: &RenderSettings, cancel: &CancelToken, progress: &(dyn Fn(f32) + Sync)) -> ExrResult<RawImage> {
+        if self.vector_view.is_some() || self.depth_view.is_some() || self.relight(settings).is_some() {
+            let image = self.render_image(settings);
+            progress(1.0);
+            return Ok(image);
+        }
+        let params = self.tone_params(settings);
+        if let Some(job) = self.tone_row_job(params, settings) {
+            return tiles::render_with(&job, Rect::full(self.width, self.height), cancel, progress);
+        }
+        let transform = settings.transform;
+        let (out_w, out_h) = transform.output_size(self.width, self.height);
+        let job = |x: u32, y: u32| {
+            let (r, g, b, a) = self.raw_pixels[transform.source_index(x, y, self.width, self.height)];
+            self.render_pixel(r, g, b, a, &params, settings)
+        };
+        tiles::render_with(&job, Rect::full(out_w, out_h), cancel, progress)
+    }
 
-        Image::from_rgba8(buffer)
+    /// Pełna rozdzielczość jako RGB f32 w orientacji `settings.transform`, piksele przez `map` (eksport
+    /// bez tone mappingu podglądu i bez nakładek, zob. `export_handlers::OutputTransform`); anulowanie
+    /// sprawdzane co wiersz
+    pub fn render_rgb_f32(&self, settings: &RenderSettings, map: &(dyn Fn(f32, f32, f32) -> [f32; 3] + Sync), cancel: &CancelToken) -> ExrResult<(Vec<f32>, u32, u32)> {
+        let transform = settings.transform;
+        let (out_w, out_h) = transform.output_size(self.width, self.height);
+        let mut out = vec![0.0f32; out_w as usize * out_h as usize * 3];
+        out.par_chunks_mut(out_w as usize * 3).enumerate().try_for_each(|(y, row)| -> ExrResult<()> {
+            cancel.check()?;
+            for (x, px) in row.chunks_exact_mut(3).enumerate() {
+                let (r, g, b, _) = self.raw_pixels[transform.source_index(x as u32, y as u32, self.width, self.height)];
+                px.copy_from_slice(&map(r, g, b));
+            }
+            Ok(())
+        })?;
+        Ok((out, out_w, out_h))
     }
+
     // Nowa metoda dla preview (szybsze przetwarzanie małego obrazka)
-    pub fn process_to_thumbnail(&self, exposure: f32, gamma: f32, max_size: u32) -> Image {
-        let scale = (max_size as f32 / self.width.max(self.height) as f32).min(1.0);
-        let thumb_width = (self.width as f32 * scale) as u32;
-        let thumb_height = (self.height as f32 * scale) as u32;
-        
-        let mut buffer = SharedPixelBuffer::<Rgba8Pixel>::new(thumb_width, thumb_height);
-        let slice = buffer.make_mut_slice();
-        
+    pub fn process_to_thumbnail(&self, settings: &RenderSettings, max_size: u32) -> RawImage {
+        self.cached(RenderKind::Thumbnail { max_size }, settings, || {
+            self.with_scene_passes(settings, |c| c.render_thumbnail(settings, max_size))
+        })
+    }
+
+    fn render_thumbnail(&self, settings: &RenderSettings, max_size: u32) -> RawImage {
+        // Strzałki wymagają pełnej rozdzielczości (siatka w pikselach źródła)
+        if let Some(view) = self.vector_view {
+            return self.process_vector_image(view, settings);
+        }
+        if self.relight(settings).is_some() || self.depth_view.is_some() {
+            return self.render_image(settings);
+        }
+        let transform = settings.transform;
+        let (out_w, out_h) = transform.output_size(self.width, self.height);
+        let scale = (max_size as f32 / out_w.max(out_h) as f32).min(1.0);
+        let thumb_width = (out_w as f32 * scale) as u32;
+        let thumb_height = (out_h as f32 * scale) as u32;
+
         // Proste nearest neighbor sampling dla szybkości
-        slice.par_iter_mut().enumerate().for_each(|(i, pixel)| {
-            let x = (i as u32) % thumb_width;
-            let y = (i as u32) / thumb_width;
-            
-            let src_x = ((x as f32 / scale) as u32).min(self.width.saturating_sub(1));
-            let src_y = ((y as f32 / scale) as u32).min(self.height.saturating_sub(1));
-            let src_idx = (src_y as usize) * (self.width as usize) + (src_x as usize);
-
-            let (r, g, b, a) = self.raw_pixels[src_idx];
-            *pixel = process_pixel(r, g, b, a, exposure, gamma);
-        });
-        
-        Image::from_rgba8(buffer)
+        let params = self.tone_params(settings);
+        let job = |x: u32, y: u32| {
+            let src_x = ((x as f32 / scale) as u32).min(out_w.saturating_sub(1));
+            let src_y = ((y as f32 / scale) as u32).min(out_h.saturating_sub(1));
+            let (r, g, b, a) = self.raw_pixels[transform.source_index(src_x, src_y, self.width, self.height)];
+            self.render_pixel(r, g, b, a, &params, settings)
+        };
+        tiles::render(&job, Rect::full(thumb_width, thumb_height))
+    }
+
+    /// Średnie liniowe RGBA z prostokąta podanego we współrzędnych widoku znormalizowanych do 0..1
+    /// (po obrocie/odbiciu); wartości NaN/Inf są pomijane. None gdy obszar leży poza obrazem.
+    pub fn sample_region(&self, u0: f32, v0: f32, u1: f32, v1: f32, transform: &DisplayTransform) -> Option<ColorSample> {
+        let (pixels, [x, y, width, height]) = self.region_pixels(u0, v0, u1, v1, transform)?;
+        let mut sum = [0.0f64; 4];
+        let mut count = 0u32;
+        for &(r, g, b, a) in &pixels {
+            if r.is_finite() && g.is_finite() && b.is_finite() && a.is_finite() {
+                sum = [sum[0] + r as f64, sum[1] + g as f64, sum[2] + b as f64, sum[3] + a as f64];
+                count += 1;
+            }
+        }
+        let n = count.max(1) as f64;
+        Some(ColorSample {
+            r: (sum[0] / n) as f32,
+            g: (sum[1] / n) as f32,
+            b: (sum[2] / n) as f32,
+            a: (sum[3] / n) as f32,
+            x,
+            y,
+            width,
+            height,
+        })
     }
+
+    /// Statystyki min/max/średnia/mediana per kanał z surowych wartości zaznaczonego prostokąta
+    pub fn region_stats(&self, u0: f32, v0: f32, u1: f32, v1: f32, transform: &DisplayTransform) -> Option<RegionStats> {
+        let (pixels, _) = self.region_pixels(u0, v0, u1, v1, transform)?;
+        Some(region_stats(&pixels))
+    }
+
+    /// Histogramy R, G, B i luminancji całego obrazu albo zaznaczenia (prostokąt widoku 0..1) wraz
+    /// z obrysem w pikselach źródłowych [x, y, szer., wys.]; None gdy zaznaczenie leży poza obrazem
+    pub fn channel_histograms(&self, region: Option<[f32; 4]>, transform: &DisplayTransform, params: &ToneParams) -> Option<([Histogram; 4], [u32; 4])> {
+        let params = &if self.monochrome { params.without_matrix() } else { *params };
+        match region {
+            None => Some((histogram::channel_histograms(&self.raw_pixels, params), [0, 0, self.width, self.height])),
+            Some([u0, v0, u1, v1]) => {
+                let (pixels, bounds) = self.region_pixels(u0, v0, u1, v1, transform)?;
+                Some((histogram::channel_histograms(&pixels, params), bounds))
+            }
+        }
+    }
+
+    /// Piksel źródła pod punktem widoku (0..1, po obrocie/odbiciu `transform`) względem okna danych oraz
+    /// początek okna danych bieżącej warstwy; None poza obrazem
+    pub fn pixel_at(&self, u: f32, v: f32, transform: &DisplayTransform) -> Option<((u32, u32), (i32, i32))> {
+        let (out_w, out_h) = transform.output_size(self.width, self.height);
+        if !(0.0..1.0).contains(&u) || !(0.0..1.0).contains(&v) || out_w == 0 || out_h == 0 {
+            return None;
+        }
+        let src = transform.source_index((u * out_w as f32) as u32, (v * out_h as f32) as u32, self.width, self.height);
+        let origin = self.layers_info.iter().find(|l| l.name == self.current_layer_name).map_or((0, 0), |l| l.data_origin);
+        Some(((src as u32 % self.width, src as u32 / self.width), origin))
+    }
+
+    /// Surowe piksele prostokąta widoku (0..1) oraz jego obrys w pikselach źródłowych
+    fn region_pixels(&self, u0: f32, v0: f32, u1: f32, v1: f32, transform: &DisplayTransform) -> Option<RegionPixels> {
+        let (out_w, out_h) = transform.output_size(self.width, self.height);
+        if out_w == 0 || out_h == 0 || u0.max(u1) < 0.0 || v0.max(v1) < 0.0 || u0.min(u1) > 1.0 || v0.min(v1) > 1.0 {
+            return None;
+        }
+        let to_px = |t: f32, size: u32| ((t.clamp(0.0, 1.0) * size as f32) as u32).min(size - 1);
+        let (x0, x1) = (to_px(u0.min(u1), out_w), to_px(u0.max(u1), out_w));
+        let (y0, y1) = (to_px(v0.min(v1), out_h), to_px(v0.max(v1), out_h));
+
+        let mut pixels = Vec::with_capacity(((x1 - x0 + 1) * (y1 - y0 + 1)) as usize);
+        let (mut min_x, mut min_y, mut max_x, mut max_y) = (u32::MAX, u32::MAX, 0, 0);
+        for y in y0..=y1 {
+            for x in x0..=x1 {
+                let src = transform.source_index(x, y, self.width, self.height);
+                let (sx, sy) = (src as u32 % self.width, src as u32 / self.width);
+                (min_x, min_y, max_x, max_y) = (min_x.min(sx), min_y.min(sy), max_x.max(sx), max_y.max(sy));
+                pixels.push(self.raw_pixels[src]);
+            }
+        }
+        Some((pixels, [min_x, min_y, max_x - min_x + 1, max_y - min_y + 1]))
+    }
+}
+
+pub(crate) fn extract_layers_info(path: &Path) -> ExrResult<Vec<LayerInfo>> {
+    Ok(layers_info_from_headers(&read_headers(path)?))
 }
 
-pub(crate) fn extract_layers_info(path: &PathBuf) -> anyhow::Result<Vec<LayerInfo>> {
-        let image = exr::read_all_data_from_file(path)?;
+/// Same nagłówki pliku – bez dekodowania pikseli
+pub(crate) fn read_headers(path: &Path) -> ExrResult<::exr::meta::Headers> {
+    Ok(::exr::meta::MetaData::read_from_buffered(crate::io::open(path)?, false)?.headers)
+}
 
-        // Mapowanie: nazwa_warstwy -> kanały
-        let mut layer_map: HashMap<String, Vec<ChannelInfo>> = HashMap::new();
+pub(crate) fn layers_info_from_headers(headers: &[::exr::meta::header::Header]) -> Vec<LayerInfo> {
+    // Mapowanie: nazwa_warstwy -> kanały
+    let mut layer_map: HashMap<String, Vec<ChannelInfo>> = HashMap::new();
     // Kolejność pierwszego wystąpienia nazw warstw do stabilnego porządku w UI
-    let mut layer_order: Vec<String> = Vec::new();
+    let mut layer_order: Vec<(String, usize)> = Vec::new();
 
-    for layer in image.layer_data.iter() {
-        let base_layer_name: Option<String> = layer
-            .attributes
+    for (part, header) in headers.iter().enumerate() {
+        let base_layer_name: Option<String> = header
+            .own_attributes
             .layer_name
             .as_ref()
             .map(|s| s.to_string());
 
-        let _width = layer.size.width() as u32;
-        let _height = layer.size.height() as u32;
-
-        for channel in &layer.channel_data.list {
+        for channel in &header.channels.list {
             let full_channel_name = channel.name.to_string();
             let (layer_name_effective, short_channel_name) =
                 split_layer_and_short(&full_channel_name, base_layer_name.as_deref());
 
             // Wstaw do mapy, zachowując kolejność pierwszego wystąpienia
             let entry = layer_map.entry(layer_name_effective.clone()).or_insert_with(|| {
-                layer_order.push(layer_name_effective.clone());
+                layer_order.push((layer_name_effective.clone(), part));
                 Vec::new()
             });
 
@@ -174,13 +732,196 @@ pub(crate) fn extract_layers_info(path: &PathBuf) -> anyhow::Result<Vec<LayerInf
 
     // Zbuduj listę warstw w kolejności pierwszego wystąpienia
     let mut layers: Vec<LayerInfo> = Vec::with_capacity(layer_map.len());
-    for name in layer_order {
-        if let Some(channels) = layer_map.remove(&name) {
-            layers.push(LayerInfo { name, channels });
+    let mut part_spaces: HashMap<usize, DetectedColorSpace> = HashMap::new();
+    for (name, part) in layer_order {
+        if let Some(mut channels) = layer_map.remove(&name) {
+            // Luminancja/chrominancja jest przy odczycie zamieniana na RGB (`luminance_chroma`)
+            if luminance_chroma::is_luminance_chroma(channels.iter().map(|c| c.name.as_str())) {
+                channels.retain(|c| !luminance_chroma::CHANNELS.contains(&c.name.as_str()));
+                channels.splice(0..0, ["R", "G", "B"].map(|name| ChannelInfo { name: name.into() }));
+            }
+            let color_space = part_spaces.entry(part).or_insert_with(|| detect_part_color_space(headers, part)).clone();
+            let origin = headers[part].own_attributes.layer_position;
+            layers.push(LayerInfo { name, channels, color_space, data_origin: (origin.x(), origin.y()) });
         }
     }
 
-    Ok(layers)
+    layers
+}
+
+/// Zgrubny podgląd (proxy) dużego pliku do wyświetlenia zanim zdekoduje się pełny obraz.
+/// Dekoduje tylko te bloki najlepszej warstwy (poziom 0), które zawierają próbkowane linie,
+/// i pobiera z nich co `step`-ty piksel (nearest-neighbor). Dłuższy bok wyniku to ok. `max_size`,
+/// ale krok nigdy nie jest mniejszy niż wysokość bloku – dzięki temu każdy blok dekodujemy najwyżej raz.
+/// Pliki kafelkowe z mip/rip-mapami czytane są z najmniejszego wystarczającego poziomu (w całości).
+pub(crate) fn load_preview_proxy(path: &Path, max_size: u32, cancel: &CancelToken) -> ExrResult<ImageCache> {
+    use ::exr::block::reader::ChunksReader;
+    use ::exr::meta::attribute::SampleType;
+
+    let file = open_cancellable(path, cancel)?;
+    let reader = ::exr::block::read(file, false)?;
+
+    let layers_info = layers_info_from_headers(reader.headers());
+    let best_layer = find_best_layer(&layers_info);
+    let mono = layers_info.iter().find(|l| l.name == best_layer).and_then(LayerInfo::monochrome_channel).map(str::to_string);
+    let monochrome = mono.is_some();
+    let env_map = env_map::detect(reader.headers());
+    let views = stereo::parse_views(reader.headers());
+
+    // Znajdź nagłówek (część pliku) i indeksy kanałów R/G/B/A najlepszej warstwy
+    // (kanał warstwy monochromatycznej trafia na miejsce R)
+    let mut chosen: Option<(usize, [Option<usize>; 4])> = None;
+    for (header_index, header) in reader.headers().iter().enumerate() {
+        if header.deep { continue; }
+        let base_attr: Option<String> = header.own_attributes.layer_name.as_ref().map(|s| s.to_string());
+        let mut rgba: [Option<usize>; 4] = [None; 4];
+        for (idx, ch) in header.channels.list.iter().enumerate() {
+            let (lname, short) = split_layer_and_short(&ch.name.to_string(), base_attr.as_deref());
+            if lname != best_layer { continue; }
+            match channel_alias_to_short(&short).as_str() {
+                _ if mono.as_deref() == Some(short.as_str()) => rgba[0] = Some(idx),
+                "R" => rgba[0] = Some(idx),
+                "G" => rgba[1] = Some(idx),
+                "B" => rgba[2] = Some(idx),
+                "A" => rgba[3] = Some(idx),
+                _ => {}
+            }
+        }
+        if rgba[..3].iter().any(|c| c.is_some()) {
+            chosen = Some((header_index, rgba));
+            break;
+        }
+    }
+    let (layer_index, rgba) = chosen
+        .ok_or_else(|| ExrError::MissingChannel { layer: best_layer.clone(), channel: "RGB".to_string() })?;
+
+    let header = &reader.headers()[layer_index];
+    let (level, level_size) = proxy_level(header, max_size as usize);
+    let width = level_size.width();
+    let height = level_size.height();
+    let block_height = header.max_block_pixel_size().height().max(1);
+    let sample_types: Vec<SampleType> = header.channels.list.iter().map(|c| c.sample_type).collect();
+
+    let stride = (width.max(height) as f32 / max_size.max(1) as f32).ceil().max(1.0) as usize;
+    // Mniejszy poziom dekodujemy w całości, więc krok nie musi być wielokrotnością wysokości kafla
+    let step = if level == exr::Vec2(0, 0) { stride.max(block_height) } else { stride };
+    let proxy_w = width.div_ceil(step).max(1);
+    let proxy_h = height.div_ceil(step).max(1);
+    let mut pixels = alloc_pixels(proxy_w, proxy_h)?;
+    pixels.resize(proxy_w * proxy_h, (0.0, 0.0, 0.0, 1.0));
+
+    let chunks = reader.filter_chunks(false, |_meta, _tile, block| {
+        if block.layer != layer_index || block.level != level { return false; }
+        // Czy blok zawiera którąkolwiek z próbkowanych linii (wielokrotność `step`)?
+        let y0 = block.pixel_position.y();
+        let first_sample = y0.div_ceil(step) * step;
+        first_sample < y0 + block.pixel_size.height()
+    })?;
+
+    chunks.decompress_parallel(false, |meta, block| {
+        for line in block.lines(&meta.headers[layer_index].channels) {
+            let loc = line.location;
+            if loc.position.y() % step != 0 { continue; }
+            let Some(slot) = rgba.iter().position(|c| *c == Some(loc.channel)) else { continue; };
+            let row = (loc.position.y() / step).min(proxy_h - 1);
+            let x0 = loc.position.x();
+            let mut x = x0.div_ceil(step) * step;
+            while x < x0 + loc.sample_count {
+                let v = sample_as_f32(line.value, sample_types[loc.channel], x - x0);
+                let px = &mut pixels[row * proxy_w + (x / step).min(proxy_w - 1)];
+                match slot { 0 => px.0 = v, 1 => px.1 = v, 2 => px.2 = v, _ => px.3 = v }
+                x += step;
+            }
+        }
+        Ok(())
+    })?;
+
+    // Brakujące kanały duplikujemy jak w kompozycie warstwy (G←R, B←G)
+    if rgba[1].is_none() || rgba[2].is_none() {
+        for px in pixels.iter_mut() {
+            if rgba[1].is_none() { px.1 = px.0; }
+            if rgba[2].is_none() { px.2 = px.1; }
+        }
+    }
+
+    Ok(ImageCache {
+        raw_pixels: pixels.into(),
+        width: proxy_w as u32,
+        height: proxy_h as u32,
+        layers_info,
+        current_layer_name: best_layer,
+        channel_remap: None,
+        vector_view: None,
+        normals_view: false,
+        depth_view: None,
+        deep_preview: false,
+        damage: None,
+        monochrome,
+        env_map,
+        views,
+        stereo_mode: StereoMode::Single,
+        stereo_pair: None,
+        layer_cache: LayerCache::new(0),
+        channels: HashMap::new(),
+        renders: Mutex::default(),
+    })
+}
+
+/// Najmniejszy poziom mip/rip, którego dłuższy bok ma jeszcze co najmniej `max_size` px
+/// (proporcjonalnie na każdej osi). Zwraca indeks poziomu i jego rozmiar; poziom 0 dla plików bez poziomów.
+fn proxy_level(header: &::exr::meta::header::Header, max_size: usize) -> (exr::Vec2<usize>, exr::Vec2<usize>) {
+    use ::exr::meta::{compute_level_count, compute_level_size, BlockDescription};
+    use ::exr::meta::attribute::LevelMode;
+
+    let full = header.layer_size;
+    let BlockDescription::Tiles(tiles) = header.blocks else { return (exr::Vec2(0, 0), full); };
+    let round = tiles.rounding_mode;
+    let scale = max_size as f32 / full.width().max(full.height()).max(1) as f32;
+    let pick = |size: usize| {
+        (0..compute_level_count(round, size))
+            .rev()
+            .find(|&level| compute_level_size(round, size, level) as f32 >= size as f32 * scale)
+            .unwrap_or(0)
+    };
+    let size_at = |level: exr::Vec2<usize>| {
+        exr::Vec2(compute_level_size(round, full.width(), level.x()), compute_level_size(round, full.height(), level.y()))
+    };
+    let level = match tiles.level_mode {
+        LevelMode::Singular => exr::Vec2(0, 0),
+        LevelMode::MipMap => {
+            // Poziomy mip zmniejszają obie osie jednocześnie – liczy się dłuższy bok
+            let level = pick(full.width().max(full.height()));
+            exr::Vec2(level, level)
+        }
+        LevelMode::RipMap => exr::Vec2(pick(full.width()), pick(full.height())),
+    };
+    (level, size_at(level))
+}
+
+/// Czy którakolwiek część pliku ma zapisane mniejsze poziomy rozdzielczości (mip/rip)
+pub(crate) fn has_resolution_levels(path: &Path) -> bool {
+    use ::exr::meta::BlockDescription;
+    use ::exr::meta::attribute::LevelMode;
+
+    crate::io::open(path).ok()
+        .and_then(|source| ::exr::meta::MetaData::read_from_buffered(source, false).ok())
+        .map(|meta| meta.headers.iter().any(|h| matches!(h.blocks, BlockDescription::Tiles(t) if t.level_mode != LevelMode::Singular)))
+        .unwrap_or(false)
+}
+
+#[inline]
+pub(crate) fn sample_as_f32(bytes: &[u8], sample_type: ::exr::meta::attribute::SampleType, index: usize) -> f32 {
+    use ::exr::meta::attribute::SampleType;
+    match sample_type {
+        SampleType::F16 => exr::f16::from_le_bytes([bytes[index * 2], bytes[index * 2 + 1]]).to_f32(),
+        SampleType::F32 => f32::from_le_bytes([bytes[index * 4], bytes[index * 4 + 1], bytes[index * 4 + 2], bytes[index * 4 + 3]]),
+        SampleType::U32 => u32::from_le_bytes([bytes[index * 4], bytes[index * 4 + 1], bytes[index * 4 + 2], bytes[index * 4 + 3]]) as f32,
+    }
+}
+
+/// Czy warstwa o tej nazwie jest monochromatyczna (`LayerInfo::monochrome_channel`)
+fn is_monochrome_layer(layers_info: &[LayerInfo], name: &str) -> bool {
+    layers_info.iter().find(|l| l.name == name).is_some_and(|l| l.monochrome_channel().is_some())
 }
 
 pub(crate) fn find_best_layer(layers_info: &[LayerInfo]) -> String {
@@ -233,99 +974,274 @@ pub(crate) fn find_best_layer(layers_info: &[LayerInfo]) -> String {
         .unwrap_or_else(|| "Layer 1".to_string())
 }
 
-pub(crate) fn load_specific_layer(path: &PathBuf, layer_name: &str) -> anyhow::Result<(Vec<(f32, f32, f32, f32)>, u32, u32, String)> {
+/// Wczytana warstwa: piksele RGBA, szerokość, wysokość, nazwa warstwy
+pub(crate) type LoadedLayer = (Vec<(f32, f32, f32, f32)>, u32, u32, String);
+/// Obraz po przebiegu zmieniającym geometrię (stereo, mapa otoczenia): piksele, szerokość, wysokość
+type SceneImage = (Vec<(f32, f32, f32, f32)>, usize, usize);
 
-    // Załaduj płaskie warstwy (bez mip-map), aby uzyskać FlatSamples
-    let any_image = exr::read_all_flat_layers_from_file(path)?;
+/// Otwiera plik do odczytu przerywanego przez `cancel`
+pub(crate) fn open_cancellable(path: &Path, cancel: &CancelToken) -> ExrResult<std::io::BufReader<CancellableReader<std::fs::File>>> {
+    let file = std::fs::File::open(path)?;
+    Ok(std::io::BufReader::new(CancellableReader::new(file, cancel.clone())))
+}
 
-    // Szukaj grupy kanałów odpowiadającej nazwie warstwy (spójne z extract_layers_info)
-    let wanted_lower = layer_name.to_lowercase();
-    for layer in any_image.layer_data.iter() {
-        let width = layer.size.width() as u32;
-        let height = layer.size.height() as u32;
-        let pixel_count = (width as usize) * (height as usize);
+/// Rezerwuje bufor pikseli; brak pamięci zgłaszamy jako błąd zamiast przerywać program
+pub(crate) fn alloc_pixels(width: usize, height: usize) -> ExrResult<Vec<(f32, f32, f32, f32)>> {
+    let mut out = Vec::new();
+    out.try_reserve_exact(width * height).map_err(|_| ExrError::OutOfMemory { width, height })?;
+    Ok(out)
+}
 
-        let base_attr: Option<String> = layer.attributes.layer_name.as_ref().map(|s| s.to_string());
+pub(crate) fn load_specific_layer(path: &PathBuf, layer_name: &str) -> ExrResult<LoadedLayer> {
+    load_specific_layer_cancellable(path, layer_name, &CancelToken::new())
+}
 
-        // Indeksy R/G/B/A w grupie, jeśli dopasowano, oraz lista wszystkich indeksów w grupie
+fn load_specific_layer_cancellable(path: &PathBuf, layer_name: &str, cancel: &CancelToken) -> ExrResult<LoadedLayer> {
+    Ok(load_layer_with_channels(path, layer_name, cancel)?.0)
+}
+
+/// Jak `load_specific_layer`, z informacją, czy plik był uszkodzony (obraz odzyskany częściowo)
+pub(crate) fn load_specific_layer_checked(path: &PathBuf, layer_name: &str) -> ExrResult<(LoadedLayer, Option<Damage>)> {
+    let (layer, channels) = load_layer_with_channels(path, layer_name, &CancelToken::new())?;
+    Ok((layer, channels.and_then(|c| c.damage)))
+}
+
+/// Kanały jednej warstwy w układzie planarnym – wspólne, niezmienne źródło widoków tej warstwy
+/// (kompozyt RGBA, pojedynczy kanał, głębia); widoki materializowane są dopiero na żądanie
+pub(crate) struct LayerChannels {
+    width: u32,
+    height: u32,
+    name: String,
+    /// (krótka nazwa kanału, próbki)
+    channels: Vec<(String, Arc<[f32]>)>,
+    /// Plik był uszkodzony – brakujące obszary wypełnia wzór diagnostyczny
+    damage: Option<Damage>,
+}
+
+impl LayerChannels {
+    /// Kompozyt RGBA: kanały R/G/B/A (także nazwy przyjazne), brakujące uzupełniane kolejnymi kanałami grupy
+    fn compose_rgba(&self) -> ExrResult<Vec<(f32, f32, f32, f32)>> {
+        if let Some(mono) = monochrome_channel(self.channels.iter().map(|(short, _)| short.as_str())) {
+            return self.compose_monochrome(mono);
+        }
         let mut r_idx: Option<usize> = None;
         let mut g_idx: Option<usize> = None;
         let mut b_idx: Option<usize> = None;
         let mut a_idx: Option<usize> = None;
-        let mut group_found = false;
-        let mut group_indices: Vec<usize> = Vec::with_capacity(layer.channel_data.list.len());
-
-        let name_matches = |lname: &str| -> bool {
-            let lname_lower = lname.to_lowercase();
-            if wanted_lower.is_empty() && lname_lower.is_empty() {
-                true
-            } else if wanted_lower.is_empty() || lname_lower.is_empty() {
-                false
-            } else {
-                lname_lower == wanted_lower || lname_lower.contains(&wanted_lower) || wanted_lower.contains(&lname_lower)
+        for (idx, (short, _)) in self.channels.iter().enumerate() {
+            let su = short.to_ascii_uppercase();
+            match su.as_str() {
+                "R" | "RED" => r_idx = Some(idx),
+                "G" | "GREEN" => g_idx = Some(idx),
+                "B" | "BLUE" => b_idx = Some(idx),
+                "A" | "ALPHA" => a_idx = Some(idx),
+                _ => {
+                    // Dodatkowe heurystyki: nazwy zaczynające się od R/G/B
+                    if r_idx.is_none() && su.starts_with('R') { r_idx = Some(idx); }
+                    else if g_idx.is_none() && su.starts_with('G') { g_idx = Some(idx); }
+                    else if b_idx.is_none() && su.starts_with('B') { b_idx = Some(idx); }
+                }
             }
+        }
+
+        // Zapewnij 3 kanały: jeśli brakuje, uzupełnij z listy kanałów grupy lub duplikuj poprzedni
+        let count = self.channels.len();
+        let r_idx = r_idx.or((count > 0).then_some(0));
+        let g_idx = g_idx.or((count > 1).then_some(1)).or(r_idx);
+        let b_idx = b_idx.or((count > 2).then_some(2)).or(g_idx).or(r_idx);
+        let (Some(ri), Some(gi), Some(bi)) = (r_idx, g_idx, b_idx) else {
+            return Err(ExrError::MissingChannel { layer: self.name.clone(), channel: "RGB".to_string() });
         };
 
-        for (idx, ch) in layer.channel_data.list.iter().enumerate() {
-            let full = ch.name.to_string();
-            let (lname, short) = split_layer_and_short(&full, base_attr.as_deref());
+        let (r, g, b) = (&self.channels[ri].1, &self.channels[gi].1, &self.channels[bi].1);
+        let a = a_idx.map(|ai| &self.channels[ai].1);
+        let mut out = alloc_pixels(self.width as usize, self.height as usize)?;
+        out.extend((0..r.len()).map(|i| (r[i], g[i], b[i], a.map_or(1.0, |a| a[i]))));
+        Ok(out)
+    }
+
+    /// Warstwa monochromatyczna bez kompozytu: R=G=B=kanał danych, alfa z kanału A, jeśli jest
+    fn compose_monochrome(&self, mono: &str) -> ExrResult<Vec<(f32, f32, f32, f32)>> {
+        let find = |name: &str| self.channels.iter().find(|(short, _)| short == name).map(|(_, samples)| samples);
+        let v = find(mono).ok_or_else(|| ExrError::MissingChannel { layer: self.name.clone(), channel: mono.to_string() })?;
+        let a = self.channels.iter().find(|(short, _)| channel_alias_to_short(short) == "A").map(|(_, samples)| samples);
+        let mut out = alloc_pixels(self.width as usize, self.height as usize)?;
+        out.extend((0..v.len()).map(|i| (v[i], v[i], v[i], a.map_or(1.0, |a| a[i]))));
+        Ok(out)
+    }
 
-            if name_matches(&lname) {
-                group_found = true;
-                group_indices.push(idx);
+    fn byte_size(&self) -> usize {
+        self.channels.iter().map(|(_, samples)| std::mem::size_of_val(&**samples)).sum()
+    }
+
+    /// Próbki kanału o podanej krótkiej nazwie (także aliasy); None, gdy warstwa nie ma takiego kanału
+    fn channel(&self, channel_short: &str) -> Option<&[f32]> {
+        let wanted_canon = channel_alias_to_short(channel_short);
+        let wanted_upper = channel_short.to_ascii_uppercase();
+        let (_, samples) = self.channels.iter()
+            .find(|(short, _)| short == channel_short || channel_alias_to_short(short) == wanted_canon)
+            // Warianty głębi (Z/DEPTH/DISTANCE) w tej samej grupie
+            .or_else(|| self.channels.iter().find(|(short, _)| {
                 let su = short.to_ascii_uppercase();
-                match su.as_str() {
-                    "R" | "RED" => r_idx = Some(idx),
-                    "G" | "GREEN" => g_idx = Some(idx),
-                    "B" | "BLUE" => b_idx = Some(idx),
-                    "A" | "ALPHA" => a_idx = Some(idx),
-                    _ => {
-                        // Dodatkowe heurystyki: nazwy zaczynające się od R/G/B
-                        if r_idx.is_none() && su.starts_with('R') { r_idx = Some(idx); }
-                        else if g_idx.is_none() && su.starts_with('G') { g_idx = Some(idx); }
-                        else if b_idx.is_none() && su.starts_with('B') { b_idx = Some(idx); }
-                    }
-                }
-            }
+                wanted_upper == "Z" && (su == "Z" || su.contains("DEPTH") || su == "DISTANCE")
+            }))?;
+        Some(samples)
+    }
+
+    /// Jeden kanał jako skala szarości (R=G=B=v, A=1)
+    fn grayscale_view(&self, samples: &[f32]) -> ExrResult<LoadedLayer> {
+        let mut out = alloc_pixels(self.width as usize, self.height as usize)?;
+        out.extend(samples.iter().map(|&v| (v, v, v, 1.0)));
+        Ok((out, self.width, self.height, self.name.clone()))
+    }
+}
+
+/// Wczytuje warstwę jako kompozyt RGBA wraz z jej kanałami planarnymi (None przy fallbacku
+/// do pierwszej warstwy RGBA pliku)
+fn load_layer_with_channels(path: &PathBuf, layer_name: &str, cancel: &CancelToken) -> ExrResult<(LoadedLayer, Option<Arc<LayerChannels>>)> {
+    match load_layer_channels(path, layer_name, cancel)? {
+        Some(channels) => {
+            let rgba = channels.compose_rgba()?;
+            // Zwracamy żądaną nazwę jako aktualną, aby była spójna z UI
+            Ok(((rgba, channels.width, channels.height, layer_name.to_string()), Some(Arc::new(channels))))
+        }
+        None => {
+            // Jeśli nie znaleziono warstwy, fallback do pierwszej RGBA
+            cancel.check()?;
+            let (pixels, width, height, _) = load_first_rgba_layer(path)?;
+            Ok(((pixels, width, height, layer_name.to_string()), None))
+        }
+    }
+}
+
+/// Kanały grupy odpowiadającej nazwie warstwy (spójnie z extract_layers_info) z pierwszej pasującej
+/// części pliku; None, gdy żadna część nie zawiera takiej grupy
+fn load_layer_channels(path: &Path, layer_name: &str, cancel: &CancelToken) -> ExrResult<Option<LayerChannels>> {
+    let (image, damage) = read_flat_image(path, cancel)?;
+    layer_channels_from_image(&image, layer_name, damage)
+}
+
+/// Wszystkie części pliku jako płaskie próbki (bez mip-map); odczyt przerywa się po anulowaniu.
+/// Plik, którego nie da się wczytać w całości (np. obcięty), czytany jest tolerancyjnie –
+/// wtedy zwracamy też stan uszkodzenia.
+fn read_flat_image(path: &Path, cancel: &CancelToken) -> ExrResult<(::exr::image::FlatImage, Option<Damage>)> {
+    use ::exr::prelude::traits::*;
+
+    // Kanały podpróbkowane (np. chrominancja RY/BY) czyta własny dekoder – biblioteka exr ich nie obsługuje
+    let result = if luminance_chroma::has_subsampled_channels(&crate::deep_exr::read_headers(path)?) {
+        luminance_chroma::read_image(path, cancel)
+    } else {
+        exr::read()
+            .no_deep_data()
+            .largest_resolution_level()
+            .all_channels()
+            .all_layers()
+            .all_attributes()
+            .from_buffered(open_cancellable(path, cancel)?)
+            .map_err(ExrError::from)
+    };
+    cancel.check()?;
+    let error = match result {
+        Ok(mut image) => {
+            luminance_chroma::reconstruct_rgb(&mut image);
+            return Ok((image, None));
         }
+        Err(e) => e,
+    };
 
-        if group_found {
-            // Zapewnij 3 kanały: jeśli brakuje, uzupełnij z listy kanałów grupy lub duplikuj poprzedni
-            if r_idx.is_none() {
-                r_idx = group_indices.get(0).cloned();
-            }
-            if g_idx.is_none() {
-                g_idx = group_indices.get(1).cloned().or(r_idx);
+    warn!(target: "io", "{} could not be read in full ({}), recovering complete chunks", path.display(), error);
+    match recovery::read_partial(open_cancellable(path, cancel)?) {
+        Ok((mut image, damage)) if damage.recovered > 0.0 => {
+            luminance_chroma::reconstruct_rgb(&mut image);
+            warn!(target: "io", "{}: {}", path.display(), damage.label());
+            Ok((image, Some(damage)))
+        }
+        // Nic nie odzyskano albo nagłówki też są uszkodzone – zgłaszamy pierwotny błąd
+        _ => {
+            cancel.check()?;
+            Err(error)
+        }
+    }
+}
+
+/// Dekoduje plik raz i w równoległych zadaniach (po jednym na warstwę) buduje kanały planarne
+/// wszystkich warstw; gdy nie zmieszczą się w budżecie pamięci – tylko warstwy `best`.
+/// Warstwa, której nie udało się zbudować, jest pomijana z ostrzeżeniem; pozostałe zostają.
+fn build_layer_channels(
+    path: &Path,
+    layers_info: &[LayerInfo],
+    best: &str,
+    cancel: &CancelToken,
+    progress: &dyn ProgressSink,
+) -> ExrResult<HashMap<String, Arc<LayerChannels>>> {
+    let (image, damage) = read_flat_image(path, cancel)?;
+    let total_bytes: usize = image.layer_data.iter()
+        .map(|layer| layer.channel_data.list.len() * layer.size.area() * std::mem::size_of::<f32>())
+        .sum();
+    let names: Vec<&str> = if total_bytes <= layer_cache::DEFAULT_BUDGET_BYTES {
+        layers_info.iter().map(|l| l.name.as_str()).collect()
+    } else {
+        debug!(target: "io", "layers need {} MB, building only '{}'", total_bytes >> 20, best);
+        vec![best]
+    };
+
+    let total = names.len();
+    let done = AtomicUsize::new(0);
+    let channels = names.into_par_iter()
+        .filter_map(|name| {
+            if cancel.is_cancelled() {
+                return None;
             }
-            if b_idx.is_none() {
-                b_idx = group_indices.get(2).cloned().or(g_idx).or(r_idx);
+            let result = layer_channels_from_image(&image, name, damage);
+            let n = done.fetch_add(1, Ordering::Relaxed) + 1;
+            progress.set(n as f32 / total as f32, Some(&format!("Decoded layer {}/{}: {}", n, total, name)));
+            match result {
+                Ok(channels) => channels.map(|c| (name.to_string(), Arc::new(c))),
+                Err(e) => {
+                    warn!(target: "io", "skipping layer '{}': {}", name, e);
+                    None
+                }
             }
+        })
+        .collect();
+    cancel.check()?;
+    Ok(channels)
+}
 
-            // Jeżeli nadal coś jest None (pusta grupa), zgłoś błąd
-            let (ri, gi, bi) = match (r_idx, g_idx, b_idx) {
-                (Some(ri), Some(gi), Some(bi)) => (ri, gi, bi),
-                _ => anyhow::bail!("Warstwa '{}' nie zawiera kanałów do kompozytu", layer_name),
-            };
+fn layer_channels_from_image(any_image: &::exr::image::FlatImage, layer_name: &str, damage: Option<Damage>) -> ExrResult<Option<LayerChannels>> {
+    let wanted_lower = layer_name.to_lowercase();
+    let name_matches = |lname: &str| -> bool {
+        let lname_lower = lname.to_lowercase();
+        if wanted_lower.is_empty() && lname_lower.is_empty() {
+            true
+        } else if wanted_lower.is_empty() || lname_lower.is_empty() {
+            false
+        } else {
+            lname_lower == wanted_lower || lname_lower.contains(&wanted_lower) || wanted_lower.contains(&lname_lower)
+        }
+    };
 
-            let mut out: Vec<(f32, f32, f32, f32)> = Vec::with_capacity(pixel_count);
-            for i in 0..pixel_count {
-                let r = layer.channel_data.list[ri].sample_data.value_by_flat_index(i).to_f32();
-                let g = layer.channel_data.list[gi].sample_data.value_by_flat_index(i).to_f32();
-                let b = layer.channel_data.list[bi].sample_data.value_by_flat_index(i).to_f32();
-                let a = a_idx.map(|ci| layer.channel_data.list[ci].sample_data.value_by_flat_index(i).to_f32()).unwrap_or(1.0);
-                out.push((r, g, b, a));
-            }
-            // Zwracamy żądaną nazwę jako aktualną, aby była spójna z UI
-            return Ok((out, width, height, layer_name.to_string()));
+    for layer in any_image.layer_data.iter() {
+        let (width, height) = (layer.size.width(), layer.size.height());
+        let base_attr: Option<String> = layer.attributes.layer_name.as_ref().map(|s| s.to_string());
+
+        let mut channels = Vec::new();
+        for ch in &layer.channel_data.list {
+            let (lname, short) = split_layer_and_short(&ch.name.to_string(), base_attr.as_deref());
+            if !name_matches(&lname) { continue; }
+            let mut samples = Vec::new();
+            samples.try_reserve_exact(width * height).map_err(|_| ExrError::OutOfMemory { width, height })?;
+            samples.extend((0..width * height).map(|i| ch.sample_data.value_by_flat_index(i).to_f32()));
+            channels.push((short, Arc::from(samples)));
+        }
+        if !channels.is_empty() {
+            return Ok(Some(LayerChannels { width: width as u32, height: height as u32, name: layer_name.to_string(), channels, damage }));
         }
     }
-
-    // Jeśli nie znaleziono warstwy, fallback do pierwszej RGBA
-    let (pixels, width, height, _) = load_first_rgba_layer(path)?;
-    Ok((pixels, width, height, layer_name.to_string()))
+    Ok(None)
 }
 
-fn load_first_rgba_layer(path: &PathBuf) -> anyhow::Result<(Vec<(f32, f32, f32, f32)>, u32, u32, String)> {
+fn load_first_rgba_layer(path: &PathBuf) -> ExrResult<LoadedLayer> {
     use std::convert::Infallible;
     use std::cell::RefCell;
     use std::rc::Rc;
@@ -365,25 +1281,40 @@ fn load_first_rgba_layer(path: &PathBuf) -> anyhow::Result<(Vec<(f32, f32, f32,
 // usunięto rozbudowane wykrywanie rodzaju kanału — UI pokazuje teraz realne kanały bez grupowania
 
 impl ImageCache {
-    /// Wczytuje jeden wskazany kanał z danej warstwy i zapisuje go jako grayscale (R=G=B=val, A=1)
-    pub fn load_channel(&mut self, path: &PathBuf, layer_name: &str, channel_short: &str) -> anyhow::Result<()> {
-        let (pixels, width, height, current_layer_name) = load_single_channel_as_grayscale(path, layer_name, channel_short)?;
-        self.raw_pixels = pixels;
-        self.width = width;
-        self.height = height;
-        self.current_layer_name = current_layer_name;
+    /// Pokazuje jeden kanał warstwy jako grayscale (R=G=B=val, A=1). Widok powstaje z kanałów planarnych
+    /// warstwy (wczytanych raz dla wszystkich jej kanałów); plik czytany jest ponownie tylko wtedy,
+    /// gdy kanału nie ma w grupie warstwy (dopasowanie po pełnej nazwie).
+    pub fn load_channel(&mut self, path: &PathBuf, layer_name: &str, channel_short: &str) -> ExrResult<()> {
+        let mut channels = self.channels.get(layer_name).cloned();
+        let mut loaded = false;
+        let result = self.cached_or_load(layer_cache::key(layer_name, Some(channel_short)), || {
+            if channels.is_none() {
+                channels = load_layer_channels(path, layer_name, &CancelToken::new())?.map(Arc::new);
+                loaded = true;
+            }
+            match channels.as_deref().and_then(|c| Some((c, c.channel(channel_short)?))) {
+                Some((layer, samples)) => layer.grayscale_view(samples),
+                None => load_single_channel_as_grayscale(path, layer_name, channel_short),
+            }
+        });
+        if let Some(channels) = channels.filter(|_| loaded) {
+            self.keep_channels(channels);
+        }
+        // Widok kanału to zawsze skala szarości (R=G=B), bez stereo
+        let layer = result?;
+        self.show(layer);
+        self.monochrome = true;
+        self.stereo_pair = None;
         Ok(())
     }
 
-    /// Specjalne renderowanie głębi: auto-normalizacja percentylowa + opcjonalne odwrócenie
-    pub fn process_depth_image(&self, invert: bool) -> Image {
-        let mut buffer = SharedPixelBuffer::<Rgba8Pixel>::new(self.width, self.height);
-        let slice = buffer.make_mut_slice();
-
+    /// Zakres głębi do normalizacji: percentyle 1% i 99% kanału R (odporne na outliery),
+    /// przy degeneracji lub NaN/Inf min/max wartości skończonych
+    pub fn depth_range(&self) -> (f32, f32) {
         // Wyciągnij z surowych pikseli jeden kanał (zakładamy, że R=G=B=val)
         let mut values: Vec<f32> = self.raw_pixels.iter().map(|(r, _g, _b, _a)| *r).collect();
         if values.is_empty() {
-            return Image::from_rgba8(buffer);
+            return (0.0, 1.0);
         }
 
         // Policz percentyle 1% i 99% (odporne na outliery) w ~O(n)
@@ -412,19 +1343,27 @@ impl ImageCache {
         if (hi - lo).abs() < 1e-12 {
             hi = lo + 1.0;
         }
+        (lo, hi)
+    }
 
-        let map_val = |v: f32| -> u8 {
-            let mut t = ((v - lo) / (hi - lo)).clamp(0.0, 1.0);
-            if invert { t = 1.0 - t; }
-            (t * 255.0).round().clamp(0.0, 255.0) as u8
-        };
-
-        self.raw_pixels.par_iter().zip(slice.par_iter_mut()).for_each(|(&(r, _g, _b, _a), out)| {
-            let g8 = map_val(r);
-            *out = Rgba8Pixel { r: g8, g: g8, b: g8, a: 255 };
-        });
+    /// Specjalne renderowanie głębi: auto-normalizacja percentylowa + opcjonalne odwrócenie;
+    /// przy włączonym focus peaking piksele z pasma ostrości są podświetlone
+    pub fn process_depth_image(&self, invert: bool, settings: &RenderSettings) -> RawImage {
+        if self.raw_pixels.is_empty() {
+            return RawImage::new(self.width, self.height);
+        }
+        let (lo, hi) = self.depth_range();
+        let band = settings.focus_band;
 
-        Image::from_rgba8(buffer)
+        self.map_pixels(&settings.transform, |_, (r, _g, _b, _a)| {
+            let t = ((r - lo) / (hi - lo)).clamp(0.0, 1.0);
+            let shown = if invert { 1.0 - t } else { t };
+            let g8 = (shown * 255.0).round().clamp(0.0, 255.0) as u8;
+            match band {
+                Some((near, far)) if r.is_finite() && (near..=far).contains(&t) => focus_peak(g8),
+                _ => Rgba8Pixel { r: g8, g: g8, b: g8, a: 255 },
+            }
+        })
     }
 
     // usunięto: specjalny preview Cryptomatte
@@ -441,7 +1380,7 @@ fn load_single_channel_as_grayscale(
     path: &PathBuf,
     layer_name: &str,
     channel_short: &str,
-) -> anyhow::Result<(Vec<(f32, f32, f32, f32)>, u32, u32, String)> {
+) -> ExrResult<LoadedLayer> {
     let any_image = exr::read_all_flat_layers_from_file(path)?;
 
     let wanted_layer_lower = layer_name.to_lowercase();
@@ -449,6 +1388,7 @@ fn load_single_channel_as_grayscale(
     let wanted_canon = channel_alias_to_short(&wanted_channel);
 
     // Aliasowanie realizowane wspólnym helperem channel_alias_to_short
+    let mut layer_found = false;
 
     // Przejdź po fizycznych warstwach i szukaj grupy odpowiadającej nazwie
     for layer in any_image.layer_data.iter() {
@@ -474,6 +1414,8 @@ fn load_single_channel_as_grayscale(
 
         // Znajdź kanał w obrębie dopasowanej grupy
         let mut channel_index: Option<usize> = None;
+        layer_found |= layer.channel_data.list.iter()
+            .any(|ch| group_matches(&split_layer_and_short(&ch.name.to_string(), base_attr.as_deref()).0));
         for (idx, ch) in layer.channel_data.list.iter().enumerate() {
             let full = ch.name.to_string();
             let (lname, short) = split_layer_and_short(&full, base_attr.as_deref());
@@ -507,7 +1449,7 @@ fn load_single_channel_as_grayscale(
 
         // Zbuduj grayscale, wykrywając specjalne typy: Z/Depth i Cryptomatte
         if let Some(ci) = channel_index {
-            let mut out: Vec<(f32, f32, f32, f32)> = Vec::with_capacity(pixel_count);
+            let mut out = alloc_pixels(width as usize, height as usize)?;
             let short_upper = channel_short.to_ascii_uppercase();
             let _is_depth = short_upper == "Z" || short_upper.contains("DEPTH");
 
@@ -521,6 +1463,121 @@ fn load_single_channel_as_grayscale(
         }
     }
 
-    // Jeśli nie znaleziono warstwy, zwróć błąd
-    anyhow::bail!("Nie znaleziono warstwy '{}' dla kanału '{}'", layer_name, channel_short)
-}
\ No newline at end of file
+    // Warstwa istnieje, ale bez tego kanału – inny błąd niż brak samej warstwy
+    if layer_found {
+        Err(ExrError::MissingChannel { layer: layer_name.to_string(), channel: channel_short.to_string() })
+    } else {
+        Err(ExrError::MissingLayer(layer_name.to_string()))
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Cache z pikseli w pamięci (bez pliku i bez UI)
+    fn cache_from_pixels(width: u32, height: u32, pixels: Vec<(f32, f32, f32, f32)>) -> ImageCache {
+        ImageCache {
+            raw_pixels: pixels.into(),
+            width,
+            height,
+            layers_info: Vec::new(),
+            current_layer_name: String::new(),
+            channel_remap: None,
+            vector_view: None,
+            normals_view: false,
+            depth_view: None,
+            deep_preview: false,
+            damage: None,
+            monochrome: false,
+            env_map: None,
+            views: Vec::new(),
+            stereo_mode: StereoMode::Single,
+            stereo_pair: None,
+            layer_cache: LayerCache::new(0),
+            channels: HashMap::new(),
+            renders: Mutex::default(),
+        }
+    }
+
+    #[test]
+    fn process_to_image_golden() {
+        let cache = cache_from_pixels(2, 1, vec![(0.18, 0.18, 0.18, 1.0), (0.5, 0.25, 0.1, 0.5)]);
+        let image = cache.process_to_image(&RenderSettings::standard(0.0, 2.2));
+        assert_eq!((image.width, image.height), (2, 1));
+        assert_eq!(image.pixels, [95, 95, 95, 255, 177, 122, 54, 128]);
+    }
+
+    #[test]
+    fn render_cache_keys() {
+        let mut cache = cache_from_pixels(2, 1, vec![(0.18, 0.18, 0.18, 1.0), (0.5, 0.25, 0.1, 0.5)]);
+        let key = |cache: &ImageCache, kind, exposure| cache.render_key(kind, &RenderSettings::standard(exposure, 2.2));
+        assert_eq!(key(&cache, RenderKind::Image, 0.0), key(&cache, RenderKind::Image, 0.0));
+        assert_ne!(key(&cache, RenderKind::Image, 0.0), key(&cache, RenderKind::Image, 1.0));
+        assert_ne!(key(&cache, RenderKind::Thumbnail { max_size: 512 }, 0.0), key(&cache, RenderKind::Thumbnail { max_size: 1024 }, 0.0));
+
+        // Stan A → B → A: powrót z pamięci, ten sam wynik co przeliczenie
+        let a = cache.process_to_image(&RenderSettings::standard(0.0, 2.2));
+        let b = cache.process_to_image(&RenderSettings::standard(1.0, 2.2));
+        assert_ne!(a, b);
+        let k = key(&cache, RenderKind::Image, 0.0);
+        assert_eq!(lock_or_recover(&cache.renders).get(k, &cache.raw_pixels), Some(a.clone()));
+
+        // Inne piksele (zmiana warstwy) nie trafiają w stare wpisy
+        cache.raw_pixels = vec![(1.0, 1.0, 1.0, 1.0); 2].into();
+        assert_eq!(lock_or_recover(&cache.renders).get(k, &cache.raw_pixels), None);
+        assert_ne!(cache.process_to_image(&RenderSettings::standard(0.0, 2.2)), a);
+    }
+
+    #[test]
+    fn exports_skip_diagnostic_overlays() {
+        // Pierwszy piksel ma ujemną składową – poza gamutem sRGB
+        let cache = cache_from_pixels(2, 1, vec![(-0.2, 0.5, 0.1, 1.0), (0.5, 0.25, 0.1, 1.0)]);
+        let plain = RenderSettings::standard(0.0, 2.2);
+        let warning = RenderSettings { gamut_warning: GamutWarning::Srgb, ..plain };
+        let magenta = |image: &RawImage| image.pixels.chunks_exact(4).any(|p| p == [255, 0, 255, 255]);
+        assert!(magenta(&cache.process_to_image(&warning)));
+
+        let export = |settings: &RenderSettings| cache.render_full_resolution(settings, &CancelToken::new(), &|_| {}).unwrap();
+        assert!(!magenta(&export(&warning)));
+        assert_eq!(export(&warning), cache.process_to_image(&plain));
+        // Pozostałe tryby diagnostyczne też nie trafiają do eksportu
+        let overlays = RenderSettings { grayscale: GrayscaleMode::Luma, false_color: true, focus_band: Some((0.0, 1.0)), vector_arrows: true, ..warning };
+        assert_eq!(export(&overlays), cache.process_to_image(&plain));
+    }
+
+    #[test]
+    fn depth_normalization_golden() {
+        // Mała próbka: percentyle 1%/99% to minimum i maksimum
+        let depth = |v: f32| (v, v, v, 1.0);
+        let cache = cache_from_pixels(4, 1, vec![depth(2.0), depth(4.0), depth(6.0), depth(10.0)]);
+        assert_eq!(cache.depth_range(), (2.0, 10.0));
+        let gray = |image: RawImage| image.pixels.chunks_exact(4).map(|p| p[0]).collect::<Vec<_>>();
+        let settings = RenderSettings::standard(0.0, 2.2);
+        assert_eq!(gray(cache.process_depth_image(false, &settings)), [0, 64, 128, 255]);
+        assert_eq!(gray(cache.process_depth_image(true, &settings)), [255, 191, 128, 0]);
+
+        // Płaska głębia nie dzieli przez zero
+        let flat = cache_from_pixels(2, 1, vec![depth(3.0), depth(3.0)]);
+        assert_eq!(gray(flat.process_depth_image(false, &settings)), [0, 0]);
+    }
+
+    #[test]
+    fn monochrome_layers_skip_rgb_composite() {
+        assert_eq!(monochrome_channel(["Y"]), Some("Y"));
+        assert_eq!(monochrome_channel(["A", "Y"]), Some("Y"));
+        assert_eq!(monochrome_channel(["Z"]), Some("Z"));
+        assert_eq!(monochrome_channel(["R", "A"]), None);
+        assert_eq!(monochrome_channel(["Y", "RY", "BY"]), None);
+
+        // Y z alfą: alfa nie trafia do kanału G jak przy uzupełnianiu kompozytu indeksami
+        let samples = |v: &[f32]| Arc::<[f32]>::from(v);
+        let layer = LayerChannels {
+            width: 2,
+            height: 1,
+            name: String::new(),
+            channels: vec![("A".into(), samples(&[1.0, 0.5])), ("Y".into(), samples(&[0.25, 2.0]))],
+            damage: None,
+        };
+        assert_eq!(layer.compose_rgba().unwrap(), [(0.25, 0.25, 0.25, 1.0), (2.0, 2.0, 2.0, 0.5)]);
+    }
+}
@@ -1,69 +1,868 @@
 use slint::Rgba8Pixel;
+use std::hash::{Hash, Hasher};
+use rayon::prelude::*;
+use crate::color_processing::{self, Mat3};
+use crate::display_filters::LowRes;
+
+/// Gdzie stosowana jest ekspozycja względem tone mappingu
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ExposureMode {
+    /// Scene-referred: mnożnik w liniowej przestrzeni sceny, przed tone mappingiem (domyślnie)
+    SceneLinear,
+    /// Display-referred: wzmocnienie obrazu wyświetlanego, po tone mappingu
+    DisplayGain,
+}
+
+impl ExposureMode {
+    pub fn from_label(label: &str) -> Self {
+        if label.starts_with("Display") { ExposureMode::DisplayGain } else { ExposureMode::SceneLinear }
+    }
+
+    /// Etykieta jak w liście wyboru UI
+    pub fn label(self) -> &'static str {
+        match self {
+            ExposureMode::SceneLinear => "Scene (before tone map)",
+            ExposureMode::DisplayGain => "Display (after tone map)",
+        }
+    }
+}
+
+/// Sposób redukcji RGB do skali szarości (w liniowej przestrzeni sceny, przed tone mappingiem)
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum GrayscaleMode {
+    /// Pełne RGB (kanały pojedyncze nadal jako luminancja)
+    Off,
+    /// Luminancja Rec.709: 0.2126 R + 0.7152 G + 0.0722 B
+    Luma,
+    Average,
+    Max,
+}
+
+impl GrayscaleMode {
+    pub fn from_label(label: &str) -> Self {
+        if label.starts_with("Lum") {
+            GrayscaleMode::Luma
+        } else if label.starts_with("Average") {
+            GrayscaleMode::Average
+        } else if label.starts_with("Max") {
+            GrayscaleMode::Max
+        } else {
+            GrayscaleMode::Off
+        }
+    }
+
+    /// Etykieta jak w liście wyboru UI
+    pub fn label(self) -> &'static str {
+        match self {
+            GrayscaleMode::Off => "RGB",
+            GrayscaleMode::Luma => "Luminance (Rec.709)",
+            GrayscaleMode::Average => "Average",
+            GrayscaleMode::Max => "Max",
+        }
+    }
+
+    #[inline]
+    pub fn reduce(self, r: f32, g: f32, b: f32) -> f32 {
+        match self {
+            GrayscaleMode::Off | GrayscaleMode::Luma => 0.2126 * r + 0.7152 * g + 0.0722 * b,
+            GrayscaleMode::Average => (r + g + b) / 3.0,
+            GrayscaleMode::Max => r.max(g).max(b),
+        }
+    }
+}
+
+/// Przestrzeń barw danych wejściowych (prymarki); podgląd pracuje w liniowym Rec.709/sRGB
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum InputColorSpace {
+    /// Liniowe Rec.709/sRGB – domyślne dla EXR bez atrybutu `chromaticities`
+    LinearRec709,
+    /// ACES2065-1 (prymarki AP0) – kontener ACES
+    Aces2065,
+    /// ACEScg (prymarki AP1)
+    AcesCg,
+    /// Inne prymarki z atrybutu `chromaticities` (np. biel D60 lub DCI) – macierz w `ProcessingGraph::file_primaries`
+    FilePrimaries,
+}
+
+impl InputColorSpace {
+    pub const ALL: [InputColorSpace; 4] = [InputColorSpace::LinearRec709, InputColorSpace::Aces2065, InputColorSpace::AcesCg, InputColorSpace::FilePrimaries];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            InputColorSpace::LinearRec709 => "Linear Rec.709 / sRGB",
+            InputColorSpace::Aces2065 => "ACES2065-1 (AP0)",
+            InputColorSpace::AcesCg => "ACEScg (AP1)",
+            InputColorSpace::FilePrimaries => "File chromaticities",
+        }
+    }
+
+    /// None dla "Auto" (przestrzeń wykryta z nagłówka)
+    pub fn from_label(label: &str) -> Option<Self> {
+        if label.starts_with("ACES2065") {
+            Some(InputColorSpace::Aces2065)
+        } else if label.starts_with("ACEScg") {
+            Some(InputColorSpace::AcesCg)
+        } else if label.starts_with("Linear") {
+            Some(InputColorSpace::LinearRec709)
+        } else {
+            None
+        }
+    }
+}
+
+/// Wartość sceny mapowana na średnią szarość (18%) przy zerowej ekspozycji
+pub const DEFAULT_MIDDLE_GRAY: f32 = 0.18;
+
+/// Transformacja wyświetlania: obrót o wielokrotność 90° (zgodnie z ruchem wskazówek) po odbiciach.
+/// Realizowana przez remapowanie indeksów przy generowaniu obrazu – dane źródłowe pozostają bez zmian.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct DisplayTransform {
+    pub quarter_turns: u8,
+    pub flip_h: bool,
+    pub flip_v: bool,
+}
+
+impl DisplayTransform {
+    pub fn is_identity(&self) -> bool {
+        self.quarter_turns & 3 == 0 && !self.flip_h && !self.flip_v
+    }
+
+    /// Wymiary obrazu po transformacji
+    pub fn output_size(&self, width: u32, height: u32) -> (u32, u32) {
+        if self.quarter_turns & 1 == 1 { (height, width) } else { (width, height) }
+    }
+
+    /// Indeks piksela źródłowego dla piksela (x, y) obrazu wyjściowego
+    #[inline]
+    pub fn source_index(&self, x: u32, y: u32, width: u32, height: u32) -> usize {
+        // Odwróć obrót, a potem odbicia
+        let (mut sx, mut sy) = match self.quarter_turns % 4 {
+            1 => (y, height - 1 - x),
+            2 => (width - 1 - x, height - 1 - y),
+            3 => (width - 1 - y, x),
+            _ => (x, y),
+        };
+        if self.flip_h { sx = width - 1 - sx; }
+        if self.flip_v { sy = height - 1 - sy; }
+        (sy as usize) * (width as usize) + (sx as usize)
+    }
+}
+
+/// Liniowe mapowanie zakresu AOV technicznych (normal/position/velocity) do [0, 1]:
+/// `v' = gain * (abs ? |v| : v) + offset`, bez ekspozycji i tone mappingu (to dane, nie kolor)
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ChannelRemap {
+    pub gain: f32,
+    pub offset: f32,
+    pub abs: bool,
+}
+
+impl ChannelRemap {
+    #[inline]
+    fn apply(&self, v: f32) -> u8 {
+        if !v.is_finite() { return 0; }
+        let v = if self.abs { v.abs() } else { v };
+        ((v * self.gain + self.offset).clamp(0.0, 1.0) * 255.0).round() as u8
+    }
+
+    #[inline]
+    pub fn remap_pixel(&self, r: f32, g: f32, b: f32, a: f32) -> Rgba8Pixel {
+        let safe_a = if a.is_finite() { a.clamp(0.0, 1.0) } else { 1.0 };
+        Rgba8Pixel {
+            r: self.apply(r),
+            g: self.apply(g),
+            b: self.apply(b),
+            a: (safe_a * 255.0).round() as u8,
+        }
+    }
+}
+
+/// Widok wektorów 2D (np. motion vectors): indeksy składowych piksela RGBA tworzących (x, y)
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct VectorView {
+    pub x: usize,
+    pub y: usize,
+}
+
+impl VectorView {
+    #[inline]
+    pub fn vector(&self, (r, g, b, a): (f32, f32, f32, f32)) -> (f32, f32) {
+        let c = [r, g, b, a];
+        (c[self.x], c[self.y])
+    }
+}
+
+/// Domyślna długość wektora (w pikselach) mapowana na pełną jasność
+pub const DEFAULT_VECTOR_MAX_MAGNITUDE: f32 = 16.0;
+
+/// Kodowanie HSV wektora: kierunek → barwa, długość / `max_magnitude` → jasność (nasycenie pełne)
+pub fn vector_to_hsv(x: f32, y: f32, max_magnitude: f32) -> Rgba8Pixel {
+    if !x.is_finite() || !y.is_finite() {
+        return Rgba8Pixel { r: 0, g: 0, b: 0, a: 255 };
+    }
+    let hue = (y.atan2(x).to_degrees() + 360.0) % 360.0;
+    let value = ((x * x + y * y).sqrt() / max_magnitude).min(1.0);
+    let sector = hue / 60.0;
+    let f = sector - sector.floor();
+    let (p, q, t) = (0.0, value * (1.0 - f), value * f);
+    let (r, g, b) = match sector as u32 {
+        0 => (value, t, p),
+        1 => (q, value, p),
+        2 => (p, value, t),
+        3 => (p, q, value),
+        4 => (t, p, value),
+        _ => (value, p, q),
+    };
+    let to8 = |v: f32| (v * 255.0).round().clamp(0.0, 255.0) as u8;
+    Rgba8Pixel { r: to8(r), g: to8(g), b: to8(b), a: 255 }
+}
+
+/// Kierunek światła podglądu normalnych (wektor jednostkowy) z azymutu i elewacji w stopniach.
+/// Azymut 0° = +X, 90° = +Y; elewacja 90° = wprost z +Z (w stronę kamery dla normalnych w przestrzeni kamery).
+pub fn relight_direction(azimuth_deg: f32, elevation_deg: f32) -> [f32; 3] {
+    let azimuth = azimuth_deg.to_radians();
+    let elevation = elevation_deg.clamp(-90.0, 90.0).to_radians();
+    [elevation.cos() * azimuth.cos(), elevation.cos() * azimuth.sin(), elevation.sin()]
+}
+
+/// Cieniowanie Lamberta (N·L z niewielkim światłem otoczenia); zdegenerowane normalne (zero/NaN) na czerwono
+pub fn shade_normal(n: (f32, f32, f32), light: [f32; 3]) -> Rgba8Pixel {
+    let length = (n.0 * n.0 + n.1 * n.1 + n.2 * n.2).sqrt();
+    if !length.is_finite() || length < 1e-6 {
+        return Rgba8Pixel { r: 255, g: 0, b: 0, a: 255 };
+    }
+    let n_dot_l = (n.0 * light[0] + n.1 * light[1] + n.2 * light[2]) / length;
+    let shade = 0.08 + 0.92 * n_dot_l.max(0.0);
+    let v = to_u8(GammaCurve::from_gamma_inv(1.0 / 2.2).apply(shade));
+    Rgba8Pixel { r: v, g: v, b: v, a: 255 }
+}
+
+/// Pasmo focus peakingu w widoku głębi (near ≤ far) jako ułamki znormalizowanego zakresu Z (0 = najbliżej)
+pub fn focus_band(near: f32, far: f32) -> (f32, f32) {
+    let (near, far) = (near.clamp(0.0, 1.0), far.clamp(0.0, 1.0));
+    (near.min(far), near.max(far))
+}
+
+/// Podświetlenie piksela w paśmie ostrości: szarość głębi zmieszana z zielenią
+#[inline]
+pub fn focus_peak(gray: u8) -> Rgba8Pixel {
+    let mix = |c: u8| ((gray as u16 * 2 + c as u16 * 3) / 5) as u8;
+    Rgba8Pixel { r: mix(40), g: mix(255), b: mix(40), a: 255 }
+}
+
+/// Przestrzeń docelowa ostrzeżenia o gamucie: piksele, których nie da się w niej zapisać bez
+/// ujemnych składowych (np. kolory urojone z renderów spektralnych), są zaznaczane na podglądzie
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum GamutWarning {
+    Off,
+    Srgb,
+    DisplayP3,
+    Rec2020,
+}
+
+impl GamutWarning {
+    pub fn from_label(label: &str) -> Self {
+        if label.starts_with("sRGB") {
+            GamutWarning::Srgb
+        } else if label.starts_with("Display P3") {
+            GamutWarning::DisplayP3
+        } else if label.starts_with("Rec.2020") {
+            GamutWarning::Rec2020
+        } else {
+            GamutWarning::Off
+        }
+    }
+
+    /// Etykieta jak w liście wyboru UI
+    pub fn label(self) -> &'static str {
+        match self {
+            GamutWarning::Off => "Off",
+            GamutWarning::Srgb => "sRGB / Rec.709",
+            GamutWarning::DisplayP3 => "Display P3",
+            GamutWarning::Rec2020 => "Rec.2020",
+        }
+    }
+
+    /// Macierz z liniowego Rec.709 do prymarek celu (biel D65); None = Rec.709 bez konwersji
+    fn rec709_to_target(self) -> Option<[[f32; 3]; 3]> {
+        match self {
+            GamutWarning::Off | GamutWarning::Srgb => None,
+            GamutWarning::DisplayP3 => Some([
+                [0.822_462, 0.177_538, 0.0],
+                [0.033_194, 0.966_806, 0.0],
+                [0.017_083, 0.072_397, 0.910_520],
+            ]),
+            GamutWarning::Rec2020 => Some([
+                [0.627_404, 0.329_283, 0.043_313],
+                [0.069_097, 0.919_541, 0.011_362],
+                [0.016_391, 0.088_013, 0.895_595],
+            ]),
+        }
+    }
+
+    /// Czy piksel w liniowym Rec.709 wypada poza gamut celu. Tolerancja względna do największej
+    /// składowej, żeby szum half-float wokół zera nie zapalał ostrzeżenia.
+    #[inline]
+    pub fn is_out_of_gamut(self, r: f32, g: f32, b: f32) -> bool {
+        if self == GamutWarning::Off || !(r.is_finite() && g.is_finite() && b.is_finite()) {
+            return false;
+        }
+        let (r, g, b) = match self.rec709_to_target() {
+            None => (r, g, b),
+            Some(m) => (
+                m[0][0] * r + m[0][1] * g + m[0][2] * b,
+                m[1][0] * r + m[1][1] * g + m[1][2] * b,
+                m[2][0] * r + m[2][1] * g + m[2][2] * b,
+            ),
+        };
+        let tolerance = 1e-3 * r.abs().max(g.abs()).max(b.abs());
+        r.min(g).min(b) < -tolerance
+    }
+}
+
+/// Kolor zaznaczenia pikseli poza gamutem (magenta – rzadka w rzeczywistych obrazach)
+pub const GAMUT_WARNING_COLOR: Rgba8Pixel = Rgba8Pixel { r: 255, g: 0, b: 255, a: 255 };
+
+const fn rgb(r: u8, g: u8, b: u8) -> Rgba8Pixel {
+    Rgba8Pixel { r, g, b, a: 255 }
+}
+
+/// Pasma false color: dolna granica w stopniach względem średniej szarości (po ekspozycji i pivocie)
+/// i kolor; fiolet = czerń, zieleń = 18%, czerwień = przepalenie
+pub const FALSE_COLOR_BANDS: [(f32, Rgba8Pixel); 9] = [
+    (f32::NEG_INFINITY, rgb(0x60, 0x1A, 0x8C)),
+    (-6.0, rgb(0x1F, 0x4F, 0xD6)),
+    (-4.0, rgb(0x1A, 0xA3, 0xA3)),
+    (-2.0, rgb(0x5A, 0x7A, 0x5A)),
+    (-0.5, rgb(0x3C, 0xCF, 0x4E)),
+    (0.5, rgb(0xA0, 0xA0, 0xA0)),
+    (2.0, rgb(0xF2, 0xD9, 0x1A)),
+    (4.0, rgb(0xF2, 0x86, 0x1A)),
+    (6.0, rgb(0xE0, 0x20, 0x1A)),
+];
+
+/// Luminancja sceny 1.0 w nitach (biel odniesienia SDR) – jednostka legendy false color
+pub const SCENE_WHITE_NITS: f32 = 100.0;
+
+/// Jednostka skali legendy false color
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LegendUnit {
+    /// Stopnie sceny względem średniej szarości (0.18)
+    Ev,
+    Nits,
+}
+
+/// Kolor pasma dla luminancji sceny w przestrzeni roboczej; wartości ≤ 0 i NaN w pierwszym paśmie
+pub fn false_color_pixel(y: f32, params: &ToneParams) -> Rgba8Pixel {
+    let ev = (y * params.scene_multiplier / DEFAULT_MIDDLE_GRAY).log2();
+    FALSE_COLOR_BANDS.iter().rev()
+        .find(|(lower, _)| ev >= *lower)
+        .map_or(FALSE_COLOR_BANDS[0].1, |&(_, color)| color)
+}
+
+/// Legenda false color od najjaśniejszego pasma: kolor i zakres wartości sceny, które w nie
+/// trafiają przy bieżącej ekspozycji i pivocie (te same `ToneParams` co podgląd)
+pub fn false_color_legend(params: &ToneParams, unit: LegendUnit) -> Vec<(Rgba8Pixel, String)> {
+    let value = |ev: f32| match unit {
+        LegendUnit::Ev => format!("{:+.1} EV", ev - params.scene_multiplier.log2()),
+        LegendUnit::Nits => {
+            let nits = DEFAULT_MIDDLE_GRAY * ev.exp2() / params.scene_multiplier * SCENE_WHITE_NITS;
+            match nits {
+                n if n < 1.0 => format!("{:.3} nits", n),
+                n if n < 100.0 => format!("{:.1} nits", n),
+                n => format!("{:.0} nits", n),
+            }
+        }
+    };
+    (0..FALSE_COLOR_BANDS.len()).rev()
+        .map(|i| {
+            let (lower, color) = FALSE_COLOR_BANDS[i];
+            let upper = FALSE_COLOR_BANDS.get(i + 1).map(|&(upper, _)| upper);
+            let label = match (lower.is_finite(), upper) {
+                (false, Some(upper)) => format!("< {}", value(upper)),
+                (true, Some(upper)) => format!("{} … {}", value(lower), value(upper)),
+                (_, None) => format!("≥ {}", value(lower)),
+            };
+            (color, label)
+        })
+        .collect()
+}
 
 /// Przetwarza pojedynczy piksel z wartościami HDR na 8-bitowe RGB
 pub fn process_pixel(r: f32, g: f32, b: f32, a: f32, exposure: f32, gamma: f32) -> Rgba8Pixel {
-    let exposure_multiplier = 2.0_f32.powf(exposure);
-    let gamma_inv = 1.0 / gamma.max(1e-4);
-    
-    // Sprawdzenie NaN/Inf i clamp do sensownych wartości
-    let safe_r = if r.is_finite() { r.max(0.0) } else { 0.0 };
-    let safe_g = if g.is_finite() { g.max(0.0) } else { 0.0 };
-    let safe_b = if b.is_finite() { b.max(0.0) } else { 0.0 };
-    let safe_a = if a.is_finite() { a.clamp(0.0, 1.0) } else { 1.0 };
-    
-    // Zastosowanie ekspozycji
-    let exposed_r = safe_r * exposure_multiplier;
-    let exposed_g = safe_g * exposure_multiplier;
-    let exposed_b = safe_b * exposure_multiplier;
-    
-    // ACES tone mapping (lepszy niż Reinhard)
-    let tone_mapped_r = aces_tonemap(exposed_r);
-    let tone_mapped_g = aces_tonemap(exposed_g);
-    let tone_mapped_b = aces_tonemap(exposed_b);
-    
-    // Optymalizowana gamma correction
-    let corrected_r = apply_gamma_fast(tone_mapped_r, gamma_inv);
-    let corrected_g = apply_gamma_fast(tone_mapped_g, gamma_inv);
-    let corrected_b = apply_gamma_fast(tone_mapped_b, gamma_inv);
-    
-    Rgba8Pixel {
-        r: (corrected_r * 255.0).round().clamp(0.0, 255.0) as u8,
-        g: (corrected_g * 255.0).round().clamp(0.0, 255.0) as u8,
-        b: (corrected_b * 255.0).round().clamp(0.0, 255.0) as u8,
-        a: (safe_a * 255.0).round().clamp(0.0, 255.0) as u8,
+    ProcessingGraph::standard(exposure, gamma).tone_params().pixel(r, g, b, a)
+}
+
+/// Operator etapu tone mappingu
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TonemapMode {
+    /// Globalna krzywa ACES (Narkowicz)
+    Aces,
+    /// Lokalna adaptacja (wyrównanie jasności dużych obszarów do średniej obrazu), potem ACES
+    LocalAces,
+}
+
+impl TonemapMode {
+    pub fn from_label(label: &str) -> Self {
+        if label.starts_with("Local") { TonemapMode::LocalAces } else { TonemapMode::Aces }
+    }
+
+    /// Etykieta jak w liście wyboru UI
+    pub fn label(self) -> &'static str {
+        match self {
+            TonemapMode::Aces => "ACES (Narkowicz)",
+            TonemapMode::LocalAces => "Local (ACES)",
+        }
+    }
+}
+
+/// Parametry lokalnego tone mappingu
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LocalTonemap {
+    /// Promień adaptacji (sigma rozmycia) jako ułamek dłuższego boku obrazu
+    pub radius: f32,
+    /// 0 = sam ACES, 1 = jasność dużych obszarów w pełni wyrównana do średniej
+    pub strength: f32,
+}
+
+impl LocalTonemap {
+    /// Lokalny tone mapping dla trybu `LocalAces` (parametry przycięte do zakresu suwaków), None dla ACES
+    pub fn for_mode(mode: TonemapMode, radius: f32, strength: f32) -> Option<Self> {
+        (mode == TonemapMode::LocalAces).then(|| LocalTonemap { radius: radius.clamp(0.005, 0.25), strength: strength.clamp(0.0, 1.0) })
+    }
+}
+
+/// Dłuższy bok mapy adaptacji
+const LOCAL_ADAPTATION_RESOLUTION: usize = 256;
+/// Zakres wzmocnienia lokalnego (±6 EV)
+const LOCAL_GAIN_LIMIT: f32 = 64.0;
+
+/// Lokalna adaptacja przed globalną krzywą: log2 luminancji rozmyty w małej rozdzielczości to
+/// jasność otoczenia piksela; wzmocnienie 2^(strength · (klucz − otoczenie)), gdzie klucz to średnia
+/// logarytmiczna całego obrazu. Kontrast dużych obszarów maleje, szczegół (stosunek piksela do
+/// otoczenia) zostaje. Wzmocnienie jest wspólne dla RGB (bez zmiany barwy) i nie zależy od ekspozycji.
+pub fn local_adaptation(pixels: &[(f32, f32, f32, f32)], width: usize, height: usize, local: &LocalTonemap) -> Vec<(f32, f32, f32, f32)> {
+    if width == 0 || height == 0 || local.strength <= 0.0 {
+        return pixels.to_vec();
+    }
+    let mut surround = LowRes::average(pixels, width, height, LOCAL_ADAPTATION_RESOLUTION, |(r, g, b, _)| {
+        let y = GrayscaleMode::Luma.reduce(r, g, b);
+        [if y.is_finite() { y.max(1e-6).log2() } else { 0.0 }]
+    });
+    let key = surround.data.iter().sum::<f32>() / surround.data.len() as f32;
+    surround.blur(local.radius);
+
+    let mut out = pixels.to_vec();
+    out.par_chunks_mut(width).enumerate().for_each(|(y, row)| {
+        for (x, px) in row.iter_mut().enumerate() {
+            let gain = (local.strength * (key - surround.at(x, y, 0))).exp2().clamp(1.0 / LOCAL_GAIN_LIMIT, LOCAL_GAIN_LIMIT);
+            *px = (px.0 * gain, px.1 * gain, px.2 * gain, px.3);
+        }
+    });
+    out
+}
+
+/// Etap transformacji widoku w kolejności stosowania (panel "Pipeline")
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Stage {
+    InputMatrix = 0,
+    Exposure = 1,
+    WhiteBalance = 2,
+    Tonemap = 3,
+    Gamma = 4,
+}
+
+impl Stage {
+    pub const ALL: [Stage; 5] = [Stage::InputMatrix, Stage::Exposure, Stage::WhiteBalance, Stage::Tonemap, Stage::Gamma];
+
+    pub fn from_index(index: usize) -> Option<Self> {
+        Self::ALL.get(index).copied()
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Stage::InputMatrix => "Input matrix",
+            Stage::Exposure => "Exposure",
+            Stage::WhiteBalance => "White balance",
+            Stage::Tonemap => "Tonemap",
+            Stage::Gamma => "Gamma",
+        }
+    }
+
+    fn bit(self) -> u8 {
+        1 << self as u8
+    }
+}
+
+const ALL_STAGES: u8 = 0b1_1111;
+
+/// Mnożniki balansu bieli w liniowym Rec.709 z próbki neutralnej (liniowe RGB): wyrównują kanały
+/// do luminancji próbki. None (albo próbka bez dodatnich kanałów) daje neutralne mnożniki.
+pub fn white_balance_gains(neutral: Option<[f32; 3]>) -> [f32; 3] {
+    match neutral {
+        Some([r, g, b]) if r > 0.0 && g > 0.0 && b > 0.0 => {
+            let y = 0.2126 * r + 0.7152 * g + 0.0722 * b;
+            [y / r, y / g, y / b]
+        }
+        _ => [1.0; 3],
+    }
+}
+
+/// Uporządkowany opis transformacji widoku (prymarki wejściowe → ekspozycja → balans bieli →
+/// tone mapping → gamma) z przełącznikami etapów; ścieżki obrazu budują z niego `ToneParams`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ProcessingGraph {
+    pub input: InputColorSpace,
+    pub exposure: f32,
+    pub exposure_mode: ExposureMode,
+    /// Wartość sceny mapowana na średnią szarość (pivot)
+    pub middle_gray: f32,
+    pub white_balance: [f32; 3],
+    pub gamma: f32,
+    /// Lokalny tone mapping (None = globalny ACES)
+    pub local_tonemap: Option<LocalTonemap>,
+    /// Prymarki pliku → Rec.709 dla wejścia `FilePrimaries` (zapamiętane z pliku, z którego powstał graf)
+    pub file_primaries: Mat3,
+    enabled: u8,
+}
+
+impl ProcessingGraph {
+    /// Wszystkie etapy włączone, wejście Rec.709, ekspozycja sceny z pivotem 0.18, neutralny balans
+    /// bieli i globalny ACES (miniatury, skrypty, metryki, punkt wyjścia ustawień widoku `AppContext`)
+    pub fn standard(exposure: f32, gamma: f32) -> Self {
+        ProcessingGraph {
+            input: InputColorSpace::LinearRec709,
+            exposure,
+            exposure_mode: ExposureMode::SceneLinear,
+            middle_gray: DEFAULT_MIDDLE_GRAY,
+            white_balance: [1.0; 3],
+            gamma,
+            local_tonemap: None,
+            file_primaries: color_processing::IDENTITY,
+            enabled: ALL_STAGES,
+        }
+    }
+
+    pub fn is_enabled(&self, stage: Stage) -> bool {
+        self.enabled & stage.bit() != 0
+    }
+
+    pub fn set_enabled(&mut self, stage: Stage, enabled: bool) {
+        if enabled { self.enabled |= stage.bit(); } else { self.enabled &= !stage.bit(); }
+    }
+
+    /// Macierz z przestrzeni wejściowej do `target`; prymarki pliku z grafu, nie z bieżącego pliku
+    pub fn input_to(&self, target: InputColorSpace) -> Option<Mat3> {
+        use InputColorSpace::{FilePrimaries, LinearRec709};
+        match (self.input, target) {
+            (input, target) if input == target => None,
+            (FilePrimaries, target) => {
+                let to_target = color_processing::conversion_matrix(LinearRec709, target);
+                Some(to_target.map_or(self.file_primaries, |m| color_processing::mul(&m, &self.file_primaries)))
+            }
+            (input, FilePrimaries) => {
+                let from_rec709 = color_processing::invert(&self.file_primaries).unwrap_or(color_processing::IDENTITY);
+                let to_rec709 = color_processing::conversion_matrix(input, LinearRec709);
+                Some(to_rec709.map_or(from_rec709, |m| color_processing::mul(&from_rec709, &m)))
+            }
+            (input, target) => color_processing::conversion_matrix(input, target),
+        }
+    }
+
+    /// Czy etap cokolwiek zmienia przy bieżących parametrach (np. macierz dla wejścia Rec.709 – nie)
+    pub fn is_active(&self, stage: Stage) -> bool {
+        match stage {
+            Stage::InputMatrix => self.input_to(InputColorSpace::LinearRec709).is_some(),
+            Stage::WhiteBalance => self.white_balance != [1.0; 3],
+            _ => true,
+        }
+    }
+
+    /// Krótki opis parametrów etapu do panelu
+    pub fn describe(&self, stage: Stage) -> String {
+        match stage {
+            Stage::InputMatrix => format!("{} → Rec.709", self.input.label()),
+            Stage::Exposure => format!("{:+.2} EV ({})", self.exposure, self.exposure_mode.label()),
+            Stage::WhiteBalance => {
+                let [r, g, b] = self.white_balance;
+                format!("×{:.3} ×{:.3} ×{:.3}", r, g, b)
+            }
+            Stage::Tonemap => match self.local_tonemap {
+                None => TonemapMode::Aces.label().to_string(),
+                Some(local) => format!("{}, radius {:.1}%, strength {:.2}", TonemapMode::LocalAces.label(), local.radius * 100.0, local.strength),
+            },
+            Stage::Gamma => format!("{:.2}", self.gamma),
+        }
+    }
+
+    /// Lokalny tone mapping do zastosowania na pikselach przed `tone_params` (etap włączony)
+    pub fn local_adaptation(&self) -> Option<LocalTonemap> {
+        self.local_tonemap.filter(|_| self.is_enabled(Stage::Tonemap))
+    }
+
+    /// Parametry dla ścieżki skalarnej i kerneli wektorowych; balans bieli (liniowy, po macierzy
+    /// wejściowej) jest wliczany w macierz
+    pub fn tone_params(&self) -> ToneParams {
+        let input = if self.is_enabled(Stage::InputMatrix) { self.input_to(InputColorSpace::LinearRec709) } else { None };
+        let matrix = match (input, self.is_enabled(Stage::WhiteBalance) && self.white_balance != [1.0; 3]) {
+            (m, false) => m,
+            (m, true) => {
+                let gains = self.white_balance;
+                let m = m.unwrap_or([[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]]);
+                Some([0, 1, 2].map(|row| m[row].map(|v| v * gains[row])))
+            }
+        };
+
+        let display_gain = self.exposure_mode == ExposureMode::DisplayGain;
+        let pivot_scale = DEFAULT_MIDDLE_GRAY / self.middle_gray.clamp(0.001, 10.0);
+        let exposure_multiplier = 2.0_f32.powf(self.exposure);
+        // W trybie display ekspozycja działa dopiero po tone mappingu
+        let (scene_multiplier, display_multiplier) = match (self.is_enabled(Stage::Exposure), display_gain) {
+            (false, _) => (1.0, 1.0),
+            (true, true) => (pivot_scale, exposure_multiplier),
+            (true, false) => (exposure_multiplier * pivot_scale, 1.0),
+        };
+        let gamma = if self.is_enabled(Stage::Gamma) {
+            GammaCurve::from_gamma_inv(1.0 / self.gamma.max(1e-4))
+        } else {
+            GammaCurve::Linear
+        };
+        ToneParams { matrix, scene_multiplier, display_multiplier, tonemap: self.is_enabled(Stage::Tonemap), gamma }
     }
 }
 
+/// Krzywa gammy wyświetlania; typowe wartości mają szybkie warianty bez `powf`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum GammaCurve {
+    Linear,
+    /// Gamma 2.0
+    Sqrt,
+    /// Przybliżenie 2.2 używane przez podgląd: √v · ⁴√v
+    Fast22,
+    Power(f32),
+}
+
+impl GammaCurve {
+    pub fn from_gamma_inv(gamma_inv: f32) -> Self {
+        match gamma_inv {
+            x if (x - 0.45454545).abs() < 0.001 => GammaCurve::Fast22,
+            x if (x - 0.5).abs() < 0.001 => GammaCurve::Sqrt,
+            x if (x - 1.0).abs() < 0.001 => GammaCurve::Linear,
+            x => GammaCurve::Power(x),
+        }
+    }
+
+    #[inline]
+    pub fn apply(self, value: f32) -> f32 {
+        match self {
+            GammaCurve::Linear => value,
+            GammaCurve::Sqrt => value.sqrt(),
+            GammaCurve::Fast22 => {
+                let sqrt_val = value.sqrt();
+                sqrt_val * sqrt_val.sqrt()
+            }
+            GammaCurve::Power(gamma_inv) => value.powf(gamma_inv),
+        }
+    }
+}
+
+/// Parametry pipeline'u koloru (prymarki wejściowe → ekspozycja → ACES → gamma) odczytane raz na
+/// przebieg z `ProcessingGraph`; wspólne dla ścieżki skalarnej i wektorowej (`simd_processing`)
+#[derive(Clone, Copy, Debug)]
+pub struct ToneParams {
+    /// Macierz do liniowego Rec.709 (z balansem bieli); None gdy jednostkowa
+    pub matrix: Option<[[f32; 3]; 3]>,
+    pub scene_multiplier: f32,
+    pub display_multiplier: f32,
+    /// false = etap tone mappingu wyłączony (liniowe obcięcie do 1.0)
+    pub tonemap: bool,
+    pub gamma: GammaCurve,
+}
+
+impl ToneParams {
+    /// Czy kernele wektorowe obsługują te parametry (bez `powf` i z tone mappingiem ACES)
+    pub fn is_vectorizable(&self) -> bool {
+        self.tonemap && !matches!(self.gamma, GammaCurve::Power(_))
+    }
+
+    /// Etap macierzy wejściowej – dla ścieżek redukujących kolor przed tone mappingiem
+    #[inline]
+    pub fn working_rgb(&self, r: f32, g: f32, b: f32) -> (f32, f32, f32) {
+        match self.matrix {
+            None => (r, g, b),
+            Some(m) => (
+                m[0][0] * r + m[0][1] * g + m[0][2] * b,
+                m[1][0] * r + m[1][1] * g + m[1][2] * b,
+                m[2][0] * r + m[2][1] * g + m[2][2] * b,
+            ),
+        }
+    }
+
+    /// Te same parametry dla danych już w przestrzeni roboczej (po `working_rgb`)
+    pub fn without_matrix(&self) -> Self {
+        ToneParams { matrix: None, ..*self }
+    }
+
+    /// Wartość wyświetlana 0..1 przed gammą (ekspozycja, pivot, tone mapping) dla nieujemnej
+    /// wartości sceny w przestrzeni roboczej
+    #[inline]
+    pub fn display_value(&self, v: f32) -> f32 {
+        let scene = v * self.scene_multiplier;
+        let curve = if self.tonemap { aces_tonemap(scene) } else { scene.min(1.0) };
+        (curve * self.display_multiplier).min(1.0)
+    }
+
+    /// Wersja skalarna – wzorzec dla kerneli wektorowych (wyniki muszą być identyczne)
+    #[inline]
+    pub fn pixel(&self, r: f32, g: f32, b: f32, a: f32) -> Rgba8Pixel {
+        let (r, g, b) = self.working_rgb(r, g, b);
+
+        // Sprawdzenie NaN/Inf i clamp do sensownych wartości
+        let safe_r = if r.is_finite() { r.max(0.0) } else { 0.0 };
+        let safe_g = if g.is_finite() { g.max(0.0) } else { 0.0 };
+        let safe_b = if b.is_finite() { b.max(0.0) } else { 0.0 };
+        let safe_a = if a.is_finite() { a.clamp(0.0, 1.0) } else { 1.0 };
+
+        // Ekspozycja (scene-linear) i pivot średniej szarości, ACES, w trybie display wzmocnienie po tone mappingu
+        let channel = |v: f32| to_u8(self.gamma.apply(self.display_value(v)));
+        Rgba8Pixel { r: channel(safe_r), g: channel(safe_g), b: channel(safe_b), a: to_u8(safe_a) }
+    }
+}
+
+// Hash wartości wynikowych (a nie ustawień), więc obejmuje też pivot, tryb ekspozycji i macierz
+// prymarek pliku – klucz pamięci podręcznej renderów (`render_cache`)
+impl Hash for ToneParams {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.matrix.map(|m| m.map(|row| row.map(f32::to_bits))).hash(state);
+        self.scene_multiplier.to_bits().hash(state);
+        self.display_multiplier.to_bits().hash(state);
+        self.tonemap.hash(state);
+        std::mem::discriminant(&self.gamma).hash(state);
+        if let GammaCurve::Power(gamma_inv) = self.gamma {
+            gamma_inv.to_bits().hash(state);
+        }
+    }
+}
+
+#[inline]
+fn to_u8(value: f32) -> u8 {
+    (value * 255.0).round().clamp(0.0, 255.0) as u8
+}
+
+/// Współczynniki dopasowania ACES (Narkowicz): x(ax + b) / (x(cx + d) + e)
+pub(crate) const ACES_COEFFICIENTS: [f32; 5] = [2.51, 0.03, 2.43, 0.59, 0.14];
+
 /// ACES tone mapping - znacznie lepszy od Reinhard
 #[inline]
 fn aces_tonemap(x: f32) -> f32 {
-    let a = 2.51;
-    let b = 0.03;
-    let c = 2.43;
-    let d = 0.59;
-    let e = 0.14;
+    let [a, b, c, d, e] = ACES_COEFFICIENTS;
     ((x * (a * x + b)) / (x * (c * x + d) + e)).clamp(0.0, 1.0)
 }
 
-/// Szybka gamma correction z lookup table dla typowych wartości
-#[inline]
-fn apply_gamma_fast(value: f32, gamma_inv: f32) -> f32 {
-    match gamma_inv {
-        x if (x - 0.45454545).abs() < 0.001 => {
-            // sRGB gamma (1/2.2) - najczęstsza
-            let sqrt_val = value.sqrt();
-            sqrt_val * sqrt_val.sqrt()
-        },
-        x if (x - 0.5).abs() < 0.001 => {
-            // Gamma 2.0
-            value.sqrt()
-        },
-        x if (x - 1.0).abs() < 0.001 => {
-            // Gamma 1.0 (linear)
-            value
-        },
-        _ => value.powf(gamma_inv)
+// usunięto nieużywaną funkcję read_exr_to_slint_image
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rgba(px: Rgba8Pixel) -> [u8; 4] {
+        [px.r, px.g, px.b, px.a]
     }
-}
 
-// usunięto nieużywaną funkcję read_exr_to_slint_image
+    /// Wartości wzorcowe potoku ACES + gamma; zmiana krzywej lub zaokrągleń musi je świadomie zaktualizować
+    #[test]
+    fn tone_mapping_golden() {
+        let cases = [
+            ((0.0, 0.0, 0.0, 1.0), 0.0, 2.2, [0, 0, 0, 255]),
+            ((0.18, 0.18, 0.18, 1.0), 0.0, 2.2, [95, 95, 95, 255]),
+            ((1.0, 1.0, 1.0, 1.0), 0.0, 2.2, [216, 216, 216, 255]),
+            ((4.0, 4.0, 4.0, 1.0), 0.0, 2.2, [250, 250, 250, 255]),
+            ((0.5, 0.25, 0.1, 0.5), 0.0, 2.2, [177, 122, 54, 128]),
+            ((0.5, 0.25, 0.1, 0.5), 1.0, 2.2, [216, 177, 103, 128]),
+            ((0.5, 0.25, 0.1, 0.5), -1.0, 1.0, [95, 44, 11, 128]),
+            ((0.5, 0.25, 0.1, 0.5), 0.0, 2.4, [208, 169, 108, 128]),
+            // Wartości niefizyczne: ujemne i NaN → czerń, nieskończoność bez przepełnień
+            ((-1.0, f32::NAN, f32::INFINITY, 2.0), 0.0, 2.2, [0, 0, 0, 255]),
+        ];
+        for ((r, g, b, a), exposure, gamma, expected) in cases {
+            assert_eq!(rgba(process_pixel(r, g, b, a, exposure, gamma)), expected, "({r}, {g}, {b}, {a}) exposure {exposure} gamma {gamma}");
+        }
+    }
+
+    #[test]
+    fn color_matrices_golden() {
+        for space in [InputColorSpace::LinearRec709, InputColorSpace::Aces2065, InputColorSpace::AcesCg] {
+            // Biel pozostaje bielą (adaptacja Bradforda D60 → D65)
+            let graph = ProcessingGraph { input: space, ..ProcessingGraph::standard(0.0, 2.2) };
+            let (r, g, b) = graph.tone_params().working_rgb(1.0, 1.0, 1.0);
+            assert!((r - 1.0).abs() < 2e-3 && (g - 1.0).abs() < 2e-3 && (b - 1.0).abs() < 2e-3, "{:?}: white → ({r}, {g}, {b})", space);
+        }
+        let cases = [
+            (InputColorSpace::LinearRec709, (0.5, 0.25, 0.1), [177, 122, 54, 255]),
+            (InputColorSpace::Aces2065, (0.5, 0.25, 0.1), [214, 101, 37, 255]),
+            (InputColorSpace::Aces2065, (0.1, 0.4, 0.8), [0, 169, 210, 255]),
+            (InputColorSpace::AcesCg, (0.5, 0.25, 0.1), [198, 111, 37, 255]),
+            (InputColorSpace::AcesCg, (0.1, 0.4, 0.8), [0, 167, 210, 255]),
+        ];
+        for (space, (r, g, b), expected) in cases {
+            let graph = ProcessingGraph { input: space, ..ProcessingGraph::standard(0.0, 2.2) };
+            assert_eq!(rgba(graph.tone_params().pixel(r, g, b, 1.0)), expected, "{:?}: ({r}, {g}, {b})", space);
+        }
+    }
+
+    #[test]
+    fn pipeline_stages() {
+        let standard = ProcessingGraph::standard(1.0, 2.2);
+        let mut graph = standard;
+        graph.enabled &= !(Stage::Exposure.bit() | Stage::Tonemap.bit() | Stage::Gamma.bit());
+        // Bez ekspozycji, tone mappingu i gammy zostaje liniowe obcięcie do 1.0
+        assert_eq!(rgba(graph.tone_params().pixel(0.5, 0.25, 2.0, 1.0)), [128, 64, 255, 255]);
+
+        // Balans bieli z szarej próbki jest neutralny, z kolorowej wyrównuje kanały do jej luminancji
+        let balanced = ProcessingGraph { white_balance: [2.0, 1.0, 0.5], ..standard };
+        assert_eq!(balanced.tone_params().working_rgb(0.25, 0.5, 1.0), (0.5, 0.5, 0.5));
+        graph = ProcessingGraph { enabled: ALL_STAGES & !Stage::WhiteBalance.bit(), ..balanced };
+        assert!(graph.tone_params().matrix.is_none());
+
+        // Tryb ekspozycji i pivot pochodzą z grafu, nie z ustawień globalnych podglądu
+        assert_eq!((standard.tone_params().scene_multiplier, standard.tone_params().display_multiplier), (2.0, 1.0));
+        let display = ProcessingGraph { exposure_mode: ExposureMode::DisplayGain, middle_gray: 0.36, ..standard }.tone_params();
+        assert_eq!((display.scene_multiplier, display.display_multiplier), (0.5, 2.0));
+    }
+
+    #[test]
+    fn local_adaptation_compresses_large_scale_contrast() {
+        // Lewa połowa 0.05, prawa 5.0 (ok. 6.6 EV różnicy), alfa zachowana
+        let (width, height) = (64, 16);
+        let pixels: Vec<_> = (0..width * height).map(|i| if i % width < 32 { (0.05, 0.05, 0.05, 0.5) } else { (5.0, 5.0, 5.0, 0.5) }).collect();
+        let local = LocalTonemap { radius: 0.05, strength: 0.5 };
+        let out = local_adaptation(&pixels, width, height, &local);
+        let (dark, bright) = (out[8 * width + 2], out[8 * width + 61]);
+        assert!(dark.0 > 0.05 && bright.0 < 5.0, "{:?} {:?}", dark, bright);
+        assert!((bright.0 / dark.0).log2() < 4.0 && (dark.0, dark.3) == (dark.2, 0.5));
+        assert_eq!(local_adaptation(&pixels, width, height, &LocalTonemap { strength: 0.0, ..local }), pixels);
+
+        let graph = ProcessingGraph { local_tonemap: Some(local), ..ProcessingGraph::standard(0.0, 2.2) };
+        assert_eq!(graph.local_adaptation(), Some(local));
+        assert_eq!(ProcessingGraph { enabled: ALL_STAGES & !Stage::Tonemap.bit(), ..graph }.local_adaptation(), None);
+    }
+
+    #[test]
+    fn false_color_legend_follows_exposure() {
+        let params = ProcessingGraph::standard(0.0, 2.2).tone_params();
+        assert_eq!(false_color_pixel(0.18, &params), FALSE_COLOR_BANDS[4].1);
+        assert_eq!(false_color_pixel(-1.0, &params), FALSE_COLOR_BANDS[0].1);
+        assert_eq!(false_color_pixel(f32::NAN, &params), FALSE_COLOR_BANDS[0].1);
+
+        let legend = false_color_legend(&params, LegendUnit::Ev);
+        assert_eq!(legend.len(), FALSE_COLOR_BANDS.len());
+        assert_eq!((legend[0].0, legend[0].1.as_str()), (FALSE_COLOR_BANDS[8].1, "≥ +6.0 EV"));
+        assert_eq!(legend[8].1, "< -6.0 EV");
+        assert_eq!(false_color_legend(&params, LegendUnit::Nits)[4].1, "12.7 nits … 25.5 nits");
+
+        // +1 EV: te same pasma obejmują o stopień ciemniejsze wartości sceny
+        let brighter = ProcessingGraph::standard(1.0, 2.2).tone_params();
+        assert_eq!(false_color_legend(&brighter, LegendUnit::Ev)[0].1, "≥ +5.0 EV");
+        assert_eq!(false_color_pixel(0.09, &brighter), FALSE_COLOR_BANDS[4].1);
+    }
+}
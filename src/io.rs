@@ -0,0 +1,77 @@
+// Odczyt plików na potrzeby skanów nagłówków. Po włączeniu w ustawieniach (`preferences`) plik
+// jest mapowany w pamięć (memmap2): parser dotyka tylko stron z nagłówkiem, a przy powtórnych
+// skanach katalogów (udziały sieciowe) strony zostają w pamięci podręcznej systemu. Domyślnie,
+// dla plików świeżo zapisywanych (klatki renderu w toku, tryb obserwacji folderu) i gdy mapowanie
+// się nie uda (puste pliki, część systemów plików FUSE/sieciowych) czytamy buforowanym odczytem.
+
+pub mod fast_exr_metadata;
+pub mod file_operations;
+pub mod luminance_chroma;
+pub mod recovery;
+#[cfg(all(test, feature = "synthetic"))]
+pub mod synthetic;
+
+use std::fs::File;
+use std::io::{BufReader, Cursor, Read, Seek, SeekFrom};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, SystemTime};
+use memmap2::Mmap;
+use tracing::debug;
+
+/// Pliki zmienione w tym czasie mogą jeszcze rosnąć lub zostać skrócone przez zapisujący proces
+const RECENT_WRITE: Duration = Duration::from_secs(30);
+
+static MMAP_READS: AtomicBool = AtomicBool::new(false);
+
+pub fn set_mmap_reads(enabled: bool) {
+    MMAP_READS.store(enabled, Ordering::Relaxed);
+}
+
+pub fn mmap_reads() -> bool {
+    MMAP_READS.load(Ordering::Relaxed)
+}
+
+/// Czy plik był modyfikowany przed chwilą (także data z przyszłości – zegar udziału)
+fn recently_written(metadata: &std::fs::Metadata) -> bool {
+    metadata.modified().is_ok_and(|modified| SystemTime::now().duration_since(modified).ok().is_none_or(|age| age < RECENT_WRITE))
+}
+
+/// Źródło bajtów pliku: mapowanie albo buforowany odczyt – oba z Read + Seek
+pub enum FileSource {
+    Mapped(Cursor<Mmap>),
+    Buffered(BufReader<File>),
+}
+
+impl Read for FileSource {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        match self {
+            FileSource::Mapped(cursor) => cursor.read(buf),
+            FileSource::Buffered(reader) => reader.read(buf),
+        }
+    }
+}
+
+impl Seek for FileSource {
+    fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+        match self {
+            FileSource::Mapped(cursor) => cursor.seek(pos),
+            FileSource::Buffered(reader) => reader.seek(pos),
+        }
+    }
+}
+
+/// Otwiera plik do odczytu nagłówków: mapowanie, jeśli włączone i możliwe, w przeciwnym razie bufor
+pub fn open(path: &Path) -> std::io::Result<FileSource> {
+    let file = File::open(path)?;
+    if mmap_reads() && file.metadata().is_ok_and(|m| m.len() > 0 && !recently_written(&m)) {
+        // SAFETY: mapowanie tylko do odczytu; plik skrócony w trakcie odczytu przez inny proces
+        // może przerwać program (SIGBUS) – dlatego mapowanie jest opcją wyłączoną domyślnie
+        // i pomija pliki zapisywane przed chwilą
+        match unsafe { Mmap::map(&file) } {
+            Ok(map) => return Ok(FileSource::Mapped(Cursor::new(map))),
+            Err(e) => debug!(target: "io", "mmap of {} failed ({}), using buffered read", path.display(), e),
+        }
+    }
+    Ok(FileSource::Buffered(BufReader::new(file)))
+}
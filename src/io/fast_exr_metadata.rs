@@ -0,0 +1,136 @@
+// Szybki skan samych nagłówków EXR (bez danych pikseli): rozdzielczość, liczba części, warstw
+// i kanałów, typy próbek i kompresja. Używany przy listowaniu katalogów – koszt rośnie z liczbą
+// plików, nie z ich rozmiarem.
+
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{LazyLock, Mutex};
+use std::time::SystemTime;
+use ::exr::compression::Compression;
+use ::exr::meta::attribute::SampleType;
+use ::exr::meta::header::Header;
+use crate::utils::error_handling::ExrResult;
+use crate::utils::split_layer_and_short;
+
+#[derive(Clone, Debug)]
+pub struct FastExrMetadata {
+    /// Rozmiar display window (pierwsza część)
+    pub width: usize,
+    pub height: usize,
+    pub part_count: usize,
+    /// Warstwy w rozumieniu drzewa warstw (prefiks nazwy kanału albo atrybut `name` części)
+    pub layer_count: usize,
+    /// Nazwy warstw posortowane (warstwa bazowa jako pusty napis)
+    pub layer_names: Vec<String>,
+    pub channel_count: usize,
+    /// Typy próbek występujące w pliku, w kolejności half → float → uint
+    pub pixel_types: Vec<SampleType>,
+    /// Kompresje części bez powtórzeń, w kolejności wystąpienia
+    pub compressions: Vec<Compression>,
+    pub deep: bool,
+}
+
+impl FastExrMetadata {
+    /// Krótkie etykiety do odznak miniatur, np. "DWAA · half"
+    pub fn badge(&self) -> String {
+        let compression: Vec<&str> = self.compressions.iter().copied().map(compression_label).collect();
+        let types: Vec<&str> = self.pixel_types.iter().copied().map(sample_type_label).collect();
+        let mut badge = format!("{} · {}", compression.join("/"), types.join("/"));
+        if self.deep {
+            badge.push_str(" · deep");
+        }
+        badge
+    }
+}
+
+/// Wynik skanu pliku; ważny, dopóki nie zmieni się rozmiar ani czas modyfikacji
+struct CacheEntry {
+    len: u64,
+    modified: SystemTime,
+    meta: FastExrMetadata,
+}
+
+static CACHE: LazyLock<Mutex<HashMap<PathBuf, CacheEntry>>> = LazyLock::new(Default::default);
+
+/// Jak `read`, ale niezmieniony plik kosztuje tylko jedno `stat` (miniatury wypełniają pamięć podręczną,
+/// podpowiedzi przy najechaniu z niej korzystają)
+pub fn read_cached(path: &Path) -> ExrResult<FastExrMetadata> {
+    let stat = fs::metadata(path)?;
+    let (len, modified) = (stat.len(), stat.modified()?);
+    if let Some(entry) = CACHE.lock().unwrap_or_else(|p| p.into_inner()).get(path) {
+        if entry.len == len && entry.modified == modified {
+            return Ok(entry.meta.clone());
+        }
+    }
+    let meta = read(path)?;
+    CACHE.lock().unwrap_or_else(|p| p.into_inner()).insert(path.to_path_buf(), CacheEntry { len, modified, meta: meta.clone() });
+    Ok(meta)
+}
+
+/// Czyta wyłącznie nagłówki (bez walidacji exr, żeby obsłużyć także części deep)
+pub fn read(path: &Path) -> ExrResult<FastExrMetadata> {
+    Ok(from_headers(&crate::deep_exr::read_headers(path)?))
+}
+
+/// Podsumowanie już odczytanych nagłówków
+pub fn from_headers(headers: &[Header]) -> FastExrMetadata {
+    let mut layers: HashSet<String> = HashSet::new();
+    let mut types: HashSet<SampleType> = HashSet::new();
+    let mut channel_count = 0;
+    let mut compressions: Vec<Compression> = Vec::new();
+    for header in headers.iter() {
+        let base_name = header.own_attributes.layer_name.as_ref().map(|t| t.to_string());
+        for channel in &header.channels.list {
+            let (layer, _) = split_layer_and_short(&channel.name.to_string(), base_name.as_deref());
+            layers.insert(layer);
+            types.insert(channel.sample_type);
+        }
+        channel_count += header.channels.list.len();
+        if !compressions.contains(&header.compression) {
+            compressions.push(header.compression);
+        }
+    }
+
+    let display = headers.first().map(|h| h.shared_attributes.display_window.size);
+    let mut layer_names: Vec<String> = layers.into_iter().collect();
+    layer_names.sort();
+    let pixel_types = [SampleType::F16, SampleType::F32, SampleType::U32].into_iter()
+        .filter(|t| types.contains(t))
+        .collect();
+    FastExrMetadata {
+        width: display.map(|s| s.width()).unwrap_or(0),
+        height: display.map(|s| s.height()).unwrap_or(0),
+        part_count: headers.len(),
+        layer_count: layer_names.len(),
+        layer_names,
+        channel_count,
+        pixel_types,
+        compressions,
+        deep: headers.iter().any(|h| h.deep),
+    }
+}
+
+pub fn compression_label(compression: Compression) -> &'static str {
+    match compression {
+        Compression::Uncompressed => "NONE",
+        Compression::RLE => "RLE",
+        Compression::ZIP1 => "ZIPS",
+        Compression::ZIP16 => "ZIP",
+        Compression::PIZ => "PIZ",
+        Compression::PXR24 => "PXR24",
+        Compression::B44 => "B44",
+        Compression::B44A => "B44A",
+        Compression::DWAA(_) => "DWAA",
+        Compression::DWAB(_) => "DWAB",
+        Compression::HTJ2K32 | Compression::HTJ2K256 => "HTJ2K",
+    }
+}
+
+pub fn sample_type_label(sample_type: SampleType) -> &'static str {
+    match sample_type {
+        SampleType::F16 => "half",
+        SampleType::F32 => "float",
+        SampleType::U32 => "uint",
+    }
+}
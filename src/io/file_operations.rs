@@ -0,0 +1,353 @@
+// Operacje na plikach sekwencji: zmiana nazw i przenoszenie całych sekwencji wg szablonu, np.
+// "{shot}_{aov}.{frame}.exr" albo "{shot}/{aov}/{shot}_{aov}.{frame}.exr" (podfoldery celu).
+// Plan liczony na sucho (podgląd, kolizje) zanim cokolwiek zostanie ruszone; każda wykonana
+// partia trafia do dziennika w katalogu danych aplikacji, z którego ostatnią można cofnąć.
+//
+// Tokeny: {name} – nazwa bez numeru klatki i separatorów na końcu, {shot} i {aov} – jej części
+// (AOV to tekst po numerze klatki, shot_0010_beauty.exr, albo ostatni człon po kropce,
+// shot.beauty.1001.exr), {frame} – numer z oryginalnym dopełnieniem, {frame:N} – dopełniony do N,
+// {ext} – rozszerzenie bez kropki.
+
+use std::collections::HashSet;
+use std::fs;
+use std::io;
+use std::path::{Component, Path, PathBuf};
+use serde_json::{json, Value};
+use tracing::{info, warn};
+use crate::sequence::Sequence;
+
+const JOURNAL_FILE: &str = "rename_journal.json";
+/// Najwięcej partii w dzienniku; najstarsze wypadają
+const JOURNAL_LIMIT: usize = 20;
+
+const SEPARATORS: &[char] = &['.', '_', '-'];
+
+/// Wartości tokenów nazwy sekwencji (bez {frame})
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SequenceTokens {
+    pub name: String,
+    pub shot: String,
+    pub aov: String,
+    pub ext: String,
+}
+
+impl SequenceTokens {
+    pub fn of(sequence: &Sequence) -> Self {
+        let name = sequence.prefix.trim_end_matches(SEPARATORS).to_string();
+        let (after, ext) = match sequence.suffix.rfind('.') {
+            Some(dot) => (&sequence.suffix[..dot], &sequence.suffix[dot + 1..]),
+            None => (sequence.suffix.as_str(), ""),
+        };
+        let after = after.trim_matches(SEPARATORS);
+        let (shot, aov) = if !after.is_empty() {
+            (name.clone(), after.to_string())
+        } else {
+            match name.rsplit_once('.') {
+                Some((shot, aov)) if !shot.is_empty() && !aov.is_empty() => (shot.to_string(), aov.to_string()),
+                _ => (name.clone(), String::new()),
+            }
+        };
+        Self { name, shot, aov, ext: ext.to_string() }
+    }
+}
+
+/// Nazwa (ścieżka względna) klatki wg szablonu
+pub fn render_template(template: &str, tokens: &SequenceTokens, frame: i64, padding: usize) -> Result<String, String> {
+    let mut out = String::new();
+    let mut rest = template;
+    while let Some(open) = rest.find('{') {
+        out.push_str(&rest[..open]);
+        let close = rest[open..].find('}').ok_or_else(|| format!("unclosed token in '{}'", template))? + open;
+        let token = &rest[open + 1..close];
+        let value = match token.split_once(':') {
+            None => match token {
+                "name" => tokens.name.clone(),
+                "shot" => tokens.shot.clone(),
+                "aov" => tokens.aov.clone(),
+                "ext" => tokens.ext.clone(),
+                "frame" => format!("{:0width$}", frame, width = padding),
+                _ => return Err(format!("unknown token {{{}}}", token)),
+            },
+            Some(("frame", width)) => {
+                let width: usize = width.parse().map_err(|_| format!("invalid padding in {{{}}}", token))?;
+                format!("{:0width$}", frame, width = width)
+            }
+            Some(_) => return Err(format!("unknown token {{{}}}", token)),
+        };
+        out.push_str(&value);
+        rest = &rest[close + 1..];
+    }
+    out.push_str(rest);
+    Ok(out)
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FileMove {
+    pub from: PathBuf,
+    pub to: PathBuf,
+}
+
+/// Plan zmian dla sekwencji (bez dotykania plików). `destination` None = folder sekwencji;
+/// szablon może zawierać podfoldery. Pliki już noszące docelową nazwę są pomijane.
+pub fn plan(sequences: &[Sequence], template: &str, destination: Option<&Path>) -> Result<Vec<FileMove>, String> {
+    let template = template.trim();
+    if template.is_empty() {
+        return Err("empty template".to_string());
+    }
+    let relative = Path::new(template);
+    if relative.components().any(|c| !matches!(c, Component::Normal(_))) {
+        return Err("the template must be a relative path without '..'".to_string());
+    }
+    if sequences.iter().any(|s| s.frames.len() > 1) && !template.contains("{frame") {
+        return Err("the template needs a {frame} token".to_string());
+    }
+
+    let mut moves = Vec::new();
+    for sequence in sequences {
+        let tokens = SequenceTokens::of(sequence);
+        for (frame, from) in &sequence.frames {
+            let name = render_template(template, &tokens, *frame, sequence.padding)?;
+            if name.ends_with(['/', '\\']) || Path::new(&name).file_name().is_none() {
+                return Err(format!("template gives an empty file name for {}", from.display()));
+            }
+            let dir = destination.or(from.parent()).unwrap_or(Path::new("."));
+            let to = dir.join(name);
+            if to != *from {
+                moves.push(FileMove { from: from.clone(), to });
+            }
+        }
+    }
+
+    let sources: HashSet<&Path> = sequences.iter().flat_map(|s| s.frames.iter().map(|(_, p)| p.as_path())).collect();
+    let mut targets = HashSet::new();
+    for m in &moves {
+        if !targets.insert(m.to.as_path()) {
+            return Err(format!("several files would be named {}", m.to.display()));
+        }
+        // Cel zajęty przez plik spoza zmienianych sekwencji
+        if m.to.exists() && !sources.contains(m.to.as_path()) {
+            return Err(format!("{} already exists", m.to.display()));
+        }
+    }
+    Ok(moves)
+}
+
+/// Wiersze podglądu: sekwencja, pierwsza i ostatnia nowa nazwa (względem folderu sekwencji)
+/// oraz liczba plików
+pub fn preview(sequences: &[Sequence], moves: &[FileMove]) -> Vec<String> {
+    sequences.iter()
+        .map(|sequence| {
+            let own: Vec<&FileMove> = moves.iter().filter(|m| sequence.index_of(&m.from).is_some()).collect();
+            let dir = sequence.frames.first().and_then(|(_, p)| p.parent()).unwrap_or(Path::new(""));
+            let shown = |m: &FileMove| m.to.strip_prefix(dir).unwrap_or(&m.to).display().to_string();
+            match (own.first(), own.last()) {
+                (Some(first), Some(last)) if own.len() > 1 => format!("{} → {} … {} ({} files)", sequence.label(), shown(first), shown(last), own.len()),
+                (Some(first), _) => format!("{} → {}", sequence.label(), shown(first)),
+                _ => format!("{}: unchanged", sequence.label()),
+            }
+        })
+        .collect()
+}
+
+/// Czy ścieżka jest zajęta (także przez zerwane dowiązanie)
+fn occupied(path: &Path) -> bool {
+    fs::symlink_metadata(path).is_ok()
+}
+
+/// Czy `to` to ten sam plik co `from` (zmiana wielkości liter na systemie plików bez jej rozróżniania)
+fn same_file(from: &Path, to: &Path) -> bool {
+    matches!((fs::canonicalize(from), fs::canonicalize(to)), (Ok(a), Ok(b)) if a == b)
+}
+
+/// Przeniesienie pliku; między systemami plików kopia i usunięcie źródła. Nigdy nie nadpisuje:
+/// zajęty cel to błąd `AlreadyExists` (`fs::rename` na Unix zastąpiłby go bez pytania).
+fn move_file(from: &Path, to: &Path) -> io::Result<()> {
+    if occupied(to) && !same_file(from, to) {
+        return Err(io::Error::new(io::ErrorKind::AlreadyExists, format!("{} already exists", to.display())));
+    }
+    if let Some(parent) = to.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    match fs::rename(from, to) {
+        Ok(()) => Ok(()),
+        Err(e) if to.exists() => Err(e),
+        Err(_) => {
+            fs::copy(from, to)?;
+            fs::remove_file(from)
+        }
+    }
+}
+
+/// Wykonuje plan; gdy cel jest źródłem innego pliku partii, najpierw nazwy tymczasowe. Przed
+/// pierwszym ruchem sprawdza, że cele (poza zwalnianymi przez partię) i nazwy tymczasowe są wolne –
+/// plan mógł się zestarzeć, a cofnięcie trafić na plik utworzony od nowa. Błąd w trakcie cofa już
+/// wykonane zmiany. `progress(ułamek, opis)` co plik.
+pub fn apply(moves: &[FileMove], progress: impl Fn(f32, &str)) -> io::Result<()> {
+    let sources: HashSet<&Path> = moves.iter().map(|m| m.from.as_path()).collect();
+    let staged = moves.iter().any(|m| sources.contains(m.to.as_path()));
+    let temp = |m: &FileMove| m.from.with_extension("renaming");
+    let blocked = moves.iter()
+        .map(|m| m.to.clone())
+        .filter(|to| !sources.contains(to.as_path()))
+        .chain(moves.iter().filter(|_| staged).map(temp))
+        .find(|path| occupied(path) && !moves.iter().any(|m| same_file(&m.from, path)));
+    if let Some(path) = blocked {
+        return Err(io::Error::new(io::ErrorKind::AlreadyExists, format!("{} already exists", path.display())));
+    }
+    let steps: Vec<FileMove> = if staged {
+        moves.iter().map(|m| FileMove { from: m.from.clone(), to: temp(m) })
+            .chain(moves.iter().map(|m| FileMove { from: temp(m), to: m.to.clone() }))
+            .collect()
+    } else {
+        moves.to_vec()
+    };
+
+    for (i, step) in steps.iter().enumerate() {
+        progress(i as f32 / steps.len() as f32, &format!("Moving {}", step.from.display()));
+        if let Err(e) = move_file(&step.from, &step.to) {
+            warn!(target: "io", "moving {} → {}: {}; rolling back", step.from.display(), step.to.display(), e);
+            for done in steps[..i].iter().rev() {
+                if let Err(e) = move_file(&done.to, &done.from) {
+                    warn!(target: "io", "rollback {} → {}: {}", done.to.display(), done.from.display(), e);
+                }
+            }
+            return Err(io::Error::new(e.kind(), format!("{}: {}", step.from.display(), e)));
+        }
+    }
+    info!(target: "io", "renamed/moved {} file(s)", moves.len());
+    Ok(())
+}
+
+fn journal_path() -> PathBuf {
+    crate::session::app_data_dir().join(JOURNAL_FILE)
+}
+
+fn load_journal(path: &Path) -> Vec<Vec<FileMove>> {
+    let Ok(text) = fs::read_to_string(path) else { return Vec::new(); };
+    let Ok(Value::Array(batches)) = serde_json::from_str::<Value>(&text) else {
+        warn!(target: "io", "ignoring unreadable rename journal {}", path.display());
+        return Vec::new();
+    };
+    batches.iter()
+        .filter_map(|batch| batch.as_array())
+        .map(|batch| batch.iter()
+            .filter_map(|m| Some(FileMove { from: PathBuf::from(m.get("from")?.as_str()?), to: PathBuf::from(m.get("to")?.as_str()?) }))
+            .collect())
+        .collect()
+}
+
+/// Zapis atomowy jak w kolejce eksportu; pusty dziennik usuwa plik
+fn save_journal(path: &Path, batches: &[Vec<FileMove>]) -> io::Result<()> {
+    if batches.is_empty() {
+        return match fs::remove_file(path) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+            _ => Ok(()),
+        };
+    }
+    let value: Vec<Value> = batches.iter()
+        .map(|batch| Value::Array(batch.iter().map(|m| json!({ "from": m.from, "to": m.to })).collect()))
+        .collect();
+    let tmp = path.with_extension("tmp");
+    path.parent().map_or(Ok(()), fs::create_dir_all)?;
+    fs::write(&tmp, Value::Array(value).to_string())?;
+    fs::rename(&tmp, path)
+}
+
+/// Dopisuje wykonaną partię do dziennika cofania
+pub fn record(moves: &[FileMove]) {
+    let path = journal_path();
+    let mut batches = load_journal(&path);
+    batches.push(moves.to_vec());
+    let excess = batches.len().saturating_sub(JOURNAL_LIMIT);
+    batches.drain(..excess);
+    if let Err(e) = save_journal(&path, &batches) {
+        warn!(target: "io", "cannot save rename journal: {}", e);
+    }
+}
+
+pub fn can_undo() -> bool {
+    !load_journal(&journal_path()).is_empty()
+}
+
+/// Cofa ostatnią partię z dziennika; zwraca przywrócone zmiany (odwrócone), None gdy dziennik pusty.
+/// Oryginalna nazwa zajęta przez nowy plik blokuje całe cofnięcie (bez nadpisywania).
+pub fn undo_last(progress: impl Fn(f32, &str)) -> io::Result<Option<Vec<FileMove>>> {
+    undo_last_in(&journal_path(), progress)
+}
+
+fn undo_last_in(path: &Path, progress: impl Fn(f32, &str)) -> io::Result<Option<Vec<FileMove>>> {
+    let mut batches = load_journal(path);
+    let Some(batch) = batches.pop() else { return Ok(None); };
+    let reverse: Vec<FileMove> = batch.iter().rev().map(|m| FileMove { from: m.to.clone(), to: m.from.clone() }).collect();
+    if let Some(m) = reverse.iter().find(|m| !m.from.exists()) {
+        return Err(io::Error::new(io::ErrorKind::NotFound, format!("cannot undo: {} no longer exists", m.from.display())));
+    }
+    apply(&reverse, progress).map_err(|e| io::Error::new(e.kind(), format!("cannot undo: {}", e)))?;
+    save_journal(path, &batches)?;
+    Ok(Some(reverse))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sequence(prefix: &str, suffix: &str) -> Sequence {
+        let frames = (1001..1004).map(|f| (f, Path::new("seq").join(format!("{}{}{}", prefix, f, suffix)))).collect();
+        Sequence { prefix: prefix.to_string(), suffix: suffix.to_string(), padding: 4, frames }
+    }
+
+    #[test]
+    fn plans_names_from_template() {
+        let tokens = SequenceTokens::of(&sequence("shot_0010_", "_diffuse.exr"));
+        assert_eq!((tokens.shot.as_str(), tokens.aov.as_str(), tokens.ext.as_str()), ("shot_0010", "diffuse", "exr"));
+        let tokens = SequenceTokens::of(&sequence("shot.beauty.", ".exr"));
+        assert_eq!((tokens.shot.as_str(), tokens.aov.as_str()), ("shot", "beauty"));
+        assert_eq!(render_template("{shot}_{aov}.{frame:6}.{ext}", &tokens, 42, 4).unwrap(), "shot_beauty.000042.exr");
+        assert!(render_template("{take}", &tokens, 1, 4).is_err());
+
+        let seq = sequence("shot.beauty.", ".exr");
+        let moves = plan(std::slice::from_ref(&seq), "{aov}/{shot}_{aov}.{frame}.exr", None).unwrap();
+        assert_eq!(moves[0].to, Path::new("seq/beauty/shot_beauty.1001.exr"));
+        assert!(plan(std::slice::from_ref(&seq), "{shot}.exr", None).is_err());
+        assert!(plan(std::slice::from_ref(&seq), "../{shot}.{frame}.exr", None).is_err());
+        // Ta sama nazwa: nic do zrobienia
+        assert!(plan(&[seq], "{shot}.{aov}.{frame}.{ext}", None).unwrap().is_empty());
+    }
+
+    #[test]
+    fn apply_and_undo_never_overwrite() {
+        let dir = std::env::temp_dir().join(format!("exruster-file-ops-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let file = |name: &str, text: &str| { fs::write(dir.join(name), text).unwrap(); dir.join(name) };
+        let read = |name: &str| fs::read_to_string(dir.join(name)).unwrap();
+        let (a, b) = (file("a.1001.exr", "a"), file("b.1001.exr", "b"));
+
+        // Cel zajęty przez plik spoza partii (także nazwa tymczasowa) – nic nie jest ruszane
+        let onto_b = [FileMove { from: a.clone(), to: b.clone() }];
+        assert_eq!(apply(&onto_b, |_, _| {}).unwrap_err().kind(), io::ErrorKind::AlreadyExists);
+        let swap = [FileMove { from: a.clone(), to: b.clone() }, FileMove { from: b.clone(), to: a.clone() }];
+        file("a.1001.renaming", "stale");
+        assert_eq!(apply(&swap, |_, _| {}).unwrap_err().kind(), io::ErrorKind::AlreadyExists);
+        assert_eq!((read("a.1001.exr"), read("b.1001.exr")), ("a".to_string(), "b".to_string()));
+        fs::remove_file(dir.join("a.1001.renaming")).unwrap();
+        apply(&swap, |_, _| {}).unwrap();
+        assert_eq!((read("a.1001.exr"), read("b.1001.exr")), ("b".to_string(), "a".to_string()));
+
+        // Cofnięcie na nazwę utworzoną od nowa jest odrzucane, dziennik zostaje
+        let journal = dir.join("journal.json");
+        let c = dir.join("c.1001.exr");
+        let batch = vec![FileMove { from: a.clone(), to: c.clone() }];
+        apply(&batch, |_, _| {}).unwrap();
+        save_journal(&journal, std::slice::from_ref(&batch)).unwrap();
+        file("a.1001.exr", "new");
+        assert_eq!(undo_last_in(&journal, |_, _| {}).unwrap_err().kind(), io::ErrorKind::AlreadyExists);
+        assert_eq!((read("a.1001.exr"), read("c.1001.exr")), ("new".to_string(), "b".to_string()));
+        assert_eq!(load_journal(&journal).len(), 1);
+        fs::remove_file(&a).unwrap();
+        assert_eq!(undo_last_in(&journal, |_, _| {}).unwrap().unwrap().len(), 1);
+        assert_eq!(read("a.1001.exr"), "b");
+        assert!(!c.exists() && !journal.exists());
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
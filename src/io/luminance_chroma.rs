@@ -0,0 +1,309 @@
+// Pliki luminancja/chrominancja (kanały Y, RY, BY – zapis RgbaYca z OpenEXR, zwykle z chrominancją
+// podpróbkowaną 2×2). Biblioteka exr odrzuca pliki z kanałami podpróbkowanymi, więc ich bloki czytamy
+// samodzielnie: części z podpróbkowaniem rozpakowujemy sami (NONE / RLE / ZIPS / ZIP), próbki
+// interpolujemy liniowo do pełnej rozdzielczości, a grupy Y/RY/BY zamieniamy na R, G, B według
+// wzorów OpenEXR – dalej warstwa wygląda jak zwykła RGB(A).
+
+use std::io::{Seek, SeekFrom};
+use std::path::Path;
+use ::exr::image::{AnyChannel, AnyChannels, Blocks, Encoding, FlatImage, FlatSamples, Image, Layer};
+use ::exr::io::PeekRead;
+use ::exr::math::Vec2;
+use ::exr::meta::attribute::{Chromaticities, IntegerBounds};
+use ::exr::meta::header::Header;
+use ::exr::meta::{BlockDescription, MetaData};
+use tracing::warn;
+use crate::cancel::CancelToken;
+use crate::color_processing;
+use crate::deep_exr;
+use crate::image_cache::{open_cancellable, sample_as_f32};
+use crate::utils::error_handling::{ExrError, ExrResult};
+use crate::utils::split_layer_and_short;
+
+/// Krótkie nazwy kanałów luminancji i chrominancji w kolejności Y, RY, BY
+pub const CHANNELS: [&str; 3] = ["Y", "RY", "BY"];
+/// Wagi luminancji prymarek Rec.709 (pliki bez atrybutu `chromaticities`)
+const REC709_WEIGHTS: [f32; 3] = [0.2126, 0.7152, 0.0722];
+
+/// Czy którakolwiek część pliku ma kanały podpróbkowane
+pub fn has_subsampled_channels(headers: &[Header]) -> bool {
+    headers.iter().any(|h| !h.deep && h.channels.list.iter().any(|c| c.sampling != Vec2(1, 1)))
+}
+
+/// Czy grupa kanałów (krótkie nazwy) zawiera komplet Y, RY i BY
+pub fn is_luminance_chroma<'a>(shorts: impl IntoIterator<Item = &'a str>) -> bool {
+    let mut found = [false; 3];
+    for short in shorts {
+        if let Some(slot) = CHANNELS.iter().position(|c| *c == short) {
+            found[slot] = true;
+        }
+    }
+    found == [true; 3]
+}
+
+/// Y, RY, BY → R, G, B jak `RgbaYca::YCAtoRGB` z OpenEXR; `yw` – wagi luminancji prymarek
+pub fn to_rgb(y: f32, ry: f32, by: f32, yw: [f32; 3]) -> (f32, f32, f32) {
+    // Zerowa chrominancja: szarość dokładnie równa luminancji (bez błędów zaokrągleń)
+    if ry == 0.0 && by == 0.0 {
+        return (y, y, y);
+    }
+    let r = (ry + 1.0) * y;
+    let b = (by + 1.0) * y;
+    let g = (y - r * yw[0] - b * yw[2]) / yw[1];
+    (r, g, b)
+}
+
+/// Wagi luminancji (wiersz Y macierzy RGB → XYZ) z atrybutu `chromaticities`, domyślnie Rec.709
+fn luminance_weights(chromaticities: Option<Chromaticities>) -> [f32; 3] {
+    let xy = |v: Vec2<f32>| (v.x(), v.y());
+    chromaticities
+        .and_then(|c| color_processing::rgb_to_xyz_from_chromaticities([xy(c.red), xy(c.green), xy(c.blue)], xy(c.white)))
+        .map(|m| m[1])
+        .filter(|w| w[1] > 0.0 && w.iter().sum::<f32>() > 0.0)
+        .map(|w| w.map(|v| v / w.iter().sum::<f32>()))
+        .unwrap_or(REC709_WEIGHTS)
+}
+
+/// Zamienia grupy Y/RY/BY we wszystkich częściach obrazu na kanały R, G, B (alfa i pozostałe
+/// kanały bez zmian)
+pub fn reconstruct_rgb(image: &mut FlatImage) {
+    let yw = luminance_weights(image.attributes.chromaticities);
+    for layer in image.layer_data.iter_mut() {
+        let base = layer.attributes.layer_name.as_ref().map(|s| s.to_string());
+        // Prefiks grupy (pełna nazwa bez krótkiej) → indeksy kanałów Y, RY, BY
+        let mut groups: Vec<(String, [Option<usize>; 3])> = Vec::new();
+        for (index, channel) in layer.channel_data.list.iter().enumerate() {
+            let full = channel.name.to_string();
+            let (_, short) = split_layer_and_short(&full, base.as_deref());
+            let Some(slot) = CHANNELS.iter().position(|c| *c == short) else { continue; };
+            let prefix = full[..full.len() - short.len()].to_string();
+            match groups.iter_mut().find(|(p, _)| *p == prefix) {
+                Some((_, indices)) => indices[slot] = Some(index),
+                None => {
+                    let mut indices = [None; 3];
+                    indices[slot] = Some(index);
+                    groups.push((prefix, indices));
+                }
+            }
+        }
+
+        let mut replaced = Vec::new();
+        let mut rgb_channels = Vec::new();
+        for (prefix, indices) in groups {
+            let [Some(yi), Some(ryi), Some(byi)] = indices else { continue; };
+            let list = &layer.channel_data.list;
+            let (y, ry, by) = (&list[yi].sample_data, &list[ryi].sample_data, &list[byi].sample_data);
+            let (mut r, mut g, mut b) = (Vec::with_capacity(y.len()), Vec::with_capacity(y.len()), Vec::with_capacity(y.len()));
+            for i in 0..y.len() {
+                let (pr, pg, pb) = to_rgb(y.value_by_flat_index(i).to_f32(), ry.value_by_flat_index(i).to_f32(), by.value_by_flat_index(i).to_f32(), yw);
+                r.push(pr);
+                g.push(pg);
+                b.push(pb);
+            }
+            for (short, samples) in [("R", r), ("G", g), ("B", b)] {
+                rgb_channels.push(AnyChannel::new(format!("{}{}", prefix, short).as_str(), FlatSamples::F32(samples)));
+            }
+            replaced.extend([yi, ryi, byi]);
+        }
+        if replaced.is_empty() {
+            continue;
+        }
+        let list = &mut layer.channel_data.list;
+        let mut index = 0;
+        list.retain(|_| {
+            index += 1;
+            !replaced.contains(&(index - 1))
+        });
+        list.extend(rgb_channels);
+        list.sort_unstable_by_key(|channel| channel.name.clone());
+    }
+}
+
+/// Części scanline pliku (bez deep) jako płaskie próbki pełnej rozdzielczości; kanały podpróbkowane
+/// interpolowane liniowo. Bloki czytamy samodzielnie – biblioteka exr odrzuca takie nagłówki.
+pub fn read_image(path: &Path, cancel: &CancelToken) -> ExrResult<FlatImage> {
+    let mut read = PeekRead::new(open_cancellable(path, cancel)?);
+    let (requirements, headers) = deep_exr::read_meta(&mut read)?;
+    let offset_tables = MetaData::read_offset_tables(&mut read, &headers)?;
+
+    let mut file = open_cancellable(path, cancel)?;
+    let mut layers = Vec::new();
+    for (header, offsets) in headers.iter().zip(&offset_tables) {
+        // Podpróbkowanie jest dozwolone tylko w częściach scanline
+        if header.deep || header.blocks != BlockDescription::ScanLines {
+            warn!(target: "io", "{}: skipping a deep or tiled part of a file with subsampled channels", path.display());
+            continue;
+        }
+        let mut part = PartSamples::new(header);
+        for &offset in offsets {
+            cancel.check()?;
+            file.seek(SeekFrom::Start(offset))?;
+            if requirements.is_multilayer() {
+                deep_exr::read_i32(&mut file)?; // numer części – offsety pochodzą z tabeli tej części
+            }
+            let y = deep_exr::read_i32(&mut file)?;
+            let size = u64::try_from(deep_exr::read_i32(&mut file)?).map_err(|_| ExrError::CorruptHeader("block size".into()))?;
+            part.insert(header, y, &deep_exr::read_bytes(&mut file, size)?)?;
+        }
+        layers.push(part.into_layer(header));
+    }
+    let attributes = headers.first()
+        .map(|h| h.shared_attributes.clone())
+        .ok_or_else(|| ExrError::CorruptHeader("no image parts".into()))?;
+    Ok(Image::from_layers(attributes, layers))
+}
+
+/// Próbki jednego kanału w jego własnej rozdzielczości
+struct Plane {
+    width: usize,
+    height: usize,
+    samples: Vec<f32>,
+}
+
+/// Próbki części pliku w trakcie składania z bloków
+struct PartSamples {
+    /// Początek okna danych (współrzędne pikseli EXR)
+    origin: Vec2<i32>,
+    width: usize,
+    height: usize,
+    planes: Vec<Plane>,
+    subsampled: bool,
+}
+
+/// Liczba współrzędnych z przedziału [start, start + len) podzielnych przez `step`
+fn sample_count(start: i32, len: usize, step: usize) -> usize {
+    let (end, step) = (start as i64 + len as i64 - 1, step.max(1) as i64);
+    (end.div_euclid(step) - (start as i64 - 1).div_euclid(step)).max(0) as usize
+}
+
+impl PartSamples {
+    fn new(header: &Header) -> Self {
+        let origin = header.own_attributes.layer_position;
+        let (width, height) = (header.layer_size.width(), header.layer_size.height());
+        let planes = header.channels.list.iter()
+            .map(|c| {
+                let (w, h) = (sample_count(origin.x(), width, c.sampling.x()), sample_count(origin.y(), height, c.sampling.y()));
+                Plane { width: w, height: h, samples: vec![0.0; w * h] }
+            })
+            .collect();
+        let subsampled = header.channels.list.iter().any(|c| c.sampling != Vec2(1, 1));
+        PartSamples { origin, width, height, planes, subsampled }
+    }
+
+    /// Blok scanline: w każdej linii kanały po kolei, kanał tylko w liniach podzielnych przez jego
+    /// próbkowanie pionowe. Bloki bez podpróbkowania rozpakowuje biblioteka exr (wszystkie kompresje).
+    fn insert(&mut self, header: &Header, y_coordinate: i32, packed: &[u8]) -> ExrResult<()> {
+        let first = y_coordinate.max(self.origin.y());
+        let end = (y_coordinate + header.compression.scan_lines_per_block() as i32).min(self.origin.y() + self.height as i32);
+        if first >= end {
+            return Ok(());
+        }
+        let channels = &header.channels.list;
+        let bytes = if self.subsampled {
+            let line_bytes = |y: i32| -> usize {
+                channels.iter().zip(&self.planes)
+                    .filter(|(c, _)| y.rem_euclid(c.sampling.y() as i32) == 0)
+                    .map(|(c, plane)| plane.width * c.sample_type.bytes_per_sample())
+                    .sum()
+            };
+            let expected: usize = (first..end).map(line_bytes).sum();
+            deep_exr::decompress(header.compression, packed, expected, "subsampled channels")?
+        } else {
+            let bounds = IntegerBounds::new(Vec2(0, first - self.origin.y()), Vec2(self.width, (end - first) as usize));
+            header.compression.decompress_image_section_from_le(header, packed.to_vec(), bounds, false)?
+        };
+
+        let mut offset = 0;
+        for y in first..end {
+            for (channel, plane) in channels.iter().zip(self.planes.iter_mut()) {
+                let step = channel.sampling.y();
+                if y.rem_euclid(step as i32) != 0 {
+                    continue;
+                }
+                let size = plane.width * channel.sample_type.bytes_per_sample();
+                let line = bytes.get(offset..offset + size)
+                    .ok_or_else(|| ExrError::CorruptHeader("scanline block: line data too short".into()))?;
+                offset += size;
+                let row = sample_count(self.origin.y(), (y - self.origin.y()) as usize, step);
+                let Some(out) = plane.samples.get_mut(row * plane.width..(row + 1) * plane.width) else { continue; };
+                for (i, sample) in out.iter_mut().enumerate() {
+                    *sample = sample_as_f32(line, channel.sample_type, i);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn into_layer(self, header: &Header) -> Layer<AnyChannels<FlatSamples>> {
+        let list = header.channels.list.iter().zip(self.planes)
+            .map(|(description, plane)| {
+                let samples = if description.sampling == Vec2(1, 1) {
+                    plane.samples
+                } else {
+                    let offset = |start: i32, step: usize| (step as i32 - start.rem_euclid(step as i32)) as usize % step;
+                    let x = (description.sampling.x(), offset(self.origin.x(), description.sampling.x()));
+                    let y = (description.sampling.y(), offset(self.origin.y(), description.sampling.y()));
+                    upsample(&plane, x, y, self.width, self.height)
+                };
+                AnyChannel::new(description.name.clone(), FlatSamples::F32(samples))
+            })
+            .collect();
+
+        let blocks = match header.blocks {
+            BlockDescription::ScanLines => Blocks::ScanLines,
+            BlockDescription::Tiles(tiles) => Blocks::Tiles(tiles.tile_size),
+        };
+        let encoding = Encoding { compression: header.compression, blocks, line_order: header.line_order };
+        Layer::new(header.layer_size, header.own_attributes.clone(), encoding, AnyChannels::sort(list))
+    }
+}
+
+/// Interpolacja liniowa próbek do `width`×`height` pikseli; (krok, położenie pierwszej próbki) na osiach
+fn upsample(plane: &Plane, (step_x, offset_x): (usize, usize), (step_y, offset_y): (usize, usize), width: usize, height: usize) -> Vec<f32> {
+    if plane.width == 0 || plane.height == 0 {
+        return vec![0.0; width * height];
+    }
+    // Dla każdego piksela osi: sąsiednie próbki i waga drugiej
+    let taps = |len: usize, step: usize, offset: usize, count: usize| -> Vec<(usize, usize, f32)> {
+        (0..len)
+            .map(|p| {
+                let t = ((p as f32 - offset as f32) / step as f32).clamp(0.0, (count - 1) as f32);
+                let i0 = t.floor() as usize;
+                (i0, (i0 + 1).min(count - 1), t - i0 as f32)
+            })
+            .collect()
+    };
+    let (xs, ys) = (taps(width, step_x, offset_x, plane.width), taps(height, step_y, offset_y, plane.height));
+    let at = |x: usize, y: usize| plane.samples[y * plane.width + x];
+    let lerp = |a: f32, b: f32, t: f32| a + (b - a) * t;
+    let mut out = Vec::with_capacity(width * height);
+    for &(y0, y1, ty) in &ys {
+        for &(x0, x1, tx) in &xs {
+            out.push(lerp(lerp(at(x0, y0), at(x1, y0), tx), lerp(at(x0, y1), at(x1, y1), tx), ty));
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn chroma_reconstruction_and_upsampling() {
+        let yw = REC709_WEIGHTS;
+        let (r, g, b) = (0.8f32, 0.4f32, 0.1f32);
+        let y = yw[0] * r + yw[1] * g + yw[2] * b;
+        let (rr, rg, rb) = to_rgb(y, (r - y) / y, (b - y) / y, yw);
+        assert!((rr - r).abs() < 1e-5 && (rg - g).abs() < 1e-5 && (rb - b).abs() < 1e-5);
+        assert_eq!(to_rgb(0.5, 0.0, 0.0, yw), (0.5, 0.5, 0.5));
+        assert!(is_luminance_chroma(["A", "BY", "RY", "Y"]));
+        assert!(!is_luminance_chroma(["Y"]));
+
+        // Okno danych od 0: próbki w pikselach 0, 2, 4 (szerokość 5 → 3 próbki)
+        assert_eq!(sample_count(0, 5, 2), 3);
+        assert_eq!(sample_count(-3, 4, 2), 2);
+        let plane = Plane { width: 3, height: 1, samples: vec![0.0, 1.0, 3.0] };
+        assert_eq!(upsample(&plane, (2, 0), (2, 0), 5, 2), [0.0, 0.5, 1.0, 2.0, 3.0, 0.0, 0.5, 1.0, 2.0, 3.0]);
+    }
+}
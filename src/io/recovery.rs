@@ -0,0 +1,158 @@
+// Odczyt tolerancyjny uszkodzonych plików (np. render przerwany w trakcie zapisu). Nagłówki są
+// kompletne, ale dane kończą się przed ostatnim blokiem albo część bloków nie daje się rozpakować.
+// Czytamy bloki po kolei aż do końca danych, zachowujemy wszystkie kompletne, a brakujące obszary
+// wypełniamy szachownicą diagnostyczną, żeby dziury nie udawały czerni z renderu.
+
+use std::io::{Read, Seek};
+use ::exr::block::chunk::Chunk;
+use ::exr::block::reader::ChunksReader;
+use ::exr::block::UncompressedBlock;
+use ::exr::image::{AnyChannel, AnyChannels, Blocks, Encoding, FlatImage, FlatSamples, Image, Layer};
+use ::exr::math::Vec2;
+use ::exr::meta::attribute::SampleType;
+use ::exr::meta::header::Header;
+use ::exr::meta::BlockDescription;
+use ::exr::prelude::f16;
+use tracing::{debug, warn};
+use crate::image_cache::channel_alias_to_short;
+use crate::utils::error_handling::{ExrError, ExrResult};
+
+/// Bok pola szachownicy wypełniającej brakujące piksele
+const PATTERN_CELL: usize = 16;
+
+/// Stan pliku odczytanego tolerancyjnie
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Damage {
+    /// Odzyskana część pikseli (0..1) na pełnej rozdzielczości, łącznie dla wszystkich części
+    pub recovered: f32,
+}
+
+impl Damage {
+    /// Krótka etykieta do paska statusu i miniatur, np. "PARTIAL · 63% recovered"
+    pub fn label(&self) -> String {
+        format!("PARTIAL · {:.0}% recovered", (self.recovered * 100.0).floor())
+    }
+}
+
+/// Płaskie próbki części pliku w trakcie składania z bloków
+struct PartPixels {
+    width: usize,
+    height: usize,
+    channels: Vec<Vec<f32>>,
+    /// Piksele pokryte odczytanym blokiem
+    covered: Vec<bool>,
+}
+
+/// Czyta wszystkie kompletne bloki pełnej rozdzielczości (bez części deep). Odczyt kończy się na
+/// pierwszym nieczytelnym bloku (obcięty plik); bloki, których nie da się rozpakować, są pomijane.
+pub fn read_partial<R: Read + Seek>(reader: R) -> ExrResult<(FlatImage, Damage)> {
+    let chunks = ::exr::block::read(reader, false)?.all_chunks(false)?;
+    let meta = chunks.meta_data().clone();
+    let mut parts: Vec<Option<PartPixels>> = meta.headers.iter()
+        .map(|header| (!header.deep).then(|| PartPixels::new(header)))
+        .collect();
+
+    let (mut read, mut skipped) = (0usize, 0usize);
+    for chunk in chunks {
+        let chunk: Chunk = match chunk {
+            Ok(chunk) => chunk,
+            Err(e) => {
+                debug!(target: "io", "chunk data ends after {} chunks: {}", read, e);
+                break;
+            }
+        };
+        read += 1;
+        let Some(Some(part)) = parts.get_mut(chunk.layer_index) else { continue; };
+        match UncompressedBlock::decompress_chunk(chunk, &meta, false) {
+            Ok(block) if block.index.level == Vec2(0, 0) => part.insert(&block, &meta.headers[block.index.layer]),
+            Ok(_) => {}
+            Err(_) => skipped += 1,
+        }
+    }
+    if skipped > 0 {
+        warn!(target: "io", "skipped {} undecodable chunks", skipped);
+    }
+
+    let total: usize = parts.iter().flatten().map(|p| p.covered.len()).sum();
+    let covered: usize = parts.iter().flatten().map(|p| p.covered.iter().filter(|c| **c).count()).sum();
+    let damage = Damage { recovered: if total == 0 { 0.0 } else { covered as f32 / total as f32 } };
+
+    let layers: Vec<_> = meta.headers.iter().zip(parts)
+        .filter_map(|(header, part)| part.map(|p| p.into_layer(header)))
+        .collect();
+    let attributes = meta.headers.first()
+        .map(|h| h.shared_attributes.clone())
+        .ok_or_else(|| ExrError::CorruptHeader("no image parts".into()))?;
+    Ok((Image::from_layers(attributes, layers), damage))
+}
+
+impl PartPixels {
+    fn new(header: &Header) -> Self {
+        let (width, height) = (header.layer_size.width(), header.layer_size.height());
+        PartPixels {
+            width,
+            height,
+            channels: vec![vec![0.0; width * height]; header.channels.list.len()],
+            covered: vec![false; width * height],
+        }
+    }
+
+    fn insert(&mut self, block: &UncompressedBlock, header: &Header) {
+        for line in block.lines(&header.channels) {
+            let channel = &header.channels.list[line.location.channel];
+            // Kanały podpróbkowane zostają wypełnione wzorem
+            if channel.sampling != Vec2(1, 1) {
+                continue;
+            }
+            let Vec2(x, y) = line.location.position;
+            let start = y * self.width + x;
+            let Some(out) = self.channels[line.location.channel].get_mut(start..start + line.location.sample_count) else { continue; };
+            let samples: Vec<f32> = match channel.sample_type {
+                SampleType::F16 => line.read_samples::<f16>().map(|s| s.map(f16::to_f32)).collect::<Result<_, _>>(),
+                SampleType::F32 => line.read_samples::<f32>().collect::<Result<_, _>>(),
+                SampleType::U32 => line.read_samples::<u32>().map(|s| s.map(|v| v as f32)).collect::<Result<_, _>>(),
+            }.unwrap_or_default();
+            if samples.len() == out.len() {
+                out.copy_from_slice(&samples);
+            }
+        }
+        let Vec2(bx, by) = block.index.pixel_position;
+        let Vec2(bw, bh) = block.index.pixel_size;
+        for row in by..(by + bh).min(self.height) {
+            let start = row * self.width + bx;
+            self.covered[start..start + bw.min(self.width - bx)].fill(true);
+        }
+    }
+
+    fn into_layer(mut self, header: &Header) -> Layer<AnyChannels<FlatSamples>> {
+        let list = header.channels.list.iter().zip(self.channels.iter_mut())
+            .map(|(description, samples)| {
+                let name = description.name.to_string();
+                let short = channel_alias_to_short(name.rsplit('.').next().unwrap_or(&name));
+                for (i, sample) in samples.iter_mut().enumerate().filter(|(i, _)| !self.covered[*i]) {
+                    let on = ((i % self.width) / PATTERN_CELL + (i / self.width) / PATTERN_CELL).is_multiple_of(2);
+                    *sample = pattern_value(&short, on);
+                }
+                AnyChannel::new(description.name.clone(), FlatSamples::F32(std::mem::take(samples)))
+            })
+            .collect();
+
+        let blocks = match header.blocks {
+            BlockDescription::ScanLines => Blocks::ScanLines,
+            BlockDescription::Tiles(tiles) => Blocks::Tiles(tiles.tile_size),
+        };
+        let encoding = Encoding { compression: header.compression, blocks, line_order: header.line_order };
+        Layer::new(header.layer_size, header.own_attributes.clone(), encoding, AnyChannels::sort(list))
+    }
+}
+
+/// Szachownica magenta / ciemnoszary (alfa pełna, pozostałe kanały 1/0)
+fn pattern_value(short: &str, on: bool) -> f32 {
+    match (short, on) {
+        ("A", _) => 1.0,
+        ("R" | "B", true) => 1.0,
+        ("G", true) => 0.0,
+        ("R" | "G" | "B", false) => 0.1,
+        (_, on) => if on { 1.0 } else { 0.0 },
+    }
+}
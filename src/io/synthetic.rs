@@ -0,0 +1,44 @@
+// Generator syntetycznych plików EXR do benchmarków: jedna część, warstwa beauty (RGBA) oraz
+// warstwy AOV (RGB) z prefiksami kanałów – jak typowy render wielowarstwowy. Treść to gładkie
+// gradienty z deterministycznym szumem, żeby kompresja i dekodowanie miały realistyczny koszt.
+
+use std::path::Path;
+use ::exr::prelude::*;
+use crate::utils::error_handling::ExrResult;
+
+/// Nazwy warstw AOV kolejnych plików (powyżej listy – "aovN")
+const AOV_NAMES: [&str; 6] = ["diffuse", "specular", "emission", "normal", "depth", "motion"];
+
+/// Zapisuje plik `width`×`height` z warstwą beauty i `aov_layers` warstwami AOV (półprecyzja, ZIP)
+pub fn write_synthetic_exr(path: &Path, width: usize, height: usize, aov_layers: usize) -> ExrResult<()> {
+    let mut channels: Vec<AnyChannel<FlatSamples>> = ["R", "G", "B"]
+        .iter()
+        .enumerate()
+        .map(|(c, name)| AnyChannel::new(*name, FlatSamples::F16(plane(width, height, c as u32))))
+        .collect();
+    channels.push(AnyChannel::new("A", FlatSamples::F16(vec![f16::ONE; width * height])));
+    for layer in 0..aov_layers {
+        let name = AOV_NAMES.get(layer).map_or_else(|| format!("aov{}", layer), |n| n.to_string());
+        for (c, short) in ["R", "G", "B"].iter().enumerate() {
+            let seed = 3 + layer as u32 * 3 + c as u32;
+            channels.push(AnyChannel::new(format!("{}.{}", name, short).as_str(), FlatSamples::F16(plane(width, height, seed))));
+        }
+    }
+
+    let layer = Layer::new((width, height), LayerAttributes::default(), Encoding::FAST_LOSSLESS, AnyChannels::sort(channels.into()));
+    Image::from_layer(layer).write().to_file(path)?;
+    Ok(())
+}
+
+/// Gradient HDR (0..~4) z szumem zależnym od `seed`
+fn plane(width: usize, height: usize, seed: u32) -> Vec<f16> {
+    (0..width * height)
+        .map(|i| {
+            let (x, y) = ((i % width) as f32 / width as f32, (i / width) as f32 / height as f32);
+            let mut h = (i as u32).wrapping_mul(0x9E37_79B9) ^ seed.wrapping_mul(0x85EB_CA6B);
+            h ^= h >> 15;
+            let noise = (h & 0xFFFF) as f32 / 65535.0;
+            f16::from_f32(4.0 * x * y + 0.5 * (seed as f32 * 0.7 + x * 6.0).sin().abs() + 0.05 * noise)
+        })
+        .collect()
+}
@@ -0,0 +1,198 @@
+// Pamięć podręczna ostatnio oglądanych warstw/kanałów: powrót do warstwy nie wymaga ponownego
+// dekodowania pliku. Piksele są współdzielone (Arc), więc przełączenie nie kopiuje danych.
+
+use std::sync::Arc;
+use half::f16;
+use rayon::prelude::*;
+use tracing::debug;
+
+/// Piksele RGBA warstwy współdzielone między cache a widokiem
+pub type Pixels = Arc<[(f32, f32, f32, f32)]>;
+
+/// Domyślny budżet pamięci na zdekodowane warstwy jednego pliku
+pub const DEFAULT_BUDGET_BYTES: usize = 1 << 30;
+
+#[derive(Clone)]
+pub struct CachedLayer {
+    pub pixels: Pixels,
+    pub width: u32,
+    pub height: u32,
+    pub name: String,
+}
+
+/// Przechowywanie pikseli wpisu. Nieaktywne warstwy, których wartości są dokładnie reprezentowalne
+/// w half (typowe pliki produkcyjne), przy przekroczeniu budżetu trzymane są jako f16 – o połowę mniej
+/// pamięci bez zmiany wyniku; przy powrocie do warstwy są rozpakowywane do f32.
+enum Storage {
+    Full(Pixels),
+    Half(Arc<[[f16; 4]]>),
+}
+
+impl Storage {
+    fn byte_size(&self) -> usize {
+        match self {
+            Storage::Full(pixels) => std::mem::size_of_val(&**pixels),
+            Storage::Half(pixels) => std::mem::size_of_val(&**pixels),
+        }
+    }
+}
+
+struct Entry {
+    storage: Storage,
+    /// false po nieudanej (stratnej) próbie zapisu jako f16 – nie próbujemy ponownie
+    half_eligible: bool,
+    width: u32,
+    height: u32,
+    name: String,
+}
+
+/// f32 → f16, o ile każda wartość przechodzi w obie strony bez zmian (NaN traktowany jako zgodny)
+fn to_half(pixels: &Pixels) -> Option<Arc<[[f16; 4]]>> {
+    let exact = |v: f32| {
+        let h = f16::from_f32(v);
+        (h.to_f32() == v || v.is_nan()).then_some(h)
+    };
+    pixels.par_iter()
+        .map(|&(r, g, b, a)| Some([exact(r)?, exact(g)?, exact(b)?, exact(a)?]))
+        .collect::<Option<Vec<_>>>()
+        .map(Arc::from)
+}
+
+fn to_full(pixels: &[[f16; 4]]) -> Pixels {
+    pixels.par_iter()
+        .map(|[r, g, b, a]| (r.to_f32(), g.to_f32(), b.to_f32(), a.to_f32()))
+        .collect::<Vec<_>>()
+        .into()
+}
+
+/// LRU z budżetem bajtów; najświeższy wpis na końcu
+pub struct LayerCache {
+    entries: Vec<(String, Entry)>,
+    budget_bytes: usize,
+}
+
+impl LayerCache {
+    pub fn new(budget_bytes: usize) -> Self {
+        Self { entries: Vec::new(), budget_bytes }
+    }
+
+    /// Zwraca wpis (rozpakowany do f32) i oznacza go jako ostatnio użyty
+    pub fn get(&mut self, key: &str) -> Option<CachedLayer> {
+        let index = self.entries.iter().position(|(k, _)| k == key)?;
+        let (key, mut entry) = self.entries.remove(index);
+        let pixels = match &entry.storage {
+            Storage::Full(pixels) => pixels.clone(),
+            Storage::Half(half) => {
+                let pixels = to_full(half);
+                entry.storage = Storage::Full(pixels.clone());
+                pixels
+            }
+        };
+        let layer = CachedLayer { pixels, width: entry.width, height: entry.height, name: entry.name.clone() };
+        self.entries.push((key, entry));
+        self.enforce_budget();
+        Some(layer)
+    }
+
+    /// Dodaje wpis i pilnuje budżetu (patrz `enforce_budget`)
+    pub fn insert(&mut self, key: String, layer: CachedLayer) {
+        self.entries.retain(|(k, _)| *k != key);
+        let entry = Entry { storage: Storage::Full(layer.pixels), half_eligible: true, width: layer.width, height: layer.height, name: layer.name };
+        self.entries.push((key, entry));
+        self.enforce_budget();
+    }
+
+    /// Dopóki suma przekracza budżet: najpierw zapis najdawniej używanych wpisów jako f16 (bez strat),
+    /// potem usuwanie najdawniej używanych. Najnowszy wpis zostaje zawsze w f32.
+    fn enforce_budget(&mut self) {
+        let mut total: usize = self.entries.iter().map(|(_, e)| e.storage.byte_size()).sum();
+        let newest = self.entries.len().saturating_sub(1);
+        for (key, entry) in self.entries.iter_mut().take(newest) {
+            if total <= self.budget_bytes {
+                return;
+            }
+            let Storage::Full(pixels) = &entry.storage else { continue; };
+            if !entry.half_eligible {
+                continue;
+            }
+            match to_half(pixels) {
+                Some(half) => {
+                    let before = entry.storage.byte_size();
+                    entry.storage = Storage::Half(half);
+                    total = total - before + entry.storage.byte_size();
+                    debug!(target: "processing", "layer cache: '{}' stored as half float", key.replace('\u{0}', "."));
+                }
+                None => entry.half_eligible = false,
+            }
+        }
+        while total > self.budget_bytes && self.entries.len() > 1 {
+            let (_, evicted) = self.entries.remove(0);
+            total -= evicted.storage.byte_size();
+        }
+    }
+}
+
+/// Klucz wpisu: sama warstwa albo warstwa + kanał
+pub fn key(layer: &str, channel: Option<&str>) -> String {
+    match channel {
+        Some(channel) => format!("{}\u{0}{}", layer, channel),
+        None => layer.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 4 piksele = 64 bajty w f32, 32 bajty w f16
+    fn layer(value: f32) -> CachedLayer {
+        CachedLayer { pixels: vec![(value, value * 2.0, value * 4.0, 1.0); 4].into(), width: 2, height: 2, name: "layer".into() }
+    }
+
+    fn stored_as_half(cache: &LayerCache, key: &str) -> bool {
+        cache.entries.iter().any(|(k, e)| k == key && matches!(e.storage, Storage::Half(_)))
+    }
+
+    fn keys(cache: &LayerCache) -> Vec<&str> {
+        cache.entries.iter().map(|(k, _)| k.as_str()).collect()
+    }
+
+    #[test]
+    fn exact_layer_is_stored_as_half_and_round_trips() {
+        let mut cache = LayerCache::new(100);
+        cache.insert("a".into(), layer(0.5));
+        cache.insert("b".into(), layer(0.25));
+        assert!(stored_as_half(&cache, "a"));
+        assert!(!stored_as_half(&cache, "b"));
+        assert_eq!(keys(&cache), ["a", "b"]);
+
+        let restored = cache.get("a").unwrap();
+        assert_eq!(&*restored.pixels, &*layer(0.5).pixels);
+        assert!(!stored_as_half(&cache, "a"));
+    }
+
+    #[test]
+    fn lossy_layer_stays_f32() {
+        let mut cache = LayerCache::new(160);
+        cache.insert("lossy".into(), layer(0.1));
+        cache.insert("exact".into(), layer(0.5));
+        cache.insert("newest".into(), layer(0.25));
+        assert!(!stored_as_half(&cache, "lossy"));
+        assert!(!cache.entries[0].1.half_eligible);
+        assert!(stored_as_half(&cache, "exact"));
+        assert_eq!(keys(&cache), ["lossy", "exact", "newest"]);
+        assert_eq!(&*cache.get("lossy").unwrap().pixels, &*layer(0.1).pixels);
+    }
+
+    #[test]
+    fn least_recently_used_is_evicted_first() {
+        let mut cache = LayerCache::new(200);
+        for key in ["a", "b", "c"] {
+            cache.insert(key.into(), layer(0.1));
+        }
+        assert!(cache.get("a").is_some());
+        cache.insert("d".into(), layer(0.1));
+        assert_eq!(keys(&cache), ["c", "a", "d"]);
+        assert!(cache.get("b").is_none());
+    }
+}
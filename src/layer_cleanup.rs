@@ -0,0 +1,176 @@
+// Porządki w warstwach EXR: kopia pliku z wybranymi warstwami usuniętymi albo przemianowanymi
+// (np. prefiksy programów "RenderLayer.Image.R" → "beauty.R"). Próbki przepisywane bez konwersji
+// typu, kompresja, kafelkowanie i pozostałe atrybuty każdej części bez zmian. Warstwa jak w drzewie
+// warstw: atrybut `name` części albo nazwa kanału bez członu po ostatniej kropce.
+// Czytnik płaski zna tylko jeden poziom rozdzielczości i nie czyta danych deep, więc pliki deep
+// i z mapami MIP/RIP są odrzucane przed zapisem (kopia straciłaby dane).
+
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+use exr::meta::attribute::LevelMode;
+use exr::meta::header::Header;
+use exr::meta::BlockDescription;
+use exr::prelude::{read_all_flat_layers_from_file, AnyChannels, Text, WritableImage};
+use tracing::info;
+use crate::utils::error_handling::{ExrError, ExrResult};
+use crate::utils::split_layer_and_short;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum LayerAction {
+    Drop,
+    Rename(String),
+}
+
+/// Zmiany wg nazwy warstwy; warstwy spoza planu zostają bez zmian
+pub type CleanupPlan = HashMap<String, LayerAction>;
+
+/// Pierwsze człony nazw warstw dodawane przez programy renderujące (warstwa renderu / widoku)
+const VENDOR_PREFIXES: &[&str] = &["RenderLayer", "ViewLayer", "renderlayer", "render_layer"];
+/// Nazwy głównego przebiegu zamieniane na "beauty"
+const BEAUTY_NAMES: &[&str] = &["Image", "Combined", "rgba", "RGBA", "beauty", "Beauty"];
+
+/// Proponowana nazwa warstwy bez prefiksu programu; None gdy nie ma czego poprawiać
+pub fn suggested_name(layer: &str) -> Option<String> {
+    let (first, rest) = layer.split_once('.')?;
+    if !VENDOR_PREFIXES.iter().any(|p| first.starts_with(p)) || rest.is_empty() {
+        return None;
+    }
+    Some(if BEAUTY_NAMES.contains(&rest) { "beauty".to_string() } else { rest.to_string() })
+}
+
+/// Plan poprawiający prefiksy programów we wszystkich warstwach pliku
+pub fn prefix_fixes(layer_names: &[String]) -> CleanupPlan {
+    layer_names.iter()
+        .filter_map(|layer| Some((layer.clone(), LayerAction::Rename(suggested_name(layer)?))))
+        .collect()
+}
+
+/// Warstwy pliku z liczbą kanałów, wg nazwy (same nagłówki, bez pikseli)
+pub fn layer_channel_counts(path: &Path) -> ExrResult<Vec<(String, usize)>> {
+    let mut counts: BTreeMap<String, usize> = BTreeMap::new();
+    for header in crate::deep_exr::read_headers(path)? {
+        let base = header.own_attributes.layer_name.as_ref().map(Text::to_string);
+        for channel in &header.channels.list {
+            *counts.entry(split_layer_and_short(&channel.name.to_string(), base.as_deref()).0).or_default() += 1;
+        }
+    }
+    Ok(counts.into_iter().collect())
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct CleanupSummary {
+    pub kept: usize,
+    pub dropped: usize,
+    pub renamed: usize,
+}
+
+/// Pełna nazwa kanału po zmianie warstwy (pusta warstwa = kanał bez prefiksu)
+fn channel_name(layer: &str, short: &str) -> String {
+    if layer.is_empty() { short.to_string() } else { format!("{}.{}", layer, short) }
+}
+
+/// Odrzuca części, których nie da się przepisać bez utraty danych (deep, poziomy MIP/RIP)
+fn check_supported(headers: &[Header]) -> ExrResult<()> {
+    if headers.iter().any(|h| h.deep) {
+        return Err(ExrError::Unsupported("layer clean-up does not support deep files".into()));
+    }
+    if headers.iter().any(|h| matches!(h.blocks, BlockDescription::Tiles(t) if t.level_mode != LevelMode::Singular)) {
+        return Err(ExrError::Unsupported("layer clean-up does not support mip-mapped or rip-mapped files (only the largest level would be kept)".into()));
+    }
+    Ok(())
+}
+
+/// Zapisuje kopię `source` po zmianach (przez plik tymczasowy); liczniki w kanałach
+pub fn write_cleaned(source: &Path, target: &Path, plan: &CleanupPlan) -> ExrResult<CleanupSummary> {
+    if source == target {
+        return Err(ExrError::Io(std::io::Error::other("the clean copy must not overwrite the source file")));
+    }
+    check_supported(&crate::deep_exr::read_headers(source)?)?;
+    let mut image = read_all_flat_layers_from_file(source)?;
+    let mut summary = CleanupSummary::default();
+    for part in image.layer_data.iter_mut() {
+        let base = part.attributes.layer_name.as_ref().map(Text::to_string);
+        if let Some(base) = &base {
+            // Warstwa z atrybutu części: zmiana dotyczy całej części
+            let count = part.channel_data.list.len();
+            match plan.get(base) {
+                Some(LayerAction::Drop) => {
+                    part.channel_data.list.clear();
+                    summary.dropped += count;
+                }
+                Some(LayerAction::Rename(name)) => {
+                    part.attributes.layer_name = (!name.is_empty()).then(|| Text::from(name.as_str()));
+                    summary.kept += count;
+                    summary.renamed += count;
+                }
+                None => summary.kept += count,
+            }
+            continue;
+        }
+        let channels = std::mem::take(&mut part.channel_data.list);
+        let mut kept = exr::prelude::SmallVec::new();
+        for mut channel in channels {
+            let (layer, short) = split_layer_and_short(&channel.name.to_string(), None);
+            match plan.get(&layer) {
+                Some(LayerAction::Drop) => summary.dropped += 1,
+                Some(LayerAction::Rename(name)) => {
+                    channel.name = Text::from(channel_name(name, &short).as_str());
+                    summary.renamed += 1;
+                    kept.push(channel);
+                }
+                None => kept.push(channel),
+            }
+        }
+        summary.kept += kept.len();
+        let before = kept.len();
+        part.channel_data = AnyChannels::sort(kept);
+        part.channel_data.list.dedup_by(|a, b| a.name == b.name);
+        if part.channel_data.list.len() != before {
+            return Err(ExrError::Io(std::io::Error::other("renaming produces duplicate channel names")));
+        }
+    }
+    image.layer_data.retain(|part| !part.channel_data.list.is_empty());
+    if image.layer_data.is_empty() {
+        return Err(ExrError::Io(std::io::Error::other("all layers would be dropped")));
+    }
+
+    let temp = target.with_extension("exr.tmp");
+    if let Err(e) = image.write().to_file(&temp) {
+        let _ = std::fs::remove_file(&temp);
+        return Err(e.into());
+    }
+    std::fs::rename(&temp, target)?;
+    info!(target: "io", "clean copy written: {} ({} channels kept, {} dropped, {} renamed)", target.display(), summary.kept, summary.dropped, summary.renamed);
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn suggests_names_without_vendor_prefixes() {
+        assert_eq!(suggested_name("RenderLayer.Image").as_deref(), Some("beauty"));
+        assert_eq!(suggested_name("ViewLayer.Combined").as_deref(), Some("beauty"));
+        assert_eq!(suggested_name("RenderLayer.AO").as_deref(), Some("AO"));
+        assert_eq!(suggested_name("diffuse"), None);
+        assert_eq!(suggested_name("crypto.00"), None);
+        assert_eq!(channel_name("", "R"), "R");
+        let plan = prefix_fixes(&["".to_string(), "RenderLayer.Image".to_string()]);
+        assert_eq!(plan.len(), 1);
+        assert_eq!(plan["RenderLayer.Image"], LayerAction::Rename("beauty".to_string()));
+    }
+
+    #[test]
+    fn rejects_deep_and_multi_level_files() {
+        use exr::math::{RoundingMode, Vec2};
+        use exr::meta::attribute::{ChannelDescription, SampleType, TileDescription};
+        let flat = Header::new(Text::from("beauty"), (8, 8), vec![ChannelDescription::new(Text::from("R"), SampleType::F16, false)].into());
+        assert!(check_supported(std::slice::from_ref(&flat)).is_ok());
+        let deep = Header { deep: true, ..flat.clone() };
+        assert!(matches!(check_supported(&[flat.clone(), deep]), Err(ExrError::Unsupported(_))));
+        let tiles = TileDescription { tile_size: Vec2(4, 4), level_mode: LevelMode::MipMap, rounding_mode: RoundingMode::Down };
+        let mip = Header { blocks: BlockDescription::Tiles(tiles), ..flat };
+        assert!(matches!(check_supported(&[mip]), Err(ExrError::Unsupported(_))));
+    }
+}
@@ -0,0 +1,252 @@
+// Przepinanie kanałów do nowego pliku EXR: każdy kanał wyjściowy bierze kanał źródła (dowolnej
+// warstwy) albo stałą, np. "beauty.A = AO.Y" pakuje AO do alfy. Mapowanie to tekst wierszami
+// `wyjście = źródło`; nazwy kanałów pełne, jak w pliku ("warstwa.kanał" albo samo "R"). Wybór typu
+// próbek i kompresji; atrybuty (m.in. chromatyczności) z części źródła pierwszego kanału.
+
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use exr::prelude::{read_all_flat_layers_from_file, f16, AnyChannel, AnyChannels, Blocks, Compression, Encoding, FlatSamples, Image, Layer, LineOrder, SmallVec, WritableImage};
+use rayon::prelude::*;
+use tracing::info;
+use crate::cancel::CancelToken;
+use crate::utils::error_handling::{ExrError, ExrResult};
+use crate::utils::split_layer_and_short;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ChannelSource {
+    /// Pełna nazwa kanału w pliku źródłowym
+    Channel(String),
+    Constant(f32),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct OutputChannel {
+    pub name: String,
+    pub source: ChannelSource,
+}
+
+impl fmt::Display for OutputChannel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.source {
+            ChannelSource::Channel(name) => write!(f, "{} = {}", self.name, name),
+            ChannelSource::Constant(value) => write!(f, "{} = {}", self.name, value),
+        }
+    }
+}
+
+/// Mapowanie z tekstu: wiersze (albo pozycje rozdzielone ';') `wyjście = źródło`, '#' zaczyna
+/// komentarz. Źródło będące liczbą to stała.
+pub fn parse_mapping(text: &str) -> Result<Vec<OutputChannel>, String> {
+    let mut channels: Vec<OutputChannel> = Vec::new();
+    for entry in text.lines().flat_map(|line| line.split('#').next().unwrap_or("").split(';')) {
+        let entry = entry.trim();
+        if entry.is_empty() {
+            continue;
+        }
+        let Some((name, source)) = entry.split_once('=') else {
+            return Err(format!("expected 'output = source', got '{}'", entry));
+        };
+        let (name, source) = (name.trim(), source.trim());
+        if name.is_empty() || source.is_empty() {
+            return Err(format!("expected 'output = source', got '{}'", entry));
+        }
+        if channels.iter().any(|c| c.name == name) {
+            return Err(format!("output channel {} is mapped twice", name));
+        }
+        let source = source.parse::<f32>().map_or_else(|_| ChannelSource::Channel(source.to_string()), ChannelSource::Constant);
+        channels.push(OutputChannel { name: name.to_string(), source });
+    }
+    if channels.is_empty() {
+        return Err("no output channels".to_string());
+    }
+    Ok(channels)
+}
+
+/// Mapowanie tożsamościowe kanałów warstwy (punkt wyjścia do edycji)
+pub fn identity_mapping(layer: &str, channels: &[String]) -> String {
+    channels.iter()
+        .map(|short| if layer.is_empty() { short.clone() } else { format!("{}.{}", layer, short) })
+        .map(|name| format!("{} = {}", name, name))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SampleKind {
+    F16,
+    F32,
+    U32,
+}
+
+impl SampleKind {
+    pub fn from_label(label: &str) -> Self {
+        match label {
+            "32-bit float" => SampleKind::F32,
+            "32-bit uint" => SampleKind::U32,
+            _ => SampleKind::F16,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            SampleKind::F16 => "16-bit half",
+            SampleKind::F32 => "32-bit float",
+            SampleKind::U32 => "32-bit uint",
+        }
+    }
+
+    fn samples(self, values: Vec<f32>) -> FlatSamples {
+        match self {
+            SampleKind::F16 => FlatSamples::F16(values.into_iter().map(f16::from_f32).collect()),
+            SampleKind::F32 => FlatSamples::F32(values),
+            // Rzutowanie saturuje, NaN daje 0 (ID obiektów, maski)
+            SampleKind::U32 => FlatSamples::U32(values.into_iter().map(|v| v.round() as u32).collect()),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LayerCompression {
+    None,
+    Rle,
+    Zips,
+    Zip,
+    Piz,
+    Pxr24,
+    Dwaa,
+}
+
+impl LayerCompression {
+    pub fn from_label(label: &str) -> Self {
+        match label {
+            "None" => LayerCompression::None,
+            "RLE" => LayerCompression::Rle,
+            "ZIPS" => LayerCompression::Zips,
+            "PIZ" => LayerCompression::Piz,
+            "PXR24 (lossy)" => LayerCompression::Pxr24,
+            "DWAA (lossy)" => LayerCompression::Dwaa,
+            _ => LayerCompression::Zip,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            LayerCompression::None => "None",
+            LayerCompression::Rle => "RLE",
+            LayerCompression::Zips => "ZIPS",
+            LayerCompression::Zip => "ZIP",
+            LayerCompression::Piz => "PIZ",
+            LayerCompression::Pxr24 => "PXR24 (lossy)",
+            LayerCompression::Dwaa => "DWAA (lossy)",
+        }
+    }
+
+    fn compression(self) -> Compression {
+        match self {
+            LayerCompression::None => Compression::Uncompressed,
+            LayerCompression::Rle => Compression::RLE,
+            LayerCompression::Zips => Compression::ZIP1,
+            LayerCompression::Zip => Compression::ZIP16,
+            LayerCompression::Piz => Compression::PIZ,
+            LayerCompression::Pxr24 => Compression::PXR24,
+            LayerCompression::Dwaa => Compression::DWAA(None),
+        }
+    }
+}
+
+/// Zapisuje przepięte kanały `source` do `target` (przez plik tymczasowy). Zwraca liczbę pikseli.
+pub fn export_remapped(
+    source: &Path,
+    mapping: &[OutputChannel],
+    sample: SampleKind,
+    compression: LayerCompression,
+    target: &Path,
+    cancel: &CancelToken,
+    progress: &(dyn Fn(f32, &str) + Sync),
+) -> ExrResult<u64> {
+    progress(0.0, &format!("Reading {}...", source.display()));
+    let image = read_all_flat_layers_from_file(source)?;
+    cancel.check()?;
+
+    // Pełna nazwa kanału → (część pliku, kanał)
+    let mut by_name = HashMap::new();
+    for (part, layer) in image.layer_data.iter().enumerate() {
+        let prefix = layer.attributes.layer_name.as_ref().map(|n| format!("{}.", n)).unwrap_or_default();
+        for channel in &layer.channel_data.list {
+            by_name.entry(format!("{}{}", prefix, channel.name)).or_insert((part, channel));
+        }
+    }
+    let sources: Vec<_> = mapping.iter()
+        .filter_map(|c| match &c.source {
+            ChannelSource::Channel(name) => Some(by_name.get(name).copied().ok_or_else(|| {
+                let (layer, channel) = split_layer_and_short(name, None);
+                ExrError::MissingChannel { layer, channel }
+            })),
+            ChannelSource::Constant(_) => None,
+        })
+        .collect::<ExrResult<_>>()?;
+    let parts: HashSet<usize> = sources.iter().map(|(part, _)| *part).collect();
+    let first = sources.first().map_or(0, |(part, _)| *part);
+    let size = image.layer_data[first].size;
+    if let Some(part) = parts.iter().find(|&&p| image.layer_data[p].size != size) {
+        let other = image.layer_data[*part].size;
+        let message = format!("source channels differ in size ({}x{} vs {}x{})", size.width(), size.height(), other.width(), other.height());
+        return Err(ExrError::Io(std::io::Error::other(message)));
+    }
+
+    let done = AtomicUsize::new(0);
+    let channels: SmallVec<[AnyChannel<FlatSamples>; 4]> = mapping.par_iter()
+        .map(|output| {
+            cancel.check()?;
+            let values: Vec<f32> = match &output.source {
+                ChannelSource::Constant(value) => vec![*value; size.area()],
+                ChannelSource::Channel(name) => {
+                    let samples = &by_name[name].1.sample_data;
+                    (0..size.area()).map(|i| samples.value_by_flat_index(i).to_f32()).collect()
+                }
+            };
+            let n = done.fetch_add(1, Ordering::Relaxed) + 1;
+            progress(0.2 + 0.3 * n as f32 / mapping.len() as f32, &format!("Channel {}/{}: {}", n, mapping.len(), output.name));
+            Ok(AnyChannel::new(output.name.as_str(), sample.samples(values)))
+        })
+        .collect::<ExrResult<Vec<_>>>()?
+        .into_iter()
+        .collect();
+    cancel.check()?;
+
+    let mut attributes = image.layer_data[first].attributes.clone();
+    attributes.layer_name = None;
+    let encoding = Encoding { compression: compression.compression(), blocks: Blocks::ScanLines, line_order: LineOrder::Increasing };
+    let output = Image::from_layer(Layer::new(size, attributes, encoding, AnyChannels::sort(channels)));
+    progress(0.5, &format!("Writing {}...", target.display()));
+    let temp = target.with_extension("exr.tmp");
+    let written = output.write()
+        .on_progress(|f| progress(0.5 + 0.5 * f as f32, &format!("Writing {}...", target.display())))
+        .to_file(&temp);
+    if let Err(e) = written {
+        let _ = std::fs::remove_file(&temp);
+        return Err(e.into());
+    }
+    std::fs::rename(&temp, target)?;
+    info!(target: "io", "remapped EXR written: {} ({} channels, {}, {})", target.display(), mapping.len(), sample.label(), compression.label());
+    Ok(size.area() as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_mapping_text() {
+        let mapping = parse_mapping("beauty.R = beauty.R\n# pakowanie AO\nbeauty.A = AO.Y; mask = 1").unwrap();
+        assert_eq!(mapping.len(), 3);
+        assert_eq!(mapping[1].source, ChannelSource::Channel("AO.Y".to_string()));
+        assert_eq!(mapping[2].source, ChannelSource::Constant(1.0));
+        assert_eq!(mapping[1].to_string(), "beauty.A = AO.Y");
+        assert!(parse_mapping("R = R\nR = G").is_err());
+        assert!(parse_mapping("R").is_err());
+        assert_eq!(identity_mapping("", &["R".to_string(), "G".to_string()]), "R = R\nG = G");
+    }
+}
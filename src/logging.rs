@@ -0,0 +1,164 @@
+use std::collections::VecDeque;
+use std::fmt::Write as _;
+use std::sync::{Mutex, OnceLock};
+use tracing::field::{Field, Visit};
+use tracing::{Event, Level, Subscriber};
+use tracing_subscriber::filter::LevelFilter;
+use tracing_subscriber::layer::{Context, Layer, SubscriberExt};
+use tracing_subscriber::{reload, Registry};
+
+/// Pojedynczy wpis logu przekazywany do konsoli w UI.
+/// Kategoria to `target` makra `tracing`: "io", "gpu", "processing" lub "ui" (filtr w panelu konsoli).
+#[derive(Clone, Debug)]
+pub struct LogEntry {
+    pub timestamp: String,
+    pub level: Level,
+    pub category: String,
+    pub message: String,
+}
+
+impl LogEntry {
+    /// Linia wyświetlana w konsoli: `12:34:56 INFO  io: message`
+    pub fn format_line(&self) -> String {
+        format!("{} {:<5} {}: {}", self.timestamp, self.level, self.category, self.message)
+    }
+}
+
+// Wpisy czekające na odebranie przez wątek UI (logować można z dowolnego wątku). Bufor cykliczny:
+// bez okna (wiersz poleceń, skrypty) nikt go nie opróżnia, więc najstarsze wpisy są usuwane
+static PENDING: Mutex<VecDeque<LogEntry>> = Mutex::new(VecDeque::new());
+/// Limit wpisów czekających na konsolę – tyle, ile konsola i tak przechowuje
+const MAX_PENDING_ENTRIES: usize = 5000;
+// Uchwyt do zmiany poziomu logowania w trakcie działania
+static LEVEL_HANDLE: OnceLock<reload::Handle<LevelFilter, Registry>> = OnceLock::new();
+
+/// Inicjalizuje logowanie: rotowany dziennie plik w katalogu danych aplikacji + konsola UI.
+/// Zwrócony guard trzeba trzymać do końca programu (opróżnia bufor pliku przy zamknięciu).
+pub fn init() -> Option<tracing_appender::non_blocking::WorkerGuard> {
+    let (level_layer, handle) = reload::Layer::new(LevelFilter::INFO);
+    let _ = LEVEL_HANDLE.set(handle);
+
+    let log_dir = crate::session::app_data_dir().join("logs");
+    let (file_layer, guard) = match tracing_appender::rolling::Builder::new()
+        .rotation(tracing_appender::rolling::Rotation::DAILY)
+        .filename_prefix("exruster")
+        .filename_suffix("log")
+        .max_log_files(7)
+        .build(&log_dir)
+    {
+        Ok(appender) => {
+            let (writer, guard) = tracing_appender::non_blocking(appender);
+            (Some(tracing_subscriber::fmt::layer().with_writer(writer).with_ansi(false)), Some(guard))
+        }
+        Err(_) => (None, None),
+    };
+
+    let subscriber = Registry::default()
+        .with(level_layer)
+        .with(file_layer)
+        .with(UiConsoleLayer);
+    let _ = tracing::subscriber::set_global_default(subscriber);
+    guard
+}
+
+/// Zmienia poziom logowania w trakcie działania (dotyczy pliku i konsoli)
+pub fn set_level(level: LevelFilter) {
+    if let Some(handle) = LEVEL_HANDLE.get() {
+        let _ = handle.modify(|filter| *filter = level);
+    }
+}
+
+/// Parsuje nazwę poziomu z UI ("Error", "Warn", "Info", "Debug", "Trace")
+pub fn parse_level(name: &str) -> Option<LevelFilter> {
+    name.trim().parse::<LevelFilter>().ok()
+}
+
+/// Odbiera wpisy zebrane od ostatniego wywołania (wołane z timera na wątku UI)
+pub fn drain_pending() -> Vec<LogEntry> {
+    let mut pending = PENDING.lock().unwrap_or_else(|p| p.into_inner());
+    std::mem::take(&mut *pending).into()
+}
+
+fn push_pending(entry: LogEntry) {
+    let mut pending = PENDING.lock().unwrap_or_else(|p| p.into_inner());
+    if pending.len() >= MAX_PENDING_ENTRIES {
+        pending.pop_front();
+    }
+    pending.push_back(entry);
+}
+
+/// Warstwa `tracing` przekazująca zdarzenia do konsoli w UI
+struct UiConsoleLayer;
+
+impl<S: Subscriber> Layer<S> for UiConsoleLayer {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        let mut visitor = MessageVisitor::default();
+        event.record(&mut visitor);
+        let meta = event.metadata();
+        let entry = LogEntry {
+            timestamp: utc_time_of_day(),
+            level: *meta.level(),
+            category: meta.target().to_string(),
+            message: visitor.finish(),
+        };
+        push_pending(entry);
+    }
+}
+
+/// Składa treść wpisu: pole `message` + pozostałe pola jako `klucz=wartość`
+#[derive(Default)]
+struct MessageVisitor {
+    message: String,
+    fields: String,
+}
+
+impl MessageVisitor {
+    fn finish(self) -> String {
+        if self.fields.is_empty() { self.message } else { format!("{}{}", self.message, self.fields) }
+    }
+}
+
+impl Visit for MessageVisitor {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == "message" {
+            self.message.push_str(value);
+        } else {
+            let _ = write!(self.fields, " {}={}", field.name(), value);
+        }
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        if field.name() == "message" {
+            let _ = write!(self.message, "{:?}", value);
+        } else {
+            let _ = write!(self.fields, " {}={:?}", field.name(), value);
+        }
+    }
+}
+
+/// Godzina (UTC) w formacie HH:MM:SS – bez zależności od biblioteki dat
+fn utc_time_of_day() -> String {
+    let secs = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0) % 86_400;
+    format!("{:02}:{:02}:{:02}", secs / 3600, (secs / 60) % 60, secs % 60)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pending_entries_drop_the_oldest() {
+        let entry = |i: usize| LogEntry { timestamp: String::new(), level: Level::INFO, category: "io".into(), message: i.to_string() };
+        for i in 0..MAX_PENDING_ENTRIES + 10 {
+            push_pending(entry(i));
+        }
+        let drained = drain_pending();
+        assert_eq!(drained.len(), MAX_PENDING_ENTRIES);
+        assert_eq!(drained[0].message, "10");
+        assert_eq!(drained[MAX_PENDING_ENTRIES - 1].message, (MAX_PENDING_ENTRIES + 9).to_string());
+        assert!(drain_pending().is_empty());
+    }
+}
@@ -1,211 +1,354 @@
 #![windows_subsystem = "windows"]
+#![cfg_attr(feature = "portable-simd", feature(portable_simd))]
+// Funkcja "fuzz": binarium jest celem libFuzzera (main dostarcza libFuzzer, UI nie jest używane)
+#![cfg_attr(feature = "fuzz", no_main, allow(dead_code, unused_imports))]
 
 slint::include_modules!();
 
 mod image_cache;
+mod layer_cache;
+mod render_cache;
+mod tiles;
+mod raw_image;
+mod simd_processing;
 mod image_processing;
+mod render_settings;
+mod app_context;
+mod color_processing;
+mod histogram;
 mod file_operations;
 mod ui_handlers;
 mod thumbnails;
+mod dir_scan;
+mod sequence;
+mod timeline;
+mod playlist;
+mod preload;
 mod exr_metadata;
+mod deep_exr;
 mod progress;
 mod utils;
+mod io;
+mod browser;
+mod cancel;
+mod session;
+mod crash;
+mod logging;
+mod console;
+mod channel_classification;
+mod compare;
+mod metrics;
+mod qc_report;
+mod cli;
+mod color_picker;
+mod point_cloud;
+mod export_handlers;
+mod export_executor;
+mod export_queue;
+mod layer_export;
+mod layer_cleanup;
+mod video_export;
+mod animated_export;
+mod proxy_files;
+mod history;
+mod display_profile;
+mod display_filters;
+mod env_map;
+mod stereo;
+mod worker_threads;
+mod preferences;
+mod annotations;
+mod snapshot_gallery;
+mod theme;
+mod platform;
+mod actions;
+mod remote;
+mod single_instance;
+#[cfg(feature = "scripting")]
+mod scripting;
+#[cfg(all(test, feature = "synthetic"))]
+mod bench;
+#[cfg(any(test, feature = "fuzz"))]
+mod metadata_fuzz;
 
+use std::path::PathBuf;
 use std::sync::{Arc, Mutex};
-use crate::ui_handlers::push_console;
 use ui_handlers::{ImageCacheType, CurrentFilePathType};
-use slint::{ModelRc, VecModel, SharedString};
+use slint::{VecModel, SharedString};
 use std::rc::Rc;
-use crate::utils::human_size;
+use actions::{Action, Dispatcher};
 
+#[cfg(feature = "fuzz")]
+libfuzzer_sys::fuzz_target!(|data: &[u8]| metadata_fuzz::fuzz_one(data));
+
+#[cfg(not(feature = "fuzz"))]
 fn main() -> Result<(), slint::PlatformError> {
+    // Logi: plik rotowany dziennie + konsola w UI (guard opróżnia bufor pliku przy wyjściu)
+    let _log_guard = logging::init();
+    // Raport awarii zamiast niewidocznego wpisu na stderr
+    crash::install_panic_hook();
+    io::set_mmap_reads(preferences::current().mmap_reads);
+
     // Ustaw Rayon thread pool na podstawie CPU cores
     rayon::ThreadPoolBuilder::new()
         .num_threads((num_cpus::get() - 1).max(1)) // Zostaw 1 core dla UI
         .build_global()
         .expect("Failed to initialize thread pool");
 
+    // Tryb wiersza poleceń (np. --compare a.exr b.exr) – bez okna
+    if let Some(code) = cli::run_from_args() {
+        std::process::exit(code);
+    }
+    // Plik lub folder z wiersza poleceń (dwuklik w Eksploratorze, "Browse folder in EXRuster");
+    // przy działającym oknie trafia do niego, a to uruchomienie się kończy
+    let startup_paths = cli::startup_paths();
+    if let [path] = startup_paths.as_slice() {
+        if single_instance::forward(path) {
+            return Ok(());
+        }
+    }
+
     let ui = AppWindow::new()?;
+    theme::apply(&ui);
+    preferences::apply(&ui);
     
     let image_cache: ImageCacheType = Arc::new(Mutex::new(None));
     let current_file_path: CurrentFilePathType = Arc::new(Mutex::new(None));
+    let context = Arc::new(app_context::AppContext::default());
 
     // Setup UI callbacks...
-    setup_ui_callbacks(&ui, image_cache.clone(), current_file_path.clone());
+    let dispatcher = setup_ui_callbacks(&ui, image_cache.clone(), current_file_path.clone(), context);
+
+    // Po awarii zaproponuj przywrócenie poprzedniej sesji
+    if let Some(saved) = crash::check_previous_crash() {
+        restore_session(&ui, saved);
+        dispatcher.sync_view_state(&ui);
+    }
+    match startup_paths.len() {
+        0 => {}
+        1 => dispatcher.dispatch(Action::open(startup_paths[0].clone())),
+        _ => dispatcher.dispatch(Action::OpenPlaylist(startup_paths)),
+    }
+    // Zadania eksportu przerwane zamknięciem lub awarią poprzedniej sesji
+    export_queue::resume(&ui);
+    single_instance::start_listener(&ui, &dispatcher);
     
     ui.run()
 }
 
-fn setup_menu_callbacks(
-    ui: &AppWindow,
-    current_file_path: CurrentFilePathType,
-    image_cache: ImageCacheType,
-    console_model: Rc<VecModel<SharedString>>,
-) {
-    ui.on_clear_console({
-        let ui_handle = ui.as_weak();
-        let console_for_clear = console_model.clone();
-        move || {
-            if let Some(ui) = ui_handle.upgrade() {
-                console_for_clear.set_vec(vec![]);
-                ui.set_console_text(SharedString::from(""));
-                ui.set_status_text(SharedString::from("Console cleared"));
-            }
-        }
-    });
-
-    ui.on_exit({
-        let ui_handle = ui.as_weak();
-        move || {
-            ui_handlers::handle_exit(ui_handle.clone());
-        }
-    });
+/// Przywraca sesję przez te same callbacki, których używa UI (folder, plik), oraz parametry podglądu
+fn restore_session(ui: &AppWindow, saved: session::SessionState) {
+    ui.set_exposure_value(saved.exposure);
+    ui.set_gamma_value(saved.gamma);
+    if let Some(dir) = saved.working_dir {
+        ui.set_show_folder_browser(true);
+        ui.invoke_folder_selected(dir.display().to_string().into());
+    }
+    if let Some(file) = saved.last_file {
+        ui.invoke_open_thumbnail(file.display().to_string().into());
+    }
+}
 
-    ui.on_open_exr({
-        let ui_handle = ui.as_weak();
-        let current_file_path = current_file_path.clone();
-        let image_cache = image_cache.clone();
-        let console = console_model.clone(); // Use console_model directly
-        move || {
-            ui_handlers::handle_open_exr(ui_handle.clone(), current_file_path.clone(), image_cache.clone(), console.clone());
-        }
-    });
+/// Podpina callback Slint pod akcję: `on!(ui, dispatcher, on_exit, || Action::Exit)`
+macro_rules! on {
+    ($ui:expr, $dispatcher:expr, $callback:ident, || $action:expr) => {{
+        let dispatcher = $dispatcher.clone();
+        $ui.$callback(move || dispatcher.dispatch($action));
+    }};
+    ($ui:expr, $dispatcher:expr, $callback:ident, |$($arg:ident: $ty:ty),*| $action:expr) => {{
+        let dispatcher = $dispatcher.clone();
+        $ui.$callback(move |$($arg: $ty),*| dispatcher.dispatch($action));
+    }};
 }
 
-fn setup_image_control_callbacks(
-    ui: &AppWindow,
-    image_cache: ImageCacheType,
-    current_file_path: CurrentFilePathType,
-    console_model: Rc<VecModel<SharedString>>,
-) {
-    let ui_weak_for_throttle = ui.as_weak();
-    let cache_weak_for_throttle = image_cache.clone();
-    let console_for_throttle = console_model.clone(); // Use console_model directly
-
-    let throttled_updater = ui_handlers::ThrottledUpdate::new(move |exp, gamma| {
-        if let Some(_ui) = ui_weak_for_throttle.upgrade() {
-            ui_handlers::handle_parameter_changed_throttled(
-                ui_weak_for_throttle.clone(), 
-                cache_weak_for_throttle.clone(), 
-                console_for_throttle.clone(),
-                exp, 
-                gamma
-            );
-        }
+fn setup_menu_callbacks(ui: &AppWindow, dispatcher: &Rc<Dispatcher>) {
+    on!(ui, dispatcher, on_clear_console, || Action::ClearConsole);
+    on!(ui, dispatcher, on_console_search_changed, |text: SharedString| Action::SetConsoleSearch(text));
+    on!(ui, dispatcher, on_save_console_log, || Action::SaveConsoleLog);
+    on!(ui, dispatcher, on_console_level_changed, |level: SharedString| Action::SetConsoleLevel(level));
+    on!(ui, dispatcher, on_console_category_changed, |category: SharedString| Action::SetConsoleCategory(category));
+    on!(ui, dispatcher, on_exit, || Action::Exit);
+    on!(ui, dispatcher, on_open_exr, || Action::OpenFileDialog);
+    on!(ui, dispatcher, on_run_script, || Action::RunScriptDialog);
+    on!(ui, dispatcher, on_open_original, || Action::OpenOriginal);
+    on!(ui, dispatcher, on_monitor_profile_changed, |enabled: bool| Action::SetMonitorProfile(enabled));
+    on!(ui, dispatcher, on_generate_proxies, || Action::GenerateProxies);
+    on!(ui, dispatcher, on_qc_report, || Action::QcReport);
+    on!(ui, dispatcher, on_check_sequence, || Action::CheckSequence);
+    on!(ui, dispatcher, on_timeline_scrubbed, |fraction: f32| Action::ScrubTimeline(fraction));
+    on!(ui, dispatcher, on_step_frame, |delta: i32| Action::StepFrame(delta as i64));
+    on!(ui, dispatcher, on_step_playlist, |delta: i32| Action::StepPlaylist(delta as i64));
+    on!(ui, dispatcher, on_close_playlist, || Action::ClosePlaylist);
+    on!(ui, dispatcher, on_toggle_playback, || Action::TogglePlayback);
+    on!(ui, dispatcher, on_cycle_gap_mode, || Action::CycleGapMode);
+    on!(ui, dispatcher, on_prefer_proxies_changed, |prefer: bool| Action::SetPreferProxies(prefer));
+    on!(ui, dispatcher, on_theme_mode_changed, |light: bool| {
+        Action::SetThemeMode(if light { theme::ThemeMode::Light } else { theme::ThemeMode::Dark })
     });
-    let throttled_update = Arc::new(Mutex::new(throttled_updater));
-
-    ui.on_exposure_changed({
-        let throttled_update = throttled_update.clone();
-        
-        move |exposure: f32| {
-            let updater = throttled_update.lock().unwrap();
-                updater.update_exposure(exposure);
-        }
+    on!(ui, dispatcher, on_accent_changed, |hex: SharedString| Action::SetAccent(hex.to_string()));
+    on!(ui, dispatcher, on_worker_threads_changed, |text: SharedString| Action::SetWorkerThreads(text.to_string()));
+    on!(ui, dispatcher, on_worker_low_priority_changed, |low: bool| Action::SetWorkerLowPriority(low));
+    on!(ui, dispatcher, on_mmap_reads_changed, |enabled: bool| Action::SetMmapReads(enabled));
+    on!(ui, dispatcher, on_remote_port_changed, |text: SharedString| Action::SetRemotePort(text.to_string()));
+    on!(ui, dispatcher, on_cancel_task, |id: i32| Action::CancelTask(id as u64));
+    on!(ui, dispatcher, on_pause_task, |id: i32| Action::PauseTask(id as u64));
+    on!(ui, dispatcher, on_export_channels, |format: SharedString, scope: SharedString| {
+        Action::ExportChannels { format: export_handlers::ChannelFormat::from_label(&format), all_layers: scope.starts_with("All") }
     });
-
-    ui.on_gamma_changed({
-        let throttled_update = throttled_update.clone();
-        
-        move |gamma: f32| {
-            let updater = throttled_update.lock().unwrap();
-            updater.update_gamma(gamma);
-        }
+    on!(ui, dispatcher, on_export_image, |format: SharedString, quality: f32, chroma: SharedString, output: SharedString| {
+        Action::ExportImage(export_handlers::DeliveryOptions {
+            format: export_handlers::DeliveryFormat::from_label(&format),
+            quality: quality.round().clamp(1.0, 100.0) as u8,
+            subsampling: export_handlers::ChromaSubsampling::from_label(&chroma),
+            output: export_handlers::OutputTransform::from_label(&output),
+        })
     });
-
-    ui.on_layer_tree_clicked({
-        let ui_handle = ui.as_weak();
-        let image_cache = image_cache.clone();
-        let current_file_path = current_file_path.clone();
-        let console = console_model.clone(); // Use console_model directly
-        move |clicked_item: slint::SharedString| {
-            ui_handlers::handle_layer_tree_click(
-                ui_handle.clone(),
-                image_cache.clone(), 
-                clicked_item.to_string(),
-                current_file_path.clone(),
-                console.clone()
-            );
+    on!(ui, dispatcher, on_export_remapped_exr, |mapping: SharedString, sample: SharedString, compression: SharedString| {
+        Action::ExportRemappedExr {
+            mapping: mapping.to_string(),
+            sample: layer_export::SampleKind::from_label(&sample),
+            compression: layer_export::LayerCompression::from_label(&compression),
         }
     });
+    on!(ui, dispatcher, on_fill_exr_mapping, || Action::FillExrMapping);
+    on!(ui, dispatcher, on_open_cleanup, || Action::OpenCleanup);
+    on!(ui, dispatcher, on_write_cleanup, || Action::WriteCleanup);
+    on!(ui, dispatcher, on_export_video, |size: SharedString, quality: SharedString, fps: i32| {
+        Action::ExportVideo(video_export::VideoOptions {
+            size: video_export::VideoSize::from_label(&size),
+            quality: video_export::VideoQuality::from_label(&quality),
+            fps: fps.clamp(1, 120) as u32,
+        })
+    });
+    on!(ui, dispatcher, on_export_animation, |format: SharedString, max_side: i32, every: i32, fps: i32| {
+        Action::ExportAnimation(animated_export::AnimatedOptions {
+            format: animated_export::AnimatedFormat::from_label(&format),
+            max_side: max_side.clamp(16, 8192) as u32,
+            every: every.clamp(1, 100) as u32,
+            fps: fps.clamp(1, 120) as u32,
+        })
+    });
 }
 
-fn setup_panel_callbacks(
-    ui: &AppWindow,
-    current_file_path: CurrentFilePathType,
-    image_cache: ImageCacheType,
-    console_model: Rc<VecModel<SharedString>>,
-) {
-    ui.on_choose_working_folder({
-        let ui_handle = ui.as_weak();
-        let console_model = console_model.clone(); // Use console_model directly
-        move || {
-            if let Some(ui) = ui_handle.upgrade() {
-                push_console(&ui, &console_model, "[folder] choosing working folder...".to_string());
-
-                if let Some(dir) = crate::file_operations::open_folder_dialog() {
-                    ui.set_status_text(format!("Loading thumbnails: {}", dir.display()).into());
-                    let exposure = ui.get_exposure_value();
-                    let gamma = ui.get_gamma_value();
-                    let t0 = std::time::Instant::now();
-                    match crate::thumbnails::generate_exr_thumbnails_in_dir(&dir, 150, exposure, gamma) {
-                        Ok(mut thumbs) => {
-                            thumbs.sort_by(|a, b| a.file_name.to_lowercase().cmp(&b.file_name.to_lowercase()));
-                            let items: Vec<ThumbItem> = thumbs.into_iter().map(|t| ThumbItem {
-                                img: t.image,
-                                name: t.file_name.into(),
-                                size: human_size(t.file_size_bytes).into(),
-                                layers: format!("{} layers", t.num_layers).into(),
-                                path: t.path.display().to_string().into(),
-                                width: t.width as i32,
-                                height: t.height as i32,
-                            }).collect();
-                            let count = items.len();
-                            ui.set_thumbnails(ModelRc::new(VecModel::from(items)));
-                            let ms = t0.elapsed().as_millis();
-                            ui.set_status_text("Thumbnails loaded".into());
-                            ui.set_bottom_panel_visible(true);
-                            push_console(&ui, &console_model, format!("[folder] {} EXR files | thumbnails in {} ms", count, ms));
-                        }
-                        Err(e) => {
-                            ui.set_status_text(format!("Error loading thumbnails: {}", e).into());
-                            push_console(&ui, &console_model, format!("[error][folder] {}", e));
-                        }
-                    }
-                } else {
-                    push_console(&ui, &console_model, "[folder] selection canceled".to_string());
-                }
-            }
-        }
+fn setup_image_control_callbacks(ui: &AppWindow, dispatcher: &Rc<Dispatcher>) {
+    on!(ui, dispatcher, on_exposure_changed, |exposure: f32| Action::SetExposure(exposure));
+    on!(ui, dispatcher, on_gamma_changed, |gamma: f32| Action::SetGamma(gamma));
+    on!(ui, dispatcher, on_undo_view, || Action::UndoView);
+    on!(ui, dispatcher, on_redo_view, || Action::RedoView);
+    on!(ui, dispatcher, on_auto_exposure, || Action::AutoExposure);
+    on!(ui, dispatcher, on_store_snapshot_a, || Action::StoreSnapshotA);
+    on!(ui, dispatcher, on_toggle_ab, || Action::ToggleAb);
+    on!(ui, dispatcher, on_capture_snapshot, |name: SharedString, u0: f32, v0: f32, u1: f32, v1: f32| {
+        Action::CaptureSnapshot { name: name.to_string(), region: [u0, v0, u1, v1] }
     });
-
-    ui.on_open_thumbnail({
-        let ui_handle = ui.as_weak();
-        let current_file_path = current_file_path.clone();
-        let image_cache = image_cache.clone();
-        let console_model = console_model.clone(); // Use console_model directly
-        move |path_str: slint::SharedString| {
-            if let Some(_ui) = ui_handle.upgrade() {
-                let path = std::path::PathBuf::from(path_str.as_str());
-                {
-                    let line = SharedString::from(format!("[thumbnails] opening file {}", path.display()));
-                    console_model.push(line.clone());
-                }
-                ui_handlers::handle_open_exr_from_path(ui_handle.clone(), current_file_path.clone(), image_cache.clone(), console_model.clone(), path);
-            }
-        }
+    on!(ui, dispatcher, on_restore_snapshot, |index: i32| Action::RestoreSnapshot(index.max(0) as usize));
+    on!(ui, dispatcher, on_delete_snapshot, |index: i32| Action::DeleteSnapshot(index.max(0) as usize));
+    on!(ui, dispatcher, on_exposure_mode_changed, |mode: SharedString| {
+        Action::SetExposureMode(image_processing::ExposureMode::from_label(&mode))
+    });
+    on!(ui, dispatcher, on_annotation_draw, |phase: i32, u: f32, v: f32| {
+        Action::AnnotationDraw { phase: annotations::DrawPhase::from_index(phase), u, v }
+    });
+    on!(ui, dispatcher, on_undo_annotation, || Action::UndoAnnotation);
+    on!(ui, dispatcher, on_clear_annotations, || Action::ClearAnnotations);
+    on!(ui, dispatcher, on_export_annotated, || Action::ExportAnnotated);
+    on!(ui, dispatcher, on_view_zoom_changed, |zoom: f32| Action::SetViewZoom(zoom));
+    on!(ui, dispatcher, on_image_hovered, |u: f32, v: f32| Action::HoverPixel(u, v));
+    on!(ui, dispatcher, on_tonemap_changed, |mode: SharedString, radius_percent: f32, strength: f32| {
+        Action::SetTonemap { mode: image_processing::TonemapMode::from_label(&mode), radius: radius_percent / 100.0, strength }
+    });
+    on!(ui, dispatcher, on_vector_display_changed, |max_magnitude: f32, arrows: bool| {
+        Action::SetVectorDisplay { max_magnitude, arrows }
+    });
+    on!(ui, dispatcher, on_relight_changed, |enabled: bool, azimuth: f32, elevation: f32| {
+        Action::SetRelight { enabled, azimuth, elevation }
+    });
+    on!(ui, dispatcher, on_env_view_changed, |mode: SharedString, yaw: f32, pitch: f32, fov: f32| {
+        Action::SetEnvView(env_map::EnvView::from_label(&mode, yaw, pitch, fov))
+    });
+    on!(ui, dispatcher, on_stereo_mode_changed, |mode: SharedString| Action::SetStereoMode(stereo::StereoMode::from_label(&mode)));
+    on!(ui, dispatcher, on_stereo_toggle_view, || Action::ToggleStereoView);
+    on!(ui, dispatcher, on_sharpen_changed, |enabled: bool, amount: f32, radius: f32, in_exports: bool| {
+        Action::SetSharpen { enabled, amount, radius, in_exports }
+    });
+    on!(ui, dispatcher, on_bloom_changed, |enabled: bool, threshold: f32, intensity: f32, display_only: bool| {
+        Action::SetBloom { enabled, threshold, intensity, display_only }
     });
+    on!(ui, dispatcher, on_focus_band_changed, |enabled: bool, near: f32, far: f32| Action::SetFocusBand { enabled, near, far });
+    on!(ui, dispatcher, on_input_color_space_changed, |label: SharedString| {
+        Action::SetInputColorSpace(image_processing::InputColorSpace::from_label(&label))
+    });
+    on!(ui, dispatcher, on_pipeline_stage_toggled, |index: i32, enabled: bool| {
+        Action::SetPipelineStage { index: index.max(0) as usize, enabled }
+    });
+    on!(ui, dispatcher, on_white_balance_from_sample, || Action::WhiteBalanceFromSample);
+    on!(ui, dispatcher, on_white_balance_reset, || Action::ResetWhiteBalance);
+    on!(ui, dispatcher, on_middle_gray_pivot_changed, |pivot: f32| Action::SetMiddleGray(pivot));
+    on!(ui, dispatcher, on_grayscale_mode_changed, |mode: SharedString| {
+        Action::SetGrayscaleMode(image_processing::GrayscaleMode::from_label(&mode))
+    });
+    on!(ui, dispatcher, on_false_color_changed, |enabled: bool| Action::SetFalseColor(enabled));
+    on!(ui, dispatcher, on_gamut_warning_changed, |label: SharedString| {
+        Action::SetGamutWarning(image_processing::GamutWarning::from_label(&label))
+    });
+    on!(ui, dispatcher, on_display_transform_changed, |quarter_turns: i32, flip_h: bool, flip_v: bool| {
+        Action::SetDisplayTransform(image_processing::DisplayTransform {
+            quarter_turns: quarter_turns.rem_euclid(4) as u8,
+            flip_h,
+            flip_v,
+        })
+    });
+    on!(ui, dispatcher, on_aov_remap_changed, |gain: f32, offset: f32, abs: bool| {
+        Action::SetAovRemap(image_processing::ChannelRemap { gain, offset, abs })
+    });
+
+    on!(ui, dispatcher, on_set_reference, || Action::SetReference);
+    on!(ui, dispatcher, on_clear_reference, || Action::ClearReference);
+    on!(ui, dispatcher, on_compare_mode_changed, |mode: SharedString| Action::SetCompareMode(compare::DiffMode::from_label(&mode)));
+    on!(ui, dispatcher, on_compare_tolerance_changed, |tolerance: f32| Action::SetCompareTolerance(tolerance));
+
+    on!(ui, dispatcher, on_sample_color, |u0: f32, v0: f32, u1: f32, v1: f32| Action::SampleColor(u0, v0, u1, v1));
+    on!(ui, dispatcher, on_clear_swatches, || Action::ClearSwatches);
+    on!(ui, dispatcher, on_open_point_cloud, || Action::OpenPointCloud);
+    on!(ui, dispatcher, on_orbit_point_cloud, |yaw: f32, pitch: f32, zoom: f32| Action::OrbitPointCloud { yaw, pitch, zoom });
+
+    on!(ui, dispatcher, on_layer_tree_clicked, |node: LayerNode| Action::SelectLayerNode(node));
+    on!(ui, dispatcher, on_layer_node_toggled, |id: i32| Action::ToggleLayerNode(id));
+    on!(ui, dispatcher, on_layer_grouping_changed, |_grouped: bool| Action::RegroupLayers);
+    on!(ui, dispatcher, on_lighting_only_changed, |lighting_only: bool| Action::SetLightingOnly(lighting_only));
+}
+
+fn setup_panel_callbacks(ui: &AppWindow, dispatcher: &Rc<Dispatcher>) {
+    on!(ui, dispatcher, on_choose_working_folder, || Action::ChooseWorkingFolder);
+    on!(ui, dispatcher, on_folder_selected, |path_str: SharedString| Action::OpenFolder(PathBuf::from(path_str.as_str())));
+    on!(ui, dispatcher, on_open_thumbnail, |path_str: SharedString| Action::OpenThumbnail(PathBuf::from(path_str.as_str())));
+    on!(ui, dispatcher, on_step_thumbnail, |delta: i32| Action::StepThumbnail(delta as i64));
+    on!(ui, dispatcher, on_clear_thumb_selection, || Action::ClearThumbnailSelection);
+    on!(ui, dispatcher, on_thumbnail_context_menu, |path: SharedString| Action::ThumbnailContextMenu(PathBuf::from(path.as_str())));
+    on!(ui, dispatcher, on_open_sequence_rename, || Action::OpenSequenceRename);
+    on!(ui, dispatcher, on_preview_sequence_rename, || Action::PreviewSequenceRename);
+    on!(ui, dispatcher, on_apply_sequence_rename, || Action::ApplySequenceRename);
+    on!(ui, dispatcher, on_undo_sequence_rename, || Action::UndoSequenceRename);
+    on!(ui, dispatcher, on_browse_rename_destination, || Action::BrowseRenameDestination);
+    on!(ui, dispatcher, on_trash_selected_thumbnails, || Action::TrashSelectedThumbnails);
+    on!(ui, dispatcher, on_thumbnail_hovered, |path_str: SharedString| Action::ThumbnailHovered(PathBuf::from(path_str.as_str())));
 }
 
 fn setup_ui_callbacks(
     ui: &AppWindow,
     image_cache: ImageCacheType,
     current_file_path: CurrentFilePathType,
-) {
+    context: Arc<app_context::AppContext>,
+) -> Rc<Dispatcher> {
     let console_model: Rc<VecModel<SharedString>> = Rc::new(VecModel::from(vec![]));
-    ui.set_console_text(SharedString::from(""));
+    ui.set_console_lines(slint::ModelRc::from(console_model.clone()));
+
+    console::start_log_pump(console_model.clone());
 
-    setup_menu_callbacks(ui, current_file_path.clone(), image_cache.clone(), console_model.clone());
-    setup_image_control_callbacks(ui, image_cache.clone(), current_file_path.clone(), console_model.clone());
-    setup_panel_callbacks(ui, current_file_path.clone(), image_cache.clone(), console_model.clone());
-}
\ No newline at end of file
+    let dispatcher = Dispatcher::new(ui, image_cache, current_file_path, console_model, context);
+    setup_menu_callbacks(ui, &dispatcher);
+    setup_image_control_callbacks(ui, &dispatcher);
+    setup_panel_callbacks(ui, &dispatcher);
+    remote::start_if_enabled(&dispatcher);
+    dispatcher
+}
@@ -0,0 +1,183 @@
+// Odporność parsowania metadanych na nietypowe pliki: dowolne nazwy kanałów (unicode, wiele kropek,
+// puste segmenty, duplikaty) i uszkodzone bajty nagłówka. Każde wejście przechodzi przez wszystkie
+// ścieżki, którymi nagłówki trafiają do UI – podział nazw, drzewo warstw, klasyfikację AOV, szybki
+// skan i panel metadanych – a `check_headers` sprawdza, że wyniki są ze sobą spójne.
+// Testy właściwościowe: `cargo test metadata_fuzz`; fuzzing libFuzzer: funkcja "fuzz" (README).
+
+use std::io::Cursor;
+use std::path::Path;
+use ::exr::meta::attribute::{ChannelDescription, SampleType, Text};
+use ::exr::meta::header::Header;
+use crate::channel_classification;
+use crate::image_cache::{channel_alias_to_short, layers_info_from_headers};
+use crate::io::fast_exr_metadata;
+use crate::utils::split_layer_and_short;
+
+/// Część pliku: atrybut `name` (opcjonalny) i pełne nazwy kanałów jako surowe bajty
+pub type PartSpec = (Option<Vec<u8>>, Vec<Vec<u8>>);
+
+/// Nagłówki z nazwami przepisanymi bajt po bajcie, bez walidacji – jak przy odczycie pliku
+pub fn headers_from_spec(parts: &[PartSpec]) -> Vec<Header> {
+    parts.iter()
+        .map(|(name, channels)| {
+            let list: Vec<ChannelDescription> = channels.iter()
+                .map(|c| ChannelDescription::new(Text::from_slice_unchecked(c), SampleType::F16, false))
+                .collect();
+            let mut header = Header::new(Text::from("part"), (4, 4), list.into());
+            header.own_attributes.layer_name = name.as_deref().map(Text::from_slice_unchecked);
+            header
+        })
+        .collect()
+}
+
+/// Przepuszcza nagłówki przez ścieżki metadanych i sprawdza niezmienniki (panika = błąd)
+pub fn check_headers(headers: &[Header]) {
+    let channel_count: usize = headers.iter().map(|h| h.channels.list.len()).sum();
+
+    for header in headers {
+        let base = header.own_attributes.layer_name.as_ref().map(|t| t.to_string());
+        for channel in &header.channels.list {
+            let full = channel.name.to_string();
+            let (layer, short) = split_layer_and_short(&full, base.as_deref());
+            assert!(!short.contains('.') && full.ends_with(&short), "bad short name {:?} of {:?}", short, full);
+            match &base {
+                Some(base) => assert_eq!(&layer, base),
+                None if full.contains('.') => assert_eq!(format!("{}.{}", layer, short), full),
+                None => assert!(layer.is_empty()),
+            }
+            let kind = channel_classification::classify(&layer, &short);
+            let _ = channel_classification::preview_mode(kind, false);
+            let _ = channel_alias_to_short(&short);
+        }
+    }
+
+    // Drzewo warstw: każdy kanał dokładnie raz, nazwy warstw bez powtórzeń
+    let layers = layers_info_from_headers(headers);
+    assert_eq!(layers.iter().map(|l| l.channels.len()).sum::<usize>(), channel_count);
+    let mut names: Vec<&str> = layers.iter().map(|l| l.name.as_str()).collect();
+    names.sort_unstable();
+    names.dedup();
+    assert_eq!(names.len(), layers.len(), "duplicate layers in tree");
+    for layer in &layers {
+        assert!(!layer.channels.is_empty(), "empty layer {:?}", layer.name);
+        let kind = channel_classification::classify(&layer.name, "");
+        let _ = channel_classification::tree_style(kind);
+        let _ = channel_classification::preview_mode(kind, true);
+    }
+
+    // Szybki skan (miniatury, podpowiedzi) liczy te same warstwy co drzewo
+    let fast = fast_exr_metadata::from_headers(headers);
+    assert_eq!(fast.layer_count, layers.len());
+    assert_eq!(fast.channel_count, channel_count);
+    assert_eq!(fast.part_count, headers.len());
+    let _ = fast.badge();
+
+    // Panel metadanych: jedna pozycja na część
+    let meta = crate::exr_metadata::group_headers(Path::new("fuzz.exr"), 0, headers);
+    assert_eq!(meta.layers.len(), headers.len());
+    let _ = crate::exr_metadata::build_ui_lines(&meta);
+}
+
+/// Surowe bajty jako początek pliku; błąd odczytu jest poprawnym wynikiem, panika nie
+pub fn check_bytes(data: &[u8]) {
+    if let Ok(headers) = crate::deep_exr::read_headers_from(Cursor::new(data)) {
+        check_headers(&headers);
+    }
+}
+
+/// Wejście libFuzzera: pierwszy bajt wybiera surowy nagłówek albo części zbudowane z reszty danych
+#[cfg(feature = "fuzz")]
+pub fn fuzz_one(data: &[u8]) {
+    use libfuzzer_sys::arbitrary::Unstructured;
+    match data.split_first() {
+        Some((selector, rest)) if selector % 2 == 0 => check_bytes(rest),
+        Some((_, rest)) => {
+            if let Ok(parts) = Unstructured::new(rest).arbitrary::<Vec<PartSpec>>() {
+                check_headers(&headers_from_spec(&parts));
+            }
+        }
+        None => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use proptest::prelude::*;
+    use super::*;
+
+    /// Magiczna liczba EXR i wersja 2; flagi wersji (kafelki, długie nazwy, deep, wiele części) osobno
+    const MAGIC_AND_VERSION: [u8; 5] = [0x76, 0x2f, 0x31, 0x01, 2];
+
+    /// Nazwy kanałów: typowe, z wieloma kropkami i pustymi segmentami, dowolny unicode, surowe bajty
+    fn channel_name() -> impl Strategy<Value = Vec<u8>> {
+        prop_oneof![
+            "[RGBAZ]|[a-z]{1,8}\\.[RGBA]".prop_map(String::into_bytes),
+            "([A-Za-z_]{0,4}\\.){0,8}[A-Za-z]{0,3}".prop_map(String::into_bytes),
+            "\\PC{0,16}".prop_map(String::into_bytes),
+            prop::collection::vec(any::<u8>(), 0..24),
+        ]
+    }
+
+    /// Kanały części; początek listy powtórzony na końcu (duplikaty nazw)
+    fn channel_list() -> impl Strategy<Value = Vec<Vec<u8>>> {
+        (prop::collection::vec(channel_name(), 0..12), 0usize..4).prop_map(|(mut names, duplicates)| {
+            let repeated: Vec<Vec<u8>> = names.iter().take(duplicates).cloned().collect();
+            names.extend(repeated);
+            names
+        })
+    }
+
+    fn part() -> impl Strategy<Value = PartSpec> {
+        let layer_name = prop_oneof![
+            Just(Vec::new()),
+            "[a-z]{1,6}(\\.[a-z]{0,6}){0,3}".prop_map(String::into_bytes),
+            "\\PC{0,12}".prop_map(String::into_bytes),
+        ];
+        (proptest::option::of(layer_name), channel_list())
+    }
+
+    proptest! {
+        #[test]
+        fn split_layer_and_short_keeps_name(full in "\\PC{0,24}", base in proptest::option::of("\\PC{0,8}")) {
+            let (layer, short) = split_layer_and_short(&full, base.as_deref());
+            prop_assert!(!short.contains('.'));
+            prop_assert!(full.ends_with(&short));
+            match base {
+                Some(base) => prop_assert_eq!(layer, base),
+                None if full.contains('.') => prop_assert_eq!(format!("{}.{}", layer, short), full),
+                None => prop_assert!(layer.is_empty()),
+            }
+        }
+
+        #[test]
+        fn adversarial_channel_names_never_panic(parts in prop::collection::vec(part(), 0..5)) {
+            check_headers(&headers_from_spec(&parts));
+        }
+
+        #[test]
+        fn corrupt_header_bytes_never_panic(
+            flags in prop::sample::select(vec![0u8, 0x02, 0x04, 0x08, 0x10, 0x18]),
+            body in prop::collection::vec(any::<u8>(), 0..512),
+        ) {
+            let mut data = MAGIC_AND_VERSION.to_vec();
+            data.extend([flags, 0, 0]);
+            data.extend(body);
+            check_bytes(&data);
+        }
+    }
+
+    #[test]
+    fn known_edge_cases() {
+        let names: Vec<Vec<u8>> = ["", ".", "..", "R.", ".R", "a..b", "a.b.c.d.e.f.g.R", "R", "R", "ß.Łódź.A", "🎨.beauty.G", "diffuse.R", "diffuse.R"]
+            .iter()
+            .map(|n| n.as_bytes().to_vec())
+            .chain([vec![0xff, b'.', 0xfe], vec![0]])
+            .collect();
+        check_headers(&headers_from_spec(&[
+            (None, names.clone()),
+            (Some(Vec::new()), names.clone()),
+            (Some(b"a..".to_vec()), names),
+            (None, Vec::new()),
+        ]));
+    }
+}
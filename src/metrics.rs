@@ -0,0 +1,229 @@
+// Metryki porównania obrazów (PSNR, SSIM, MAE per kanał) – używane przez tryb porównania w UI i tryb CLI
+
+use std::path::PathBuf;
+use rayon::prelude::*;
+use thiserror::Error;
+use crate::cancel::CancelToken;
+use crate::image_cache::ImageCache;
+use crate::image_processing::{ProcessingGraph, ToneParams};
+use crate::progress::NoopProgress;
+use crate::utils::error_handling::ExrError;
+
+type Pixel = (f32, f32, f32, f32);
+
+/// Wynik porównania dwóch obrazów tej samej wielkości
+#[derive(Clone, Copy, Debug)]
+pub struct ImageMetrics {
+    /// PSNR [dB] na RGB po tone mappingu (`display_value`); inf dla identycznych obrazów
+    pub psnr: f64,
+    /// Średnie SSIM luminancji po tone mappingu (okna 8×8, krok 4)
+    pub ssim: f64,
+    /// Średni błąd bezwzględny per kanał R, G, B, A (wartości liniowe, bez obcinania)
+    pub mae: [f64; 4],
+}
+
+impl ImageMetrics {
+    pub fn summary(&self) -> String {
+        let psnr = if self.psnr.is_finite() { format!("{:.2} dB", self.psnr) } else { "∞".to_string() };
+        format!(
+            "PSNR: {} | SSIM: {:.4} (tone-mapped) | MAE R/G/B/A: {:.5}/{:.5}/{:.5}/{:.5} (linear)",
+            psnr, self.ssim, self.mae[0], self.mae[1], self.mae[2], self.mae[3]
+        )
+    }
+}
+
+/// Statystyki obszaru per kanał R, G, B, A (surowe wartości liniowe, bez NaN/Inf)
+#[derive(Clone, Copy, Debug)]
+pub struct RegionStats {
+    pub min: [f64; 4],
+    pub max: [f64; 4],
+    pub mean: [f64; 4],
+    pub median: [f64; 4],
+    pub pixels: usize,
+    /// Wartości NaN/Inf pominięte w statystykach
+    pub non_finite: usize,
+}
+
+impl RegionStats {
+    /// Linie do panelu: liczba pikseli, nagłówek kolumn R G B A i wiersz na każdą statystykę
+    pub fn lines(&self) -> Vec<String> {
+        let row = |name: &str, v: &[f64; 4]| format!("{:<6} {:>7.4} {:>7.4} {:>7.4} {:>7.4}", name, v[0], v[1], v[2], v[3]);
+        let mut lines = vec![format!("{} px{}", self.pixels, if self.non_finite > 0 { format!(", {} NaN/Inf", self.non_finite) } else { String::new() })];
+        lines.push(format!("{:<6} {:>7} {:>7} {:>7} {:>7}", "", "R", "G", "B", "A"));
+        lines.push(row("min", &self.min));
+        lines.push(row("max", &self.max));
+        lines.push(row("mean", &self.mean));
+        lines.push(row("median", &self.median));
+        lines
+    }
+}
+
+/// Min/max/średnia/mediana każdego kanału; wartości nieskończone i NaN są pomijane
+pub fn region_stats(pixels: &[Pixel]) -> RegionStats {
+    let mut stats = RegionStats { min: [0.0; 4], max: [0.0; 4], mean: [0.0; 4], median: [0.0; 4], pixels: pixels.len(), non_finite: 0 };
+    for c in 0..4 {
+        let mut values: Vec<f32> = pixels.iter()
+            .map(|p| [p.0, p.1, p.2, p.3][c])
+            .filter(|v| v.is_finite())
+            .collect();
+        stats.non_finite = stats.non_finite.max(pixels.len() - values.len());
+        if values.is_empty() { continue; }
+        values.sort_unstable_by(f32::total_cmp);
+        let n = values.len();
+        stats.min[c] = values[0] as f64;
+        stats.max[c] = values[n - 1] as f64;
+        stats.mean[c] = values.iter().map(|&v| v as f64).sum::<f64>() / n as f64;
+        stats.median[c] = if n % 2 == 1 { values[n / 2] as f64 } else { (values[n / 2 - 1] as f64 + values[n / 2] as f64) / 2.0 };
+    }
+    stats
+}
+
+#[derive(Debug, Error)]
+pub enum MetricsError {
+    #[error("image size mismatch: {0}x{1} vs {2}x{3}")]
+    SizeMismatch(u32, u32, u32, u32),
+    #[error(transparent)]
+    Load(#[from] ExrError),
+}
+
+/// Porównuje bieżące warstwy dwóch obrazów w pamięci
+pub fn compare_caches(a: &ImageCache, b: &ImageCache) -> Result<ImageMetrics, MetricsError> {
+    compare_pixels(&a.raw_pixels, &b.raw_pixels, a.width, a.height, b.width, b.height)
+}
+
+/// Porównanie bez UI: wczytuje domyślną warstwę obu plików i liczy metryki
+pub fn compare_files(a: &PathBuf, b: &PathBuf) -> Result<ImageMetrics, MetricsError> {
+    let cancel = CancelToken::new();
+    let first = ImageCache::new(a, &cancel, &NoopProgress)?;
+    let second = ImageCache::new(b, &cancel, &NoopProgress)?;
+    compare_caches(&first, &second)
+}
+
+pub fn compare_pixels(a: &[Pixel], b: &[Pixel], aw: u32, ah: u32, bw: u32, bh: u32) -> Result<ImageMetrics, MetricsError> {
+    if aw != bw || ah != bh || a.len() != b.len() {
+        return Err(MetricsError::SizeMismatch(aw, ah, bw, bh));
+    }
+    Ok(ImageMetrics { psnr: psnr(a, b), ssim: ssim(a, b, aw as usize, ah as usize), mae: mae(a, b) })
+}
+
+#[inline]
+fn finite(v: f32) -> f64 {
+    if v.is_finite() { v as f64 } else { 0.0 }
+}
+
+/// Stały widok, w którym liczone są PSNR i SSIM: ACES i gamma 2.2 przy zerowej ekspozycji.
+/// Obcięcie wartości sceny do [0, 1] ukrywałoby różnice w światłach HDR; krzywa je kompresuje,
+/// ale zachowuje, a wynik nie zależy od bieżących ustawień podglądu.
+fn display_params() -> ToneParams {
+    ProcessingGraph::standard(0.0, 2.2).tone_params()
+}
+
+#[inline]
+fn display(params: &ToneParams, v: f32) -> f64 {
+    let v = if v.is_finite() { v.max(0.0) } else { 0.0 };
+    params.gamma.apply(params.display_value(v)) as f64
+}
+
+#[inline]
+fn luma(params: &ToneParams, p: &Pixel) -> f64 {
+    0.2126 * display(params, p.0) + 0.7152 * display(params, p.1) + 0.0722 * display(params, p.2)
+}
+
+pub fn psnr(a: &[Pixel], b: &[Pixel]) -> f64 {
+    if a.is_empty() { return f64::INFINITY; }
+    let params = display_params();
+    let d = |v: f32| display(&params, v);
+    let sum_sq: f64 = a
+        .par_iter()
+        .zip(b.par_iter())
+        .map(|(x, y)| (d(x.0) - d(y.0)).powi(2) + (d(x.1) - d(y.1)).powi(2) + (d(x.2) - d(y.2)).powi(2))
+        .sum();
+    let mse = sum_sq / (a.len() as f64 * 3.0);
+    if mse > 0.0 { 10.0 * (1.0 / mse).log10() } else { f64::INFINITY }
+}
+
+pub fn mae(a: &[Pixel], b: &[Pixel]) -> [f64; 4] {
+    if a.is_empty() { return [0.0; 4]; }
+    let sums = a
+        .par_iter()
+        .zip(b.par_iter())
+        .map(|(x, y)| {
+            [
+                (finite(x.0) - finite(y.0)).abs(),
+                (finite(x.1) - finite(y.1)).abs(),
+                (finite(x.2) - finite(y.2)).abs(),
+                (finite(x.3) - finite(y.3)).abs(),
+            ]
+        })
+        .reduce(|| [0.0; 4], |s, v| [s[0] + v[0], s[1] + v[1], s[2] + v[2], s[3] + v[3]]);
+    let n = a.len() as f64;
+    sums.map(|s| s / n)
+}
+
+/// SSIM luminancji liczone w oknach 8×8 przesuwanych co 4 piksele (mniejszy obraz = jedno okno)
+pub fn ssim(a: &[Pixel], b: &[Pixel], width: usize, height: usize) -> f64 {
+    const WINDOW: usize = 8;
+    const STEP: usize = 4;
+    const C1: f64 = 0.01 * 0.01;
+    const C2: f64 = 0.03 * 0.03;
+    if a.is_empty() || width == 0 || height == 0 { return 1.0; }
+
+    let params = display_params();
+    let win_w = WINDOW.min(width);
+    let win_h = WINDOW.min(height);
+    let rows: Vec<usize> = (0..=height - win_h).step_by(STEP).collect();
+    let (sum, count) = rows
+        .par_iter()
+        .map(|&y0| {
+            let mut sum = 0.0;
+            let mut count = 0usize;
+            for x0 in (0..=width - win_w).step_by(STEP) {
+                let (mut sa, mut sb, mut saa, mut sbb, mut sab) = (0.0, 0.0, 0.0, 0.0, 0.0);
+                for y in y0..y0 + win_h {
+                    let row = y * width;
+                    for x in x0..x0 + win_w {
+                        let va = luma(&params, &a[row + x]);
+                        let vb = luma(&params, &b[row + x]);
+                        sa += va;
+                        sb += vb;
+                        saa += va * va;
+                        sbb += vb * vb;
+                        sab += va * vb;
+                    }
+                }
+                let n = (win_w * win_h) as f64;
+                let (ma, mb) = (sa / n, sb / n);
+                let var_a = (saa / n - ma * ma).max(0.0);
+                let var_b = (sbb / n - mb * mb).max(0.0);
+                let cov = sab / n - ma * mb;
+                sum += ((2.0 * ma * mb + C1) * (2.0 * cov + C2)) / ((ma * ma + mb * mb + C1) * (var_a + var_b + C2));
+                count += 1;
+            }
+            (sum, count)
+        })
+        .reduce(|| (0.0, 0), |x, y| (x.0 + y.0, x.1 + y.1));
+
+    if count == 0 { 1.0 } else { sum / count as f64 }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn metrics_of_identical_and_offset_images() {
+        let (width, height) = (16, 16);
+        let a: Vec<Pixel> = (0..width * height).map(|i| (i as f32 / 64.0, 0.5, 0.25, 1.0)).collect();
+        let same = compare_pixels(&a, &a, width, height, width, height).unwrap();
+        assert!(same.psnr.is_infinite() && (same.ssim - 1.0).abs() < 1e-9 && same.mae == [0.0; 4]);
+
+        // Jednolite 2.0 i 4.0 – powyżej 1.0, więc obcięte dawałyby obrazy identyczne.
+        // ACES: 2.0 → 0.91486, 4.0 → 0.97342; gamma 2.2 (v^0.75): 0.93544 i 0.98000.
+        // PSNR = -20·log10(0.04456) = 27.02 dB; SSIM okna o zerowej wariancji = (2ab + C1) / (a² + b² + C1)
+        let (dim, bright) = (vec![(2.0, 2.0, 2.0, 1.0); 256], vec![(4.0, 4.0, 4.0, 0.5); 256]);
+        let m = compare_pixels(&dim, &bright, width, height, width, height).unwrap();
+        assert!((m.psnr - 27.021).abs() < 1e-2, "{}", m.psnr);
+        assert!((m.ssim - 0.99892).abs() < 1e-4, "{}", m.ssim);
+        assert_eq!(m.mae, [2.0, 2.0, 2.0, 0.5]);
+    }
+}
@@ -0,0 +1,429 @@
+// Zapytania zależne od systemu operacyjnego (środowisko wyświetlania), integracja z powłoką
+// i kanał między uruchomieniami aplikacji (tryb jednej instancji)
+
+use std::io::{self, Read, Write};
+use std::path::PathBuf;
+
+/// Identyfikator typu pliku EXRuster w rejestrze (HKCU\Software\Classes)
+#[cfg(windows)]
+const PROG_ID: &str = "EXRuster.exr";
+/// Polecenie menu kontekstowego folderu i tła folderu w Eksploratorze
+#[cfg(windows)]
+const FOLDER_MENU_KEYS: [&str; 2] = [r"Directory\shell\EXRuster", r"Directory\Background\shell\EXRuster"];
+
+/// Ścieżka profilu ICC przypisanego monitorowi, na którym leży punkt (x, y) ekranu (piksele fizyczne).
+/// Windows: zarządzanie kolorami GDI (GetICMProfileW na kontekście tego monitora).
+#[cfg(windows)]
+pub fn monitor_icc_profile(x: i32, y: i32) -> Option<PathBuf> {
+    use std::ffi::OsString;
+    use std::os::windows::ffi::OsStringExt;
+    use windows_sys::Win32::Foundation::POINT;
+    use windows_sys::Win32::Graphics::Gdi::{CreateDCW, DeleteDC, GetMonitorInfoW, MonitorFromPoint, MONITORINFO, MONITORINFOEXW, MONITOR_DEFAULTTONEAREST};
+    use windows_sys::Win32::UI::ColorSystem::GetICMProfileW;
+
+    // SAFETY: struktury zainicjowane zgodnie z dokumentacją (cbSize), bufory żyją do końca wywołań,
+    // a kontekst urządzenia jest zwalniany przed powrotem
+    unsafe {
+        let monitor = MonitorFromPoint(POINT { x, y }, MONITOR_DEFAULTTONEAREST);
+        let mut info = MONITORINFOEXW::default();
+        info.monitorInfo.cbSize = std::mem::size_of::<MONITORINFOEXW>() as u32;
+        if GetMonitorInfoW(monitor, &mut info as *mut MONITORINFOEXW as *mut MONITORINFO) == 0 {
+            return None;
+        }
+        let dc = CreateDCW(info.szDevice.as_ptr(), info.szDevice.as_ptr(), std::ptr::null(), std::ptr::null());
+        if dc.is_null() {
+            return None;
+        }
+        let mut len: u32 = 260;
+        let mut buffer = vec![0u16; len as usize];
+        let mut ok = GetICMProfileW(dc, &mut len, buffer.as_mut_ptr());
+        if ok == 0 && len as usize > buffer.len() {
+            // Za krótki bufor: funkcja zwraca potrzebną długość
+            buffer = vec![0u16; len as usize];
+            ok = GetICMProfileW(dc, &mut len, buffer.as_mut_ptr());
+        }
+        DeleteDC(dc);
+        if ok == 0 {
+            return None;
+        }
+        let end = buffer.iter().position(|&c| c == 0).unwrap_or(buffer.len());
+        Some(PathBuf::from(OsString::from_wide(&buffer[..end])))
+    }
+}
+
+/// Poza Windows profil wskazuje się ustawieniem EXRUSTER_ICC_PROFILE
+#[cfg(not(windows))]
+pub fn monitor_icc_profile(_x: i32, _y: i32) -> Option<PathBuf> {
+    None
+}
+
+/// Dodaje EXRuster do listy "Otwórz za pomocą" plików .exr i polecenie "Browse folder in EXRuster"
+/// w menu kontekstowym folderów. Wpisy trafiają do HKCU, więc nie są potrzebne uprawnienia administratora;
+/// domyślnego programu dla .exr nie zmieniamy (Windows pozwala to zrobić tylko użytkownikowi).
+#[cfg(windows)]
+pub fn register_file_association() -> io::Result<()> {
+    let exe = std::env::current_exe()?;
+    let exe_name = exe.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_else(|| "EXRuster.exe".into());
+    let open = format!("\"{}\" \"%1\"", exe.display());
+    let icon = format!("\"{}\",0", exe.display());
+
+    let mut values: Vec<(String, &str, String)> = vec![
+        (PROG_ID.into(), "", "OpenEXR image".into()),
+        (format!(r"{}\DefaultIcon", PROG_ID), "", icon.clone()),
+        (format!(r"{}\shell\open\command", PROG_ID), "", open.clone()),
+        (r".exr\OpenWithProgids".into(), PROG_ID, String::new()),
+        (format!(r"Applications\{}\shell\open\command", exe_name), "", open),
+        (format!(r"Applications\{}\SupportedTypes", exe_name), ".exr", String::new()),
+    ];
+    // Tło folderu przekazuje katalog jako %V, sam folder jako %1
+    for (key, argument) in FOLDER_MENU_KEYS.iter().zip(["%1", "%V"]) {
+        values.push((key.to_string(), "", "Browse folder in EXRuster".into()));
+        values.push((key.to_string(), "Icon", icon.clone()));
+        values.push((format!(r"{}\command", key), "", format!("\"{}\" \"{}\"", exe.display(), argument)));
+    }
+    for (key, name, data) in &values {
+        registry::set_string(key, name, data)?;
+    }
+    registry::notify_association_changed();
+    Ok(())
+}
+
+/// Usuwa wpisy dodane przez `register_file_association` (brakujące wpisy nie są błędem)
+#[cfg(windows)]
+pub fn unregister_file_association() -> io::Result<()> {
+    let exe = std::env::current_exe()?;
+    let exe_name = exe.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_else(|| "EXRuster.exe".into());
+    registry::delete_value(r".exr\OpenWithProgids", PROG_ID)?;
+    registry::delete_tree(PROG_ID)?;
+    registry::delete_tree(&format!(r"Applications\{}", exe_name))?;
+    for key in FOLDER_MENU_KEYS {
+        registry::delete_tree(key)?;
+    }
+    registry::notify_association_changed();
+    Ok(())
+}
+
+#[cfg(not(windows))]
+pub fn register_file_association() -> io::Result<()> {
+    Err(io::Error::new(io::ErrorKind::Unsupported, "file association is only supported on Windows"))
+}
+
+#[cfg(not(windows))]
+pub fn unregister_file_association() -> io::Result<()> {
+    register_file_association()
+}
+
+/// Dodaje plik do ostatnio używanych dokumentów aplikacji: Windows pokazuje je w Jump List ikony na pasku
+/// zadań (sekcja Recent, z której użytkownik może przypinać pliki). Wybranie pozycji uruchamia
+/// EXRuster z plikiem jako argumentem. Lista pojawia się dla typów zarejestrowanych przez `--register`.
+#[cfg(windows)]
+pub fn add_recent_file(path: &std::path::Path) {
+    use windows_sys::Win32::UI::Shell::{SHAddToRecentDocs, SHARD_PATHW};
+    let path = std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf());
+    let wide: Vec<u16> = path.as_os_str().to_string_lossy().encode_utf16().chain(Some(0)).collect();
+    // SAFETY: ścieżka zakończona zerem żyje do końca wywołania
+    unsafe { SHAddToRecentDocs(SHARD_PATHW as u32, wide.as_ptr().cast()) }
+}
+
+#[cfg(not(windows))]
+pub fn add_recent_file(_path: &std::path::Path) {}
+
+/// Obniża priorytet bieżącego wątku (pula ciężkich zadań), żeby eksport w tle nie zabierał czasu
+/// procesora aplikacjom na pierwszym planie. Windows: THREAD_PRIORITY_BELOW_NORMAL.
+#[cfg(windows)]
+pub fn lower_current_thread_priority() {
+    use windows_sys::Win32::System::Threading::{GetCurrentThread, SetThreadPriority, THREAD_PRIORITY_BELOW_NORMAL};
+    // SAFETY: pseudo-uchwyt bieżącego wątku nie wymaga zamykania
+    if unsafe { SetThreadPriority(GetCurrentThread(), THREAD_PRIORITY_BELOW_NORMAL) } == 0 {
+        tracing::warn!(target: "processing", "cannot lower worker thread priority: {}", io::Error::last_os_error());
+    }
+}
+
+/// Poza Windows priorytet wątków zostaje bez zmian
+#[cfg(not(windows))]
+pub fn lower_current_thread_priority() {}
+
+/// Przenosi pliki do kosza systemu (z możliwością przywrócenia), nie usuwa ich trwale.
+/// Windows: operacja powłoki z FOF_ALLOWUNDO, wszystkie pliki naraz. Plików, których kosz nie
+/// przyjmie (udział sieciowy, rozmiar ponad limit kosza), powłoka nie usuwa bez pytania
+/// (FOF_WANTNUKEWARNING); odmowa użytkownika kończy się błędem `Interrupted`.
+#[cfg(windows)]
+pub fn move_to_trash(paths: &[PathBuf]) -> io::Result<()> {
+    use windows_sys::Win32::UI::Shell::{SHFileOperationW, FOF_ALLOWUNDO, FOF_NOCONFIRMATION, FOF_NOERRORUI, FOF_SILENT, FOF_WANTNUKEWARNING, FO_DELETE, SHFILEOPSTRUCTW};
+    // Lista ścieżek rozdzielonych zerem i zakończona podwójnym zerem; powłoka wymaga ścieżek pełnych
+    let mut from: Vec<u16> = Vec::new();
+    for path in paths {
+        let path = std::path::absolute(path)?;
+        from.extend(path.as_os_str().to_string_lossy().encode_utf16().chain(Some(0)));
+    }
+    from.push(0);
+    let mut operation = SHFILEOPSTRUCTW {
+        wFunc: FO_DELETE,
+        pFrom: from.as_ptr(),
+        fFlags: (FOF_ALLOWUNDO | FOF_NOCONFIRMATION | FOF_NOERRORUI | FOF_SILENT | FOF_WANTNUKEWARNING) as u16,
+        ..Default::default()
+    };
+    // SAFETY: lista ścieżek żyje do końca wywołania, pozostałe pola są zerowe (bez okna i mapowań nazw)
+    let code = unsafe { SHFileOperationW(&mut operation) };
+    if code != 0 {
+        return Err(io::Error::other(format!("moving to the Recycle Bin failed (shell error 0x{:x})", code)));
+    }
+    if operation.fAnyOperationsAborted != 0 {
+        return Err(io::Error::new(io::ErrorKind::Interrupted, "moving to the Recycle Bin was aborted"));
+    }
+    Ok(())
+}
+
+/// macOS: Finder (przez AppleScript), więc pliki można przywrócić poleceniem "Put Back"
+#[cfg(target_os = "macos")]
+pub fn move_to_trash(paths: &[PathBuf]) -> io::Result<()> {
+    let files: Vec<String> = paths.iter()
+        .map(|p| std::path::absolute(p).map(|p| format!("POSIX file \"{}\"", p.display().to_string().replace('\\', "\\\\").replace('"', "\\\""))))
+        .collect::<io::Result<_>>()?;
+    let script = format!("tell application \"Finder\" to delete {{{}}}", files.join(", "));
+    let output = std::process::Command::new("osascript").arg("-e").arg(script).output()?;
+    if !output.status.success() {
+        return Err(io::Error::other(String::from_utf8_lossy(&output.stderr).trim().to_string()));
+    }
+    Ok(())
+}
+
+/// Pozostałe systemy uniksowe: kosz domowy wg specyfikacji freedesktop.org ($XDG_DATA_HOME/Trash),
+/// który pokazują menedżery plików. Plik z innego systemu plików jest kopiowany do kosza.
+#[cfg(all(unix, not(target_os = "macos")))]
+pub fn move_to_trash(paths: &[PathBuf]) -> io::Result<()> {
+    let data_home = std::env::var_os("XDG_DATA_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|h| PathBuf::from(h).join(".local/share")))
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no home directory for the trash"))?;
+    freedesktop_trash::move_to(&data_home.join("Trash"), paths)
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+mod freedesktop_trash {
+    use std::fs;
+    use std::io::{self, Write};
+    use std::path::{Path, PathBuf};
+
+    /// Ścieżka w pliku .trashinfo: bajty spoza znaków bezpiecznych w URI zakodowane jako %XX
+    fn encode_path(path: &Path) -> String {
+        use std::os::unix::ffi::OsStrExt;
+        path.as_os_str().as_bytes().iter()
+            .map(|&b| match b {
+                b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'/' | b'-' | b'_' | b'.' | b'~' => (b as char).to_string(),
+                _ => format!("%{:02X}", b),
+            })
+            .collect()
+    }
+
+    /// DeletionDate w czasie lokalnym, jak wymaga specyfikacji ("RRRR-MM-DDTGG:MM:SS"). Bez biblioteki
+    /// dat strefę czasową zna tylko polecenie `date`; gdy go brak – czas UTC.
+    fn deletion_date() -> String {
+        std::process::Command::new("date").arg("+%Y-%m-%dT%H:%M:%S").output().ok()
+            .filter(|output| output.status.success())
+            .map(|output| String::from_utf8_lossy(&output.stdout).trim().to_string())
+            .filter(|date| date.len() == 19)
+            .unwrap_or_else(|| {
+                let (date, time) = crate::utils::utc_date_time(std::time::SystemTime::now());
+                format!("{}T{}:00", date, time)
+            })
+    }
+
+    pub fn move_to(trash: &Path, paths: &[PathBuf]) -> io::Result<()> {
+        let (files, info) = (trash.join("files"), trash.join("info"));
+        fs::create_dir_all(&files)?;
+        fs::create_dir_all(&info)?;
+        let deleted = deletion_date();
+        for path in paths {
+            let path = std::path::absolute(path)?;
+            let name = path.file_name().ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, format!("not a file: {}", path.display())))?;
+            // Nazwa w koszu rezerwowana przez utworzenie pliku .trashinfo (create_new), przy kolizji " 2", " 3"...
+            let (mut n, mut entry) = (1, name.to_os_string());
+            let (mut record, info_path) = loop {
+                let info_path = info.join(format!("{}.trashinfo", entry.to_string_lossy()));
+                match fs::OpenOptions::new().write(true).create_new(true).open(&info_path) {
+                    Ok(file) if !files.join(&entry).exists() => break (file, info_path),
+                    Ok(_) => fs::remove_file(&info_path)?,
+                    Err(e) if e.kind() == io::ErrorKind::AlreadyExists => {}
+                    Err(e) => return Err(e),
+                }
+                n += 1;
+                entry = format!("{} {}", name.to_string_lossy(), n).into();
+            };
+            let moved = write!(record, "[Trash Info]\nPath={}\nDeletionDate={}\n", encode_path(&path), deleted)
+                .and_then(|_| match fs::rename(&path, files.join(&entry)) {
+                    Ok(()) => Ok(()),
+                    // Inny system plików: kopia do kosza domowego
+                    Err(_) if path.is_file() => fs::copy(&path, files.join(&entry)).and_then(|_| fs::remove_file(&path)),
+                    Err(e) => Err(e),
+                });
+            if let Err(e) = moved {
+                // Źródło zostało na miejscu: bez wpisu i częściowej kopii w koszu
+                let _ = fs::remove_file(files.join(&entry));
+                let _ = fs::remove_file(&info_path);
+                return Err(io::Error::new(e.kind(), format!("{}: {}", path.display(), e)));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Nasłuch pierwszej instancji na wiadomości od kolejnych uruchomień: nazwany potok użytkownika
+/// (Windows) albo gniazdo Unix w katalogu danych aplikacji
+pub struct InstanceListener {
+    #[cfg(windows)]
+    name: Vec<u16>,
+    /// Instancja potoku czekająca na następnego klienta (HANDLE jako liczba, żeby przejść do wątku)
+    #[cfg(windows)]
+    pending: isize,
+    #[cfg(unix)]
+    listener: std::os::unix::net::UnixListener,
+}
+
+/// Wysyła wiadomość działającej instancji; błąd = brak innej instancji
+pub fn send_to_instance(message: &str) -> io::Result<()> {
+    #[cfg(windows)]
+    let mut stream = std::fs::OpenOptions::new().write(true).open(instance_pipe_name())?;
+    #[cfg(unix)]
+    let mut stream = std::os::unix::net::UnixStream::connect(instance_socket_path())?;
+    stream.write_all(message.as_bytes())
+}
+
+#[cfg(windows)]
+fn instance_pipe_name() -> String {
+    format!(r"\\.\pipe\EXRuster-{}", std::env::var("USERNAME").unwrap_or_default())
+}
+
+#[cfg(unix)]
+fn instance_socket_path() -> PathBuf {
+    crate::session::app_data_dir().join("instance.sock")
+}
+
+#[cfg(windows)]
+impl InstanceListener {
+    /// Tworzy pierwszą instancję potoku; błąd, jeśli potok ma już inny proces
+    pub fn bind() -> io::Result<Self> {
+        let name: Vec<u16> = instance_pipe_name().encode_utf16().chain(Some(0)).collect();
+        let pending = Self::create_pipe(&name, true)?;
+        Ok(Self { name, pending })
+    }
+
+    /// Czeka na klienta; od razu przygotowuje kolejną instancję potoku
+    pub fn accept(&mut self) -> io::Result<impl Read> {
+        use std::os::windows::io::FromRawHandle;
+        use windows_sys::Win32::Foundation::{GetLastError, ERROR_PIPE_CONNECTED, HANDLE};
+        use windows_sys::Win32::System::Pipes::ConnectNamedPipe;
+
+        let handle = self.pending as HANDLE;
+        // SAFETY: uchwyt potoku należy do nas; File przejmuje go i zamyka po odczycie
+        let (file, connected) = unsafe {
+            let connected = ConnectNamedPipe(handle, std::ptr::null_mut()) != 0 || GetLastError() == ERROR_PIPE_CONNECTED;
+            (std::fs::File::from_raw_handle(handle), connected)
+        };
+        let error = io::Error::last_os_error();
+        self.pending = Self::create_pipe(&self.name, false)?;
+        if connected { Ok(file) } else { Err(error) }
+    }
+
+    fn create_pipe(name: &[u16], first: bool) -> io::Result<isize> {
+        use windows_sys::Win32::Foundation::INVALID_HANDLE_VALUE;
+        use windows_sys::Win32::Storage::FileSystem::{FILE_FLAG_FIRST_PIPE_INSTANCE, PIPE_ACCESS_INBOUND};
+        use windows_sys::Win32::System::Pipes::{
+            CreateNamedPipeW, PIPE_READMODE_BYTE, PIPE_REJECT_REMOTE_CLIENTS, PIPE_TYPE_BYTE, PIPE_UNLIMITED_INSTANCES, PIPE_WAIT,
+        };
+
+        let open_mode = PIPE_ACCESS_INBOUND | if first { FILE_FLAG_FIRST_PIPE_INSTANCE } else { 0 };
+        let pipe_mode = PIPE_TYPE_BYTE | PIPE_READMODE_BYTE | PIPE_WAIT | PIPE_REJECT_REMOTE_CLIENTS;
+        // SAFETY: nazwa zakończona zerem żyje do końca wywołania, domyślne zabezpieczenia (null)
+        let handle = unsafe { CreateNamedPipeW(name.as_ptr(), open_mode, pipe_mode, PIPE_UNLIMITED_INSTANCES, 0, 4096, 0, std::ptr::null()) };
+        if handle == INVALID_HANDLE_VALUE {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(handle as isize)
+    }
+}
+
+#[cfg(unix)]
+impl InstanceListener {
+    /// Gniazdo po poprzednim procesie usuwamy, ale tylko gdy nikt na nim nie słucha
+    pub fn bind() -> io::Result<Self> {
+        use std::os::unix::net::{UnixListener, UnixStream};
+        let path = instance_socket_path();
+        if UnixStream::connect(&path).is_ok() {
+            return Err(io::Error::new(io::ErrorKind::AddrInUse, "another instance is listening"));
+        }
+        let _ = std::fs::remove_file(&path);
+        if let Some(dir) = path.parent() { std::fs::create_dir_all(dir)?; }
+        Ok(Self { listener: UnixListener::bind(&path)? })
+    }
+
+    pub fn accept(&mut self) -> io::Result<impl Read> {
+        self.listener.accept().map(|(stream, _)| stream)
+    }
+}
+
+/// Klucze użytkownika w HKCU\Software\Classes
+#[cfg(windows)]
+mod registry {
+    use std::io;
+    use windows_sys::Win32::Foundation::{ERROR_FILE_NOT_FOUND, ERROR_SUCCESS, WIN32_ERROR};
+    use windows_sys::Win32::System::Registry::{
+        RegCloseKey, RegCreateKeyExW, RegDeleteKeyValueW, RegDeleteTreeW, RegSetValueExW, HKEY, HKEY_CURRENT_USER,
+        KEY_WRITE, REG_OPTION_NON_VOLATILE, REG_SZ,
+    };
+    use windows_sys::Win32::UI::Shell::{SHChangeNotify, SHCNE_ASSOCCHANGED, SHCNF_IDLIST};
+
+    const CLASSES: &str = r"Software\Classes";
+
+    fn wide(text: &str) -> Vec<u16> {
+        text.encode_utf16().chain(Some(0)).collect()
+    }
+
+    fn check(code: WIN32_ERROR) -> io::Result<()> {
+        match code {
+            ERROR_SUCCESS => Ok(()),
+            code => Err(io::Error::from_raw_os_error(code as i32)),
+        }
+    }
+
+    /// Wartość tekstowa; pusta nazwa = wartość domyślna klucza (klucz tworzony w razie potrzeby)
+    pub fn set_string(key: &str, name: &str, data: &str) -> io::Result<()> {
+        let (key, name, data) = (wide(&format!(r"{}\{}", CLASSES, key)), wide(name), wide(data));
+        let mut hkey: HKEY = std::ptr::null_mut();
+        // SAFETY: napisy zakończone zerem żyją do końca wywołań, `hkey` jest zamykany przed powrotem
+        unsafe {
+            check(RegCreateKeyExW(
+                HKEY_CURRENT_USER, key.as_ptr(), 0, std::ptr::null(), REG_OPTION_NON_VOLATILE, KEY_WRITE,
+                std::ptr::null(), &mut hkey, std::ptr::null_mut(),
+            ))?;
+            let result = RegSetValueExW(hkey, name.as_ptr(), 0, REG_SZ, data.as_ptr() as *const u8, (data.len() * 2) as u32);
+            RegCloseKey(hkey);
+            check(result)
+        }
+    }
+
+    /// Klucz razem z podkluczami
+    pub fn delete_tree(key: &str) -> io::Result<()> {
+        let key = wide(&format!(r"{}\{}", CLASSES, key));
+        // SAFETY: napis zakończony zerem żyje do końca wywołania
+        match unsafe { RegDeleteTreeW(HKEY_CURRENT_USER, key.as_ptr()) } {
+            ERROR_FILE_NOT_FOUND => Ok(()),
+            code => check(code),
+        }
+    }
+
+    pub fn delete_value(key: &str, name: &str) -> io::Result<()> {
+        let (key, name) = (wide(&format!(r"{}\{}", CLASSES, key)), wide(name));
+        // SAFETY: napisy zakończone zerem żyją do końca wywołania
+        match unsafe { RegDeleteKeyValueW(HKEY_CURRENT_USER, key.as_ptr(), name.as_ptr()) } {
+            ERROR_FILE_NOT_FOUND => Ok(()),
+            code => check(code),
+        }
+    }
+
+    /// Eksplorator odświeża ikony i menu "Otwórz za pomocą" bez wylogowania
+    pub fn notify_association_changed() {
+        // SAFETY: zdarzenie globalne bez wskaźników na elementy
+        unsafe { SHChangeNotify(SHCNE_ASSOCCHANGED as i32, SHCNF_IDLIST, std::ptr::null(), std::ptr::null()) }
+    }
+}
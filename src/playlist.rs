@@ -0,0 +1,97 @@
+// Lista odtwarzania kilku plików otwartych naraz (wielokrotny wybór w dialogu, kilka ścieżek
+// w wierszu poleceń): przełączanie poprzedni/następny przyciskami i klawiszami PageUp/PageDown.
+// Każdy plik wczytuje się zwykłą ścieżką otwierania (proxy i podgląd zgrubny dużych plików).
+// Stan żyje w wątku UI; otwarcie pliku spoza listy jej nie usuwa, tylko zdejmuje wskazanie.
+
+use std::cell::RefCell;
+use std::path::{Path, PathBuf};
+use tracing::info;
+use crate::AppWindow;
+
+pub struct Playlist {
+    pub files: Vec<PathBuf>,
+    /// Pozycja pokazywanego pliku (None – otwarto plik spoza listy)
+    pub current: Option<usize>,
+}
+
+impl Playlist {
+    /// Plik o `delta` pozycji od bieżącego, z zawinięciem na końcach listy
+    pub fn step(&mut self, delta: i64) -> Option<&Path> {
+        let len = self.files.len() as i64;
+        if len == 0 {
+            return None;
+        }
+        let next = match self.current {
+            Some(current) => (current as i64 + delta).rem_euclid(len),
+            None if delta < 0 => len - 1,
+            None => 0,
+        } as usize;
+        self.current = Some(next);
+        Some(&self.files[next])
+    }
+
+    /// "2 / 5  name.exr"; pozycja "–" dla pliku spoza listy
+    pub fn label(&self) -> String {
+        let position = self.current.map_or("–".to_string(), |i| (i + 1).to_string());
+        let name = self.current.and_then(|i| self.files[i].file_name()).map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+        format!("{} / {}  {}", position, self.files.len(), name)
+    }
+}
+
+thread_local! {
+    static PLAYLIST: RefCell<Option<Playlist>> = const { RefCell::new(None) };
+}
+
+/// Nowa lista (mniej niż dwa pliki – brak listy); zwraca pierwszy plik do otwarcia
+pub fn set(ui: &AppWindow, files: Vec<PathBuf>) -> Option<PathBuf> {
+    let first = files.first().cloned();
+    if files.len() > 1 {
+        info!(target: "ui", "playlist: {} files", files.len());
+    }
+    PLAYLIST.with(|p| *p.borrow_mut() = (files.len() > 1).then_some(Playlist { files, current: Some(0) }));
+    publish(ui);
+    first
+}
+
+/// Plik o `delta` pozycji od bieżącego (None bez listy)
+pub fn step(ui: &AppWindow, delta: i64) -> Option<PathBuf> {
+    let path = PLAYLIST.with(|p| p.borrow_mut().as_mut()?.step(delta).map(Path::to_path_buf));
+    publish(ui);
+    path
+}
+
+/// Po otwarciu pliku: wskazanie jego pozycji na liście
+pub fn follow(ui: &AppWindow, path: &Path) {
+    PLAYLIST.with(|p| {
+        if let Some(playlist) = p.borrow_mut().as_mut() {
+            playlist.current = playlist.files.iter().position(|f| f == path);
+        }
+    });
+    publish(ui);
+}
+
+pub fn clear(ui: &AppWindow) {
+    PLAYLIST.with(|p| *p.borrow_mut() = None);
+    publish(ui);
+}
+
+fn publish(ui: &AppWindow) {
+    let label = PLAYLIST.with(|p| p.borrow().as_ref().map(Playlist::label));
+    ui.set_playlist_label(label.unwrap_or_default().into());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn step_wraps_and_starts_from_outside_file() {
+        let mut playlist = Playlist { files: ["a.exr", "b.exr", "c.exr"].map(PathBuf::from).to_vec(), current: Some(0) };
+        assert_eq!(playlist.step(-1), Some(Path::new("c.exr")));
+        assert_eq!(playlist.step(1), Some(Path::new("a.exr")));
+        assert_eq!(playlist.label(), "1 / 3  a.exr");
+        playlist.current = None;
+        assert_eq!(playlist.label(), "– / 3  ");
+        assert_eq!(playlist.step(-1), Some(Path::new("c.exr")));
+    }
+}
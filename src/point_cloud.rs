@@ -0,0 +1,106 @@
+// Podgląd 3D AOV pozycji: chmura punktów (co N-ty piksel) kolorowana obrazem beauty,
+// renderowana programowo do obrazu Slint z kamerą orbitalną – bez osobnego kontekstu GPU.
+
+use rayon::prelude::*;
+use slint::{Image, Rgba8Pixel, SharedPixelBuffer};
+use crate::image_processing::process_pixel;
+
+/// Limit punktów po decymacji – utrzymuje płynny obrót także dla dużych klatek
+pub const MAX_POINTS: usize = 250_000;
+/// Rozdzielczość renderu w oknie podglądu
+pub const POINT_CLOUD_SIZE: (u32, u32) = (640, 480);
+
+pub struct PointCloud {
+    points: Vec<([f32; 3], Rgba8Pixel)>,
+    center: [f32; 3],
+    radius: f32,
+    /// Krok decymacji (co który piksel w obu osiach)
+    pub step: usize,
+}
+
+/// Kamera orbitalna: obrót wokół środka chmury (stopnie) i przybliżenie (1.0 = cała chmura w kadrze)
+#[derive(Clone, Copy, Debug)]
+pub struct Orbit {
+    pub yaw: f32,
+    pub pitch: f32,
+    pub zoom: f32,
+}
+
+impl PointCloud {
+    /// Buduje chmurę z pozycji (RGB = XYZ) i koloru beauty tej samej wielkości; pomija punkty NaN/Inf.
+    /// Kolor przechodzi przez pipeline podglądu z podaną ekspozycją i gammą.
+    pub fn from_pixels(
+        position: &[(f32, f32, f32, f32)],
+        beauty: &[(f32, f32, f32, f32)],
+        width: usize,
+        height: usize,
+        exposure: f32,
+        gamma: f32,
+    ) -> Self {
+        let step = ((width * height) as f64 / MAX_POINTS as f64).sqrt().ceil().max(1.0) as usize;
+        let points: Vec<([f32; 3], Rgba8Pixel)> = (0..height).step_by(step)
+            .flat_map(|y| (0..width).step_by(step).map(move |x| y * width + x))
+            .filter_map(|i| {
+                let (px, py, pz, _) = position[i];
+                if !(px.is_finite() && py.is_finite() && pz.is_finite()) { return None; }
+                // Piksele tła zwykle mają pozycję (0,0,0) – nie zaśmiecają środka chmury
+                if px == 0.0 && py == 0.0 && pz == 0.0 { return None; }
+                let (r, g, b, a) = beauty.get(i).copied().unwrap_or((1.0, 1.0, 1.0, 1.0));
+                Some(([px, py, pz], process_pixel(r, g, b, a.max(1.0), exposure, gamma)))
+            })
+            .collect();
+
+        let count = points.len().max(1) as f32;
+        let sum = points.iter().fold([0.0f32; 3], |s, (p, _)| [s[0] + p[0], s[1] + p[1], s[2] + p[2]]);
+        let center = [sum[0] / count, sum[1] / count, sum[2] / count];
+        let radius = points.iter()
+            .map(|(p, _)| ((p[0] - center[0]).powi(2) + (p[1] - center[1]).powi(2) + (p[2] - center[2]).powi(2)).sqrt())
+            .fold(0.0f32, f32::max)
+            .max(1e-3);
+        PointCloud { points, center, radius, step }
+    }
+
+    pub fn len(&self) -> usize {
+        self.points.len()
+    }
+
+    /// Rzut perspektywiczny z buforem głębokości; punkt = kwadrat 2×2 px
+    pub fn render(&self, orbit: Orbit, width: u32, height: u32) -> Image {
+        let (w, h) = (width.max(1) as usize, height.max(1) as usize);
+        let (sin_yaw, cos_yaw) = orbit.yaw.to_radians().sin_cos();
+        let (sin_pitch, cos_pitch) = orbit.pitch.to_radians().sin_cos();
+        let distance = 2.5 * self.radius;
+        let focal = 0.5 * w.min(h) as f32 * 2.5 * orbit.zoom.max(0.05);
+
+        // Rzutowanie równoległe, rasteryzacja sekwencyjna (z-test na wspólnym buforze)
+        let projected: Vec<(usize, usize, f32, Rgba8Pixel)> = self.points.par_iter()
+            .filter_map(|&(p, color)| {
+                let (x, y, z) = (p[0] - self.center[0], p[1] - self.center[1], p[2] - self.center[2]);
+                // Obrót wokół osi Y (yaw), potem X (pitch)
+                let (x, z) = (x * cos_yaw - z * sin_yaw, x * sin_yaw + z * cos_yaw);
+                let (y, z) = (y * cos_pitch - z * sin_pitch, y * sin_pitch + z * cos_pitch);
+                let depth = distance - z;
+                if depth <= 1e-4 { return None; }
+                let sx = w as f32 * 0.5 + x / depth * focal;
+                let sy = h as f32 * 0.5 - y / depth * focal;
+                if sx < 0.0 || sy < 0.0 || sx >= (w - 1) as f32 || sy >= (h - 1) as f32 { return None; }
+                Some((sx as usize, sy as usize, depth, color))
+            })
+            .collect();
+
+        let mut buffer = SharedPixelBuffer::<Rgba8Pixel>::new(w as u32, h as u32);
+        let pixels = buffer.make_mut_slice();
+        pixels.fill(Rgba8Pixel { r: 24, g: 24, b: 24, a: 255 });
+        let mut depth_buffer = vec![f32::INFINITY; w * h];
+        for (x, y, depth, color) in projected {
+            for (dx, dy) in [(0, 0), (1, 0), (0, 1), (1, 1)] {
+                let i = (y + dy) * w + x + dx;
+                if depth < depth_buffer[i] {
+                    depth_buffer[i] = depth;
+                    pixels[i] = color;
+                }
+            }
+        }
+        Image::from_rgba8(buffer)
+    }
+}
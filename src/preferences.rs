@@ -0,0 +1,99 @@
+// Ustawienia zaawansowane z menu View, zapisywane w pliku preferences.txt w katalogu danych
+// aplikacji (format `klucz=wartość` jak theme.txt). Domyślnie wszystko wyłączone – to opcje dla
+// konkretnych środowisk, które użytkownik włącza świadomie.
+
+use std::fs;
+use std::path::Path;
+use std::sync::{LazyLock, Mutex};
+use tracing::warn;
+use crate::AppWindow;
+use crate::session::app_data_dir;
+
+const PREFERENCES_FILE: &str = "preferences.txt";
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Preferences {
+    /// Skany nagłówków przez mapowanie pliku w pamięć (szybsze na udziałach sieciowych, ale plik
+    /// skrócony w trakcie odczytu przerywa program – stąd opcja, nie domyślna)
+    pub mmap_reads: bool,
+    /// Port serwera zdalnego sterowania na 127.0.0.1 (`remote`); None = wyłączony
+    pub remote_port: Option<u16>,
+    /// Liczba wątków puli ciężkich zadań (`worker_threads`); 0 = automatycznie
+    pub worker_threads: usize,
+    /// Wątki puli ciężkich zadań z priorytetem poniżej normalnego (tylko Windows)
+    pub worker_low_priority: bool,
+}
+
+impl Preferences {
+    /// Port w polu menu: "Off" albo numer
+    pub fn remote_port_label(&self) -> String {
+        self.remote_port.map_or_else(|| "Off".into(), |port| port.to_string())
+    }
+
+    /// Liczba wątków w polu menu: "Auto" albo liczba
+    pub fn worker_threads_label(&self) -> String {
+        if self.worker_threads == 0 { "Auto".into() } else { self.worker_threads.to_string() }
+    }
+}
+
+/// Port wpisany w menu: "Off" (lub puste, 0) wyłącza serwer
+pub fn parse_remote_port(text: &str) -> Option<Option<u16>> {
+    let text = text.trim();
+    if text.is_empty() || text.eq_ignore_ascii_case("off") {
+        return Some(None);
+    }
+    text.parse::<u16>().ok().map(|port| (port != 0).then_some(port))
+}
+
+// Wczytywane przy pierwszym użyciu, zapisywane przy każdej zmianie
+static PREFERENCES: LazyLock<Mutex<Preferences>> = LazyLock::new(|| Mutex::new(load()));
+
+pub fn current() -> Preferences {
+    *PREFERENCES.lock().unwrap_or_else(|p| p.into_inner())
+}
+
+/// Zmienia ustawienia i od razu zapisuje je na dysk
+pub fn update(f: impl FnOnce(&mut Preferences)) -> Preferences {
+    let preferences = {
+        let mut guard = PREFERENCES.lock().unwrap_or_else(|p| p.into_inner());
+        f(&mut guard);
+        *guard
+    };
+    if let Err(e) = save_to(&app_data_dir().join(PREFERENCES_FILE), &preferences) {
+        warn!(target: "io", "cannot save preferences: {}", e);
+    }
+    preferences
+}
+
+/// Przenosi ustawienia do menu
+pub fn apply(ui: &AppWindow) {
+    let preferences = current();
+    ui.set_mmap_reads(preferences.mmap_reads);
+    ui.set_remote_port(preferences.remote_port_label().into());
+    ui.set_worker_threads(preferences.worker_threads_label().into());
+    ui.set_worker_low_priority(preferences.worker_low_priority);
+}
+
+fn load() -> Preferences {
+    let mut preferences = Preferences::default();
+    let Ok(text) = fs::read_to_string(app_data_dir().join(PREFERENCES_FILE)) else { return preferences; };
+    for line in text.lines() {
+        let Some((key, value)) = line.split_once('=') else { continue; };
+        match (key.trim(), value.trim()) {
+            ("mmap_reads", value) => preferences.mmap_reads = value == "true",
+            ("remote_port", value) => preferences.remote_port = value.parse().ok().filter(|&port| port != 0),
+            ("worker_threads", value) => preferences.worker_threads = value.parse().unwrap_or(0),
+            ("worker_low_priority", value) => preferences.worker_low_priority = value == "true",
+            _ => {}
+        }
+    }
+    preferences
+}
+
+fn save_to(path: &Path, preferences: &Preferences) -> std::io::Result<()> {
+    if let Some(dir) = path.parent() { fs::create_dir_all(dir)?; }
+    fs::write(path, format!(
+        "mmap_reads={}\nremote_port={}\nworker_threads={}\nworker_low_priority={}\n",
+        preferences.mmap_reads, preferences.remote_port.unwrap_or(0), preferences.worker_threads, preferences.worker_low_priority,
+    ))
+}
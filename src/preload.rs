@@ -0,0 +1,115 @@
+// Wczytywanie z wyprzedzeniem sąsiadów pliku otwartego z paska miniatur: poprzedni i następny plik
+// dekodują się w tle (najlepsza warstwa, jak przy zwykłym otwarciu), więc przejście strzałką
+// podmienia tylko cache. Ograniczone budżetem pamięci, bez dużych plików (te mają ścieżkę
+// progresywną); przeskok w inne miejsce anuluje trwające odczyty i porzuca niepotrzebne wyniki.
+
+use std::cell::RefCell;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Instant, SystemTime};
+use slint::ComponentHandle;
+use tracing::{debug, info};
+use crate::AppWindow;
+use crate::cancel::CancelToken;
+use crate::image_cache::ImageCache;
+use crate::progress::NoopProgress;
+use crate::proxy_files;
+
+/// Większe pliki nie są wczytywane z wyprzedzeniem
+const MAX_FILE_BYTES: u64 = 64 * 1024 * 1024;
+/// Łączny rozmiar zdekodowanych obrazów sąsiadów (RGBA f32)
+const BUDGET_BYTES: u64 = 512 * 1024 * 1024;
+
+struct Preloaded {
+    /// Plik, który wczyta `open_exr` (proxy, jeśli jest aktualne)
+    path: PathBuf,
+    modified: Option<SystemTime>,
+    cache: ImageCache,
+}
+
+#[derive(Default)]
+struct PreloadState {
+    ready: Vec<Preloaded>,
+    /// Pliki, dla których wyniki są jeszcze potrzebne (sąsiedzi bieżącego pliku)
+    wanted: Vec<PathBuf>,
+    cancel: Option<CancelToken>,
+}
+
+thread_local! {
+    static PRELOAD: RefCell<PreloadState> = RefCell::new(PreloadState::default());
+}
+
+fn decoded_bytes(cache: &ImageCache) -> u64 {
+    cache.width as u64 * cache.height as u64 * 16
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+/// Zaczyna wczytywać `neighbours` (pominięci ci już gotowi), anulując poprzednie odczyty
+pub fn schedule(ui: &AppWindow, neighbours: Vec<PathBuf>) {
+    let cancel = CancelToken::new();
+    let pending: Vec<PathBuf> = PRELOAD.with(|p| {
+        let mut state = p.borrow_mut();
+        if let Some(previous) = state.cancel.replace(cancel.clone()) {
+            previous.cancel();
+        }
+        let source = |r: &Preloaded| proxy_files::original_path(&r.path).unwrap_or_else(|| r.path.clone());
+        state.ready.retain(|r| neighbours.contains(&source(r)));
+        let pending = neighbours.iter().filter(|n| !state.ready.iter().any(|r| source(r) == **n)).cloned().collect();
+        state.wanted = neighbours;
+        pending
+    });
+    for requested in pending {
+        let ui_weak = ui.as_weak();
+        let cancel = cancel.clone();
+        rayon::spawn(move || {
+            let path = proxy_files::prefer_proxies().then(|| proxy_files::fresh_proxy(&requested)).flatten().unwrap_or(requested.clone());
+            let size = fs::metadata(&path).map(|m| m.len()).unwrap_or(u64::MAX);
+            if size > MAX_FILE_BYTES || cancel.is_cancelled() {
+                return;
+            }
+            let t0 = Instant::now();
+            let Ok(cache) = ImageCache::new(&path, &cancel, &NoopProgress) else { return; };
+            let elapsed = t0.elapsed().as_millis();
+            let _ = ui_weak.upgrade_in_event_loop(move |_| {
+                PRELOAD.with(|p| {
+                    let mut state = p.borrow_mut();
+                    let used: u64 = state.ready.iter().map(|r| decoded_bytes(&r.cache)).sum();
+                    if cancel.is_cancelled() || !state.wanted.contains(&requested) || used + decoded_bytes(&cache) > BUDGET_BYTES {
+                        debug!(target: "io", "preload of {} dropped", path.display());
+                        return;
+                    }
+                    debug!(target: "io", "preloaded {} in {} ms", path.display(), elapsed);
+                    state.ready.push(Preloaded { modified: modified(&path), path, cache });
+                });
+            });
+        });
+    }
+}
+
+/// Gotowy cache pliku (ten sam czas modyfikacji co przy wczytaniu)
+pub fn take(path: &Path) -> Option<ImageCache> {
+    let found = PRELOAD.with(|p| {
+        let mut state = p.borrow_mut();
+        let index = state.ready.iter().position(|r| r.path == path)?;
+        Some(state.ready.swap_remove(index))
+    })?;
+    if found.modified != modified(path) {
+        info!(target: "io", "preloaded {} changed on disk, loading again", path.display());
+        return None;
+    }
+    Some(found.cache)
+}
+
+/// Porzuca gotowe wyniki i anuluje odczyty (np. nowy folder)
+pub fn clear() {
+    PRELOAD.with(|p| {
+        let mut state = p.borrow_mut();
+        if let Some(cancel) = state.cancel.take() {
+            cancel.cancel();
+        }
+        *state = PreloadState::default();
+    });
+}
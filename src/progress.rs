@@ -1,8 +1,13 @@
-use std::sync::{Arc, Mutex};
-use std::time::{Duration, Instant};
+// Postęp długich operacji. Każda praca w tle (wczytywanie pliku, miniatury, eksport, skrypt)
+// rejestruje w rejestrze zadań własny postęp; pasek statusu pokazuje postęp łączny, a lista zadań
+// pod paskiem – każde zadanie osobno, z przyciskami anulowania i wstrzymania dla zadań z tokenami.
 
-// Import komponentów Slint
-use crate::AppWindow;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+use slint::{ComponentHandle, ModelRc, VecModel};
+use crate::cancel::{CancelToken, PauseToken};
+use crate::{AppWindow, ProgressTask};
 
 pub trait ProgressSink: Send + Sync {
     fn start_indeterminate(&self, message: Option<&str>);
@@ -11,76 +16,211 @@ pub trait ProgressSink: Send + Sync {
     fn reset(&self);
 }
 
-pub struct UiProgress {
+/// Wpis rejestru; `fraction` < 0 oznacza postęp nieokreślony
+struct TaskEntry {
+    id: u64,
+    name: String,
+    fraction: f32,
+    message: String,
+    cancel: Option<CancelToken>,
+    pause: Option<PauseToken>,
+}
+
+static TASKS: Mutex<Vec<TaskEntry>> = Mutex::new(Vec::new());
+static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+// Komunikat do paska statusu z ostatniej zmiany postępu i id zadania (None = bez zmiany tekstu)
+static STATUS: Mutex<Option<(u64, String)>> = Mutex::new(None);
+// Odświeżenie czeka już w pętli zdarzeń – kolejne zmiany trafią do niego (naturalny throttling)
+static REFRESH_PENDING: AtomicBool = AtomicBool::new(false);
+// Ostatnie zadanie zakończyło się sukcesem – pełny pasek przez chwilę przed wyzerowaniem
+static FINISHED: AtomicBool = AtomicBool::new(false);
+
+fn tasks() -> std::sync::MutexGuard<'static, Vec<TaskEntry>> {
+    TASKS.lock().unwrap_or_else(|p| p.into_inner())
+}
+
+fn status() -> std::sync::MutexGuard<'static, Option<(u64, String)>> {
+    STATUS.lock().unwrap_or_else(|p| p.into_inner())
+}
+
+/// Uchwyt zadania w rejestrze; `finish`/`reset` albo upuszczenie usuwa zadanie z listy
+pub struct TaskProgress {
+    id: u64,
     ui: slint::Weak<AppWindow>,
-    last_update: Arc<Mutex<Instant>>, // throttling
-    min_interval: Duration,
 }
 
-impl UiProgress {
-    pub fn new(ui: slint::Weak<AppWindow>) -> Self {
-        Self { ui, last_update: Arc::new(Mutex::new(Instant::now() - Duration::from_millis(100))), min_interval: Duration::from_millis(80) }
+/// Rejestruje zadanie o podanej nazwie; z tokenem lista zadań pokazuje przycisk anulowania
+pub fn register(ui: slint::Weak<AppWindow>, name: impl Into<String>, cancel: Option<CancelToken>) -> TaskProgress {
+    let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+    tasks().push(TaskEntry { id, name: name.into(), fraction: 0.0, message: String::new(), cancel, pause: None });
+    FINISHED.store(false, Ordering::Relaxed);
+    schedule_refresh(&ui);
+    TaskProgress { id, ui }
+}
+
+/// Anuluje zadanie z listy (przycisk ✕, wątek UI); zadanie samo usuwa się z rejestru po przerwaniu pracy
+pub fn cancel(ui: &AppWindow, id: u64) -> bool {
+    {
+        let mut tasks = tasks();
+        let Some(task) = tasks.iter_mut().find(|t| t.id == id) else { return false; };
+        let Some(token) = &task.cancel else { return false; };
+        token.cancel();
+        task.message = "Canceling...".to_string();
+        tracing::info!(target: "ui", "task canceled: {}", task.name);
     }
+    refresh(ui);
+    true
+}
 
-    fn maybe_update<F: FnOnce(&AppWindow)>(&self, f: F) {
-        if let Some(ui) = self.ui.upgrade() {
-            let mut last = self.last_update.lock().unwrap();
-            let now = Instant::now();
-            if now.duration_since(*last) >= self.min_interval {
-                f(&ui);
-                *last = now;
-            }
+/// Wstrzymuje albo wznawia zadanie z listy (przycisk ❚❚/▶, wątek UI); None = zadanie nie obsługuje
+/// wstrzymania
+pub fn toggle_pause(ui: &AppWindow, id: u64) -> Option<bool> {
+    let paused = {
+        let mut tasks = tasks();
+        let task = tasks.iter_mut().find(|t| t.id == id)?;
+        let pause = task.pause.as_ref()?;
+        let paused = !pause.is_paused();
+        pause.set_paused(paused);
+        task.message = if paused { "Paused".to_string() } else { String::new() };
+        tracing::info!(target: "ui", "task {}: {}", if paused { "paused" } else { "resumed" }, task.name);
+        paused
+    };
+    refresh(ui);
+    Some(paused)
+}
+
+impl TaskProgress {
+    /// Zadanie z listy można wstrzymać; praca sama czeka na `pause` między etapami
+    pub fn pausable(self, pause: PauseToken) -> Self {
+        if let Some(task) = tasks().iter_mut().find(|t| t.id == self.id) {
+            if pause.is_paused() { task.message = "Paused".to_string(); }
+            task.pause = Some(pause);
         }
+        schedule_refresh(&self.ui);
+        self
+    }
+
+    /// Opis na liście zadań bez zmiany paska statusu (np. "Queued")
+    pub fn set_message(&self, message: &str) {
+        if let Some(task) = tasks().iter_mut().find(|t| t.id == self.id) {
+            task.message = message.to_string();
+        }
+        schedule_refresh(&self.ui);
+    }
+
+    fn update(&self, fraction: f32, message: Option<&str>) {
+        if let Some(task) = tasks().iter_mut().find(|t| t.id == self.id) {
+            task.fraction = fraction;
+            if let Some(m) = message { task.message = m.to_string(); }
+        }
+        if let Some(m) = message { self.set_status(m); }
+        schedule_refresh(&self.ui);
+    }
+
+    fn set_status(&self, message: &str) {
+        *status() = Some((self.id, message.to_string()));
+    }
+
+    fn remove(&self) -> bool {
+        let mut tasks = tasks();
+        let before = tasks.len();
+        tasks.retain(|t| t.id != self.id);
+        tasks.len() != before
     }
 }
 
-impl ProgressSink for UiProgress {
+impl ProgressSink for TaskProgress {
     fn start_indeterminate(&self, message: Option<&str>) {
-        // Natychmiastowa aktualizacja (bez throttlingu), aby użytkownik zobaczył pasek od razu
-        if let Some(ui) = self.ui.upgrade() {
-            ui.set_progress_value(-1.0);
-            if let Some(m) = message { ui.set_status_text(m.into()); }
-            *self.last_update.lock().unwrap() = Instant::now();
-        }
+        self.update(-1.0, message);
     }
 
     fn set(&self, progress_0_1: f32, message: Option<&str>) {
-        let clamped = progress_0_1.clamp(0.0, 1.0);
-        // Jeżeli to duży skok lub jest komunikat – aktualizuj natychmiast, inaczej throttling
-        let force = message.is_some() || clamped >= 0.99 || clamped <= 0.01;
-        if force {
-            if let Some(ui) = self.ui.upgrade() {
-                ui.set_progress_value(clamped);
-                if let Some(m) = message { ui.set_status_text(m.into()); }
-                *self.last_update.lock().unwrap() = Instant::now();
-            }
-        } else {
-            self.maybe_update(|ui| {
-                ui.set_progress_value(clamped);
-                if let Some(m) = message { ui.set_status_text(m.into()); }
-            });
-        }
+        self.update(progress_0_1.clamp(0.0, 1.0), message);
     }
 
     fn finish(&self, message: Option<&str>) {
-        if let Some(ui) = self.ui.upgrade() {
-            ui.set_progress_value(1.0);
-            if let Some(m) = message { ui.set_status_text(m.into()); }
-            // krótki reset po 400ms
-            let weak = self.ui.clone();
-            slint::Timer::single_shot(std::time::Duration::from_millis(400), move || {
-                if let Some(ui2) = weak.upgrade() {
-                    ui2.set_progress_value(0.0);
-                }
-            });
+        if let Some(m) = message { self.set_status(m); }
+        if self.remove() {
+            FINISHED.store(true, Ordering::Relaxed);
+            schedule_refresh(&self.ui);
         }
     }
 
     fn reset(&self) {
-        if let Some(ui) = self.ui.upgrade() {
+        if self.remove() {
+            // Niewyświetlony komunikat przerwanego zadania nie może nadpisać statusu błędu
+            let mut status = status();
+            if status.as_ref().is_some_and(|(id, _)| *id == self.id) { *status = None; }
+            drop(status);
+            schedule_refresh(&self.ui);
+        }
+    }
+}
+
+impl Drop for TaskProgress {
+    fn drop(&mut self) {
+        self.reset();
+    }
+}
+
+/// Postęp łączny: średnia zadań o znanym postępie; same nieokreślone dają -1, brak zadań 0
+fn aggregate(tasks: &[TaskEntry]) -> f32 {
+    let known: Vec<f32> = tasks.iter().map(|t| t.fraction).filter(|f| *f >= 0.0).collect();
+    match (tasks.is_empty(), known.is_empty()) {
+        (true, _) => 0.0,
+        (false, true) => -1.0,
+        (false, false) => known.iter().sum::<f32>() / known.len() as f32,
+    }
+}
+
+fn schedule_refresh(ui: &slint::Weak<AppWindow>) {
+    if REFRESH_PENDING.swap(true, Ordering::AcqRel) {
+        return;
+    }
+    let _ = ui.upgrade_in_event_loop(|ui| {
+        REFRESH_PENDING.store(false, Ordering::Release);
+        refresh(&ui);
+    });
+}
+
+/// Przenosi stan rejestru do UI (wątek UI)
+fn refresh(ui: &AppWindow) {
+    let (value, items) = {
+        let tasks = tasks();
+        let items: Vec<ProgressTask> = tasks.iter()
+            .map(|t| ProgressTask {
+                id: t.id as i32,
+                name: t.name.as_str().into(),
+                progress: t.fraction,
+                message: t.message.as_str().into(),
+                cancellable: t.cancel.is_some(),
+                pausable: t.pause.is_some(),
+                paused: t.pause.as_ref().is_some_and(PauseToken::is_paused),
+            })
+            .collect();
+        (aggregate(&tasks), items)
+    };
+    if let Some((_, message)) = status().take() {
+        ui.set_status_text(message.into());
+    }
+    if items.is_empty() {
+        ui.set_task_list_open(false);
+        if FINISHED.swap(false, Ordering::Relaxed) {
+            // Krótko pełny pasek, potem zero (o ile w międzyczasie nie ruszyło nowe zadanie)
+            ui.set_progress_value(1.0);
+            let weak = ui.as_weak();
+            slint::Timer::single_shot(Duration::from_millis(400), move || {
+                if let Some(ui) = weak.upgrade() {
+                    if tasks().is_empty() { ui.set_progress_value(0.0); }
+                }
+            });
+        } else {
             ui.set_progress_value(0.0);
         }
+    } else {
+        ui.set_progress_value(value);
     }
+    ui.set_progress_tasks(ModelRc::new(VecModel::from(items)));
 }
 
 pub struct NoopProgress;
@@ -90,3 +230,12 @@ impl ProgressSink for NoopProgress {
     fn finish(&self, _message: Option<&str>) {}
     fn reset(&self) {}
 }
+
+/// Przekazuje postęp do funkcji (np. kanału do wątku UI): -1 oznacza postęp nieokreślony
+pub struct FnProgress<F: Fn(f32, Option<&str>) + Send + Sync>(pub F);
+impl<F: Fn(f32, Option<&str>) + Send + Sync> ProgressSink for FnProgress<F> {
+    fn start_indeterminate(&self, message: Option<&str>) { (self.0)(-1.0, message) }
+    fn set(&self, progress_0_1: f32, message: Option<&str>) { (self.0)(progress_0_1, message) }
+    fn finish(&self, message: Option<&str>) { (self.0)(1.0, message) }
+    fn reset(&self) { (self.0)(0.0, None) }
+}
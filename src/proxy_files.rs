@@ -0,0 +1,150 @@
+// Pliki proxy na dysku: zmniejszone (1/4) EXR half-float z jedną warstwą beauty, kompresja DWAA,
+// zapisywane obok ciężkich plików źródłowych jako `<nazwa>.proxy.exr`. Przy otwieraniu pliku
+// aktualne proxy ma pierwszeństwo (jeśli włączone), a oryginał można doczytać jednym kliknięciem.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use exr::prelude::*;
+use rayon::prelude::*;
+use tracing::{info, warn};
+use crate::image_cache::{extract_layers_info, find_best_layer, load_specific_layer};
+use crate::utils::error_handling::ExrResult;
+
+pub const PROXY_SUFFIX: &str = ".proxy.exr";
+/// Skala proxy względem oryginału (w każdej osi)
+pub const PROXY_SCALE: usize = 4;
+/// Mniejsze pliki wczytują się szybko – proxy nie jest potrzebne
+pub const MIN_SOURCE_BYTES: u64 = 64 * 1024 * 1024;
+/// Atrybut nagłówka z nazwą pliku źródłowego
+const PROXY_OF_ATTRIBUTE: &str = "exrusterProxyOf";
+
+static PREFER_PROXIES: AtomicBool = AtomicBool::new(true);
+
+pub fn set_prefer_proxies(prefer: bool) {
+    PREFER_PROXIES.store(prefer, Ordering::Relaxed);
+}
+
+pub fn prefer_proxies() -> bool {
+    PREFER_PROXIES.load(Ordering::Relaxed)
+}
+
+pub fn is_proxy(path: &Path) -> bool {
+    path.file_name()
+        .map(|n| n.to_string_lossy().to_ascii_lowercase().ends_with(PROXY_SUFFIX))
+        .unwrap_or(false)
+}
+
+/// `shot.0001.exr` → `shot.0001.proxy.exr` w tym samym katalogu
+pub fn proxy_path(source: &Path) -> PathBuf {
+    let stem = source.file_stem().map(|s| s.to_string_lossy().into_owned()).unwrap_or_default();
+    source.with_file_name(format!("{}{}", stem, PROXY_SUFFIX))
+}
+
+/// Ścieżka oryginału dla pliku proxy (None dla zwykłych plików)
+pub fn original_path(proxy: &Path) -> Option<PathBuf> {
+    if !is_proxy(proxy) { return None; }
+    let name = proxy.file_name()?.to_string_lossy().into_owned();
+    Some(proxy.with_file_name(format!("{}.exr", &name[..name.len() - PROXY_SUFFIX.len()])))
+}
+
+/// Proxy obok pliku, o ile istnieje i jest nowsze niż źródło
+pub fn fresh_proxy(source: &Path) -> Option<PathBuf> {
+    if is_proxy(source) { return None; }
+    let proxy = proxy_path(source);
+    let modified = |p: &Path| fs::metadata(p).and_then(|m| m.modified()).ok();
+    match (modified(&proxy), modified(source)) {
+        (Some(p), Some(s)) if p >= s => Some(proxy),
+        _ => None,
+    }
+}
+
+/// Zapisuje proxy pliku: najlepsza warstwa (beauty), uśrednianie bloków PROXY_SCALE×PROXY_SCALE,
+/// half-float RGBA z kompresją DWAA. Zapis przez plik tymczasowy – przerwany zapis nie zostawia
+/// uszkodzonego proxy, które loader mógłby wybrać.
+pub fn generate_proxy(source: &Path) -> ExrResult<PathBuf> {
+    let source_buf = source.to_path_buf();
+    let layer_name = find_best_layer(&extract_layers_info(&source_buf)?);
+    let (pixels, width, height, _) = load_specific_layer(&source_buf, &layer_name)?;
+    let (width, height) = (width as usize, height as usize);
+    let (out_w, out_h) = (width.div_ceil(PROXY_SCALE), height.div_ceil(PROXY_SCALE));
+
+    let downscaled: Vec<(f32, f32, f32, f32)> = (0..out_w * out_h).into_par_iter()
+        .map(|i| {
+            let (ox, oy) = (i % out_w, i / out_w);
+            let mut sum = [0.0f32; 4];
+            let mut count = 0.0f32;
+            for y in oy * PROXY_SCALE..((oy + 1) * PROXY_SCALE).min(height) {
+                for x in ox * PROXY_SCALE..((ox + 1) * PROXY_SCALE).min(width) {
+                    let (r, g, b, a) = pixels[y * width + x];
+                    // NaN/Inf nie mogą zatruć całego bloku
+                    if r.is_finite() && g.is_finite() && b.is_finite() && a.is_finite() {
+                        sum = [sum[0] + r, sum[1] + g, sum[2] + b, sum[3] + a];
+                        count += 1.0;
+                    }
+                }
+            }
+            let n = count.max(1.0);
+            (sum[0] / n, sum[1] / n, sum[2] / n, sum[3] / n)
+        })
+        .collect();
+
+    let mut attributes = LayerAttributes::default();
+    let source_name = source.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+    attributes.other.insert(Text::from(PROXY_OF_ATTRIBUTE), AttributeValue::Text(Text::from(source_name.as_str())));
+    let encoding = Encoding { compression: Compression::DWAA(None), blocks: Blocks::ScanLines, line_order: LineOrder::Increasing };
+    let channels = SpecificChannels::rgba(|Vec2(x, y): Vec2<usize>| {
+        let (r, g, b, a) = downscaled[y * out_w + x];
+        (f16::from_f32(r), f16::from_f32(g), f16::from_f32(b), f16::from_f32(a))
+    });
+    let image = Image::from_layer(Layer::new(Vec2(out_w, out_h), attributes, encoding, channels));
+
+    let target = proxy_path(source);
+    let temp = target.with_extension("exr.tmp");
+    image.write().to_file(&temp)?;
+    fs::rename(&temp, &target)?;
+    Ok(target)
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ProxySummary {
+    pub written: usize,
+    /// Pliki z aktualnym proxy
+    pub up_to_date: usize,
+    pub failed: usize,
+}
+
+/// Generuje brakujące lub nieaktualne proxy dla ciężkich plików EXR w katalogu (bez rekursji).
+/// `progress(ułamek, komunikat)` wołane z wątków roboczych.
+pub fn generate_for_directory(dir: &Path, progress: impl Fn(f32, &str) + Sync) -> std::io::Result<ProxySummary> {
+    let mut heavy = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        let is_exr = path.extension().is_some_and(|e| e.eq_ignore_ascii_case("exr"));
+        if !is_exr || is_proxy(&path) { continue; }
+        if fs::metadata(&path).map(|m| m.len()).unwrap_or(0) >= MIN_SOURCE_BYTES {
+            heavy.push(path);
+        }
+    }
+    let (pending, fresh): (Vec<PathBuf>, Vec<PathBuf>) = heavy.into_iter().partition(|p| fresh_proxy(p).is_none());
+
+    let total = pending.len();
+    let done = AtomicUsize::new(0);
+    let failed = AtomicUsize::new(0);
+    // Po jednym pliku na wątek – każdy i tak dekoduje równolegle, a pamięć rośnie z liczbą plików w locie
+    pending.par_iter().with_max_len(1).for_each(|source| {
+        let name = source.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+        match generate_proxy(source) {
+            Ok(proxy) => info!(target: "io", "proxy written: {}", proxy.display()),
+            Err(e) => {
+                warn!(target: "io", "proxy for {} failed: {}", source.display(), e);
+                failed.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+        let n = done.fetch_add(1, Ordering::Relaxed) + 1;
+        progress(n as f32 / total as f32, &format!("Proxies {}/{}: {}", n, total, name));
+    });
+
+    let failed = failed.into_inner();
+    Ok(ProxySummary { written: total - failed, up_to_date: fresh.len(), failed })
+}
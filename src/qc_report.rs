@@ -0,0 +1,346 @@
+// Raport QC do kontroli dostaw: dla pliku albo folderu EXR jedna strona HTML z miniaturą,
+// rozdzielczością, listą warstw, min/max i liczbą NaN/Inf każdej warstwy (AOV), histogramem oraz
+// tabelą metadanych. Obrazy są osadzone (miniatura jako PNG w base64, histogram jako SVG), więc
+// raport to pojedynczy plik; PDF powstaje przez wydruk strony z przeglądarki (styl ma wersję do
+// druku – każdy plik od nowej strony).
+
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+use anyhow::Context;
+use tracing::{info, warn};
+use crate::cancel::CancelToken;
+use crate::dir_scan::{self, ScanOptions};
+use crate::exr_metadata;
+use crate::histogram::{self, Histogram};
+use crate::image_cache::{self, ImageCache};
+use crate::metrics::{region_stats, RegionStats};
+use crate::progress::NoopProgress;
+use crate::raw_image::RawImage;
+use crate::render_settings::RenderSettings;
+
+/// Dłuższy bok miniatury w raporcie
+const THUMBNAIL_SIZE: u32 = 320;
+/// Kolory krzywych histogramu (kolejność `histogram::CHANNELS`)
+const CURVE_COLORS: [&str; 4] = ["#e05050", "#50c050", "#5080f0", "#d0d0d0"];
+
+const STYLE: &str = "\
+body { font-family: sans-serif; font-size: 12px; color: #222; margin: 24px; }
+h1 { font-size: 18px; } h2 { font-size: 15px; margin-top: 32px; border-bottom: 1px solid #999; }
+table { border-collapse: collapse; margin: 8px 0; }
+td, th { border: 1px solid #ccc; padding: 2px 6px; text-align: left; vertical-align: top; }
+td.num { text-align: right; font-family: monospace; }
+th.section { background: #eee; }
+.bad { color: #c00; font-weight: bold; }
+.ok { color: #080; }
+.file { display: flex; gap: 16px; align-items: flex-start; }
+.histogram { background: #222; width: 384px; height: 120px; }
+.axis { display: flex; justify-content: space-between; width: 384px; color: #666; }
+@media print { .page { page-break-before: always; } body { margin: 0; } }";
+
+/// Statystyki jednej warstwy; błąd odczytu zamiast statystyk (np. dane deep)
+pub struct LayerReport {
+    pub name: String,
+    pub channels: Vec<String>,
+    pub stats: Result<RegionStats, String>,
+}
+
+impl LayerReport {
+    fn non_finite(&self) -> usize {
+        self.stats.as_ref().map_or(0, |s| s.non_finite)
+    }
+}
+
+pub struct FileReport {
+    pub size_bytes: u64,
+    pub width: u32,
+    pub height: u32,
+    pub thumbnail: RawImage,
+    /// Histogramy warstwy podglądu (R, G, B, luminancja)
+    pub histograms: Option<[Histogram; 4]>,
+    pub preview_layer: String,
+    pub layers: Vec<LayerReport>,
+    /// Wiersze tabeli metadanych; pusta wartość = nagłówek sekcji
+    pub metadata: Vec<(String, String)>,
+}
+
+impl FileReport {
+    fn non_finite(&self) -> usize {
+        self.layers.iter().map(LayerReport::non_finite).sum()
+    }
+}
+
+/// Wynik zapisu raportu do paska statusu / trybu CLI
+#[derive(Clone, Copy, Debug, Default)]
+pub struct QcSummary {
+    pub files: usize,
+    /// Pliki, których nie udało się odczytać
+    pub failed: usize,
+    /// Pliki z wartościami NaN/Inf w którejkolwiek warstwie
+    pub non_finite: usize,
+}
+
+impl QcSummary {
+    pub fn passed(&self) -> bool {
+        self.failed == 0 && self.non_finite == 0
+    }
+}
+
+/// Zbiera dane raportu jednego pliku: podgląd i histogram z najlepszej warstwy, statystyki z każdej
+pub fn analyze(path: &Path, cancel: &CancelToken) -> anyhow::Result<FileReport> {
+    let path = path.to_path_buf();
+    let cache = ImageCache::new(&path, cancel, &NoopProgress)?;
+    let settings = RenderSettings::standard(0.0, 2.2);
+    let thumbnail = cache.process_to_thumbnail(&settings, THUMBNAIL_SIZE);
+    let histograms = cache.channel_histograms(None, &settings.transform, &settings.graph.tone_params()).map(|(h, _)| h);
+
+    let mut layers = Vec::with_capacity(cache.layers_info.len());
+    for layer in &cache.layers_info {
+        cancel.check()?;
+        let stats = if layer.name == cache.current_layer_name {
+            Ok(region_stats(&cache.raw_pixels))
+        } else {
+            image_cache::load_specific_layer(&path, &layer.name)
+                .map(|(pixels, ..)| region_stats(&pixels))
+                .map_err(|e| e.to_string())
+        };
+        if let Err(e) = &stats {
+            warn!(target: "io", "QC stats of {} / {}: {}", path.display(), layer.name, e);
+        }
+        layers.push(LayerReport { name: layer.name.clone(), channels: layer.channels.iter().map(|c| c.name.clone()).collect(), stats });
+    }
+
+    let metadata = exr_metadata::read_and_group_metadata(&path)
+        .map(|meta| exr_metadata::build_ui_rows(&meta))
+        .unwrap_or_default();
+    Ok(FileReport {
+        size_bytes: std::fs::metadata(&path).map(|m| m.len()).unwrap_or(0),
+        width: cache.width,
+        height: cache.height,
+        thumbnail,
+        histograms,
+        preview_layer: cache.current_layer_name.clone(),
+        layers,
+        metadata,
+    })
+}
+
+/// Raport dla pliku albo folderu (pliki EXR jak w pasku miniaturek, z EXRUSTER_SCAN_DEPTH)
+/// zapisany do `output`; `progress(ułamek, opis)` po każdym pliku
+pub fn write_report(input: &Path, output: &Path, cancel: &CancelToken, progress: impl Fn(f32, &str)) -> anyhow::Result<QcSummary> {
+    let mut files = if input.is_dir() {
+        dir_scan::scan(input, &ScanOptions::from_env(), cancel)?.files
+    } else {
+        vec![input.to_path_buf()]
+    };
+    files.sort();
+
+    let mut summary = QcSummary { files: files.len(), ..Default::default() };
+    let mut reports = Vec::with_capacity(files.len());
+    for (i, file) in files.into_iter().enumerate() {
+        cancel.check()?;
+        let name = file.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+        progress(i as f32 / summary.files as f32, &format!("QC {}/{}: {}", i + 1, summary.files, name));
+        let report = analyze(&file, cancel).map_err(|e| {
+            warn!(target: "io", "QC report of {}: {}", file.display(), e);
+            e.to_string()
+        });
+        match &report {
+            Ok(r) if r.non_finite() > 0 => summary.non_finite += 1,
+            Ok(_) => {}
+            Err(_) => summary.failed += 1,
+        }
+        reports.push((file, report));
+    }
+    cancel.check()?;
+
+    std::fs::write(output, render_html(input, &reports))
+        .with_context(|| format!("Nie można zapisać raportu: {}", output.display()))?;
+    info!(target: "io", "QC report written: {} ({} files, {} failed, {} with NaN/Inf)", output.display(), summary.files, summary.failed, summary.non_finite);
+    Ok(summary)
+}
+
+/// Strona raportu: tabela zbiorcza, potem sekcja każdego pliku
+pub fn render_html(input: &Path, reports: &[(PathBuf, Result<FileReport, String>)]) -> String {
+    let title = format!("QC report: {}", input.display());
+    let mut html = format!(
+        "<!DOCTYPE html>\n<html><head><meta charset=\"utf-8\"><title>{0}</title><style>\n{1}\n</style></head><body>\n<h1>{0}</h1>\n",
+        escape(&title), STYLE
+    );
+
+    html.push_str("<table><tr><th>File</th><th>Resolution</th><th>Layers</th><th>NaN/Inf</th></tr>\n");
+    for (path, report) in reports {
+        let name = escape(&file_name(path));
+        match report {
+            Ok(r) => {
+                let _ = writeln!(html, "<tr><td>{}</td><td>{}×{}</td><td class=\"num\">{}</td><td>{}</td></tr>", name, r.width, r.height, r.layers.len(), non_finite_cell(r.non_finite()));
+            }
+            Err(e) => {
+                let _ = writeln!(html, "<tr><td>{}</td><td colspan=\"3\" class=\"bad\">{}</td></tr>", name, escape(e));
+            }
+        }
+    }
+    html.push_str("</table>\n");
+
+    for (path, report) in reports {
+        let _ = writeln!(html, "<div class=\"page\"><h2>{}</h2>", escape(&path.display().to_string()));
+        match report {
+            Ok(r) => file_section(&mut html, r),
+            Err(e) => {
+                let _ = writeln!(html, "<p class=\"bad\">Read error: {}</p>", escape(e));
+            }
+        }
+        html.push_str("</div>\n");
+    }
+    html.push_str("</body></html>\n");
+    html
+}
+
+fn file_section(html: &mut String, r: &FileReport) {
+    html.push_str("<div class=\"file\">");
+    if let Some(png) = encode_png(&r.thumbnail) {
+        let _ = write!(html, "<img alt=\"thumbnail\" src=\"data:image/png;base64,{}\">", base64(&png));
+    }
+    let _ = write!(
+        html,
+        "<div><table><tr><td>Resolution</td><td>{}×{}</td></tr><tr><td>File size</td><td>{:.2} MB</td></tr><tr><td>Layers</td><td>{}</td></tr><tr><td>Preview layer</td><td>{}</td></tr></table>",
+        r.width, r.height, r.size_bytes as f64 / 1_048_576.0, r.layers.len(), escape(&layer_label(&r.preview_layer))
+    );
+    if let Some(h) = &r.histograms {
+        histogram_svg(html, h);
+    }
+    html.push_str("</div></div>\n");
+
+    html.push_str("<table><tr><th>Layer</th><th>Channels</th><th>NaN/Inf</th>");
+    for stat in ["min", "max"] {
+        for c in ["R", "G", "B", "A"] {
+            let _ = write!(html, "<th>{} {}</th>", stat, c);
+        }
+    }
+    html.push_str("</tr>\n");
+    for layer in &r.layers {
+        let _ = write!(html, "<tr><td>{}</td><td>{}</td>", escape(&layer_label(&layer.name)), escape(&layer.channels.join(" ")));
+        match &layer.stats {
+            Ok(s) => {
+                let _ = write!(html, "<td>{}</td>", non_finite_cell(s.non_finite));
+                for v in s.min.iter().chain(&s.max) {
+                    let _ = write!(html, "<td class=\"num\">{:.5}</td>", v);
+                }
+            }
+            Err(e) => {
+                let _ = write!(html, "<td colspan=\"9\" class=\"bad\">{}</td>", escape(e));
+            }
+        }
+        html.push_str("</tr>\n");
+    }
+    html.push_str("</table>\n");
+
+    if !r.metadata.is_empty() {
+        html.push_str("<table>\n");
+        for (key, value) in &r.metadata {
+            if value.is_empty() {
+                let _ = writeln!(html, "<tr><th class=\"section\" colspan=\"2\">{}</th></tr>", escape(key));
+            } else {
+                let _ = writeln!(html, "<tr><td>{}</td><td>{}</td></tr>", escape(key), escape(value));
+            }
+        }
+        html.push_str("</table>\n");
+    }
+}
+
+/// Krzywe R, G, B (wypełnione) i luminancji (obrys) na wspólnej osi EV, z opisem końców osi
+fn histogram_svg(html: &mut String, histograms: &[Histogram; 4]) {
+    let Some(range) = histogram::common_range(histograms) else { return; };
+    let width = range.1 - range.0 + 1;
+    let _ = write!(html, "<svg class=\"histogram\" viewBox=\"0 0 {} 100\" preserveAspectRatio=\"none\">", width);
+    for (i, (h, color)) in histograms.iter().zip(CURVE_COLORS).enumerate() {
+        let paint = if i == histogram::LUMINANCE {
+            format!("fill=\"none\" stroke=\"{}\" stroke-width=\"1\" vector-effect=\"non-scaling-stroke\"", color)
+        } else {
+            format!("fill=\"{}\" fill-opacity=\"0.45\"", color)
+        };
+        let _ = write!(html, "<path d=\"{}\" {}/>", h.path_commands(range), paint);
+    }
+    let _ = write!(
+        html,
+        "</svg><div class=\"axis\"><span>{:+.1} EV</span><span>{}</span><span>{:+.1} EV</span></div>",
+        histogram::bin_ev(range.0), histogram::CHANNELS.join(" "), histogram::bin_ev(range.1 + 1)
+    );
+}
+
+fn non_finite_cell(count: usize) -> String {
+    if count > 0 { format!("<span class=\"bad\">{}</span>", count) } else { "<span class=\"ok\">0</span>".to_string() }
+}
+
+fn layer_label(name: &str) -> String {
+    if name.is_empty() { "(default)".to_string() } else { name.to_string() }
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_else(|| path.display().to_string())
+}
+
+fn encode_png(image: &RawImage) -> Option<Vec<u8>> {
+    use image::ImageEncoder;
+    if image.width == 0 || image.height == 0 {
+        return None;
+    }
+    let mut png = Vec::new();
+    image::codecs::png::PngEncoder::new(&mut png)
+        .write_image(&image.pixels, image.width, image.height, image::ExtendedColorType::Rgba8)
+        .ok()?;
+    Some(png)
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+/// Base64 (RFC 4648, z dopełnieniem) dla osadzonych obrazów
+fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut out = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let n = chunk.iter().enumerate().fold(0u32, |n, (i, &b)| n | (b as u32) << (16 - 8 * i));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(ALPHABET[(n >> (18 - 6 * i) & 63) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn base64_padding() {
+        assert_eq!(base64(b""), "");
+        assert_eq!(base64(b"f"), "Zg==");
+        assert_eq!(base64(b"fo"), "Zm8=");
+        assert_eq!(base64(b"foobar"), "Zm9vYmFy");
+    }
+
+    #[test]
+    fn report_flags_non_finite_layers() {
+        let stats = region_stats(&[(1.0, f32::NAN, 0.0, 1.0), (0.5, 0.25, f32::INFINITY, 1.0)]);
+        let report = FileReport {
+            size_bytes: 0,
+            width: 2,
+            height: 1,
+            thumbnail: RawImage::new(2, 1),
+            histograms: None,
+            preview_layer: String::new(),
+            layers: vec![LayerReport { name: "beauty<1>".into(), channels: vec!["R".into(), "G".into(), "B".into()], stats: Ok(stats) }],
+            metadata: vec![("General".into(), String::new()), ("owner".into(), "a & b".into())],
+        };
+        assert_eq!(report.non_finite(), 1);
+        let html = render_html(Path::new("shot"), &[(PathBuf::from("shot/a.exr"), Ok(report)), (PathBuf::from("shot/b.exr"), Err("truncated".into()))]);
+        assert!(html.contains("beauty&lt;1&gt;") && html.contains("a &amp; b"));
+        assert!(html.contains("<span class=\"bad\">1</span>") && html.contains("truncated"));
+        assert!(html.contains("data:image/png;base64,"));
+    }
+}
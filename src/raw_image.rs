@@ -0,0 +1,33 @@
+// Obraz wyjściowy warstwy przetwarzania: RGBA8 (sRGB) w pamięci, bez typów Slint. Przetwarzanie
+// (podgląd, miniatury, eksport, różnice) zwraca RawImage; na obraz Slint zamieniamy go dopiero
+// na granicy UI, dzięki czemu potok da się testować bez okna i pętli zdarzeń.
+
+use slint::Rgba8Pixel;
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct RawImage {
+    pub width: u32,
+    pub height: u32,
+    /// Piksele RGBA8 wierszami (4 bajty na piksel)
+    pub pixels: Vec<u8>,
+}
+
+impl RawImage {
+    /// Obraz przezroczysty (same zera)
+    pub fn new(width: u32, height: u32) -> Self {
+        RawImage { width, height, pixels: vec![0; width as usize * height as usize * 4] }
+    }
+
+    /// Zapisuje piksele od (x, y) w tym samym wierszu
+    pub(crate) fn put_row(&mut self, x: u32, y: u32, row: &[Rgba8Pixel]) {
+        let start = (y as usize * self.width as usize + x as usize) * 4;
+        for (dst, px) in self.pixels[start..start + row.len() * 4].chunks_exact_mut(4).zip(row) {
+            dst.copy_from_slice(&[px.r, px.g, px.b, px.a]);
+        }
+    }
+
+    /// Konwersja na granicy UI
+    pub fn to_slint_image(&self) -> slint::Image {
+        slint::Image::from_rgba8(slint::SharedPixelBuffer::<Rgba8Pixel>::clone_from_slice(&self.pixels, self.width, self.height))
+    }
+}
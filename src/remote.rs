@@ -0,0 +1,127 @@
+// Zdalne sterowanie dla integracji z pipeline'em (monitory farmy, pluginy DCC): lokalny serwer TCP
+// przyjmujący komendy JSON, po jednej na linię, np. {"open": "D:/render/beauty.0101.exr"}.
+// Włączany portem w menu View (zapisanym w `preferences`); nasłuchuje tylko na 127.0.0.1.
+// Odpowiedź to też linia JSON – dla otwarcia pliku w formacie zdarzenia konsoli `file.open`,
+// wysyłana dopiero po wczytaniu pliku (z opisem błędu, gdy się nie udało).
+
+use std::cell::RefCell;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::PathBuf;
+use std::rc::Rc;
+use std::sync::mpsc;
+use serde_json::{json, Value};
+use tracing::{error, info, warn};
+use crate::actions::{Action, Dispatcher};
+use crate::preferences;
+use crate::ui_handlers;
+
+thread_local! {
+    // Dispatcher żyje w wątku UI; komendy z sieci trafiają do niego przez pętlę zdarzeń Slint
+    static DISPATCHER: RefCell<Option<Rc<Dispatcher>>> = const { RefCell::new(None) };
+    // Port działającego serwera (nasłuch trwa do końca programu)
+    static LISTENING: RefCell<Option<u16>> = const { RefCell::new(None) };
+}
+
+/// Port, na którym serwer już nasłuchuje (wątek UI)
+pub fn listening_port() -> Option<u16> {
+    LISTENING.with(|l| *l.borrow())
+}
+
+/// Zapamiętuje dispatcher i uruchamia serwer, jeśli port jest ustawiony w preferencjach (wołać z wątku UI)
+pub fn start_if_enabled(dispatcher: &Rc<Dispatcher>) {
+    DISPATCHER.with(|d| *d.borrow_mut() = Some(dispatcher.clone()));
+    if let Some(port) = preferences::current().remote_port {
+        start(port);
+    }
+}
+
+/// Nasłuch na 127.0.0.1:`port`; false gdy serwer już działa albo port jest zajęty (wątek UI)
+pub fn start(port: u16) -> bool {
+    if listening_port().is_some() {
+        return false;
+    }
+    let listener = match TcpListener::bind(("127.0.0.1", port)) {
+        Ok(listener) => listener,
+        Err(e) => {
+            error!(target: "remote", "cannot listen on 127.0.0.1:{}: {}", port, e);
+            return false;
+        }
+    };
+    LISTENING.with(|l| *l.borrow_mut() = Some(port));
+    info!(target: "remote", "listening on 127.0.0.1:{}", port);
+
+    std::thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            std::thread::spawn(move || serve_client(stream));
+        }
+    });
+    true
+}
+
+fn serve_client(stream: TcpStream) {
+    let peer = stream.peer_addr().map(|a| a.to_string()).unwrap_or_default();
+    let Ok(mut writer) = stream.try_clone() else { return; };
+    info!(target: "remote", "client connected: {}", peer);
+
+    for line in BufReader::new(stream).lines() {
+        let Ok(line) = line else { break; };
+        if line.trim().is_empty() { continue; }
+        let reply = match parse_command(&line) {
+            Ok((action, mut reply)) => {
+                info!(target: "remote", "{}: {:?}", peer, action);
+                // Odpowiedź dopiero po wykonaniu komendy; otwarcie pliku czeka na koniec wczytywania
+                let (done_tx, done_rx) = mpsc::channel();
+                let _ = slint::invoke_from_event_loop(move || {
+                    DISPATCHER.with(|d| {
+                        let Some(dispatcher) = d.borrow().clone() else { return; };
+                        let opens_file = matches!(action, Action::OpenFile(_));
+                        dispatcher.dispatch(action);
+                        if opens_file {
+                            ui_handlers::notify_when_loaded(dispatcher.context(), done_tx);
+                        } else {
+                            let _ = done_tx.send(Ok(()));
+                        }
+                    });
+                });
+                let outcome = done_rx.recv().unwrap_or_else(|_| Err("the viewer did not run the command".to_string()));
+                if let Err(e) = outcome {
+                    warn!(target: "remote", "{}: {}", peer, e);
+                    reply["ok"] = json!(false);
+                    reply["error"] = json!(e);
+                }
+                reply
+            }
+            Err(e) => {
+                warn!(target: "remote", "{}: {}", peer, e);
+                json!({ "ok": false, "error": e })
+            }
+        };
+        if writeln!(writer, "{}", reply).is_err() { break; }
+    }
+    info!(target: "remote", "client disconnected: {}", peer);
+}
+
+/// Tłumaczy komendę JSON na akcję UI oraz odpowiedź dla klienta
+fn parse_command(line: &str) -> Result<(Action, Value), String> {
+    let value: Value = serde_json::from_str(line).map_err(|e| format!("invalid JSON: {}", e))?;
+    let Some(command) = value.as_object().filter(|o| o.len() == 1) else {
+        return Err("expected an object with a single command, e.g. {\"open\": \"path\"}".to_string());
+    };
+    let (name, arg) = command.iter().next().expect("one entry");
+
+    match name.as_str() {
+        "open" => {
+            let path = arg.as_str().ok_or("open: expected a path string")?;
+            let reply = json!({ "ok": true, "event": "file.open", "path": path });
+            Ok((Action::open(PathBuf::from(path)), reply))
+        }
+        // Klatka sekwencji bieżącego pliku (oś czasu); brak klatki obsługiwany wg trybu braków
+        "goto_frame" => {
+            let frame = arg.as_i64().ok_or("goto_frame: expected a frame number")?;
+            let reply = json!({ "ok": true, "event": "timeline.goto", "frame": frame });
+            Ok((Action::GotoFrame(frame), reply))
+        }
+        other => Err(format!("unknown command '{}'", other)),
+    }
+}
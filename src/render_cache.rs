@@ -0,0 +1,61 @@
+// Pamięć podręczna ostatnich obrazów podglądu: klucz to hash parametrów widoku (`ProcessingGraph`,
+// tryby wyświetlania, rozmiar wyjścia), więc powrót do niedawnego stanu (A/B, cofnięcie, ten sam
+// poziom powiększenia) nie przelicza całego obrazu. Wpis pamięta też piksele źródłowe (Arc) –
+// zmiana warstwy unieważnia go bez osobnego czyszczenia.
+
+use std::sync::Arc;
+use tracing::debug;
+use crate::layer_cache::Pixels;
+use crate::raw_image::RawImage;
+
+/// Liczba pamiętanych obrazów (np. stany A i B w dwóch poziomach powiększenia)
+pub const DEFAULT_ENTRIES: usize = 4;
+/// Limit łącznego rozmiaru obrazów RGBA8
+const BUDGET_BYTES: usize = 512 << 20;
+
+struct Entry {
+    key: u64,
+    source: Pixels,
+    image: RawImage,
+}
+
+/// LRU obrazów wyjściowych; najświeższy wpis na końcu
+pub struct RenderCache {
+    entries: Vec<Entry>,
+    capacity: usize,
+}
+
+impl Default for RenderCache {
+    fn default() -> Self {
+        Self::new(DEFAULT_ENTRIES)
+    }
+}
+
+impl RenderCache {
+    pub fn new(capacity: usize) -> Self {
+        Self { entries: Vec::new(), capacity }
+    }
+
+    /// Obraz dla klucza i pikseli źródłowych, oznaczony jako ostatnio użyty
+    pub fn get(&mut self, key: u64, source: &Pixels) -> Option<RawImage> {
+        let index = self.entries.iter().position(|e| e.key == key && Arc::ptr_eq(&e.source, source))?;
+        let entry = self.entries.remove(index);
+        let image = entry.image.clone();
+        self.entries.push(entry);
+        debug!(target: "processing", "render cache hit ({:016x})", key);
+        Some(image)
+    }
+
+    pub fn insert(&mut self, key: u64, source: &Pixels, image: RawImage) {
+        if self.capacity == 0 || image.pixels.len() > BUDGET_BYTES {
+            return;
+        }
+        // Wpisy innej warstwy już się nie przydadzą (piksele warstwy trzyma `LayerCache`)
+        self.entries.retain(|e| e.key != key && Arc::ptr_eq(&e.source, source));
+        self.entries.push(Entry { key, source: source.clone(), image });
+        let mut total: usize = self.entries.iter().map(|e| e.image.pixels.len()).sum();
+        while self.entries.len() > self.capacity || total > BUDGET_BYTES {
+            total -= self.entries.remove(0).image.pixels.len();
+        }
+    }
+}
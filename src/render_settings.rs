@@ -0,0 +1,205 @@
+// Migawka ustawień widoku, z których powstaje obraz: graf przetwarzania (ekspozycja, gamma,
+// przestrzeń wejściowa, etapy), obrót/odbicie, tryby diagnostyczne, filtry i widoki specjalne.
+// `ImageCache` renderuje wyłącznie z niej – podgląd bierze bieżące ustawienia z `AppContext`, eksport
+// ich kopię bez nakładek diagnostycznych (`for_export`). Zlecenia w kolejce eksportu trzymają migawkę
+// z chwili zlecenia (także w export_queue.json), więc zmiany widoku po zleceniu nie trafiają do pliku.
+
+use serde_json::{json, Value};
+use crate::display_filters::{Bloom, Sharpen};
+use crate::env_map::EnvView;
+use crate::image_processing::{self, DisplayTransform, ExposureMode, GamutWarning, GrayscaleMode, InputColorSpace, LocalTonemap, ProcessingGraph, Stage};
+use crate::stereo::StereoMode;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RenderSettings {
+    pub graph: ProcessingGraph,
+    pub transform: DisplayTransform,
+    pub grayscale: GrayscaleMode,
+    pub gamut_warning: GamutWarning,
+    pub false_color: bool,
+    /// Długość wektora mapowana na pełną jasność w widoku wektorów
+    pub vector_max_magnitude: f32,
+    pub vector_arrows: bool,
+    /// Kierunek światła podglądu normalnych; None = relight wyłączony
+    pub relight: Option<[f32; 3]>,
+    /// Pasmo focus peakingu w widoku głębi
+    pub focus_band: Option<(f32, f32)>,
+    pub bloom: Option<Bloom>,
+    pub sharpen: Option<Sharpen>,
+    pub env_view: EnvView,
+    pub stereo_mode: StereoMode,
+}
+
+impl RenderSettings {
+    /// Graf `ProcessingGraph::standard`, bez transformacji, trybów diagnostycznych i filtrów
+    pub fn standard(exposure: f32, gamma: f32) -> Self {
+        RenderSettings {
+            graph: ProcessingGraph::standard(exposure, gamma),
+            transform: DisplayTransform::default(),
+            grayscale: GrayscaleMode::Off,
+            gamut_warning: GamutWarning::Off,
+            false_color: false,
+            vector_max_magnitude: image_processing::DEFAULT_VECTOR_MAX_MAGNITUDE,
+            vector_arrows: false,
+            relight: None,
+            focus_band: None,
+            bloom: None,
+            sharpen: None,
+            env_view: EnvView::Flat,
+            stereo_mode: StereoMode::Single,
+        }
+    }
+
+    /// Ustawienia renderu eksportu: bez nakładek diagnostycznych (skala szarości, ostrzeżenie
+    /// o gamucie, false color, focus peaking, strzałki wektorów), z filtrami włączonymi dla eksportu
+    pub fn for_export(self) -> Self {
+        RenderSettings {
+            grayscale: GrayscaleMode::Off,
+            gamut_warning: GamutWarning::Off,
+            false_color: false,
+            vector_arrows: false,
+            focus_band: None,
+            bloom: self.bloom.filter(|b| !b.display_only),
+            sharpen: self.sharpen.filter(|s| s.in_exports),
+            ..self
+        }
+    }
+
+    pub fn to_json(self) -> Value {
+        let graph = &self.graph;
+        json!({
+            "input": graph.input.label(),
+            "file_primaries": graph.file_primaries,
+            "exposure": graph.exposure,
+            "exposure_mode": graph.exposure_mode.label(),
+            "middle_gray": graph.middle_gray,
+            "white_balance": graph.white_balance,
+            "gamma": graph.gamma,
+            "local_tonemap": graph.local_tonemap.map(|local| [local.radius, local.strength]),
+            "stages": Stage::ALL.iter().filter(|s| graph.is_enabled(**s)).map(|s| s.label()).collect::<Vec<_>>(),
+            "quarter_turns": self.transform.quarter_turns,
+            "flip_h": self.transform.flip_h,
+            "flip_v": self.transform.flip_v,
+            "grayscale": self.grayscale.label(),
+            "gamut_warning": self.gamut_warning.label(),
+            "false_color": self.false_color,
+            "vector_max_magnitude": self.vector_max_magnitude,
+            "vector_arrows": self.vector_arrows,
+            "relight": self.relight,
+            "focus_band": self.focus_band.map(|(near, far)| [near, far]),
+            "bloom": self.bloom.map(|b| json!({ "threshold": b.threshold, "intensity": b.intensity, "display_only": b.display_only })),
+            "sharpen": self.sharpen.map(|s| json!({ "amount": s.amount, "radius": s.radius, "in_exports": s.in_exports })),
+            "env_view": match self.env_view {
+                EnvView::Panorama { yaw, pitch, fov } => json!({ "mode": "Panorama", "yaw": yaw, "pitch": pitch, "fov": fov }),
+                view => json!({ "mode": view.label() }),
+            },
+            "stereo": self.stereo_mode.label(),
+        })
+    }
+
+    /// Migawka zapisana przez `to_json`; None, gdy brakuje któregoś pola grafu
+    pub fn from_json(value: &Value) -> Option<Self> {
+        let text = |key: &str| value.get(key).and_then(Value::as_str);
+        let number = |v: &Value, key: &str| v.get(key).and_then(Value::as_f64).map(|n| n as f32);
+        let floats = |key: &str| -> Option<Vec<f32>> {
+            value.get(key)?.as_array()?.iter().map(|v| v.as_f64().map(|n| n as f32)).collect()
+        };
+        let triple = |v: Vec<f32>| <[f32; 3]>::try_from(v).ok();
+
+        let input_label = text("input")?;
+        let input = InputColorSpace::ALL.into_iter().find(|s| s.label() == input_label)?;
+        let file_primaries = value.get("file_primaries")?.as_array()?.iter()
+            .map(|row| row.as_array()?.iter().map(|v| v.as_f64().map(|n| n as f32)).collect::<Option<Vec<_>>>().and_then(triple))
+            .collect::<Option<Vec<_>>>()
+            .and_then(|rows| <[[f32; 3]; 3]>::try_from(rows).ok())?;
+        let mut graph = ProcessingGraph::standard(number(value, "exposure")?, number(value, "gamma")?);
+        graph.input = input;
+        graph.exposure_mode = ExposureMode::from_label(text("exposure_mode")?);
+        graph.middle_gray = number(value, "middle_gray")?;
+        graph.white_balance = triple(floats("white_balance")?)?;
+        graph.local_tonemap = floats("local_tonemap").and_then(|v| match v[..] {
+            [radius, strength] => Some(LocalTonemap { radius, strength }),
+            _ => None,
+        });
+        graph.file_primaries = file_primaries;
+        let stages: Vec<&str> = value.get("stages")?.as_array()?.iter().filter_map(Value::as_str).collect();
+        for stage in Stage::ALL {
+            graph.set_enabled(stage, stages.contains(&stage.label()));
+        }
+
+        let flag = |key: &str| value.get(key).and_then(Value::as_bool).unwrap_or(false);
+        let env_view = value.get("env_view").map_or(EnvView::Flat, |view| {
+            let angle = |key: &str| number(view, key).unwrap_or(0.0);
+            EnvView::from_label(view.get("mode").and_then(Value::as_str).unwrap_or_default(), angle("yaw"), angle("pitch"), angle("fov"))
+        });
+        Some(RenderSettings {
+            graph,
+            transform: DisplayTransform {
+                quarter_turns: value.get("quarter_turns").and_then(Value::as_u64).unwrap_or(0) as u8 & 3,
+                flip_h: flag("flip_h"),
+                flip_v: flag("flip_v"),
+            },
+            grayscale: GrayscaleMode::from_label(text("grayscale").unwrap_or_default()),
+            gamut_warning: GamutWarning::from_label(text("gamut_warning").unwrap_or_default()),
+            false_color: flag("false_color"),
+            vector_max_magnitude: number(value, "vector_max_magnitude").unwrap_or(image_processing::DEFAULT_VECTOR_MAX_MAGNITUDE),
+            vector_arrows: flag("vector_arrows"),
+            relight: floats("relight").and_then(triple),
+            focus_band: floats("focus_band").and_then(|v| match v[..] {
+                [near, far] => Some((near, far)),
+                _ => None,
+            }),
+            bloom: value.get("bloom").filter(|b| b.is_object()).and_then(|b| Some(Bloom {
+                threshold: number(b, "threshold")?,
+                intensity: number(b, "intensity")?,
+                display_only: b.get("display_only")?.as_bool()?,
+            })),
+            sharpen: value.get("sharpen").filter(|s| s.is_object()).and_then(|s| Some(Sharpen {
+                amount: number(s, "amount")?,
+                radius: number(s, "radius")?,
+                in_exports: s.get("in_exports")?.as_bool()?,
+            })),
+            env_view,
+            stereo_mode: StereoMode::from_label(text("stereo").unwrap_or_default()),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn snapshot_survives_json_round_trip() {
+        let mut graph = ProcessingGraph::standard(1.5, 2.4);
+        graph.input = InputColorSpace::FilePrimaries;
+        graph.exposure_mode = ExposureMode::DisplayGain;
+        graph.middle_gray = 0.25;
+        graph.white_balance = [1.1, 1.0, 0.9];
+        graph.local_tonemap = Some(LocalTonemap { radius: 0.05, strength: 0.75 });
+        graph.file_primaries = [[0.9, 0.1, 0.0], [0.05, 0.9, 0.05], [0.0, 0.2, 0.8]];
+        graph.set_enabled(Stage::WhiteBalance, false);
+        let settings = RenderSettings {
+            graph,
+            transform: DisplayTransform { quarter_turns: 3, flip_h: true, flip_v: false },
+            grayscale: GrayscaleMode::Average,
+            gamut_warning: GamutWarning::DisplayP3,
+            false_color: true,
+            vector_max_magnitude: 4.0,
+            vector_arrows: true,
+            relight: Some([0.0, 0.6, 0.8]),
+            focus_band: Some((0.25, 0.5)),
+            bloom: Some(Bloom { threshold: 1.5, intensity: 0.25, display_only: false }),
+            sharpen: Some(Sharpen { amount: 0.5, radius: 1.5, in_exports: true }),
+            env_view: EnvView::Panorama { yaw: 30.0, pitch: -10.0, fov: 75.0 },
+            stereo_mode: StereoMode::Anaglyph,
+        };
+        let text = settings.to_json().to_string();
+        let restored = RenderSettings::from_json(&serde_json::from_str(&text).unwrap()).unwrap();
+        assert_eq!(restored, settings);
+
+        let plain = RenderSettings::standard(0.0, 2.2);
+        assert_eq!(RenderSettings::from_json(&plain.to_json()), Some(plain));
+        assert_eq!(RenderSettings::from_json(&json!({ "exposure": 1.0 })), None);
+    }
+}
@@ -0,0 +1,159 @@
+// Skrypty wsadowe (Rhai) dla automatyzacji QC: otwieranie plików, statystyki, eksport podglądu.
+// Skrypt działa poza wątkiem UI na własnym, niezależnym od okna stanie; postęp raportuje przez callback.
+// Moduł kompilowany tylko z funkcją `scripting` (cargo build --features scripting).
+
+use std::cell::RefCell;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use rhai::{Array, Dynamic, Engine, EvalAltResult, Map};
+use tracing::info;
+use crate::cancel::CancelToken;
+use crate::image_cache::ImageCache;
+use crate::progress::NoopProgress;
+use crate::image_processing::process_pixel;
+
+type ScriptResult<T> = Result<T, Box<EvalAltResult>>;
+
+/// Stan skryptu: bieżący plik i parametry podglądu używane przy eksporcie
+struct ScriptState {
+    cache: Option<ImageCache>,
+    path: Option<PathBuf>,
+    exposure: f32,
+    gamma: f32,
+}
+
+impl ScriptState {
+    fn cache(&self) -> ScriptResult<&ImageCache> {
+        self.cache.as_ref().ok_or_else(|| "no file opened - call open(path) first".into())
+    }
+}
+
+/// Uruchamia skrypt; `progress(ułamek, komunikat)` wywoływane jest z wątku skryptu
+pub fn run_script(script: &Path, progress: impl Fn(f32, &str) + 'static) -> Result<(), String> {
+    let state = Rc::new(RefCell::new(ScriptState { cache: None, path: None, exposure: 0.0, gamma: 2.2 }));
+    let progress = Rc::new(progress);
+    let mut engine = Engine::new();
+
+    engine.on_print(|text| info!(target: "script", "{}", text));
+    engine.on_debug(|text, _source, pos| info!(target: "script", "{:?}: {}", pos, text));
+
+    engine.register_fn("list_exr", |dir: &str| -> ScriptResult<Array> {
+        let mut files: Vec<PathBuf> = std::fs::read_dir(dir)
+            .map_err(|e| format!("cannot read '{}': {}", dir, e))?
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .filter(|p| p.extension().and_then(|e| e.to_str()).is_some_and(|e| e.eq_ignore_ascii_case("exr")))
+            .collect();
+        files.sort();
+        Ok(files.into_iter().map(|p| Dynamic::from(p.display().to_string())).collect())
+    });
+
+    engine.register_fn("open", {
+        let state = state.clone();
+        move |path: &str| -> ScriptResult<()> {
+            let path = PathBuf::from(path);
+            let cache = ImageCache::new(&path, &CancelToken::new(), &NoopProgress).map_err(|e| format!("open '{}': {}", path.display(), e))?;
+            info!(target: "script", "opened {}", path.display());
+            let mut state = state.borrow_mut();
+            state.cache = Some(cache);
+            state.path = Some(path);
+            Ok(())
+        }
+    });
+
+    engine.register_fn("layers", {
+        let state = state.clone();
+        move || -> ScriptResult<Array> {
+            let state = state.borrow();
+            Ok(state.cache()?.layers_info.iter().map(|l| Dynamic::from(l.name.clone())).collect())
+        }
+    });
+
+    engine.register_fn("load_layer", {
+        let state = state.clone();
+        move |name: &str| -> ScriptResult<()> {
+            let mut state = state.borrow_mut();
+            let path = state.path.clone().ok_or("no file opened - call open(path) first")?;
+            let cache = state.cache.as_mut().ok_or("no file opened - call open(path) first")?;
+            cache.load_layer(&path, name).map_err(|e| format!("load_layer '{}': {}", name, e).into())
+        }
+    });
+
+    engine.register_fn("set_exposure", {
+        let state = state.clone();
+        move |value: f64| state.borrow_mut().exposure = value as f32
+    });
+
+    engine.register_fn("set_gamma", {
+        let state = state.clone();
+        move |value: f64| state.borrow_mut().gamma = value as f32
+    });
+
+    engine.register_fn("get_stats", {
+        let state = state.clone();
+        move || -> ScriptResult<Map> {
+            let state = state.borrow();
+            Ok(layer_stats(state.cache()?))
+        }
+    });
+
+    // Eksport podglądu (ekspozycja + gamma jak w oknie); format z rozszerzenia (png, jpg)
+    engine.register_fn("export", {
+        let state = state.clone();
+        move |path: &str| -> ScriptResult<()> {
+            let state = state.borrow();
+            let cache = state.cache()?;
+            let mut buffer: Vec<u8> = Vec::with_capacity(cache.raw_pixels.len() * 4);
+            for &(r, g, b, a) in cache.raw_pixels.iter() {
+                let px = process_pixel(r, g, b, a, state.exposure, state.gamma);
+                buffer.extend_from_slice(&[px.r, px.g, px.b, px.a]);
+            }
+            image::save_buffer(path, &buffer, cache.width, cache.height, image::ExtendedColorType::Rgba8)
+                .map_err(|e| format!("export '{}': {}", path, e).into())
+        }
+    });
+
+    engine.register_fn("progress", {
+        let progress = progress.clone();
+        move |done: i64, total: i64| {
+            let fraction = if total > 0 { done as f32 / total as f32 } else { 0.0 };
+            progress(fraction, &format!("Script: {}/{}", done, total));
+        }
+    });
+
+    progress(0.0, &format!("Running script {}", script.display()));
+    let result = engine.run_file(script.to_path_buf()).map_err(|e| e.to_string());
+    progress(1.0, if result.is_ok() { "Script finished" } else { "Script failed" });
+    result
+}
+
+/// Liczba wartości NaN/Inf oraz min/max/średnia kanałów RGB bieżącej warstwy
+fn layer_stats(cache: &ImageCache) -> Map {
+    let (mut nan, mut inf) = (0i64, 0i64);
+    let (mut min, mut max, mut sum, mut count) = (f64::INFINITY, f64::NEG_INFINITY, 0.0f64, 0i64);
+    for &(r, g, b, _a) in cache.raw_pixels.iter() {
+        for v in [r, g, b] {
+            if v.is_nan() {
+                nan += 1;
+            } else if v.is_infinite() {
+                inf += 1;
+            } else {
+                let v = v as f64;
+                min = min.min(v);
+                max = max.max(v);
+                sum += v;
+                count += 1;
+            }
+        }
+    }
+
+    let mut stats = Map::new();
+    stats.insert("layer".into(), Dynamic::from(cache.current_layer_name.clone()));
+    stats.insert("width".into(), Dynamic::from(cache.width as i64));
+    stats.insert("height".into(), Dynamic::from(cache.height as i64));
+    stats.insert("nan".into(), Dynamic::from(nan));
+    stats.insert("inf".into(), Dynamic::from(inf));
+    stats.insert("min".into(), Dynamic::from(if count > 0 { min } else { 0.0 }));
+    stats.insert("max".into(), Dynamic::from(if count > 0 { max } else { 0.0 }));
+    stats.insert("mean".into(), Dynamic::from(if count > 0 { sum / count as f64 } else { 0.0 }));
+    stats
+}
@@ -0,0 +1,253 @@
+// Sekwencje klatek: pliki EXR jednego folderu o nazwach <prefiks><numer><sufiks> (np. shot.0042.exr
+// albo shot_0042_beauty.exr), grupowane po prefiksie i sufiksie. Numerem klatki jest ostatni ciąg
+// cyfr w nazwie bez rozszerzenia. Sprawdzenie spójności czyta same nagłówki (szybki skan, równolegle)
+// i zgłasza brakujące klatki oraz zmiany rozdzielczości, zestawu warstw i kompresji.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use anyhow::Context;
+use rayon::prelude::*;
+use tracing::{info, warn};
+use crate::cancel::CancelToken;
+use crate::io::fast_exr_metadata::{self, FastExrMetadata};
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Sequence {
+    pub prefix: String,
+    pub suffix: String,
+    /// Liczba cyfr numeru pierwszej klatki (do etykiety `shot.####.exr`)
+    pub padding: usize,
+    /// Klatki rosnąco wg numeru
+    pub frames: Vec<(i64, PathBuf)>,
+}
+
+impl Sequence {
+    pub fn first(&self) -> i64 {
+        self.frames.first().map_or(0, |f| f.0)
+    }
+
+    pub fn last(&self) -> i64 {
+        self.frames.last().map_or(0, |f| f.0)
+    }
+
+    /// Nazwa z '#' w miejscu numeru i zakres klatek, np. "shot.####.exr [1001-1100]"
+    pub fn label(&self) -> String {
+        format!("{}{}{} [{}-{}]", self.prefix, "#".repeat(self.padding.max(1)), self.suffix, self.first(), self.last())
+    }
+
+    /// Krok numeracji: najmniejsza różnica sąsiednich klatek (1, albo np. 2 dla renderu "na dwójkach")
+    pub fn step(&self) -> i64 {
+        self.frames.windows(2).map(|w| w[1].0 - w[0].0).min().unwrap_or(1).max(1)
+    }
+
+    /// Luki w numeracji jako zakresy brakujących klatek (włącznie)
+    pub fn missing(&self) -> Vec<(i64, i64)> {
+        let step = self.step();
+        self.frames.windows(2)
+            .filter(|w| w[1].0 - w[0].0 > step)
+            .map(|w| (w[0].0 + step, w[1].0 - step))
+            .collect()
+    }
+
+    pub fn index_of(&self, path: &Path) -> Option<usize> {
+        self.frames.iter().position(|(_, p)| p == path)
+    }
+}
+
+/// Prefiks, numer i sufiks (z rozszerzeniem) nazwy pliku; None gdy nazwa nie zawiera cyfr
+fn split_frame(file_name: &str) -> Option<(&str, &str, &str)> {
+    let stem_end = file_name.rfind('.').unwrap_or(file_name.len());
+    let stem = &file_name[..stem_end];
+    let end = stem.rfind(|c: char| c.is_ascii_digit())? + 1;
+    let start = stem[..end].rfind(|c: char| !c.is_ascii_digit()).map_or(0, |i| i + 1);
+    Some((&file_name[..start], &file_name[start..end], &file_name[end..]))
+}
+
+/// Sekwencje (co najmniej dwie klatki) wśród podanych plików; kolejność wg prefiksu i sufiksu
+pub fn detect(files: &[PathBuf]) -> Vec<Sequence> {
+    let mut groups: BTreeMap<(PathBuf, String, String), Vec<(i64, PathBuf)>> = BTreeMap::new();
+    for path in files {
+        let Some(name) = path.file_name().and_then(|n| n.to_str()) else { continue };
+        let Some((prefix, digits, suffix)) = split_frame(name) else { continue };
+        let Ok(frame) = digits.parse::<i64>() else { continue };
+        let dir = path.parent().map(Path::to_path_buf).unwrap_or_default();
+        groups.entry((dir, prefix.to_string(), suffix.to_string())).or_default().push((frame, path.clone()));
+    }
+    groups.into_iter()
+        .filter(|(_, frames)| frames.len() > 1)
+        .map(|((_, prefix, suffix), mut frames)| {
+            frames.sort_by_key(|f| f.0);
+            let first = frames[0].1.file_name().and_then(|n| n.to_str()).and_then(split_frame);
+            Sequence { padding: first.map_or(0, |(_, digits, _)| digits.len()), prefix, suffix, frames }
+        })
+        .collect()
+}
+
+/// Sekwencja, do której należy plik (pliki EXR tego samego folderu, bez plików proxy)
+pub fn sequence_of(path: &Path) -> anyhow::Result<Option<Sequence>> {
+    let dir = path.parent().unwrap_or(Path::new("."));
+    let files: Vec<PathBuf> = fs::read_dir(dir)
+        .with_context(|| format!("Nie można odczytać katalogu: {}", dir.display()))?
+        .flatten()
+        .map(|e| e.path())
+        .filter(|p| p.extension().is_some_and(|e| e.eq_ignore_ascii_case("exr")) && !crate::proxy_files::is_proxy(p))
+        .collect();
+    Ok(detect(&files).into_iter().find(|s| s.index_of(path).is_some()))
+}
+
+/// Niespójność sekwencji; zmiany zgłaszane względem poprzedniej czytelnej klatki
+#[derive(Clone, Debug, PartialEq)]
+pub enum Issue {
+    Missing { first: i64, last: i64 },
+    Unreadable { frame: i64, error: String },
+    Resolution { frame: i64, from: (usize, usize), to: (usize, usize) },
+    Layers { frame: i64, added: Vec<String>, removed: Vec<String> },
+    Compression { frame: i64, from: String, to: String },
+}
+
+impl Issue {
+    pub fn first_frame(&self) -> i64 {
+        match self {
+            Issue::Missing { first, .. } => *first,
+            Issue::Unreadable { frame, .. } | Issue::Resolution { frame, .. } | Issue::Layers { frame, .. } | Issue::Compression { frame, .. } => *frame,
+        }
+    }
+
+    /// Klatka albo zakres klatek (pierwsza kolumna tabeli)
+    pub fn frames(&self) -> String {
+        match self {
+            Issue::Missing { first, last } if first == last => first.to_string(),
+            Issue::Missing { first, last } => format!("{}-{}", first, last),
+            other => other.first_frame().to_string(),
+        }
+    }
+}
+
+impl fmt::Display for Issue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let layers = |names: &[String]| names.iter().map(|n| if n.is_empty() { "(default)" } else { n.as_str() }).collect::<Vec<_>>().join(", ");
+        match self {
+            Issue::Missing { first, last } => write!(f, "missing {} frame(s)", last - first + 1),
+            Issue::Unreadable { error, .. } => write!(f, "unreadable: {}", error),
+            Issue::Resolution { from, to, .. } => write!(f, "resolution {}×{} → {}×{}", from.0, from.1, to.0, to.1),
+            Issue::Layers { added, removed, .. } => {
+                let mut parts = Vec::new();
+                if !added.is_empty() { parts.push(format!("added {}", layers(added))); }
+                if !removed.is_empty() { parts.push(format!("removed {}", layers(removed))); }
+                write!(f, "layers {}", parts.join("; "))
+            }
+            Issue::Compression { from, to, .. } => write!(f, "compression {} → {}", from, to),
+        }
+    }
+}
+
+pub struct SequenceCheck {
+    pub label: String,
+    pub frames: usize,
+    pub issues: Vec<Issue>,
+}
+
+impl SequenceCheck {
+    /// Wiersze tabeli (klucz, wartość) jak w oknie metadanych; pusta wartość = nagłówek
+    pub fn rows(&self) -> Vec<(String, String)> {
+        let mut rows = vec![
+            (self.label.clone(), String::new()),
+            ("Frames".to_string(), self.frames.to_string()),
+        ];
+        if self.issues.is_empty() {
+            rows.push(("Result".to_string(), "No inconsistencies".to_string()));
+        } else {
+            rows.push((format!("Issues ({})", self.issues.len()), String::new()));
+            rows.extend(self.issues.iter().map(|issue| (issue.frames(), issue.to_string())));
+        }
+        rows
+    }
+}
+
+/// Skanuje nagłówki wszystkich klatek równolegle; każda niespójność trafia też do konsoli
+/// (ostrzeżenie). `progress(ułamek, opis)` co klatkę.
+pub fn check(sequence: &Sequence, cancel: &CancelToken, progress: impl Fn(f32, &str) + Sync) -> anyhow::Result<SequenceCheck> {
+    let total = sequence.frames.len();
+    let done = AtomicUsize::new(0);
+    let headers: Vec<Option<Result<FastExrMetadata, String>>> = sequence.frames.par_iter()
+        .map(|(_, path)| {
+            if cancel.is_cancelled() {
+                return None;
+            }
+            let meta = fast_exr_metadata::read_cached(path).map_err(|e| e.to_string());
+            let n = done.fetch_add(1, Ordering::Relaxed) + 1;
+            progress(n as f32 / total as f32, &format!("Checking frames {}/{}", n, total));
+            Some(meta)
+        })
+        .collect();
+    cancel.check()?;
+
+    let mut issues: Vec<Issue> = sequence.missing().into_iter().map(|(first, last)| Issue::Missing { first, last }).collect();
+    let mut previous: Option<&FastExrMetadata> = None;
+    for ((frame, _), meta) in sequence.frames.iter().zip(headers.iter().flatten()) {
+        let meta = match meta {
+            Ok(meta) => meta,
+            Err(error) => {
+                issues.push(Issue::Unreadable { frame: *frame, error: error.clone() });
+                continue;
+            }
+        };
+        if let Some(prev) = previous {
+            issues.extend(changes(*frame, prev, meta));
+        }
+        previous = Some(meta);
+    }
+    // Stabilnie: luka przed zmianami klatki, która ją kończy
+    issues.sort_by_key(Issue::first_frame);
+
+    let label = sequence.label();
+    for issue in &issues {
+        warn!(target: "io", "{}: frame {}: {}", label, issue.frames(), issue);
+    }
+    info!(target: "io", "sequence check {}: {} frames, {} issue(s)", label, total, issues.len());
+    Ok(SequenceCheck { label, frames: total, issues })
+}
+
+fn changes(frame: i64, prev: &FastExrMetadata, meta: &FastExrMetadata) -> Vec<Issue> {
+    let mut issues = Vec::new();
+    if (prev.width, prev.height) != (meta.width, meta.height) {
+        issues.push(Issue::Resolution { frame, from: (prev.width, prev.height), to: (meta.width, meta.height) });
+    }
+    if prev.layer_names != meta.layer_names {
+        let added = meta.layer_names.iter().filter(|n| !prev.layer_names.contains(n)).cloned().collect();
+        let removed = prev.layer_names.iter().filter(|n| !meta.layer_names.contains(n)).cloned().collect();
+        issues.push(Issue::Layers { frame, added, removed });
+    }
+    let compression = |m: &FastExrMetadata| m.compressions.iter().copied().map(fast_exr_metadata::compression_label).collect::<Vec<_>>().join("/");
+    let (from, to) = (compression(prev), compression(meta));
+    if from != to {
+        issues.push(Issue::Compression { frame, from, to });
+    }
+    issues
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detects_sequences_and_gaps() {
+        let files: Vec<PathBuf> = ["shot.1001.exr", "shot.1002.exr", "shot.1005.exr", "shot.1006.exr", "shot_v2_0010_beauty.exr", "shot_v2_0011_beauty.exr", "plate.exr", "single.0001.exr"]
+            .iter()
+            .map(|n| Path::new("seq").join(n))
+            .collect();
+        let sequences = detect(&files);
+        assert_eq!(sequences.len(), 2);
+        let shot = &sequences[0];
+        assert_eq!(shot.label(), "shot.####.exr [1001-1006]");
+        assert_eq!(shot.missing(), vec![(1003, 1004)]);
+        assert_eq!(sequences[1].label(), "shot_v2_####_beauty.exr [10-11]");
+
+        // Render "na dwójkach" nie ma luk
+        let twos = detect(&["a.0001.exr", "a.0003.exr", "a.0005.exr"].map(PathBuf::from));
+        assert_eq!((twos[0].step(), twos[0].missing()), (2, vec![]));
+    }
+}
@@ -0,0 +1,90 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// Stan sesji przywracany po awarii: ostatni plik, folder roboczy i parametry podglądu
+#[derive(Clone, Debug, PartialEq)]
+pub struct SessionState {
+    pub last_file: Option<PathBuf>,
+    pub working_dir: Option<PathBuf>,
+    pub exposure: f32,
+    pub gamma: f32,
+}
+
+impl Default for SessionState {
+    fn default() -> Self {
+        Self { last_file: None, working_dir: None, exposure: 0.0, gamma: 2.2 }
+    }
+}
+
+// Bieżąca sesja – globalna, aby hook paniki mógł ją zapisać bez dostępu do UI
+static SESSION: Mutex<Option<SessionState>> = Mutex::new(None);
+
+const SESSION_FILE: &str = "session.txt";
+
+/// Katalog danych aplikacji (%APPDATA%\EXRuster, $XDG_CONFIG_HOME/EXRuster lub ~/.config/EXRuster)
+pub fn app_data_dir() -> PathBuf {
+    let base = std::env::var_os("APPDATA")
+        .or_else(|| std::env::var_os("XDG_CONFIG_HOME"))
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|h| PathBuf::from(h).join(".config")))
+        .unwrap_or_else(std::env::temp_dir);
+    base.join("EXRuster")
+}
+
+/// Aktualizuje bieżącą sesję; `persist` od razu zapisuje ją na dysk
+pub fn update(persist: bool, f: impl FnOnce(&mut SessionState)) {
+    let snapshot = {
+        let mut guard = SESSION.lock().unwrap_or_else(|p| p.into_inner());
+        let state = guard.get_or_insert_with(SessionState::default);
+        f(state);
+        state.clone()
+    };
+    if persist {
+        let _ = save_to(&app_data_dir().join(SESSION_FILE), &snapshot);
+    }
+}
+
+/// Zapisuje bieżącą sesję na dysk. Używa `try_lock`, bo może być wołane z hooka paniki.
+pub fn save_current() {
+    let snapshot = match SESSION.try_lock() {
+        Ok(guard) => guard.clone(),
+        Err(std::sync::TryLockError::Poisoned(p)) => p.into_inner().clone(),
+        Err(std::sync::TryLockError::WouldBlock) => None,
+    };
+    if let Some(state) = snapshot {
+        let _ = save_to(&app_data_dir().join(SESSION_FILE), &state);
+    }
+}
+
+/// Wczytuje ostatnio zapisaną sesję (None jeśli brak pliku)
+pub fn load_saved() -> Option<SessionState> {
+    let text = fs::read_to_string(app_data_dir().join(SESSION_FILE)).ok()?;
+    let mut state = SessionState::default();
+    for line in text.lines() {
+        let Some((key, value)) = line.split_once('=') else { continue; };
+        match key.trim() {
+            "last_file" if !value.is_empty() => state.last_file = Some(PathBuf::from(value)),
+            "working_dir" if !value.is_empty() => state.working_dir = Some(PathBuf::from(value)),
+            "exposure" => state.exposure = value.trim().parse().unwrap_or(state.exposure),
+            "gamma" => state.gamma = value.trim().parse().unwrap_or(state.gamma),
+            _ => {}
+        }
+    }
+    Some(state)
+}
+
+/// Zapis atomowy: najpierw plik tymczasowy, potem rename – przerwany zapis nie psuje poprzedniej sesji
+fn save_to(path: &Path, state: &SessionState) -> std::io::Result<()> {
+    if let Some(dir) = path.parent() { fs::create_dir_all(dir)?; }
+    let text = format!(
+        "last_file={}\nworking_dir={}\nexposure={}\ngamma={}\n",
+        state.last_file.as_deref().map(|p| p.display().to_string()).unwrap_or_default(),
+        state.working_dir.as_deref().map(|p| p.display().to_string()).unwrap_or_default(),
+        state.exposure,
+        state.gamma,
+    );
+    let tmp = path.with_extension("tmp");
+    fs::write(&tmp, text)?;
+    fs::rename(&tmp, path)
+}
@@ -0,0 +1,347 @@
+// Wektorowe kernele pipeline'u koloru (`ToneParams`) z wyborem szerokości w czasie działania:
+// 8 pasów f32 (AVX2), gdy procesor je obsługuje, w przeciwnym razie ścieżka skalarna.
+// Funkcja `portable-simd` (nightly) dodaje kernel std::simd używany, gdy AVX2 nie ma – domyślna
+// kompilacja działa na stabilnym Ruście. Kernele wektorowe powtarzają kolejność operacji wersji
+// skalarnej (bez FMA, zaokrąglenie jak `f32::round`), więc wyniki są identyczne co do bitu.
+
+use std::sync::LazyLock;
+use slint::Rgba8Pixel;
+use tracing::debug;
+use crate::image_processing::ToneParams;
+
+/// Piksel źródłowy RGBA (jak w `layer_cache::Pixels`)
+type Px = (f32, f32, f32, f32);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SimdLevel {
+    Scalar,
+    /// f32x8
+    Avx2,
+    /// std::simd f32x8 (funkcja `portable-simd`)
+    Portable,
+}
+
+impl SimdLevel {
+    pub fn lanes(self) -> usize {
+        match self {
+            SimdLevel::Scalar => 1,
+            SimdLevel::Avx2 | SimdLevel::Portable => 8,
+        }
+    }
+}
+
+static LEVEL: LazyLock<SimdLevel> = LazyLock::new(|| {
+    let level = detect();
+    debug!(target: "processing", "tone mapping kernel: {:?} ({} lanes)", level, level.lanes());
+    level
+});
+
+fn detect() -> SimdLevel {
+    #[cfg(target_arch = "x86_64")]
+    if is_x86_feature_detected!("avx2") {
+        return SimdLevel::Avx2;
+    }
+    if cfg!(feature = "portable-simd") {
+        return SimdLevel::Portable;
+    }
+    SimdLevel::Scalar
+}
+
+/// Najszerszy kernel dostępny na tym procesorze (wykrywany raz)
+pub fn simd_level() -> SimdLevel {
+    *LEVEL
+}
+
+/// Kernel przetwarzający ciąg pikseli; `input` i `out` mają tę samą długość
+pub trait ToneKernel {
+    /// Pikseli na iterację
+    const LANES: usize;
+    fn process(params: &ToneParams, input: &[Px], out: &mut [Rgba8Pixel]);
+}
+
+pub struct Scalar;
+
+impl ToneKernel for Scalar {
+    const LANES: usize = 1;
+
+    fn process(params: &ToneParams, input: &[Px], out: &mut [Rgba8Pixel]) {
+        for (&(r, g, b, a), px) in input.iter().zip(out.iter_mut()) {
+            *px = params.pixel(r, g, b, a);
+        }
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+pub struct Avx2;
+
+#[cfg(target_arch = "x86_64")]
+impl ToneKernel for Avx2 {
+    const LANES: usize = 8;
+
+    fn process(params: &ToneParams, input: &[Px], out: &mut [Rgba8Pixel]) {
+        // Dowolna gamma wymaga powf, którego nie ma w wersji wektorowej (podobnie wyłączony tone
+        // mapping) – wtedy cały wiersz skalarnie
+        if !is_x86_feature_detected!("avx2") || !params.is_vectorizable() {
+            return Scalar::process(params, input, out);
+        }
+        let split = input.len() / Self::LANES * Self::LANES;
+        // SAFETY: obsługa AVX2 sprawdzona wyżej, długość jest wielokrotnością 8
+        unsafe { avx2::process(params, &input[..split], &mut out[..split]) };
+        Scalar::process(params, &input[split..], &mut out[split..]);
+    }
+}
+
+/// Przetwarza wiersz najszerszym dostępnym kernelem
+pub fn process_row(params: &ToneParams, input: &[Px], out: &mut [Rgba8Pixel]) {
+    match simd_level() {
+        #[cfg(target_arch = "x86_64")]
+        SimdLevel::Avx2 => Avx2::process(params, input, out),
+        #[cfg(feature = "portable-simd")]
+        SimdLevel::Portable => Portable::process(params, input, out),
+        _ => Scalar::process(params, input, out),
+    }
+}
+
+#[cfg(feature = "portable-simd")]
+pub struct Portable;
+
+#[cfg(feature = "portable-simd")]
+impl ToneKernel for Portable {
+    const LANES: usize = 8;
+
+    fn process(params: &ToneParams, input: &[Px], out: &mut [Rgba8Pixel]) {
+        if !params.is_vectorizable() {
+            return Scalar::process(params, input, out);
+        }
+        let split = input.len() / Self::LANES * Self::LANES;
+        portable::process(params, &input[..split], &mut out[..split]);
+        Scalar::process(params, &input[split..], &mut out[split..]);
+    }
+}
+
+#[cfg(feature = "portable-simd")]
+mod portable {
+    use std::simd::prelude::*;
+    use std::simd::StdFloat;
+    use slint::Rgba8Pixel;
+    use crate::image_processing::{GammaCurve, ToneParams, ACES_COEFFICIENTS};
+    use super::Px;
+
+    /// Długość `input` musi być wielokrotnością 8
+    pub fn process(params: &ToneParams, input: &[Px], out: &mut [Rgba8Pixel]) {
+        let (zero, one) = (f32x8::splat(0.0), f32x8::splat(1.0));
+        let [ka, kb, kc, kd, ke] = ACES_COEFFICIENTS.map(f32x8::splat);
+        let scene = f32x8::splat(params.scene_multiplier);
+        let display = f32x8::splat(params.display_multiplier);
+        let to_u8 = |v: f32x8| (v * f32x8::splat(255.0)).round().simd_clamp(zero, f32x8::splat(255.0)).cast::<u8>().to_array();
+
+        for (pixels, out) in input.chunks_exact(8).zip(out.chunks_exact_mut(8)) {
+            let mut r = f32x8::from_array(std::array::from_fn(|i| pixels[i].0));
+            let mut g = f32x8::from_array(std::array::from_fn(|i| pixels[i].1));
+            let mut b = f32x8::from_array(std::array::from_fn(|i| pixels[i].2));
+            let alpha = f32x8::from_array(std::array::from_fn(|i| pixels[i].3));
+
+            if let Some(m) = params.matrix {
+                let dot = |k: usize| f32x8::splat(m[k][0]) * r + f32x8::splat(m[k][1]) * g + f32x8::splat(m[k][2]) * b;
+                (r, g, b) = (dot(0), dot(1), dot(2));
+            }
+
+            let channel = |v: f32x8| {
+                let x = v.is_finite().select(v.simd_max(zero), zero) * scene;
+                // clamp przepuszcza NaN (przepełnienie ACES), a `simd_min` zamienia go na 1 – jak w wersji skalarnej
+                let t = ((x * (ka * x + kb)) / (x * (kc * x + kd) + ke)).simd_clamp(zero, one);
+                let tone_mapped = (t * display).simd_min(one);
+                to_u8(match params.gamma {
+                    GammaCurve::Sqrt => tone_mapped.sqrt(),
+                    GammaCurve::Fast22 => {
+                        let sqrt_val = tone_mapped.sqrt();
+                        sqrt_val * sqrt_val.sqrt()
+                    }
+                    GammaCurve::Linear | GammaCurve::Power(_) => tone_mapped,
+                })
+            };
+            let (r, g, b) = (channel(r), channel(g), channel(b));
+            let alpha = to_u8(alpha.is_finite().select(alpha.simd_clamp(zero, one), one));
+
+            for (i, px) in out.iter_mut().enumerate() {
+                *px = Rgba8Pixel { r: r[i], g: g[i], b: b[i], a: alpha[i] };
+            }
+        }
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+mod avx2 {
+    use std::arch::x86_64::*;
+    use slint::Rgba8Pixel;
+    use crate::image_processing::{GammaCurve, ToneParams, ACES_COEFFICIENTS};
+    use super::Px;
+
+    /// SAFETY: wymaga AVX2; długość `input` musi być wielokrotnością 8
+    #[target_feature(enable = "avx2")]
+    pub unsafe fn process(params: &ToneParams, input: &[Px], out: &mut [Rgba8Pixel]) {
+        let zero = _mm256_setzero_ps();
+        let one = _mm256_set1_ps(1.0);
+        let scene = _mm256_set1_ps(params.scene_multiplier);
+        let display = _mm256_set1_ps(params.display_multiplier);
+
+        for (pixels, out) in input.chunks_exact(8).zip(out.chunks_exact_mut(8)) {
+            let mut lanes = [[0.0f32; 8]; 4];
+            for (i, &(r, g, b, a)) in pixels.iter().enumerate() {
+                lanes[0][i] = r;
+                lanes[1][i] = g;
+                lanes[2][i] = b;
+                lanes[3][i] = a;
+            }
+            let (mut r, mut g, mut b) = (_mm256_loadu_ps(lanes[0].as_ptr()), _mm256_loadu_ps(lanes[1].as_ptr()), _mm256_loadu_ps(lanes[2].as_ptr()));
+            let a = _mm256_loadu_ps(lanes[3].as_ptr());
+
+            if let Some(m) = params.matrix {
+                (r, g, b) = (dot(m[0], r, g, b), dot(m[1], r, g, b), dot(m[2], r, g, b));
+            }
+
+            // Alfa: skończona → clamp 0..1, w przeciwnym razie 1
+            let a = _mm256_blendv_ps(one, _mm256_min_ps(_mm256_max_ps(a, zero), one), finite_mask(a));
+
+            let mut channels = [[0i32; 8]; 4];
+            for (v, target) in [r, g, b].into_iter().zip(channels.iter_mut()) {
+                let v = _mm256_mul_ps(sanitize(v), scene);
+                let t = aces(v);
+                // NaN (przepełnienie ACES) → 1, jak `f32::min` w wersji skalarnej
+                let tone_mapped = _mm256_min_ps(_mm256_mul_ps(t, display), one);
+                store_u8(gamma(params.gamma, tone_mapped), target);
+            }
+            store_u8(a, &mut channels[3]);
+
+            for (i, px) in out.iter_mut().enumerate() {
+                *px = Rgba8Pixel { r: channels[0][i] as u8, g: channels[1][i] as u8, b: channels[2][i] as u8, a: channels[3][i] as u8 };
+            }
+        }
+    }
+
+    /// m[0]·r + m[1]·g + m[2]·b w kolejności dodawania wersji skalarnej
+    #[inline]
+    #[target_feature(enable = "avx2")]
+    unsafe fn dot(m: [f32; 3], r: __m256, g: __m256, b: __m256) -> __m256 {
+        let rg = _mm256_add_ps(_mm256_mul_ps(_mm256_set1_ps(m[0]), r), _mm256_mul_ps(_mm256_set1_ps(m[1]), g));
+        _mm256_add_ps(rg, _mm256_mul_ps(_mm256_set1_ps(m[2]), b))
+    }
+
+    /// Pasy ze skończoną wartością (v - v == 0 tylko dla liczb skończonych)
+    #[inline]
+    #[target_feature(enable = "avx2")]
+    unsafe fn finite_mask(v: __m256) -> __m256 {
+        _mm256_cmp_ps::<_CMP_EQ_OQ>(_mm256_sub_ps(v, v), _mm256_setzero_ps())
+    }
+
+    /// NaN/Inf → 0, ujemne → 0
+    #[inline]
+    #[target_feature(enable = "avx2")]
+    unsafe fn sanitize(v: __m256) -> __m256 {
+        _mm256_and_ps(_mm256_max_ps(v, _mm256_setzero_ps()), finite_mask(v))
+    }
+
+    /// ACES z clampem 0..1 przepuszczającym NaN (jak `f32::clamp`)
+    #[inline]
+    #[target_feature(enable = "avx2")]
+    unsafe fn aces(x: __m256) -> __m256 {
+        let [a, b, c, d, e] = ACES_COEFFICIENTS;
+        let num = _mm256_mul_ps(x, _mm256_add_ps(_mm256_mul_ps(_mm256_set1_ps(a), x), _mm256_set1_ps(b)));
+        let den = _mm256_add_ps(_mm256_mul_ps(x, _mm256_add_ps(_mm256_mul_ps(_mm256_set1_ps(c), x), _mm256_set1_ps(d))), _mm256_set1_ps(e));
+        // max/min zwracają drugi argument, gdy któryś jest NaN
+        _mm256_min_ps(_mm256_set1_ps(1.0), _mm256_max_ps(_mm256_setzero_ps(), _mm256_div_ps(num, den)))
+    }
+
+    #[inline]
+    #[target_feature(enable = "avx2")]
+    unsafe fn gamma(curve: GammaCurve, v: __m256) -> __m256 {
+        match curve {
+            GammaCurve::Sqrt => _mm256_sqrt_ps(v),
+            GammaCurve::Fast22 => {
+                let sqrt_val = _mm256_sqrt_ps(v);
+                _mm256_mul_ps(sqrt_val, _mm256_sqrt_ps(sqrt_val))
+            }
+            // Power obsługuje ścieżka skalarna
+            GammaCurve::Linear | GammaCurve::Power(_) => v,
+        }
+    }
+
+    /// round(v · 255) z zaokrągleniem połówek od zera, clamp 0..255
+    #[inline]
+    #[target_feature(enable = "avx2")]
+    unsafe fn store_u8(v: __m256, out: &mut [i32; 8]) {
+        let x = _mm256_mul_ps(v, _mm256_set1_ps(255.0));
+        let floor = _mm256_floor_ps(x);
+        let round_up = _mm256_cmp_ps::<_CMP_GE_OQ>(_mm256_sub_ps(x, floor), _mm256_set1_ps(0.5));
+        let rounded = _mm256_blendv_ps(floor, _mm256_add_ps(floor, _mm256_set1_ps(1.0)), round_up);
+        let clamped = _mm256_min_ps(_mm256_max_ps(rounded, _mm256_setzero_ps()), _mm256_set1_ps(255.0));
+        _mm256_storeu_si256(out.as_mut_ptr() as *mut __m256i, _mm256_cvttps_epi32(clamped));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::image_processing::GammaCurve;
+
+    /// Deterministyczne wartości HDR z domieszką przypadków brzegowych
+    fn sample_pixels(count: usize) -> Vec<Px> {
+        let mut state: u32 = 0x1234_5678;
+        let mut next = || {
+            state = state.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+            (state >> 8) as f32 / (1u32 << 24) as f32
+        };
+        let specials = [0.0, -0.0, -1.0, 0.5, 1.0, 1e-8, 1e20, f32::NAN, f32::INFINITY, f32::NEG_INFINITY];
+        (0..count)
+            .map(|i| {
+                if i % 17 == 0 {
+                    let s = specials[i / 17 % specials.len()];
+                    (s, next() * 4.0, s, s)
+                } else {
+                    (next() * 16.0 - 1.0, next() * 4.0, next() * 64.0, next() * 1.5)
+                }
+            })
+            .collect()
+    }
+
+    fn params(gamma: GammaCurve, matrix: Option<[[f32; 3]; 3]>, display_multiplier: f32) -> ToneParams {
+        ToneParams { matrix, scene_multiplier: 1.37, display_multiplier, tonemap: true, gamma }
+    }
+
+    fn all_params() -> Vec<ToneParams> {
+        let ap0 = [[2.5217, -1.1341, -0.3876], [-0.2765, 1.3727, -0.0962], [-0.0153, -0.1530, 1.1683]];
+        vec![
+            params(GammaCurve::Fast22, None, 1.0),
+            params(GammaCurve::Sqrt, Some(ap0), 1.0),
+            params(GammaCurve::Linear, None, 2.5),
+            params(GammaCurve::Power(1.0 / 2.4), Some(ap0), 0.5),
+        ]
+    }
+
+    type KernelFn = fn(&ToneParams, &[Px], &mut [Rgba8Pixel]);
+
+    fn assert_matches_scalar(kernel: KernelFn) {
+        // Długość niepodzielna przez 8 – sprawdza także ogon wiersza
+        let input = sample_pixels(4099);
+        for params in all_params() {
+            let mut expected = vec![Rgba8Pixel::default(); input.len()];
+            let mut actual = expected.clone();
+            Scalar::process(&params, &input, &mut expected);
+            kernel(&params, &input, &mut actual);
+            for (i, (e, a)) in expected.iter().zip(&actual).enumerate() {
+                assert_eq!((e.r, e.g, e.b, e.a), (a.r, a.g, a.b, a.a), "pixel {} {:?} with {:?}", i, input[i], params);
+            }
+        }
+    }
+
+    #[test]
+    fn dispatched_kernel_matches_scalar() {
+        assert_matches_scalar(process_row);
+    }
+
+    #[cfg(feature = "portable-simd")]
+    #[test]
+    fn portable_kernel_matches_scalar() {
+        assert_matches_scalar(Portable::process);
+    }
+}
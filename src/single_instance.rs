@@ -0,0 +1,87 @@
+// Tryb jednej instancji: kolejne uruchomienie z plikiem lub folderem (dwuklik w Eksploratorze,
+// "Otwórz za pomocą") przekazuje ścieżkę działającemu oknu i kończy się, zamiast otwierać nowe.
+// Kanał (nazwany potok / gniazdo Unix) zapewnia `platform`; wiadomość to ścieżka w jednej linii.
+// Wyłączany ustawieniem EXRUSTER_SINGLE_INSTANCE=0.
+
+use std::cell::RefCell;
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use slint::ComponentHandle;
+use tracing::{debug, info};
+use crate::AppWindow;
+use crate::actions::{Action, Dispatcher};
+use crate::platform::{self, InstanceListener};
+
+/// Zmienna środowiskowa; "0" = każde uruchomienie otwiera własne okno
+pub const SINGLE_INSTANCE_ENV: &str = "EXRUSTER_SINGLE_INSTANCE";
+
+thread_local! {
+    // Jak w `remote`: ścieżki z innych procesów trafiają do dispatchera przez pętlę zdarzeń Slint
+    static DISPATCHER: RefCell<Option<Rc<Dispatcher>>> = const { RefCell::new(None) };
+}
+
+fn enabled() -> bool {
+    std::env::var(SINGLE_INSTANCE_ENV).map_or(true, |v| v.trim() != "0")
+}
+
+/// Przekazuje ścieżkę działającej instancji; true = przekazano i to uruchomienie ma się zakończyć
+pub fn forward(path: &Path) -> bool {
+    if !enabled() {
+        return false;
+    }
+    // Ścieżka względna dotyczy katalogu tego procesu, nie działającego okna
+    let path = std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf());
+    match platform::send_to_instance(&format!("{}\n", path.display())) {
+        Ok(()) => {
+            info!(target: "ui", "forwarded to the running instance: {}", path.display());
+            true
+        }
+        Err(e) => {
+            debug!(target: "ui", "no running instance ({}), opening a new window", e);
+            false
+        }
+    }
+}
+
+/// Nasłuch ścieżek od kolejnych uruchomień (wołać z wątku UI). Jeśli inne okno już nasłuchuje,
+/// to okno działa samodzielnie.
+pub fn start_listener(ui: &AppWindow, dispatcher: &Rc<Dispatcher>) {
+    if !enabled() {
+        return;
+    }
+    let mut listener = match InstanceListener::bind() {
+        Ok(listener) => listener,
+        Err(e) => {
+            debug!(target: "ui", "single-instance channel not available: {}", e);
+            return;
+        }
+    };
+    DISPATCHER.with(|d| *d.borrow_mut() = Some(dispatcher.clone()));
+    let ui = ui.as_weak();
+
+    std::thread::spawn(move || {
+        while let Ok(stream) = listener.accept() {
+            let mut line = String::new();
+            if BufReader::new(stream).read_line(&mut line).is_err() {
+                continue;
+            }
+            let path = PathBuf::from(line.trim_end_matches(['\r', '\n']));
+            if path.as_os_str().is_empty() {
+                continue;
+            }
+            info!(target: "ui", "path from another launch: {}", path.display());
+            let ui = ui.clone();
+            let _ = slint::invoke_from_event_loop(move || {
+                if let Some(ui) = ui.upgrade() {
+                    ui.window().set_minimized(false);
+                }
+                DISPATCHER.with(|d| {
+                    if let Some(dispatcher) = d.borrow().as_ref() {
+                        dispatcher.dispatch(Action::open(path));
+                    }
+                });
+            });
+        }
+    });
+}
@@ -0,0 +1,154 @@
+// Galeria migawek widoku: nazwane zakładki problematycznych miejsc obrazu (obszar widoku, ekspozycja,
+// gamma, warstwa) z miniaturą wyciętą z podglądu. Osobna galeria dla każdego pliku w katalogu danych
+// aplikacji (`snapshots/<skrót ścieżki>/`), żeby nie zaśmiecać folderów z renderami.
+
+use std::fs;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::io;
+use std::path::{Path, PathBuf};
+use serde_json::{json, Value};
+use tracing::warn;
+use crate::raw_image::RawImage;
+use crate::session::app_data_dir;
+
+/// Dłuższy bok miniatury w galerii
+pub const THUMBNAIL_SIZE: u32 = 160;
+const INDEX_FILE: &str = "index.json";
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Snapshot {
+    pub name: String,
+    /// Widoczny obszar w punktach widoku 0..1 (u, v, szerokość, wysokość)
+    pub region: [f32; 4],
+    pub exposure: f32,
+    pub gamma: f32,
+    /// Etykieta trybu ekspozycji (`ExposureMode::label`)
+    pub exposure_mode: String,
+    /// Wybrany węzeł drzewa warstw jako (warstwa, kanał)
+    pub selection: Option<(String, String)>,
+    /// Nazwa pliku PNG miniatury w katalogu galerii
+    pub thumbnail: String,
+}
+
+/// Katalog galerii pliku; klucz to skrót ścieżki (ten sam plik otwarty z innego folderu = inna galeria)
+pub fn gallery_dir(file: &Path) -> PathBuf {
+    let mut hasher = DefaultHasher::new();
+    file.hash(&mut hasher);
+    app_data_dir().join("snapshots").join(format!("{:016x}", hasher.finish()))
+}
+
+/// Migawki pliku; brak albo nieczytelny indeks = pusta galeria
+pub fn load(file: &Path) -> Vec<Snapshot> {
+    let path = gallery_dir(file).join(INDEX_FILE);
+    let Ok(text) = fs::read_to_string(&path) else { return Vec::new(); };
+    let items = serde_json::from_str::<Value>(&text).ok().and_then(|v| v.get("snapshots").and_then(Value::as_array).cloned());
+    let Some(items) = items else {
+        warn!(target: "io", "ignoring unreadable snapshot gallery {}", path.display());
+        return Vec::new();
+    };
+    items.iter().filter_map(from_json).collect()
+}
+
+/// Zapis atomowy indeksu; ścieżka pliku zostaje w indeksie dla czytelności katalogu
+pub fn save(file: &Path, snapshots: &[Snapshot]) -> io::Result<()> {
+    let dir = gallery_dir(file);
+    fs::create_dir_all(&dir)?;
+    let value = json!({
+        "file": file.to_string_lossy(),
+        "snapshots": snapshots.iter().map(to_json).collect::<Vec<_>>(),
+    });
+    let tmp = dir.join("index.tmp");
+    fs::write(&tmp, serde_json::to_string_pretty(&value).unwrap_or_default())?;
+    fs::rename(&tmp, dir.join(INDEX_FILE))
+}
+
+fn to_json(snapshot: &Snapshot) -> Value {
+    json!({
+        "name": snapshot.name,
+        "region": snapshot.region,
+        "exposure": snapshot.exposure,
+        "gamma": snapshot.gamma,
+        "exposure_mode": snapshot.exposure_mode,
+        "layer": snapshot.selection.as_ref().map(|(layer, _)| layer),
+        "channel": snapshot.selection.as_ref().map(|(_, channel)| channel),
+        "thumbnail": snapshot.thumbnail,
+    })
+}
+
+fn from_json(value: &Value) -> Option<Snapshot> {
+    let text = |key: &str| value.get(key).and_then(Value::as_str).map(str::to_string);
+    let region = value.get("region")?.as_array()?;
+    let region: Vec<f32> = region.iter().filter_map(Value::as_f64).map(|v| v as f32).collect();
+    Some(Snapshot {
+        name: text("name")?,
+        region: region.try_into().ok()?,
+        exposure: value.get("exposure")?.as_f64()? as f32,
+        gamma: value.get("gamma")?.as_f64()? as f32,
+        exposure_mode: text("exposure_mode").unwrap_or_default(),
+        selection: text("layer").zip(text("channel")),
+        thumbnail: text("thumbnail").unwrap_or_default(),
+    })
+}
+
+/// Wycinek podglądu w obszarze `region` pomniejszony do `THUMBNAIL_SIZE`
+pub fn crop_thumbnail(preview: &RawImage, region: [f32; 4]) -> Option<RawImage> {
+    let image = image::RgbaImage::from_raw(preview.width, preview.height, preview.pixels.clone())?;
+    let (w, h) = (preview.width as f32, preview.height as f32);
+    let (x, y) = ((region[0] * w).floor().max(0.0) as u32, (region[1] * h).floor().max(0.0) as u32);
+    if x >= preview.width || y >= preview.height {
+        return None;
+    }
+    let crop_w = ((region[2] * w).ceil() as u32).clamp(1, preview.width - x);
+    let crop_h = ((region[3] * h).ceil() as u32).clamp(1, preview.height - y);
+    let scale = (THUMBNAIL_SIZE as f32 / crop_w.max(crop_h) as f32).min(1.0);
+    let crop = image::imageops::crop_imm(&image, x, y, crop_w, crop_h).to_image();
+    let thumb = image::imageops::thumbnail(&crop, ((crop_w as f32 * scale).round() as u32).max(1), ((crop_h as f32 * scale).round() as u32).max(1));
+    Some(RawImage { width: thumb.width(), height: thumb.height(), pixels: thumb.into_raw() })
+}
+
+pub fn save_thumbnail(file: &Path, name: &str, thumbnail: &RawImage) -> io::Result<()> {
+    let dir = gallery_dir(file);
+    fs::create_dir_all(&dir)?;
+    image::save_buffer(dir.join(name), &thumbnail.pixels, thumbnail.width, thumbnail.height, image::ExtendedColorType::Rgba8)
+        .map_err(io::Error::other)
+}
+
+pub fn load_thumbnail(file: &Path, snapshot: &Snapshot) -> Option<RawImage> {
+    let image = image::open(gallery_dir(file).join(&snapshot.thumbnail)).ok()?.into_rgba8();
+    Some(RawImage { width: image.width(), height: image.height(), pixels: image.into_raw() })
+}
+
+/// Usuwa miniaturę migawki (indeks zapisuje wywołujący)
+pub fn remove_thumbnail(file: &Path, snapshot: &Snapshot) {
+    let path = gallery_dir(file).join(&snapshot.thumbnail);
+    if let Err(e) = fs::remove_file(&path) {
+        if e.kind() != io::ErrorKind::NotFound {
+            warn!(target: "io", "cannot remove snapshot thumbnail {}: {}", path.display(), e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn snapshot_round_trip_and_thumbnail_crop() {
+        let snapshot = Snapshot {
+            name: "Fireflies".into(),
+            region: [0.25, 0.5, 0.5, 0.25],
+            exposure: 1.5,
+            gamma: 2.2,
+            exposure_mode: "Scene (before tone map)".into(),
+            selection: Some(("beauty".into(), String::new())),
+            thumbnail: "1.png".into(),
+        };
+        assert_eq!(from_json(&to_json(&snapshot)), Some(snapshot));
+
+        let mut preview = RawImage::new(800, 400);
+        preview.pixels.chunks_exact_mut(4).enumerate().for_each(|(i, p)| p[0] = if i % 800 >= 400 { 255 } else { 0 });
+        let thumb = crop_thumbnail(&preview, [0.5, 0.0, 0.5, 1.0]).unwrap();
+        assert_eq!((thumb.width, thumb.height), (THUMBNAIL_SIZE, THUMBNAIL_SIZE));
+        assert!(thumb.pixels.chunks_exact(4).all(|p| p[0] == 255));
+    }
+}
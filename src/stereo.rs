@@ -0,0 +1,136 @@
+// Podgląd stereo plików wielowidokowych (konwencja OpenEXR multi-view): lista widoków z atrybutu
+// `multiView` (plik jednoczęściowy) albo z atrybutów `view` części. W pliku jednoczęściowym widok
+// domyślny (pierwszy na liście) ma kanały bez nazwy widoku ("R", "diffuse.R"), pozostałe mają ją
+// jako ostatni człon warstwy ("right.R", "diffuse.right.R"). Tryby: pojedynczy widok (przełączany
+// między oczami), obok siebie i anaglif czerwony/cyjan – składane w wartościach sceny w `ImageCache`.
+
+use ::exr::meta::attribute::{AttributeValue, Text};
+use ::exr::meta::header::Header;
+use rayon::prelude::*;
+
+type Pixel = (f32, f32, f32, f32);
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum StereoMode {
+    /// Jeden widok, jak zwykła warstwa
+    #[default]
+    Single,
+    SideBySide,
+    Anaglyph,
+}
+
+impl StereoMode {
+    pub fn from_label(label: &str) -> Self {
+        match label {
+            "Side-by-side" => StereoMode::SideBySide,
+            "Anaglyph (red/cyan)" => StereoMode::Anaglyph,
+            _ => StereoMode::Single,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            StereoMode::Single => "Single view",
+            StereoMode::SideBySide => "Side-by-side",
+            StereoMode::Anaglyph => "Anaglyph (red/cyan)",
+        }
+    }
+}
+
+/// Nazwy widoków pliku (pusta lista – plik jednowidokowy)
+pub fn parse_views(headers: &[Header]) -> Vec<String> {
+    let text = |header: &Header, name: &str| header.own_attributes.other.get(&Text::from(name)).cloned();
+    let mut views: Vec<String> = Vec::new();
+    for header in headers {
+        if let Some(AttributeValue::Text(view)) = text(header, "view") {
+            let view = view.to_string();
+            if !views.contains(&view) {
+                views.push(view);
+            }
+        }
+    }
+    if views.is_empty() {
+        if let Some(AttributeValue::TextVector(list)) = headers.first().and_then(|h| text(h, "multiView")) {
+            views = list.iter().map(Text::to_string).collect();
+        }
+    }
+    if views.len() < 2 { Vec::new() } else { views }
+}
+
+/// Indeks widoku warstwy: człon nazwy równy nazwie widoku, inaczej widok domyślny (0)
+pub fn view_of(layer: &str, views: &[String]) -> usize {
+    layer.split('.').find_map(|part| views.iter().position(|v| v == part)).unwrap_or(0)
+}
+
+/// Odpowiednik warstwy w widoku `target` spośród `layers` (podmiana członu widoku, dopisanie go
+/// do nazwy albo – dla widoku domyślnego – nazwa bez widoku)
+pub fn layer_in_view<'a>(layer: &str, target: usize, views: &[String], layers: impl IntoIterator<Item = &'a str> + Clone) -> Option<String> {
+    let target_name = views.get(target)?;
+    let parts: Vec<&str> = layer.split('.').filter(|p| !p.is_empty()).collect();
+    let own = parts.iter().position(|p| views.iter().any(|v| v == p));
+    let base: Vec<&str> = parts.iter().enumerate().filter(|(i, _)| Some(*i) != own).map(|(_, p)| *p).collect();
+    let mut candidates = Vec::new();
+    if let Some(i) = own {
+        let mut replaced = parts.clone();
+        replaced[i] = target_name;
+        candidates.push(replaced.join("."));
+    }
+    candidates.push(base.iter().copied().chain(std::iter::once(target_name.as_str())).collect::<Vec<_>>().join("."));
+    if target == 0 {
+        candidates.push(base.join("."));
+    }
+    candidates.into_iter().find(|c| layers.clone().into_iter().any(|l| l == c))
+}
+
+/// Indeksy widoków lewego i prawego oka: po nazwach "left"/"right", inaczej dwa pierwsze widoki
+pub fn eye_views(views: &[String]) -> (usize, usize) {
+    let find = |name: &str| views.iter().position(|v| v.eq_ignore_ascii_case(name));
+    let left = find("left").unwrap_or(0);
+    let right = find("right").filter(|r| *r != left).unwrap_or(if left == 0 { 1 } else { 0 });
+    (left, right)
+}
+
+/// Obraz stereo z pikseli lewego i prawego oka o tym samym rozmiarze; None dla pojedynczego widoku
+pub fn compose(mode: StereoMode, left: &[Pixel], right: &[Pixel], width: usize, height: usize) -> Option<(Vec<Pixel>, usize, usize)> {
+    match mode {
+        StereoMode::Single => None,
+        StereoMode::SideBySide => {
+            let mut out = vec![(0.0, 0.0, 0.0, 0.0); width * 2 * height];
+            out.par_chunks_mut(width * 2).enumerate().for_each(|(y, row)| {
+                row[..width].copy_from_slice(&left[y * width..(y + 1) * width]);
+                row[width..].copy_from_slice(&right[y * width..(y + 1) * width]);
+            });
+            Some((out, width * 2, height))
+        }
+        // Anaglif barwny: czerwień z lewego oka, zieleń i błękit z prawego
+        StereoMode::Anaglyph => {
+            let out = left.par_iter().zip(right.par_iter()).map(|(l, r)| (l.0, r.1, r.2, l.3.max(r.3))).collect();
+            Some((out, width, height))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn view_layers_and_stereo_compose() {
+        let views: Vec<String> = ["left", "right"].map(String::from).to_vec();
+        let layers = ["", "right", "diffuse", "diffuse.right", "left.spec", "right.spec"];
+        let find = |layer: &str, target: usize| layer_in_view(layer, target, &views, layers.iter().copied());
+        assert_eq!((view_of("", &views), view_of("diffuse.right", &views)), (0, 1));
+        assert_eq!(find("", 1).as_deref(), Some("right"));
+        assert_eq!(find("diffuse.right", 0).as_deref(), Some("diffuse"));
+        assert_eq!(find("left.spec", 1).as_deref(), Some("right.spec"));
+        assert_eq!(find("diffuse", 2), None);
+        assert_eq!(eye_views(&["right".into(), "left".into()]), (1, 0));
+
+        let (left, right) = ([(1.0, 0.0, 0.0, 1.0); 2], [(0.0, 0.5, 0.25, 1.0); 2]);
+        let (pair, w, h) = compose(StereoMode::SideBySide, &left, &right, 1, 2).unwrap();
+        assert_eq!((w, h, pair[0], pair[1]), (2, 2, left[0], right[0]));
+        let (anaglyph, _, _) = compose(StereoMode::Anaglyph, &left, &right, 1, 2).unwrap();
+        assert_eq!(anaglyph[0], (1.0, 0.5, 0.25, 1.0));
+        assert!(compose(StereoMode::Single, &left, &right, 1, 2).is_none());
+    }
+}
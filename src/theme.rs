@@ -0,0 +1,224 @@
+// Motyw interfejsu: tryb ciemny/jasny i kolor akcentu. Ustawienia żyją po stronie Rusta (plik
+// theme.txt w katalogu danych aplikacji), a paleta trafia do globalnego `Kolory` w Slint – wszystkie
+// panele, okna pomocnicze i kolory drzewa warstw czytają te same wartości.
+
+use std::fs;
+use std::path::Path;
+use std::sync::{LazyLock, Mutex};
+use slint::{Color, ComponentHandle};
+use crate::{AppWindow, Kolory};
+use crate::session::app_data_dir;
+use crate::utils::channel_config::parse_hex_color;
+
+const THEME_FILE: &str = "theme.txt";
+
+/// Domyślny akcent (pomarańczowy, jak w colors.slint)
+pub const DEFAULT_ACCENT: (u8, u8, u8) = (0xf4, 0x97, 0x1d);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ThemeMode {
+    Dark,
+    Light,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ThemeSettings {
+    pub mode: ThemeMode,
+    pub accent: (u8, u8, u8),
+}
+
+impl Default for ThemeSettings {
+    fn default() -> Self {
+        Self { mode: ThemeMode::Dark, accent: DEFAULT_ACCENT }
+    }
+}
+
+// Wczytywane przy pierwszym użyciu, zapisywane przy każdej zmianie
+static SETTINGS: LazyLock<Mutex<ThemeSettings>> = LazyLock::new(|| Mutex::new(load()));
+
+/// Paleta trybu (0xRRGGBB), pola jak w globalnym `Kolory`; akcent (`hover`) jest osobno
+struct Palette {
+    tlo: u32,
+    obramowanie: u32,
+    tekst: u32,
+    tekst_silny: u32,
+    tekst_slabszy: u32,
+    panel_tlo: u32,
+    linia_podzialu: u32,
+    menu_tlo: u32,
+    menu_obramowanie: u32,
+    zakladka_tlo: u32,
+    zakladka_obramowanie: u32,
+    zakladka_aktywna_tlo: u32,
+    zakladka_aktywna_obramowanie: u32,
+    konsola_tlo: u32,
+    suwak_tlo: u32,
+    suwak_tor: u32,
+    ekspozycja_galka: u32,
+    ekspozycja_galka_hover: u32,
+    gamma_galka: u32,
+    gamma_galka_hover: u32,
+    progress_fill: u32,
+    kanal_r: u32,
+    kanal_g: u32,
+    kanal_b: u32,
+}
+
+const DARK: Palette = Palette {
+    tlo: 0x242532,
+    obramowanie: 0x1e1f28,
+    tekst: 0xcccccc,
+    tekst_silny: 0xffffff,
+    tekst_slabszy: 0xaaaaaa,
+    panel_tlo: 0x242532,
+    linia_podzialu: 0x1e1f28,
+    menu_tlo: 0x242532,
+    menu_obramowanie: 0x1e1f28,
+    zakladka_tlo: 0x242532,
+    zakladka_obramowanie: 0x1e1f28,
+    zakladka_aktywna_tlo: 0x242532,
+    zakladka_aktywna_obramowanie: 0x1e1f28,
+    konsola_tlo: 0x242532,
+    suwak_tlo: 0x242532,
+    suwak_tor: 0x606060,
+    ekspozycja_galka: 0x242532,
+    ekspozycja_galka_hover: 0x8080ff,
+    gamma_galka: 0x242532,
+    gamma_galka_hover: 0xff8080,
+    progress_fill: 0xaf4cac,
+    kanal_r: 0xff8181,
+    kanal_g: 0xb0ffb0,
+    kanal_b: 0x92d2ff,
+};
+
+const LIGHT: Palette = Palette {
+    tlo: 0xeeeef2,
+    obramowanie: 0xc8c9d2,
+    tekst: 0x2b2b33,
+    tekst_silny: 0x000000,
+    tekst_slabszy: 0x5c5d66,
+    panel_tlo: 0xf5f5f8,
+    linia_podzialu: 0xc8c9d2,
+    menu_tlo: 0xffffff,
+    menu_obramowanie: 0xbdbec8,
+    zakladka_tlo: 0xeeeef2,
+    zakladka_obramowanie: 0xc8c9d2,
+    zakladka_aktywna_tlo: 0xffffff,
+    zakladka_aktywna_obramowanie: 0xbdbec8,
+    konsola_tlo: 0xffffff,
+    suwak_tlo: 0xf5f5f8,
+    suwak_tor: 0x9a9aa4,
+    ekspozycja_galka: 0xffffff,
+    ekspozycja_galka_hover: 0x4848c8,
+    gamma_galka: 0xffffff,
+    gamma_galka_hover: 0xc84848,
+    progress_fill: 0x9c3a99,
+    kanal_r: 0xc62828,
+    kanal_g: 0x2e7d32,
+    kanal_b: 0x1565c0,
+};
+
+pub fn current() -> ThemeSettings {
+    *SETTINGS.lock().unwrap_or_else(|p| p.into_inner())
+}
+
+/// Zmienia ustawienia motywu i od razu zapisuje je na dysk
+pub fn update(f: impl FnOnce(&mut ThemeSettings)) -> ThemeSettings {
+    let settings = {
+        let mut guard = SETTINGS.lock().unwrap_or_else(|p| p.into_inner());
+        f(&mut guard);
+        *guard
+    };
+    if let Err(e) = save_to(&app_data_dir().join(THEME_FILE), &settings) {
+        tracing::warn!(target: "ui", "cannot save theme settings: {}", e);
+    }
+    settings
+}
+
+/// Akcent w postaci "#rrggbb" (znak # opcjonalny)
+pub fn parse_accent(text: &str) -> Option<(u8, u8, u8)> {
+    let text = text.trim();
+    parse_hex_color(text).or_else(|| parse_hex_color(&format!("#{}", text)))
+}
+
+pub fn accent_hex((r, g, b): (u8, u8, u8)) -> String {
+    format!("#{:02x}{:02x}{:02x}", r, g, b)
+}
+
+/// Kolor reguły AOV w drzewie warstw: pastelowe kolory z konfiguracji kanałów są czytelne na
+/// ciemnym tle, w trybie jasnym przyciemniamy je do ~55%
+pub fn tree_color((r, g, b): (u8, u8, u8)) -> Color {
+    match current().mode {
+        ThemeMode::Dark => Color::from_rgb_u8(r, g, b),
+        ThemeMode::Light => {
+            let dim = |c: u8| (c as f32 * 0.55).round() as u8;
+            Color::from_rgb_u8(dim(r), dim(g), dim(b))
+        }
+    }
+}
+
+/// Przenosi bieżący motyw do UI: globalny `Kolory`, schemat widżetów standardowych i stan menu
+pub fn apply(ui: &AppWindow) {
+    let settings = current();
+    let palette = match settings.mode {
+        ThemeMode::Dark => &DARK,
+        ThemeMode::Light => &LIGHT,
+    };
+    let rgb = |v: u32| Color::from_argb_encoded(0xff00_0000 | v);
+    let k = ui.global::<Kolory>();
+    k.set_tlo(rgb(palette.tlo));
+    k.set_obramowanie(rgb(palette.obramowanie));
+    k.set_tekst(rgb(palette.tekst));
+    k.set_tekst_silny(rgb(palette.tekst_silny));
+    k.set_tekst_slabszy(rgb(palette.tekst_slabszy));
+    k.set_panel_tlo(rgb(palette.panel_tlo));
+    k.set_linia_podzialu(rgb(palette.linia_podzialu));
+    k.set_menu_tlo(rgb(palette.menu_tlo));
+    k.set_menu_obramowanie(rgb(palette.menu_obramowanie));
+    k.set_zakladka_tlo(rgb(palette.zakladka_tlo));
+    k.set_zakladka_obramowanie(rgb(palette.zakladka_obramowanie));
+    k.set_zakladka_aktywna_tlo(rgb(palette.zakladka_aktywna_tlo));
+    k.set_zakladka_aktywna_obramowanie(rgb(palette.zakladka_aktywna_obramowanie));
+    k.set_konsola_tlo(rgb(palette.konsola_tlo));
+    k.set_suwak_tlo(rgb(palette.suwak_tlo));
+    k.set_suwak_tor(rgb(palette.suwak_tor));
+    k.set_ekspozycja_galka(rgb(palette.ekspozycja_galka));
+    k.set_ekspozycja_galka_hover(rgb(palette.ekspozycja_galka_hover));
+    k.set_gamma_galka(rgb(palette.gamma_galka));
+    k.set_gamma_galka_hover(rgb(palette.gamma_galka_hover));
+    k.set_progress_fill(rgb(palette.progress_fill));
+    k.set_kanal_r(rgb(palette.kanal_r));
+    k.set_kanal_g(rgb(palette.kanal_g));
+    k.set_kanal_b(rgb(palette.kanal_b));
+    let (r, g, b) = settings.accent;
+    k.set_hover(Color::from_rgb_u8(r, g, b));
+
+    let light = settings.mode == ThemeMode::Light;
+    ui.invoke_apply_color_scheme(light);
+    ui.set_light_theme(light);
+    ui.set_accent_hex(accent_hex(settings.accent).into());
+}
+
+fn load() -> ThemeSettings {
+    let mut settings = ThemeSettings::default();
+    let Ok(text) = fs::read_to_string(app_data_dir().join(THEME_FILE)) else { return settings; };
+    for line in text.lines() {
+        let Some((key, value)) = line.split_once('=') else { continue; };
+        match (key.trim(), value.trim()) {
+            ("mode", "light") => settings.mode = ThemeMode::Light,
+            ("mode", "dark") => settings.mode = ThemeMode::Dark,
+            ("accent", value) => settings.accent = parse_accent(value).unwrap_or(settings.accent),
+            _ => {}
+        }
+    }
+    settings
+}
+
+fn save_to(path: &Path, settings: &ThemeSettings) -> std::io::Result<()> {
+    if let Some(dir) = path.parent() { fs::create_dir_all(dir)?; }
+    let mode = match settings.mode {
+        ThemeMode::Dark => "dark",
+        ThemeMode::Light => "light",
+    };
+    fs::write(path, format!("mode={}\naccent={}\n", mode, accent_hex(settings.accent)))
+}
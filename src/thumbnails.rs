@@ -1,11 +1,24 @@
 use anyhow::Context;
 use std::fs;
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
 use rayon::prelude::*;
-use slint::{Image, Rgba8Pixel, SharedPixelBuffer};
+use slint::Image;
+use tracing::info;
 
+use ::exr::meta::header::Header;
 use crate::image_processing::process_pixel;
-use crate::image_cache::{extract_layers_info, find_best_layer, load_specific_layer};
+use crate::cancel::CancelToken;
+use crate::dir_scan::{self, ScanOptions};
+use crate::progress::ProgressSink;
+use crate::io::fast_exr_metadata::{self, FastExrMetadata};
+use crate::image_cache::{find_best_layer, has_resolution_levels, layers_info_from_headers, load_preview_proxy, load_specific_layer_checked, read_headers};
+use crate::io::recovery::Damage;
+use crate::raw_image::RawImage;
+use crate::utils::human_size;
+
+/// Dłuższy bok poziomu mip czytanego dla miniatury, w wielokrotnościach jej wysokości (pokrywa proporcje do 4:1)
+const MIP_THUMB_ASPECT: u32 = 4;
 
 /// Zwięzła reprezentacja miniaturki EXR do wyświetlenia w UI
 pub struct ExrThumbnailInfo {
@@ -16,82 +29,74 @@ pub struct ExrThumbnailInfo {
     pub width: u32,  // rzeczywista szerokość miniaturki po skalowaniu
     pub height: u32, // rzeczywista wysokość miniaturki (zawsze thumb_height)
     pub image: Image,
+    /// Szybki skan nagłówków (rozdzielczość źródła, kompresja, typy próbek); None przy błędzie odczytu
+    pub metadata: Option<FastExrMetadata>,
+    /// Plik uszkodzony – miniatura z odzyskanych bloków
+    pub damage: Option<Damage>,
 }
 
-/// Główny interfejs: generuje miniaturki dla wszystkich plików .exr w katalogu (bez rekursji).
+/// Główny interfejs: generuje miniaturki dla wszystkich plików .exr w katalogu (podfoldery wg `dir_scan`).
+/// - Przed generowaniem postęp pokazuje liczbę i łączny rozmiar znalezionych plików
 /// - Przetwarzanie odbywa się równolegle (Rayon)
 /// - Miniaturki powstają z kompozytu kanałów R, G, B z "najlepszej" warstwy (wybór scentralizowany w `image_cache`)
 /// - Transformacje zgodne z podglądem (ACES + gamma) przez `process_pixel`, z przekazanymi parametrami
-pub fn generate_exr_thumbnails_in_dir(
+/// - Wykonywane w tle (bez slint::Image, który nie jest Send – obraz tworzy `ExrThumbWork::into_info`
+///   na wątku UI); anulowanie pomija pliki jeszcze nieprzetworzone, postęp to ułamek gotowych plików
+pub fn generate_thumbnail_works(
     directory: &Path,
     thumb_height: u32,
     exposure: f32,
     gamma: f32,
-) -> anyhow::Result<Vec<ExrThumbnailInfo>> {
-    let files = list_exr_files(directory)?;
+    cancel: &CancelToken,
+    progress: &dyn ProgressSink,
+) -> anyhow::Result<Vec<ExrThumbWork>> {
+    progress.start_indeterminate(Some("Scanning folder..."));
+    let scan = dir_scan::scan(directory, &ScanOptions::from_env(), cancel)?;
+    let summary = format!("{} EXR files, {} in {} folders", scan.files.len(), human_size(scan.total_bytes), scan.folders);
+    info!(target: "io", "scan {}: {}", directory.display(), summary);
+    progress.set(0.0, Some(&summary));
+    let files = scan.files;
+    let done = AtomicUsize::new(0);
 
-    // 1) Równolegle generuj dane miniaturek w typie bezpiecznym dla wątków (bez slint::Image)
     let works: Vec<ExrThumbWork> = files
         .par_iter()
-        .filter_map(|path| match generate_single_exr_thumbnail_work(path, thumb_height, exposure, gamma) {
-            Ok(work) => Some(work),
-            Err(_e) => None, // tu można logować błąd
+        .filter_map(|path| {
+            if cancel.is_cancelled() { return None; }
+            let work = generate_single_exr_thumbnail_work(path, thumb_height, exposure, gamma).ok(); // tu można logować błąd
+            let n = done.fetch_add(1, Ordering::Relaxed) + 1;
+            progress.set(n as f32 / files.len() as f32, None);
+            work
         })
         .collect();
-
-    // 2) Na głównym wątku skonstruuj slint::Image (nie jest Send)
-    let thumbnails: Vec<ExrThumbnailInfo> = works
-        .into_iter()
-        .map(|w| {
-            let mut buffer = SharedPixelBuffer::<Rgba8Pixel>::new(w.width, w.height);
-            let slice = buffer.make_mut_slice();
-            // skopiuj surowe RGBA8 do bufora Slint
-            for (dst, chunk) in slice.iter_mut().zip(w.pixels.chunks_exact(4)) {
-                *dst = Rgba8Pixel { r: chunk[0], g: chunk[1], b: chunk[2], a: chunk[3] };
-            }
-
-            ExrThumbnailInfo {
-                path: w.path,
-                file_name: w.file_name,
-                file_size_bytes: w.file_size_bytes,
-                num_layers: w.num_layers,
-                width: w.width,
-                height: w.height,
-                image: Image::from_rgba8(buffer),
-            }
-        })
-        .collect();
-
-    Ok(thumbnails)
-}
-
-fn list_exr_files(dir: &Path) -> anyhow::Result<Vec<PathBuf>> {
-    let entries = fs::read_dir(dir)
-        .with_context(|| format!("Nie można odczytać katalogu: {}", dir.display()))?;
-
-    let mut out = Vec::new();
-    for entry in entries {
-        let entry = entry?;
-        let path = entry.path();
-        if path.is_file() {
-            if let Some(ext) = path.extension().and_then(|e| e.to_str()) {
-                if ext.eq_ignore_ascii_case("exr") {
-                    out.push(path);
-                }
-            }
-        }
-    }
-    Ok(out)
+    cancel.check()?;
+    Ok(works)
 }
 
-struct ExrThumbWork {
+/// Dane miniaturki policzone w tle; `into_info` na wątku UI tworzy obraz Slint
+pub struct ExrThumbWork {
     path: PathBuf,
     file_name: String,
     file_size_bytes: u64,
-    width: u32,
-    height: u32,
     num_layers: usize,
-    pixels: Vec<u8>, // RGBA8 interleaved
+    image: RawImage,
+    metadata: Option<FastExrMetadata>,
+    damage: Option<Damage>,
+}
+
+impl ExrThumbWork {
+    pub fn into_info(self) -> ExrThumbnailInfo {
+        ExrThumbnailInfo {
+            path: self.path,
+            file_name: self.file_name,
+            file_size_bytes: self.file_size_bytes,
+            num_layers: self.num_layers,
+            width: self.image.width,
+            height: self.image.height,
+            image: self.image.to_slint_image(),
+            metadata: self.metadata,
+            damage: self.damage,
+        }
+    }
 }
 
 fn generate_single_exr_thumbnail_work(
@@ -100,13 +105,29 @@ fn generate_single_exr_thumbnail_work(
     exposure: f32,
     gamma: f32,
 ) -> anyhow::Result<ExrThumbWork> {
-    // Scentralizowany wybór i wczytanie warstwy
     let path_buf = path.to_path_buf();
-    let layers_info = extract_layers_info(&path_buf)
-        .with_context(|| format!("Błąd odczytu EXR: {}", path.display()))?;
+    let headers = read_headers(path).with_context(|| format!("Błąd odczytu EXR: {}", path.display()))?;
+    let layers_info = layers_info_from_headers(&headers);
+    // Osadzony podgląd z nagłówka – bez dekodowania pikseli (pierwszy skan katalogu produkcyjnego)
+    if let Some(image) = embedded_preview_thumbnail(&headers, thumb_height) {
+        return Ok(thumb_work(path, layers_info.len(), image, None));
+    }
+
+    // Scentralizowany wybór i wczytanie warstwy
     let best_layer_name = find_best_layer(&layers_info);
-    let (raw_pixels, width, height, _current_layer) = load_specific_layer(&path_buf, &best_layer_name)
-        .with_context(|| format!("Błąd wczytania warstwy '{}': {}", best_layer_name, path.display()))?;
+    // Pliki z mip-mapami (np. mapy otoczenia 16K): wystarczy mniejszy zapisany poziom zamiast pełnej rozdzielczości;
+    // gdy nie da się go odczytać (uszkodzony plik), czytamy tolerancyjnie pełną warstwę
+    let mip_proxy = has_resolution_levels(path)
+        .then(|| load_preview_proxy(path, thumb_height * MIP_THUMB_ASPECT, &CancelToken::new()).ok())
+        .flatten();
+    let (raw_pixels, width, height, damage) = match mip_proxy {
+        Some(proxy) => (proxy.raw_pixels.to_vec(), proxy.width, proxy.height, None),
+        None => {
+            let ((raw_pixels, width, height, _current_layer), damage) = load_specific_layer_checked(&path_buf, &best_layer_name)
+                .with_context(|| format!("Błąd wczytania warstwy '{}': {}", best_layer_name, path.display()))?;
+            (raw_pixels, width, height, damage)
+        }
+    };
 
     // Oblicz rozmiar miniaturki - zawsze 150px wysokości, szerokość proporcjonalna
     let scale = thumb_height as f32 / height as f32;
@@ -114,11 +135,11 @@ fn generate_single_exr_thumbnail_work(
     let thumb_w = (width as f32 * scale) as u32;
 
     // Bufor wyjściowy miniaturki (RGBA8)
-    let mut pixels: Vec<u8> = vec![0; (thumb_w as usize) * (thumb_h as usize) * 4];
+    let mut image = RawImage::new(thumb_w, thumb_h);
 
     // Samplowanie nearest-neighbor z mapowaniem procesem jak w preview (ACES + gamma)
     let raw_width = width as usize;
-    pixels
+    image.pixels
         .par_chunks_mut(4)
         .enumerate()
         .for_each(|(i, out)| {
@@ -134,17 +155,69 @@ fn generate_single_exr_thumbnail_work(
             out[0] = px.r; out[1] = px.g; out[2] = px.b; out[3] = px.a;
         });
 
+    Ok(thumb_work(path, layers_info.len(), image, damage))
+}
+
+fn thumb_work(path: &Path, num_layers: usize, image: RawImage, damage: Option<Damage>) -> ExrThumbWork {
     let file_name = path.file_name().and_then(|n| n.to_str()).unwrap_or("?").to_string();
     let file_size_bytes = fs::metadata(path).map(|m| m.len()).unwrap_or(0);
+    // Same nagłówki – błąd skanu nie blokuje miniatury
+    let metadata = fast_exr_metadata::read_cached(path).ok();
 
-    Ok(ExrThumbWork {
+    ExrThumbWork {
         path: path.to_path_buf(),
         file_name,
         file_size_bytes,
-        width: thumb_w,
-        height: thumb_h,
-        num_layers: layers_info.len(),
-        pixels,
-    })
+        num_layers,
+        image,
+        metadata,
+        damage,
+    }
+}
+
+/// Miniatura z atrybutu `preview` (RGBA8, już po tone mappingu autora pliku – ekspozycja i gamma
+/// podglądu jej nie zmieniają), przeskalowana do wysokości miniatury; None, gdy pliku go nie ma
+fn embedded_preview_thumbnail(headers: &[Header], thumb_height: u32) -> Option<RawImage> {
+    let preview = headers.iter().find_map(|h| h.own_attributes.preview.as_ref())?;
+    let (width, height) = (preview.size.width(), preview.size.height());
+    if width == 0 || height == 0 || preview.pixel_data.len() < width * height * 4 {
+        return None;
+    }
+    let scale = thumb_height as f32 / height as f32;
+    let thumb_w = ((width as f32 * scale) as u32).max(1);
+    let mut image = RawImage::new(thumb_w, thumb_height);
+    for (i, out) in image.pixels.chunks_exact_mut(4).enumerate() {
+        let (x, y) = (i as u32 % thumb_w, i as u32 / thumb_w);
+        let src_x = ((x as f32 / scale) as usize).min(width - 1);
+        let src_y = ((y as f32 / scale) as usize).min(height - 1);
+        let src = (src_y * width + src_x) * 4;
+        for (o, v) in out.iter_mut().zip(&preview.pixel_data[src..src + 4]) {
+            *o = *v as u8;
+        }
+    }
+    Some(image)
 }
 
+/// Tekst podpowiedzi miniatury: ścieżka, rozdzielczość, warstwy/kanały, kompresja, data modyfikacji, rozmiar.
+/// Nagłówki z pamięci podręcznej szybkiego skanu – dla niezmienionego pliku wystarcza jedno `stat`.
+pub fn thumbnail_tooltip(path: &Path) -> anyhow::Result<String> {
+    let stat = fs::metadata(path).with_context(|| format!("Nie można odczytać pliku: {}", path.display()))?;
+    let meta = fast_exr_metadata::read_cached(path)
+        .with_context(|| format!("Błąd odczytu nagłówków EXR: {}", path.display()))?;
+    let (date, time) = crate::utils::utc_date_time(stat.modified()?);
+
+    let mut resolution = format!("{} × {}", meta.width, meta.height);
+    if meta.part_count > 1 {
+        resolution.push_str(&format!(" · {} parts", meta.part_count));
+    }
+    let compression: Vec<&str> = meta.compressions.iter().copied().map(fast_exr_metadata::compression_label).collect();
+    let types: Vec<&str> = meta.pixel_types.iter().copied().map(fast_exr_metadata::sample_type_label).collect();
+    Ok([
+        path.display().to_string(),
+        resolution,
+        format!("{} layers · {} channels{}", meta.layer_count, meta.channel_count, if meta.deep { " · deep" } else { "" }),
+        format!("{} · {}", compression.join(", "), types.join(", ")),
+        format!("Modified {} {} UTC", date, time),
+        crate::utils::human_size(stat.len()),
+    ].join("\n"))
+}
@@ -0,0 +1,101 @@
+// Rdzeń przetwarzania kafelkowego: obraz wyjściowy (albo jego wycinek – ROI) dzielony jest na kafelki
+// TILE_SIZE×TILE_SIZE liczone równolegle do własnych buforów. Kafelek czyta zwarty fragment źródła
+// (lepsza lokalność niż przebieg po całych wierszach przy obrocie), a między kafelkami można
+// sprawdzić anulowanie i zgłosić postęp.
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use rayon::prelude::*;
+use slint::Rgba8Pixel;
+use crate::cancel::CancelToken;
+use crate::raw_image::RawImage;
+use crate::utils::error_handling::ExrResult;
+
+/// Bok kafelka w pikselach wyjściowych
+pub const TILE_SIZE: u32 = 256;
+
+/// Prostokąt w pikselach obrazu wyjściowego
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Rect {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Rect {
+    pub fn full(width: u32, height: u32) -> Self {
+        Rect { x: 0, y: 0, width, height }
+    }
+}
+
+/// Zadanie kafelkowe: kolor piksela wyjściowego (x, y). Wiersz kafelka liczony jest przez `row`,
+/// które implementacje mogą nadpisać wersją wektorową.
+pub trait TileJob: Sync {
+    fn pixel(&self, x: u32, y: u32) -> Rgba8Pixel;
+
+    /// Wypełnia `out` pikselami (x.., y) – domyślnie piksel po pikselu
+    fn row(&self, x: u32, y: u32, out: &mut [Rgba8Pixel]) {
+        for (dx, px) in out.iter_mut().enumerate() {
+            *px = self.pixel(x + dx as u32, y);
+        }
+    }
+}
+
+impl<F: Fn(u32, u32) -> Rgba8Pixel + Sync> TileJob for F {
+    fn pixel(&self, x: u32, y: u32) -> Rgba8Pixel {
+        self(x, y)
+    }
+}
+
+/// Kafelki pokrywające `roi` (brzegowe mogą być mniejsze), wierszami
+pub fn tiles(roi: Rect, size: u32) -> Vec<Rect> {
+    let size = size.max(1);
+    (0..roi.height.div_ceil(size))
+        .flat_map(|ty| (0..roi.width.div_ceil(size)).map(move |tx| (tx * size, ty * size)))
+        .map(|(dx, dy)| Rect {
+            x: roi.x + dx,
+            y: roi.y + dy,
+            width: size.min(roi.width - dx),
+            height: size.min(roi.height - dy),
+        })
+        .collect()
+}
+
+/// Renderuje `roi` do bufora o jego rozmiarze (piksel (0, 0) bufora = (roi.x, roi.y))
+pub fn render(job: &impl TileJob, roi: Rect) -> RawImage {
+    // Bez tokenu anulowania przebieg nie może się nie udać
+    run(job, roi, None, &|_| {}).unwrap_or_else(|_| RawImage::new(roi.width, roi.height))
+}
+
+/// Jak `render`, ale z anulowaniem sprawdzanym przed każdym kafelkiem i postępem (0..1) po każdym kafelku
+pub fn render_with(job: &impl TileJob, roi: Rect, cancel: &CancelToken, progress: &(dyn Fn(f32) + Sync)) -> ExrResult<RawImage> {
+    run(job, roi, Some(cancel), progress)
+}
+
+fn run(job: &impl TileJob, roi: Rect, cancel: Option<&CancelToken>, progress: &(dyn Fn(f32) + Sync)) -> ExrResult<RawImage> {
+    let tiles = tiles(roi, TILE_SIZE);
+    let total = tiles.len().max(1);
+    let done = AtomicUsize::new(0);
+    let rendered: Vec<(Rect, Vec<Rgba8Pixel>)> = tiles.into_par_iter()
+        .map(|tile| {
+            if let Some(token) = cancel {
+                token.check()?;
+            }
+            let mut pixels = vec![Rgba8Pixel::default(); (tile.width * tile.height) as usize];
+            for (row, out) in pixels.chunks_exact_mut(tile.width as usize).enumerate() {
+                job.row(tile.x, tile.y + row as u32, out);
+            }
+            progress((done.fetch_add(1, Ordering::Relaxed) + 1) as f32 / total as f32);
+            Ok((tile, pixels))
+        })
+        .collect::<ExrResult<_>>()?;
+
+    // Złożenie kafelków: kopiowanie całych wierszy kafelka
+    let mut image = RawImage::new(roi.width, roi.height);
+    for (tile, pixels) in rendered {
+        for (row, src) in pixels.chunks_exact(tile.width as usize).enumerate() {
+            image.put_row(tile.x - roi.x, tile.y - roi.y + row as u32, src);
+        }
+    }
+    Ok(image)
+}
@@ -0,0 +1,344 @@
+// Oś czasu sekwencji bieżącego pliku: przewijanie, klatka po klatce i odtwarzanie. Braki w numeracji
+// są częścią osi (zaznaczone na pasku), a ich obsługę wybiera `GapMode`: przytrzymanie poprzedniej
+// klatki, plansza "missing frame" albo pominięcie. Stan żyje w wątku UI; wykrycie sekwencji
+// (odczyt katalogu) odbywa się w tle.
+
+use std::cell::RefCell;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::time::Duration;
+use slint::{ComponentHandle, Timer, TimerMode};
+use tracing::{info, warn};
+use crate::AppWindow;
+use crate::sequence::{self, Sequence};
+
+/// Zmienna środowiskowa: domyślna obsługa brakujących klatek (hold, slate, skip)
+pub const GAP_MODE_ENV: &str = "EXRUSTER_GAP_MODE";
+/// Tempo odtwarzania; wolniejsze wczytywanie klatek po prostu je obniża (tyknięcia są pomijane)
+const PLAYBACK_FPS: u64 = 24;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GapMode {
+    /// Obraz poprzedniej istniejącej klatki z oznaczeniem braku
+    Hold,
+    /// Plansza z numerem brakującej klatki zamiast obrazu
+    Slate,
+    /// Braki pomijane przy odtwarzaniu i przewijaniu
+    Skip,
+}
+
+impl GapMode {
+    pub fn label(self) -> &'static str {
+        match self {
+            GapMode::Hold => "Hold",
+            GapMode::Slate => "Slate",
+            GapMode::Skip => "Skip",
+        }
+    }
+
+    pub fn from_label(label: &str) -> Option<Self> {
+        match label.trim().to_ascii_lowercase().as_str() {
+            "hold" => Some(GapMode::Hold),
+            "slate" => Some(GapMode::Slate),
+            "skip" => Some(GapMode::Skip),
+            _ => None,
+        }
+    }
+
+    /// Następny tryb przycisku na pasku osi czasu
+    pub fn next(self) -> Self {
+        match self {
+            GapMode::Hold => GapMode::Slate,
+            GapMode::Slate => GapMode::Skip,
+            GapMode::Skip => GapMode::Hold,
+        }
+    }
+}
+
+static GAP_MODE: AtomicU8 = AtomicU8::new(u8::MAX);
+
+pub fn gap_mode() -> GapMode {
+    match GAP_MODE.load(Ordering::Relaxed) {
+        0 => GapMode::Hold,
+        1 => GapMode::Slate,
+        2 => GapMode::Skip,
+        _ => {
+            let mode = std::env::var(GAP_MODE_ENV).ok().and_then(|v| GapMode::from_label(&v)).unwrap_or(GapMode::Hold);
+            set_gap_mode(mode);
+            mode
+        }
+    }
+}
+
+pub fn set_gap_mode(mode: GapMode) {
+    GAP_MODE.store(mode as u8, Ordering::Relaxed);
+}
+
+/// Co pokazać dla pozycji na osi
+#[derive(Clone, Debug, PartialEq)]
+pub enum Shown {
+    File(PathBuf),
+    /// Brak klatki – obraz poprzedniej istniejącej
+    Held(PathBuf),
+    /// Brak klatki – plansza
+    Slate,
+}
+
+pub struct Timeline {
+    pub sequence: Sequence,
+    /// Bieżąca pozycja (może wskazywać brakującą klatkę)
+    pub current: i64,
+}
+
+impl Timeline {
+    pub fn new(sequence: Sequence, path: &Path) -> Self {
+        let current = sequence.index_of(path).map_or(sequence.first(), |i| sequence.frames[i].0);
+        Timeline { sequence, current }
+    }
+
+    /// Liczba pozycji osi (z brakami) przy kroku numeracji sekwencji
+    pub fn positions(&self) -> i64 {
+        (self.sequence.last() - self.sequence.first()) / self.sequence.step() + 1
+    }
+
+    /// Klatka pod ułamkiem długości paska (0..1)
+    pub fn frame_at(&self, fraction: f32) -> i64 {
+        let position = (fraction.clamp(0.0, 1.0) * self.positions() as f32).floor() as i64;
+        self.sequence.first() + position.min(self.positions() - 1) * self.sequence.step()
+    }
+
+    /// Środek pozycji klatki jako ułamek długości paska
+    pub fn fraction_of(&self, frame: i64) -> f32 {
+        ((frame - self.sequence.first()) / self.sequence.step()) as f32 / self.positions() as f32 + 0.5 / self.positions() as f32
+    }
+
+    /// Indeks ostatniej istniejącej klatki nie późniejszej niż `frame`
+    fn existing_at_or_before(&self, frame: i64) -> usize {
+        self.sequence.frames.partition_point(|f| f.0 <= frame).saturating_sub(1)
+    }
+
+    /// Pozycja docelowa i zawartość dla klatki (przycinanej do zakresu); w trybie Skip brak
+    /// przechodzi na następną istniejącą klatkę
+    pub fn resolve(&self, frame: i64, mode: GapMode) -> (i64, Shown) {
+        let frames = &self.sequence.frames;
+        let frame = frame.clamp(self.sequence.first(), self.sequence.last());
+        if let Ok(i) = frames.binary_search_by_key(&frame, |f| f.0) {
+            return (frame, Shown::File(frames[i].1.clone()));
+        }
+        match mode {
+            GapMode::Hold => (frame, Shown::Held(frames[self.existing_at_or_before(frame)].1.clone())),
+            GapMode::Slate => (frame, Shown::Slate),
+            GapMode::Skip => {
+                let next = &frames[(self.existing_at_or_before(frame) + 1).min(frames.len() - 1)];
+                (next.0, Shown::File(next.1.clone()))
+            }
+        }
+    }
+
+    /// Klatka o `delta` pozycji dalej, z zawinięciem na końcach (odtwarzanie w pętli); w trybie Skip
+    /// liczą się tylko istniejące klatki
+    pub fn step(&self, delta: i64, mode: GapMode) -> i64 {
+        let frames = &self.sequence.frames;
+        if mode == GapMode::Skip {
+            let index = (self.existing_at_or_before(self.current) as i64 + delta).rem_euclid(frames.len() as i64);
+            return frames[index as usize].0;
+        }
+        let position = ((self.current - self.sequence.first()) / self.sequence.step() + delta).rem_euclid(self.positions());
+        self.sequence.first() + position * self.sequence.step()
+    }
+
+    /// Pliki kolejnych pozycji osi wg trybu braków (eksport wideo): Hold powtarza poprzednią
+    /// klatkę, Slate daje None (czarna klatka), Skip pomija braki
+    pub fn playlist(&self, mode: GapMode) -> Vec<Option<PathBuf>> {
+        (0..self.positions())
+            .map(|p| self.sequence.first() + p * self.sequence.step())
+            .filter(|&frame| mode != GapMode::Skip || self.sequence.frames.binary_search_by_key(&frame, |f| f.0).is_ok())
+            .map(|frame| match self.resolve(frame, mode).1 {
+                Shown::File(path) | Shown::Held(path) => Some(path),
+                Shown::Slate => None,
+            })
+            .collect()
+    }
+
+    /// Braki jako prostokąty dla `Path` w Slint (viewbox `positions()` × 1)
+    pub fn gaps_path(&self) -> String {
+        let step = self.sequence.step();
+        self.sequence.missing().iter()
+            .map(|&(first, last)| {
+                let x0 = (first - self.sequence.first()) / step;
+                let x1 = (last - self.sequence.first()) / step + 1;
+                format!("M {0} 0 L {1} 0 L {1} 1 L {0} 1 Z", x0, x1)
+            })
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+thread_local! {
+    static TIMELINE: RefCell<Option<Timeline>> = const { RefCell::new(None) };
+    // Plik, dla którego trwa wykrywanie sekwencji (wynik dla innego pliku jest porzucany)
+    static PENDING: RefCell<Option<PathBuf>> = const { RefCell::new(None) };
+    static PLAYBACK: Timer = Timer::default();
+}
+
+/// Otwarto plik: pozycja osi idzie za nim, a plik spoza bieżącej sekwencji uruchamia wykrywanie
+/// nowej w tle. Otwarcie pliku, który jest już pokazywany dla bieżącej pozycji (Hold), jej nie zmienia.
+pub fn follow(ui: &AppWindow, path: &Path) {
+    let known = TIMELINE.with(|t| {
+        let mut timeline = t.borrow_mut();
+        let timeline = timeline.as_mut()?;
+        let index = timeline.sequence.index_of(path)?;
+        let shown = timeline.resolve(timeline.current, gap_mode()).1;
+        if !matches!(&shown, Shown::File(p) | Shown::Held(p) if p == path) {
+            timeline.current = timeline.sequence.frames[index].0;
+            ui.set_timeline_slate("".into());
+            ui.set_timeline_note("".into());
+        }
+        Some(())
+    });
+    if known.is_some() {
+        publish(ui);
+        return;
+    }
+
+    stop_playback(ui);
+    TIMELINE.with(|t| *t.borrow_mut() = None);
+    ui.set_timeline_slate("".into());
+    ui.set_timeline_note("".into());
+    publish(ui);
+    PENDING.with(|p| *p.borrow_mut() = Some(path.to_path_buf()));
+    let ui_weak = ui.as_weak();
+    let path = path.to_path_buf();
+    std::thread::spawn(move || {
+        let found = sequence::sequence_of(&path).unwrap_or_else(|e| {
+            warn!(target: "io", "sequence detection for {}: {:#}", path.display(), e);
+            None
+        });
+        let _ = ui_weak.upgrade_in_event_loop(move |ui| {
+            if PENDING.with(|p| p.borrow().as_deref() != Some(path.as_path())) {
+                return;
+            }
+            PENDING.with(|p| *p.borrow_mut() = None);
+            if let Some(sequence) = found {
+                info!(target: "io", "sequence {}: {} frames, {} gap(s)", sequence.label(), sequence.frames.len(), sequence.missing().len());
+                TIMELINE.with(|t| *t.borrow_mut() = Some(Timeline::new(sequence, &path)));
+                publish(&ui);
+            }
+        });
+    });
+}
+
+/// Przechodzi na klatkę wg bieżącego trybu braków; zwraca plik do otwarcia, jeśli to inny niż
+/// pokazywany (`current`)
+pub fn show(ui: &AppWindow, frame: impl FnOnce(&Timeline) -> i64, current: Option<&Path>) -> Option<PathBuf> {
+    let mode = gap_mode();
+    let (frame, shown) = TIMELINE.with(|t| {
+        let mut timeline = t.borrow_mut();
+        let timeline = timeline.as_mut()?;
+        let (frame, shown) = timeline.resolve(frame(timeline), mode);
+        timeline.current = frame;
+        Some((frame, shown))
+    })?;
+    let (slate, note, path) = match shown {
+        Shown::File(path) => (String::new(), String::new(), Some(path)),
+        Shown::Held(path) => {
+            let held = path.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+            (String::new(), format!("Frame {} missing, holding {}", frame, held), Some(path))
+        }
+        Shown::Slate => (format!("MISSING FRAME {}", frame), format!("Frame {} missing", frame), None),
+    };
+    ui.set_timeline_slate(slate.into());
+    ui.set_timeline_note(note.into());
+    publish(ui);
+    path.filter(|p| Some(p.as_path()) != current)
+}
+
+/// Klatka pod ułamkiem paska (przewijanie)
+pub fn frame_at(fraction: f32) -> Option<i64> {
+    TIMELINE.with(|t| t.borrow().as_ref().map(|t| t.frame_at(fraction)))
+}
+
+/// Etykieta sekwencji i lista klatek do eksportu wideo (bieżący tryb braków)
+pub fn playlist() -> Option<(Sequence, Vec<Option<PathBuf>>)> {
+    TIMELINE.with(|t| t.borrow().as_ref().map(|t| (t.sequence.clone(), t.playlist(gap_mode()))))
+}
+
+pub fn has_timeline() -> bool {
+    TIMELINE.with(|t| t.borrow().is_some())
+}
+
+/// Odtwarzanie w pętli: co tyknięcie krok o klatkę przez callback `step-frame` (akcja jak
+/// z przycisku); tyknięcie w trakcie wczytywania poprzedniej klatki jest pomijane
+pub fn toggle_playback(ui: &AppWindow, loading: impl Fn() -> bool + 'static) {
+    if ui.get_timeline_playing() {
+        stop_playback(ui);
+        return;
+    }
+    if !has_timeline() {
+        return;
+    }
+    ui.set_timeline_playing(true);
+    let ui_weak = ui.as_weak();
+    PLAYBACK.with(|timer| {
+        timer.start(TimerMode::Repeated, Duration::from_millis(1000 / PLAYBACK_FPS), move || {
+            let Some(ui) = ui_weak.upgrade() else { return; };
+            if !loading() {
+                ui.invoke_step_frame(1);
+            }
+        });
+    });
+}
+
+pub fn stop_playback(ui: &AppWindow) {
+    PLAYBACK.with(|timer| timer.stop());
+    ui.set_timeline_playing(false);
+}
+
+fn publish(ui: &AppWindow) {
+    TIMELINE.with(|t| match t.borrow().as_ref() {
+        Some(timeline) => {
+            ui.set_timeline_visible(true);
+            ui.set_timeline_label(timeline.sequence.label().into());
+            ui.set_timeline_frame(timeline.current as i32);
+            ui.set_timeline_positions(timeline.positions() as i32);
+            ui.set_timeline_position(timeline.fraction_of(timeline.current));
+            ui.set_timeline_gaps(timeline.gaps_path().into());
+        }
+        None => ui.set_timeline_visible(false),
+    });
+    ui.set_timeline_gap_mode(gap_mode().label().into());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn timeline(frames: &[i64], current: i64) -> Timeline {
+        let files: Vec<PathBuf> = frames.iter().map(|f| PathBuf::from(format!("shot.{:04}.exr", f))).collect();
+        let sequence = sequence::detect(&files).remove(0);
+        Timeline { sequence, current }
+    }
+
+    #[test]
+    fn gaps_follow_mode() {
+        // 1, 2, [3, 4], 5
+        let t = timeline(&[1, 2, 5], 2);
+        let file = |f: i64| PathBuf::from(format!("shot.{:04}.exr", f));
+        assert_eq!(t.positions(), 5);
+        assert_eq!(t.gaps_path(), "M 2 0 L 4 0 L 4 1 L 2 1 Z");
+        assert_eq!(t.resolve(3, GapMode::Hold), (3, Shown::Held(file(2))));
+        assert_eq!(t.resolve(4, GapMode::Slate), (4, Shown::Slate));
+        assert_eq!(t.resolve(3, GapMode::Skip), (5, Shown::File(file(5))));
+        assert_eq!(t.resolve(99, GapMode::Hold), (5, Shown::File(file(5))));
+
+        // Krok przez brak (Hold/Slate) albo nad nim (Skip); koniec zawija na początek
+        assert_eq!((t.step(1, GapMode::Hold), t.step(1, GapMode::Skip)), (3, 5));
+        assert_eq!(timeline(&[1, 2, 5], 5).step(1, GapMode::Slate), 1);
+        assert_eq!((t.frame_at(0.0), t.frame_at(0.5), t.frame_at(1.0)), (1, 3, 5));
+
+        let some = |f: i64| Some(file(f));
+        assert_eq!(t.playlist(GapMode::Hold), vec![some(1), some(2), some(2), some(2), some(5)]);
+        assert_eq!(t.playlist(GapMode::Slate), vec![some(1), some(2), None, None, some(5)]);
+        assert_eq!(t.playlist(GapMode::Skip), vec![some(1), some(2), some(5)]);
+    }
+}
@@ -1,237 +1,223 @@
-use slint::{Weak, ComponentHandle, Timer, TimerMode, ModelRc, VecModel, SharedString, Color};
+use slint::{Weak, ComponentHandle, Timer, TimerMode, Model, ModelRc, VecModel, SharedString, Color};
 use std::sync::{Arc, Mutex, MutexGuard};
-use std::path::PathBuf;
+use std::sync::mpsc::Sender;
+use std::path::{Path, PathBuf};
 use std::time::{Duration, Instant};
-use std::collections::HashMap;
-use crate::image_cache::ImageCache;
-use crate::file_operations::{open_file_dialog, get_file_name};
+use crate::image_cache::{ImageCache, load_preview_proxy};
+use crate::file_operations::{self, get_file_name};
 use std::rc::Rc;
 // removed unused: use exr::prelude as exr;
 use crate::exr_metadata;
-use crate::progress::{ProgressSink, UiProgress};
+use crate::progress::{self, FnProgress, ProgressSink, TaskProgress};
+use crate::browser::{FolderBrowser, list_folder_entries};
+use crate::utils::human_size;
+use crate::cancel::CancelToken;
+use crate::utils::error_handling::ExrResult;
+use crate::session;
+use crate::proxy_files;
+use crate::env_map::EnvMapKind;
+use crate::stereo;
+use crate::channel_classification::{self, AovKind, PreviewMode};
+use crate::image_processing::{self, ChannelRemap, LegendUnit, Stage};
+use crate::app_context::{AnnotationState, AppContext};
+use crate::histogram;
+use crate::raw_image::RawImage;
+use crate::theme;
+use crate::platform;
+use crate::annotations::{self, Annotation, Shape};
+use crate::snapshot_gallery::{self, Snapshot};
+use tracing::{debug, error, info, warn};
 
 // Import komponentów Slint
-use crate::AppWindow;
+use crate::{AnnotationItem, AppWindow, FolderItem, HistogramChannel, LayerNode, PipelineStage, SnapshotItem, Swatch, ThumbItem};
 
 pub type ImageCacheType = Arc<Mutex<Option<ImageCache>>>;
 pub type CurrentFilePathType = Arc<Mutex<Option<PathBuf>>>;
 pub type ConsoleModel = Rc<VecModel<SharedString>>;
+pub type FolderBrowserType = Arc<Mutex<FolderBrowser>>;
 
-/// Dodaje linię do modelu konsoli i aktualizuje tekst w `TextEdit` (console-text)
-pub fn push_console(ui: &crate::AppWindow, console: &ConsoleModel, line: String) {
-    console.push(line.clone().into());
-    let mut joined = ui.get_console_text().to_string();
-    if !joined.is_empty() { joined.push('\n'); }
-    joined.push_str(&line);
-    ui.set_console_text(joined.into());
-}
-
-static LAST_PREVIEW_LOG: std::sync::Mutex<Option<Instant>> = std::sync::Mutex::new(None);
+// Rodzaje węzłów drzewa warstw (pole `kind` w LayerNode)
+pub(crate) const NODE_KIND_GROUP: &str = "group";
+pub(crate) const NODE_KIND_LAYER: &str = "layer";
+const NODE_KIND_CHANNEL: &str = "channel";
 
 
 
 #[inline]
-fn lock_or_recover<T>(m: &Mutex<T>) -> MutexGuard<'_, T> {
+pub(crate) fn lock_or_recover<T>(m: &Mutex<T>) -> MutexGuard<'_, T> {
     match m.lock() {
         Ok(g) => g,
         Err(p) => p.into_inner(),
     }
 }
 
-// Uproszczone: usunięty stan drzewa i globalny TREE_STATE
-// Mapowanie linii modelu na nazwę warstwy (aby kanał wiedział, do której warstwy należy)
-static ITEM_TO_LAYER: std::sync::LazyLock<std::sync::Mutex<HashMap<String, String>>> =
-    std::sync::LazyLock::new(|| std::sync::Mutex::new(HashMap::new()));
+/// Synchronizuje kontrolki gain/offset/abs w panelu z mapowaniem bieżącego widoku
+fn sync_remap_controls(ui: &AppWindow, remap: Option<ChannelRemap>) {
+    ui.set_aov_remap_active(remap.is_some());
+    if let Some(remap) = remap {
+        ui.set_aov_gain(remap.gain);
+        ui.set_aov_offset(remap.offset);
+        ui.set_aov_abs(remap.abs);
+    }
+}
 
-// Mapowanie wyświetlanej nazwy warstwy → rzeczywista nazwa z pliku (np. "Beauty" → "")
-static DISPLAY_TO_REAL_LAYER: std::sync::LazyLock<std::sync::Mutex<HashMap<String, String>>> =
-    std::sync::LazyLock::new(|| std::sync::Mutex::new(HashMap::new()));
+/// W trybie porównania zwraca obraz różnicy względem referencji i opis z metrykami; None = zwykły podgląd
+fn render_compare(context: &AppContext, cache: &ImageCache, exposure: f32, gamma: f32) -> Option<(RawImage, String)> {
+    let transform = context.view().render.transform;
+    match context.compare().render_diff(cache, exposure, gamma, &transform)? {
+        Ok((image, stats)) => Some((image, format!("Diff | {}", stats.summary()))),
+        Err(msg) => {
+            warn!(target: "processing", "{}", msg);
+            None
+        }
+    }
+}
 
-// Normalizacja nazw kanałów do skrótu R/G/B/A
-#[inline]
-fn normalize_channel_display_to_short(channel_display: &str) -> String {
-    let lower = channel_display.trim().to_ascii_lowercase();
-    match lower.as_str() {
-        "r" | "red" => "R".to_string(),
-        "g" | "green" => "G".to_string(),
-        "b" | "blue" => "B".to_string(),
-        "a" | "alpha" => "A".to_string(),
-        _ => channel_display.to_string(),
+/// Ustawia tryb podglądu wg reguły dla rodzaju AOV i generuje obraz; zwraca obraz i opis trybu
+fn render_classified(ui: &AppWindow, context: &AppContext, cache: &mut ImageCache, kind: AovKind, lighting_rgb: bool) -> (RawImage, String) {
+    let exposure = ui.get_exposure_value();
+    let gamma = ui.get_gamma_value();
+    let settings = context.render_settings(exposure, gamma);
+    cache.channel_remap = None;
+    cache.vector_view = None;
+    cache.depth_view = None;
+    // Relight dotyczy całych warstw normalnych (kanał pojedynczy to zwykłe dane)
+    cache.normals_view = kind == AovKind::Normal && lighting_rgb;
+    ui.set_normals_view_active(cache.normals_view);
+    // Porównanie z referencją ma pierwszeństwo przed trybem AOV
+    if let Some(rendered) = render_compare(context, cache, exposure, gamma) {
+        sync_remap_controls(ui, None);
+        ui.set_vector_view_active(false);
+        ui.set_depth_view_active(false);
+        return rendered;
     }
+    let rendered = match channel_classification::preview_mode(kind, lighting_rgb) {
+        PreviewMode::Percentile { invert } => {
+            let mode = format!("{} (auto-normalized{})", kind.label(), if invert { ", inverted" } else { "" });
+            // Focus peaking tylko dla głębi; inne AOV z percentylami (ID, cryptomatte) bez pasma
+            if kind == AovKind::Depth {
+                cache.depth_view = Some(invert);
+            }
+            (cache.process_depth_image(invert, &settings), mode)
+        }
+        PreviewMode::Remap(remap) => {
+            cache.channel_remap = Some(remap);
+            (cache.process_to_composite(&settings, lighting_rgb), format!("{} (gain/offset)", kind.label()))
+        }
+        PreviewMode::Vector(view) => {
+            cache.vector_view = Some(view);
+            (cache.process_to_image(&settings), format!("{} (vector HSV)", kind.label()))
+        }
+        PreviewMode::Color => {
+            let mode = if lighting_rgb { "RGB" } else { "Grayscale" };
+            (cache.process_to_composite(&settings, lighting_rgb), mode.to_string())
+        }
+    };
+    sync_remap_controls(ui, cache.channel_remap);
+    ui.set_vector_view_active(cache.vector_view.is_some());
+    ui.set_depth_view_active(cache.depth_view.is_some());
+    rendered
+}
+
+/// Transformacja wejściowa "Auto" wg części pliku z bieżącą warstwą – w pliku wieloczęściowym
+/// każda część może mieć własne `chromaticities`
+fn apply_layer_color_space(ui: &AppWindow, context: &AppContext, cache: &ImageCache) {
+    let Some(layer) = cache.layers_info.iter().find(|l| l.name == cache.current_layer_name) else { return; };
+    let detected = &layer.color_space;
+    context.set_detected_input(detected.space, detected.matrix);
+    ui.set_detected_color_space(detected.space.label().into());
+    debug!(target: "processing", "input color space for layer {}: {} ({})", layer.name, detected.space.label(), detected.reason);
 }
 
 pub fn handle_layer_tree_click(
     ui_handle: Weak<AppWindow>,
     image_cache: ImageCacheType,
-    clicked_item: String,
+    node: LayerNode,
     current_file_path: CurrentFilePathType,
-    console: ConsoleModel,
+    context: Arc<AppContext>,
 ) {
-    // Sprawdź czy kliknięto na warstwę (zaczyna się od 📁)
-    if clicked_item.starts_with("📁") {
-                if let Some(ui) = ui_handle.upgrade() {
-            // Wyodrębnij wyświetlaną nazwę warstwy (usuń emoji i spacje)
-            let display_layer_name = clicked_item.trim_start_matches("📁").trim().to_string();
-            // Zmapuj na rzeczywistą nazwę z pliku (np. "Beauty" → "")
-            let layer_name = {
-                let map = lock_or_recover(&DISPLAY_TO_REAL_LAYER);
-                map.get(&display_layer_name).cloned().unwrap_or_else(|| display_layer_name.clone())
-            };
-            
-            let mut status_msg = String::new();
-            status_msg.push_str(&format!("Loading layer: {}", display_layer_name));
-            push_console(&ui, &console, format!("[layer] clicked: {} (real='{}')", display_layer_name, layer_name));
-            
-            // Pobierz ścieżkę do pliku
-            let file_path = {
-                let path_guard = lock_or_recover(&current_file_path);
-                path_guard.clone()
-            };
-            
-            if let Some(path) = file_path {
-                // Załaduj nową warstwę
-                let mut cache_guard = lock_or_recover(&image_cache);
-                if let Some(ref mut cache) = *cache_guard {
-                    match cache.load_layer(&path, &layer_name) {
-                        Ok(()) => {
-                            // Pobierz aktualne wartości ekspozycji i gammy
-                            let exposure = ui.get_exposure_value();
-                            let gamma = ui.get_gamma_value();
-                            // Warstwa → kompozyt RGB (z duplikowaniem brakujących kanałów)
-                            let image = cache.process_to_composite(exposure, gamma, true);
-                            ui.set_exr_image(image);
-                            push_console(&ui, &console, format!("[layer] {} → mode: RGB (composite)", layer_name));
-                            push_console(&ui, &console, format!("[preview] updated → mode: RGB (composite), layer: {}", layer_name));
-                            let channels = cache.layers_info
-                                .iter()
-                                .find(|l| l.name == layer_name)
-                                .map(|l| l.channels.iter().map(|c| c.name.clone()).collect::<Vec<_>>().join(", "))
-                                .unwrap_or_else(|| "?".into());
-                            status_msg = format!("Layer: {} | mode: RGB | channels: {}", layer_name, channels);
-                            ui.set_status_text(status_msg.into());
-                            // Zaznacz w liście wybraną warstwę
-                            ui.set_selected_layer_item(format!("📁 {}", display_layer_name).into());
-                        }
-                        Err(e) => {
-                            ui.set_status_text(format!("Error loading layer {}: {}", layer_name, e).into());
-                            push_console(&ui, &console, format!("[error] loading layer {}: {}", layer_name, e));
-                        }
-                    }
-                }
-            } else {
-                ui.set_status_text("Error: No file loaded".into());
-                push_console(&ui, &console, "[error] no file loaded".to_string());
+    // Klik w sekcję tylko ją zwija/rozwija
+    if node.kind == NODE_KIND_GROUP {
+        handle_layer_node_toggled(ui_handle, node.id);
+        return;
+    }
+    let Some(ui) = ui_handle.upgrade() else { return; };
+    // Węzeł niesie rzeczywistą nazwę warstwy i kanału z pliku – bez parsowania etykiet
+    let layer_name = node.layer.to_string();
+    let channel = node.channel.to_string();
+    let is_layer = node.kind == NODE_KIND_LAYER;
+    debug!(target: "ui", "layer tree clicked: {} (layer='{}', channel='{}')", node.label, layer_name, channel);
+
+    let file_path = lock_or_recover(&current_file_path).clone();
+    let Some(path) = file_path else {
+        ui.set_status_text("Error: No file loaded".into());
+        warn!(target: "ui", "no file loaded");
+        return;
+    };
+
+    let mut cache_guard = lock_or_recover(&image_cache);
+    let Some(ref mut cache) = *cache_guard else { return; };
+
+    if is_layer {
+        ui.set_status_text(format!("Loading layer: {}", node.label).into());
+        match cache.load_layer(&path, &layer_name) {
+            Ok(()) => {
+                apply_layer_color_space(&ui, &context, cache);
+                update_view_panels(&ui, &context, cache);
+                // Warstwa → kompozyt RGB (z duplikowaniem brakujących kanałów), warstwa monochromatyczna
+                // → skala szarości; tryb wg reguł klasyfikacji AOV
+                let kind = channel_classification::classify(&layer_name, "");
+                let rgb = !cache.monochrome;
+                let (image, mode) = render_classified(&ui, &context, cache, kind, rgb);
+                ui.set_exr_image(context.for_display(image));
+                info!(target: "ui", "layer {} → mode: {} (composite)", layer_name, mode);
+                debug!(target: "processing", "preview updated → mode: {} (composite), layer: {}", mode, layer_name);
+                let channels = cache.layers_info
+                    .iter()
+                    .find(|l| l.name == layer_name)
+                    .map(|l| l.channels.iter().map(|c| c.name.clone()).collect::<Vec<_>>().join(", "))
+                    .unwrap_or_else(|| "?".into());
+                ui.set_status_text(format!("Layer: {} | mode: {} | channels: {}", layer_name, mode, channels).into());
+                ui.set_selected_layer_node(node.id);
+            }
+            Err(e) => {
+                ui.set_status_text(format!("Error loading layer {}: {}", layer_name, e.user_message()).into());
+                error!(target: "io", "loading layer {}: {}", layer_name, e);
             }
         }
-    }
-    // Sprawdź klik kanału (wiersz zaczyna się od „• ” lub emoji koloru)
-    else {
-        // próbujemy dopasować „    • X” lub „    🔴 R/🟢 G/🔵 B/⚪ A”
-        let trimmed = clicked_item.trim();
-        let is_dot = trimmed.starts_with("• ");
-        let is_rgba_emoji = trimmed.starts_with("🔴") || trimmed.starts_with("🟢") || trimmed.starts_with("🔵") || trimmed.starts_with("⚪");
-        if !(is_dot || is_rgba_emoji) { return; }
-
-        // Ustal aktywną warstwę i skrót kanału z klikniętej linii (preferuj sufiks '@Warstwa' jeżeli jest obecny)
-        if let Some(ui) = ui_handle.upgrade() {
-            let file_path = {
-                let path_guard = lock_or_recover(&current_file_path);
-                path_guard.clone()
-            };
-            if file_path.is_none() { return; }
-
-            let mut cache_guard = lock_or_recover(&image_cache);
-            if let Some(ref mut cache) = *cache_guard {
-                // Preferuj parsowanie z sufiksu '@Warstwa' aby uniknąć kolizji duplikatów w mapie
-                let (active_layer, channel_short) = {
-                    let s = trimmed;
-                    if let Some(at_pos) = s.rfind('@') {
-                        let layer_display = s[at_pos + 1..].trim().to_string();
-                        // Zmapuj wyświetlaną nazwę warstwy na rzeczywistą (np. "Beauty" → "")
-                        let layer = {
-                            let map = lock_or_recover(&DISPLAY_TO_REAL_LAYER);
-                            map.get(&layer_display).cloned().unwrap_or(layer_display)
-                        };
-                        let left = s[..at_pos].trim();
-                        let ch_short = if is_dot {
-                            left.trim_start_matches('•').trim().to_string()
-                        } else {
-                            left.split_whitespace().nth(1).unwrap_or("").to_string()
-                        };
-                        (layer, ch_short)
-                    } else {
-                        // Fallback: użyj mapy i dotychczasowego parsowania
-                        let active_layer = {
-                            let key = clicked_item.trim_end().to_string();
-                            let map = lock_or_recover(&ITEM_TO_LAYER);
-                            map.get(&key).cloned().unwrap_or_else(|| cache.current_layer_name.clone())
-                        };
-                        let ch_short = if is_dot {
-                            trimmed.trim_start_matches("• ").trim().to_string()
-                        } else {
-                            trimmed.split_whitespace().nth(1).unwrap_or("").to_string()
-                        };
-                        (active_layer, ch_short)
-                    }
-                };
-                // Jeżeli kliknięto na przyjazną nazwę (Red/Green/Blue/Alpha), zamień na skrót R/G/B/A
-                let channel_short = normalize_channel_display_to_short(&channel_short);
-                // NIE normalizujemy nazw — używamy 1:1 z pliku; jedynie tryb Depth rozpoznamy później po wzorcu
-
-                let path = file_path.unwrap();
-                // Brak specjalnego traktowania Cryptomatte – kanały jak w każdej warstwie
-
-                match cache.load_channel(&path, &active_layer, &channel_short) {
-                    Ok(()) => {
-                        let exposure = ui.get_exposure_value();
-                        let gamma = ui.get_gamma_value();
-
-                        // Specjalny przypadek Depth: jeżeli nazwa kanału to Z/Depth, użyj process_depth_image z invertem= true (near jasne)
-                        let upper = channel_short.to_ascii_uppercase();
-                        if upper == "Z" || upper.contains("DEPTH") {
-                            let image = cache.process_depth_image(true);
-                            ui.set_exr_image(image);
-                            ui.set_status_text(format!("Layer: {} | Channel: {} | mode: Depth (auto-normalized, inverted)", active_layer, channel_short).into());
-                            push_console(&ui, &console, format!("[channel] {}@{} → mode: Depth (auto-normalized, inverted)", channel_short, active_layer));
-                            push_console(&ui, &console, format!("[preview] updated → mode: Depth (auto-normalized, inverted), {}::{}", active_layer, channel_short));
-                        } else {
-                            // Kanał → grayscale przez standardowy pipeline
-                            let image = cache.process_to_composite(exposure, gamma, false);
-                            ui.set_exr_image(image);
-                            ui.set_status_text(format!("Layer: {} | Channel: {} | mode: Grayscale", active_layer, channel_short).into());
-                            push_console(&ui, &console, format!("[channel] {}@{} → mode: Grayscale", channel_short, active_layer));
-                            push_console(&ui, &console, format!("[preview] updated → mode: Grayscale, {}::{}", active_layer, channel_short));
-                        }
-                        // Ustaw podświetlenie wybranego wiersza na liście
-                        let display_layer = {
-                            let map = lock_or_recover(&DISPLAY_TO_REAL_LAYER);
-                            // Odwrotne mapowanie: znajdź klucz po wartości jeśli to możliwe
-                            map.iter().find_map(|(k, v)| if v == &active_layer { Some(k.clone()) } else { None }).unwrap_or(active_layer.clone())
-                        };
-                        let label = match channel_short.as_str() {
-                            "R" | "r" => "    🔴 Red",
-                            "G" | "g" => "    🟢 Green",
-                            "B" | "b" => "    🔵 Blue",
-                            "A" | "a" => "    ⚪ Alpha",
-                            _ => "    • ",
-                        };
-                        let selected = if label == "    • " {
-                            format!("{} @{}", channel_short, display_layer)
-                        } else {
-                            format!("{} @{}", label, display_layer)
-                        };
-                        ui.set_selected_layer_item(selected.into());
-                    }
-                    Err(e) => {
-                        ui.set_status_text(format!("Error loading channel {}: {}", channel_short, e).into());
-                        push_console(&ui, &console, format!("[error] loading channel {}@{}: {}", channel_short, active_layer, e));
-                    }
-                }
+    } else {
+        match cache.load_channel(&path, &layer_name, &channel) {
+            Ok(()) => {
+                apply_layer_color_space(&ui, &context, cache);
+                update_view_panels(&ui, &context, cache);
+                // Tryb wg reguł klasyfikacji AOV: Depth → auto-normalizacja percentylowa (near jasne),
+                // AOV techniczne → mapowanie gain/offset, pozostałe → grayscale przez standardowy pipeline
+                let kind = channel_classification::classify(&layer_name, &channel);
+                let (image, mode) = render_classified(&ui, &context, cache, kind, false);
+                ui.set_exr_image(context.for_display(image));
+                ui.set_status_text(format!("Layer: {} | Channel: {} | mode: {}", layer_name, channel, mode).into());
+                info!(target: "ui", "channel {}@{} → mode: {}", channel, layer_name, mode);
+                debug!(target: "processing", "preview updated → mode: {}, {}::{}", mode, layer_name, channel);
+                ui.set_selected_layer_node(node.id);
+            }
+            Err(e) => {
+                ui.set_status_text(format!("Error loading channel {}: {}", channel, e.user_message()).into());
+                error!(target: "io", "loading channel {}@{}: {}", channel, layer_name, e);
             }
         }
     }
 }
 
+/// Zwija/rozwija dzieci węzła o podanym id (stan trzymany w modelu węzłów)
+pub fn handle_layer_node_toggled(ui_handle: Weak<AppWindow>, id: i32) {
+    let Some(ui) = ui_handle.upgrade() else { return; };
+    let nodes = ui.get_layer_nodes();
+    let Some(mut node) = nodes.row_data(id as usize).filter(|n| n.id == id) else { return; };
+    node.expanded = !node.expanded;
+    nodes.set_row_data(id as usize, node);
+    refresh_layer_visibility(&nodes, ui.get_lighting_only());
+}
+
 // Dodaj throttling timer dla smooth updates
 pub struct ThrottledUpdate {
     _timer: Timer,
@@ -275,6 +261,7 @@ impl ThrottledUpdate {
 
 /// Obsługuje callback wyjścia z aplikacji
 pub fn handle_exit(ui_handle: Weak<AppWindow>) {
+    session::save_current();
     if let Some(ui) = ui_handle.upgrade() {
         let _ = ui.window().hide();
     }
@@ -285,20 +272,26 @@ pub fn handle_open_exr(
     ui_handle: Weak<AppWindow>,
     current_file_path: CurrentFilePathType,
     image_cache: ImageCacheType,
-    console: ConsoleModel,
+    context: Arc<AppContext>,
 ) {
-    if let Some(ui) = ui_handle.upgrade() {
-        let prog = UiProgress::new(ui.as_weak());
-        prog.start_indeterminate(Some("Opening EXR file..."));
-        push_console(&ui, &console, "[file] opening EXR file".to_string());
+    let Some(ui) = ui_handle.upgrade() else { return; };
+    ui.set_status_text("Opening EXR file...".into());
+    info!(target: "io", "opening EXR file");
 
-        if let Some(path) = open_file_dialog() {
-            handle_open_exr_from_path(ui_handle, current_file_path, image_cache, console, path);
-        } else {
-            prog.reset();
+    let spawned = slint::spawn_local(async move {
+        let paths = file_operations::pick_image_files().await;
+        let Some(ui) = ui_handle.upgrade() else { return; };
+        if paths.is_empty() {
             ui.set_status_text("File selection canceled".into());
-            push_console(&ui, &console, "[file] selection canceled".to_string());
+            info!(target: "ui", "file selection canceled");
+            return;
         }
+        // Kilka plików tworzy listę odtwarzania (PageUp/PageDown), pojedynczy ją zamyka
+        let Some(first) = crate::playlist::set(&ui, paths) else { return; };
+        handle_open_exr_from_path(ui_handle, current_file_path, image_cache, context, first);
+    });
+    if let Err(e) = spawned {
+        error!(target: "ui", "cannot show the file dialog: {}", e);
     }
 }
 
@@ -307,13 +300,55 @@ pub fn handle_open_exr_from_path(
     ui_handle: Weak<AppWindow>,
     current_file_path: CurrentFilePathType,
     image_cache: ImageCacheType,
-    console: ConsoleModel,
+    context: Arc<AppContext>,
     path: PathBuf,
+) {
+    open_exr(ui_handle, current_file_path, image_cache, context, path, proxy_files::prefer_proxies());
+}
+
+/// Otwiera oryginał z pominięciem pliku proxy (dla proxy: jego plik źródłowy)
+pub fn handle_open_original(
+    ui_handle: Weak<AppWindow>,
+    current_file_path: CurrentFilePathType,
+    image_cache: ImageCacheType,
+    context: Arc<AppContext>,
+) {
+    let Some(path) = lock_or_recover(&current_file_path).clone() else { return; };
+    let original = proxy_files::original_path(&path).unwrap_or(path);
+    open_exr(ui_handle, current_file_path, image_cache, context, original, false);
+}
+
+fn open_exr(
+    ui_handle: Weak<AppWindow>,
+    current_file_path: CurrentFilePathType,
+    image_cache: ImageCacheType,
+    context: Arc<AppContext>,
+    requested: PathBuf,
+    allow_proxy: bool,
 ) {
     if let Some(ui) = ui_handle.upgrade() {
-        let prog = UiProgress::new(ui.as_weak());
+        // Aktualne proxy obok ciężkiego pliku wczytuje się zamiast niego; sesja pamięta oryginał
+        let proxy = if allow_proxy { proxy_files::fresh_proxy(&requested) } else { None };
+        if let Some(proxy) = &proxy {
+            info!(target: "io", "using proxy {} for {}", proxy.display(), requested.display());
+        }
+        let original = proxy_files::original_path(&requested).unwrap_or_else(|| requested.clone());
+        let path = proxy.unwrap_or(requested);
+        ui.set_proxy_original(if proxy_files::is_proxy(&path) { original.display().to_string().into() } else { "".into() });
+
+        // Przerwij trwające wczytywanie poprzedniego pliku (zwalnia jego bufory)
+        let cancel = CancelToken::new();
+        let previous = context.load().cancel.replace(cancel.clone());
+        if let Some(prev) = previous {
+            prev.cancel();
+            finish_load_waiters(&context, Err("superseded by another file".to_string()));
+            info!(target: "io", "previous load canceled");
+        }
+
+        // Zadanie na liście zadań; ✕ przerywa wczytywanie tym samym tokenem co nowy plik
+        let prog = progress::register(ui.as_weak(), format!("Open {}", get_file_name(&path)), Some(cancel.clone()));
         prog.set(0.05, Some(&format!("Loading: {}", path.display())));
-        push_console(&ui, &console, format!("{{\"event\":\"file.open\",\"path\":\"{}\"}}", path.display()));
+        info!(target: "io", path = %path.display(), "file.open");
 
         // Zbuduj i wyświetl metadane w zakładce Meta
         match exr_metadata::read_and_group_metadata(&path) {
@@ -327,80 +362,534 @@ pub fn handle_open_exr_from_path(
                 let (keys, vals): (Vec<_>, Vec<_>) = rows.into_iter().unzip();
                 ui.set_meta_table_keys(ModelRc::new(VecModel::from(keys.into_iter().map(SharedString::from).collect::<Vec<_>>())));
                 ui.set_meta_table_values(ModelRc::new(VecModel::from(vals.into_iter().map(SharedString::from).collect::<Vec<_>>())));
-                push_console(&ui, &console, format!("[meta] layers: {}", meta.layers.len()));
+                info!(target: "io", "metadata: {} layers", meta.layers.len());
+                // Transformacja wejściowa z nagłówka (tryb "Auto" w panelu); ręczny wybór ma pierwszeństwo
+                context.set_detected_input(meta.color_space.space, meta.color_space.matrix);
+                ui.set_detected_color_space(meta.color_space.space.label().into());
+                info!(target: "processing", "input color space: {} ({})", meta.color_space.space.label(), meta.color_space.reason);
                 prog.set(0.15, Some("Metadata loaded"));
             }
             Err(e) => {
                 ui.set_meta_text(format!("Błąd odczytu metadanych: {}", e).into());
-                push_console(&ui, &console, format!("[error][meta] {}", e));
+                error!(target: "io", "metadata: {}", e);
                 prog.reset();
             }
         }
 
-        // Zapisz ścieżkę do pliku
+        // Zapisz ścieżkę do pliku (także w sesji przywracanej po awarii)
         { *lock_or_recover(&current_file_path) = Some(path.clone()); }
+        crate::timeline::follow(&ui, &original);
+        crate::playlist::follow(&ui, &original);
+        session::update(true, |s| s.last_file = Some(original.clone()));
+        platform::add_recent_file(&original);
+        // Porzuć poprzedni cache, aby zmiany suwaków nie nadpisywały podglądu nowego pliku starym obrazem
+        { *lock_or_recover(&image_cache) = None; }
+
+        // Duże pliki: najpierw zgrubne proxy, potem pełna jakość (coarse-to-fine)
+        let file_size = std::fs::metadata(&path).map(|m| m.len()).unwrap_or(0);
+        let progressive = file_size >= PROGRESSIVE_MIN_FILE_BYTES;
 
-        // Utwórz cache obrazu (jednorazowy odczyt z dysku)
-        prog.set(0.25, Some("Creating image cache..."));
-        push_console(&ui, &console, "[cache] creating image cache".to_string());
-        let t_new = Instant::now();
-        match ImageCache::new(&path) {
-            Ok(cache) => {
-                prog.set(0.45, Some("Cache created, processing..."));
-                push_console(&ui, &console, "[cache] cache created".to_string());
-                push_console(&ui, &console, format!("{{\"type\":\"timing\",\"op\":\"ImageCache.new\",\"ms\":{}}}", t_new.elapsed().as_millis()));
-
-                // Pobierz aktualne wartości ekspozycji i gammy
-                let exposure = ui.get_exposure_value();
-                let gamma = ui.get_gamma_value();
-
-                // Przetwórz obraz z cache'a
-                let pixel_count = cache.raw_pixels.len();
-                let t_proc = Instant::now();
-                // sygnalizuj dłuższe przetwarzanie (duże obrazy) jako indeterminate
-                if pixel_count > 2_000_000 { prog.start_indeterminate(Some("Processing image...")); }
-                let image = cache.process_to_image(exposure, gamma);
-                push_console(&ui, &console, format!("{{\"type\":\"timing\",\"op\":\"process_to_image\",\"pixels\":{},\"ms\":{}}}", pixel_count, t_proc.elapsed().as_millis()));
-                push_console(&ui, &console, format!("[preview] image generated: {} pixels (exp: {:.2}, gamma: {:.2})", pixel_count, exposure, gamma));
-
-                // Przekaż informacje o warstwach do UI (prosty model, bez stanu drzewa)
+        // Utwórz cache obrazu w tle (jednorazowy odczyt z dysku); wyniki wracają do pętli zdarzeń UI
+        prog.set(0.25, Some(if progressive { "Decoding preview..." } else { "Creating image cache..." }));
+        debug!(target: "io", "creating image cache");
+        // Drugie oko pliku stereo wczytuje się razem z plikiem, gdy widok je składa
+        let stereo_mode = context.view().render.stereo_mode;
+        // Sąsiad z paska miniatur wczytany z wyprzedzeniem – gotowy wynik bez dekodowania
+        let preloaded = crate::preload::take(&path);
+        let ui_weak = ui.as_weak();
+        rayon::spawn(move || {
+            let (result, ms) = match preloaded {
+                Some(mut cache) => {
+                    info!(target: "io", "using preloaded {}", path.display());
+                    cache.set_stereo_mode(&path, stereo_mode);
+                    (Ok(cache), 0)
+                }
+                None => {
+                    if progressive {
+                        let t_proxy = Instant::now();
+                        if let Ok(proxy) = load_preview_proxy(&path, PROXY_MAX_SIZE, &cancel) {
+                            show_load_proxy(&ui_weak, &context, &cancel, proxy, t_proxy.elapsed().as_millis());
+                            prog.set(0.35, Some("Preview ready, decoding full image..."));
+                        }
+                    }
+                    let t_new = Instant::now();
+                    let progress = FnProgress(|p: f32, message: Option<&str>| prog.set(0.35 + 0.1 * p.max(0.0), message));
+                    let result = ImageCache::new(&path, &cancel, &progress).map(|mut cache| {
+                        cache.set_stereo_mode(&path, stereo_mode);
+                        cache
+                    });
+                    (result, t_new.elapsed().as_millis())
+                }
+            };
+            // Anulowany odczyt nie ma już odbiorcy – wynik (i jego bufory) porzucamy w tym wątku
+            let result = (!cancel.is_cancelled()).then_some(result);
+            let _ = ui_weak.upgrade_in_event_loop(move |ui| {
+                // Nowszy plik przejął wczytywanie (jego otwarcie powiadomiło już oczekujących)
                 {
-                    let (layers_model, layers_colors, layers_font_sizes) = create_layers_model(&cache.layers_info, &ui);
-                    ui.set_layers_model(layers_model);
-                    ui.set_layers_colors(layers_colors);
-                    ui.set_layers_font_sizes(layers_font_sizes);
+                    let mut load = context.load();
+                    if !load.cancel.as_ref().is_some_and(|current| current.same_as(&cancel)) { return; }
+                    load.cancel = None;
                 }
-                // Loguj warstwy i kanały (tytuły)
-                push_console(&ui, &console, format!("[layers] count: {}", cache.layers_info.len()));
-                for layer in &cache.layers_info {
-                    let channel_count = layer.channels.len();
-                    push_console(&ui, &console, format!("  • {} (channels: {})", layer.name, channel_count));
+                match result {
+                    Some(result) => {
+                        let outcome = result.as_ref().map(|_| ()).map_err(ToString::to_string);
+                        apply_loaded_cache(&ui, &context, &image_cache, &prog, &path, result, ms);
+                        finish_load_waiters(&context, outcome);
+                    }
+                    // Wczytywanie anulowane z listy zadań
+                    None => {
+                        prog.reset();
+                        finish_load_waiters(&context, Err("loading canceled".to_string()));
+                        ui.set_status_text("Loading canceled".into());
+                        info!(target: "io", "load canceled: {}", path.display());
+                    }
                 }
+            });
+        });
+    }
+}
 
-                // Zapisz cache
-                {
-                    let mut cache_guard = lock_or_recover(&image_cache);
-                    *cache_guard = Some(cache);
+/// Zgrubny podgląd dużego pliku (wątek wczytujący), o ile wczytywanie wciąż trwa
+fn show_load_proxy(ui_weak: &Weak<AppWindow>, context: &Arc<AppContext>, cancel: &CancelToken, proxy: ImageCache, ms: u128) {
+    let (context, cancel) = (context.clone(), cancel.clone());
+    let _ = ui_weak.upgrade_in_event_loop(move |ui| {
+        if cancel.is_cancelled() { return; }
+        let image = proxy.process_to_image(&context.render_settings(ui.get_exposure_value(), ui.get_gamma_value()));
+        ui.set_exr_image(context.for_display(image));
+        info!(target: "processing", "proxy preview {}x{} in {} ms", proxy.width, proxy.height, ms);
+    });
+}
+
+/// Trwa wczytywanie pliku
+pub fn is_loading(context: &AppContext) -> bool {
+    context.load().cancel.is_some()
+}
+
+/// Wynik bieżącego wczytywania trafi do `done` (Ok albo opis błędu); bez wczytywania – od razu błąd.
+/// Wołać z wątku UI tuż po rozpoczęciu otwierania pliku (zdalne sterowanie czeka na wynik).
+pub fn notify_when_loaded(context: &AppContext, done: Sender<Result<(), String>>) {
+    let mut load = context.load();
+    if load.cancel.is_some() {
+        load.waiters.push(done);
+    } else {
+        let _ = done.send(Err("the file was not opened".to_string()));
+    }
+}
+
+fn finish_load_waiters(context: &AppContext, outcome: Result<(), String>) {
+    let waiters = std::mem::take(&mut context.load().waiters);
+    for waiter in waiters {
+        let _ = waiter.send(outcome.clone());
+    }
+}
+
+/// Pliki od tego rozmiaru wczytujemy progresywnie (najpierw proxy)
+const PROGRESSIVE_MIN_FILE_BYTES: u64 = 64 * 1024 * 1024;
+/// Docelowy dłuższy bok podglądu proxy
+const PROXY_MAX_SIZE: u32 = 512;
+
+/// Kończy wczytywanie na wątku UI: przetwarza obraz, publikuje warstwy i zapisuje cache
+fn apply_loaded_cache(
+    ui: &AppWindow,
+    context: &AppContext,
+    image_cache: &ImageCacheType,
+    prog: &TaskProgress,
+    path: &Path,
+    result: ExrResult<ImageCache>,
+    load_ms: u128,
+) {
+    match result {
+        Ok(cache) => {
+            prog.set(0.45, Some("Cache created, processing..."));
+            debug!(target: "io", "image cache created");
+            apply_layer_color_space(ui, context, &cache);
+            // Zaznaczenie dotyczyło poprzedniego obrazu
+            set_selection(ui, context, None);
+            update_view_panels(ui, context, &cache);
+            ui.set_env_map_kind(cache.env_map.map_or("", EnvMapKind::label).into());
+            info!(target: "processing", op = "ImageCache.new", ms = load_ms as u64, "timing");
+
+            // Pobierz aktualne wartości ekspozycji i gammy
+            let exposure = ui.get_exposure_value();
+            let gamma = ui.get_gamma_value();
+
+            // Przetwórz obraz z cache'a
+            let pixel_count = cache.raw_pixels.len();
+            let t_proc = Instant::now();
+            // sygnalizuj dłuższe przetwarzanie (duże obrazy) jako indeterminate
+            if pixel_count > 2_000_000 { prog.start_indeterminate(Some("Processing image...")); }
+            let settings = context.render_settings(exposure, gamma);
+            let (image, diff_status) = match render_compare(context, &cache, exposure, gamma) {
+                Some((image, status)) => (image, Some(status)),
+                None => (cache.process_to_image(&settings), None),
+            };
+            info!(target: "processing", op = "process_to_image", pixels = pixel_count, ms = t_proc.elapsed().as_millis() as u64, "timing");
+            debug!(target: "processing", "image generated: {} pixels (exp: {:.2}, gamma: {:.2})", pixel_count, exposure, gamma);
+
+            // Przekaż informacje o warstwach do UI (prosty model, bez stanu drzewa)
+            {
+                ui.set_layer_nodes(create_layers_model(&cache.layers_info, ui));
+                ui.set_selected_layer_node(-1);
+            }
+            // Loguj warstwy i kanały (tytuły)
+            info!(target: "io", "layers: {}", cache.layers_info.len());
+            for layer in &cache.layers_info {
+                let channel_count = layer.channels.len();
+                debug!(target: "io", "  • {} (channels: {})", layer.name, channel_count);
+            }
+
+            sync_remap_controls(ui, cache.channel_remap);
+            let deep_preview = cache.deep_preview;
+            let damage = cache.damage;
+            let monochrome = cache.monochrome;
+            let (source_width, source_height) = settings.transform.output_size(cache.width, cache.height);
+
+            // Zapisz cache
+            {
+                let mut cache_guard = lock_or_recover(image_cache);
+                *cache_guard = Some(cache);
+            }
+
+            ui.set_exr_image(context.for_display(image));
+            ui.set_image_source_width(source_width as i32);
+            ui.set_image_source_height(source_height as i32);
+            let original = proxy_files::original_path(path).unwrap_or_else(|| path.to_path_buf());
+            show_annotations(ui, context, &original, source_width as f32 / source_height.max(1) as f32);
+            show_snapshots(ui, context, &original);
+            ui.set_deep_preview(deep_preview);
+            ui.set_partial_file(damage.map(|d| d.label()).unwrap_or_default().into());
+            let status = diff_status.unwrap_or_else(|| if let Some(damage) = damage {
+                format!("Incomplete file: {} - missing regions shown as a checkerboard", damage.label())
+            } else if deep_preview {
+                format!("Loaded deep EXR: flattened preview (front-to-back composite), {} pixels", pixel_count)
+            } else if monochrome {
+                format!("Loaded monochrome image: {} pixels (exp: {:.2}, gamma: {:.2})", pixel_count, exposure, gamma)
+            } else {
+                format!("Loaded: {} pixels (exp: {:.2}, gamma: {:.2})", pixel_count, exposure, gamma)
+            });
+            ui.set_status_text(status.into());
+            prog.finish(Some("Ready"));
+        }
+        Err(e) => {
+            let path_buf = path.to_path_buf();
+            ui.set_status_text(format!("Read error '{}': {}", get_file_name(&path_buf), e.user_message()).into());
+            error!(target: "io", "reading file '{}': {}", get_file_name(&path_buf), e);
+            prog.reset();
+        }
+    }
+}
+
+/// Generuje w tle miniaturki dla wszystkich plików EXR w folderze i przekazuje je do dolnego panelu;
+/// po wczytaniu przywraca pozycję przewinięcia `scroll_x`. Nowy folder przerywa poprzednie zadanie.
+pub fn load_thumbnails_for_directory(ui: &AppWindow, context: &Arc<AppContext>, dir: &Path, scroll_x: f32) {
+    ui.set_status_text(format!("Loading thumbnails: {}", dir.display()).into());
+    let exposure = ui.get_exposure_value();
+    let gamma = ui.get_gamma_value();
+    let t0 = Instant::now();
+
+    let cancel = CancelToken::new();
+    let previous = context.thumbnails_cancel().replace(cancel.clone());
+    if let Some(prev) = previous {
+        prev.cancel();
+    }
+    let folder_name = dir.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_else(|| dir.display().to_string());
+    let task = progress::register(ui.as_weak(), format!("Thumbnails {}", folder_name), Some(cancel.clone()));
+    let (ui_weak, context, dir) = (ui.as_weak(), context.clone(), dir.to_path_buf());
+    std::thread::spawn(move || {
+        let result = crate::thumbnails::generate_thumbnail_works(&dir, 150, exposure, gamma, &cancel, &task);
+        task.reset();
+        let _ = ui_weak.upgrade_in_event_loop(move |ui| {
+            // Wynik przerwanego zadania (inny folder, ✕) nie trafia do panelu
+            if cancel.is_cancelled() {
+                info!(target: "io", "thumbnails canceled: {}", dir.display());
+                return;
+            }
+            *context.thumbnails_cancel() = None;
+            match result {
+                Ok(works) => {
+                    let count = show_thumbnails(&ui, works);
+                    ui.set_thumbs_viewport_x(scroll_x);
+                    info!(target: "io", "folder: {} EXR files | thumbnails in {} ms", count, t0.elapsed().as_millis());
                 }
+                Err(e) => {
+                    ui.set_status_text(format!("Error loading thumbnails: {}", e).into());
+                    error!(target: "io", "thumbnails: {}", e);
+                }
+            }
+        });
+    });
+}
 
-                ui.set_exr_image(image);
-                ui.set_status_text(format!("Loaded: {} pixels (exp: {:.2}, gamma: {:.2})", pixel_count, exposure, gamma).into());
-                prog.finish(Some("Ready"));
+/// Wypełnia pasek miniaturek (wątek UI); zwraca liczbę plików
+fn show_thumbnails(ui: &AppWindow, works: Vec<crate::thumbnails::ExrThumbWork>) -> usize {
+    let mut thumbs: Vec<_> = works.into_iter().map(crate::thumbnails::ExrThumbWork::into_info).collect();
+    thumbs.sort_by(|a, b| a.file_name.to_lowercase().cmp(&b.file_name.to_lowercase()));
+    let items: Vec<ThumbItem> = thumbs.into_iter().map(|t| {
+        let (layers, resolution, badge) = match &t.metadata {
+            Some(meta) => {
+                let mut layers = format!("{} layers · {} ch", meta.layer_count, meta.channel_count);
+                if meta.part_count > 1 {
+                    layers.push_str(&format!(" · {} parts", meta.part_count));
+                }
+                (layers, format!("{}×{}", meta.width, meta.height), meta.badge())
+            }
+            None => (format!("{} layers", t.num_layers), String::new(), String::new()),
+        };
+        let badge = match t.damage {
+            Some(damage) => format!("{} · {}", damage.label(), badge),
+            None => badge,
+        };
+        ThumbItem {
+            img: t.image,
+            name: t.file_name.into(),
+            size: human_size(t.file_size_bytes).into(),
+            layers: layers.into(),
+            path: t.path.display().to_string().into(),
+            width: t.width as i32,
+            height: t.height as i32,
+            resolution: resolution.into(),
+            badge: badge.into(),
+            selected: false,
+        }
+    }).collect();
+    let count = items.len();
+    ui.set_thumbnails(ModelRc::new(VecModel::from(items)));
+    ui.set_status_text("Thumbnails loaded".into());
+    ui.set_bottom_panel_visible(true);
+    count
+}
+
+/// Obsługuje wybór folderu w panelu nawigacji: odświeża listę podfolderów, wczytuje miniaturki
+/// i przywraca zapamiętaną pozycję przewinięcia paska miniaturek
+pub fn handle_folder_selected(
+    ui_handle: Weak<AppWindow>,
+    browser: FolderBrowserType,
+    context: Arc<AppContext>,
+    dir: PathBuf,
+) {
+    if let Some(ui) = ui_handle.upgrade() {
+        let mut browser = lock_or_recover(&browser);
+        browser.remember_scroll(ui.get_thumbs_viewport_x());
+
+        match list_folder_entries(&dir) {
+            Ok(entries) => {
+                let items: Vec<FolderItem> = entries.into_iter().map(|e| FolderItem {
+                    name: e.name.into(),
+                    path: e.path.display().to_string().into(),
+                }).collect();
+                ui.set_folder_items(ModelRc::new(VecModel::from(items)));
             }
             Err(e) => {
-                ui.set_status_text(format!("Read error '{}': {}", get_file_name(&path), e).into());
-                push_console(&ui, &console, format!("[error] reading file '{}': {}", get_file_name(&path), e));
-                prog.reset();
+                ui.set_status_text(format!("Error reading folder: {}", e).into());
+                error!(target: "io", "folder: {}", e);
+                return;
+            }
+        }
+
+        browser.current_dir = Some(dir.clone());
+        session::update(true, |s| s.working_dir = Some(dir.clone()));
+        ui.set_current_folder(dir.display().to_string().into());
+        info!(target: "ui", "browsing folder {}", dir.display());
+
+        load_thumbnails_for_directory(&ui, &context, &dir, browser.scroll_for(&dir));
+    }
+}
+
+/// Panel "Pipeline": etapy grafu użytego do bieżącego renderu, w kolejności stosowania
+fn update_pipeline_panel(ui: &AppWindow, context: &AppContext, exposure: f32, gamma: f32) {
+    let graph = context.processing_graph(exposure, gamma);
+    let stages: Vec<PipelineStage> = Stage::ALL.iter()
+        .map(|&stage| PipelineStage {
+            name: stage.label().into(),
+            detail: graph.describe(stage).into(),
+            enabled: graph.is_enabled(stage),
+            active: graph.is_active(stage),
+        })
+        .collect();
+    ui.set_pipeline_stages(ModelRc::new(VecModel::from(stages)));
+}
+
+/// Legenda false color (od najjaśniejszego pasma) dla bieżącej ekspozycji; pusta gdy tryb wyłączony
+fn update_false_color_legend(ui: &AppWindow, context: &AppContext, exposure: f32, gamma: f32) {
+    let settings = context.render_settings(exposure, gamma);
+    let entries: Vec<Swatch> = if settings.false_color {
+        let unit = if ui.get_false_color_nits() { LegendUnit::Nits } else { LegendUnit::Ev };
+        image_processing::false_color_legend(&settings.graph.tone_params(), unit)
+            .into_iter()
+            .map(|(px, label)| Swatch { color: Color::from_rgb_u8(px.r, px.g, px.b), text: label.into() })
+            .collect()
+    } else {
+        Vec::new()
+    };
+    ui.set_false_color_legend(ModelRc::new(VecModel::from(entries)));
+}
+
+/// Zaznaczenie (marquee) próbnika koloru we współrzędnych widoku 0..1; histogram i "Auto" liczone
+/// są wtedy tylko z niego. Pojedynczy klik albo "Clear" usuwa zaznaczenie.
+pub fn set_selection(ui: &AppWindow, context: &AppContext, region: Option<[f32; 4]>) {
+    context.update_view(|view| view.selection = region);
+    let [u0, v0, u1, v1] = region.unwrap_or_default();
+    ui.set_has_selection(region.is_some());
+    ui.set_selection_x(u0.min(u1).clamp(0.0, 1.0));
+    ui.set_selection_y(v0.min(v1).clamp(0.0, 1.0));
+    ui.set_selection_width((u1 - u0).abs().min(1.0));
+    ui.set_selection_height((v1 - v0).abs().min(1.0));
+}
+
+/// Wczytuje notatki pliku z pliku obok obrazu i pokazuje je w nakładce
+pub fn show_annotations(ui: &AppWindow, context: &AppContext, file: &Path, aspect: f32) {
+    let items = annotations::load(file);
+    if !items.is_empty() {
+        info!(target: "io", "{} annotations for {}", items.len(), file.display());
+    }
+    let mut state = context.annotations();
+    *state = AnnotationState { file: Some(file.to_path_buf()), aspect, items, draft: None };
+    publish_annotations(ui, &state);
+}
+
+/// Zmiana notatek bieżącego pliku: `edit` dostaje zapisane notatki i szkic, zwraca true, gdy
+/// zapisane notatki się zmieniły (wtedy trafiają do pliku obok obrazu)
+pub fn edit_annotations(ui: &AppWindow, context: &AppContext, edit: impl FnOnce(&mut Vec<Annotation>, &mut Option<Annotation>) -> bool) {
+    let mut state = context.annotations();
+    let state = &mut *state;
+    let Some(file) = state.file.clone() else { return; };
+    if edit(&mut state.items, &mut state.draft) {
+        if let Err(e) = annotations::save(&file, &state.items) {
+            ui.set_status_text(format!("Cannot save annotations: {}", e).into());
+            error!(target: "io", "saving annotations for {}: {}", file.display(), e);
+        }
+    }
+    publish_annotations(ui, state);
+}
+
+/// Plik, do którego należą wyświetlane notatki
+pub fn annotations_file(context: &AppContext) -> Option<PathBuf> {
+    context.annotations().file.clone()
+}
+
+/// Wczytuje galerię migawek pliku i pokazuje ją w panelu
+pub fn show_snapshots(ui: &AppWindow, context: &AppContext, file: &Path) {
+    let items = snapshot_gallery::load(file);
+    let mut state = context.snapshots();
+    *state = (Some(file.to_path_buf()), items);
+    publish_snapshots(ui, &state);
+}
+
+/// Zmiana galerii bieżącego pliku: `edit` dostaje plik i migawki, zwraca true, gdy indeks trzeba zapisać
+pub fn edit_snapshots(ui: &AppWindow, context: &AppContext, edit: impl FnOnce(&Path, &mut Vec<Snapshot>) -> bool) {
+    let mut state = context.snapshots();
+    let (Some(file), items) = &mut *state else { return; };
+    if edit(file, items) {
+        if let Err(e) = snapshot_gallery::save(file, items) {
+            ui.set_status_text(format!("Cannot save snapshots: {}", e).into());
+            error!(target: "io", "saving snapshot gallery for {}: {}", file.display(), e);
+        }
+    }
+    publish_snapshots(ui, &state);
+}
+
+/// Migawka galerii bieżącego pliku
+pub fn snapshot(context: &AppContext, index: usize) -> Option<Snapshot> {
+    context.snapshots().1.get(index).cloned()
+}
+
+fn publish_snapshots(ui: &AppWindow, (file, items): &(Option<PathBuf>, Vec<Snapshot>)) {
+    let items: Vec<SnapshotItem> = items.iter()
+        .map(|s| {
+            let thumbnail = file.as_deref().and_then(|f| snapshot_gallery::load_thumbnail(f, s)).map(|t| t.to_slint_image()).unwrap_or_default();
+            let layer = s.selection.as_ref().map(|(layer, channel)| format!("  ·  {}", if channel.is_empty() { layer } else { channel })).unwrap_or_default();
+            let detail = format!("{:+.2} EV  ·  γ {:.2}{}", s.exposure, s.gamma, layer);
+            SnapshotItem { name: s.name.clone().into(), thumbnail, detail: detail.into() }
+        })
+        .collect();
+    ui.set_snapshot_items(ModelRc::new(VecModel::from(items)));
+}
+
+fn publish_annotations(ui: &AppWindow, state: &AnnotationState) {
+    let items: Vec<AnnotationItem> = state.items.iter().chain(&state.draft)
+        .map(|a| {
+            let [r, g, b] = a.color;
+            let (text, [x, y]) = match &a.shape {
+                Shape::Text { at, text } => (text.as_str(), *at),
+                _ => ("", [0.0; 2]),
+            };
+            AnnotationItem { commands: a.path_commands(state.aspect).into(), color: Color::from_rgb_u8(r, g, b), text: text.into(), x, y }
+        })
+        .collect();
+    ui.set_annotation_count(state.items.len() as i32);
+    ui.set_annotation_items(ModelRc::new(VecModel::from(items)));
+}
+
+/// Powiększenie widoku (1.0 = dopasowanie do okna); zwraca true, gdy zmienia się rozdzielczość
+/// podglądu (duże pliki bez powiększenia oglądane są z pomniejszenia do 2048 px)
+pub fn set_preview_zoom(context: &AppContext, zoom: f32) -> bool {
+    let zoomed = zoom > 1.0;
+    let changed = context.view().preview_zoomed != zoomed;
+    context.update_view(|view| view.preview_zoomed = zoomed);
+    changed
+}
+
+/// Panel histogramów sceny: cztery stałe kanały (R, G, B, L) na wspólnej osi EV z krzywymi
+/// i znacznikami percentyli; w trybie zaznaczenia tytuł podaje jego rozmiar
+fn update_histogram_panel(ui: &AppWindow, context: &AppContext, cache: &ImageCache, exposure: f32, gamma: f32) {
+    let view = context.view();
+    let region = view.selection;
+    let settings = view.render_settings(exposure, gamma);
+    let Some((hists, [_, _, width, height])) = cache.channel_histograms(region, &settings.transform, &settings.graph.tone_params()) else { return; };
+    let title = match region {
+        Some(_) => format!("Histogram (region {}×{} px)", width, height),
+        None => "Histogram".to_string(),
+    };
+    ui.set_histogram_title(title.into());
+    let range = histogram::common_range(&hists);
+    // Obraz monochromatyczny (R=G=B): tylko krzywa luminancji
+    let channels: Vec<HistogramChannel> = hists.iter().zip(histogram::CHANNELS).enumerate()
+        .filter(|(index, _)| !cache.monochrome || *index == histogram::LUMINANCE)
+        .map(|(_, (hist, name))| {
+            let name = if cache.monochrome { "Y" } else { name };
+            let percentiles = histogram::MARKER_PERCENTILES.iter()
+                .map(|&p| format!("P{} {:.4}", p, hist.percentile(p).unwrap_or(0.0)))
+                .collect::<Vec<_>>()
+                .join("  ");
+            HistogramChannel {
+                name: name.into(),
+                path: range.map(|r| hist.path_commands(r)).unwrap_or_default().into(),
+                markers: range.map(|r| hist.marker_commands(r, &histogram::MARKER_PERCENTILES)).unwrap_or_default().into(),
+                info: format!("{}  {}", name, percentiles).into(),
             }
+        })
+        .collect();
+    ui.set_histogram_channels(ModelRc::new(VecModel::from(channels)));
+    match range {
+        Some((start, end)) => {
+            ui.set_histogram_bins((end - start + 1) as i32);
+            ui.set_histogram_info(format!("{:+.1} … {:+.1} EV", histogram::bin_ev(start), histogram::bin_ev(end + 1)).into());
         }
+        None => ui.set_histogram_info("no positive values".into()),
     }
 }
 
+/// Panele zależne od obrazu i parametrów (pipeline, histogram) po zmianie pliku lub warstwy
+fn update_view_panels(ui: &AppWindow, context: &AppContext, cache: &ImageCache) {
+    let (exposure, gamma) = (ui.get_exposure_value(), ui.get_gamma_value());
+    update_pipeline_panel(ui, context, exposure, gamma);
+    update_false_color_legend(ui, context, exposure, gamma);
+    update_histogram_panel(ui, context, cache, exposure, gamma);
+    update_stereo_panel(ui, cache);
+}
+
+/// Lista widoków pliku stereo i widok bieżącej warstwy (panel warstw)
+fn update_stereo_panel(ui: &AppWindow, cache: &ImageCache) {
+    ui.set_stereo_views(cache.views.join(", ").into());
+    let current = cache.views.get(stereo::view_of(&cache.current_layer_name, &cache.views));
+    ui.set_stereo_current_view(current.cloned().unwrap_or_default().into());
+}
+
 // Ulepszona funkcja obsługi ekspozycji I gamma z throttling
 pub fn handle_parameter_changed_throttled(
     ui_handle: Weak<AppWindow>,
     image_cache: ImageCacheType,
-    console: ConsoleModel,
+    context: &AppContext,
     exposure: Option<f32>,
     gamma: Option<f32>,
 ) {
@@ -410,23 +899,40 @@ pub fn handle_parameter_changed_throttled(
             // Pobierz aktualne wartości jeśli nie zostały przekazane
             let final_exposure = exposure.unwrap_or_else(|| ui.get_exposure_value());
             let final_gamma = gamma.unwrap_or_else(|| ui.get_gamma_value());
-            
-            // Użyj thumbnail dla real-time preview jeśli obraz jest duży
-            let image = if cache.raw_pixels.len() > 2_000_000 {
-                cache.process_to_thumbnail(final_exposure, final_gamma, 2048)
+            session::update(false, |s| { s.exposure = final_exposure; s.gamma = final_gamma; });
+            update_pipeline_panel(&ui, context, final_exposure, final_gamma);
+            update_false_color_legend(&ui, context, final_exposure, final_gamma);
+            update_histogram_panel(&ui, context, cache, final_exposure, final_gamma);
+
+            // Tryb porównania: obraz różnicy i metryki w statusie zamiast informacji o parametrach
+            if let Some((image, status)) = render_compare(context, cache, final_exposure, final_gamma) {
+                ui.set_exr_image(context.for_display(image));
+                ui.set_status_text(status.into());
+                return;
+            }
+
+            // Rozmiar w pikselach źródła po obrocie – skala siatki pikseli przy powiększeniu
+            let settings = context.render_settings(final_exposure, final_gamma);
+            let (source_width, source_height) = settings.transform.output_size(cache.width, cache.height);
+            ui.set_image_source_width(source_width as i32);
+            ui.set_image_source_height(source_height as i32);
+
+            // Użyj thumbnail dla real-time preview jeśli obraz jest duży (chyba że widok jest powiększony)
+            let image = if cache.raw_pixels.len() > 2_000_000 && !context.view().preview_zoomed {
+                cache.process_to_thumbnail(&settings, 2048)
             } else {
-                cache.process_to_image(final_exposure, final_gamma)
+                cache.process_to_image(&settings)
             };
             
-            ui.set_exr_image(image);
+            ui.set_exr_image(context.for_display(image));
             // Throttled log do konsoli: co najmniej 300 ms odstępu
-            let mut last = lock_or_recover(&LAST_PREVIEW_LOG);
             let now = Instant::now();
-            if last.map(|t| now.duration_since(t).as_millis() >= 300).unwrap_or(true) {
-                push_console(&ui, &console,
-                    format!("[preview] updated → params: exp={:.2}, gamma={:.2}", final_exposure, final_gamma));
-                *last = Some(now);
+            let mut last_log = context.last_preview_log();
+            if last_log.is_none_or(|t| now.duration_since(t).as_millis() >= 300) {
+                debug!(target: "processing", "preview updated → params: exp={:.2}, gamma={:.2}", final_exposure, final_gamma);
+                *last_log = Some(now);
             }
+            drop(last_log);
             
             // Aktualizuj status bar z informacją o zmienionym parametrze
             if exposure.is_some() && gamma.is_some() {
@@ -440,95 +946,193 @@ pub fn handle_parameter_changed_throttled(
     }
 }
 
-// usunięto nieużywaną funkcję create_layers_model
-
+/// Buduje model drzewa: [sekcja →] warstwa → rzeczywiste kanały (RGBA najpierw, tylko jeśli istnieją w pliku).
+/// Sekcje (Lighting, Data, Cryptomatte, Tech) tylko przy włączonym grupowaniu.
+/// Identyfikatory są indeksami w modelu, więc duplikaty nazw nie kolidują.
 pub fn create_layers_model(
     layers_info: &[crate::image_cache::LayerInfo],
     ui: &AppWindow,
-) -> (ModelRc<slint::SharedString>, ModelRc<slint::Color>, ModelRc<i32>) {
-    // UPROSZCZONE DRZEWO: Warstwa → faktyczne kanały (bez grup). RGBA tylko jeśli istnieją w pliku.
-    let mut items: Vec<SharedString> = Vec::new();
-    let mut colors: Vec<Color> = Vec::new();
-    let mut font_sizes: Vec<i32> = Vec::new();
-    // Wyczyść mapę
-    lock_or_recover(&ITEM_TO_LAYER).clear();
-    lock_or_recover(&DISPLAY_TO_REAL_LAYER).clear();
-    for layer in layers_info {
-        // Przyjazna nazwa dla pustej warstwy RGBA
-        let display_name = if layer.name.is_empty() { "Beauty".to_string() } else { layer.name.clone() };
-        // Zapisz mapowanie wyświetlanej nazwy na rzeczywistą
-        {
-            let mut map = lock_or_recover(&DISPLAY_TO_REAL_LAYER);
-            map.insert(display_name.clone(), layer.name.clone());
-        }
-        // Wiersz nagłówka warstwy
-        items.push(format!("📁 {}", display_name).into());
-        colors.push(ui.get_layers_color_default());
-        font_sizes.push(12);
-
-        // Zbierz listę rzeczywistych kanałów (krótkie nazwy)
-        let mut short_channels: Vec<String> = layer
-            .channels
-            .iter()
-            .map(|c| c.name.split('.').last().unwrap_or(&c.name).to_string())
-            .collect();
-
-        // Zachowaj kolejność: R, G, B, A (jeśli są), potem reszta alfabetycznie
-        // Uwzględnij synonimy: Red/Green/Blue/Alpha (case-insensitive)
-        let mut ordered: Vec<String> = Vec::new();
-        let wanted_groups: [&[&str]; 4] = [
-            &["R", "RED"],
-            &["G", "GREEN"],
-            &["B", "BLUE"],
-            &["A", "ALPHA"],
-        ];
-        for aliases in wanted_groups {
-            if let Some(pos) = short_channels.iter().position(|s| {
-                let su = s.to_ascii_uppercase();
-                aliases.iter().any(|a| su == *a || su.starts_with(*a))
-            }) {
-                ordered.push(short_channels.remove(pos));
+) -> ModelRc<LayerNode> {
+    let classified: Vec<(&crate::image_cache::LayerInfo, AovKind)> = layers_info
+        .iter()
+        .map(|l| (l, channel_classification::classify(&l.name, "")))
+        .collect();
+
+    let mut nodes: Vec<LayerNode> = Vec::new();
+    if ui.get_group_layers() {
+        for section in channel_classification::SECTIONS {
+            let members: Vec<_> = classified.iter().filter(|(_, kind)| kind.section() == section).collect();
+            if members.is_empty() { continue; }
+            let group_id = nodes.len() as i32;
+            nodes.push(LayerNode {
+                id: group_id,
+                parent: -1,
+                depth: 0,
+                kind: NODE_KIND_GROUP.into(),
+                section: section.into(),
+                label: format!("{} ({})", section, members.len()).into(),
+                icon: SharedString::new(),
+                layer: SharedString::new(),
+                channel: SharedString::new(),
+                color: ui.get_layers_color_default(),
+                expanded: true,
+                visible: true,
+            });
+            for (layer, kind) in members {
+                push_layer_nodes(&mut nodes, layer, *kind, group_id, ui);
             }
         }
-        short_channels.sort_by(|a, b| a.to_lowercase().cmp(&b.to_lowercase()));
-        ordered.extend(short_channels);
-
-        for ch in ordered {
-            // Emoji dla RGBA, kropka dla pozostałych, oraz sufiks @<warstwa> dla jednoznaczności
-            let (emoji, display_ch) = match ch.as_str() {
-                "R" | "r" => ("🔴", "Red".to_string()),
-                "G" | "g" => ("🟢", "Green".to_string()),
-                "B" | "b" => ("🔵", "Blue".to_string()),
-                "A" | "a" => ("⚪", "Alpha".to_string()),
-                _ => ("•", ch.clone()),
-            };
-            let base = format!("    {} {}", emoji, display_ch);
-            let line = format!("{} @{}", base, display_name);
-            ITEM_TO_LAYER
-                .lock()
-                .unwrap_or_else(|e| e.into_inner())
-                .insert(line.clone(), layer.name.clone());
-            items.push(line.clone().into());
-            // Kolor tekstu dla WSZYSTKICH kanałów: rozpoznaj Red/Green/Blue po nazwie segmentu (case-insensitive)
-            let su = display_ch.to_ascii_uppercase();
-            let c = if su == "R" || su == "RED" || su.starts_with('R') || su.starts_with("RED") {
-                ui.get_layers_color_r()
-            } else if su == "G" || su == "GREEN" || su.starts_with('G') || su.starts_with("GREEN") {
-                ui.get_layers_color_g()
-            } else if su == "B" || su == "BLUE" || su.starts_with('B') || su.starts_with("BLUE") {
-                ui.get_layers_color_b()
-            } else {
-                ui.get_layers_color_default()
-            };
-            colors.push(c);
-            font_sizes.push(10);
+    } else {
+        for (layer, kind) in &classified {
+            push_layer_nodes(&mut nodes, layer, *kind, -1, ui);
         }
     }
 
-    (
-        ModelRc::new(VecModel::from(items)),
-        ModelRc::new(VecModel::from(colors)),
-        ModelRc::new(VecModel::from(font_sizes)),
-    )
+    let model = ModelRc::new(VecModel::from(nodes));
+    refresh_layer_visibility(&model, ui.get_lighting_only());
+    model
 }
 
+/// Dodaje węzeł warstwy i węzły jej kanałów pod węzłem `parent` (-1 = korzeń)
+fn push_layer_nodes(nodes: &mut Vec<LayerNode>, layer: &crate::image_cache::LayerInfo, kind: AovKind, parent: i32, ui: &AppWindow) {
+    let depth = if parent < 0 { 0 } else { 1 };
+    // Przyjazna nazwa dla pustej warstwy RGBA
+    let display_name = if layer.name.is_empty() { "Beauty".to_string() } else { layer.name.clone() };
+    // Węzeł warstwy: ikona i kolor wg reguł klasyfikacji AOV
+    let (icon, color) = channel_classification::tree_style(kind);
+    let layer_id = nodes.len() as i32;
+    nodes.push(LayerNode {
+        id: layer_id,
+        parent,
+        depth,
+        kind: NODE_KIND_LAYER.into(),
+        section: kind.section().into(),
+        label: display_name.into(),
+        icon: icon.unwrap_or("📁").into(),
+        layer: layer.name.clone().into(),
+        channel: SharedString::new(),
+        color: layer_node_color(color, ui),
+        expanded: true,
+        visible: true,
+    });
+
+    // Zbierz listę rzeczywistych kanałów (krótkie nazwy)
+    let mut short_channels: Vec<String> = layer
+        .channels
+        .iter()
+        .map(|c| c.name.split('.').next_back().unwrap_or(&c.name).to_string())
+        .collect();
+
+    // Zachowaj kolejność: R, G, B, A (jeśli są), potem reszta alfabetycznie
+    // Uwzględnij synonimy: Red/Green/Blue/Alpha (case-insensitive)
+    let mut ordered: Vec<String> = Vec::new();
+    let wanted_groups: [&[&str]; 4] = [
+        &["R", "RED"],
+        &["G", "GREEN"],
+        &["B", "BLUE"],
+        &["A", "ALPHA"],
+    ];
+    for aliases in wanted_groups {
+        if let Some(pos) = short_channels.iter().position(|s| {
+            let su = s.to_ascii_uppercase();
+            aliases.iter().any(|a| su == *a || su.starts_with(*a))
+        }) {
+            ordered.push(short_channels.remove(pos));
+        }
+    }
+    short_channels.sort_by(|a, b| a.to_lowercase().cmp(&b.to_lowercase()));
+    ordered.extend(short_channels);
+
+    for ch in ordered {
+        // Emoji dla RGBA, kropka dla pozostałych
+        let (icon, label) = match ch.as_str() {
+            "R" | "r" => ("🔴", "Red".to_string()),
+            "G" | "g" => ("🟢", "Green".to_string()),
+            "B" | "b" => ("🔵", "Blue".to_string()),
+            "A" | "a" => ("⚪", "Alpha".to_string()),
+            _ => ("•", ch.clone()),
+        };
+        let color = channel_node_color(&label, ui);
+        nodes.push(LayerNode {
+            id: nodes.len() as i32,
+            parent: layer_id,
+            depth: depth + 1,
+            kind: NODE_KIND_CHANNEL.into(),
+            section: kind.section().into(),
+            label: label.into(),
+            icon: icon.into(),
+            layer: layer.name.clone().into(),
+            channel: ch.into(),
+            color,
+            expanded: false,
+            visible: true,
+        });
+    }
+}
+
+/// Kolor węzła warstwy: kolor reguły AOV dopasowany do motywu albo domyślny
+fn layer_node_color(rule_color: Option<(u8, u8, u8)>, ui: &AppWindow) -> Color {
+    rule_color.map(theme::tree_color).unwrap_or_else(|| ui.get_layers_color_default())
+}
+
+/// Kolor tekstu dla WSZYSTKICH kanałów: rozpoznaj Red/Green/Blue po nazwie segmentu (case-insensitive)
+fn channel_node_color(label: &str, ui: &AppWindow) -> Color {
+    let su = label.to_ascii_uppercase();
+    if su.starts_with('R') {
+        ui.get_layers_color_r()
+    } else if su.starts_with('G') {
+        ui.get_layers_color_g()
+    } else if su.starts_with('B') {
+        ui.get_layers_color_b()
+    } else {
+        ui.get_layers_color_default()
+    }
+}
+
+/// Przelicza kolory drzewa po zmianie motywu (stan rozwinięcia i zaznaczenie zostają)
+pub fn recolor_layer_nodes(ui: &AppWindow) {
+    let nodes = ui.get_layer_nodes();
+    for row in 0..nodes.row_count() {
+        let Some(mut node) = nodes.row_data(row) else { continue; };
+        node.color = match node.kind.as_str() {
+            NODE_KIND_LAYER => {
+                let kind = channel_classification::classify(&node.layer, "");
+                layer_node_color(channel_classification::tree_style(kind).1, ui)
+            }
+            NODE_KIND_CHANNEL => channel_node_color(&node.label, ui),
+            _ => ui.get_layers_color_default(),
+        };
+        nodes.set_row_data(row, node);
+    }
+}
+
+/// Przelicza widoczność węzłów: wszyscy przodkowie rozwinięci i (opcjonalnie) tylko sekcja Lighting.
+/// Rodzic zawsze poprzedza dzieci w modelu, więc wystarczy jedno przejście.
+fn refresh_layer_visibility(nodes: &ModelRc<LayerNode>, lighting_only: bool) {
+    let mut open: Vec<bool> = Vec::with_capacity(nodes.row_count());
+    for row in 0..nodes.row_count() {
+        let Some(mut node) = nodes.row_data(row) else { open.push(false); continue; };
+        let parent_open = node.parent < 0 || open.get(node.parent as usize).copied().unwrap_or(false);
+        let visible = parent_open && (!lighting_only || node.section == channel_classification::SECTION_LIGHTING);
+        open.push(visible && node.expanded);
+        if node.visible != visible {
+            node.visible = visible;
+            nodes.set_row_data(row, node);
+        }
+    }
+}
+
+/// Przebudowuje drzewo warstw bieżącego pliku (np. po przełączeniu grupowania)
+pub fn handle_layer_grouping_changed(ui_handle: Weak<AppWindow>, image_cache: ImageCacheType) {
+    let Some(ui) = ui_handle.upgrade() else { return; };
+    let cache_guard = lock_or_recover(&image_cache);
+    let Some(ref cache) = *cache_guard else { return; };
+    ui.set_layer_nodes(create_layers_model(&cache.layers_info, &ui));
+    ui.set_selected_layer_node(-1);
+}
+
+/// Stosuje filtr „tylko AOV oświetlenia” bez przebudowy drzewa (stan rozwinięcia zostaje)
+pub fn handle_lighting_only_changed(ui_handle: Weak<AppWindow>, lighting_only: bool) {
+    let Some(ui) = ui_handle.upgrade() else { return; };
+    refresh_layer_visibility(&ui.get_layer_nodes(), lighting_only);
+}
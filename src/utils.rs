@@ -1,5 +1,8 @@
 // Wspólne funkcje pomocnicze używane w wielu modułach
 
+pub mod error_handling;
+pub mod channel_config;
+
 #[inline]
 pub(crate) fn split_layer_and_short(full: &str, base_attr: Option<&str>) -> (String, String) {
     if let Some(base) = base_attr {
@@ -27,3 +30,23 @@ pub(crate) fn human_size(bytes: u64) -> String {
         format!("{:.2} {}", size, UNITS[unit])
     }
 }
+
+/// Data i godzina UTC ("RRRR-MM-DD", "GG:MM") – bez zależności od biblioteki dat
+pub(crate) fn utc_date_time(time: std::time::SystemTime) -> (String, String) {
+    let secs = time.duration_since(std::time::UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0) as i64;
+    let (days, secs_of_day) = (secs.div_euclid(86_400), secs.rem_euclid(86_400));
+    // Konwersja dni od epoki na datę kalendarza gregoriańskiego (algorytm "civil from days")
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (
+        format!("{:04}-{:02}-{:02}", year, month, day),
+        format!("{:02}:{:02}", secs_of_day / 3600, secs_of_day % 3600 / 60),
+    )
+}
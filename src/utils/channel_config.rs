@@ -0,0 +1,231 @@
+// Konfiguracja klasyfikacji AOV: reguły edytowalne przez użytkownika w pliku channel_rules.ini
+// w katalogu danych aplikacji. Brakujący plik jest tworzony z regułami domyślnymi.
+
+use std::fs;
+use std::path::PathBuf;
+use tracing::warn;
+use crate::channel_classification::AovKind;
+use crate::image_processing::ChannelRemap;
+
+const CONFIG_FILE: &str = "channel_rules.ini";
+
+/// Sposób wyświetlania: kolor (ekspozycja + tone mapping), dane (bez tone mappingu)
+/// lub wektor 2D (kierunek → barwa, długość → jasność)
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DisplayMode {
+    Color,
+    Data,
+    Vector,
+}
+
+/// Normalizacja wartości w trybie danych
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Normalization {
+    None,
+    Percentile { invert: bool },
+    Remap(ChannelRemap),
+}
+
+/// Reguła: wzorce nazw (glob z `*`, bez rozróżniania wielkości liter) → rodzaj AOV i sposób wyświetlania
+#[derive(Clone, Debug)]
+pub struct ChannelRule {
+    pub kind: AovKind,
+    pub patterns: Vec<String>,
+    pub display: DisplayMode,
+    pub normalization: Normalization,
+    pub color: Option<(u8, u8, u8)>,
+    pub icon: Option<String>,
+    /// Składowe (0..3 = r, g, b, a) tworzące wektor 2D (x, y) w trybie `display = vector`
+    pub vector: Option<(usize, usize)>,
+}
+
+impl ChannelRule {
+    pub fn matches(&self, name: &str) -> bool {
+        let name = name.trim().to_ascii_lowercase();
+        self.patterns.iter().any(|p| glob_match(p, &name))
+    }
+}
+
+/// Reguły sprawdzane w kolejności z pliku – pierwsza pasująca wygrywa
+#[derive(Clone, Debug)]
+pub struct ChannelConfig {
+    pub rules: Vec<ChannelRule>,
+}
+
+const DEFAULT_CONFIG: &str = "\
+# EXRuster – AOV classification rules (first matching section wins)
+# patterns      = comma-separated names, '*' wildcard, case-insensitive
+#                 (matched against the last segment of the layer name, then the channel name)
+# display       = color | data | vector
+# normalization = none | percentile | percentile-inverted | remap <gain> <offset> [abs]
+# color         = #rrggbb (layer tree)
+# icon          = text/emoji shown before the layer name
+# vector        = <x> <y> components (r, g, b, a) paired into a 2D vector for display = vector
+
+[cryptomatte]
+patterns = crypto*
+display = data
+normalization = percentile
+color = #c8a0ff
+icon = 🎭
+
+[depth]
+patterns = z, *depth*
+display = data
+normalization = percentile-inverted
+color = #b0b0b0
+icon = 🌫
+
+[normal]
+patterns = n, normal*, *normal, *normals
+display = data
+normalization = remap 0.5 0.5
+color = #8fa8ff
+icon = 🧭
+
+[position]
+patterns = p, pworld, position*, *position
+display = data
+normalization = remap 0.1 0.5
+color = #ffd27f
+icon = 📍
+
+[velocity]
+patterns = *velocity*, *motion*, vector, mv
+display = vector
+vector = r g
+normalization = remap 0.05 0.5
+color = #7fffd4
+icon = 💨
+
+[id]
+patterns = id, objectid, materialid, object_id, material_id, *_id
+display = data
+normalization = percentile
+color = #ff9f7f
+icon = 🆔
+
+[emission]
+patterns = emission*, *emission, emit, *_emit
+display = color
+normalization = none
+color = #ffef7f
+icon = 💡
+";
+
+pub fn config_path() -> PathBuf {
+    crate::session::app_data_dir().join(CONFIG_FILE)
+}
+
+/// Wczytuje reguły z pliku użytkownika; przy braku pliku zapisuje domyślne (do edycji)
+pub fn load_or_create() -> ChannelConfig {
+    let path = config_path();
+    match fs::read_to_string(&path) {
+        Ok(text) => parse(&text),
+        Err(_) => {
+            if let Some(dir) = path.parent() {
+                let _ = fs::create_dir_all(dir).and_then(|_| fs::write(&path, DEFAULT_CONFIG));
+            }
+            parse(DEFAULT_CONFIG)
+        }
+    }
+}
+
+/// Parsuje plik INI z sekcjami nazwanymi rodzajem AOV; błędne wpisy są pomijane z ostrzeżeniem
+pub fn parse(text: &str) -> ChannelConfig {
+    let mut rules: Vec<ChannelRule> = Vec::new();
+    for (no, raw) in text.lines().enumerate() {
+        let line = raw.trim();
+        if line.is_empty() || line.starts_with('#') || line.starts_with(';') { continue; }
+
+        if let Some(section) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+            match AovKind::from_name(section) {
+                Some(kind) => rules.push(ChannelRule {
+                    kind,
+                    patterns: Vec::new(),
+                    display: DisplayMode::Color,
+                    normalization: Normalization::None,
+                    color: None,
+                    icon: None,
+                    vector: None,
+                }),
+                None => warn!(target: "io", "{}:{}: unknown AOV kind '{}'", CONFIG_FILE, no + 1, section),
+            }
+            continue;
+        }
+
+        let (Some(rule), Some((key, value))) = (rules.last_mut(), line.split_once('=')) else {
+            warn!(target: "io", "{}:{}: ignored line '{}'", CONFIG_FILE, no + 1, line);
+            continue;
+        };
+        let value = value.trim();
+        let ok = match key.trim() {
+            "patterns" => {
+                rule.patterns = value.split(',').map(|p| p.trim().to_ascii_lowercase()).filter(|p| !p.is_empty()).collect();
+                true
+            }
+            "display" => match value {
+                "color" => { rule.display = DisplayMode::Color; true }
+                "data" => { rule.display = DisplayMode::Data; true }
+                "vector" => { rule.display = DisplayMode::Vector; true }
+                _ => false,
+            },
+            "vector" => parse_vector_pair(value).map(|v| rule.vector = Some(v)).is_some(),
+            "normalization" => parse_normalization(value).map(|n| rule.normalization = n).is_some(),
+            "color" => parse_hex_color(value).map(|c| rule.color = Some(c)).is_some(),
+            "icon" => { rule.icon = (!value.is_empty()).then(|| value.to_string()); true }
+            _ => false,
+        };
+        if !ok {
+            warn!(target: "io", "{}:{}: invalid entry '{}'", CONFIG_FILE, no + 1, line);
+        }
+    }
+    ChannelConfig { rules }
+}
+
+fn parse_normalization(value: &str) -> Option<Normalization> {
+    let mut parts = value.split_whitespace();
+    match parts.next()? {
+        "none" => Some(Normalization::None),
+        "percentile" => Some(Normalization::Percentile { invert: false }),
+        "percentile-inverted" => Some(Normalization::Percentile { invert: true }),
+        "remap" => {
+            let gain = parts.next()?.parse().ok()?;
+            let offset = parts.next()?.parse().ok()?;
+            let abs = parts.next() == Some("abs");
+            Some(Normalization::Remap(ChannelRemap { gain, offset, abs }))
+        }
+        _ => None,
+    }
+}
+
+/// "r g" → (0, 1): nazwy składowych wczytanego kompozytu RGBA
+fn parse_vector_pair(value: &str) -> Option<(usize, usize)> {
+    let component = |name: &str| ["r", "g", "b", "a"].iter().position(|c| name.eq_ignore_ascii_case(c));
+    let mut parts = value.split_whitespace();
+    let pair = (component(parts.next()?)?, component(parts.next()?)?);
+    parts.next().is_none().then_some(pair)
+}
+
+pub(crate) fn parse_hex_color(value: &str) -> Option<(u8, u8, u8)> {
+    let hex = value.strip_prefix('#')?;
+    if hex.len() != 6 { return None; }
+    let channel = |i: usize| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok();
+    Some((channel(0)?, channel(2)?, channel(4)?))
+}
+
+/// Dopasowanie wzorca z `*` (dowolny ciąg znaków); oba argumenty już małymi literami
+pub(crate) fn glob_match(pattern: &str, text: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or("");
+    let Some(mut rest) = text.strip_prefix(first) else { return false; };
+    let parts: Vec<&str> = parts.collect();
+    let Some((last, middle)) = parts.split_last() else { return rest.is_empty(); };
+    for part in middle {
+        match rest.find(part) {
+            Some(pos) => rest = &rest[pos + part.len()..],
+            None => return false,
+        }
+    }
+    rest.len() >= last.len() && rest.ends_with(last)
+}
@@ -0,0 +1,81 @@
+// Błędy warstwy wczytywania/przetwarzania EXR oraz ich tłumaczenie na komunikaty dla użytkownika
+
+use thiserror::Error;
+
+/// Błędy wczytywania i przetwarzania plików EXR
+#[derive(Debug, Error)]
+pub enum ExrError {
+    #[error("unsupported compression: {0}")]
+    UnsupportedCompression(String),
+    #[error("unsupported file feature: {0}")]
+    Unsupported(String),
+    #[error("layer '{0}' not found")]
+    MissingLayer(String),
+    #[error("channel '{channel}' not found in layer '{layer}'")]
+    MissingChannel { layer: String, channel: String },
+    #[error("corrupt or invalid header: {0}")]
+    CorruptHeader(String),
+    #[error("not enough memory for a {width}x{height} image")]
+    OutOfMemory { width: usize, height: usize },
+    #[error("operation canceled")]
+    Canceled,
+    #[error("I/O error: {0}")]
+    Io(#[source] std::io::Error),
+}
+
+pub type ExrResult<T> = Result<T, ExrError>;
+
+impl ExrError {
+    /// Sugerowane rozwiązanie problemu (pokazywane razem z opisem błędu)
+    pub fn remediation(&self) -> &'static str {
+        match self {
+            ExrError::UnsupportedCompression(_) => "Re-save the file with ZIP, PIZ or uncompressed encoding.",
+            ExrError::Unsupported(_) => "Re-export the file without deep data or exotic features.",
+            ExrError::MissingLayer(_) | ExrError::MissingChannel { .. } => "Pick another layer from the list; the file may have been re-rendered.",
+            ExrError::CorruptHeader(_) => "The file is damaged or incomplete - check that the render/copy finished.",
+            ExrError::OutOfMemory { .. } => "Close other files or applications, or open a smaller render.",
+            ExrError::Canceled => "Open the file again to retry.",
+            ExrError::Io(_) => "Check that the file exists and is readable.",
+        }
+    }
+
+    /// Komunikat dla paska statusu: opis + sugerowane rozwiązanie
+    pub fn user_message(&self) -> String {
+        format!("{} - {}", capitalize(&self.to_string()), self.remediation())
+    }
+
+    pub fn is_canceled(&self) -> bool {
+        matches!(self, ExrError::Canceled)
+    }
+}
+
+impl From<std::io::Error> for ExrError {
+    fn from(e: std::io::Error) -> Self {
+        // Reader anulowany przez CancelToken zgłasza błąd odczytu z ExrError::Canceled w środku
+        if e.get_ref().and_then(|inner| inner.downcast_ref::<ExrError>()).is_some_and(ExrError::is_canceled) {
+            return ExrError::Canceled;
+        }
+        ExrError::Io(e)
+    }
+}
+
+impl From<exr::error::Error> for ExrError {
+    fn from(e: exr::error::Error) -> Self {
+        use exr::error::Error;
+        match e {
+            Error::Aborted => ExrError::Canceled,
+            Error::NotSupported(msg) if msg.to_lowercase().contains("compression") => ExrError::UnsupportedCompression(msg.to_string()),
+            Error::NotSupported(msg) => ExrError::Unsupported(msg.to_string()),
+            Error::Invalid(msg) => ExrError::CorruptHeader(msg.to_string()),
+            Error::Io(io) => io.into(),
+        }
+    }
+}
+
+fn capitalize(s: &str) -> String {
+    let mut chars = s.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
@@ -0,0 +1,282 @@
+// Eksport sekwencji do wideo podglądowego (MP4/H.264, bez dźwięku): każda klatka przechodzi przez
+// bieżący widok (ekspozycja, gamma, potok koloru, orientacja) w pełnej rozdzielczości, a surowe
+// RGB8 trafia na stdin procesu ffmpeg (EXRUSTER_FFMPEG albo `ffmpeg` z PATH), który skaluje
+// i koduje. Braki klatek zgodnie z listą klatek zlecenia: powtórzenie poprzedniej albo czarna klatka.
+
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+use tracing::{debug, info};
+use crate::cancel::CancelToken;
+use crate::image_cache::ImageCache;
+use crate::progress::NoopProgress;
+use crate::raw_image::RawImage;
+use crate::render_settings::RenderSettings;
+use crate::utils::error_handling::{ExrError, ExrResult};
+
+/// Zmienna środowiskowa: ścieżka do programu ffmpeg
+pub const FFMPEG_ENV: &str = "EXRUSTER_FFMPEG";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum VideoSize {
+    Full,
+    Hd1080,
+    Hd720,
+    Half,
+}
+
+impl VideoSize {
+    pub fn from_label(label: &str) -> Self {
+        match label {
+            "1080p" => VideoSize::Hd1080,
+            "720p" => VideoSize::Hd720,
+            "Half resolution" => VideoSize::Half,
+            _ => VideoSize::Full,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            VideoSize::Full => "Full resolution",
+            VideoSize::Hd1080 => "1080p",
+            VideoSize::Hd720 => "720p",
+            VideoSize::Half => "Half resolution",
+        }
+    }
+
+    /// Rozmiar wyjścia dla klatki źródłowej: presety HD ograniczają wysokość (bez powiększania),
+    /// wymiary parzyste (wymóg yuv420p)
+    pub fn output_size(self, width: u32, height: u32) -> (u32, u32) {
+        let scale = match self {
+            VideoSize::Full => 1.0,
+            VideoSize::Hd1080 => (1080.0 / height as f32).min(1.0),
+            VideoSize::Hd720 => (720.0 / height as f32).min(1.0),
+            VideoSize::Half => 0.5,
+        };
+        let even = |v: u32| ((v as f32 * scale).round() as u32 / 2 * 2).max(2);
+        (even(width), even(height))
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum VideoQuality {
+    High,
+    Medium,
+    Draft,
+}
+
+impl VideoQuality {
+    pub fn from_label(label: &str) -> Self {
+        match label {
+            "High" => VideoQuality::High,
+            "Draft" => VideoQuality::Draft,
+            _ => VideoQuality::Medium,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            VideoQuality::High => "High",
+            VideoQuality::Medium => "Medium",
+            VideoQuality::Draft => "Draft",
+        }
+    }
+
+    /// CRF i preset x264
+    fn x264(self) -> (&'static str, &'static str) {
+        match self {
+            VideoQuality::High => ("16", "slow"),
+            VideoQuality::Medium => ("20", "medium"),
+            VideoQuality::Draft => ("26", "veryfast"),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct VideoOptions {
+    pub size: VideoSize,
+    pub quality: VideoQuality,
+    pub fps: u32,
+}
+
+fn ffmpeg_program() -> String {
+    std::env::var(FFMPEG_ENV).unwrap_or_else(|_| "ffmpeg".to_string())
+}
+
+fn video_error(target: &Path, e: impl std::fmt::Display) -> ExrError {
+    ExrError::Io(std::io::Error::other(format!("{}: {}", target.display(), e)))
+}
+
+/// Proces ffmpeg; przerwany eksport (błąd, anulowanie) zabija proces i usuwa niedokończony plik
+struct Encoder {
+    child: Child,
+    target: PathBuf,
+    done: bool,
+}
+
+impl Encoder {
+    fn start(target: &Path, width: u32, height: u32, options: VideoOptions) -> ExrResult<Self> {
+        let (out_w, out_h) = options.size.output_size(width, height);
+        let (crf, preset) = options.quality.x264();
+        let mut command = Command::new(ffmpeg_program());
+        command
+            .args(["-hide_banner", "-loglevel", "error", "-y"])
+            .args(["-f", "rawvideo", "-pix_fmt", "rgb24", "-s", &format!("{}x{}", width, height), "-r", &options.fps.to_string(), "-i", "-"])
+            .args(["-an", "-vf", &format!("scale={}:{}:flags=lanczos", out_w, out_h)])
+            .args(["-c:v", "libx264", "-preset", preset, "-crf", crf, "-pix_fmt", "yuv420p", "-movflags", "+faststart"])
+            .arg(target)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped());
+        debug!(target: "io", "video encoder: {:?}", command);
+        let child = command.spawn()
+            .map_err(|e| video_error(target, format!("cannot start ffmpeg ({}; set {} to its path): {}", ffmpeg_program(), FFMPEG_ENV, e)))?;
+        Ok(Encoder { child, target: target.to_path_buf(), done: false })
+    }
+
+    fn write(&mut self, rgb: &[u8]) -> ExrResult<()> {
+        let stdin = self.child.stdin.as_mut().expect("ffmpeg stdin is piped");
+        if stdin.write_all(rgb).is_err() {
+            // Proces zakończył się przed końcem danych – przyczyna jest na stderr
+            return Err(self.failure());
+        }
+        Ok(())
+    }
+
+    fn failure(&mut self) -> ExrError {
+        drop(self.child.stdin.take());
+        let _ = self.child.wait();
+        let mut message = String::new();
+        if let Some(mut stderr) = self.child.stderr.take() {
+            let _ = stderr.read_to_string(&mut message);
+        }
+        video_error(&self.target, format!("ffmpeg failed: {}", message.trim()))
+    }
+
+    fn finish(mut self) -> ExrResult<()> {
+        drop(self.child.stdin.take());
+        let status = self.child.wait()?;
+        if !status.success() {
+            return Err(self.failure());
+        }
+        self.done = true;
+        Ok(())
+    }
+}
+
+impl Drop for Encoder {
+    fn drop(&mut self) {
+        if !self.done {
+            let _ = self.child.kill();
+            let _ = self.child.wait();
+            let _ = std::fs::remove_file(&self.target);
+        }
+    }
+}
+
+/// Klatka sekwencji po renderze; braki mają rozmiar pozostałych klatek
+pub enum Rendered<'a> {
+    Image(&'a RawImage),
+    Black { width: u32, height: u32 },
+}
+
+/// Renderuje klatki (None = brak, czarna klatka; ta sama ścieżka co poprzednia = powtórzenie bez
+/// ponownego renderu) warstwy `layer` z ustawieniami widoku `settings` (render eksportu, zob.
+/// `ImageCache::render_full_resolution`) i przekazuje je po kolei do `sink`.
+/// Braki przed pierwszą czytelną klatką czekają, aż rozmiar będzie znany; klatka o innym rozmiarze
+/// przerywa eksport. Zwraca liczbę wyrenderowanych pikseli.
+pub fn render_frames(
+    frames: &[Option<PathBuf>],
+    layer: &str,
+    settings: &RenderSettings,
+    cancel: &CancelToken,
+    report: &(dyn Fn(f32, &str) + Sync),
+    mut sink: impl FnMut(Rendered) -> ExrResult<()>,
+) -> ExrResult<u64> {
+    let total = frames.len();
+    let mut last: Option<(&PathBuf, RawImage)> = None;
+    let mut leading_gaps = 0;
+    let mut pixels = 0u64;
+    for (i, frame) in frames.iter().enumerate() {
+        cancel.check()?;
+        let progress = |f: f32| report((i as f32 + f) / total as f32, &format!("Frame {}/{}", i + 1, total));
+        progress(0.0);
+        match frame {
+            Some(path) if last.as_ref().is_none_or(|(previous, _)| *previous != path) => {
+                let mut cache = ImageCache::new(path, cancel, &NoopProgress)?;
+                if cache.current_layer_name != layer && cache.layers_info.iter().any(|l| l.name == layer) {
+                    cache.load_layer(path, layer)?;
+                }
+                cache.set_stereo_mode(path, settings.stereo_mode);
+                let image = cache.render_full_resolution(settings, cancel, &|f| progress(f * 0.9))?;
+                pixels += image.width as u64 * image.height as u64;
+                if let Some((first, previous)) = &last {
+                    if (previous.width, previous.height) != (image.width, image.height) {
+                        return Err(ExrError::Io(std::io::Error::other(format!("{} is {}x{}, {} is {}x{}", path.display(), image.width, image.height, first.display(), previous.width, previous.height))));
+                    }
+                }
+                last = Some((path, image));
+            }
+            _ => {}
+        }
+        let Some((_, image)) = &last else {
+            // Braki przed pierwszą klatką – rozmiar jeszcze nieznany
+            leading_gaps += 1;
+            continue;
+        };
+        let black = || Rendered::Black { width: image.width, height: image.height };
+        for _ in 0..std::mem::take(&mut leading_gaps) {
+            sink(black())?;
+        }
+        sink(if frame.is_some() { Rendered::Image(image) } else { black() })?;
+    }
+    if last.is_none() {
+        return Err(ExrError::Io(std::io::Error::other("the sequence has no readable frames")));
+    }
+    Ok(pixels)
+}
+
+/// Koduje klatki (zob. `render_frames`) do `target`. Zwraca liczbę wyrenderowanych pikseli.
+pub fn export_sequence(
+    frames: &[Option<PathBuf>],
+    layer: &str,
+    settings: &RenderSettings,
+    target: &Path,
+    options: VideoOptions,
+    cancel: &CancelToken,
+    report: &(dyn Fn(f32, &str) + Sync),
+) -> ExrResult<u64> {
+    let mut encoder: Option<Encoder> = None;
+    let pixels = render_frames(frames, layer, settings, cancel, report, |frame| {
+        let (width, height) = match frame {
+            Rendered::Image(image) => (image.width, image.height),
+            Rendered::Black { width, height } => (width, height),
+        };
+        if encoder.is_none() {
+            encoder = Some(Encoder::start(target, width, height, options)?);
+        }
+        let encoder = encoder.as_mut().expect("encoder started");
+        match frame {
+            Rendered::Image(image) => encoder.write(&image.pixels.chunks_exact(4).flat_map(|p| [p[0], p[1], p[2]]).collect::<Vec<u8>>()),
+            Rendered::Black { .. } => encoder.write(&vec![0u8; width as usize * height as usize * 3]),
+        }
+    })?;
+    let encoder = encoder.expect("render_frames delivers at least one frame");
+    report(1.0, &format!("Finishing {}...", target.display()));
+    encoder.finish()?;
+    info!(target: "io", "video written: {} ({} frames at {} fps, {:?}, {:?})", target.display(), frames.len(), options.fps, options.size, options.quality);
+    Ok(pixels)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn output_sizes_are_even_and_never_upscaled() {
+        assert_eq!(VideoSize::Full.output_size(2049, 1081), (2048, 1080));
+        assert_eq!(VideoSize::Hd1080.output_size(4096, 2160), (2048, 1080));
+        assert_eq!(VideoSize::Hd720.output_size(1280, 540), (1280, 540));
+        assert_eq!(VideoSize::Half.output_size(1920, 1080), (960, 540));
+    }
+}
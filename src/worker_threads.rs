@@ -0,0 +1,65 @@
+// Pula wątków ciężkich zadań w tle (eksport, generowanie proxy, raport QC), oddzielna od globalnej
+// puli podglądu. Liczba wątków i obniżony priorytet (Windows) pochodzą z preferencji
+// (`Preferences::worker_threads`, `worker_low_priority`). Globalna pula rayon powstaje raz przy
+// starcie, tę budujemy od nowa po zmianie ustawień: nowe zadania dostają nową konfigurację bez
+// restartu, a trwające kończą na poprzedniej puli (trzymają jej Arc).
+
+use std::sync::{Arc, Mutex};
+use tracing::info;
+use crate::platform;
+use crate::preferences;
+
+/// Liczba wątków dla ustawienia `threads`; 0 = automatycznie (połowa rdzeni – reszta zostaje dla podglądu)
+pub fn thread_count(threads: usize) -> usize {
+    let cores = std::thread::available_parallelism().map_or(2, |n| n.get());
+    if threads == 0 { cores.div_ceil(2) } else { threads.min(cores) }
+}
+
+/// Liczba wątków wpisana w menu: "Auto" (lub puste, 0) = automatycznie
+pub fn parse_threads(text: &str) -> Option<usize> {
+    let text = text.trim();
+    if text.is_empty() || text.eq_ignore_ascii_case("auto") {
+        return Some(0);
+    }
+    text.parse().ok()
+}
+
+/// Ustawienia puli: liczba wątków z preferencji, niski priorytet
+type PoolSettings = (usize, bool);
+
+/// Pula zbudowana dla ustawień, które trzyma obok
+static POOL: Mutex<Option<(PoolSettings, Arc<rayon::ThreadPool>)>> = Mutex::new(None);
+
+/// Pula dla bieżących ustawień (budowana przy pierwszym użyciu i po zmianie ustawień)
+pub fn pool() -> Arc<rayon::ThreadPool> {
+    let preferences = preferences::current();
+    let settings = (preferences.worker_threads, preferences.worker_low_priority);
+    let mut guard = POOL.lock().unwrap_or_else(|p| p.into_inner());
+    if let Some((built, pool)) = guard.as_ref() {
+        if *built == settings {
+            return pool.clone();
+        }
+    }
+    let threads = thread_count(preferences.worker_threads);
+    let low_priority = preferences.worker_low_priority;
+    let pool = Arc::new(
+        rayon::ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .thread_name(|i| format!("worker-{}", i))
+            .start_handler(move |_| {
+                if low_priority {
+                    platform::lower_current_thread_priority();
+                }
+            })
+            .build()
+            .expect("failed to build worker thread pool"),
+    );
+    info!(target: "processing", "worker pool: {} threads{}", threads, if low_priority { ", low priority" } else { "" });
+    *guard = Some((settings, pool.clone()));
+    pool
+}
+
+/// Uruchamia `job` na puli ciężkich zadań (także jego `par_iter`)
+pub fn install<R: Send>(job: impl FnOnce() -> R + Send) -> R {
+    pool().install(job)
+}